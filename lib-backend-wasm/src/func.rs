@@ -11,6 +11,11 @@ use crate::multi_value_polyfill;
 use crate::pre_traverse::ShiftedStringPool;
 use crate::string_prim_inst;
 use crate::Options;
+use crate::ARRAY_CAPACITY_OFFSET;
+use crate::ARRAY_DATA_OFFSET;
+use crate::ARRAY_ELEMENT_SIZE;
+use crate::ARRAY_LENGTH_OFFSET;
+use crate::ARRAY_MAX_LENGTH;
 
 use super::opt_var_conv::*;
 use super::var_conv::*;
@@ -24,13 +29,17 @@ use std::collections::HashMap;
 struct EncodeContext<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h, 'i, Heap: HeapManager> {
     // Local to this function
     return_type: Option<ir::VarType>,
-    is_repl: bool, // whether this function is from the repl
+    is_repl: bool,                        // whether this function is from the repl
+    funcidx: Option<ir::FuncIdx>, // ir funcidx of this function (None for thunks), used to detect self tail calls
+    is_entry_point: bool, // whether this function is called by the host (so it cannot leave a pending trampoline call)
+    location: Option<ir::SourceLocation>, // location of the innermost enclosing expr that has one, for source maps
 
     // Global for whole program
     struct_types: &'a [Box<[ir::VarType]>],
     struct_field_byte_offsets: &'b [Box<[u32]>], // has same sizes as `struct_types`, but instead stores the byte offset of each field from the beginning of the struct
     ir_signature_list: &'c [Signature], // mapping from ir::FuncIdx, for callers to check the param type or return type
     wasm_funcidxs: &'d [wasmgen::FuncIdx], // mapping from ir::FuncIdx to wasmgen::FuncIdx (used when we need to invoke a DirectAppl), this includes imports too
+    num_imports: usize, // the imports come before the funcs in the ir::FuncIdx space
 
     // Global var management (does not include special globals like the stackptr)
     globals: GlobalVarManagerRef<'e>,
@@ -45,6 +54,22 @@ struct EncodeContext<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h, 'i, Heap: HeapManager> {
     error_func: wasmgen::FuncIdx, // imported function to call to error out (e.g. runtime type errors)
     repl_sl: ir::SourceLocation,  // source location constant for REPL errors
     options: Options,             // Compilation options (it implements Copy)
+    trampoline: Option<Trampoline>, // only present if we cannot use the wasm tail call proposal
+}
+
+/**
+ * Globals that hold a pending tail call, used when the wasm tail call proposal is not available.
+ * Instead of calling the callee, an indirect call in tail position writes its arguments to the unprotected stack
+ * (like a normal indirect call), stores the rest of the call in these globals, and returns a dummy value.
+ * Every non-tail call site then keeps making the pending call until there is none left,
+ * so the wasm stack does not grow with the number of tail calls.
+ */
+#[derive(Copy, Clone)]
+struct Trampoline {
+    num_args: wasmgen::GlobalIdx, // -1 if there is no pending call
+    closure: wasmgen::GlobalIdx,
+    callerid: wasmgen::GlobalIdx,
+    tableidx: wasmgen::GlobalIdx,
 }
 
 // Have to implement Copy and Clone manually, because #[derive(Copy, Clone)] doesn't work for generic types like Heap
@@ -96,6 +121,7 @@ impl<'a> ModuleEncodeWrapper<'a> {
 pub struct Signature {
     pub params: Box<[ir::VarType]>,
     pub result: Option<ir::VarType>,
+    pub typed_trampoline_caller: bool, // whether the function might leave a pending tail call even though it does not return Any (see encode_return())
 }
 
 pub fn encode_funcs<'a, Heap: HeapManager>(
@@ -127,6 +153,16 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
 
     let (thunk_list, thunk_map) = thunk_sv.into_parts();
 
+    let num_imports: usize = imported_funcs.len();
+
+    // add the globals for the trampoline (if we can't use real tail calls)
+    let trampoline: Option<Trampoline> = (!options.wasm_tail_call).as_some_from(|| Trampoline {
+        num_args: wasm_module.add_i32_global(wasmgen::Mut::Var, -1),
+        closure: wasm_module.add_i32_global(wasmgen::Mut::Var, 0),
+        callerid: wasm_module.add_i32_global(wasmgen::Mut::Var, 0),
+        tableidx: wasm_module.add_i32_global(wasmgen::Mut::Var, 0),
+    });
+
     // reserve space for the indirect function table
     let tableidx = wasm_module.get_or_add_table();
     let thunk_table_offset = wasm_module.reserve_table_elements(tableidx, thunk_list.len() as u32);
@@ -146,6 +182,9 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
                     encode_result(ir_func.result, options.wasm_multi_value),
                 );
                 let (_, wasm_funcidx) = wasm_module.register_func(&wasm_functype);
+                if let Some(name) = &ir_func.name {
+                    wasm_module.set_func_name(wasm_funcidx, name.clone());
+                }
                 let code_builder = wasmgen::CodeBuilder::new(wasm_functype);
                 (
                     WasmRegistry {
@@ -172,7 +211,8 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
 
     let thunk_funcidxs: Box<[wasmgen::FuncIdx]> = thunk_list
         .into_iter()
-        .map(|overload_entries| {
+        .enumerate()
+        .map(|(i, overload_entries)| {
             let wasm_functype = wasmgen::FuncType::new(
                 // closure, num_params, callerid
                 Box::new([
@@ -183,6 +223,7 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
                 encode_result(Some(ir::VarType::Any), options.wasm_multi_value),
             );
             let (_, wasm_funcidx) = wasm_module.register_func(&wasm_functype);
+            wasm_module.set_func_name(wasm_funcidx, format!("thunk_{}", i));
             let mut code_builder = wasmgen::CodeBuilder::new(wasm_functype);
             {
                 let (locals_builder, expr_builder) = code_builder.split();
@@ -190,10 +231,14 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
                 let ctx = EncodeContext {
                     return_type: Some(ir::VarType::Any),
                     is_repl: false,
+                    funcidx: None,
+                    is_entry_point: false,
+                    location: None,
                     struct_types: ir_struct_types,
                     struct_field_byte_offsets: ir_struct_field_byte_offsets,
                     ir_signature_list: ir_signature_list,
                     wasm_funcidxs: &wasm_funcidxs,
+                    num_imports: num_imports,
                     globals: global_var_manager,
                     stackptr: globalidx_stackptr,
                     memidx: memidx,
@@ -204,6 +249,7 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
                     error_func: error_func,
                     repl_sl: repl_sl,
                     options: options,
+                    trampoline: trampoline,
                 };
                 let mut mutctx = MutContext::new(
                     scratch,
@@ -239,10 +285,14 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
                 let ctx = EncodeContext {
                     return_type: ir_func.result,
                     is_repl: ir_funcidx >= repl_funcidx_start,
+                    funcidx: Some(num_imports + ir_funcidx),
+                    is_entry_point: num_imports + ir_funcidx == ir_entry_point_funcidx,
+                    location: None,
                     struct_types: ir_struct_types,
                     struct_field_byte_offsets: ir_struct_field_byte_offsets,
                     ir_signature_list: ir_signature_list,
                     wasm_funcidxs: &wasm_funcidxs,
+                    num_imports: num_imports,
                     globals: global_var_manager,
                     stackptr: globalidx_stackptr,
                     memidx: memidx,
@@ -253,6 +303,7 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
                     error_func: error_func,
                     repl_sl: repl_sl,
                     options: options,
+                    trampoline: trampoline,
                 };
                 let mut mutctx = MutContext::new(
                     scratch,
@@ -261,22 +312,59 @@ pub fn encode_funcs<'a, Heap: HeapManager>(
                     &registry.param_types,
                     ModuleEncodeWrapper { wasm_module },
                 );
-                let wasm_reachable = encode_expr(&ir_func.expr, ctx, &mut mutctx, expr_builder);
+                // the named locals of a fresh context are exactly the params
+                ir_func
+                    .param_names
+                    .iter()
+                    .enumerate()
+                    .for_each(|(i, opt_name)| {
+                        if let Some(name) = opt_name {
+                            mutctx.name_local(i, name);
+                        }
+                    });
+                let encode_body =
+                    |mutctx: &mut MutContext, expr_builder: &mut wasmgen::ExprBuilder| {
+                        let wasm_reachable = encode_expr(&ir_func.expr, ctx, mutctx, expr_builder);
 
-                if let Some(vartype) = ir_func.expr.vartype {
-                    assert!(wasm_reachable);
-                    encode_return_calling_conv(
-                        ir_func.result.unwrap(),
-                        vartype,
-                        options.wasm_multi_value,
-                        globalidx_stackptr,
-                        mutctx.scratch_mut(),
-                        expr_builder,
-                    );
-                } else if wasm_reachable {
+                        if let Some(vartype) = ir_func.expr.vartype {
+                            assert!(wasm_reachable);
+                            encode_return_calling_conv(
+                                ir_func.result.unwrap(),
+                                vartype,
+                                options.wasm_multi_value,
+                                globalidx_stackptr,
+                                mutctx.scratch_mut(),
+                                expr_builder,
+                            );
+                            true
+                        } else {
+                            if wasm_reachable {
+                                expr_builder.unreachable();
+                            }
+                            // if !wasm_reachable then wasm knows that this point is unreachable, so we don't need to emit the `unreachable` instruction
+                            false
+                        }
+                    };
+
+                if trampoline.is_some() {
+                    // Without real tail calls, self tail calls are encoded as a branch to the beginning of the function,
+                    // so we wrap the function body in a loop.
+                    // loop {
+                    //   <body>
+                    //   return
+                    // }
+                    // unreachable
+                    expr_builder.loop_(&[]);
+                    mutctx.with_tail_call_landing(|mutctx| {
+                        if encode_body(mutctx, expr_builder) {
+                            expr_builder.return_();
+                        }
+                    });
+                    expr_builder.end();
                     expr_builder.unreachable();
+                } else {
+                    encode_body(&mut mutctx, expr_builder);
                 }
-                // if !wasm_reachable then wasm knows that this point is unreachable, so we don't need to emit the `unreachable` instruction
 
                 // append the end instruction to end of the function
                 expr_builder.end();
@@ -363,8 +451,8 @@ fn encode_drop_value(ir_vartype: ir::VarType, expr_builder: &mut wasmgen::ExprBu
             expr_builder.drop();
         }
         ir::VarType::Number | ir::VarType::Boolean | ir::VarType::String => expr_builder.drop(),
-        ir::VarType::StructT { typeidx: _ } => expr_builder.drop(),
-        ir::VarType::Undefined => {}
+        ir::VarType::Array | ir::VarType::StructT { typeidx: _ } => expr_builder.drop(),
+        ir::VarType::Undefined | ir::VarType::Null => {}
         ir::VarType::Unassigned => panic!("Unassigned variable must not exist on the stack"),
     }
 }
@@ -537,6 +625,37 @@ fn encode_expr<H: HeapManager>(
     ctx: EncodeContext<H>,
     mutctx: &mut MutContext,
     expr_builder: &mut wasmgen::ExprBuilder,
+) -> bool {
+    // the location of an expr applies to all the code generated for it, except for subexprs that have their own location
+    match expr.location {
+        Some(location) if ctx.location != Some(location) => {
+            let inner_ctx = EncodeContext {
+                location: Some(location),
+                ..ctx
+            };
+            expr_builder.set_source_position(Some(encode_source_position(location)));
+            let ret = encode_expr_kind(expr, inner_ctx, mutctx, expr_builder);
+            expr_builder.set_source_position(ctx.location.map(encode_source_position));
+            ret
+        }
+        _ => encode_expr_kind(expr, ctx, mutctx, expr_builder),
+    }
+}
+
+fn encode_source_position(location: ir::SourceLocation) -> wasmgen::SourcePosition {
+    // ir lines are one-based (like ESTree) but source map lines are zero-based
+    wasmgen::SourcePosition {
+        source: location.file,
+        line: location.start.line.saturating_sub(1),
+        column: location.start.column,
+    }
+}
+
+fn encode_expr_kind<H: HeapManager>(
+    expr: &ir::Expr,
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
 ) -> bool {
     match &expr.kind {
         ir::ExprKind::PrimUndefined => {
@@ -548,6 +667,15 @@ fn encode_expr<H: HeapManager>(
             // Don't do anything, because undefined is encoded as <nothing>
             true
         }
+        ir::ExprKind::PrimNull => {
+            // encodes the 'null' value
+            assert!(
+                expr.vartype == Some(ir::VarType::Null),
+                "ICE: IR->Wasm: PrimNull does not have type null"
+            );
+            // Don't do anything, because null is encoded as <nothing>
+            true
+        }
         ir::ExprKind::PrimNumber { val } => {
             // encodes a literal number
             assert!(
@@ -619,6 +747,18 @@ fn encode_expr<H: HeapManager>(
             mutctx.heap_encode_fixed_allocation(ctx.heap, expr.vartype.unwrap(), expr_builder);
             true
         }
+        ir::ExprKind::PrimArray => {
+            // encodes an empty array (semantically this is like PrimStructT), it will do a heap allocation
+            assert!(
+                expr.vartype == Some(ir::VarType::Array),
+                "ICE: IR->Wasm: PrimArray does not have type array"
+            );
+            // net wasm stack: [] -> [i32(capacity)]
+            expr_builder.i32_const(0);
+            // net wasm stack: [i32(capacity)] -> [i32(array)]
+            mutctx.heap_encode_dynamic_allocation(ctx.heap, ir::VarType::Array, expr_builder);
+            true
+        }
         ir::ExprKind::PrimFunc { funcidxs, closure } => {
             // encodes a function pointer and associated closure struct
             assert!(
@@ -802,6 +942,33 @@ fn encode_expr<H: HeapManager>(
             encode_prim_inst(expr.vartype, *prim_inst, args, ctx, mutctx, expr_builder);
             true
         }
+        ir::ExprKind::ArrayLoad {
+            array,
+            index,
+            location,
+        } => {
+            // encodes a read of an array element, e.g. `a[i]`
+            assert!(
+                expr.vartype == Some(ir::VarType::Any),
+                "ICE: IR->Wasm: ArrayLoad does not have type any"
+            );
+            encode_array_load(array, index, location, ctx, mutctx, expr_builder);
+            true
+        }
+        ir::ExprKind::ArrayStore {
+            array,
+            index,
+            expr: value,
+            location,
+        } => {
+            // encodes a write to an array element, e.g. `a[i] = v`
+            assert!(
+                expr.vartype == Some(ir::VarType::Undefined),
+                "ICE: IR->Wasm: ArrayStore does not have type undefined"
+            );
+            encode_array_store(array, index, value, location, ctx, mutctx, expr_builder);
+            true
+        }
         ir::ExprKind::Appl {
             func,
             args,
@@ -813,6 +980,7 @@ fn encode_expr<H: HeapManager>(
                 func,
                 args,
                 location,
+                CallKind::Normal,
                 ctx,
                 mutctx,
                 expr_builder,
@@ -821,7 +989,15 @@ fn encode_expr<H: HeapManager>(
         }
         ir::ExprKind::DirectAppl { funcidx, args } => {
             // encodes a function call
-            encode_direct_appl(expr.vartype, *funcidx, args, ctx, mutctx, expr_builder);
+            encode_direct_appl(
+                expr.vartype,
+                *funcidx,
+                args,
+                CallKind::Normal,
+                ctx,
+                mutctx,
+                expr_builder,
+            );
             true
         }
         ir::ExprKind::Conditional {
@@ -879,6 +1055,7 @@ fn encode_expr<H: HeapManager>(
         }
         ir::ExprKind::Declaration {
             local,
+            name,
             init,
             contained_expr,
         } => {
@@ -891,6 +1068,9 @@ fn encode_expr<H: HeapManager>(
                 // net wasm stack: [] -> [<init_expr.vartype>]
                 encode_expr(init_expr, ctx, mutctx, expr_builder);
                 mutctx.with_uninitialized_named_local(*local, |mutctx, named_localidx| {
+                    if let Some(name) = name {
+                        mutctx.name_local(named_localidx, name);
+                    }
                     // net wasm stack: [<init_expr.vartype>] -> []
                     encode_store_local(
                         mutctx.named_wasm_local_slice(named_localidx),
@@ -906,7 +1086,10 @@ fn encode_expr<H: HeapManager>(
                     *local,
                     ctx.heap,
                     expr_builder,
-                    |mutctx, expr_builder, _| {
+                    |mutctx, expr_builder, named_localidx| {
+                        if let Some(name) = name {
+                            mutctx.name_local(named_localidx, name);
+                        }
                         encode_expr(contained_expr, ctx, mutctx, expr_builder)
                     },
                 )
@@ -937,24 +1120,12 @@ fn encode_expr<H: HeapManager>(
             // - the return type is at least as wide as the inner expr type
             match inner_expr.vartype {
                 None => panic!("ICE: IR->Wasm: expression in return statement cannot be Void"),
-                Some(inner_type) => {
+                Some(_) => {
                     match ctx.return_type {
                         None => panic!("ICE: IR->Wasm: cannot have return expression in a function that returns Void"),
                         Some(ret_type) => {
-                            // net wasm stack: [] -> [<expr.vartype>]
-                            encode_expr(inner_expr, ctx, mutctx, expr_builder);
-
-                            // net wasm stack: [<expr.vartype>] -> [<return_calling_conv(ctx.return_type.unwrap())>]
-                            encode_return_calling_conv(
-                                ret_type,
-                                inner_type,
-                                ctx.options.wasm_multi_value,
-                                ctx.stackptr,
-                                mutctx.scratch_mut(),
-                                expr_builder,
-                            );
-                            // return the value on the stack (or in the unprotected stack) (which now has the correct type)
-                            expr_builder.return_();
+                            // net wasm stack: [] -> [stack-polymorphic]
+                            encode_return(inner_expr, ret_type, ctx, mutctx, expr_builder);
                         }
                     };
                 }
@@ -1006,8 +1177,20 @@ fn encode_expr<H: HeapManager>(
                 mutctx,
                 expr_builder,
                 |mutctx, landing_ctx, expr_builder| {
-                    mutctx.with_landing(expr.vartype.unwrap(), landing_ctx, |mutctx| {
-                        encode_expr(inner_expr, ctx, mutctx, expr_builder);
+                    // if the block is noreturn, then no Break can target it (since the expr of a Break cannot be noreturn),
+                    // so it does not matter what the landing type is
+                    let landing_vartype = expr.vartype.unwrap_or(ir::VarType::Undefined);
+                    mutctx.with_landing(landing_vartype, landing_ctx, |mutctx| {
+                        // net wasm stack: [] -> [<inner_expr.vartype>]
+                        let wasm_reachable = encode_expr(inner_expr, ctx, mutctx, expr_builder);
+                        // net wasm stack: [<inner_expr.vartype>] -> [<expr.vartype>]
+                        encode_opt_result_widening_operation(
+                            expr.vartype,
+                            inner_expr.vartype,
+                            wasm_reachable,
+                            mutctx.scratch_mut(),
+                            expr_builder,
+                        );
                     })
                 },
             );
@@ -1015,6 +1198,33 @@ fn encode_expr<H: HeapManager>(
             // returns true, because WebAssembly never regards a block as stack-polymorphic even if it is actually the case
             true
         }
+        ir::ExprKind::Loop { expr: inner_expr } => {
+            // register that a Break can land here (at the beginning of the loop)
+            // breaks that target a loop carry no values, so the landing is Undefined and needs no landing locals
+            multi_value_polyfill::loop_(
+                encode_opt_vartype(expr.vartype),
+                ctx.options.wasm_multi_value,
+                mutctx,
+                expr_builder,
+                |mutctx, expr_builder| {
+                    mutctx.with_landing(ir::VarType::Undefined, &[], |mutctx| {
+                        // net wasm stack: [] -> [<inner_expr.vartype>]
+                        let wasm_reachable = encode_expr(inner_expr, ctx, mutctx, expr_builder);
+                        // net wasm stack: [<inner_expr.vartype>] -> [<expr.vartype>]
+                        encode_opt_result_widening_operation(
+                            expr.vartype,
+                            inner_expr.vartype,
+                            wasm_reachable,
+                            mutctx.scratch_mut(),
+                            expr_builder,
+                        );
+                    })
+                },
+            );
+
+            // returns true, because WebAssembly never regards a loop as stack-polymorphic even if it is actually the case
+            true
+        }
         ir::ExprKind::Sequence { content } => {
             if content.is_empty() {
                 assert!(
@@ -1044,15 +1254,7 @@ fn encode_expr<H: HeapManager>(
         }
         ir::ExprKind::Trap { code, location } => {
             // Calls the predefined imported function, which must never return.
-            expr_builder.i32_const(*code as i32);
-            expr_builder.i32_const(0);
-            expr_builder.i32_const(location.file as i32);
-            expr_builder.i32_const(location.start.line as i32);
-            expr_builder.i32_const(location.start.column as i32);
-            expr_builder.i32_const(location.end.line as i32);
-            expr_builder.i32_const(location.end.column as i32);
-            expr_builder.call(ctx.error_func);
-            expr_builder.unreachable();
+            encode_trap(*code, location, ctx, expr_builder);
             // in the future, PrimInst::Trap should take a error code parameter, and maybe source location
             // and call a noreturn function to the embedder (JavaScript).
             false
@@ -1272,19 +1474,631 @@ fn encode_prim_inst<H: HeapManager>(
         ir::PrimInst::StringLe => {
             string_prim_inst::encode_string_le(mutctx.scratch_mut(), expr_builder);
         }
+        ir::PrimInst::ArrayLength => {
+            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+            expr_builder.f64_convert_i32_u();
+        }
+        ir::PrimInst::ReferenceEq => {
+            encode_reference_eq(mutctx.scratch_mut(), expr_builder);
+        }
+        ir::PrimInst::ReferenceNeq => {
+            encode_reference_eq(mutctx.scratch_mut(), expr_builder);
+            expr_builder.i32_eqz();
+        }
+    }
+}
+
+// Compares two Anys by their tag and data, which for references is pointer equality.
+// net wasm stack: [i64(data_a), i32(tag_a), i64(data_b), i32(tag_b)] -> [i32(a == b)]
+fn encode_reference_eq(scratch: &mut Scratch, expr_builder: &mut wasmgen::ExprBuilder) {
+    let localidx_tag_b: wasmgen::LocalIdx = scratch.push_i32();
+    let localidx_data_b: wasmgen::LocalIdx = scratch.push_i64();
+    let localidx_tag_a: wasmgen::LocalIdx = scratch.push_i32();
+    expr_builder.local_set(localidx_tag_b);
+    expr_builder.local_set(localidx_data_b);
+    expr_builder.local_set(localidx_tag_a);
+    expr_builder.local_get(localidx_data_b);
+    expr_builder.i64_eq();
+    expr_builder.local_get(localidx_tag_a);
+    expr_builder.local_get(localidx_tag_b);
+    expr_builder.i32_eq();
+    expr_builder.i32_and();
+    scratch.pop_i32();
+    scratch.pop_i64();
+    scratch.pop_i32();
+}
+
+// Calls the predefined imported error function, which must never return.
+// net wasm stack: [] -> []
+fn encode_trap<H: HeapManager>(
+    code: u32,
+    location: &ir::SourceLocation,
+    ctx: EncodeContext<H>,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    expr_builder.i32_const(code as i32);
+    expr_builder.i32_const(0);
+    expr_builder.i32_const(location.file as i32);
+    expr_builder.i32_const(location.start.line as i32);
+    expr_builder.i32_const(location.start.column as i32);
+    expr_builder.i32_const(location.end.line as i32);
+    expr_builder.i32_const(location.end.column as i32);
+    expr_builder.call(ctx.error_func);
+    expr_builder.unreachable();
+}
+
+// Traps with ERROR_CODE_ARRAY_INDEX_OUT_OF_RANGE unless the f64 in `localidx_index` is an integer in the range [0, limit).
+// `encode_limit` should have net wasm stack [] -> [i32(limit)], where the limit is unsigned.
+// NaN will always trap, because all comparisons with it are false.
+// net wasm stack: [] -> []
+fn encode_array_index_check<H: HeapManager, F: FnOnce(&mut wasmgen::ExprBuilder)>(
+    localidx_index: wasmgen::LocalIdx,
+    encode_limit: F,
+    location: &ir::SourceLocation,
+    ctx: EncodeContext<H>,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    // if (!(index >= 0 && index < limit && floor(index) == index)) trap();
+    expr_builder.local_get(localidx_index);
+    expr_builder.f64_const(0.0);
+    expr_builder.f64_ge();
+    expr_builder.local_get(localidx_index);
+    encode_limit(expr_builder);
+    expr_builder.f64_convert_i32_u();
+    expr_builder.f64_lt();
+    expr_builder.i32_and();
+    expr_builder.local_get(localidx_index);
+    expr_builder.f64_floor();
+    expr_builder.local_get(localidx_index);
+    expr_builder.f64_eq();
+    expr_builder.i32_and();
+    expr_builder.i32_eqz();
+    expr_builder.if_(&[]);
+    encode_trap(
+        ir::error::ERROR_CODE_ARRAY_INDEX_OUT_OF_RANGE,
+        location,
+        ctx,
+        expr_builder,
+    );
+    expr_builder.end();
+}
+
+// Requires: `array` has static type Array and `index` has static type Number.
+// Traps if the index is not an integer in the range [0, length).
+// net wasm stack: [] -> [<Any>]
+fn encode_array_load<H: HeapManager>(
+    array: &ir::Expr,
+    index: &ir::Expr,
+    location: &ir::SourceLocation,
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    assert!(
+        array.vartype == Some(ir::VarType::Array),
+        "ICE: IR->Wasm: array of ArrayLoad must have static type array"
+    );
+    assert!(
+        index.vartype == Some(ir::VarType::Number),
+        "ICE: IR->Wasm: index of ArrayLoad must have static type number"
+    );
+
+    // Algorithm:
+    /*
+    let arr = <array>;
+    let idx = <index>;
+    if (!(idx >= 0 && idx < arr->length && floor(idx) == idx)) trap();
+    return arr->data[idx];
+    */
+
+    // net wasm stack: [] -> [i32(array)]
+    encode_expr(array, ctx, mutctx, expr_builder);
+
+    // the array is stored in a shadow local, because evaluating the index might allocate memory
+    mutctx.with_uninitialized_shadow_local(ir::VarType::Array, |mutctx, localidx_array| {
+        // net wasm stack: [i32(array)] -> []
+        encode_store_local(
+            mutctx.wasm_local_slice(localidx_array),
+            ir::VarType::Array,
+            ir::VarType::Array,
+            expr_builder,
+        );
+
+        // net wasm stack: [] -> [f64(index)]
+        encode_expr(index, ctx, mutctx, expr_builder);
+
+        let wasm_localidx_array: wasmgen::LocalIdx = mutctx.wasm_local_slice(localidx_array)[0];
+        mutctx.with_scratch_f64(|mutctx, localidx_index| {
+            // net wasm stack: [f64(index)] -> []
+            expr_builder.local_set(localidx_index);
+
+            // net wasm stack: [] -> []
+            encode_array_index_check(
+                localidx_index,
+                |expr_builder| {
+                    expr_builder.local_get(wasm_localidx_array);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+                },
+                location,
+                ctx,
+                expr_builder,
+            );
+
+            // net wasm stack: [] -> [i32(element_ptr)]
+            expr_builder.local_get(wasm_localidx_array);
+            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+            expr_builder.local_get(localidx_index);
+            expr_builder.i32_trunc_f64_u();
+            expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+            expr_builder.i32_mul();
+            expr_builder.i32_add();
+
+            // net wasm stack: [i32(element_ptr)] -> [<Any>]
+            encode_load_memory(
+                0,
+                ir::VarType::Any,
+                ir::VarType::Any,
+                mutctx.scratch_mut(),
+                expr_builder,
+            );
+        });
+    });
+}
+
+// Requires: `array` has static type Array, `index` has static type Number, and `value` is not noreturn.
+// Traps if the index is not an integer in the range [0, ARRAY_MAX_LENGTH).
+// If the index is past the end of the array, the array will be lengthened, and the new elements before the index will be set to undefined.
+// net wasm stack: [] -> []
+fn encode_array_store<H: HeapManager>(
+    array: &ir::Expr,
+    index: &ir::Expr,
+    value: &ir::Expr,
+    location: &ir::SourceLocation,
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    assert!(
+        array.vartype == Some(ir::VarType::Array),
+        "ICE: IR->Wasm: array of ArrayStore must have static type array"
+    );
+    assert!(
+        index.vartype == Some(ir::VarType::Number),
+        "ICE: IR->Wasm: index of ArrayStore must have static type number"
+    );
+    let value_vartype: ir::VarType = value
+        .vartype
+        .expect("ICE: IR->Wasm: value of ArrayStore cannot be noreturn");
+
+    // Algorithm:
+    /*
+    let arr = <array>;
+    let idx = <index>;
+    if (!(idx >= 0 && idx < ARRAY_MAX_LENGTH && floor(idx) == idx)) trap();
+    let val = <value>;
+    let i = (u32)idx;
+    if (i >= arr->capacity) {
+        // note: allocating might run the gc, so arr and val must be in shadow locals
+        let new_capacity = max(i + 1, arr->capacity * 2, 4);
+        let new_arr = new_array(new_capacity);
+        memcpy(new_arr->data, arr->data, arr->length * ARRAY_ELEMENT_SIZE);
+        arr->capacity = new_arr->capacity;
+        arr->data = new_arr->data;
+    }
+    if (i >= arr->length) {
+        let it = arr->data + arr->length * ARRAY_ELEMENT_SIZE;
+        let it_end = arr->data + i * ARRAY_ELEMENT_SIZE;
+        while (it != it_end) {
+            it->tag = Undefined;
+            it += ARRAY_ELEMENT_SIZE;
+        }
+        arr->length = i + 1;
     }
+    arr->data[i] = val;
+    */
+
+    // net wasm stack: [] -> [i32(array)]
+    encode_expr(array, ctx, mutctx, expr_builder);
+
+    // the array is stored in a shadow local, because evaluating the index or value might allocate memory
+    mutctx.with_uninitialized_shadow_local(ir::VarType::Array, |mutctx, localidx_array| {
+        // net wasm stack: [i32(array)] -> []
+        encode_store_local(
+            mutctx.wasm_local_slice(localidx_array),
+            ir::VarType::Array,
+            ir::VarType::Array,
+            expr_builder,
+        );
+
+        // net wasm stack: [] -> [f64(index)]
+        encode_expr(index, ctx, mutctx, expr_builder);
+
+        mutctx.with_scratch_f64(|mutctx, localidx_index| {
+            // net wasm stack: [f64(index)] -> []
+            expr_builder.local_set(localidx_index);
+
+            // net wasm stack: [] -> []
+            encode_array_index_check(
+                localidx_index,
+                |expr_builder| {
+                    expr_builder.i32_const(ARRAY_MAX_LENGTH as i32);
+                },
+                location,
+                ctx,
+                expr_builder,
+            );
+
+            // net wasm stack: [] -> [<value_vartype>]
+            encode_expr(value, ctx, mutctx, expr_builder);
+
+            // the value is stored in a shadow local, because growing the array might allocate memory
+            mutctx.with_uninitialized_shadow_local(value_vartype, |mutctx, localidx_value| {
+                // net wasm stack: [<value_vartype>] -> []
+                encode_store_local(
+                    mutctx.wasm_local_slice(localidx_value),
+                    value_vartype,
+                    value_vartype,
+                    expr_builder,
+                );
+
+                mutctx.with_scratch_i32(|mutctx, localidx_i| {
+                    // let i = (u32)idx;
+                    // net wasm stack: [] -> []
+                    expr_builder.local_get(localidx_index);
+                    expr_builder.i32_trunc_f64_u();
+                    expr_builder.local_set(localidx_i);
+
+                    // if (i >= arr->capacity) { ... }
+                    // net wasm stack: [] -> []
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.local_get(mutctx.wasm_local_slice(localidx_array)[0]);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_CAPACITY_OFFSET));
+                    expr_builder.i32_ge_u();
+                    expr_builder.if_(&[]);
+                    encode_array_grow(localidx_array, localidx_i, ctx, mutctx, expr_builder);
+                    expr_builder.end();
+
+                    // the array might have been moved by the gc, so we only read the local after growing
+                    let wasm_localidx_array: wasmgen::LocalIdx =
+                        mutctx.wasm_local_slice(localidx_array)[0];
+
+                    // if (i >= arr->length) { ... }
+                    // net wasm stack: [] -> []
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.local_get(wasm_localidx_array);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+                    expr_builder.i32_ge_u();
+                    expr_builder.if_(&[]);
+                    mutctx.with_scratch_i32(|mutctx, localidx_it| {
+                        mutctx.with_scratch_i32(|_mutctx, localidx_it_end| {
+                            // let it = arr->data + arr->length * ARRAY_ELEMENT_SIZE;
+                            expr_builder.local_get(wasm_localidx_array);
+                            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+                            expr_builder.local_get(wasm_localidx_array);
+                            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+                            expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                            expr_builder.i32_mul();
+                            expr_builder.i32_add();
+                            expr_builder.local_set(localidx_it);
+
+                            // let it_end = arr->data + i * ARRAY_ELEMENT_SIZE;
+                            expr_builder.local_get(wasm_localidx_array);
+                            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+                            expr_builder.local_get(localidx_i);
+                            expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                            expr_builder.i32_mul();
+                            expr_builder.i32_add();
+                            expr_builder.local_set(localidx_it_end);
+
+                            // while (it != it_end) { ... }
+                            expr_builder.block(&[]);
+                            expr_builder.loop_(&[]);
+                            {
+                                expr_builder.local_get(localidx_it);
+                                expr_builder.local_get(localidx_it_end);
+                                expr_builder.i32_eq();
+                                expr_builder.br_if(1);
+
+                                expr_builder.local_get(localidx_it);
+                                expr_builder.i32_const(ir::VarType::Undefined.tag());
+                                expr_builder.i32_store(wasmgen::MemArg::new4(0));
+
+                                expr_builder.local_get(localidx_it);
+                                expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                                expr_builder.i32_add();
+                                expr_builder.local_set(localidx_it);
+
+                                expr_builder.br(0);
+                            }
+                            expr_builder.end();
+                            expr_builder.end();
+
+                            // arr->length = i + 1;
+                            expr_builder.local_get(wasm_localidx_array);
+                            expr_builder.local_get(localidx_i);
+                            expr_builder.i32_const(1);
+                            expr_builder.i32_add();
+                            expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+                        });
+                    });
+                    expr_builder.end();
+
+                    // arr->data[i] = val;
+                    // net wasm stack: [] -> []
+                    expr_builder.local_get(wasm_localidx_array);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                    expr_builder.i32_mul();
+                    expr_builder.i32_add();
+                    encode_load_local(
+                        mutctx.wasm_local_slice(localidx_value),
+                        value_vartype,
+                        value_vartype,
+                        expr_builder,
+                    );
+                    encode_store_memory(
+                        0,
+                        ir::VarType::Any,
+                        value_vartype,
+                        mutctx.scratch_mut(),
+                        expr_builder,
+                    );
+                });
+            });
+        });
+    });
+}
+
+// Grows the array in the shadow local `localidx_array` so that it has space for the element at `localidx_i`.
+// The existing elements are moved to the new storage, but the length is not modified.
+// The array in the shadow local might be moved by the gc, so callers should read the local again after calling this function.
+// net wasm stack: [] -> []
+fn encode_array_grow<H: HeapManager>(
+    localidx_array: usize,
+    localidx_i: wasmgen::LocalIdx,
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    mutctx.with_scratch_i32(|mutctx, localidx_new_capacity| {
+        // let new_capacity = max(i + 1, arr->capacity * 2, 4);
+        // net wasm stack: [] -> []
+        {
+            expr_builder.local_get(mutctx.wasm_local_slice(localidx_array)[0]);
+            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_CAPACITY_OFFSET));
+            expr_builder.i32_const(1);
+            expr_builder.i32_shl();
+            expr_builder.local_tee(localidx_new_capacity);
+            expr_builder.local_get(localidx_i);
+            expr_builder.i32_const(1);
+            expr_builder.i32_add();
+            expr_builder.local_get(localidx_new_capacity);
+            expr_builder.local_get(localidx_i);
+            expr_builder.i32_gt_u();
+            expr_builder.select();
+            expr_builder.local_tee(localidx_new_capacity);
+            expr_builder.i32_const(4);
+            expr_builder.local_get(localidx_new_capacity);
+            expr_builder.i32_const(4);
+            expr_builder.i32_gt_u();
+            expr_builder.select();
+            expr_builder.local_set(localidx_new_capacity);
+        }
+
+        // let new_arr = new_array(new_capacity);
+        // net wasm stack: [] -> [i32(new_arr)]
+        expr_builder.local_get(localidx_new_capacity);
+        mutctx.heap_encode_dynamic_allocation(ctx.heap, ir::VarType::Array, expr_builder);
+
+        // reload the array, since the allocation might have moved it
+        let wasm_localidx_array: wasmgen::LocalIdx = mutctx.wasm_local_slice(localidx_array)[0];
+
+        mutctx.with_scratch_i32(|mutctx, localidx_new_data| {
+            // net wasm stack: [i32(new_arr)] -> []
+            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+            expr_builder.local_set(localidx_new_data);
+
+            // memcpy(new_arr->data, arr->data, arr->length * ARRAY_ELEMENT_SIZE);
+            // net wasm stack: [] -> []
+            if ctx.options.wasm_bulk_memory {
+                expr_builder.local_get(localidx_new_data);
+                expr_builder.local_get(wasm_localidx_array);
+                expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+                expr_builder.local_get(wasm_localidx_array);
+                expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+                expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                expr_builder.i32_mul();
+                expr_builder.memory_copy(ctx.memidx, ctx.memidx);
+            } else {
+                // we copy 4 bytes at a time, since elements are 4-byte aligned
+                mutctx.with_scratch_i32(|mutctx, localidx_dest| {
+                    mutctx.with_scratch_i32(|mutctx, localidx_src| {
+                        mutctx.with_scratch_i32(|_mutctx, localidx_src_end| {
+                            expr_builder.local_get(localidx_new_data);
+                            expr_builder.local_set(localidx_dest);
+                            expr_builder.local_get(wasm_localidx_array);
+                            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+                            expr_builder.local_tee(localidx_src);
+                            expr_builder.local_get(wasm_localidx_array);
+                            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+                            expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                            expr_builder.i32_mul();
+                            expr_builder.i32_add();
+                            expr_builder.local_set(localidx_src_end);
+
+                            expr_builder.block(&[]);
+                            expr_builder.loop_(&[]);
+                            {
+                                expr_builder.local_get(localidx_src);
+                                expr_builder.local_get(localidx_src_end);
+                                expr_builder.i32_eq();
+                                expr_builder.br_if(1);
+
+                                expr_builder.local_get(localidx_dest);
+                                expr_builder.local_get(localidx_src);
+                                expr_builder.i32_load(wasmgen::MemArg::new4(0));
+                                expr_builder.i32_store(wasmgen::MemArg::new4(0));
+
+                                expr_builder.local_get(localidx_dest);
+                                expr_builder.i32_const(4);
+                                expr_builder.i32_add();
+                                expr_builder.local_set(localidx_dest);
+                                expr_builder.local_get(localidx_src);
+                                expr_builder.i32_const(4);
+                                expr_builder.i32_add();
+                                expr_builder.local_set(localidx_src);
+
+                                expr_builder.br(0);
+                            }
+                            expr_builder.end();
+                            expr_builder.end();
+                        });
+                    });
+                });
+            }
+
+            // arr->capacity = new_capacity;
+            // arr->data = new_arr->data;
+            // net wasm stack: [] -> []
+            expr_builder.local_get(wasm_localidx_array);
+            expr_builder.local_get(localidx_new_capacity);
+            expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_CAPACITY_OFFSET));
+            expr_builder.local_get(wasm_localidx_array);
+            expr_builder.local_get(localidx_new_data);
+            expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+        });
+    });
+}
+
+// Encodes the return of the given expression from the current function (i.e. the inner expr of a Return).
+// If the expression is a function call, it is encoded as a tail call when possible.
+// Conditionals are handled by returning from each branch separately, so that calls in their branches are also in tail position.
+// net wasm stack: [] -> [stack-polymorphic]
+fn encode_return<H: HeapManager>(
+    expr: &ir::Expr,
+    ret_type: ir::VarType,
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    match &expr.kind {
+        ir::ExprKind::Appl {
+            func,
+            args,
+            location,
+        } if ret_type == ir::VarType::Any => {
+            if ctx.options.wasm_tail_call {
+                encode_appl(
+                    expr.vartype,
+                    func,
+                    args,
+                    location,
+                    CallKind::TailCall,
+                    ctx,
+                    mutctx,
+                    expr_builder,
+                );
+                return;
+            } else if ctx.trampoline.is_some() && !ctx.is_entry_point {
+                encode_appl(
+                    expr.vartype,
+                    func,
+                    args,
+                    location,
+                    CallKind::Trampoline,
+                    ctx,
+                    mutctx,
+                    expr_builder,
+                );
+                return;
+            }
+        }
+        ir::ExprKind::DirectAppl { funcidx, args }
+            if ctx.ir_signature_list[*funcidx].result == Some(ret_type) =>
+        {
+            if ctx.options.wasm_tail_call {
+                encode_direct_appl(
+                    expr.vartype,
+                    *funcidx,
+                    args,
+                    CallKind::TailCall,
+                    ctx,
+                    mutctx,
+                    expr_builder,
+                );
+                return;
+            } else if ctx.funcidx == Some(*funcidx) && mutctx.get_tail_call_landing().is_some() {
+                encode_self_tail_call(*funcidx, args, ctx, mutctx, expr_builder);
+                return;
+            } else if ctx.trampoline.is_some() && !ctx.is_entry_point && *funcidx >= ctx.num_imports
+            {
+                // (this must match pre_traverse_func(), which adds the thunk of the callee)
+                encode_direct_trampoline_call(*funcidx, args, ctx, mutctx, expr_builder);
+                return;
+            }
+        }
+        ir::ExprKind::Conditional {
+            cond,
+            true_expr,
+            false_expr,
+        } => {
+            // net wasm stack: [] -> [<cond.vartype>(bool)]
+            encode_expr(cond, ctx, mutctx, expr_builder);
+            // both branches return, so the if statement does not produce any value
+            // net wasm stack: [i32 result] -> []
+            mutctx.with_unused_landing(|mutctx| {
+                expr_builder.if_(&[]);
+                encode_return(true_expr, ret_type, ctx, mutctx, expr_builder);
+                expr_builder.else_();
+                encode_return(false_expr, ret_type, ctx, mutctx, expr_builder);
+                expr_builder.end();
+            });
+            expr_builder.unreachable();
+            return;
+        }
+        _ => {}
+    }
+
+    // not a tail call, so we just evaluate the expression and return it
+    // net wasm stack: [] -> [<expr.vartype>]
+    let wasm_reachable = encode_expr(expr, ctx, mutctx, expr_builder);
+    if let Some(vartype) = expr.vartype {
+        // net wasm stack: [<expr.vartype>] -> [<return_calling_conv(ret_type)>]
+        encode_return_calling_conv(
+            ret_type,
+            vartype,
+            ctx.options.wasm_multi_value,
+            ctx.stackptr,
+            mutctx.scratch_mut(),
+            expr_builder,
+        );
+        // return the value on the stack (or in the unprotected stack) (which now has the correct type)
+        expr_builder.return_();
+    } else if wasm_reachable {
+        expr_builder.unreachable();
+    }
+}
+
+// How a function call should be made.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum CallKind {
+    Normal,     // a normal call, which returns back to the caller
+    TailCall, // a tail call using return_call or return_call_indirect (needs the wasm tail call proposal)
+    Trampoline, // (indirect calls only) a pending tail call that is made by the nearest non-tail call site (see `Trampoline`)
 }
 
 // Requires: the callee actually has the correct number of parameters,
 // and the func_expr has type VarType::Func or VarType::Any
 // and the callee must have all params of type Any, and return type must also be Any.
 // (to use more specific types, we must know the target function at compilation time, and hence use the DirectAppl)
-// net wasm stack: [] -> [<return_type>]
+// net wasm stack: [] -> [<return_type>] (or [stack-polymorphic] if it is not a CallKind::Normal call)
 fn encode_appl<H: HeapManager>(
     return_type: Option<ir::VarType>,
     func_expr: &ir::Expr,
     args: &[ir::Expr],
     location: &ir::SourceLocation,
+    call_kind: CallKind,
     ctx: EncodeContext<H>,
     mutctx: &mut MutContext,
     expr_builder: &mut wasmgen::ExprBuilder,
@@ -1339,66 +2153,317 @@ fn encode_appl<H: HeapManager>(
         // net wasm stack: [] -> [tableidx]
         expr_builder.local_get(mutctx.wasm_local_slice(localidx_func)[0]);
 
-        // todo!(For optimisation, heap_encode_prologue_epilogue should only be called if the callee might allocate)
-        // Note: encode_args_to_call_function should be *before* encode_local_roots_prologue, since the args themselves might make function calls.
-        if true {
-            // This function might allocate memory, so we need to store the locals in the gc_roots stack first.
+        match call_kind {
+            CallKind::Normal => {
+                // todo!(For optimisation, heap_encode_prologue_epilogue should only be called if the callee might allocate)
+                // Note: encode_args_to_call_function should be *before* encode_local_roots_prologue, since the args themselves might make function calls.
+                if true {
+                    // This function might allocate memory, so we need to store the locals in the gc_roots stack first.
 
-            // call the function with gc prologue and epilogue
-            mutctx.heap_encode_prologue_epilogue(ctx.heap, expr_builder, |mutctx, expr_builder| {
-                // call the function (indirectly, using uniform calling convention)
-                expr_builder.call_indirect(
+                    // call the function with gc prologue and epilogue
+                    mutctx.heap_encode_prologue_epilogue(
+                        ctx.heap,
+                        expr_builder,
+                        |mutctx, expr_builder| {
+                            // call the function (indirectly, using uniform calling convention)
+                            expr_builder.call_indirect(
+                                mutctx.module_wrapper().add_wasm_type(
+                                    uniform_calling_conv_functype(ctx.options.wasm_multi_value),
+                                ),
+                                wasmgen::TableIdx { idx: 0 },
+                            );
+                            // make any pending tail calls (this might allocate memory too)
+                            encode_trampoline_calls(ctx, mutctx, expr_builder);
+                        },
+                    );
+                } else {
+                    // This function is guaranteed not to allocate memory, so we don't need to put the locals on the gc_roots stack.
+
+                    // call the function (indirectly)
+                    expr_builder.call_indirect(
+                        mutctx
+                            .module_wrapper()
+                            .add_wasm_type(uniform_calling_conv_functype(
+                                ctx.options.wasm_multi_value,
+                            )),
+                        wasmgen::TableIdx { idx: 0 },
+                    );
+                }
+
+                // fetch return values from the location prescribed by the calling convention back to the stack
+                encode_post_appl_calling_conv(
+                    Some(ir::VarType::Any),
+                    ctx.options.wasm_multi_value,
+                    ctx.stackptr,
+                    mutctx.scratch_mut(),
+                    expr_builder,
+                );
+            }
+            CallKind::TailCall => {
+                // The locals of this function are dead after the call, so there's no need for the gc prologue and epilogue.
+                // The callee returns Any, which is also our return type, so the callee returns directly to our caller.
+                assert!(ctx.return_type == Some(ir::VarType::Any));
+                expr_builder.return_call_indirect(
                     mutctx
                         .module_wrapper()
-                        .add_wasm_type(wasmgen::FuncType::new(
-                            Box::new([
-                                wasmgen::ValType::I32,
-                                wasmgen::ValType::I32,
-                                wasmgen::ValType::I32,
-                            ]),
-                            encode_result(Some(ir::VarType::Any), ctx.options.wasm_multi_value),
-                        )),
+                        .add_wasm_type(uniform_calling_conv_functype(ctx.options.wasm_multi_value)),
                     wasmgen::TableIdx { idx: 0 },
                 );
-            });
-        } else {
-            // This function is guaranteed not to allocate memory, so we don't need to put the locals on the gc_roots stack.
-
-            // call the function (indirectly)
-            expr_builder.call_indirect(
-                mutctx
-                    .module_wrapper()
-                    .add_wasm_type(wasmgen::FuncType::new(
-                        Box::new([
-                            wasmgen::ValType::I32,
-                            wasmgen::ValType::I32,
-                            wasmgen::ValType::I32,
-                        ]),
-                        encode_result(Some(ir::VarType::Any), ctx.options.wasm_multi_value),
-                    )),
-                wasmgen::TableIdx { idx: 0 },
-            );
+            }
+            CallKind::Trampoline => {
+                encode_pending_call(ctx, expr_builder);
+            }
         }
+    });
+}
 
-        // fetch return values from the location prescribed by the calling convention back to the stack
-        encode_post_appl_calling_conv(
-            Some(ir::VarType::Any),
-            ctx.options.wasm_multi_value,
-            ctx.stackptr,
-            mutctx.scratch_mut(),
-            expr_builder,
-        );
+// Leaves a pending call for the trampoline and returns a dummy value.  The nearest non-tail call site will make the call for us.
+// Requires: the args are already on the unprotected stack, and the current function returns Any
+// (or it is a typed trampoline caller, whose call sites also look for pending calls).
+// net wasm stack: [i32(closure), i32(num_args), i32(callerid), i32(tableidx)] -> [stack-polymorphic]
+fn encode_pending_call<H: HeapManager>(
+    ctx: EncodeContext<H>,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    assert!(ctx.return_type.is_some());
+    let trampoline: Trampoline = ctx.trampoline.unwrap();
+    // net wasm stack: [i32(closure), i32(num_args), i32(callerid), i32(tableidx)] -> []
+    expr_builder.global_set(trampoline.tableidx);
+    expr_builder.global_set(trampoline.callerid);
+    expr_builder.global_set(trampoline.num_args);
+    expr_builder.global_set(trampoline.closure);
+    // net wasm stack: [] -> [return_calling_conv(return_type)]
+    // (if the return value is supposed to be on the unprotected stack, we must not write it, because the args are there)
+    encode_load_dummies(
+        &encode_result(ctx.return_type, ctx.options.wasm_multi_value),
+        expr_builder,
+    );
+    expr_builder.return_();
+}
+
+// Encodes a direct tail call to another function without using the wasm tail call proposal,
+// by leaving a pending call to the callee's thunk (which pre_traverse added for this purpose).
+// The args are passed as Any with the uniform calling convention, like an indirect call.
+// Requires: the current function and the callee have the same return type.
+// net wasm stack: [] -> [stack-polymorphic]
+fn encode_direct_trampoline_call<H: HeapManager>(
+    funcidx: ir::FuncIdx,
+    args: &[ir::Expr],
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    assert!(ctx.ir_signature_list[funcidx].result == ctx.return_type);
+    let tableidx: u32 = *ctx
+        .thunk_map
+        .get(&[ir::OverloadEntry {
+            funcidx: funcidx,
+            has_closure_param: false,
+        }] as &[ir::OverloadEntry])
+        .expect("ICE: IR->Wasm: missing thunk for direct tail call");
+    mutctx.with_scratch_i32(|mutctx, localidx_closure| {
+        // the thunk does not take a closure (any closure is passed as the first arg)
+        expr_builder.i32_const(0);
+        expr_builder.local_set(localidx_closure);
+        // net wasm stack: [] -> [i32(closure), i32(num_args)]
+        encode_args_to_call_indirect_function(args, localidx_closure, ctx, mutctx, expr_builder);
     });
+    // the thunk only uses the callerid to report a wrong number of args or arg types,
+    // which cannot happen because the args already fit the params of the callee
+    // net wasm stack: [] -> [i32(callerid), i32(tableidx)]
+    expr_builder.i32_const(*ctx.appl_data_encoder.get(&ctx.repl_sl).unwrap() as i32);
+    expr_builder.i32_const(tableidx as i32);
+    // net wasm stack: [i32(closure), i32(num_args), i32(callerid), i32(tableidx)] -> [stack-polymorphic]
+    encode_pending_call(ctx, expr_builder);
+}
+
+// Returns a dummy value if there is a pending tail call, so that the nearest non-tail call site will make it.
+// Requires: the current function returns Any.
+// net wasm stack: [] -> []
+fn encode_return_if_pending<H: HeapManager>(
+    ctx: EncodeContext<H>,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    assert!(ctx.return_type == Some(ir::VarType::Any));
+    let trampoline: Trampoline = ctx.trampoline.unwrap();
+    // if (trampoline.num_args != -1) return <dummy>;
+    expr_builder.global_get(trampoline.num_args);
+    expr_builder.i32_const(-1);
+    expr_builder.i32_ne();
+    expr_builder.if_(&[]);
+    encode_load_dummies(
+        &encode_result(Some(ir::VarType::Any), ctx.options.wasm_multi_value),
+        expr_builder,
+    );
+    expr_builder.return_();
+    expr_builder.end();
+}
+
+// Makes the pending tail calls (if any) that were left by the function that was just called.
+// Does nothing if we are using real tail calls.
+// Requires: the function that was just called returns Any.
+// net wasm stack: [return_calling_conv(Any)] -> [return_calling_conv(Any)]
+fn encode_trampoline_calls<H: HeapManager>(
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    if let Some(trampoline) = ctx.trampoline {
+        let wasm_result: Box<[wasmgen::ValType]> =
+            encode_result(Some(ir::VarType::Any), ctx.options.wasm_multi_value);
+        mutctx.with_scratches(&wasm_result, |mutctx, localidxs_result| {
+            // save the return value, because we can't access it from inside the loop
+            // net wasm stack: [return_calling_conv(Any)] -> []
+            localidxs_result.iter().rev().for_each(|localidx| {
+                expr_builder.local_set(*localidx);
+            });
+
+            // while (trampoline.num_args != -1) {
+            //   <make the pending call>
+            // }
+            expr_builder.loop_(&[]);
+            {
+                expr_builder.global_get(trampoline.num_args);
+                expr_builder.i32_const(-1);
+                expr_builder.i32_ne();
+                expr_builder.if_(&[]);
+                {
+                    // net wasm stack: [] -> [i32(closure), i32(num_args), i32(callerid), i32(tableidx)]
+                    expr_builder.global_get(trampoline.closure);
+                    expr_builder.global_get(trampoline.num_args);
+                    expr_builder.global_get(trampoline.callerid);
+                    expr_builder.global_get(trampoline.tableidx);
+                    // clear the pending call (the callee might set a new one)
+                    expr_builder.i32_const(-1);
+                    expr_builder.global_set(trampoline.num_args);
+                    // net wasm stack: [i32(closure), i32(num_args), i32(callerid), i32(tableidx)] -> [return_calling_conv(Any)]
+                    expr_builder.call_indirect(
+                        mutctx
+                            .module_wrapper()
+                            .add_wasm_type(uniform_calling_conv_functype(
+                                ctx.options.wasm_multi_value,
+                            )),
+                        wasmgen::TableIdx { idx: 0 },
+                    );
+                    // net wasm stack: [return_calling_conv(Any)] -> []
+                    localidxs_result.iter().rev().for_each(|localidx| {
+                        expr_builder.local_set(*localidx);
+                    });
+                    expr_builder.br(1);
+                }
+                expr_builder.end();
+            }
+            expr_builder.end();
+
+            // net wasm stack: [] -> [return_calling_conv(Any)]
+            localidxs_result.iter().for_each(|localidx| {
+                expr_builder.local_get(*localidx);
+            });
+        });
+    }
+}
+
+// Makes the pending tail calls (if any) that were left by the typed trampoline caller that was just called.
+// The trampoline returns an Any, which is narrowed back to the return type of that function (the last callee returns the same type).
+// Does nothing if we are using real tail calls.
+// net wasm stack: [return_calling_conv(vartype)] -> [return_calling_conv(vartype)]
+fn encode_typed_trampoline_calls<H: HeapManager>(
+    vartype: ir::VarType,
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    if let Some(trampoline) = ctx.trampoline {
+        let wasm_result: Box<[wasmgen::ValType]> =
+            encode_result(Some(vartype), ctx.options.wasm_multi_value);
+        mutctx.with_scratches(&wasm_result, |mutctx, localidxs_result| {
+            // save the return value (it is a dummy if there is a pending call)
+            // net wasm stack: [return_calling_conv(vartype)] -> []
+            localidxs_result.iter().rev().for_each(|localidx| {
+                expr_builder.local_set(*localidx);
+            });
+
+            // if (trampoline.num_args != -1) {
+            //   <make the pending calls, and replace the return value with their result>
+            // }
+            expr_builder.global_get(trampoline.num_args);
+            expr_builder.i32_const(-1);
+            expr_builder.i32_ne();
+            expr_builder.if_(&[]);
+            {
+                // the dummy result is never read
+                // net wasm stack: [] -> [return_calling_conv(Any)]
+                encode_load_dummies(
+                    &encode_result(Some(ir::VarType::Any), ctx.options.wasm_multi_value),
+                    expr_builder,
+                );
+                encode_trampoline_calls(ctx, mutctx, expr_builder);
+                // net wasm stack: [return_calling_conv(Any)] -> [Any]
+                encode_post_appl_calling_conv(
+                    Some(ir::VarType::Any),
+                    ctx.options.wasm_multi_value,
+                    ctx.stackptr,
+                    mutctx.scratch_mut(),
+                    expr_builder,
+                );
+                // net wasm stack: [Any] -> [vartype]
+                mutctx.with_scratches(encode_vartype(ir::VarType::Any), |_, localidxs_any| {
+                    encode_store_local(
+                        localidxs_any,
+                        ir::VarType::Any,
+                        ir::VarType::Any,
+                        expr_builder,
+                    );
+                    // (unchecked, because the last callee returned a vartype)
+                    encode_load_local(localidxs_any, ir::VarType::Any, vartype, expr_builder);
+                });
+                // net wasm stack: [vartype] -> [return_calling_conv(vartype)]
+                encode_return_calling_conv(
+                    vartype,
+                    vartype,
+                    ctx.options.wasm_multi_value,
+                    ctx.stackptr,
+                    mutctx.scratch_mut(),
+                    expr_builder,
+                );
+                // net wasm stack: [return_calling_conv(vartype)] -> []
+                localidxs_result.iter().rev().for_each(|localidx| {
+                    expr_builder.local_set(*localidx);
+                });
+            }
+            expr_builder.end();
+
+            // net wasm stack: [] -> [return_calling_conv(vartype)]
+            localidxs_result.iter().for_each(|localidx| {
+                expr_builder.local_get(*localidx);
+            });
+        });
+    }
+}
+
+// The wasm function type of functions called with the uniform calling convention (i.e. thunks):
+// [i32(closure), i32(num_args), i32(callerid)] -> [return_calling_conv(Any)]
+fn uniform_calling_conv_functype(use_wasm_multi_value_feature: bool) -> wasmgen::FuncType {
+    wasmgen::FuncType::new(
+        Box::new([
+            wasmgen::ValType::I32,
+            wasmgen::ValType::I32,
+            wasmgen::ValType::I32,
+        ]),
+        encode_result(Some(ir::VarType::Any), use_wasm_multi_value_feature),
+    )
 }
 
 // Requires: the callee actually has the correct number of parameters,
 // and the each parameter of the callee must have a type at least as wide as (i.e. be a supertype of) the corresponding args[i].vartype,
 // and the return type of the callee is exactly return_type.
-// net wasm stack: [] -> [<return_type>]
+// Trampoline calls are not supported (use encode_self_tail_call() for self tail calls instead).
+// net wasm stack: [] -> [<return_type>] (or [stack-polymorphic] if it is a CallKind::TailCall)
 fn encode_direct_appl<H: HeapManager>(
     return_type: Option<ir::VarType>,
     funcidx: ir::FuncIdx,
     args: &[ir::Expr],
+    call_kind: CallKind,
     ctx: EncodeContext<H>,
     mutctx: &mut MutContext,
     expr_builder: &mut wasmgen::ExprBuilder,
@@ -1414,31 +2479,90 @@ fn encode_direct_appl<H: HeapManager>(
     // Encode all the arguments
     encode_args_to_call_function(&signature.params, args, ctx, mutctx, expr_builder);
 
-    // todo!(For optimisation, heap_encode_prologue_epilogue should only be called if the callee might allocate)
-    // Note: encode_args_to_call_function should be *before* encode_local_roots_prologue, since the args themselves might make function calls.
-    if true {
-        // This function might allocate memory, so we need to store the locals in the gc_roots stack first.
+    match call_kind {
+        CallKind::Normal => {
+            // todo!(For optimisation, heap_encode_prologue_epilogue should only be called if the callee might allocate)
+            // Note: encode_args_to_call_function should be *before* encode_local_roots_prologue, since the args themselves might make function calls.
+            if true {
+                // This function might allocate memory, so we need to store the locals in the gc_roots stack first.
 
-        // call the function with gc prologue and epilogue
-        mutctx.heap_encode_prologue_epilogue(ctx.heap, expr_builder, |_mutctx, expr_builder| {
-            // call the function
-            expr_builder.call(ctx.wasm_funcidxs[funcidx]);
-        });
-    } else {
-        // This function is guaranteed not to allocate memory, so we don't need to put the locals on the gc_roots stack.
+                // call the function with gc prologue and epilogue
+                mutctx.heap_encode_prologue_epilogue(
+                    ctx.heap,
+                    expr_builder,
+                    |mutctx, expr_builder| {
+                        // call the function
+                        expr_builder.call(ctx.wasm_funcidxs[funcidx]);
+                        // only functions that return Any and typed trampoline callers can leave a pending tail call
+                        if return_type == Some(ir::VarType::Any) {
+                            encode_trampoline_calls(ctx, mutctx, expr_builder);
+                        } else if signature.typed_trampoline_caller {
+                            encode_typed_trampoline_calls(
+                                return_type.unwrap(),
+                                ctx,
+                                mutctx,
+                                expr_builder,
+                            );
+                        }
+                    },
+                );
+            } else {
+                // This function is guaranteed not to allocate memory, so we don't need to put the locals on the gc_roots stack.
+
+                // call the function
+                expr_builder.call(ctx.wasm_funcidxs[funcidx]);
+            }
 
-        // call the function
-        expr_builder.call(ctx.wasm_funcidxs[funcidx]);
+            // fetch return values from the location prescribed by the calling convention back to the stack
+            encode_post_appl_calling_conv(
+                return_type,
+                ctx.options.wasm_multi_value,
+                ctx.stackptr,
+                mutctx.scratch_mut(),
+                expr_builder,
+            );
+        }
+        CallKind::TailCall => {
+            // The locals of this function are dead after the call, so there's no need for the gc prologue and epilogue.
+            assert!(ctx.return_type == return_type);
+            expr_builder.return_call(ctx.wasm_funcidxs[funcidx]);
+        }
+        CallKind::Trampoline => {
+            panic!("ICE: IR->Wasm: direct calls cannot use the trampoline");
+        }
     }
+}
 
-    // fetch return values from the location prescribed by the calling convention back to the stack
-    encode_post_appl_calling_conv(
-        return_type,
-        ctx.options.wasm_multi_value,
-        ctx.stackptr,
-        mutctx.scratch_mut(),
-        expr_builder,
-    );
+// Encodes a tail call to the current function without using the wasm tail call proposal,
+// by assigning the args to the params and then branching to the beginning of the function.
+// Requires: the function body is wrapped in a tail call landing (see MutContext::with_tail_call_landing()).
+// net wasm stack: [] -> [stack-polymorphic]
+fn encode_self_tail_call<H: HeapManager>(
+    funcidx: ir::FuncIdx,
+    args: &[ir::Expr],
+    ctx: EncodeContext<H>,
+    mutctx: &mut MutContext,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    let signature: &Signature = &ctx.ir_signature_list[funcidx];
+
+    // Encode all the arguments (they must all be evaluated before we overwrite any param)
+    // net wasm stack: [] -> [<signature.params[0]>, <signature.params[1]>, ...]
+    encode_args_to_call_function(&signature.params, args, ctx, mutctx, expr_builder);
+
+    // Assign them to the params (the params are the first locals in the mutctx), last param first
+    // net wasm stack: [<signature.params[0]>, <signature.params[1]>, ...] -> []
+    for (i, param_type) in signature.params.iter().copied().enumerate().rev() {
+        encode_store_local(
+            mutctx.wasm_local_slice(i),
+            param_type,
+            param_type,
+            expr_builder,
+        );
+    }
+
+    // Go back to the beginning of the function
+    expr_builder.br(mutctx.get_tail_call_landing().unwrap() as u32);
 }
 
 // This function prepares subexpressions when calling a function.
@@ -1950,6 +3074,13 @@ fn encode_thunk<H: HeapManager>(
             } else {
                 // need to do a type conversion
                 if let Some(res) = result {
+                    if ctx.trampoline.is_some()
+                        && ctx.ir_signature_list[oe.funcidx].typed_trampoline_caller
+                    {
+                        // if the callee left a pending tail call, pass it on to our caller, which will make it
+                        // (the result is a dummy, and we must not write it to the unprotected stack, because the args are there)
+                        encode_return_if_pending(ctx, expr_builder);
+                    }
                     // net wasm stack: [return_calling_conv(vartype)] -> [vartype]
                     encode_post_appl_calling_conv(
                         result,
@@ -1,6 +1,9 @@
 use wasmgen::Scratch;
 
 use super::WASM_PAGE_BITS;
+use crate::ARRAY_ELEMENT_SIZE;
+use crate::ARRAY_HEADER_SIZE;
+use crate::ARRAY_LENGTH_OFFSET;
 
 // returns the base table element index from which indirect access should be calculated (i.e. the "table offset")
 // e.g. if we want to access copy_children_$i, we should call_indirect with index = (table_offset+i)
@@ -48,6 +51,102 @@ pub fn make_copy_children_elements(
         func_idx
     }
 
+    // make the array version of copy_children
+    // it calls copy_indirect on every element, and returns the ptr past-the-end of the elements
+    // note: copy_$i for arrays always places the elements directly after the header, so we don't need to read the data field
+    fn make_array_function(
+        wasm_module: &mut wasmgen::WasmModule,
+        tableidx: wasmgen::TableIdx,
+        copy_indirect_table_offset: u32,
+    ) -> wasmgen::FuncIdx {
+        let functype = wasmgen::FuncType::new(
+            Box::new([wasmgen::ValType::I32]),
+            Box::new([wasmgen::ValType::I32]),
+        );
+        let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+        let mut code_builder = wasmgen::CodeBuilder::new(functype);
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let localidx_param = wasmgen::LocalIdx { idx: 0 };
+            let mut scratch = Scratch::new(locals_builder);
+
+            /*
+            // Algorithm:
+            let it = ptr + ARRAY_HEADER_SIZE;
+            let end = it + ptr->length * ARRAY_ELEMENT_SIZE;
+            while (it != end) {
+                it->data = (*(GC_TABLE_PTR_COPY_INDIRECT_OFFSET + it->tag))(it->data);
+                it += ARRAY_ELEMENT_SIZE;
+            }
+            return end;
+            */
+            let localidx_it = scratch.push_i32();
+            let localidx_end = scratch.push_i32();
+
+            // net wasm stack: [] -> []
+            expr_builder.local_get(localidx_param);
+            expr_builder.i32_const(ARRAY_HEADER_SIZE as i32);
+            expr_builder.i32_add();
+            expr_builder.local_tee(localidx_it);
+            expr_builder.local_get(localidx_param);
+            expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+            expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+            expr_builder.i32_mul();
+            expr_builder.i32_add();
+            expr_builder.local_set(localidx_end);
+
+            // net wasm stack: [] -> []
+            expr_builder.block(&[]);
+            expr_builder.loop_(&[]);
+            {
+                // if (it == end) break;
+                expr_builder.local_get(localidx_it);
+                expr_builder.local_get(localidx_end);
+                expr_builder.i32_eq();
+                expr_builder.br_if(1);
+
+                // it->data = (*(GC_TABLE_PTR_COPY_INDIRECT_OFFSET + it->tag))(it->data);
+                expr_builder.local_get(localidx_it);
+                expr_builder.local_get(localidx_it);
+                expr_builder.i64_load(wasmgen::MemArg::new4(4)); // the `data` of the Any is at offset 4
+                expr_builder.local_get(localidx_it);
+                expr_builder.i32_load(wasmgen::MemArg::new4(0)); // the `tag` of the Any is at offset 0
+                if copy_indirect_table_offset != 0 {
+                    expr_builder.i32_const(copy_indirect_table_offset as i32);
+                    expr_builder.i32_add();
+                }
+                expr_builder.call_indirect(
+                    wasm_module.insert_type_into(wasmgen::FuncType::new(
+                        Box::new([wasmgen::ValType::I64]),
+                        Box::new([wasmgen::ValType::I64]),
+                    )),
+                    tableidx,
+                );
+                expr_builder.i64_store(wasmgen::MemArg::new4(4)); // the `data` of the Any is at offset 4
+
+                // it += ARRAY_ELEMENT_SIZE;
+                expr_builder.local_get(localidx_it);
+                expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                expr_builder.i32_add();
+                expr_builder.local_set(localidx_it);
+
+                expr_builder.br(0);
+            }
+            expr_builder.end();
+            expr_builder.end();
+
+            // net wasm stack: [] -> [ret(i32)]
+            expr_builder.local_get(localidx_end);
+
+            scratch.pop_i32();
+            scratch.pop_i32();
+
+            expr_builder.end(); // return it
+        }
+        wasm_module.commit_func(func_idx, code_builder);
+        func_idx
+    }
+
     // make the struct version of copy_children
     fn make_struct_function(
         wasm_module: &mut wasmgen::WasmModule,
@@ -101,6 +200,7 @@ pub fn make_copy_children_elements(
                         }
                         ir::VarType::Unassigned => {}
                         ir::VarType::Undefined => {}
+                        ir::VarType::Null => {}
                         ir::VarType::Number => {}
                         ir::VarType::Boolean => {}
                         ir::VarType::String => {
@@ -188,6 +288,19 @@ pub fn make_copy_children_elements(
                             scratch.pop_i32();
                             scratch.pop_i32();
                         }
+                        ir::VarType::Array => {
+                            // net wasm stack: [] -> []
+                            gen(
+                                expr_builder,
+                                &mut scratch,
+                                localidx_param,
+                                byte_offset,
+                                tableidx,
+                                copy_funcs[ir::VarType::Array.tag() as usize].unwrap(),
+                                heap_begin,
+                                false,
+                            );
+                        }
                         ir::VarType::StructT { typeidx } => {
                             // net wasm stack: [] -> []
                             gen(
@@ -300,17 +413,33 @@ pub fn make_copy_children_elements(
     // Note: some reserved table elements are left uncommitted.  They will automatically trap if called at runtime.  (If that happens, then the compiler has a bug.)
 
     let funcidx_string: wasmgen::FuncIdx = make_string_function(wasm_module);
+    wasm_module.set_func_name(
+        funcidx_string,
+        format!("copy_children_{}", ir::VarType::String.tag()),
+    );
     wasm_module.commit_table_elements(
         tableidx,
         copy_children_table_offset + ir::VarType::String.tag() as u32,
         Box::new([funcidx_string]),
     );
+    let funcidx_array: wasmgen::FuncIdx =
+        make_array_function(wasm_module, tableidx, copy_indirect_table_offset);
+    wasm_module.set_func_name(
+        funcidx_array,
+        format!("copy_children_{}", ir::VarType::Array.tag()),
+    );
+    wasm_module.commit_table_elements(
+        tableidx,
+        copy_children_table_offset + ir::VarType::Array.tag() as u32,
+        Box::new([funcidx_array]),
+    );
     let funcidxs_structs: Box<[wasmgen::FuncIdx]> = struct_types
         .iter()
         .zip(struct_field_byte_offsets.iter())
         .zip(struct_sizes.iter().cloned())
-        .map(|((ir_vartypes, byte_offsets), struct_size)| {
-            make_struct_function(
+        .enumerate()
+        .map(|(n, ((ir_vartypes, byte_offsets), struct_size))| {
+            let funcidx = make_struct_function(
                 wasm_module,
                 ir_vartypes,
                 byte_offsets,
@@ -319,7 +448,12 @@ pub fn make_copy_children_elements(
                 copy_indirect_table_offset,
                 copy_funcs,
                 heap_begin,
-            )
+            );
+            wasm_module.set_func_name(
+                funcidx,
+                format!("copy_children_{}", ir::NUM_PRIMITIVE_TAG_TYPES + n),
+            );
+            funcidx
         })
         .collect();
     wasm_module.commit_table_elements(
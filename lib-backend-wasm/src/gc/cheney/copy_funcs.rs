@@ -1,3 +1,8 @@
+use crate::ARRAY_CAPACITY_OFFSET;
+use crate::ARRAY_DATA_OFFSET;
+use crate::ARRAY_ELEMENT_SIZE;
+use crate::ARRAY_HEADER_SIZE;
+use crate::ARRAY_LENGTH_OFFSET;
 use wasmgen::Scratch;
 
 pub fn make_copy_funcs(
@@ -11,6 +16,7 @@ pub fn make_copy_funcs(
             Box::new([wasmgen::ValType::I32]),
         );
         let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+        wasm_module.set_func_name(func_idx, "copy_string".to_string());
         let mut code_builder = wasmgen::CodeBuilder::new(functype);
         {
             let (locals_builder, expr_builder) = code_builder.split();
@@ -117,6 +123,13 @@ pub fn make_copy_funcs(
                         expr_builder.end();
                     }
 
+                    // free_mem_ptr = (local) free_mem_ptr;
+                    // net wasm stack: [] -> []
+                    {
+                        expr_builder.local_get(localidx_free_mem_ptr);
+                        expr_builder.global_set(free_mem_ptr);
+                    }
+
                     scratch.pop_i32();
                 }
 
@@ -144,6 +157,172 @@ pub fn make_copy_funcs(
         func_idx
     };
 
+    let funcidx_copy_array: wasmgen::FuncIdx = {
+        let functype = wasmgen::FuncType::new(
+            Box::new([wasmgen::ValType::I32]),
+            Box::new([wasmgen::ValType::I32]),
+        );
+        let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+        wasm_module.set_func_name(func_idx, "copy_array".to_string());
+        let mut code_builder = wasmgen::CodeBuilder::new(functype);
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let mut scratch = Scratch::new(locals_builder);
+            let localidx_param = wasmgen::LocalIdx { idx: 0 };
+
+            // Algorithm
+            // (the elements are compacted to sit directly after the header, and the capacity is shrunk to the length)
+            /*
+            let new_ptr = free_mem_ptr + 4; // skip the tag
+            let len = ptr->length;
+            *free_mem_ptr = *(ptr-4); // copy the tag
+            new_ptr->length = len;
+            new_ptr->capacity = len;
+            new_ptr->data = new_ptr + ARRAY_HEADER_SIZE;
+            let it = ptr->data;
+            let end = it + len * ARRAY_ELEMENT_SIZE;
+            let dest = new_ptr + ARRAY_HEADER_SIZE;
+            while (it != end) {
+                *dest = *it; // copy one Any
+                it += ARRAY_ELEMENT_SIZE;
+                dest += ARRAY_ELEMENT_SIZE;
+            }
+            free_mem_ptr = dest;
+            (*(ptr-4)) = I32_MIN | (new_ptr >> 1); // say that we already copied it.
+            return new_ptr;
+            */
+            {
+                let localidx_new_ptr = scratch.push_i32();
+                let localidx_len = scratch.push_i32();
+                let localidx_it = scratch.push_i32();
+                let localidx_end = scratch.push_i32();
+                let localidx_dest = scratch.push_i32();
+
+                // let new_ptr = free_mem_ptr + 4;
+                // let len = ptr->length;
+                // *free_mem_ptr = *(ptr-4);
+                // net wasm stack: [] -> []
+                {
+                    expr_builder.global_get(free_mem_ptr);
+                    expr_builder.local_get(localidx_param);
+                    expr_builder.i32_const(4);
+                    expr_builder.i32_sub();
+                    expr_builder.i32_load(wasmgen::MemArg::new4(0));
+                    expr_builder.i32_store(wasmgen::MemArg::new4(0));
+                    expr_builder.global_get(free_mem_ptr);
+                    expr_builder.i32_const(4);
+                    expr_builder.i32_add();
+                    expr_builder.local_set(localidx_new_ptr);
+                    expr_builder.local_get(localidx_param);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+                    expr_builder.local_set(localidx_len);
+                }
+
+                // write the new header, and set dest to the start of the new elements
+                // net wasm stack: [] -> []
+                {
+                    expr_builder.local_get(localidx_new_ptr);
+                    expr_builder.local_get(localidx_len);
+                    expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+                    expr_builder.local_get(localidx_new_ptr);
+                    expr_builder.local_get(localidx_len);
+                    expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_CAPACITY_OFFSET));
+                    expr_builder.local_get(localidx_new_ptr);
+                    expr_builder.local_get(localidx_new_ptr);
+                    expr_builder.i32_const(ARRAY_HEADER_SIZE as i32);
+                    expr_builder.i32_add();
+                    expr_builder.local_tee(localidx_dest);
+                    expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+                }
+
+                // let it = ptr->data;
+                // let end = it + len * ARRAY_ELEMENT_SIZE;
+                // net wasm stack: [] -> []
+                {
+                    expr_builder.local_get(localidx_param);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+                    expr_builder.local_tee(localidx_it);
+                    expr_builder.local_get(localidx_len);
+                    expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                    expr_builder.i32_mul();
+                    expr_builder.i32_add();
+                    expr_builder.local_set(localidx_end);
+                }
+
+                // copy the elements
+                // net wasm stack: [] -> []
+                {
+                    expr_builder.block(&[]);
+                    expr_builder.loop_(&[]);
+
+                    // if (it == end) break;
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.local_get(localidx_end);
+                    expr_builder.i32_eq();
+                    expr_builder.br_if(1);
+
+                    // *dest = *it;
+                    assert!(ARRAY_ELEMENT_SIZE % 4 == 0);
+                    for offset in (0..ARRAY_ELEMENT_SIZE).step_by(4) {
+                        expr_builder.local_get(localidx_dest);
+                        expr_builder.local_get(localidx_it);
+                        expr_builder.i32_load(wasmgen::MemArg::new4(offset));
+                        expr_builder.i32_store(wasmgen::MemArg::new4(offset));
+                    }
+
+                    // it += ARRAY_ELEMENT_SIZE;
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                    expr_builder.i32_add();
+                    expr_builder.local_set(localidx_it);
+
+                    // dest += ARRAY_ELEMENT_SIZE;
+                    expr_builder.local_get(localidx_dest);
+                    expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                    expr_builder.i32_add();
+                    expr_builder.local_set(localidx_dest);
+
+                    expr_builder.br(0);
+                    expr_builder.end();
+                    expr_builder.end();
+                }
+
+                // free_mem_ptr = dest;
+                // net wasm stack: [] -> []
+                {
+                    expr_builder.local_get(localidx_dest);
+                    expr_builder.global_set(free_mem_ptr);
+                }
+
+                // (*(ptr-4)) = I32_MIN | (new_ptr >> 1);
+                // net wasm stack: [] -> []
+                {
+                    expr_builder.local_get(localidx_param);
+                    expr_builder.i32_const(4);
+                    expr_builder.i32_sub();
+                    expr_builder.i32_const(i32::min_value());
+                    expr_builder.local_get(localidx_new_ptr);
+                    expr_builder.i32_const(1);
+                    expr_builder.i32_shr_u();
+                    expr_builder.i32_or();
+                    expr_builder.i32_store(wasmgen::MemArg::new4(0));
+                }
+
+                // return new_ptr;
+                expr_builder.local_get(localidx_new_ptr);
+                expr_builder.end();
+
+                scratch.pop_i32();
+                scratch.pop_i32();
+                scratch.pop_i32();
+                scratch.pop_i32();
+                scratch.pop_i32();
+            }
+        }
+        wasm_module.commit_func(func_idx, code_builder);
+        func_idx
+    };
+
     // Generate functions for copy_$i
     // Since copy_$i only depends on sizeof($i), we can combine all structs with the same size.
     std::iter::empty()
@@ -153,6 +332,8 @@ pub fn make_copy_funcs(
         .chain(std::iter::once(None)) // Boolean
         .chain(std::iter::once(Some(funcidx_copy_string))) // String
         .chain(std::iter::once(None)) // Func
+        .chain(std::iter::once(None)) // Null
+        .chain(std::iter::once(Some(funcidx_copy_array))) // Array
         .chain(
             struct_sizes
                 .iter()
@@ -168,6 +349,8 @@ pub fn make_copy_funcs(
                                     Box::new([wasmgen::ValType::I32]),
                                 );
                                 let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+                                wasm_module
+                                    .set_func_name(func_idx, format!("copy_struct_{}", size));
                                 let mut code_builder = wasmgen::CodeBuilder::new(functype);
                                 {
                                     let (locals_builder, expr_builder) = code_builder.split();
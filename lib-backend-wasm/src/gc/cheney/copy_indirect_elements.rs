@@ -23,7 +23,7 @@ pub fn make_copy_indirect_elements(
                     return make_func(f.idx, i32_wrap_i64((*(GC_TABLE_PTR_COPY_INDIRECT_OFFSET + *(f.closure-4)))(i64_extend_i32(f.closure))));
                 }
             }
-        } else if constexpr $i is not a ptr (i.e. not StructT or string) {
+        } else if constexpr $i is not a ptr (i.e. not StructT, string or array) {
             // NO-OP
         } else {
             if (ptr != -1 && (f is not String || ptr > heap_begin * WASM_PAGE_SIZE)) {
@@ -187,7 +187,7 @@ pub fn make_copy_indirect_elements(
                 } else {
                     return to_any_data(copy_$i(from_any_data(data)));
                 }
-            } else return -1;
+            } else return data;
             */
 
             let localidx_ptr = scratch.push_i32(); // from_any_data(data)
@@ -239,7 +239,8 @@ pub fn make_copy_indirect_elements(
             }
             expr_builder.else_();
             {
-                expr_builder.i64_const(((((-1) as i32) as u32) as u64) as i64); // weird conversion so that the high bits will be zero, not technically necessary since we are allowed to have arbitrary high bits, but it is nicer.
+                // not a heap pointer (i.e. -1 or a string from global data), so the data stays the same
+                expr_builder.local_get(localidx_param);
             }
             expr_builder.end();
 
@@ -259,14 +260,33 @@ pub fn make_copy_indirect_elements(
     );
 
     let no_op_funcidx: wasmgen::FuncIdx = make_no_op_function(wasm_module);
+    wasm_module.set_func_name(no_op_funcidx, "copy_indirect_no_op".to_string());
     let func_funcidx: wasmgen::FuncIdx =
         make_func_function(wasm_module, copy_indirect_table_offset, tableidx);
+    wasm_module.set_func_name(
+        func_funcidx,
+        format!("copy_indirect_{}", ir::VarType::Func.tag()),
+    );
     let string_funcidx: wasmgen::FuncIdx = make_struct_function(
         wasm_module,
         copy_funcs[ir::VarType::String.tag() as usize].unwrap(),
         heap_begin,
         true,
     );
+    wasm_module.set_func_name(
+        string_funcidx,
+        format!("copy_indirect_{}", ir::VarType::String.tag()),
+    );
+    let array_funcidx: wasmgen::FuncIdx = make_struct_function(
+        wasm_module,
+        copy_funcs[ir::VarType::Array.tag() as usize].unwrap(),
+        heap_begin,
+        false,
+    );
+    wasm_module.set_func_name(
+        array_funcidx,
+        format!("copy_indirect_{}", ir::VarType::Array.tag()),
+    );
 
     let copy_indirect_elements: Box<[wasmgen::FuncIdx]> = std::iter::empty()
         .chain(std::iter::once(no_op_funcidx)) // Unassigned
@@ -275,13 +295,20 @@ pub fn make_copy_indirect_elements(
         .chain(std::iter::once(no_op_funcidx)) // Boolean
         .chain(std::iter::once(string_funcidx)) // String
         .chain(std::iter::once(func_funcidx)) // Func
+        .chain(std::iter::once(no_op_funcidx)) // Null
+        .chain(std::iter::once(array_funcidx)) // Array
         .chain((0..num_structs).map(|n| {
-            make_struct_function(
+            let funcidx = make_struct_function(
                 wasm_module,
                 copy_funcs[ir::NUM_PRIMITIVE_TAG_TYPES + n].unwrap(),
                 heap_begin,
                 false,
-            )
+            );
+            wasm_module.set_func_name(
+                funcidx,
+                format!("copy_indirect_{}", ir::NUM_PRIMITIVE_TAG_TYPES + n),
+            );
+            funcidx
         }))
         .collect();
     assert!(copy_indirect_elements.len() == ir::NUM_PRIMITIVE_TAG_TYPES + num_structs);
@@ -70,6 +70,7 @@ pub fn make_do_cheney<'a>(
                     }
                     ir::VarType::Unassigned => {}
                     ir::VarType::Undefined => {}
+                    ir::VarType::Null => {}
                     ir::VarType::Number => {}
                     ir::VarType::Boolean => {}
                     ir::VarType::String => {
@@ -152,6 +153,18 @@ pub fn make_do_cheney<'a>(
                         scratch.pop_i32();
                         scratch.pop_i32();
                     }
+                    ir::VarType::Array => {
+                        // net wasm stack: [] -> []
+                        gen(
+                            expr_builder,
+                            scratch,
+                            wasm_globalidxs[0],
+                            tableidx,
+                            copy_funcs[ir::VarType::Array.tag() as usize].unwrap(),
+                            heap_begin,
+                            false,
+                        );
+                    }
                     ir::VarType::StructT { typeidx } => {
                         // net wasm stack: [] -> []
                         gen(
@@ -377,6 +390,7 @@ pub fn make_do_cheney<'a>(
         Box::new([wasmgen::ValType::I32]),
     );
     let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+    wasm_module.set_func_name(func_idx, "do_cheney".to_string());
     let mut code_builder = wasmgen::CodeBuilder::new(functype);
     {
         let (locals_builder, expr_builder) = code_builder.split();
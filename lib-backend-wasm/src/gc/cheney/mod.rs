@@ -1,8 +1,16 @@
+use super::encode_array_header_init;
+use super::encode_pointer_locals_init;
+use super::encode_roots_push;
+use super::encode_struct_fields_init;
+use super::filter_roots;
+use super::wasm_local_slice;
 use super::HeapManager;
 use super::WASM_PAGE_BITS;
 use super::WASM_PAGE_SIZE;
 use crate::global_var::GlobalVarManagerRef;
 use crate::var_conv::*;
+use crate::ARRAY_ELEMENT_SIZE;
+use crate::ARRAY_HEADER_SIZE;
 use wasmgen::Scratch;
 
 mod copy_children_elements;
@@ -10,7 +18,7 @@ mod copy_funcs;
 mod copy_indirect_elements;
 mod do_cheney;
 
-#[cfg(feature = "wasmtest")]
+#[cfg(any(test, feature = "wasmtest"))]
 pub mod wasmtest;
 
 /**
@@ -308,24 +316,6 @@ impl<'a, 'b, 'c> Cheney<'a, 'b, 'c> {
         }
     }
 
-    fn filter_roots(
-        local_types: &[ir::VarType],
-        local_map: &[usize],
-    ) -> Box<[(ir::VarType, usize)]> {
-        local_types
-            .iter()
-            .copied()
-            .zip(local_map.iter().copied())
-            .filter(|(ir_vartype, _)| match ir_vartype {
-                ir::VarType::Unassigned
-                | ir::VarType::Undefined
-                | ir::VarType::Number
-                | ir::VarType::Boolean => false,
-                _ => true,
-            })
-            .collect()
-    }
-
     // Helper function used to encode heap allocation.
     // `f` should be a function that has net wasm stack [] -> [i32(size)], it pushes the bytes required (including tag) on the stack.
     // net wasm stack: [] -> [i32(ptr)]
@@ -440,14 +430,6 @@ impl<'a, 'b, 'c> Cheney<'a, 'b, 'c> {
     }
 }
 
-fn wasm_local_slice<'a>(
-    ir_vartype: ir::VarType,
-    wasm_local_map_idx: usize,
-    wasm_local_map: &'a [wasmgen::LocalIdx],
-) -> &'a [wasmgen::LocalIdx] {
-    &wasm_local_map[wasm_local_map_idx..(wasm_local_map_idx + encode_vartype(ir_vartype).len())]
-}
-
 impl<'a, 'b, 'c> HeapManager for Cheney<'a, 'b, 'c> {
     // Returns the initial number of pages required by this heap HeapManager.
     fn initial_heap_size() -> u32 {
@@ -485,36 +467,15 @@ impl<'a, 'b, 'c> HeapManager for Cheney<'a, 'b, 'c> {
                 );
 
                 // Write Undefined to all Any fields in the struct
-                // and write nullptr (i.e. -1) to all String, Func::closure, StructT
+                // and write nullptr (i.e. -1) to all String, Array, Func::closure, StructT
                 // todo!: String should eventually be set to an empty string in the constant string pool.... on not?
                 // net wasm stack: [i32(ptr)] -> [i32(ptr)]
-                {
-                    let localidx_ptr: wasmgen::LocalIdx = scratch.push_i32();
-                    expr_builder.local_tee(localidx_ptr);
-                    self.struct_types[typeidx]
-                        .iter()
-                        .zip(self.struct_field_byte_offsets[typeidx].iter())
-                        .for_each(|(ir_vartype, byte_offset)| match ir_vartype {
-                            ir::VarType::Any => {
-                                expr_builder.local_get(localidx_ptr);
-                                expr_builder.i32_const(ir::VarType::Unassigned.tag());
-                                expr_builder.i32_store(wasmgen::MemArg::new4(*byte_offset));
-                            }
-                            ir::VarType::String | ir::VarType::StructT { typeidx: _ } => {
-                                expr_builder.local_get(localidx_ptr);
-                                expr_builder.i32_const(-1);
-                                expr_builder.i32_store(wasmgen::MemArg::new4(*byte_offset));
-                            }
-                            ir::VarType::Func => {
-                                expr_builder.local_get(localidx_ptr);
-                                expr_builder.i32_const(-1);
-                                expr_builder.i32_store(wasmgen::MemArg::new4(*byte_offset + 4));
-                                // Note: "+4" above to access the closure
-                            }
-                            _ => {}
-                        });
-                    scratch.pop_i32();
-                }
+                encode_struct_fields_init(
+                    &self.struct_types[typeidx],
+                    &self.struct_field_byte_offsets[typeidx],
+                    scratch,
+                    expr_builder,
+                );
             }
             _ => panic!("incorrect VarType, expected StructT"),
         }
@@ -522,6 +483,7 @@ impl<'a, 'b, 'c> HeapManager for Cheney<'a, 'b, 'c> {
 
     // Encodes instructions to get a chunk of memory for an string/array of unknown size.  See `encode_fixed_allocation` for more detauls.
     // The size need not be a multiple of 4.
+    // For arrays, the operand is the capacity (number of elements) instead of the number of bytes.
     // net wasm stack: [i32(num_bytes)] -> [i32(ptr)]
     fn encode_dynamic_allocation(
         &self,
@@ -577,7 +539,44 @@ impl<'a, 'b, 'c> HeapManager for Cheney<'a, 'b, 'c> {
                 scratch.pop_i32();
                 scratch.pop_i32();
             }
-            _ => panic!("incorrect VarType, expected String"),
+            ir::VarType::Array => {
+                let localidx_capacity: wasmgen::LocalIdx = scratch.push_i32();
+
+                {
+                    expr_builder.local_set(localidx_capacity);
+                }
+
+                // Algorithm: mem_size = 4 + ARRAY_HEADER_SIZE + capacity * ARRAY_ELEMENT_SIZE
+                // net wasm stack: [] -> [i32(ptr)]
+                self.encode_allocation(
+                    |expr_builder| {
+                        // net wasm stack: [] -> [i32(size)]
+                        expr_builder.local_get(localidx_capacity);
+                        expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                        expr_builder.i32_mul();
+                        expr_builder.i32_const((4 + ARRAY_HEADER_SIZE) as i32);
+                        expr_builder.i32_add();
+                    },
+                    ir_vartype.tag(),
+                    local_types,
+                    local_map,
+                    wasm_local_map,
+                    scratch,
+                    expr_builder,
+                );
+
+                // write the array header
+                // net wasm stack: [i32(ptr)] -> [i32(ptr)]
+                {
+                    let localidx_ret: wasmgen::LocalIdx = scratch.push_i32();
+                    expr_builder.local_tee(localidx_ret);
+                    encode_array_header_init(localidx_ret, localidx_capacity, expr_builder);
+                    scratch.pop_i32();
+                }
+
+                scratch.pop_i32();
+            }
+            _ => panic!("incorrect VarType, expected String or Array"),
         }
     }
 
@@ -596,39 +595,16 @@ impl<'a, 'b, 'c> HeapManager for Cheney<'a, 'b, 'c> {
         scratch: &mut Scratch,
         expr_builder: &mut wasmgen::ExprBuilder,
     ) -> Self::RootsStackHandle {
-        let filtered_roots: Box<[(ir::VarType, usize)]> =
-            Self::filter_roots(local_types, local_map);
+        let filtered_roots: Box<[(ir::VarType, usize)]> = filter_roots(local_types, local_map);
 
-        // if there are no roots to add, then we don't need to load the gc_roots_stack_ptr.
         // net wasm stack: [] -> []
-        if !filtered_roots.is_empty() {
-            let localidx_gc_roots_stack_ptr = scratch.push_i32();
-
-            // net wasm stack: [] -> [gc_roots_stack_ptr(i32)]
-            expr_builder.global_get(self.gc_roots_stack_ptr);
-
-            for (ir_vartype, index) in filtered_roots.into_iter().copied() {
-                // net wasm stack: [gc_roots_stack_ptr(i32)] -> []
-                expr_builder.local_tee(localidx_gc_roots_stack_ptr);
-                encode_load_local(
-                    wasm_local_slice(ir_vartype, index, wasm_local_map),
-                    ir_vartype,
-                    ir_vartype,
-                    expr_builder,
-                );
-                encode_store_memory(0, ir::VarType::Any, ir_vartype, scratch, expr_builder);
-
-                // net wasm stack: [] -> [gc_roots_stack_ptr(i32)]
-                expr_builder.local_get(localidx_gc_roots_stack_ptr);
-                expr_builder.i32_const(12);
-                expr_builder.i32_add();
-            }
-
-            // net wasm stack: [gc_roots_stack_ptr(i32)] -> []
-            expr_builder.global_set(self.gc_roots_stack_ptr);
-
-            scratch.pop_i32();
-        }
+        encode_roots_push(
+            self.gc_roots_stack_ptr,
+            &filtered_roots,
+            wasm_local_map,
+            scratch,
+            expr_builder,
+        );
     }
 
     // Encodes instructions to pop local variables from gc_roots stack.
@@ -643,8 +619,7 @@ impl<'a, 'b, 'c> HeapManager for Cheney<'a, 'b, 'c> {
         scratch: &mut Scratch,
         expr_builder: &mut wasmgen::ExprBuilder,
     ) {
-        let filtered_roots: Box<[(ir::VarType, usize)]> =
-            Self::filter_roots(local_types, local_map);
+        let filtered_roots: Box<[(ir::VarType, usize)]> = filter_roots(local_types, local_map);
 
         if !filtered_roots.is_empty() {
             let localidx_gc_roots_stack_ptr = scratch.push_i32();
@@ -728,25 +703,6 @@ impl<'a, 'b, 'c> HeapManager for Cheney<'a, 'b, 'c> {
         _scratch: &mut Scratch,
         expr_builder: &mut wasmgen::ExprBuilder,
     ) {
-        for (ir_vartype, wasm_local_map_index) in
-            local_types.iter().copied().zip(local_map.iter().copied())
-        {
-            match ir_vartype {
-                ir::VarType::String | ir::VarType::StructT { typeidx: _ } => {
-                    expr_builder.i32_const(-1);
-                    expr_builder.local_set(wasm_local_map[wasm_local_map_index]);
-                }
-                ir::VarType::Func => {
-                    expr_builder.i32_const(-1);
-                    expr_builder.local_set(wasm_local_map[wasm_local_map_index + 1]);
-                    // Note: "+1" above to access the closure
-                }
-                ir::VarType::Any => {
-                    expr_builder.i32_const(ir::VarType::Unassigned.tag());
-                    expr_builder.local_set(wasm_local_map[wasm_local_map_index]);
-                }
-                _ => {}
-            }
-        }
+        encode_pointer_locals_init(local_types, local_map, wasm_local_map, expr_builder);
     }
 }
@@ -1,4 +1,5 @@
 use super::*;
+use crate::global_var::GlobalVarManager;
 use wasm_test_harness::*;
 
 pub fn wasmtest<C: TestContext>(c: &mut C) {
@@ -20,6 +21,7 @@ pub fn wasmtest<C: TestContext>(c: &mut C) {
         let struct_field_byte_offsets: [Box<[u32]>; 1] = [Box::new([0, 12, 24])];
         let struct_sizes: [u32; 1] = [28];
         let mem = wasm_module.add_unbounded_memory(MEM_INITIAL_HEAP_SIZE);
+        let global_var_manager = GlobalVarManager::default(); // no globals
         let cheney = Cheney::new(
             &struct_types,
             &struct_field_byte_offsets,
@@ -27,6 +29,7 @@ pub fn wasmtest<C: TestContext>(c: &mut C) {
             mem,
             0,
             MEM_INITIAL_HEAP_SIZE,
+            global_var_manager.deref(),
             error_func,
             wasm_module,
         );
@@ -34,7 +37,7 @@ pub fn wasmtest<C: TestContext>(c: &mut C) {
         let (locals_builder, expr_builder) = code_builder.split();
         let mut scratch = Scratch::new(locals_builder);
 
-        for i in 0..4 {
+        for _ in 0..4 {
             // add 32768 structs, ensuring that they are at proper positions
             // net wasm stack: [] -> []
             {
@@ -154,6 +157,7 @@ pub fn wasmtest<C: TestContext>(c: &mut C) {
         let struct_field_byte_offsets: [Box<[u32]>; 1] = [Box::new([0, 12, 24])];
         let struct_sizes: [u32; 1] = [28];
         let mem = wasm_module.add_unbounded_memory(MEM_INITIAL_HEAP_SIZE);
+        let global_var_manager = GlobalVarManager::default(); // no globals
         let cheney = Cheney::new(
             &struct_types,
             &struct_field_byte_offsets,
@@ -161,6 +165,7 @@ pub fn wasmtest<C: TestContext>(c: &mut C) {
             mem,
             0,
             MEM_INITIAL_HEAP_SIZE,
+            global_var_manager.deref(),
             error_func,
             wasm_module,
         );
@@ -1,5 +1,8 @@
+use super::encode_array_header_init;
 use super::WASM_PAGE_BITS;
 use super::WASM_PAGE_SIZE;
+use crate::ARRAY_ELEMENT_SIZE;
+use crate::ARRAY_HEADER_SIZE;
 use wasmgen::Scratch;
 
 /**
@@ -38,17 +41,22 @@ impl<'a, 'b, 'c> Leaky<'a, 'b, 'c> {
         wasm_module: &mut wasmgen::WasmModule,
     ) -> Self {
         assert!(heap_begin + MEM_INITIAL_HEAP_SIZE == heap_initial_end);
+        let free_mem_ptr: wasmgen::GlobalIdx =
+            wasm_module.add_i32_global(wasmgen::Mut::Var, (heap_begin * WASM_PAGE_SIZE) as i32);
+        let end_mem_ptr: wasmgen::GlobalIdx = wasm_module.add_i32_global(
+            wasmgen::Mut::Var,
+            (heap_initial_end * WASM_PAGE_SIZE) as i32,
+        );
+        // export the globals so that the driver knows how to restore them (for REPL resumption)
+        wasm_module.export_global(free_mem_ptr, "free_mem_ptr".to_string());
+        wasm_module.export_global(end_mem_ptr, "end_mem_ptr".to_string());
         Leaky {
             struct_types: struct_types,
             struct_field_byte_offsets: struct_field_byte_offsets,
             struct_sizes: struct_sizes,
             memidx: memidx,
-            free_mem_ptr: wasm_module
-                .add_i32_global(wasmgen::Mut::Var, (heap_begin * WASM_PAGE_SIZE) as i32),
-            end_mem_ptr: wasm_module.add_i32_global(
-                wasmgen::Mut::Var,
-                (heap_initial_end * WASM_PAGE_SIZE) as i32,
-            ),
+            free_mem_ptr: free_mem_ptr,
+            end_mem_ptr: end_mem_ptr,
             heap_begin: heap_begin,
             error_func: error_func,
         }
@@ -224,6 +232,7 @@ impl<'a, 'b, 'c> super::HeapManager for Leaky<'a, 'b, 'c> {
 
     // Encodes instructions to get a chunk of memory for an string/array of unknown size.  See `encode_fixed_allocation` for more detauls.
     // The size need not be a multiple of 4.
+    // For arrays, the operand is the capacity (number of elements) instead of the number of bytes.
     // net wasm stack: [i32(num_bytes)] -> [i32(ptr)]
     fn encode_dynamic_allocation(
         &self,
@@ -277,7 +286,39 @@ impl<'a, 'b, 'c> super::HeapManager for Leaky<'a, 'b, 'c> {
                 scratch.pop_i32();
                 scratch.pop_i32();
             }
-            _ => panic!("incorrect VarType, expected String"),
+            ir::VarType::Array => {
+                let localidx_capacity: wasmgen::LocalIdx = scratch.push_i32();
+
+                {
+                    expr_builder.local_set(localidx_capacity);
+                }
+
+                // net wasm stack: [] -> [i32(ptr)]
+                self.encode_allocation(
+                    |expr_builder| {
+                        // net wasm stack: [] -> [i32(size)]
+                        expr_builder.local_get(localidx_capacity);
+                        expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                        expr_builder.i32_mul();
+                        expr_builder.i32_const(ARRAY_HEADER_SIZE as i32);
+                        expr_builder.i32_add();
+                    },
+                    scratch,
+                    expr_builder,
+                );
+
+                // write the array header
+                // net wasm stack: [i32(ptr)] -> [i32(ptr)]
+                {
+                    let localidx_ret: wasmgen::LocalIdx = scratch.push_i32();
+                    expr_builder.local_tee(localidx_ret);
+                    encode_array_header_init(localidx_ret, localidx_capacity, expr_builder);
+                    scratch.pop_i32();
+                }
+
+                scratch.pop_i32();
+            }
+            _ => panic!("incorrect VarType, expected String or Array"),
         }
     }
 
@@ -0,0 +1,870 @@
+use super::mark_funcs::MarkFuncs;
+use super::Globals;
+use super::BLOCK_HEADER_SIZE;
+use super::BLOCK_SIZE_OFFSET;
+use super::BLOCK_TAG_OFFSET;
+use super::FREE_BLOCK_FLAG;
+use super::MARK_BIT;
+use super::MIN_FREE_BLOCK_SIZE;
+use crate::global_var::GlobalVarManagerRef;
+use crate::WASM_PAGE_BITS;
+use crate::WASM_PAGE_SIZE;
+use wasmgen::Scratch;
+
+/*
+// Marks everything reachable from the roots, then sweeps the heap to rebuild the free list.
+// Returns the number of bytes that are still in use.
+fn collect() -> i32 {
+    mark_stack_ptr = gc_roots_stack_ptr;
+    mark_stack_end = memory_size() << WASM_PAGE_BITS;
+    mark_stack_overflow = 0;
+
+    for each global g {
+        mark the object referenced by g (if any);
+    }
+    let it = gc_roots_stack_base_ptr;
+    while (it != gc_roots_stack_ptr) {
+        mark_any(it->tag, it->data);
+        it += 12; // 12 is the size of Any
+    }
+    drain_mark_stack();
+
+    // some marked objects might not have been traced, because the mark stack was full
+    while (mark_stack_overflow) {
+        mark_stack_overflow = 0;
+        let it = heap_begin * WASM_PAGE_SIZE;
+        while (it != free_mem_ptr) {
+            if (!(it->size & FREE_BLOCK_FLAG) && (it->tag & MARK_BIT)) {
+                trace(it + BLOCK_HEADER_SIZE);
+                drain_mark_stack();
+            }
+            it += it->size & ~FREE_BLOCK_FLAG;
+        }
+    }
+
+    // sweep (consecutive dead blocks are merged into one free block)
+    free_list_ptr = 0;
+    let live = 0;
+    let run = 0; // start of the current run of dead blocks, or zero if the previous block is alive
+    let it = heap_begin * WASM_PAGE_SIZE;
+    while (it != free_mem_ptr) {
+        let size = it->size & ~FREE_BLOCK_FLAG;
+        if (!(it->size & FREE_BLOCK_FLAG) && (it->tag & MARK_BIT)) {
+            it->tag &= ~MARK_BIT;
+            live += size;
+            if (run) {
+                run->size = (it - run) | FREE_BLOCK_FLAG;
+                run->tag = free_list_ptr; // i.e. the `next` pointer
+                free_list_ptr = run;
+                run = 0;
+            }
+        } else {
+            if (!run) run = it;
+        }
+        it += size;
+    }
+    // a run at the end of the heap is given back to the free space
+    if (run) free_mem_ptr = run;
+    return live;
+}
+// this function will be inlined - it doesn't really exist in the wasm file
+fn drain_mark_stack() {
+    while (mark_stack_ptr != gc_roots_stack_ptr) {
+        mark_stack_ptr -= 4;
+        trace(*mark_stack_ptr);
+    }
+}
+// Finds a free block that can hold `size` bytes (first fit), and removes it from the free list.
+// If the block is larger, the remaining part stays in the free list.
+// Blocks smaller than MIN_FREE_BLOCK_SIZE that are passed over are removed from the free list too (they are reclaimed by the next sweep),
+// otherwise the small leftovers of splitting accumulate at the front of the free list, and every allocation has to walk past all of them.
+// Writes the size of the block, and returns the block (or zero if there is no suitable block).
+fn find_free(size: i32) -> i32 {
+    let prev = 0;
+    let it = free_list_ptr;
+    while (it) {
+        let block_size = it->size & ~FREE_BLOCK_FLAG;
+        if (block_size >= size + BLOCK_HEADER_SIZE) {
+            // split the block
+            let rest = it + size;
+            rest->size = (block_size - size) | FREE_BLOCK_FLAG;
+            rest->tag = it->tag;
+            if (prev) prev->tag = rest; else free_list_ptr = rest;
+            it->size = size;
+            return it;
+        }
+        if (block_size >= size) {
+            // the remaining part is too small to be a free block, so we give the whole block
+            if (prev) prev->tag = it->tag; else free_list_ptr = it->tag;
+            it->size = block_size;
+            return it;
+        }
+        if (block_size < MIN_FREE_BLOCK_SIZE) {
+            if (prev) prev->tag = it->tag; else free_list_ptr = it->tag;
+        } else {
+            prev = it;
+        }
+        it = it->tag;
+    }
+    return 0;
+}
+// Grows the heap by at least `bytes`, moving the gc_roots stack to the new end of the memory.
+// Returns nonzero if successful.
+fn grow(bytes: i32) -> i32 {
+    let num_pages = (bytes + (WASM_PAGE_SIZE - 1)) >> WASM_PAGE_BITS;
+    if (memory_grow(num_pages) == -1) return 0;
+    let delta = num_pages << WASM_PAGE_BITS;
+    // copy the gc_roots stack from the top, because the new location might overlap with the old one
+    let it = gc_roots_stack_ptr;
+    while (it != gc_roots_stack_base_ptr) {
+        it -= 4;
+        *(it + delta) = *it;
+    }
+    gc_roots_stack_base_ptr += delta;
+    gc_roots_stack_ptr += delta;
+    return 1;
+}
+// Called when there is not enough free space for an allocation.
+// Returns a block of at least `size` bytes, with the size already written to its header.
+fn alloc_slow(size: i32) -> i32 {
+    let ret = find_free(size);
+    if (ret) return ret;
+    let live = collect();
+    // if the heap is still more than half full, get twice the existing amount of heap memory if possible
+    let heap_size = gc_roots_stack_base_ptr - heap_begin * WASM_PAGE_SIZE;
+    if (live > (heap_size >> 1)) grow(heap_size);
+    if (gc_roots_stack_base_ptr - free_mem_ptr < size) {
+        ret = find_free(size);
+        if (ret) return ret;
+        let heap_size = gc_roots_stack_base_ptr - heap_begin * WASM_PAGE_SIZE;
+        if (!grow(max(size, heap_size))) abort();
+    }
+    ret = free_mem_ptr;
+    free_mem_ptr += size;
+    ret->size = size;
+    return ret;
+}
+*/
+pub fn make_alloc_slow<'a>(
+    wasm_module: &mut wasmgen::WasmModule,
+    mark_funcs: MarkFuncs,
+    memidx: wasmgen::MemIdx,
+    globals: Globals,
+    global_var_manager: GlobalVarManagerRef<'a>,
+    heap_begin: u32,
+    error_func: wasmgen::FuncIdx,
+) -> wasmgen::FuncIdx {
+    let collect_funcidx: wasmgen::FuncIdx = make_collect(
+        wasm_module,
+        mark_funcs,
+        memidx,
+        globals,
+        global_var_manager,
+        heap_begin,
+    );
+    let find_free_funcidx: wasmgen::FuncIdx = make_find_free(wasm_module, globals);
+    let grow_funcidx: wasmgen::FuncIdx = make_grow(wasm_module, memidx, globals);
+
+    let functype = wasmgen::FuncType::new(
+        Box::new([wasmgen::ValType::I32]),
+        Box::new([wasmgen::ValType::I32]),
+    );
+    let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+    wasm_module.set_func_name(func_idx, "alloc_slow".to_string());
+    let mut code_builder = wasmgen::CodeBuilder::new(functype);
+    {
+        let (locals_builder, expr_builder) = code_builder.split();
+        let mut scratch = Scratch::new(locals_builder);
+        let localidx_size = wasmgen::LocalIdx { idx: 0 };
+        let localidx_ret = scratch.push_i32();
+        let localidx_heap_size = scratch.push_i32();
+
+        // let ret = find_free(size);
+        // if (ret) return ret;
+        // net wasm stack: [] -> []
+        expr_builder.local_get(localidx_size);
+        expr_builder.call(find_free_funcidx);
+        expr_builder.local_tee(localidx_ret);
+        expr_builder.if_(&[]);
+        expr_builder.local_get(localidx_ret);
+        expr_builder.return_();
+        expr_builder.end();
+
+        // let live = collect();
+        // let heap_size = gc_roots_stack_base_ptr - heap_begin * WASM_PAGE_SIZE;
+        // if (live > (heap_size >> 1)) grow(heap_size);
+        // net wasm stack: [] -> []
+        expr_builder.call(collect_funcidx);
+        expr_builder.global_get(globals.gc_roots_stack_base_ptr);
+        expr_builder.i32_const((heap_begin * WASM_PAGE_SIZE) as i32);
+        expr_builder.i32_sub();
+        expr_builder.local_tee(localidx_heap_size);
+        expr_builder.i32_const(1);
+        expr_builder.i32_shr_u();
+        expr_builder.i32_gt_u();
+        expr_builder.if_(&[]);
+        expr_builder.local_get(localidx_heap_size);
+        expr_builder.call(grow_funcidx);
+        expr_builder.drop(); // it is okay if we can't grow the memory here, since there might still be enough free space
+        expr_builder.end();
+
+        // if (gc_roots_stack_base_ptr - free_mem_ptr < size)
+        // net wasm stack: [] -> []
+        expr_builder.global_get(globals.gc_roots_stack_base_ptr);
+        expr_builder.global_get(globals.free_mem_ptr);
+        expr_builder.i32_sub();
+        expr_builder.local_get(localidx_size);
+        expr_builder.i32_lt_u();
+        expr_builder.if_(&[]);
+        {
+            // ret = find_free(size);
+            // if (ret) return ret;
+            // net wasm stack: [] -> []
+            expr_builder.local_get(localidx_size);
+            expr_builder.call(find_free_funcidx);
+            expr_builder.local_tee(localidx_ret);
+            expr_builder.if_(&[]);
+            expr_builder.local_get(localidx_ret);
+            expr_builder.return_();
+            expr_builder.end();
+
+            // let heap_size = gc_roots_stack_base_ptr - heap_begin * WASM_PAGE_SIZE;
+            // if (!grow(max(size, heap_size))) abort();
+            // net wasm stack: [] -> []
+            expr_builder.global_get(globals.gc_roots_stack_base_ptr);
+            expr_builder.i32_const((heap_begin * WASM_PAGE_SIZE) as i32);
+            expr_builder.i32_sub();
+            expr_builder.local_tee(localidx_heap_size);
+            expr_builder.local_get(localidx_size);
+            expr_builder.local_get(localidx_heap_size);
+            expr_builder.local_get(localidx_size);
+            expr_builder.i32_lt_u();
+            expr_builder.select();
+            expr_builder.call(grow_funcidx);
+            expr_builder.i32_eqz();
+            expr_builder.if_(&[]);
+            {
+                // out of memory... raise an error
+                expr_builder.i32_const(ir::error::ERROR_CODE_OUT_OF_MEMORY as i32);
+                expr_builder.i32_const(0);
+                expr_builder.i32_const(0);
+                expr_builder.i32_const(0);
+                expr_builder.i32_const(0);
+                expr_builder.i32_const(0);
+                expr_builder.i32_const(0);
+                expr_builder.call(error_func);
+                expr_builder.unreachable();
+            }
+            expr_builder.end();
+        }
+        expr_builder.end();
+
+        // ret = free_mem_ptr;
+        // free_mem_ptr += size;
+        // ret->size = size;
+        // return ret;
+        // net wasm stack: [] -> [ret(i32)]
+        expr_builder.global_get(globals.free_mem_ptr);
+        expr_builder.local_tee(localidx_ret);
+        expr_builder.local_get(localidx_size);
+        expr_builder.i32_add();
+        expr_builder.global_set(globals.free_mem_ptr);
+        expr_builder.local_get(localidx_ret);
+        expr_builder.local_get(localidx_size);
+        expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+        expr_builder.local_get(localidx_ret);
+
+        scratch.pop_i32();
+        scratch.pop_i32();
+
+        expr_builder.end();
+    }
+    wasm_module.commit_func(func_idx, code_builder);
+    func_idx
+}
+
+fn make_collect<'a>(
+    wasm_module: &mut wasmgen::WasmModule,
+    mark_funcs: MarkFuncs,
+    memidx: wasmgen::MemIdx,
+    globals: Globals,
+    global_var_manager: GlobalVarManagerRef<'a>,
+    heap_begin: u32,
+) -> wasmgen::FuncIdx {
+    let functype = wasmgen::FuncType::new(Box::new([]), Box::new([wasmgen::ValType::I32]));
+    let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+    wasm_module.set_func_name(func_idx, "collect".to_string());
+    let mut code_builder = wasmgen::CodeBuilder::new(functype);
+    {
+        let (locals_builder, expr_builder) = code_builder.split();
+        let mut scratch = Scratch::new(locals_builder);
+        let localidx_it = scratch.push_i32();
+
+        // mark_stack_ptr = gc_roots_stack_ptr;
+        // mark_stack_end = memory_size() << WASM_PAGE_BITS;
+        // mark_stack_overflow = 0;
+        // net wasm stack: [] -> []
+        expr_builder.global_get(globals.gc_roots_stack_ptr);
+        expr_builder.global_set(globals.mark_stack_ptr);
+        expr_builder.memory_size(memidx);
+        expr_builder.i32_const(WASM_PAGE_BITS as i32);
+        expr_builder.i32_shl();
+        expr_builder.global_set(globals.mark_stack_end);
+        expr_builder.i32_const(0);
+        expr_builder.global_set(globals.mark_stack_overflow);
+
+        // for each global g {
+        //     mark the object referenced by g (if any);
+        // }
+        // net wasm stack: [] -> []
+        for (ir_vartype, wasm_globalidxs) in global_var_manager {
+            match ir_vartype {
+                ir::VarType::Any => {
+                    expr_builder.global_get(wasm_globalidxs[0]); // the `tag` of the Any
+                    expr_builder.global_get(wasm_globalidxs[1]); // the `data` of the Any
+                    expr_builder.call(mark_funcs.mark_any);
+                }
+                ir::VarType::Func => {
+                    expr_builder.global_get(wasm_globalidxs[1]); // the `closure` of the Func
+                    expr_builder.call(mark_funcs.mark);
+                }
+                ir::VarType::String | ir::VarType::Array | ir::VarType::StructT { typeidx: _ } => {
+                    expr_builder.global_get(wasm_globalidxs[0]);
+                    expr_builder.call(mark_funcs.mark);
+                }
+                ir::VarType::Unassigned
+                | ir::VarType::Undefined
+                | ir::VarType::Null
+                | ir::VarType::Number
+                | ir::VarType::Boolean => {}
+            }
+        }
+
+        // let it = gc_roots_stack_base_ptr;
+        // while (it != gc_roots_stack_ptr) {
+        //     mark_any(it->tag, it->data);
+        //     it += 12;
+        // }
+        // net wasm stack: [] -> []
+        {
+            expr_builder.global_get(globals.gc_roots_stack_base_ptr);
+            expr_builder.local_set(localidx_it);
+            expr_builder.block(&[]);
+            expr_builder.loop_(&[]);
+            {
+                expr_builder.local_get(localidx_it);
+                expr_builder.global_get(globals.gc_roots_stack_ptr);
+                expr_builder.i32_eq();
+                expr_builder.br_if(1);
+
+                expr_builder.local_get(localidx_it);
+                expr_builder.i32_load(wasmgen::MemArg::new4(0)); // the `tag` of the Any is at offset 0
+                expr_builder.local_get(localidx_it);
+                expr_builder.i64_load(wasmgen::MemArg::new4(4)); // the `data` of the Any is at offset 4
+                expr_builder.call(mark_funcs.mark_any);
+
+                expr_builder.local_get(localidx_it);
+                expr_builder.i32_const(12);
+                expr_builder.i32_add();
+                expr_builder.local_set(localidx_it);
+
+                expr_builder.br(0);
+            }
+            expr_builder.end();
+            expr_builder.end();
+        }
+
+        // net wasm stack: [] -> []
+        encode_drain_mark_stack(mark_funcs, globals, expr_builder);
+
+        // while (mark_stack_overflow) { ... }
+        // net wasm stack: [] -> []
+        {
+            expr_builder.block(&[]);
+            expr_builder.loop_(&[]);
+            {
+                expr_builder.global_get(globals.mark_stack_overflow);
+                expr_builder.i32_eqz();
+                expr_builder.br_if(1);
+
+                expr_builder.i32_const(0);
+                expr_builder.global_set(globals.mark_stack_overflow);
+
+                expr_builder.i32_const((heap_begin * WASM_PAGE_SIZE) as i32);
+                expr_builder.local_set(localidx_it);
+                expr_builder.block(&[]);
+                expr_builder.loop_(&[]);
+                {
+                    // if (it == free_mem_ptr) break;
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.global_get(globals.free_mem_ptr);
+                    expr_builder.i32_eq();
+                    expr_builder.br_if(1);
+
+                    // if (!(it->size & FREE_BLOCK_FLAG) && (it->tag & MARK_BIT)) { ... }
+                    encode_is_marked_block(localidx_it, expr_builder);
+                    expr_builder.if_(&[]);
+                    {
+                        expr_builder.local_get(localidx_it);
+                        expr_builder.i32_const(BLOCK_HEADER_SIZE as i32);
+                        expr_builder.i32_add();
+                        expr_builder.call(mark_funcs.trace);
+                        encode_drain_mark_stack(mark_funcs, globals, expr_builder);
+                    }
+                    expr_builder.end();
+
+                    // it += it->size & ~FREE_BLOCK_FLAG;
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+                    expr_builder.i32_const(!FREE_BLOCK_FLAG);
+                    expr_builder.i32_and();
+                    expr_builder.i32_add();
+                    expr_builder.local_set(localidx_it);
+
+                    expr_builder.br(0);
+                }
+                expr_builder.end();
+                expr_builder.end();
+
+                expr_builder.br(0);
+            }
+            expr_builder.end();
+            expr_builder.end();
+        }
+
+        // sweep
+        // net wasm stack: [] -> [live(i32)]
+        {
+            let localidx_live = scratch.push_i32();
+            let localidx_run = scratch.push_i32();
+            let localidx_size = scratch.push_i32();
+
+            // free_list_ptr = 0;
+            // let live = 0;
+            // let run = 0;
+            // let it = heap_begin * WASM_PAGE_SIZE;
+            expr_builder.i32_const(0);
+            expr_builder.global_set(globals.free_list_ptr);
+            expr_builder.i32_const(0);
+            expr_builder.local_set(localidx_live);
+            expr_builder.i32_const(0);
+            expr_builder.local_set(localidx_run);
+            expr_builder.i32_const((heap_begin * WASM_PAGE_SIZE) as i32);
+            expr_builder.local_set(localidx_it);
+
+            expr_builder.block(&[]);
+            expr_builder.loop_(&[]);
+            {
+                // if (it == free_mem_ptr) break;
+                expr_builder.local_get(localidx_it);
+                expr_builder.global_get(globals.free_mem_ptr);
+                expr_builder.i32_eq();
+                expr_builder.br_if(1);
+
+                // let size = it->size & ~FREE_BLOCK_FLAG;
+                expr_builder.local_get(localidx_it);
+                expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+                expr_builder.i32_const(!FREE_BLOCK_FLAG);
+                expr_builder.i32_and();
+                expr_builder.local_set(localidx_size);
+
+                encode_is_marked_block(localidx_it, expr_builder);
+                expr_builder.if_(&[]);
+                {
+                    // it->tag &= ~MARK_BIT;
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+                    expr_builder.i32_const(!MARK_BIT);
+                    expr_builder.i32_and();
+                    expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+
+                    // live += size;
+                    expr_builder.local_get(localidx_live);
+                    expr_builder.local_get(localidx_size);
+                    expr_builder.i32_add();
+                    expr_builder.local_set(localidx_live);
+
+                    // if (run) { ... }
+                    expr_builder.local_get(localidx_run);
+                    expr_builder.if_(&[]);
+                    {
+                        // run->size = (it - run) | FREE_BLOCK_FLAG;
+                        expr_builder.local_get(localidx_run);
+                        expr_builder.local_get(localidx_it);
+                        expr_builder.local_get(localidx_run);
+                        expr_builder.i32_sub();
+                        expr_builder.i32_const(FREE_BLOCK_FLAG);
+                        expr_builder.i32_or();
+                        expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+
+                        // run->tag = free_list_ptr;
+                        expr_builder.local_get(localidx_run);
+                        expr_builder.global_get(globals.free_list_ptr);
+                        expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+
+                        // free_list_ptr = run;
+                        expr_builder.local_get(localidx_run);
+                        expr_builder.global_set(globals.free_list_ptr);
+
+                        // run = 0;
+                        expr_builder.i32_const(0);
+                        expr_builder.local_set(localidx_run);
+                    }
+                    expr_builder.end();
+                }
+                expr_builder.else_();
+                {
+                    // if (!run) run = it;
+                    expr_builder.local_get(localidx_run);
+                    expr_builder.i32_eqz();
+                    expr_builder.if_(&[]);
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.local_set(localidx_run);
+                    expr_builder.end();
+                }
+                expr_builder.end();
+
+                // it += size;
+                expr_builder.local_get(localidx_it);
+                expr_builder.local_get(localidx_size);
+                expr_builder.i32_add();
+                expr_builder.local_set(localidx_it);
+
+                expr_builder.br(0);
+            }
+            expr_builder.end();
+            expr_builder.end();
+
+            // if (run) free_mem_ptr = run;
+            expr_builder.local_get(localidx_run);
+            expr_builder.if_(&[]);
+            expr_builder.local_get(localidx_run);
+            expr_builder.global_set(globals.free_mem_ptr);
+            expr_builder.end();
+
+            // return live;
+            expr_builder.local_get(localidx_live);
+
+            scratch.pop_i32();
+            scratch.pop_i32();
+            scratch.pop_i32();
+        }
+
+        scratch.pop_i32();
+
+        expr_builder.end();
+    }
+    wasm_module.commit_func(func_idx, code_builder);
+    func_idx
+}
+
+// Checks if the block at `it` is a marked object
+// net wasm stack: [] -> [cond(i32)]
+fn encode_is_marked_block(localidx_it: wasmgen::LocalIdx, expr_builder: &mut wasmgen::ExprBuilder) {
+    expr_builder.local_get(localidx_it);
+    expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+    expr_builder.i32_const(FREE_BLOCK_FLAG);
+    expr_builder.i32_and();
+    expr_builder.i32_eqz();
+    expr_builder.local_get(localidx_it);
+    expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+    expr_builder.i32_const(MARK_BIT);
+    expr_builder.i32_and();
+    expr_builder.i32_const(0);
+    expr_builder.i32_ne();
+    expr_builder.i32_and();
+}
+
+// net wasm stack: [] -> []
+fn encode_drain_mark_stack(
+    mark_funcs: MarkFuncs,
+    globals: Globals,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    expr_builder.block(&[]);
+    expr_builder.loop_(&[]);
+    {
+        // if (mark_stack_ptr == gc_roots_stack_ptr) break;
+        expr_builder.global_get(globals.mark_stack_ptr);
+        expr_builder.global_get(globals.gc_roots_stack_ptr);
+        expr_builder.i32_eq();
+        expr_builder.br_if(1);
+
+        // mark_stack_ptr -= 4;
+        expr_builder.global_get(globals.mark_stack_ptr);
+        expr_builder.i32_const(4);
+        expr_builder.i32_sub();
+        expr_builder.global_set(globals.mark_stack_ptr);
+
+        // trace(*mark_stack_ptr);
+        expr_builder.global_get(globals.mark_stack_ptr);
+        expr_builder.i32_load(wasmgen::MemArg::new4(0));
+        expr_builder.call(mark_funcs.trace);
+
+        expr_builder.br(0);
+    }
+    expr_builder.end();
+    expr_builder.end();
+}
+
+fn make_find_free(wasm_module: &mut wasmgen::WasmModule, globals: Globals) -> wasmgen::FuncIdx {
+    let functype = wasmgen::FuncType::new(
+        Box::new([wasmgen::ValType::I32]),
+        Box::new([wasmgen::ValType::I32]),
+    );
+    let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+    wasm_module.set_func_name(func_idx, "find_free".to_string());
+    let mut code_builder = wasmgen::CodeBuilder::new(functype);
+    {
+        let (locals_builder, expr_builder) = code_builder.split();
+        let mut scratch = Scratch::new(locals_builder);
+        let localidx_size = wasmgen::LocalIdx { idx: 0 };
+        let localidx_prev = scratch.push_i32();
+        let localidx_it = scratch.push_i32();
+        let localidx_block_size = scratch.push_i32();
+        let localidx_rest = scratch.push_i32();
+
+        // sets the `next` pointer of `prev` (or the free_list_ptr if `prev` is zero) to the value computed by `encode_next`
+        // net wasm stack: [] -> []
+        let encode_unlink = |encode_next: &dyn Fn(&mut wasmgen::ExprBuilder),
+                             expr_builder: &mut wasmgen::ExprBuilder| {
+            expr_builder.local_get(localidx_prev);
+            expr_builder.if_(&[]);
+            expr_builder.local_get(localidx_prev);
+            encode_next(expr_builder);
+            expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+            expr_builder.else_();
+            encode_next(expr_builder);
+            expr_builder.global_set(globals.free_list_ptr);
+            expr_builder.end();
+        };
+
+        // let prev = 0;
+        // let it = free_list_ptr;
+        // net wasm stack: [] -> []
+        expr_builder.i32_const(0);
+        expr_builder.local_set(localidx_prev);
+        expr_builder.global_get(globals.free_list_ptr);
+        expr_builder.local_set(localidx_it);
+
+        // net wasm stack: [] -> []
+        expr_builder.block(&[]);
+        expr_builder.loop_(&[]);
+        {
+            // if (!it) break;
+            expr_builder.local_get(localidx_it);
+            expr_builder.i32_eqz();
+            expr_builder.br_if(1);
+
+            // let block_size = it->size & ~FREE_BLOCK_FLAG;
+            expr_builder.local_get(localidx_it);
+            expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+            expr_builder.i32_const(!FREE_BLOCK_FLAG);
+            expr_builder.i32_and();
+            expr_builder.local_set(localidx_block_size);
+
+            // if (block_size >= size + BLOCK_HEADER_SIZE) { ... }
+            expr_builder.local_get(localidx_block_size);
+            expr_builder.local_get(localidx_size);
+            expr_builder.i32_const(BLOCK_HEADER_SIZE as i32);
+            expr_builder.i32_add();
+            expr_builder.i32_ge_u();
+            expr_builder.if_(&[]);
+            {
+                // let rest = it + size;
+                // rest->size = (block_size - size) | FREE_BLOCK_FLAG;
+                expr_builder.local_get(localidx_it);
+                expr_builder.local_get(localidx_size);
+                expr_builder.i32_add();
+                expr_builder.local_tee(localidx_rest);
+                expr_builder.local_get(localidx_block_size);
+                expr_builder.local_get(localidx_size);
+                expr_builder.i32_sub();
+                expr_builder.i32_const(FREE_BLOCK_FLAG);
+                expr_builder.i32_or();
+                expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+
+                // rest->tag = it->tag;
+                expr_builder.local_get(localidx_rest);
+                expr_builder.local_get(localidx_it);
+                expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+                expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+
+                // if (prev) prev->tag = rest; else free_list_ptr = rest;
+                encode_unlink(
+                    &|expr_builder| expr_builder.local_get(localidx_rest),
+                    expr_builder,
+                );
+
+                // it->size = size;
+                // return it;
+                expr_builder.local_get(localidx_it);
+                expr_builder.local_get(localidx_size);
+                expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+                expr_builder.local_get(localidx_it);
+                expr_builder.return_();
+            }
+            expr_builder.end();
+
+            // if (block_size >= size) { ... }
+            expr_builder.local_get(localidx_block_size);
+            expr_builder.local_get(localidx_size);
+            expr_builder.i32_ge_u();
+            expr_builder.if_(&[]);
+            {
+                // if (prev) prev->tag = it->tag; else free_list_ptr = it->tag;
+                encode_unlink(
+                    &|expr_builder| {
+                        expr_builder.local_get(localidx_it);
+                        expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+                    },
+                    expr_builder,
+                );
+
+                // it->size = block_size;
+                // return it;
+                expr_builder.local_get(localidx_it);
+                expr_builder.local_get(localidx_block_size);
+                expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+                expr_builder.local_get(localidx_it);
+                expr_builder.return_();
+            }
+            expr_builder.end();
+
+            // if (block_size < MIN_FREE_BLOCK_SIZE) { unlink it } else { prev = it; }
+            expr_builder.local_get(localidx_block_size);
+            expr_builder.i32_const(MIN_FREE_BLOCK_SIZE as i32);
+            expr_builder.i32_lt_u();
+            expr_builder.if_(&[]);
+            encode_unlink(
+                &|expr_builder| {
+                    expr_builder.local_get(localidx_it);
+                    expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+                },
+                expr_builder,
+            );
+            expr_builder.else_();
+            expr_builder.local_get(localidx_it);
+            expr_builder.local_set(localidx_prev);
+            expr_builder.end();
+
+            // it = it->tag;
+            expr_builder.local_get(localidx_it);
+            expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+            expr_builder.local_set(localidx_it);
+
+            expr_builder.br(0);
+        }
+        expr_builder.end();
+        expr_builder.end();
+
+        // return 0;
+        expr_builder.i32_const(0);
+
+        scratch.pop_i32();
+        scratch.pop_i32();
+        scratch.pop_i32();
+        scratch.pop_i32();
+
+        expr_builder.end();
+    }
+    wasm_module.commit_func(func_idx, code_builder);
+    func_idx
+}
+
+fn make_grow(
+    wasm_module: &mut wasmgen::WasmModule,
+    memidx: wasmgen::MemIdx,
+    globals: Globals,
+) -> wasmgen::FuncIdx {
+    let functype = wasmgen::FuncType::new(
+        Box::new([wasmgen::ValType::I32]),
+        Box::new([wasmgen::ValType::I32]),
+    );
+    let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+    wasm_module.set_func_name(func_idx, "grow".to_string());
+    let mut code_builder = wasmgen::CodeBuilder::new(functype);
+    {
+        let (locals_builder, expr_builder) = code_builder.split();
+        let mut scratch = Scratch::new(locals_builder);
+        let localidx_bytes = wasmgen::LocalIdx { idx: 0 };
+        let localidx_delta = scratch.push_i32();
+        let localidx_it = scratch.push_i32();
+
+        // let num_pages = (bytes + (WASM_PAGE_SIZE - 1)) >> WASM_PAGE_BITS;
+        // if (memory_grow(num_pages) == -1) return 0;
+        // let delta = num_pages << WASM_PAGE_BITS;
+        // net wasm stack: [] -> []
+        expr_builder.local_get(localidx_bytes);
+        expr_builder.i32_const((WASM_PAGE_SIZE - 1) as i32);
+        expr_builder.i32_add();
+        expr_builder.i32_const(WASM_PAGE_BITS as i32);
+        expr_builder.i32_shr_u();
+        expr_builder.local_tee(localidx_delta);
+        expr_builder.memory_grow(memidx);
+        expr_builder.i32_const(-1);
+        expr_builder.i32_eq();
+        expr_builder.if_(&[]);
+        expr_builder.i32_const(0);
+        expr_builder.return_();
+        expr_builder.end();
+        expr_builder.local_get(localidx_delta);
+        expr_builder.i32_const(WASM_PAGE_BITS as i32);
+        expr_builder.i32_shl();
+        expr_builder.local_set(localidx_delta);
+
+        // let it = gc_roots_stack_ptr;
+        // while (it != gc_roots_stack_base_ptr) {
+        //     it -= 4;
+        //     *(it + delta) = *it;
+        // }
+        // net wasm stack: [] -> []
+        expr_builder.global_get(globals.gc_roots_stack_ptr);
+        expr_builder.local_set(localidx_it);
+        expr_builder.block(&[]);
+        expr_builder.loop_(&[]);
+        {
+            expr_builder.local_get(localidx_it);
+            expr_builder.global_get(globals.gc_roots_stack_base_ptr);
+            expr_builder.i32_eq();
+            expr_builder.br_if(1);
+
+            expr_builder.local_get(localidx_it);
+            expr_builder.i32_const(4);
+            expr_builder.i32_sub();
+            expr_builder.local_tee(localidx_it);
+            expr_builder.local_get(localidx_delta);
+            expr_builder.i32_add();
+            expr_builder.local_get(localidx_it);
+            expr_builder.i32_load(wasmgen::MemArg::new4(0));
+            expr_builder.i32_store(wasmgen::MemArg::new4(0));
+
+            expr_builder.br(0);
+        }
+        expr_builder.end();
+        expr_builder.end();
+
+        // gc_roots_stack_base_ptr += delta;
+        // gc_roots_stack_ptr += delta;
+        // return 1;
+        // net wasm stack: [] -> [i32(1)]
+        expr_builder.global_get(globals.gc_roots_stack_base_ptr);
+        expr_builder.local_get(localidx_delta);
+        expr_builder.i32_add();
+        expr_builder.global_set(globals.gc_roots_stack_base_ptr);
+        expr_builder.global_get(globals.gc_roots_stack_ptr);
+        expr_builder.local_get(localidx_delta);
+        expr_builder.i32_add();
+        expr_builder.global_set(globals.gc_roots_stack_ptr);
+        expr_builder.i32_const(1);
+
+        scratch.pop_i32();
+        scratch.pop_i32();
+
+        expr_builder.end();
+    }
+    wasm_module.commit_func(func_idx, code_builder);
+    func_idx
+}
@@ -0,0 +1,422 @@
+use super::Globals;
+use super::MARK_BIT;
+use crate::ARRAY_DATA_OFFSET;
+use crate::ARRAY_ELEMENT_SIZE;
+use crate::ARRAY_HEADER_SIZE;
+use crate::ARRAY_LENGTH_OFFSET;
+use crate::WASM_PAGE_SIZE;
+use wasmgen::Scratch;
+
+#[derive(Copy, Clone)]
+pub struct MarkFuncs {
+    pub mark: wasmgen::FuncIdx,     // mark(ptr: i32)
+    pub mark_any: wasmgen::FuncIdx, // mark_any(tag: i32, data: i64)
+    pub trace: wasmgen::FuncIdx,    // trace(ptr: i32)
+}
+
+/*
+// Marks the object at `ptr` and pushes it onto the mark stack, if it is not already marked.
+// `ptr` can be nullptr (i.e. -1), or point to the global data (for strings), in which case nothing happens.
+fn mark(ptr: i32) {
+    if (ptr != -1 && ptr > heap_begin * WASM_PAGE_SIZE) {
+        let tag = *(ptr-4);
+        if (!(tag & MARK_BIT)) {
+            *(ptr-4) = tag | MARK_BIT;
+            if (mark_stack_ptr == mark_stack_end) {
+                mark_stack_overflow = 1; // the collector will find this object again by scanning the heap
+            } else {
+                *mark_stack_ptr = ptr;
+                mark_stack_ptr += 4;
+            }
+        }
+    }
+}
+// Marks the object referenced by the Any, if there is one.
+fn mark_any(tag: i32, data: i64) {
+    if (tag == Func) {
+        mark(closure_of(data));
+    } else if (tag == String || tag == Array || tag is a StructT) {
+        mark(from_any_data(data));
+    }
+}
+// Marks all the children of the (already marked) object at `ptr`.
+fn trace(ptr: i32) {
+    switch (*(ptr-4) & ~MARK_BIT) {
+        case Array:
+            let it = ptr->data;
+            let end = it + ptr->length * ARRAY_ELEMENT_SIZE;
+            if (it != ptr + ARRAY_HEADER_SIZE) mark(it - ARRAY_HEADER_SIZE); // the storage belongs to another array object
+            while (it != end) {
+                mark_any(it->tag, it->data);
+                it += ARRAY_ELEMENT_SIZE;
+            }
+            break;
+        case StructT { typeidx }:
+            for each field f in *ptr {
+                if constexpr f has type Any { mark_any(f.tag, f.data); }
+                else if constexpr f is Func { mark(f.closure); }
+                else if constexpr f is a ptr type { mark(f.ptr); }
+            }
+            break;
+        default:
+            // strings (and other types) don't have children
+    }
+}
+*/
+pub fn make_mark_funcs(
+    wasm_module: &mut wasmgen::WasmModule,
+    struct_types: &[Box<[ir::VarType]>],
+    struct_field_byte_offsets: &[Box<[u32]>],
+    globals: Globals,
+    heap_begin: u32,
+) -> MarkFuncs {
+    let mark_functype = wasmgen::FuncType::new(Box::new([wasmgen::ValType::I32]), Box::new([]));
+    let (_, mark_funcidx) = wasm_module.register_func(&mark_functype);
+    wasm_module.set_func_name(mark_funcidx, "mark".to_string());
+    let mark_any_functype = wasmgen::FuncType::new(
+        Box::new([wasmgen::ValType::I32, wasmgen::ValType::I64]),
+        Box::new([]),
+    );
+    let (_, mark_any_funcidx) = wasm_module.register_func(&mark_any_functype);
+    wasm_module.set_func_name(mark_any_funcidx, "mark_any".to_string());
+    let trace_functype = wasmgen::FuncType::new(Box::new([wasmgen::ValType::I32]), Box::new([]));
+    let (_, trace_funcidx) = wasm_module.register_func(&trace_functype);
+    wasm_module.set_func_name(trace_funcidx, "trace".to_string());
+
+    // mark()
+    {
+        let mut code_builder = wasmgen::CodeBuilder::new(mark_functype);
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let mut scratch = Scratch::new(locals_builder);
+            let localidx_ptr = wasmgen::LocalIdx { idx: 0 };
+            let localidx_tag_ptr = scratch.push_i32();
+            let localidx_tag = scratch.push_i32();
+
+            // net wasm stack: [] -> [cond(i32)]
+            expr_builder.local_get(localidx_ptr);
+            expr_builder.i32_const(-1);
+            expr_builder.i32_ne();
+            expr_builder.local_get(localidx_ptr);
+            expr_builder.i32_const((heap_begin * WASM_PAGE_SIZE) as i32);
+            expr_builder.i32_gt_u();
+            expr_builder.i32_and();
+
+            // net wasm stack: [cond(i32)] -> []
+            expr_builder.if_(&[]);
+            {
+                // net wasm stack: [] -> [cond(i32)]
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i32_const(4);
+                expr_builder.i32_sub();
+                expr_builder.local_tee(localidx_tag_ptr);
+                expr_builder.i32_load(wasmgen::MemArg::new4(0));
+                expr_builder.local_tee(localidx_tag);
+                expr_builder.i32_const(MARK_BIT);
+                expr_builder.i32_and();
+                expr_builder.i32_eqz();
+
+                // net wasm stack: [cond(i32)] -> []
+                expr_builder.if_(&[]);
+                {
+                    // *(ptr-4) = tag | MARK_BIT;
+                    expr_builder.local_get(localidx_tag_ptr);
+                    expr_builder.local_get(localidx_tag);
+                    expr_builder.i32_const(MARK_BIT);
+                    expr_builder.i32_or();
+                    expr_builder.i32_store(wasmgen::MemArg::new4(0));
+
+                    // net wasm stack: [] -> [cond(i32)]
+                    expr_builder.global_get(globals.mark_stack_ptr);
+                    expr_builder.global_get(globals.mark_stack_end);
+                    expr_builder.i32_eq();
+
+                    // net wasm stack: [cond(i32)] -> []
+                    expr_builder.if_(&[]);
+                    {
+                        expr_builder.i32_const(1);
+                        expr_builder.global_set(globals.mark_stack_overflow);
+                    }
+                    expr_builder.else_();
+                    {
+                        expr_builder.global_get(globals.mark_stack_ptr);
+                        expr_builder.local_get(localidx_ptr);
+                        expr_builder.i32_store(wasmgen::MemArg::new4(0));
+                        expr_builder.global_get(globals.mark_stack_ptr);
+                        expr_builder.i32_const(4);
+                        expr_builder.i32_add();
+                        expr_builder.global_set(globals.mark_stack_ptr);
+                    }
+                    expr_builder.end();
+                }
+                expr_builder.end();
+            }
+            expr_builder.end();
+
+            scratch.pop_i32();
+            scratch.pop_i32();
+
+            expr_builder.end();
+        }
+        wasm_module.commit_func(mark_funcidx, code_builder);
+    }
+
+    // mark_any()
+    {
+        let mut code_builder = wasmgen::CodeBuilder::new(mark_any_functype);
+        {
+            let (_locals_builder, expr_builder) = code_builder.split();
+            let localidx_tag = wasmgen::LocalIdx { idx: 0 };
+            let localidx_data = wasmgen::LocalIdx { idx: 1 };
+
+            // net wasm stack: [] -> [cond(i32)]
+            expr_builder.local_get(localidx_tag);
+            expr_builder.i32_const(ir::VarType::Func.tag());
+            expr_builder.i32_eq();
+
+            // net wasm stack: [cond(i32)] -> []
+            expr_builder.if_(&[]);
+            {
+                // the closure is in the high bits
+                expr_builder.local_get(localidx_data);
+                expr_builder.i64_const(32);
+                expr_builder.i64_shr_u();
+                expr_builder.i32_wrap_i64();
+                expr_builder.call(mark_funcidx);
+            }
+            expr_builder.else_();
+            {
+                // net wasm stack: [] -> [cond(i32)]
+                expr_builder.local_get(localidx_tag);
+                expr_builder.i32_const(ir::VarType::String.tag());
+                expr_builder.i32_eq();
+                expr_builder.local_get(localidx_tag);
+                expr_builder.i32_const(ir::VarType::Array.tag());
+                expr_builder.i32_eq();
+                expr_builder.i32_or();
+                expr_builder.local_get(localidx_tag);
+                expr_builder.i32_const(ir::NUM_PRIMITIVE_TAG_TYPES as i32);
+                expr_builder.i32_ge_u();
+                expr_builder.i32_or();
+
+                // net wasm stack: [cond(i32)] -> []
+                expr_builder.if_(&[]);
+                {
+                    expr_builder.local_get(localidx_data);
+                    expr_builder.i32_wrap_i64();
+                    expr_builder.call(mark_funcidx);
+                }
+                expr_builder.end();
+            }
+            expr_builder.end();
+
+            expr_builder.end();
+        }
+        wasm_module.commit_func(mark_any_funcidx, code_builder);
+    }
+
+    // trace()
+    {
+        let mut code_builder = wasmgen::CodeBuilder::new(trace_functype);
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let mut scratch = Scratch::new(locals_builder);
+            let localidx_ptr = wasmgen::LocalIdx { idx: 0 };
+
+            // The tags that have children, in the order of their case blocks (innermost first).
+            // Structs without any pointer fields don't need a case.
+            let cases: Box<[i32]> = std::iter::once(ir::VarType::Array.tag())
+                .chain(
+                    struct_types
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, field_types)| {
+                            field_types.iter().any(|ir_vartype| {
+                                matches!(
+                                    ir_vartype,
+                                    ir::VarType::Any
+                                        | ir::VarType::String
+                                        | ir::VarType::Func
+                                        | ir::VarType::Array
+                                        | ir::VarType::StructT { typeidx: _ }
+                                )
+                            })
+                        })
+                        .map(|(typeidx, _)| ir::VarType::StructT { typeidx: typeidx }.tag()),
+                )
+                .collect();
+
+            // the label for each tag (the default label goes to the end of the outermost block, i.e. nothing to do)
+            let mut labels: Box<[u32]> =
+                vec![cases.len() as u32; ir::NUM_PRIMITIVE_TAG_TYPES + struct_types.len()]
+                    .into_boxed_slice();
+            for (i, tag) in cases.iter().enumerate() {
+                labels[*tag as usize] = i as u32;
+            }
+
+            // net wasm stack: [] -> []
+            expr_builder.block(&[]);
+            for _ in 0..cases.len() {
+                expr_builder.block(&[]);
+            }
+            {
+                // net wasm stack: [] -> []
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i32_const(4);
+                expr_builder.i32_sub();
+                expr_builder.i32_load(wasmgen::MemArg::new4(0));
+                expr_builder.i32_const(!MARK_BIT);
+                expr_builder.i32_and();
+                expr_builder.br_table(&labels, cases.len() as u32);
+            }
+            for (i, tag) in cases.iter().copied().enumerate() {
+                expr_builder.end();
+                if tag == ir::VarType::Array.tag() {
+                    encode_trace_array(
+                        localidx_ptr,
+                        mark_funcidx,
+                        mark_any_funcidx,
+                        &mut scratch,
+                        expr_builder,
+                    );
+                } else {
+                    let typeidx: usize = tag as usize - ir::NUM_PRIMITIVE_TAG_TYPES;
+                    encode_trace_struct(
+                        localidx_ptr,
+                        &struct_types[typeidx],
+                        &struct_field_byte_offsets[typeidx],
+                        mark_funcidx,
+                        mark_any_funcidx,
+                        expr_builder,
+                    );
+                }
+                // break out of the outermost block
+                expr_builder.br((cases.len() - 1 - i) as u32);
+            }
+            expr_builder.end();
+
+            expr_builder.end();
+        }
+        wasm_module.commit_func(trace_funcidx, code_builder);
+    }
+
+    MarkFuncs {
+        mark: mark_funcidx,
+        mark_any: mark_any_funcidx,
+        trace: trace_funcidx,
+    }
+}
+
+// net wasm stack: [] -> []
+fn encode_trace_array(
+    localidx_ptr: wasmgen::LocalIdx,
+    mark_funcidx: wasmgen::FuncIdx,
+    mark_any_funcidx: wasmgen::FuncIdx,
+    scratch: &mut Scratch,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    let localidx_it = scratch.push_i32();
+    let localidx_end = scratch.push_i32();
+
+    // let it = ptr->data;
+    // let end = it + ptr->length * ARRAY_ELEMENT_SIZE;
+    // net wasm stack: [] -> []
+    expr_builder.local_get(localidx_ptr);
+    expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+    expr_builder.local_tee(localidx_it);
+    expr_builder.local_get(localidx_ptr);
+    expr_builder.i32_load(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+    expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+    expr_builder.i32_mul();
+    expr_builder.i32_add();
+    expr_builder.local_set(localidx_end);
+
+    // if (it != ptr + ARRAY_HEADER_SIZE) mark(it - ARRAY_HEADER_SIZE);
+    // net wasm stack: [] -> []
+    expr_builder.local_get(localidx_it);
+    expr_builder.local_get(localidx_ptr);
+    expr_builder.i32_const(ARRAY_HEADER_SIZE as i32);
+    expr_builder.i32_add();
+    expr_builder.i32_ne();
+    expr_builder.if_(&[]);
+    {
+        expr_builder.local_get(localidx_it);
+        expr_builder.i32_const(ARRAY_HEADER_SIZE as i32);
+        expr_builder.i32_sub();
+        expr_builder.call(mark_funcidx);
+    }
+    expr_builder.end();
+
+    // net wasm stack: [] -> []
+    expr_builder.block(&[]);
+    expr_builder.loop_(&[]);
+    {
+        // if (it == end) break;
+        expr_builder.local_get(localidx_it);
+        expr_builder.local_get(localidx_end);
+        expr_builder.i32_eq();
+        expr_builder.br_if(1);
+
+        // mark_any(it->tag, it->data);
+        expr_builder.local_get(localidx_it);
+        expr_builder.i32_load(wasmgen::MemArg::new4(0)); // the `tag` of the Any is at offset 0
+        expr_builder.local_get(localidx_it);
+        expr_builder.i64_load(wasmgen::MemArg::new4(4)); // the `data` of the Any is at offset 4
+        expr_builder.call(mark_any_funcidx);
+
+        // it += ARRAY_ELEMENT_SIZE;
+        expr_builder.local_get(localidx_it);
+        expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+        expr_builder.i32_add();
+        expr_builder.local_set(localidx_it);
+
+        expr_builder.br(0);
+    }
+    expr_builder.end();
+    expr_builder.end();
+
+    scratch.pop_i32();
+    scratch.pop_i32();
+}
+
+// net wasm stack: [] -> []
+fn encode_trace_struct(
+    localidx_ptr: wasmgen::LocalIdx,
+    field_types: &[ir::VarType],
+    field_byte_offsets: &[u32],
+    mark_funcidx: wasmgen::FuncIdx,
+    mark_any_funcidx: wasmgen::FuncIdx,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    field_types
+        .iter()
+        .copied()
+        .zip(field_byte_offsets.iter().copied())
+        .for_each(|(ir_vartype, byte_offset)| match ir_vartype {
+            ir::VarType::Any => {
+                // mark_any(f.tag, f.data);
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i32_load(wasmgen::MemArg::new4(byte_offset)); // the `tag` of the Any is at offset 0
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i64_load(wasmgen::MemArg::new4(byte_offset + 4)); // the `data` of the Any is at offset 4
+                expr_builder.call(mark_any_funcidx);
+            }
+            ir::VarType::Func => {
+                // mark(f.closure);
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i32_load(wasmgen::MemArg::new4(byte_offset + 4)); // the `closure` of the Func is at offset 4
+                expr_builder.call(mark_funcidx);
+            }
+            ir::VarType::String | ir::VarType::Array | ir::VarType::StructT { typeidx: _ } => {
+                // mark(f.ptr);
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i32_load(wasmgen::MemArg::new4(byte_offset));
+                expr_builder.call(mark_funcidx);
+            }
+            ir::VarType::Unassigned
+            | ir::VarType::Undefined
+            | ir::VarType::Null
+            | ir::VarType::Number
+            | ir::VarType::Boolean => {}
+        });
+}
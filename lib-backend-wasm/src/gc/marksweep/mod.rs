@@ -0,0 +1,493 @@
+use super::encode_array_header_init;
+use super::encode_pointer_locals_init;
+use super::encode_roots_push;
+use super::encode_struct_fields_init;
+use super::filter_roots;
+use super::HeapManager;
+use crate::global_var::GlobalVarManagerRef;
+use crate::ARRAY_ELEMENT_SIZE;
+use crate::ARRAY_HEADER_SIZE;
+use crate::WASM_PAGE_SIZE;
+use wasmgen::Scratch;
+
+mod collect;
+mod mark_funcs;
+
+#[cfg(any(test, feature = "wasmtest"))]
+pub mod wasmtest;
+
+/**
+ * MarkSweep is a non-moving GC implementation that uses mark-and-sweep with a free list.
+ * Unlike Cheney, it does not need to reserve any swap space, so the heap only needs to be as large as the live data (plus some slack).
+ *
+ * Layout of heap:
+ * [.....(blocks).....|.....(free space).....|.....(gc roots).....]
+ * `blocks`: objects that have been allocated to the program, and free blocks (that are linked together in the free list).
+ * `free space`: memory that has never been allocated (or was returned by the last sweep), allocation here is just a pointer bump.
+ * `gc_roots`: stack containing roots of the mark phase, these variables are declared to be 'alive' by the program.
+ * * This stack grows upwards, so that it will trap automatically if the stack overflows.
+ * * Values are stored as 'Any' format.
+ * * During a collection, the space after the gc_roots stack is used as the mark stack.
+ *
+ * Every block has an 8-byte header: [size(4 bytes)|tag(4 bytes)], and the pointer given to the program points to the byte after the header.
+ * * `size` is the size of the whole block (including the header), and is always a multiple of 4.
+ * * For free blocks, the lowest bit of `size` is set, and the `tag` field instead stores the pointer to the next free block (or zero).
+ * * For objects, `tag` is the VarType::tag() of the object (so the tag is at *(ptr-4), like in Cheney).
+ * * The size is stored separately from the tag because the storage of a grown array can be larger than its header says.
+ * During a collection, the MSB of the tag is used as the mark bit.  It is cleared again by the sweep.
+ *
+ * The storage of an array that has grown belongs to another array object (the one that was allocated when growing),
+ * so marking an array also marks the object that owns its storage.
+ *
+ * When the free space runs out, allocation falls back to `alloc_slow()` (in collect.rs), which tries the free list (first fit),
+ * and otherwise runs a collection and grows the memory if less than half of the heap is free after that.
+ */
+pub struct MarkSweep<'a, 'b, 'c> {
+    struct_types: &'a [Box<[ir::VarType]>], // types of the fields of each struct type
+    struct_field_byte_offsets: &'b [Box<[u32]>], // byte offsets of the fields of each struct type (each Box has same lengths as that of `struct_types`)
+    struct_sizes: &'c [u32], // map from typeidx to struct_sizes.  Note: typeidx is not VarType::tag()!  It is the typeidx used in VarType::StructT
+    globals: Globals,
+    alloc_slow_funcidx: wasmgen::FuncIdx, // funcidx of alloc_slow() function
+}
+
+// Globals used by the GC
+#[derive(Copy, Clone)]
+struct Globals {
+    free_mem_ptr: wasmgen::GlobalIdx,  // pointer to start of free space
+    free_list_ptr: wasmgen::GlobalIdx, // pointer to the first free block (or zero if there are no free blocks)
+    gc_roots_stack_base_ptr: wasmgen::GlobalIdx, // pointer to beginning of gc_roots stack (also the past-the-end of free space)
+    gc_roots_stack_ptr: wasmgen::GlobalIdx,      // pointer to past-the-end of gc_roots stack
+    mark_stack_ptr: wasmgen::GlobalIdx, // pointer to past-the-end of the mark stack (only used during a collection)
+    mark_stack_end: wasmgen::GlobalIdx, // pointer to past-the-end of the memory available for the mark stack (only used during a collection)
+    mark_stack_overflow: wasmgen::GlobalIdx, // nonzero if some marked objects could not be pushed on the mark stack (only used during a collection)
+}
+
+const MEM_INITIAL_USABLE_SIZE: u32 = 1 << 4; // 1 MiB of initial heap space
+const MEM_INITIAL_HEAP_SIZE: u32 = MEM_INITIAL_USABLE_SIZE + (1 << 4); // and 1 MiB of gc_roots stack space (also used for the mark stack)
+
+const BLOCK_HEADER_SIZE: u32 = 8;
+const MIN_FREE_BLOCK_SIZE: u32 = 32; // smaller free blocks are dropped from the free list by find_free() (this is the size of a pair, including the header)
+const BLOCK_SIZE_OFFSET: u32 = 0; // relative to the start of the block
+const BLOCK_TAG_OFFSET: u32 = 4; // relative to the start of the block
+const FREE_BLOCK_FLAG: i32 = 1; // set in the `size` of a free block
+const MARK_BIT: i32 = i32::min_value(); // set in the `tag` of a marked object
+
+impl<'a, 'b, 'c> MarkSweep<'a, 'b, 'c> {
+    // Constructs a new mark-sweep GC, and initializes it appropriately.
+    pub fn new<'d>(
+        struct_types: &'a [Box<[ir::VarType]>],
+        struct_field_byte_offsets: &'b [Box<[u32]>],
+        struct_sizes: &'c [u32],
+        memidx: wasmgen::MemIdx,
+        heap_begin: u32,
+        heap_initial_end: u32,
+        global_var_manager: GlobalVarManagerRef<'d>, // stores global vars that are gc roots too
+        error_func: wasmgen::FuncIdx,
+        wasm_module: &mut wasmgen::WasmModule,
+    ) -> Self {
+        assert!(heap_begin + MEM_INITIAL_HEAP_SIZE == heap_initial_end);
+
+        let globals = Globals {
+            free_mem_ptr: wasm_module
+                .add_i32_global(wasmgen::Mut::Var, (heap_begin * WASM_PAGE_SIZE) as i32),
+            free_list_ptr: wasm_module.add_i32_global(wasmgen::Mut::Var, 0),
+            gc_roots_stack_base_ptr: wasm_module.add_i32_global(
+                wasmgen::Mut::Var,
+                ((heap_begin + MEM_INITIAL_USABLE_SIZE) * WASM_PAGE_SIZE) as i32,
+            ),
+            gc_roots_stack_ptr: wasm_module.add_i32_global(
+                wasmgen::Mut::Var,
+                ((heap_begin + MEM_INITIAL_USABLE_SIZE) * WASM_PAGE_SIZE) as i32,
+            ),
+            mark_stack_ptr: wasm_module.add_i32_global(wasmgen::Mut::Var, 0),
+            mark_stack_end: wasm_module.add_i32_global(wasmgen::Mut::Var, 0),
+            mark_stack_overflow: wasm_module.add_i32_global(wasmgen::Mut::Var, 0),
+        };
+        // export the globals so that the driver knows how to restore them (for REPL resumption)
+        // (the mark stack globals are reinitialized at the start of every collection, so they don't need to be exported)
+        wasm_module.export_global(globals.free_mem_ptr, "free_mem_ptr".to_string());
+        wasm_module.export_global(globals.free_list_ptr, "free_list_ptr".to_string());
+        wasm_module.export_global(
+            globals.gc_roots_stack_base_ptr,
+            "gc_roots_stack_base_ptr".to_string(),
+        );
+        wasm_module.export_global(globals.gc_roots_stack_ptr, "gc_roots_stack_ptr".to_string());
+
+        let mark_funcs: mark_funcs::MarkFuncs = mark_funcs::make_mark_funcs(
+            wasm_module,
+            struct_types,
+            struct_field_byte_offsets,
+            globals,
+            heap_begin,
+        );
+
+        let alloc_slow_funcidx: wasmgen::FuncIdx = collect::make_alloc_slow(
+            wasm_module,
+            mark_funcs,
+            memidx,
+            globals,
+            global_var_manager,
+            heap_begin,
+            error_func,
+        );
+
+        MarkSweep {
+            struct_types: struct_types,
+            struct_field_byte_offsets: struct_field_byte_offsets,
+            struct_sizes: struct_sizes,
+            globals: globals,
+            alloc_slow_funcidx: alloc_slow_funcidx,
+        }
+    }
+
+    // Helper function used to encode heap allocation.
+    // `f` should be a function that has net wasm stack [] -> [i32(size)], it pushes the bytes required (including the block header) on the stack.
+    // The size must be a multiple of 4, and at least BLOCK_HEADER_SIZE.
+    // net wasm stack: [] -> [i32(ptr)]
+    fn encode_allocation<F: Fn(&mut wasmgen::ExprBuilder)>(
+        &self,
+        encode_size: F,
+        tag: i32,
+        local_types: &[ir::VarType],
+        local_map: &[usize],
+        wasm_local_map: &[wasmgen::LocalIdx],
+        scratch: &mut Scratch,
+        expr_builder: &mut wasmgen::ExprBuilder,
+    ) {
+        // Algorithm:
+        /*
+        let block;
+        if (gc_roots_stack_base_ptr - free_mem_ptr < size) {
+            for local in local_roots {
+                if(local is Any, String, Func, or StructT) {
+                    *gc_roots_stack_ptr = to_any(local);
+                    gc_roots_stack_ptr += 12;
+                }
+            }
+            block = alloc_slow(size); // this will trap if we are out of memory
+            // objects are never moved, so we don't need to load the locals back from the gc_roots stack
+            gc_roots_stack_ptr -= 12 * (number of local_roots that are Any, String, Func, or StructT);
+        } else {
+            block = free_mem_ptr;
+            free_mem_ptr += size;
+            block->size = size;
+        }
+        block->tag = tag;
+        return block + BLOCK_HEADER_SIZE;
+        */
+
+        let localidx_block: wasmgen::LocalIdx = scratch.push_i32();
+
+        // (gc_roots_stack_base_ptr - free_mem_ptr < size)
+        // net wasm stack: [] -> [cond(i32)]
+        expr_builder.global_get(self.globals.gc_roots_stack_base_ptr);
+        expr_builder.global_get(self.globals.free_mem_ptr);
+        expr_builder.i32_sub();
+        encode_size(expr_builder);
+        expr_builder.i32_lt_u();
+
+        // net wasm stack: [cond(i32)] -> []
+        expr_builder.if_(&[]);
+        {
+            // net wasm stack: [] -> []
+            self.encode_local_roots_prologue(
+                local_types,
+                local_map,
+                wasm_local_map,
+                scratch,
+                expr_builder,
+            );
+
+            // net wasm stack: [] -> []
+            encode_size(expr_builder);
+            expr_builder.call(self.alloc_slow_funcidx);
+            expr_builder.local_set(localidx_block);
+
+            // net wasm stack: [] -> []
+            self.encode_local_roots_epilogue(
+                local_types,
+                local_map,
+                wasm_local_map,
+                scratch,
+                expr_builder,
+            );
+        }
+        expr_builder.else_();
+        {
+            // net wasm stack: [] -> []
+            expr_builder.global_get(self.globals.free_mem_ptr);
+            expr_builder.local_tee(localidx_block);
+            encode_size(expr_builder);
+            expr_builder.i32_add();
+            expr_builder.global_set(self.globals.free_mem_ptr);
+            expr_builder.local_get(localidx_block);
+            encode_size(expr_builder);
+            expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+        }
+        expr_builder.end();
+
+        // net wasm stack: [] -> [ptr(i32)]
+        expr_builder.local_get(localidx_block);
+        expr_builder.i32_const(tag);
+        expr_builder.i32_store(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+        expr_builder.local_get(localidx_block);
+        expr_builder.i32_const(BLOCK_HEADER_SIZE as i32);
+        expr_builder.i32_add();
+
+        scratch.pop_i32();
+    }
+}
+
+impl<'a, 'b, 'c> HeapManager for MarkSweep<'a, 'b, 'c> {
+    // Returns the initial number of pages required by this heap HeapManager.
+    fn initial_heap_size() -> u32 {
+        MEM_INITIAL_HEAP_SIZE
+    }
+
+    // Encodes instructions to get a chunk of memory suitable for the given struct type specified by ir_vartype.
+    // It is guaranteed to be 4-byte aligned.
+    // net wasm stack: [] -> [i32(ptr)]
+    fn encode_fixed_allocation(
+        &self,
+        ir_vartype: ir::VarType,
+        local_types: &[ir::VarType],
+        local_map: &[usize],
+        wasm_local_map: &[wasmgen::LocalIdx],
+        scratch: &mut Scratch,
+        expr_builder: &mut wasmgen::ExprBuilder,
+    ) {
+        match ir_vartype {
+            ir::VarType::StructT { typeidx } => {
+                let size = self.struct_sizes[typeidx];
+                assert!((size & 3) == 0, "struct size must be multiple of 4");
+                // net wasm stack: [] -> [i32(ptr)]
+                self.encode_allocation(
+                    |expr_builder| {
+                        // net wasm stack: [] -> [i32(size)]
+                        expr_builder.i32_const((size + BLOCK_HEADER_SIZE) as i32);
+                    },
+                    ir_vartype.tag(),
+                    local_types,
+                    local_map,
+                    wasm_local_map,
+                    scratch,
+                    expr_builder,
+                );
+
+                // The block might be reused from the free list, so it contains garbage.
+                // Write Undefined to all Any fields in the struct
+                // and write nullptr (i.e. -1) to all String, Array, Func::closure, StructT
+                // net wasm stack: [i32(ptr)] -> [i32(ptr)]
+                encode_struct_fields_init(
+                    &self.struct_types[typeidx],
+                    &self.struct_field_byte_offsets[typeidx],
+                    scratch,
+                    expr_builder,
+                );
+            }
+            _ => panic!("incorrect VarType, expected StructT"),
+        }
+    }
+
+    // Encodes instructions to get a chunk of memory for an string/array of unknown size.  See `encode_fixed_allocation` for more detauls.
+    // The size need not be a multiple of 4.
+    // For arrays, the operand is the capacity (number of elements) instead of the number of bytes.
+    // net wasm stack: [i32(num_bytes)] -> [i32(ptr)]
+    fn encode_dynamic_allocation(
+        &self,
+        ir_vartype: ir::VarType,
+        local_types: &[ir::VarType],
+        local_map: &[usize],
+        wasm_local_map: &[wasmgen::LocalIdx],
+        scratch: &mut Scratch,
+        expr_builder: &mut wasmgen::ExprBuilder,
+    ) {
+        match ir_vartype {
+            ir::VarType::String => {
+                let localidx_str_len: wasmgen::LocalIdx = scratch.push_i32();
+                let localidx_mem_size: wasmgen::LocalIdx = scratch.push_i32();
+
+                {
+                    expr_builder.local_tee(localidx_str_len);
+                }
+
+                // Algorithm: mem_size = ((num_bytes + 15) & (~3))   // equivalent to (BLOCK_HEADER_SIZE + 4 + round_up_to_multiple_of_4(num_bytes))
+                // net wasm stack: [i32(num_bytes)] -> []
+                expr_builder.i32_const((BLOCK_HEADER_SIZE + 4 + 3) as i32);
+                expr_builder.i32_add();
+                expr_builder.i32_const(-4); // equivalent to (~3) in two's complement
+                expr_builder.i32_and();
+                expr_builder.local_set(localidx_mem_size);
+
+                // net wasm stack: [] -> [i32(ptr)]
+                self.encode_allocation(
+                    |expr_builder| {
+                        // net wasm stack: [] -> [i32(size)]
+                        expr_builder.local_get(localidx_mem_size);
+                    },
+                    ir_vartype.tag(),
+                    local_types,
+                    local_map,
+                    wasm_local_map,
+                    scratch,
+                    expr_builder,
+                );
+
+                // write the string length
+                // net wasm stack: [i32(ptr)] -> [i32(ptr)]
+                {
+                    let localidx_ret: wasmgen::LocalIdx = scratch.push_i32();
+                    expr_builder.local_tee(localidx_ret);
+                    expr_builder.local_get(localidx_ret);
+                    expr_builder.local_get(localidx_str_len);
+                    expr_builder.i32_store(wasmgen::MemArg::new4(0));
+                    scratch.pop_i32();
+                }
+
+                scratch.pop_i32();
+                scratch.pop_i32();
+            }
+            ir::VarType::Array => {
+                let localidx_capacity: wasmgen::LocalIdx = scratch.push_i32();
+
+                {
+                    expr_builder.local_set(localidx_capacity);
+                }
+
+                // Algorithm: mem_size = BLOCK_HEADER_SIZE + ARRAY_HEADER_SIZE + capacity * ARRAY_ELEMENT_SIZE
+                // net wasm stack: [] -> [i32(ptr)]
+                self.encode_allocation(
+                    |expr_builder| {
+                        // net wasm stack: [] -> [i32(size)]
+                        expr_builder.local_get(localidx_capacity);
+                        expr_builder.i32_const(ARRAY_ELEMENT_SIZE as i32);
+                        expr_builder.i32_mul();
+                        expr_builder.i32_const((BLOCK_HEADER_SIZE + ARRAY_HEADER_SIZE) as i32);
+                        expr_builder.i32_add();
+                    },
+                    ir_vartype.tag(),
+                    local_types,
+                    local_map,
+                    wasm_local_map,
+                    scratch,
+                    expr_builder,
+                );
+
+                // write the array header
+                // net wasm stack: [i32(ptr)] -> [i32(ptr)]
+                {
+                    let localidx_ret: wasmgen::LocalIdx = scratch.push_i32();
+                    expr_builder.local_tee(localidx_ret);
+                    encode_array_header_init(localidx_ret, localidx_capacity, expr_builder);
+                    scratch.pop_i32();
+                }
+
+                scratch.pop_i32();
+            }
+            _ => panic!("incorrect VarType, expected String or Array"),
+        }
+    }
+
+    type RootsStackHandle = ();
+
+    // Encodes instructions to push local variables to gc_roots stack.
+    // This should be called before a function which might allocate memory is called.
+    // It should be paired with a call to `encode_local_roots_elilogue()`.
+    // net wasm stack: [] -> []
+    fn encode_local_roots_prologue(
+        &self,
+        local_types: &[ir::VarType],
+        local_map: &[usize],
+        wasm_local_map: &[wasmgen::LocalIdx],
+        scratch: &mut Scratch,
+        expr_builder: &mut wasmgen::ExprBuilder,
+    ) -> Self::RootsStackHandle {
+        let filtered_roots: Box<[(ir::VarType, usize)]> = filter_roots(local_types, local_map);
+
+        // net wasm stack: [] -> []
+        encode_roots_push(
+            self.globals.gc_roots_stack_ptr,
+            &filtered_roots,
+            wasm_local_map,
+            scratch,
+            expr_builder,
+        );
+    }
+
+    // Encodes instructions to pop local variables from gc_roots stack.
+    // This should be called after a function which might allocate memory is called.
+    // It should be paired with a call to `encode_local_roots_prologue()`.
+    // Since objects are never moved, the locals still have the correct values, so we only need to pop the stack.
+    // net wasm stack: [] -> []
+    fn encode_local_roots_epilogue(
+        &self,
+        local_types: &[ir::VarType],
+        local_map: &[usize],
+        _wasm_local_map: &[wasmgen::LocalIdx],
+        _scratch: &mut Scratch,
+        expr_builder: &mut wasmgen::ExprBuilder,
+    ) {
+        let num_roots: usize = filter_roots(local_types, local_map).len();
+
+        if num_roots != 0 {
+            // net wasm stack: [] -> []
+            expr_builder.global_get(self.globals.gc_roots_stack_ptr);
+            expr_builder.i32_const((num_roots * 12) as i32);
+            expr_builder.i32_sub();
+            expr_builder.global_set(self.globals.gc_roots_stack_ptr);
+        }
+    }
+
+    // Encodes instructions to read a local variable from an arbitary position in the gc_roots stack, relative to the past-the-top position.
+    // Objects are never moved, so the local variable already has the correct value.
+    // net wasm stack: [] -> []
+    fn encode_local_root_read(
+        &self,
+        _local_root: (ir::VarType, wasmgen::LocalIdx),
+        _handle: Self::RootsStackHandle,
+        _scratch: &mut Scratch,
+        _expr_builder: &mut wasmgen::ExprBuilder,
+    ) {
+    }
+
+    // Encodes instructions to write a local variable to an arbitary position in the gc_roots stack, relative to the past-the-top position.
+    // Objects are never moved, so like encode_local_root_read() there is nothing to do.
+    // net wasm stack: [] -> []
+    fn encode_local_root_write(
+        &self,
+        _local_root: (ir::VarType, wasmgen::LocalIdx),
+        _handle: Self::RootsStackHandle,
+        _scratch: &mut Scratch,
+        _expr_builder: &mut wasmgen::ExprBuilder,
+    ) {
+    }
+
+    // We allow Undefined (which is encoded as the nullptr value),
+    // and any reference type (i.e. strings and structs)
+    // net wasm stack: [<closure_irvartype>] -> [i32(closure)]
+    fn encode_closure_conversion(
+        &self,
+        vartype: ir::VarType,
+        expr_builder: &mut wasmgen::ExprBuilder,
+    ) {
+        match vartype {
+            ir::VarType::Undefined => expr_builder.i32_const(-1),
+            ir::VarType::String | ir::VarType::StructT { typeidx: _ } => {}
+            _ => panic!("VarType is not undefined and also not a reference type"),
+        }
+    }
+
+    // Encodes instructions to initialize locals that could potentially go onto the gc_roots stack.
+    // `local_types` and `local_map` should have equal length, containing just those locals that should be initialized.
+    // `wasm_local_map` should not be sliced by the caller, because we need to preserve the indexing so that `local_map` will refer to the correct indices in `wasm_local_map`.
+    fn encode_local_roots_init(
+        &self,
+        local_types: &[ir::VarType],
+        local_map: &[usize],
+        wasm_local_map: &[wasmgen::LocalIdx],
+        _scratch: &mut Scratch,
+        expr_builder: &mut wasmgen::ExprBuilder,
+    ) {
+        encode_pointer_locals_init(local_types, local_map, wasm_local_map, expr_builder);
+    }
+}
@@ -0,0 +1,255 @@
+use super::*;
+use crate::global_var::GlobalVarManager;
+use wasm_test_harness::*;
+
+pub fn wasmtest<C: TestContext>(c: &mut C) {
+    c.add_test(
+        "small free blocks",
+        |code_builder, wasm_module, error_func, t| {
+            /*
+            In this test we will create a MarkSweep with the initial size (1MiB usable size),
+            and fill it exactly with 16384 copies of a 48-byte block (garbage) followed by a 16-byte block (kept alive in a linked list).
+            The first collection turns the garbage into 16384 free blocks of 48 bytes.
+            Then we allocate 16384 blocks of 40 bytes, each of which is split from a free block, leaving an 8-byte block behind.
+            find_free() should drop each 8-byte block from the free list when it walks past it, so only the last one is left in the free list.
+            Then we allocate one more 40-byte block, which runs another collection.
+            The sweep should merge the dropped blocks with the dead 40-byte blocks, so all 16384 free blocks of 48 bytes come back.
+            The memory should not grow.
+            */
+            const COUNT: i32 = 16384;
+            const HEAP_BEGIN: u32 = 1; // the free list uses zero as the end marker, so the heap can't start at address zero
+            let struct_types: [Box<[ir::VarType]>; 3] = [
+                Box::new([ir::VarType::StructT { typeidx: 0 }]), // 16-byte block (list node)
+                Box::new([]),                                    // 48-byte block (garbage)
+                Box::new([]),                                    // 40-byte block (reallocated)
+            ];
+            let struct_field_byte_offsets: [Box<[u32]>; 3] =
+                [Box::new([0]), Box::new([]), Box::new([])];
+            let struct_sizes: [u32; 3] = [8, 40, 32];
+            let mem = wasm_module.add_unbounded_memory(HEAP_BEGIN + MEM_INITIAL_HEAP_SIZE);
+            let global_var_manager = GlobalVarManager::default(); // no globals
+            let marksweep = MarkSweep::new(
+                &struct_types,
+                &struct_field_byte_offsets,
+                &struct_sizes,
+                mem,
+                HEAP_BEGIN,
+                HEAP_BEGIN + MEM_INITIAL_HEAP_SIZE,
+                global_var_manager.deref(),
+                error_func,
+                wasm_module,
+            );
+            let heap_start: i32 = (HEAP_BEGIN * WASM_PAGE_SIZE) as i32;
+
+            let (locals_builder, expr_builder) = code_builder.split();
+
+            // the head of the linked list, which is the only root
+            let localidx_head = locals_builder.add(wasmgen::ValType::I32);
+            let root_types = [ir::VarType::StructT { typeidx: 0 }];
+            let root_map = [0];
+            let wasm_root_map = [localidx_head];
+
+            expr_builder.i32_const(-1);
+            expr_builder.local_set(localidx_head);
+
+            let mut scratch = Scratch::new(locals_builder);
+
+            // fill the heap with garbage and list nodes
+            // net wasm stack: [] -> []
+            {
+                let localidx_i = scratch.push_i32();
+
+                // i = 0;
+                expr_builder.i32_const(0);
+                expr_builder.local_set(localidx_i);
+
+                // do {..} while(..);
+                expr_builder.loop_(&[]);
+                {
+                    // assert(new struct$1() == heap_start + i * 64 + 8);
+                    marksweep.encode_fixed_allocation(
+                        ir::VarType::StructT { typeidx: 1 },
+                        &root_types,
+                        &root_map,
+                        &wasm_root_map,
+                        &mut scratch,
+                        expr_builder,
+                    );
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.i32_const(64);
+                    expr_builder.i32_mul();
+                    expr_builder.i32_const(heap_start + BLOCK_HEADER_SIZE as i32);
+                    expr_builder.i32_add();
+                    t.i32_assert_eq(&mut scratch, expr_builder);
+
+                    // node = new struct$0(); node->next = head; head = node;
+                    marksweep.encode_fixed_allocation(
+                        ir::VarType::StructT { typeidx: 0 },
+                        &root_types,
+                        &root_map,
+                        &wasm_root_map,
+                        &mut scratch,
+                        expr_builder,
+                    );
+                    let localidx_node = scratch.push_i32();
+                    expr_builder.local_tee(localidx_node);
+                    expr_builder.local_get(localidx_head);
+                    expr_builder.i32_store(wasmgen::MemArg::new4(0));
+                    expr_builder.local_get(localidx_node);
+                    expr_builder.local_set(localidx_head);
+                    scratch.pop_i32();
+
+                    // i = i + 1;
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.i32_const(1);
+                    expr_builder.i32_add();
+                    expr_builder.local_set(localidx_i);
+
+                    // while (i < COUNT);
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.i32_const(COUNT);
+                    expr_builder.i32_lt_u();
+                    expr_builder.br_if(0);
+                }
+                expr_builder.end();
+
+                scratch.pop_i32();
+            }
+
+            // reallocate the garbage as smaller blocks, which come from the free list in decreasing order of address
+            // net wasm stack: [] -> []
+            {
+                let localidx_i = scratch.push_i32();
+
+                // i = 0;
+                expr_builder.i32_const(0);
+                expr_builder.local_set(localidx_i);
+
+                // do {..} while(..);
+                expr_builder.loop_(&[]);
+                {
+                    // assert(new struct$2() == heap_start + (COUNT - 1 - i) * 64 + 8);
+                    marksweep.encode_fixed_allocation(
+                        ir::VarType::StructT { typeidx: 2 },
+                        &root_types,
+                        &root_map,
+                        &wasm_root_map,
+                        &mut scratch,
+                        expr_builder,
+                    );
+                    expr_builder.i32_const(COUNT - 1);
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.i32_sub();
+                    expr_builder.i32_const(64);
+                    expr_builder.i32_mul();
+                    expr_builder.i32_const(heap_start + BLOCK_HEADER_SIZE as i32);
+                    expr_builder.i32_add();
+                    t.i32_assert_eq(&mut scratch, expr_builder);
+
+                    // i = i + 1;
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.i32_const(1);
+                    expr_builder.i32_add();
+                    expr_builder.local_set(localidx_i);
+
+                    // while (i < COUNT);
+                    expr_builder.local_get(localidx_i);
+                    expr_builder.i32_const(COUNT);
+                    expr_builder.i32_lt_u();
+                    expr_builder.br_if(0);
+                }
+                expr_builder.end();
+
+                scratch.pop_i32();
+            }
+
+            // only the 8-byte block left over by the last allocation is still in the free list
+            encode_free_list_stats(marksweep.globals, &mut scratch, expr_builder);
+            expr_builder.i32_const(8);
+            t.i32_assert_eq(&mut scratch, expr_builder);
+            expr_builder.i32_const(1);
+            t.i32_assert_eq(&mut scratch, expr_builder);
+
+            // the next allocation drops that block too, and then collects
+            // assert(new struct$2() == heap_start + (COUNT - 1) * 64 + 8);
+            marksweep.encode_fixed_allocation(
+                ir::VarType::StructT { typeidx: 2 },
+                &root_types,
+                &root_map,
+                &wasm_root_map,
+                &mut scratch,
+                expr_builder,
+            );
+            expr_builder.i32_const(heap_start + (COUNT - 1) * 64 + BLOCK_HEADER_SIZE as i32);
+            t.i32_assert_eq(&mut scratch, expr_builder);
+
+            // the sweep gave back all the dropped blocks: every 48-byte gap between the list nodes is free, except for the 40 bytes just allocated
+            encode_free_list_stats(marksweep.globals, &mut scratch, expr_builder);
+            expr_builder.i32_const((COUNT - 1) * 48 + 8);
+            t.i32_assert_eq(&mut scratch, expr_builder);
+            expr_builder.i32_const(COUNT);
+            t.i32_assert_eq(&mut scratch, expr_builder);
+
+            // check that the memory size is as expected, HEAP_BEGIN + MEM_INITIAL_HEAP_SIZE
+            expr_builder.memory_size(mem);
+            expr_builder.i32_const((HEAP_BEGIN + MEM_INITIAL_HEAP_SIZE) as i32);
+            t.i32_assert_eq(&mut scratch, expr_builder);
+        },
+    );
+}
+
+// Walks the free list, and pushes the number of free blocks and their total size in bytes.
+// net wasm stack: [] -> [i32(count), i32(bytes)]
+fn encode_free_list_stats(
+    globals: Globals,
+    scratch: &mut Scratch,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    let localidx_it = scratch.push_i32();
+    let localidx_count = scratch.push_i32();
+    let localidx_bytes = scratch.push_i32();
+
+    // it = free_list_ptr; count = 0; bytes = 0;
+    expr_builder.global_get(globals.free_list_ptr);
+    expr_builder.local_set(localidx_it);
+    expr_builder.i32_const(0);
+    expr_builder.local_set(localidx_count);
+    expr_builder.i32_const(0);
+    expr_builder.local_set(localidx_bytes);
+
+    // while (it) { count += 1; bytes += it->size & ~FREE_BLOCK_FLAG; it = it->tag; }
+    expr_builder.block(&[]);
+    expr_builder.loop_(&[]);
+    {
+        expr_builder.local_get(localidx_it);
+        expr_builder.i32_eqz();
+        expr_builder.br_if(1);
+
+        expr_builder.local_get(localidx_count);
+        expr_builder.i32_const(1);
+        expr_builder.i32_add();
+        expr_builder.local_set(localidx_count);
+
+        expr_builder.local_get(localidx_bytes);
+        expr_builder.local_get(localidx_it);
+        expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_SIZE_OFFSET));
+        expr_builder.i32_const(!FREE_BLOCK_FLAG);
+        expr_builder.i32_and();
+        expr_builder.i32_add();
+        expr_builder.local_set(localidx_bytes);
+
+        expr_builder.local_get(localidx_it);
+        expr_builder.i32_load(wasmgen::MemArg::new4(BLOCK_TAG_OFFSET));
+        expr_builder.local_set(localidx_it);
+        expr_builder.br(0);
+    }
+    expr_builder.end();
+    expr_builder.end();
+
+    expr_builder.local_get(localidx_count);
+    expr_builder.local_get(localidx_bytes);
+
+    scratch.pop_i32();
+    scratch.pop_i32();
+    scratch.pop_i32();
+}
@@ -1,6 +1,12 @@
 pub mod cheney;
 pub mod leaky;
+pub mod marksweep;
 
+use crate::var_conv::*;
+use crate::ARRAY_CAPACITY_OFFSET;
+use crate::ARRAY_DATA_OFFSET;
+use crate::ARRAY_HEADER_SIZE;
+use crate::ARRAY_LENGTH_OFFSET;
 use crate::WASM_PAGE_BITS;
 use crate::WASM_PAGE_SIZE;
 
@@ -62,6 +68,8 @@ pub trait HeapManager {
 
     // Encodes instructions to get a chunk of memory for an string/array of unknown size.  See `encode_fixed_allocation` for more details.
     // The size need not be a multiple of 4.  (But the allocator will round up to nearest 4-byte boundary.)
+    // For Array, the operand is the capacity (in elements) instead, and the allocator will write an empty array header
+    // (length 0, and data pointing to the space directly after the header).  The elements are not initialized.
     //
     // This function generates code equivalent to, but possibly more efficient to doing this:
     // self.encode_local_roots_prologue(local_roots, expr_builder);
@@ -160,3 +168,158 @@ pub trait HeapManager {
         expr_builder: &mut wasmgen::ExprBuilder,
     );
 }
+
+// Helper function used by heap managers to write the header of a newly allocated (and empty) array.
+// The elements will be placed directly after the header.
+// net wasm stack: [] -> []
+fn encode_array_header_init(
+    localidx_ptr: wasmgen::LocalIdx,
+    localidx_capacity: wasmgen::LocalIdx,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    expr_builder.local_get(localidx_ptr);
+    expr_builder.i32_const(0);
+    expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_LENGTH_OFFSET));
+    expr_builder.local_get(localidx_ptr);
+    expr_builder.local_get(localidx_capacity);
+    expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_CAPACITY_OFFSET));
+    expr_builder.local_get(localidx_ptr);
+    expr_builder.local_get(localidx_ptr);
+    expr_builder.i32_const(ARRAY_HEADER_SIZE as i32);
+    expr_builder.i32_add();
+    expr_builder.i32_store(wasmgen::MemArg::new4(ARRAY_DATA_OFFSET));
+}
+
+// Helper function used by heap managers to get the locals that might contain pointers (i.e. those that need to be on the gc_roots stack).
+fn filter_roots(local_types: &[ir::VarType], local_map: &[usize]) -> Box<[(ir::VarType, usize)]> {
+    local_types
+        .iter()
+        .copied()
+        .zip(local_map.iter().copied())
+        .filter(|(ir_vartype, _)| match ir_vartype {
+            ir::VarType::Unassigned
+            | ir::VarType::Undefined
+            | ir::VarType::Null
+            | ir::VarType::Number
+            | ir::VarType::Boolean => false,
+            _ => true,
+        })
+        .collect()
+}
+
+fn wasm_local_slice<'a>(
+    ir_vartype: ir::VarType,
+    wasm_local_map_idx: usize,
+    wasm_local_map: &'a [wasmgen::LocalIdx],
+) -> &'a [wasmgen::LocalIdx] {
+    &wasm_local_map[wasm_local_map_idx..(wasm_local_map_idx + encode_vartype(ir_vartype).len())]
+}
+
+// Helper function used by heap managers to push the given roots (already filtered) onto a gc_roots stack (as Anys).
+// The stack grows upward, and `gc_roots_stack_ptr` points to past-the-top of the stack.
+// net wasm stack: [] -> []
+fn encode_roots_push(
+    gc_roots_stack_ptr: wasmgen::GlobalIdx,
+    filtered_roots: &[(ir::VarType, usize)],
+    wasm_local_map: &[wasmgen::LocalIdx],
+    scratch: &mut Scratch,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    // if there are no roots to add, then we don't need to load the gc_roots_stack_ptr.
+    // net wasm stack: [] -> []
+    if !filtered_roots.is_empty() {
+        let localidx_gc_roots_stack_ptr = scratch.push_i32();
+
+        // net wasm stack: [] -> [gc_roots_stack_ptr(i32)]
+        expr_builder.global_get(gc_roots_stack_ptr);
+
+        for (ir_vartype, index) in filtered_roots.iter().copied() {
+            // net wasm stack: [gc_roots_stack_ptr(i32)] -> []
+            expr_builder.local_tee(localidx_gc_roots_stack_ptr);
+            encode_load_local(
+                wasm_local_slice(ir_vartype, index, wasm_local_map),
+                ir_vartype,
+                ir_vartype,
+                expr_builder,
+            );
+            encode_store_memory(0, ir::VarType::Any, ir_vartype, scratch, expr_builder);
+
+            // net wasm stack: [] -> [gc_roots_stack_ptr(i32)]
+            expr_builder.local_get(localidx_gc_roots_stack_ptr);
+            expr_builder.i32_const(12);
+            expr_builder.i32_add();
+        }
+
+        // net wasm stack: [gc_roots_stack_ptr(i32)] -> []
+        expr_builder.global_set(gc_roots_stack_ptr);
+
+        scratch.pop_i32();
+    }
+}
+
+// Helper function used by heap managers to set all pointers in the given locals to -1 (nullptr), and all Anys to Unassigned.
+// net wasm stack: [] -> []
+fn encode_pointer_locals_init(
+    local_types: &[ir::VarType],
+    local_map: &[usize],
+    wasm_local_map: &[wasmgen::LocalIdx],
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    for (ir_vartype, wasm_local_map_index) in
+        local_types.iter().copied().zip(local_map.iter().copied())
+    {
+        match ir_vartype {
+            ir::VarType::String | ir::VarType::Array | ir::VarType::StructT { typeidx: _ } => {
+                expr_builder.i32_const(-1);
+                expr_builder.local_set(wasm_local_map[wasm_local_map_index]);
+            }
+            ir::VarType::Func => {
+                expr_builder.i32_const(-1);
+                expr_builder.local_set(wasm_local_map[wasm_local_map_index + 1]);
+                // Note: "+1" above to access the closure
+            }
+            ir::VarType::Any => {
+                expr_builder.i32_const(ir::VarType::Unassigned.tag());
+                expr_builder.local_set(wasm_local_map[wasm_local_map_index]);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Helper function used by heap managers to initialize the fields of a newly allocated struct,
+// so that the GC never sees garbage pointers in it.
+// Any fields are set to Unassigned, and String, Array, Func::closure and StructT fields are set to nullptr (i.e. -1).
+// net wasm stack: [i32(ptr)] -> [i32(ptr)]
+fn encode_struct_fields_init(
+    field_types: &[ir::VarType],
+    field_byte_offsets: &[u32],
+    scratch: &mut Scratch,
+    expr_builder: &mut wasmgen::ExprBuilder,
+) {
+    let localidx_ptr: wasmgen::LocalIdx = scratch.push_i32();
+    expr_builder.local_tee(localidx_ptr);
+    field_types
+        .iter()
+        .zip(field_byte_offsets.iter())
+        .for_each(|(ir_vartype, byte_offset)| match ir_vartype {
+            ir::VarType::Any => {
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i32_const(ir::VarType::Unassigned.tag());
+                expr_builder.i32_store(wasmgen::MemArg::new4(*byte_offset));
+            }
+            ir::VarType::String | ir::VarType::Array | ir::VarType::StructT { typeidx: _ } => {
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i32_const(-1);
+                expr_builder.i32_store(wasmgen::MemArg::new4(*byte_offset));
+            }
+            ir::VarType::Func => {
+                expr_builder.local_get(localidx_ptr);
+                expr_builder.i32_const(-1);
+                expr_builder.i32_store(wasmgen::MemArg::new4(*byte_offset + 4));
+                // Note: "+4" above to access the closure
+            }
+            _ => {}
+        });
+    scratch.pop_i32();
+}
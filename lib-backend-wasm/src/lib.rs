@@ -22,13 +22,22 @@
  * String -> i32 (ptr to unsized mem)
  * Func -> i32 (index in wasm table) + i32 (closure)
  * StructT -> i32 (ptr to data)
+ * Array -> i32 (ptr to array header)
  * Any -> i32 (tag) + i64 (data, reinterpret as the concrete type specified in the tag)
  *
- * Note on String (and in the future Array should be similar):
+ * Note on String:
  * * The content of a String is: length(4 bytes) followed by the content(length bytes).
  * * The pointer returned points to the `length` field.
  * * The actual size of the memory used is (length+4) bytes rounded up to nearest 4-byte boundary.
  *
+ * Note on Array:
+ * * The header of an Array is: length(4 bytes), capacity(4 bytes), data(4 bytes, ptr to the elements).
+ * * The pointer returned points to the `length` field.
+ * * Each element is an Any (12 bytes: i32 tag followed by the data), and only the first `length` elements are valid.
+ * * A newly allocated array has room for `capacity` elements directly after the header.
+ * * When the array needs to grow, a new array is allocated and its elements are taken over by the old header
+ *   (so the array pointer never changes, and the new header becomes garbage).
+ *
  * Most functions have a comment that looks like: net wasm stack: [...] -> [...]
  * This refers to net change to the wasm protected stack (top of stack on the right side, which agrees with the webassembly specification).
  * Stack elements in quotes (e.g. <ir_vartype>) means that that position of the stack contains a value (or values) of the given `ir_vartype` (not necessarily Any).
@@ -40,6 +49,14 @@
  * Indirect function calls use the Uniform Calling Convention, which allocates space on the stack to transfer the arguments.
  * This allows a variable number of arguments.
  *
+ * Calls in tail position (i.e. the expression of a Return) do not grow the wasm stack:
+ * * If the tail call proposal is enabled, they are encoded with return_call or return_call_indirect.
+ * * Otherwise, a self tail call assigns to the params and branches to the beginning of the function,
+ *   and an indirect tail call leaves a pending call in some globals, to be made by the nearest non-tail call site (a trampoline).
+ *   A direct tail call to another function with the same return type also goes through the trampoline, by calling the callee's thunk.
+ *   If that return type is not Any, the trampoline returns the result as an Any, so every call site of the caller
+ *   looks for the pending call after the call returns, and narrows the result of the trampoline back to the return type.
+ *
  * Memory management:
  * WebAssembly has one linear memory, growable at the right end (largest index).
  * We divide the memory as such (from 0 (left) to memory.size (right)):
@@ -68,27 +85,128 @@ mod var_conv;
 
 use gc::cheney::Cheney;
 use gc::leaky::Leaky;
+use gc::marksweep::MarkSweep;
 use gc::HeapManager;
+use global_var::GlobalVarManagerRef;
+use pre_traverse::ShiftedStringPool;
 
 use projstd::iter::*;
+use projstd::searchablevec::SearchableVec;
 use projstd::tuple::*;
 
 use wasmgen::Scratch;
 
+use std::collections::HashMap;
+
 const IR_FUNCIDX_TABLE_OFFSET: u32 = 0; // If ir::FuncIdx == x, then wasmgen::TableIdx == IR_FUNCIDX_TABLE_OFFSET + x as u32
 
 const WASM_PAGE_SIZE: u32 = 65536;
 const WASM_PAGE_BITS: u32 = WASM_PAGE_SIZE.trailing_zeros();
 
+// Byte offsets of the fields in the Array header (relative to the array pointer)
+const ARRAY_LENGTH_OFFSET: u32 = 0;
+const ARRAY_CAPACITY_OFFSET: u32 = 4;
+const ARRAY_DATA_OFFSET: u32 = 8;
+const ARRAY_HEADER_SIZE: u32 = 12;
+const ARRAY_ELEMENT_SIZE: u32 = 12; // size of an Any
+const ARRAY_MAX_LENGTH: u32 = 1 << 24; // stores to indices at least this large will trap, so that sizes never overflow an i32
+
 // In units of WASM_PAGE_SIZE
-const MEM_STACK_SIZE: u32 = 1 << 4; // 1 MiB of stack space
+const DEFAULT_MEM_STACK_SIZE: u32 = 1 << 4; // 1 MiB of stack space
+pub const MAX_STACK_SIZE: u32 = 1 << 14; // 1 GiB of stack space; the stack pointer starts at the end of the stack, and must stay a positive i32 with room for the global data and the heap after it
+
+// The heap managers (garbage collectors) that can be used by the generated code
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Heap {
+    Cheney,    // Copying garbage collector
+    Leaky,     // Never frees anything, but allocation is very fast (for short-lived programs)
+    MarkSweep, // Non-moving garbage collector with a free list (needs less memory than Cheney)
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Heap::Cheney
+    }
+}
 
-// Struct containing compilation options
-#[derive(Default, Copy, Clone)]
+/**
+ * Struct containing compilation options.
+ * Use the builder methods to change them, e.g. `Options::new().wasm_multi_value(true).stack_size(32)`.
+ * By default, no WebAssembly proposals are used, so the output will run on any WebAssembly 1.0 engine.
+ */
+#[derive(Copy, Clone)]
 pub struct Options {
     wasm_multi_value: bool, // Whether we can generate code that uses the WebAssembly multi-valued returns proposal
     wasm_bulk_memory: bool, // Whether we can generate code that uses the WebAssembly bulk memory proposal
     wasm_tail_call: bool, // Whether we can generate code that uses the WebAssembly tail call proposal
+    heap: Heap,           // The heap manager to use
+    stack_size: u32,      // Size of the stack, in units of WASM_PAGE_SIZE
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            wasm_multi_value: false,
+            wasm_bulk_memory: false,
+            wasm_tail_call: false,
+            heap: Heap::default(),
+            stack_size: DEFAULT_MEM_STACK_SIZE,
+        }
+    }
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn wasm_multi_value(mut self, enabled: bool) -> Self {
+        self.wasm_multi_value = enabled;
+        self
+    }
+    pub fn wasm_bulk_memory(mut self, enabled: bool) -> Self {
+        self.wasm_bulk_memory = enabled;
+        self
+    }
+    pub fn wasm_tail_call(mut self, enabled: bool) -> Self {
+        self.wasm_tail_call = enabled;
+        self
+    }
+    pub fn heap(mut self, heap: Heap) -> Self {
+        self.heap = heap;
+        self
+    }
+    /**
+     * Sets the size of the stack, in units of WebAssembly pages (64 KiB).
+     * The host needs to know this, because the result of the program is written to the end of the stack.
+     * Panics if `check_stack_size()` rejects `num_pages`.
+     */
+    pub fn stack_size(mut self, num_pages: u32) -> Self {
+        if let Err(e) = check_stack_size(num_pages) {
+            panic!("{}", e);
+        }
+        self.stack_size = num_pages;
+        self
+    }
+    pub fn get_stack_size(&self) -> u32 {
+        self.stack_size
+    }
+}
+
+/**
+ * Checks that the stack can have the given number of pages, i.e. that it is between one page and MAX_STACK_SIZE.
+ * Hosts should call this before `Options::stack_size()` to report an invalid size instead of panicking.
+ */
+pub fn check_stack_size(num_pages: u32) -> Result<(), String> {
+    if num_pages == 0 {
+        Err("stack size must be at least one page".to_owned())
+    } else if num_pages > MAX_STACK_SIZE {
+        Err(format!(
+            "stack size must be at most {} pages",
+            MAX_STACK_SIZE
+        ))
+    } else {
+        Ok(())
+    }
 }
 
 /**
@@ -101,7 +219,22 @@ pub fn run_backend(
     repl_funcidx_start: usize,
     options: Options,
 ) -> wasmgen::WasmModule {
-    encode_program(ir_program, repl_funcidx_start, options)
+    let wasm_module = encode_program(ir_program, repl_funcidx_start, options);
+    debug_validate(&wasm_module);
+    wasm_module
+}
+
+/**
+ * In debug builds, validates the generated module (see wasmgen's validate.rs) and panics if it is invalid,
+ * so that codegen bugs are reported at the offending instruction instead of when the module is instantiated.
+ */
+fn debug_validate(wasm_module: &wasmgen::WasmModule) {
+    if cfg!(debug_assertions) {
+        if let Err(errors) = wasm_module.validate() {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            panic!("ICE: wasm validation failed:\n{}", messages.join("\n"));
+        }
+    }
 }
 
 fn encode_program(
@@ -125,17 +258,34 @@ fn encode_program(
         string_pool,
         thunk_sv,
         appl_location_sv,
-    } = pre_traverse::pre_traverse_funcs(&ir_program.funcs, repl_sl, repl_funcidx_start);
+        typed_trampoline_callers,
+        ..
+    } = pre_traverse::pre_traverse_funcs(
+        &ir_program.funcs,
+        ir_program.imports.len(),
+        repl_sl,
+        repl_funcidx_start,
+        !options.wasm_tail_call,
+    );
+
+    let mem_stack_size: u32 = options.stack_size;
+
+    // in terms of WASM_PAGE_SIZE
+    let heap_initial_size: u32 = match options.heap {
+        Heap::Cheney => Cheney::initial_heap_size(),
+        Heap::Leaky => Leaky::initial_heap_size(),
+        Heap::MarkSweep => MarkSweep::initial_heap_size(),
+    };
 
     let (shifted_string_pool, pool_data) =
-        string_pool.into_shifted_and_buffer(MEM_STACK_SIZE << WASM_PAGE_BITS);
+        string_pool.into_shifted_and_buffer(mem_stack_size << WASM_PAGE_BITS);
 
     assert!(pool_data.len() & 3 == 0); // assert that it is at 4-byte boundary
 
     // make static data for appl locations
     let (appl_data, appl_data_encoder) = pre_traverse::make_appl_location_static_data(
         appl_location_sv,
-        (MEM_STACK_SIZE << WASM_PAGE_BITS) + pool_data.len() as u32,
+        (mem_stack_size << WASM_PAGE_BITS) + pool_data.len() as u32,
     );
 
     assert!(appl_data.len() & 3 == 0); // assert that it is at 4-byte boundary
@@ -186,12 +336,25 @@ fn encode_program(
         memidx = wasm_module_builder.import_unbounded_memory(
             "core".to_string(),
             "linear_memory".to_string(),
-            MEM_STACK_SIZE + globals_num_pages + Cheney::initial_heap_size(),
+            mem_stack_size + globals_num_pages + heap_initial_size,
         );
     }
 
     let mut wasm_module = wasm_module_builder.build();
 
+    // name the imported functions after their module and entity names
+    wasm_module.set_func_name(error_func, "core.error".to_string());
+    ir_program
+        .imports
+        .iter()
+        .zip(imported_funcs.iter())
+        .for_each(|(ir_import, funcidx)| {
+            wasm_module.set_func_name(
+                *funcidx,
+                format!("{}.{}", ir_import.module_name, ir_import.entity_name),
+            );
+        });
+
     // build the signature list (directly maps from ir::FuncIdx)
     let signature_list: Box<[func::Signature]> = ir_program
         .imports
@@ -199,16 +362,25 @@ fn encode_program(
         .map(|ir_import| func::Signature {
             params: translate_import_params(&ir_import.params),
             result: Some(translate_import_param(ir_import.result)),
+            typed_trampoline_caller: false,
         })
-        .chain(ir_program.funcs.iter().map(|ir_func| func::Signature {
-            params: ir_func.params.clone(),
-            result: ir_func.result,
-        }))
+        .chain(
+            ir_program
+                .funcs
+                .iter()
+                .enumerate()
+                .map(|(i, ir_func)| func::Signature {
+                    params: ir_func.params.clone(),
+                    result: ir_func.result,
+                    typed_trampoline_caller: typed_trampoline_callers
+                        .contains(&(ir_program.imports.len() + i)),
+                }),
+        )
         .collect();
 
     // add stack ptr
     let globalidx_stackptr =
-        wasm_module.add_i32_global(wasmgen::Mut::Var, (MEM_STACK_SIZE * WASM_PAGE_SIZE) as i32);
+        wasm_module.add_i32_global(wasmgen::Mut::Var, (mem_stack_size * WASM_PAGE_SIZE) as i32);
 
     // add ir global vars (they are exported)
     let global_var_manager =
@@ -239,13 +411,9 @@ fn encode_program(
     if repl_funcidx_start == usize::MAX {
         // add linear memory (if we are not in REPL)
         memidx = encode_mem(
-            MEM_STACK_SIZE + globals_num_pages + Cheney::initial_heap_size(),
+            mem_stack_size + globals_num_pages + heap_initial_size,
             &mut wasm_module,
         );
-        /*let memidx: wasmgen::MemIdx = encode_mem(
-            MEM_STACK_SIZE + globals_num_pages + Cheney::initial_heap_size(),
-            &mut wasm_module,
-        );*/
 
         // export the memory (so that the host can read the return value)
         wasm_module.export_mem(memidx, "linear_memory".to_string());
@@ -253,7 +421,7 @@ fn encode_program(
         // initialize pool data
         encode_static_data(
             &pool_data,
-            MEM_STACK_SIZE << WASM_PAGE_BITS,
+            mem_stack_size << WASM_PAGE_BITS,
             memidx,
             &mut wasm_module,
         );
@@ -261,62 +429,157 @@ fn encode_program(
         // initialize appl data
         encode_static_data(
             &appl_data,
-            (MEM_STACK_SIZE << WASM_PAGE_BITS) + pool_data.len() as u32,
+            (mem_stack_size << WASM_PAGE_BITS) + pool_data.len() as u32,
             memidx,
             &mut wasm_module,
         );
     }
 
     // garbage collector
-    // hack inside Cheney: Cheney needs to reserve a fixed about of table space otherwise things might break when more structs are added in REPL
-    let heap = Cheney::new(
-        &ir_program.struct_types,
-        &struct_field_byte_offsets,
-        &struct_sizes,
-        memidx,
-        MEM_STACK_SIZE + globals_num_pages,
-        MEM_STACK_SIZE + globals_num_pages + Cheney::initial_heap_size(),
-        global_var_manager.deref(),
-        error_func,
-        &mut wasm_module,
-    );
-    /*let heap = Cheney::new(
-        &ir_program.struct_types,
-        &struct_field_byte_offsets,
-        &struct_sizes,
-        memidx,
-        MEM_STACK_SIZE + globals_num_pages,
-        MEM_STACK_SIZE + globals_num_pages + Cheney::initial_heap_size(),
-        error_func,
-        &mut wasm_module,
-    );*/
+    // the rest of the encoding is generic over the heap manager, so we monomorphize it for the chosen heap here
+    let heap_begin: u32 = mem_stack_size + globals_num_pages;
+    match options.heap {
+        Heap::Cheney => {
+            // hack inside Cheney: Cheney needs to reserve a fixed about of table space otherwise things might break when more structs are added in REPL
+            let heap = Cheney::new(
+                &ir_program.struct_types,
+                &struct_field_byte_offsets,
+                &struct_sizes,
+                memidx,
+                heap_begin,
+                heap_begin + heap_initial_size,
+                global_var_manager.deref(),
+                error_func,
+                &mut wasm_module,
+            );
+            encode_funcs_with_heap(
+                &heap,
+                ir_program,
+                &signature_list,
+                &struct_field_byte_offsets,
+                imported_funcs,
+                global_var_manager.deref(),
+                globalidx_stackptr,
+                memidx,
+                thunk_sv,
+                appl_data_encoder,
+                &shifted_string_pool,
+                error_func,
+                repl_sl,
+                repl_funcidx_start,
+                options,
+                &mut wasm_module,
+            );
+        }
+        Heap::MarkSweep => {
+            let heap = MarkSweep::new(
+                &ir_program.struct_types,
+                &struct_field_byte_offsets,
+                &struct_sizes,
+                memidx,
+                heap_begin,
+                heap_begin + heap_initial_size,
+                global_var_manager.deref(),
+                error_func,
+                &mut wasm_module,
+            );
+            encode_funcs_with_heap(
+                &heap,
+                ir_program,
+                &signature_list,
+                &struct_field_byte_offsets,
+                imported_funcs,
+                global_var_manager.deref(),
+                globalidx_stackptr,
+                memidx,
+                thunk_sv,
+                appl_data_encoder,
+                &shifted_string_pool,
+                error_func,
+                repl_sl,
+                repl_funcidx_start,
+                options,
+                &mut wasm_module,
+            );
+        }
+        Heap::Leaky => {
+            let heap = Leaky::new(
+                &ir_program.struct_types,
+                &struct_field_byte_offsets,
+                &struct_sizes,
+                memidx,
+                heap_begin,
+                heap_begin + heap_initial_size,
+                error_func,
+                &mut wasm_module,
+            );
+            encode_funcs_with_heap(
+                &heap,
+                ir_program,
+                &signature_list,
+                &struct_field_byte_offsets,
+                imported_funcs,
+                global_var_manager.deref(),
+                globalidx_stackptr,
+                memidx,
+                thunk_sv,
+                appl_data_encoder,
+                &shifted_string_pool,
+                error_func,
+                repl_sl,
+                repl_funcidx_start,
+                options,
+                &mut wasm_module,
+            );
+        }
+    }
+
+    wasm_module
+}
 
+// Encodes everything that depends on the heap manager (i.e. the functions and the allocation exports).
+fn encode_funcs_with_heap<H: HeapManager>(
+    heap: &H,
+    ir_program: &ir::Program,
+    signature_list: &[func::Signature],
+    struct_field_byte_offsets: &[Box<[u32]>],
+    imported_funcs: Box<[wasmgen::FuncIdx]>,
+    global_var_manager: GlobalVarManagerRef,
+    globalidx_stackptr: wasmgen::GlobalIdx,
+    memidx: wasmgen::MemIdx,
+    thunk_sv: SearchableVec<Box<[ir::OverloadEntry]>>,
+    appl_data_encoder: HashMap<ir::SourceLocation, u32>,
+    shifted_string_pool: &ShiftedStringPool,
+    error_func: wasmgen::FuncIdx,
+    repl_sl: ir::SourceLocation,
+    repl_funcidx_start: usize,
+    options: Options,
+    wasm_module: &mut wasmgen::WasmModule,
+) {
     // Encode a bridging function to allocate strings so that the host
     // can call it to allocate a returned string.
-    encode_heap_alloc_exports(&heap, &mut wasm_module);
+    encode_heap_alloc_exports(heap, wasm_module);
 
     func::encode_funcs(
-        &signature_list, // for checking types of params and results only
+        signature_list, // for checking types of params and results only
         &ir_program.funcs,
         &ir_program.struct_types,
-        &struct_field_byte_offsets,
+        struct_field_byte_offsets,
         imported_funcs,
         ir_program.entry_point,
-        global_var_manager.deref(),
+        global_var_manager,
         globalidx_stackptr,
         memidx,
         thunk_sv,
         appl_data_encoder,
-        &heap,
-        &shifted_string_pool,
+        heap,
+        shifted_string_pool,
         error_func,
         repl_sl,
         repl_funcidx_start,
         options,
-        &mut wasm_module,
+        wasm_module,
     );
-
-    wasm_module
 }
 
 fn translate_import_params(ivts: &[ir::ImportValType]) -> Box<[ir::VarType]> {
@@ -377,6 +640,7 @@ fn encode_heap_alloc_exports<H: HeapManager>(heap: &H, wasm_module: &mut wasmgen
         Box::new([wasmgen::ValType::I32]),
     );
     let (_, string_alloc_funcidx) = wasm_module.register_func(&wasm_functype);
+    wasm_module.set_func_name(string_alloc_funcidx, "allocate_string".to_string());
     let mut code_builder = wasmgen::CodeBuilder::new(wasm_functype);
     {
         let (locals_builder, expr_builder) = code_builder.split();
@@ -399,7 +663,18 @@ fn encode_heap_alloc_exports<H: HeapManager>(heap: &H, wasm_module: &mut wasmgen
     wasm_module.export_func(string_alloc_funcidx, "allocate_string".to_string());
 }
 
-#[cfg(feature = "wasmtest")]
+#[cfg(any(test, feature = "wasmtest"))]
 pub fn wasmtest<C: wasm_test_harness::TestContext>(c: &mut C) {
     gc::cheney::wasmtest::wasmtest(c);
+    gc::marksweep::wasmtest::wasmtest(c);
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn wasmtest() {
+        let mut ctx = wasm_test_harness::NativeContext::new();
+        super::wasmtest(&mut ctx);
+        assert!(ctx.test_count() > 0);
+    }
 }
@@ -170,6 +170,48 @@ pub fn block<F: FnOnce(&mut MutContext, &[wasmgen::LocalIdx], &mut ExprBuilder)>
     }
 }
 
+// Encodes a loop, abstracting over the issues relating to lack of multi-value support by spawning new locals if necessary
+// Branches to a loop jump to its beginning and carry no values, so unlike block() there are no landing locals to pass to the inner encoder
+// net wasm stack [] -> [valtypes...]
+pub fn loop_<F: FnOnce(&mut MutContext, &mut ExprBuilder)>(
+    valtypes: &[ValType],
+    use_multi_value: bool,
+    mutctx: &mut MutContext,
+    expr_builder: &mut ExprBuilder,
+    inner_encoder: F,
+) {
+    if use_multi_value || valtypes.len() <= 1 {
+        // net wasm stack [] -> [valtypes...]
+        expr_builder.loop_(valtypes);
+        {
+            // net wasm stack [] -> [valtypes...]
+            inner_encoder(mutctx, expr_builder);
+        }
+        expr_builder.end();
+    } else {
+        // we don't have multi-value enabled, but we have more than one value.  The last value (deepest in the stack) is left onto the stack, but everything else goes into locals.
+        let (rest, last) = valtypes.split_at(valtypes.len() - 1);
+
+        // make temporary variables for them
+        mutctx.with_scratches(rest, |mutctx, tmp_locals| {
+            expr_builder.loop_(last);
+            {
+                // net wasm stack [] -> [valtypes...]
+                inner_encoder(mutctx, expr_builder);
+                // net wasm stack [valtypes...] -> [last]
+                tmp_locals.iter().copied().for_each(|localidx| {
+                    expr_builder.local_set(localidx);
+                });
+            }
+            expr_builder.end();
+            // net wasm stack [last] -> [valtypes...]
+            tmp_locals.iter().copied().rev().for_each(|localidx| {
+                expr_builder.local_get(localidx);
+            });
+        });
+    }
+}
+
 pub fn break_(
     landing_idx: usize,
     landing_ctx: &[wasmgen::LocalIdx],
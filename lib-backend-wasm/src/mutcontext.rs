@@ -32,6 +32,7 @@ pub struct MutContext<'a, 'b> {
     // information for calculating and encoding Break exprs
     ir_landings: Vec<(usize, ir::VarType, Box<[wasmgen::LocalIdx]>)>, // first item of the pair is the landing index (1-based), can be equal to (but no more than) wasm_landing_count
     wasm_landing_count: usize,
+    tail_call_landing: Option<usize>, // landing index (1-based, like ir_landings) of the loop around the function body, used for self tail calls
     // Global for whole program
     module_wrapper: ModuleEncodeWrapper<'b>,
     // will also include function indices
@@ -53,6 +54,7 @@ impl<'a, 'b> MutContext<'a, 'b> {
             named_local_map: (0..num_locals).collect(),
             ir_landings: Vec::new(),
             wasm_landing_count: 0,
+            tail_call_landing: None,
             module_wrapper: module_wrapper,
         }
     }
@@ -264,6 +266,21 @@ impl<'a, 'b> MutContext<'a, 'b> {
                 self.wasm_local_map.len()
             })]
     }
+    /**
+     * Records the source name of the given named local in the wasm name section.
+     * If the local is encoded as several wasm locals, they are suffixed with ".0", ".1", etc.
+     */
+    pub fn name_local(&mut self, named_ir_localidx: usize, name: &str) {
+        let (wasm_locals, scratch) = self.named_wasm_local_slice_and_scratch(named_ir_localidx);
+        match wasm_locals {
+            [localidx] => scratch.add_name(*localidx, name),
+            _ => {
+                for (i, localidx) in wasm_locals.iter().enumerate() {
+                    scratch.add_name(*localidx, &format!("{}.{}", name, i));
+                }
+            }
+        }
+    }
     // Same as wasm_local_slice() and scratch_mut() combined, but plays nice with the lifetime checker.
     pub fn named_wasm_local_slice_and_scratch(
         &mut self,
@@ -377,6 +394,30 @@ impl<'a, 'b> MutContext<'a, 'b> {
         )
     }
 
+    /**
+     * Adds a non-landable landing point for the loop around the whole function body.
+     * Self tail calls branch to this loop instead of making a call.
+     */
+    pub fn with_tail_call_landing<R, F: FnOnce(&mut MutContext<'a, 'b>) -> R>(
+        &mut self,
+        f: F,
+    ) -> R {
+        self.wasm_landing_count += 1;
+        let old_tail_call_landing = self.tail_call_landing.replace(self.wasm_landing_count);
+        let result = f(self);
+        self.tail_call_landing = old_tail_call_landing;
+        self.wasm_landing_count -= 1;
+        result
+    }
+    /**
+     * Gets the wasm landing index (relative to the current code) of the loop around the function body,
+     * or None if the function body is not wrapped in such a loop.
+     */
+    pub fn get_tail_call_landing(&self) -> Option<usize> {
+        self.tail_call_landing
+            .map(|wasm_abs_landing| self.wasm_landing_count - wasm_abs_landing)
+    }
+
     pub fn module_wrapper(&mut self) -> &mut ModuleEncodeWrapper<'b> {
         &mut self.module_wrapper
     }
@@ -1,6 +1,7 @@
 use projstd::searchablevec::SearchableVec;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::vec::Vec;
 
 /*
@@ -27,6 +28,8 @@ pub struct TraverseResult {
     // (note: we can know the signature from the funcidx)
     pub thunk_sv: SearchableVec<Box<[ir::OverloadEntry]>>,
     pub appl_location_sv: SearchableVec<ir::SourceLocation>,
+    pub typed_trampoline_callers: HashSet<ir::FuncIdx>, // functions that do not return Any, but might leave a pending tail call (see encode_return())
+    tail_callees: Vec<ir::FuncIdx>, // callees of the DirectAppls in tail position in the current function (see pre_traverse_func())
 }
 
 /*
@@ -34,26 +37,76 @@ Traverses the IR to:
 - put all string constants in a string pool, and encodes the static data buffer
 - put all overload sets (thunks) in a SearchableVec
 - extract all SourceLocations in Appls into a SearchableVec
+- if `use_trampoline`, add a thunk for each function that is directly tail called by another function (see encode_return()),
+  and find the functions that might leave a pending tail call even though they do not return Any
 */
 pub fn pre_traverse_funcs(
     funcs: &[ir::Func],
+    num_imports: usize,
     repl_sl: ir::SourceLocation,
     mut repl_funcidx_start: usize,
+    use_trampoline: bool,
 ) -> TraverseResult {
     let mut res = TraverseResult::default();
     res.appl_location_sv.insert(repl_sl);
     repl_funcidx_start = std::cmp::min(repl_funcidx_start, funcs.len());
-    for func in &funcs[..repl_funcidx_start] {
-        pre_traverse_func::<false>(func, &mut res);
-    }
-    for func in &funcs[repl_funcidx_start..] {
-        pre_traverse_func::<true>(func, &mut res);
+    for (i, func) in funcs.iter().enumerate() {
+        let funcidx: ir::FuncIdx = num_imports + i;
+        if i < repl_funcidx_start {
+            pre_traverse_func::<false>(funcidx, func, funcs, num_imports, use_trampoline, &mut res);
+        } else {
+            pre_traverse_func::<true>(funcidx, func, funcs, num_imports, use_trampoline, &mut res);
+        }
     }
     res
 }
 
-fn pre_traverse_func<const IS_REPL: bool>(func: &ir::Func, res: &mut TraverseResult) {
+fn pre_traverse_func<const IS_REPL: bool>(
+    funcidx: ir::FuncIdx,
+    func: &ir::Func,
+    funcs: &[ir::Func],
+    num_imports: usize,
+    use_trampoline: bool,
+    res: &mut TraverseResult,
+) {
     pre_traverse_expr::<IS_REPL>(&func.expr, res);
+
+    // Without real tail calls, a direct tail call to another function with the same return type
+    // is left as a pending call to the trampoline, which can only make indirect calls, so the callee needs a thunk.
+    // If that return type is not Any, the call sites of this function have to look for the pending call too.
+    // (Imports cannot make tail calls back into the program, so they are called normally, and self tail calls are encoded as a branch instead.)
+    for callee in std::mem::take(&mut res.tail_callees) {
+        if use_trampoline
+            && func.result.is_some()
+            && callee != funcidx
+            && callee >= num_imports
+            && funcs[callee - num_imports].result == func.result
+        {
+            res.thunk_sv.insert(Box::new([ir::OverloadEntry {
+                funcidx: callee,
+                has_closure_param: false,
+            }]) as Box<[ir::OverloadEntry]>);
+            if func.result != Some(ir::VarType::Any) {
+                res.typed_trampoline_callers.insert(funcidx);
+            }
+        }
+    }
+}
+
+// Adds the callees of the DirectAppls that would be tail calls if `expr` is returned (this must match encode_return()).
+fn pre_traverse_tail_callees(expr: &ir::Expr, res: &mut TraverseResult) {
+    match &expr.kind {
+        ir::ExprKind::DirectAppl { funcidx, args: _ } => res.tail_callees.push(*funcidx),
+        ir::ExprKind::Conditional {
+            cond: _,
+            true_expr,
+            false_expr,
+        } => {
+            pre_traverse_tail_callees(true_expr, res);
+            pre_traverse_tail_callees(false_expr, res);
+        }
+        _ => {}
+    }
 }
 
 fn pre_traverse_exprs<const IS_REPL: bool>(exprs: &[ir::Expr], res: &mut TraverseResult) {
@@ -69,8 +122,10 @@ fn pre_traverse_expr<const IS_REPL: bool>(expr: &ir::Expr, res: &mut TraverseRes
 fn pre_traverse_expr_kind<const IS_REPL: bool>(expr_kind: &ir::ExprKind, res: &mut TraverseResult) {
     match expr_kind {
         ir::ExprKind::PrimUndefined
+        | ir::ExprKind::PrimNull
         | ir::ExprKind::PrimNumber { val: _ }
         | ir::ExprKind::PrimBoolean { val: _ }
+        | ir::ExprKind::PrimArray
         | ir::ExprKind::PrimStructT { typeidx: _ } => {}
         ir::ExprKind::PrimString { val } => {
             if !IS_REPL {
@@ -94,6 +149,24 @@ fn pre_traverse_expr_kind<const IS_REPL: bool>(expr_kind: &ir::ExprKind, res: &m
         }
         ir::ExprKind::VarName { source: _ } => {}
         ir::ExprKind::PrimAppl { prim_inst: _, args } => pre_traverse_exprs::<IS_REPL>(args, res),
+        ir::ExprKind::ArrayLoad {
+            array,
+            index,
+            location: _,
+        } => {
+            pre_traverse_expr::<IS_REPL>(array, res);
+            pre_traverse_expr::<IS_REPL>(index, res);
+        }
+        ir::ExprKind::ArrayStore {
+            array,
+            index,
+            expr,
+            location: _,
+        } => {
+            pre_traverse_expr::<IS_REPL>(array, res);
+            pre_traverse_expr::<IS_REPL>(index, res);
+            pre_traverse_expr::<IS_REPL>(expr, res);
+        }
         ir::ExprKind::Appl {
             func,
             args,
@@ -117,6 +190,7 @@ fn pre_traverse_expr_kind<const IS_REPL: bool>(expr_kind: &ir::ExprKind, res: &m
         }
         ir::ExprKind::Declaration {
             local: _,
+            name: _,
             init,
             contained_expr,
         } => {
@@ -125,13 +199,17 @@ fn pre_traverse_expr_kind<const IS_REPL: bool>(expr_kind: &ir::ExprKind, res: &m
             }
             pre_traverse_expr::<IS_REPL>(contained_expr, res);
         }
+        ir::ExprKind::Return { expr } => {
+            pre_traverse_tail_callees(expr, res);
+            pre_traverse_expr::<IS_REPL>(expr, res);
+        }
         ir::ExprKind::Assign { target: _, expr }
-        | ir::ExprKind::Return { expr }
         | ir::ExprKind::Break {
             num_frames: _,
             expr,
         }
-        | ir::ExprKind::Block { expr } => pre_traverse_expr::<IS_REPL>(expr, res),
+        | ir::ExprKind::Block { expr }
+        | ir::ExprKind::Loop { expr } => pre_traverse_expr::<IS_REPL>(expr, res),
         ir::ExprKind::Sequence { content } => {
             pre_traverse_exprs::<IS_REPL>(content, res);
         }
@@ -0,0 +1,178 @@
+/**
+ * Builders for ESTree JSON nodes, which is the input that the host gives to the compiler.
+ * This file is also used by the command-line tests of source-compiler, so it must only depend on serde_json.
+ */
+use serde_json::json;
+use serde_json::Value;
+
+// all nodes get the same dummy location
+fn node(ty: &str, mut fields: Value) -> Value {
+    fields["type"] = json!(ty);
+    fields["loc"] = json!({"start": {"line": 1, "column": 0}, "end": {"line": 1, "column": 1}});
+    fields
+}
+pub fn num(value: f64) -> Value {
+    node("Literal", json!({"value": value, "raw": value.to_string()}))
+}
+pub fn string(value: &str) -> Value {
+    node(
+        "Literal",
+        json!({"value": value, "raw": json!(value).to_string()}),
+    )
+}
+pub fn boolean(value: bool) -> Value {
+    node("Literal", json!({"value": value, "raw": value.to_string()}))
+}
+pub fn null() -> Value {
+    node("Literal", json!({"value": null, "raw": "null"}))
+}
+pub fn id(name: &str) -> Value {
+    node("Identifier", json!({ "name": name }))
+}
+pub fn binary(operator: &str, left: Value, right: Value) -> Value {
+    node(
+        "BinaryExpression",
+        json!({"operator": operator, "left": left, "right": right}),
+    )
+}
+pub fn logical(operator: &str, left: Value, right: Value) -> Value {
+    node(
+        "LogicalExpression",
+        json!({"operator": operator, "left": left, "right": right}),
+    )
+}
+pub fn unary(operator: &str, argument: Value) -> Value {
+    node(
+        "UnaryExpression",
+        json!({"operator": operator, "prefix": true, "argument": argument}),
+    )
+}
+pub fn call(callee: Value, arguments: Vec<Value>) -> Value {
+    node(
+        "CallExpression",
+        json!({"callee": callee, "arguments": arguments, "optional": false}),
+    )
+}
+pub fn assign(target: Value, value: Value) -> Value {
+    node(
+        "AssignmentExpression",
+        json!({"operator": "=", "left": target, "right": value}),
+    )
+}
+pub fn conditional(test: Value, consequent: Value, alternate: Value) -> Value {
+    node(
+        "ConditionalExpression",
+        json!({"test": test, "consequent": consequent, "alternate": alternate}),
+    )
+}
+pub fn array(elements: Vec<Value>) -> Value {
+    node("ArrayExpression", json!({ "elements": elements }))
+}
+pub fn member(object: Value, index: Value) -> Value {
+    node(
+        "MemberExpression",
+        json!({"object": object, "property": index, "computed": true, "optional": false}),
+    )
+}
+pub fn arrow(params: &[&str], body: Value) -> Value {
+    let expression: bool = body["type"] != "BlockStatement";
+    node(
+        "ArrowFunctionExpression",
+        json!({
+            "params": params.iter().map(|p| id(p)).collect::<Vec<_>>(),
+            "body": body,
+            "expression": expression,
+            "generator": false,
+        }),
+    )
+}
+pub fn expr_stmt(expression: Value) -> Value {
+    node("ExpressionStatement", json!({ "expression": expression }))
+}
+fn declaration(kind: &str, name: &str, init: Value) -> Value {
+    node(
+        "VariableDeclaration",
+        json!({
+            "kind": kind,
+            "declarations": [node("VariableDeclarator", json!({"id": id(name), "init": init}))],
+        }),
+    )
+}
+pub fn let_(name: &str, init: Value) -> Value {
+    declaration("let", name, init)
+}
+pub fn const_(name: &str, init: Value) -> Value {
+    declaration("const", name, init)
+}
+pub fn block(body: Vec<Value>) -> Value {
+    node("BlockStatement", json!({ "body": body }))
+}
+pub fn while_(test: Value, body: Vec<Value>) -> Value {
+    node("WhileStatement", json!({"test": test, "body": block(body)}))
+}
+pub fn for_(init: Value, test: Value, update: Value, body: Vec<Value>) -> Value {
+    node(
+        "ForStatement",
+        json!({"init": init, "test": test, "update": update, "body": block(body)}),
+    )
+}
+pub fn if_(test: Value, consequent: Vec<Value>, alternate: Vec<Value>) -> Value {
+    node(
+        "IfStatement",
+        json!({"test": test, "consequent": block(consequent), "alternate": block(alternate)}),
+    )
+}
+pub fn break_() -> Value {
+    node("BreakStatement", json!({ "label": null }))
+}
+pub fn continue_() -> Value {
+    node("ContinueStatement", json!({ "label": null }))
+}
+pub fn return_(argument: Value) -> Value {
+    node("ReturnStatement", json!({ "argument": argument }))
+}
+pub fn function(name: &str, params: &[&str], body: Vec<Value>) -> Value {
+    node(
+        "FunctionDeclaration",
+        json!({
+            "id": id(name),
+            "params": params.iter().map(|p| id(p)).collect::<Vec<_>>(),
+            "body": block(body),
+            "generator": false,
+            "expression": false,
+        }),
+    )
+}
+pub fn import_(names: &[&str], source: &str) -> Value {
+    let specifiers: Vec<Value> = names
+        .iter()
+        .map(|name| {
+            node(
+                "ImportSpecifier",
+                json!({"local": id(name), "imported": id(name)}),
+            )
+        })
+        .collect();
+    node(
+        "ImportDeclaration",
+        json!({"specifiers": specifiers, "source": string(source)}),
+    )
+}
+pub fn export_(names: &[&str]) -> Value {
+    let specifiers: Vec<Value> = names
+        .iter()
+        .map(|name| {
+            node(
+                "ExportSpecifier",
+                json!({"local": id(name), "exported": id(name)}),
+            )
+        })
+        .collect();
+    node(
+        "ExportNamedDeclaration",
+        json!({"declaration": null, "specifiers": specifiers, "source": null}),
+    )
+}
+pub fn program(body: Vec<Value>) -> Value {
+    node("Program", json!({"body": body, "sourceType": "module"}))
+}
@@ -0,0 +1,293 @@
+/**
+ * Helpers for the end-to-end tests.
+ * Programs are built as ESTree JSON (the same input that the host gives to the compiler),
+ * compiled with the frontend, optimizer and this backend, and then run with the in-tree wasm interpreter.
+ * The IR is also run with the reference interpreter (before and after optimisation), which must give the same result.
+ */
+use projstd::log;
+use serde_json::json;
+use serde_json::Value;
+use wasmgen::WasmSerialize;
+
+mod estree;
+#[allow(unused_imports)] // some test files do not build ESTree programs
+pub use estree::*;
+
+#[derive(Copy, Clone)]
+struct TestLogger {}
+impl log::Logger for TestLogger {
+    fn log<L: log::Loggable>(&self, content: L) {
+        eprintln!(
+            "{}: {}: {}",
+            content.severity(),
+            content.location(),
+            content.message()
+        );
+    }
+}
+
+async fn fetch_dep(_name: String) -> Option<String> {
+    None
+}
+
+/**
+ * Compiles the given library modules, which are fetched with `fetch`.
+ */
+pub fn compile_library<Fut: 'static + std::future::Future<Output = Option<String>>>(
+    names: &[&str],
+    fetch: fn(String) -> Fut,
+) -> frontend_estree::Library {
+    let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    futures::executor::block_on(frontend_estree::compile_library(
+        &names,
+        fetch,
+        TestLogger {},
+    ))
+    .expect("library failed")
+}
+
+/**
+ * Runs the frontend on a program whose imports are fetched with `fetch`, optionally linking the given library.
+ */
+pub fn run_frontend_with<Fut: 'static + std::future::Future<Output = Option<String>>>(
+    estree: &Value,
+    fetch: fn(String) -> Fut,
+    opt_library: Option<&frontend_estree::Library>,
+) -> ir::Program {
+    match opt_library {
+        Some(library) => futures::executor::block_on(frontend_estree::run_frontend_with_library(
+            estree.to_string(),
+            fetch,
+            library,
+            TestLogger {},
+        )),
+        None => futures::executor::block_on(frontend_estree::run_frontend(
+            estree.to_string(),
+            fetch,
+            TestLogger {},
+        )),
+    }
+    .expect("frontend failed")
+    .1
+}
+
+/**
+ * Runs the frontend on a program without imports, and also returns the context for compiling REPL input later.
+ */
+pub fn run_frontend_for_repl(estree: &Value) -> (frontend_estree::ReplContext, ir::Program) {
+    futures::executor::block_on(frontend_estree::run_frontend(
+        estree.to_string(),
+        fetch_dep,
+        TestLogger {},
+    ))
+    .expect("frontend failed")
+}
+
+/**
+ * Appends the REPL input to the program (and updates the context), like `compile_repl()` of the source compiler.
+ * Returns the index (into `funcs`) of the first new function.
+ */
+pub fn run_frontend_repl(
+    estree: &Value,
+    repl_ctx: &mut frontend_estree::ReplContext,
+    ir_program: &mut ir::Program,
+) -> usize {
+    let (new_repl_ctx, new_funcidx_start) = frontend_estree::run_frontend_repl(
+        estree.to_string(),
+        std::mem::take(repl_ctx),
+        ir_program,
+        TestLogger {},
+    )
+    .expect("frontend failed");
+    *repl_ctx = new_repl_ctx;
+    new_funcidx_start
+}
+
+fn run_frontend(estree: &Value) -> ir::Program {
+    futures::executor::block_on(frontend_estree::run_frontend(
+        estree.to_string(),
+        fetch_dep,
+        TestLogger {},
+    ))
+    .expect("frontend failed")
+    .1
+}
+
+/**
+ * Compiles an ESTree program to a serialized wasm module (with the unused items removed after optimisation).
+ * The IR is also printed and parsed back before it is given to the backend, to check that the textual IR round-trips.
+ */
+pub fn compile(estree: &Value, options: backend_wasm::Options) -> Vec<u8> {
+    let ir_program = ir::opt::remove_unused(
+        ir::opt::optimize_all(run_frontend(estree), 0),
+        &ir::opt::KeepAlive::new(),
+    )
+    .0;
+    let text: String = ir::text::print(&ir_program);
+    let reparsed: ir::Program = ir::text::parse(&text).unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(ir::text::print(&reparsed), text);
+    compile_ir(&reparsed, options)
+}
+
+/**
+ * Runs the IR program with the reference interpreter, and returns the result in the same format as `run()`.
+ */
+pub fn interpret(ir_program: &ir::Program) -> String {
+    let mut host = |import: &ir::Import, _: &[ir_interp::Value]| -> ir_interp::Value {
+        panic!(
+            "unexpected import: {}.{}",
+            import.module_name, import.entity_name
+        )
+    };
+    match ir_interp::run(ir_program, &mut host, ir_interp::Options::new()) {
+        Ok(value) => value.to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+/**
+ * Compiles an IR program (that has already been optimised) to a serialized wasm module.
+ */
+pub fn compile_ir(ir_program: &ir::Program, options: backend_wasm::Options) -> Vec<u8> {
+    let wasm_module = backend_wasm::run_backend(ir_program, usize::MAX, options);
+    let mut receiver = Vec::<u8>::new();
+    wasm_module.wasm_serialize(&mut receiver);
+    receiver
+}
+
+// Provides the imports of the compiled program; "core.error" aborts the program with its error code.
+fn host(
+    module_name: &str,
+    entity_name: &str,
+    args: &[wasm_interp::Val],
+    _memory: &mut [u8],
+) -> Result<Vec<wasm_interp::Val>, wasm_interp::Trap> {
+    match (module_name, entity_name, args.first()) {
+        ("core", "error", Some(wasm_interp::Val::I32(code))) => {
+            Err(wasm_interp::Trap::Host(format!("error {}", code)))
+        }
+        _ => panic!("unexpected import: {}.{}", module_name, entity_name),
+    }
+}
+
+fn read_u32(memory: &[u8], offset: usize) -> u32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(&memory[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
+
+// Writes the Any at the given offset as JSON (formatted like JSON.stringify() would, so that it matches the IR interpreter).
+fn write_any(memory: &[u8], offset: usize, out: &mut String) {
+    let tag: u32 = read_u32(memory, offset);
+    let data: usize = read_u32(memory, offset + 4) as usize;
+    match tag {
+        1 => out.push_str("\"undefined\""),
+        2 => {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(&memory[offset + 4..offset + 12]);
+            let val: f64 = f64::from_le_bytes(buf);
+            if val == 0.0 {
+                out.push('0'); // JSON has no negative zero
+            } else if val.is_finite() {
+                out.push_str(&val.to_string());
+            } else {
+                out.push_str("null");
+            }
+        }
+        3 => out.push_str(if data != 0 { "true" } else { "false" }),
+        4 => {
+            let len: usize = read_u32(memory, data) as usize;
+            let s = std::str::from_utf8(&memory[data + 4..data + 4 + len]).unwrap();
+            out.push_str(&json!(s).to_string());
+        }
+        5 => out.push_str("\"function\""),
+        6 => out.push_str("null"),
+        7 => {
+            let len: usize = read_u32(memory, data) as usize;
+            let elements: usize = read_u32(memory, data + 8) as usize;
+            out.push('[');
+            for i in 0..len {
+                if i != 0 {
+                    out.push(',');
+                }
+                write_any(memory, elements + 12 * i, out);
+            }
+            out.push(']');
+        }
+        8 => {
+            // struct#0, which the frontend uses for pairs
+            out.push_str("{\"head\":");
+            write_any(memory, data, out);
+            out.push_str(",\"tail\":");
+            write_any(memory, data + 12, out);
+            out.push('}');
+        }
+        _ => out.push_str(&format!("\"tag {}\"", tag)),
+    }
+}
+
+/**
+ * Runs main() of the given wasm module with the wasm interpreter, and returns the result as JSON,
+ * or "error {code}" if the program raised a runtime error.
+ * The result is read from the end of the stack, which is where the host expects it.
+ */
+pub fn run(wasm: &[u8], stack_size: u32) -> String {
+    let module = wasm_interp::Module::parse(wasm).unwrap_or_else(|e| panic!("{}", e));
+    let mut instance = wasm_interp::Instance::new(module, host, wasm_interp::Options::new())
+        .unwrap_or_else(|e| panic!("{}", e));
+    match instance.invoke("main", &[]) {
+        Ok(_) => {
+            let mut out = String::new();
+            write_any(
+                instance.memory(),
+                stack_size as usize * 65536 - 12,
+                &mut out,
+            );
+            out
+        }
+        Err(wasm_interp::Error::Trap(wasm_interp::Trap::Host(message))) => message,
+        Err(e) => format!("failed: {}", e),
+    }
+}
+
+/**
+ * Compiles and runs the program with the given options, and checks the printed result.
+ */
+pub fn check(estree: &Value, options: backend_wasm::Options, expected: &str) {
+    check_wasm(&compile(estree, options), options, expected);
+}
+
+/**
+ * Runs the program with the reference interpreter, both before and after optimisation (at every optimisation level)
+ * and with the unused items removed, and checks the printed result.
+ * This does not depend on the backend options, so it only needs to be done once per program.
+ */
+pub fn check_interpreter(estree: &Value, expected: &str) {
+    assert_eq!(interpret(&run_frontend(estree)), expected, "unoptimised IR");
+    for level in 0..=ir::opt::MAX_OPT_LEVEL {
+        assert_eq!(
+            interpret(&ir::opt::Pipeline::for_level(level).run(run_frontend(estree), 0)),
+            expected,
+            "IR optimised at level {}",
+            level
+        );
+    }
+    assert_eq!(
+        interpret(&ir::opt::remove_unused(run_frontend(estree), &ir::opt::KeepAlive::new()).0),
+        expected,
+        "unoptimised IR without the unused items"
+    );
+}
+
+/**
+ * Like `check()`, but for a program written in the textual IR syntax (which skips the frontend and optimiser).
+ */
+pub fn check_ir(text: &str, options: backend_wasm::Options, expected: &str) {
+    let ir_program: ir::Program = ir::text::parse(text).unwrap_or_else(|e| panic!("{}", e));
+    assert_eq!(interpret(&ir_program), expected, "IR interpreter");
+    check_wasm(&compile_ir(&ir_program, options), options, expected);
+}
+
+fn check_wasm(wasm: &[u8], options: backend_wasm::Options, expected: &str) {
+    assert_eq!(run(wasm, options.get_stack_size()), expected);
+}
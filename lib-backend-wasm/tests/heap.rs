@@ -0,0 +1,382 @@
+/**
+ * End-to-end tests that run the same programs with every heap manager.
+ */
+#[allow(dead_code)] // each test file only uses some of the helpers
+mod common;
+
+use backend_wasm::{Heap, Options};
+use common::*;
+
+const HEAPS: [Heap; 3] = [Heap::Cheney, Heap::MarkSweep, Heap::Leaky];
+
+fn check_all_heaps(estree: &serde_json::Value, expected: &str) {
+    check_interpreter(estree, expected);
+    for heap in HEAPS.iter() {
+        check(estree, Options::new().heap(*heap), expected);
+    }
+}
+
+#[test]
+fn recursion() {
+    // function fact(n) { return n <= 1 ? 1 : n * fact(n - 1); }
+    // fact(10);
+    let estree = program(vec![
+        function(
+            "fact",
+            &["n"],
+            vec![return_(conditional(
+                binary("<=", id("n"), num(1.0)),
+                num(1.0),
+                binary(
+                    "*",
+                    id("n"),
+                    call(id("fact"), vec![binary("-", id("n"), num(1.0))]),
+                ),
+            ))],
+        ),
+        expr_stmt(call(id("fact"), vec![num(10.0)])),
+    ]);
+    check_all_heaps(&estree, "3628800");
+}
+
+#[test]
+fn closures_and_strings() {
+    // function make_adder(x) { return y => x + y; }
+    // const add = make_adder("a");
+    // add("b") + add("c");
+    let estree = program(vec![
+        function(
+            "make_adder",
+            &["x"],
+            vec![return_(arrow(&["y"], binary("+", id("x"), id("y"))))],
+        ),
+        const_("add", call(id("make_adder"), vec![string("a")])),
+        expr_stmt(binary(
+            "+",
+            call(id("add"), vec![string("b")]),
+            call(id("add"), vec![string("c")]),
+        )),
+    ]);
+    check_all_heaps(&estree, "\"abac\"");
+}
+
+#[test]
+fn many_allocations() {
+    // Allocates much more than the initial heap size, keeping a long list alive
+    // while most of the other allocations become garbage.
+    // let xs = null;
+    // let i = 0;
+    // while (i < 200000) { const tmp = [i, i, i]; xs = pair(tmp[1], xs); i = i + 1; }
+    // let sum = 0;
+    // while (!is_null(xs)) { sum = sum + head(xs); xs = tail(xs); }
+    // sum;
+    let estree = program(vec![
+        let_("xs", null()),
+        let_("i", num(0.0)),
+        while_(
+            binary("<", id("i"), num(200000.0)),
+            vec![
+                const_("tmp", array(vec![id("i"), id("i"), id("i")])),
+                expr_stmt(assign(
+                    id("xs"),
+                    call(id("pair"), vec![member(id("tmp"), num(1.0)), id("xs")]),
+                )),
+                expr_stmt(assign(id("i"), binary("+", id("i"), num(1.0)))),
+            ],
+        ),
+        let_("sum", num(0.0)),
+        while_(
+            unary("!", call(id("is_null"), vec![id("xs")])),
+            vec![
+                expr_stmt(assign(
+                    id("sum"),
+                    binary("+", id("sum"), call(id("head"), vec![id("xs")])),
+                )),
+                expr_stmt(assign(id("xs"), call(id("tail"), vec![id("xs")]))),
+            ],
+        ),
+        expr_stmt(id("sum")),
+    ]);
+    check_all_heaps(&estree, "19999900000");
+}
+
+#[test]
+fn growing_array() {
+    // const a = [];
+    // let i = 0;
+    // while (i < 1000) { a[i] = pair(i, "x"); i = i + 1; }
+    // [array_length(a), head(a[999]), tail(a[0])];
+    let estree = program(vec![
+        const_("a", array(vec![])),
+        let_("i", num(0.0)),
+        while_(
+            binary("<", id("i"), num(1000.0)),
+            vec![
+                expr_stmt(assign(
+                    member(id("a"), id("i")),
+                    call(id("pair"), vec![id("i"), string("x")]),
+                )),
+                expr_stmt(assign(id("i"), binary("+", id("i"), num(1.0)))),
+            ],
+        ),
+        expr_stmt(array(vec![
+            call(id("array_length"), vec![id("a")]),
+            call(id("head"), vec![member(id("a"), num(999.0))]),
+            call(id("tail"), vec![member(id("a"), num(0.0))]),
+        ])),
+    ]);
+    check_all_heaps(&estree, "[1000,999,\"x\"]");
+}
+
+#[test]
+fn growing_arrays_with_garbage() {
+    // Arrays that grow while collections are happening, so the storage of an array is owned by another array object.
+    // const a = [];
+    // let i = 0;
+    // while (i < 100000) { a[i] = [i]; i = i + 1; }
+    // let sum = 0;
+    // i = 0;
+    // while (i < 100000) { sum = sum + a[i][0]; i = i + 1; }
+    // sum;
+    let estree = program(vec![
+        const_("a", array(vec![])),
+        let_("i", num(0.0)),
+        while_(
+            binary("<", id("i"), num(100000.0)),
+            vec![
+                expr_stmt(assign(member(id("a"), id("i")), array(vec![id("i")]))),
+                expr_stmt(assign(id("i"), binary("+", id("i"), num(1.0)))),
+            ],
+        ),
+        let_("sum", num(0.0)),
+        expr_stmt(assign(id("i"), num(0.0))),
+        while_(
+            binary("<", id("i"), num(100000.0)),
+            vec![
+                expr_stmt(assign(
+                    id("sum"),
+                    binary("+", id("sum"), member(member(id("a"), id("i")), num(0.0))),
+                )),
+                expr_stmt(assign(id("i"), binary("+", id("i"), num(1.0)))),
+            ],
+        ),
+        expr_stmt(id("sum")),
+    ]);
+    check_all_heaps(&estree, "4999950000");
+}
+
+#[test]
+fn strings_survive_collections() {
+    // Keeps many strings alive across collections, both strings built at run time and literals from global data.
+    // let s = "a";
+    // let xs = null;
+    // let i = 0;
+    // while (i < 30000) { s = s === "a" ? "b" : "a"; xs = pair(s + "c", xs); i = i + 1; }
+    // let count = 0;
+    // while (!is_null(xs)) { count = count + (head(xs) === s + "c" ? 1 : 0); s = s === "a" ? "b" : "a"; xs = tail(xs); }
+    // count;
+    let toggle = || {
+        expr_stmt(assign(
+            id("s"),
+            conditional(
+                binary("===", id("s"), string("a")),
+                string("b"),
+                string("a"),
+            ),
+        ))
+    };
+    let estree = program(vec![
+        let_("s", string("a")),
+        let_("xs", null()),
+        let_("i", num(0.0)),
+        while_(
+            binary("<", id("i"), num(30000.0)),
+            vec![
+                toggle(),
+                expr_stmt(assign(
+                    id("xs"),
+                    call(
+                        id("pair"),
+                        vec![binary("+", id("s"), string("c")), id("xs")],
+                    ),
+                )),
+                expr_stmt(assign(id("i"), binary("+", id("i"), num(1.0)))),
+            ],
+        ),
+        let_("count", num(0.0)),
+        while_(
+            unary("!", call(id("is_null"), vec![id("xs")])),
+            vec![
+                expr_stmt(assign(
+                    id("count"),
+                    binary(
+                        "+",
+                        id("count"),
+                        conditional(
+                            binary(
+                                "===",
+                                call(id("head"), vec![id("xs")]),
+                                binary("+", id("s"), string("c")),
+                            ),
+                            num(1.0),
+                            num(0.0),
+                        ),
+                    ),
+                )),
+                toggle(),
+                expr_stmt(assign(id("xs"), call(id("tail"), vec![id("xs")]))),
+            ],
+        ),
+        expr_stmt(id("count")),
+    ]);
+    check_all_heaps(&estree, "30000");
+}
+
+#[test]
+fn for_loop_closures() {
+    // Each iteration has its own copy of the loop variable, which a closure captures with the value at the end of the iteration.
+    // let fs = null;
+    // for (let i = 0; i < 6; i = i + 1) { fs = pair(() => i, fs); i = i + 1; }
+    // let gs = null;
+    // for (let i = 0; i < 3; i = i + 1) { gs = pair(() => i, gs); }
+    // [head(fs)(), head(tail(fs))(), head(tail(tail(fs)))(), head(gs)(), head(tail(gs))(), head(tail(tail(gs)))()];
+    let nth_call = |list: &str, n: usize| {
+        let mut xs = id(list);
+        for _ in 0..n {
+            xs = call(id("tail"), vec![xs]);
+        }
+        call(call(id("head"), vec![xs]), vec![])
+    };
+    let estree = program(vec![
+        let_("fs", null()),
+        for_(
+            let_("i", num(0.0)),
+            binary("<", id("i"), num(6.0)),
+            assign(id("i"), binary("+", id("i"), num(1.0))),
+            vec![
+                expr_stmt(assign(
+                    id("fs"),
+                    call(id("pair"), vec![arrow(&[], id("i")), id("fs")]),
+                )),
+                expr_stmt(assign(id("i"), binary("+", id("i"), num(1.0)))),
+            ],
+        ),
+        let_("gs", null()),
+        for_(
+            let_("i", num(0.0)),
+            binary("<", id("i"), num(3.0)),
+            assign(id("i"), binary("+", id("i"), num(1.0))),
+            vec![expr_stmt(assign(
+                id("gs"),
+                call(id("pair"), vec![arrow(&[], id("i")), id("gs")]),
+            ))],
+        ),
+        expr_stmt(array(vec![
+            nth_call("fs", 0),
+            nth_call("fs", 1),
+            nth_call("fs", 2),
+            nth_call("gs", 0),
+            nth_call("gs", 1),
+            nth_call("gs", 2),
+        ])),
+    ]);
+    check_all_heaps(&estree, "[5,3,1,2,1,0]");
+}
+
+#[test]
+fn continue_in_for_loop() {
+    // The update still runs after `continue`.
+    // let sum = 0;
+    // for (let i = 0; i < 10; i = i + 1) { if (i % 2 === 0) { continue; } else {} sum = sum + i; }
+    // sum;
+    let estree = program(vec![
+        let_("sum", num(0.0)),
+        for_(
+            let_("i", num(0.0)),
+            binary("<", id("i"), num(10.0)),
+            assign(id("i"), binary("+", id("i"), num(1.0))),
+            vec![
+                if_(
+                    binary("===", binary("%", id("i"), num(2.0)), num(0.0)),
+                    vec![continue_()],
+                    vec![],
+                ),
+                expr_stmt(assign(id("sum"), binary("+", id("sum"), id("i")))),
+            ],
+        ),
+        expr_stmt(id("sum")),
+    ]);
+    check_all_heaps(&estree, "25");
+}
+
+#[test]
+fn break_out_of_nested_loops() {
+    // `break` only leaves the innermost loop.
+    // let count = 0;
+    // for (let i = 0; i < 5; i = i + 1) {
+    //   let j = 0;
+    //   while (j < 5) { if (j === 3) { break; } else {} count = count + 1; j = j + 1; }
+    //   if (i === 3) { break; } else {}
+    // }
+    // count;
+    let estree = program(vec![
+        let_("count", num(0.0)),
+        for_(
+            let_("i", num(0.0)),
+            binary("<", id("i"), num(5.0)),
+            assign(id("i"), binary("+", id("i"), num(1.0))),
+            vec![
+                let_("j", num(0.0)),
+                while_(
+                    binary("<", id("j"), num(5.0)),
+                    vec![
+                        if_(binary("===", id("j"), num(3.0)), vec![break_()], vec![]),
+                        expr_stmt(assign(id("count"), binary("+", id("count"), num(1.0)))),
+                        expr_stmt(assign(id("j"), binary("+", id("j"), num(1.0)))),
+                    ],
+                ),
+                if_(binary("===", id("i"), num(3.0)), vec![break_()], vec![]),
+            ],
+        ),
+        expr_stmt(id("count")),
+    ]);
+    check_all_heaps(&estree, "12");
+}
+
+#[test]
+fn break_in_nested_block() {
+    // `break` and `continue` from inside blocks (with their own variables) within the loop body.
+    // let i = 0;
+    // let sum = 0;
+    // while (true) {
+    //   i = i + 1;
+    //   { const j = i; if (j === 3) { { continue; } } else {} if (j === 5) { { break; } } else {} sum = sum + j; }
+    // }
+    // [i, sum];
+    let estree = program(vec![
+        let_("i", num(0.0)),
+        let_("sum", num(0.0)),
+        while_(
+            boolean(true),
+            vec![
+                expr_stmt(assign(id("i"), binary("+", id("i"), num(1.0)))),
+                block(vec![
+                    const_("j", id("i")),
+                    if_(
+                        binary("===", id("j"), num(3.0)),
+                        vec![block(vec![continue_()])],
+                        vec![],
+                    ),
+                    if_(
+                        binary("===", id("j"), num(5.0)),
+                        vec![block(vec![break_()])],
+                        vec![],
+                    ),
+                    expr_stmt(assign(id("sum"), binary("+", id("sum"), id("j")))),
+                ]),
+            ],
+        ),
+        expr_stmt(array(vec![id("i"), id("sum")])),
+    ]);
+    check_all_heaps(&estree, "[5,7]");
+}
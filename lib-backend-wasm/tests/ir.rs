@@ -0,0 +1,128 @@
+/**
+ * End-to-end tests with hand-written IR, so that the backend can be tested without going through the frontend.
+ */
+#[allow(dead_code)] // each test file only uses some of the helpers
+mod common;
+
+use backend_wasm::Options;
+use common::*;
+
+#[test]
+fn loop_with_local() {
+    // let i = 0; while (i < 10) { i = i + 1; } return i;
+    let text = r#"entry 0
+
+func 0 () -> any
+  (declare:void number
+    (number:number 0.0)
+    (seq:void
+      (loop:undefined
+        (if:undefined
+          (prim:boolean number_lt
+            (var:number (local 0))
+            (number:number 10.0))
+          (seq:void
+            (assign:undefined (local 0)
+              (prim:number number_add
+                (var:number (local 0))
+                (number:number 1.0)))
+            (break:void 0
+              (undefined:undefined)))
+          (undefined:undefined)))
+      (return:void
+        (var:number (local 0)))))
+"#;
+    check_ir(text, Options::new(), "10");
+}
+
+#[test]
+fn struct_and_direct_call() {
+    // A struct with a number and a string field, which is passed to another function that concatenates a string to its second field.
+    let text = r#"struct 0 (number string)
+entry 0
+
+func 0 () -> any
+  (declare:any struct#0
+    (struct:struct#0 0)
+    (seq:any
+      (assign:undefined (local 0 0.0)
+        (number:number 4.0))
+      (assign:undefined (local 0 0.1)
+        (string:string "ab"))
+      (direct_appl:any 1
+        (var:struct#0 (local 0)))))
+
+func 1 (struct#0) -> any
+  (return:void
+    (prim:string string_add
+      (var:string (local 0 0.1))
+      (string:string "c")))
+"#;
+    check_ir(text, Options::new(), "\"abc\"");
+}
+
+#[test]
+fn trap() {
+    let text = r#"entry 0
+
+func 0 () -> any
+  (trap:void @0:1:0-1:5 25)
+"#;
+    check_ir(text, Options::new(), "error 25");
+}
+
+#[test]
+fn name_section() {
+    // Named functions, params and locals should appear in the name section.
+    let text = r#"entry 0
+
+func 0 "answer" () -> any
+  (declare:void number "x" (number:number 21.0)
+    (return:void
+      (direct_appl:number 1
+        (var:number (local 0)))))
+
+func 1 "double" (number "n") -> number
+  (return:void
+    (prim:number number_add
+      (var:number (local 0))
+      (var:number (local 0))))
+"#;
+    check_ir(text, Options::new(), "42");
+
+    let ir_program: ir::Program = ir::text::parse(text).unwrap();
+    let wasm_module = backend_wasm::run_backend(&ir_program, usize::MAX, Options::new());
+    let wat: String = wasm_module.to_wat(wasmgen::WatStyle::Flat);
+    assert!(wat.contains("(func $answer (;"), "{}", wat);
+    assert!(wat.contains("(func $double (;"), "{}", wat);
+    assert!(wat.contains("(func $core.error (;0;)"), "{}", wat);
+
+    // the local names are only in the binary format
+    let wasm: Vec<u8> = compile_ir(&ir_program, Options::new());
+    let contains = |bytes: &[u8]| wasm.windows(bytes.len()).any(|window| window == bytes);
+    assert!(contains(b"\x04name"));
+    assert!(contains(b"\x01x"));
+    assert!(contains(b"\x01n"));
+}
+
+#[test]
+fn decode_round_trip() {
+    // Decoding a generated module and serializing it again should give back the same bytes.
+    let text = r#"entry 0
+
+func 0 "main" () -> any
+  (return:void
+    (prim:string string_add
+      (string:string "a")
+      (string:string "b")))
+"#;
+    let ir_program: ir::Program = ir::text::parse(text).unwrap();
+    for options in &[Options::new(), Options::new().wasm_bulk_memory(true)] {
+        let wasm: Vec<u8> = compile_ir(&ir_program, *options);
+        let wasm_module = wasmgen::WasmModule::decode(&wasm).unwrap();
+        assert!(wasm_module.validate().is_ok());
+        let mut bytes = Vec::<u8>::new();
+        wasmgen::WasmSerialize::wasm_serialize(&wasm_module, &mut bytes);
+        assert!(bytes == wasm);
+    }
+}
@@ -0,0 +1,116 @@
+/**
+ * End-to-end tests for programs that link a precompiled library instead of compiling the modules that they import.
+ */
+#[allow(dead_code)] // each test file only uses some of the helpers
+mod common;
+
+use backend_wasm::Options;
+use common::*;
+
+// import { lib_f } from "ffi_lib";
+// function double(x) { return x + x; }
+// function quadruple(x) { return double(double(x)); }
+// function call_lib_f(x) { return lib_f(x); }
+// export { double, quadruple, call_lib_f };
+fn util() -> serde_json::Value {
+    program(vec![
+        import_(&["lib_f"], "ffi_lib"),
+        function(
+            "double",
+            &["x"],
+            vec![return_(binary("+", id("x"), id("x")))],
+        ),
+        function(
+            "quadruple",
+            &["x"],
+            vec![return_(call(
+                id("double"),
+                vec![call(id("double"), vec![id("x")])],
+            ))],
+        ),
+        function(
+            "call_lib_f",
+            &["x"],
+            vec![return_(call(id("lib_f"), vec![id("x")]))],
+        ),
+        export_(&["double", "quadruple", "call_lib_f"]),
+    ])
+}
+
+// Serves all the modules, for compiling the library and for compiling programs without the library.
+async fn fetch_all(name: String) -> Option<String> {
+    match name.as_str() {
+        "util" => Some(util().to_string()),
+        "ffi_lib" => Some("@SourceImports\nlib_f lib f number number".to_owned()),
+        "ffi_main" => Some("@SourceImports\nmain_g main g number number".to_owned()),
+        _ => None,
+    }
+}
+
+// Serves only the modules that are not in the library, so that linking fails if the library is not used.
+async fn fetch_main(name: String) -> Option<String> {
+    match name.as_str() {
+        "util" | "ffi_lib" => None,
+        _ => fetch_all(name).await,
+    }
+}
+
+#[test]
+fn link_library() {
+    // import { quadruple } from "util";
+    // quadruple(10) + 2;
+    let estree = program(vec![
+        import_(&["quadruple"], "util"),
+        expr_stmt(binary(
+            "+",
+            call(id("quadruple"), vec![num(10.0)]),
+            num(2.0),
+        )),
+    ]);
+    let library = compile_library(&["util"], fetch_all);
+    let linked: ir::Program = run_frontend_with(&estree, fetch_main, Some(&library));
+    // the library is compiled in the same way as if the program had imported it directly
+    assert_eq!(
+        ir::text::print(&linked),
+        ir::text::print(&run_frontend_with(&estree, fetch_all, None))
+    );
+    let optimized: ir::Program = ir::opt::optimize_all(linked, 0);
+    assert_eq!(interpret(&optimized), "42");
+    assert_eq!(
+        run(
+            &compile_ir(&optimized, Options::new()),
+            Options::new().get_stack_size()
+        ),
+        "42"
+    );
+}
+
+#[test]
+fn link_library_with_new_imports() {
+    // import { double } from "util";
+    // import { main_g } from "ffi_main";
+    // const g = main_g;
+    // double(21);
+    // (the program needs an import that the library does not have, so the functions of the library have to be shifted)
+    let estree = program(vec![
+        import_(&["double"], "util"),
+        import_(&["main_g"], "ffi_main"),
+        const_("g", id("main_g")),
+        expr_stmt(call(id("double"), vec![num(21.0)])),
+    ]);
+    let library = compile_library(&["util"], fetch_all);
+    let linked: ir::Program = run_frontend_with(&estree, fetch_main, Some(&library));
+    assert_eq!(linked.imports.len(), 2);
+    assert_eq!(
+        ir::text::print(&linked),
+        ir::text::print(&run_frontend_with(&estree, fetch_all, None))
+    );
+    assert_eq!(interpret(&ir::opt::optimize_all(linked, 0)), "42");
+
+    // the library can be linked again, and is not changed by linking
+    let linked_again: ir::Program = run_frontend_with(&estree, fetch_main, Some(&library));
+    assert_eq!(
+        ir::text::print(&linked_again),
+        ir::text::print(&run_frontend_with(&estree, fetch_all, None))
+    );
+}
@@ -0,0 +1,96 @@
+/**
+ * End-to-end tests for the short-circuiting logical operators.
+ */
+#[allow(dead_code)] // each test file only uses some of the helpers
+mod common;
+
+use backend_wasm::Options;
+use common::*;
+
+fn check_both(estree: &serde_json::Value, expected: &str) {
+    check_interpreter(estree, expected);
+    check(estree, Options::new(), expected);
+}
+
+// let n = 0;
+// function f(x) { n = n + 1; return x > 0; }
+fn counted_f() -> Vec<serde_json::Value> {
+    vec![
+        let_("n", num(0.0)),
+        function(
+            "f",
+            &["x"],
+            vec![
+                expr_stmt(assign(id("n"), binary("+", id("n"), num(1.0)))),
+                return_(binary(">", id("x"), num(0.0))),
+            ],
+        ),
+    ]
+}
+
+#[test]
+fn short_circuit() {
+    // function g(x) { return x !== 0 && f(1 / x); }
+    // function h(x) { return x === 0 || f(x); }
+    // [g(0), h(0), n, g(2), h(-1), n];
+    let mut body = counted_f();
+    body.extend(vec![
+        function(
+            "g",
+            &["x"],
+            vec![return_(logical(
+                "&&",
+                binary("!==", id("x"), num(0.0)),
+                call(id("f"), vec![binary("/", num(1.0), id("x"))]),
+            ))],
+        ),
+        function(
+            "h",
+            &["x"],
+            vec![return_(logical(
+                "||",
+                binary("===", id("x"), num(0.0)),
+                call(id("f"), vec![id("x")]),
+            ))],
+        ),
+        expr_stmt(array(vec![
+            call(id("g"), vec![num(0.0)]),
+            call(id("h"), vec![num(0.0)]),
+            id("n"),
+            call(id("g"), vec![num(2.0)]),
+            call(id("h"), vec![num(-1.0)]),
+            id("n"),
+        ])),
+    ]);
+    check_both(&program(body), "[false,true,0,true,false,2]");
+}
+
+#[test]
+fn right_operand_is_returned_as_is() {
+    // [true && 5, false || "a", false && f(1), true || f(1), n];
+    let mut body = counted_f();
+    body.push(expr_stmt(array(vec![
+        logical("&&", boolean(true), num(5.0)),
+        logical("||", boolean(false), string("a")),
+        logical("&&", boolean(false), call(id("f"), vec![num(1.0)])),
+        logical("||", boolean(true), call(id("f"), vec![num(1.0)])),
+        id("n"),
+    ])));
+    check_both(&program(body), "[5,\"a\",false,true,0]");
+}
+
+#[test]
+fn non_boolean_left_operand() {
+    // function g(x) { return x && true; }
+    // g(1);
+    let estree = program(vec![
+        function(
+            "g",
+            &["x"],
+            vec![return_(logical("&&", id("x"), boolean(true)))],
+        ),
+        expr_stmt(call(id("g"), vec![num(1.0)])),
+    ]);
+    // ERROR_CODE_BINARY_OPERATOR_PARAM_TYPE
+    check_both(&estree, "error 19");
+}
@@ -0,0 +1,30 @@
+/**
+ * End-to-end tests of programs that the optimiser could easily get wrong.
+ * Each program is run with the reference interpreter before and after optimisation, and compiled to wasm.
+ */
+#[allow(dead_code)] // each test file only uses some of the helpers
+mod common;
+
+use backend_wasm::Options;
+use common::*;
+
+fn check_both(estree: &serde_json::Value, expected: &str) {
+    check_interpreter(estree, expected);
+    check(estree, Options::new(), expected);
+}
+
+#[test]
+fn const_read_before_assignment() {
+    // The read of x runs before x is assigned, so it must not be replaced by the value of x.
+    // function f() { return x; }
+    // const r = f();
+    // const x = 1;
+    // r;
+    let estree = program(vec![
+        function("f", &[], vec![return_(id("x"))]),
+        const_("r", call(id("f"), vec![])),
+        const_("x", num(1.0)),
+        expr_stmt(id("r")),
+    ]);
+    check_both(&estree, "\"tag 0\"");
+}
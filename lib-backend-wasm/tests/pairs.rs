@@ -0,0 +1,123 @@
+/**
+ * End-to-end tests for pairs, lists, arrays and null.
+ */
+#[allow(dead_code)] // each test file only uses some of the helpers
+mod common;
+
+use backend_wasm::Options;
+use common::*;
+
+fn check_both(estree: &serde_json::Value, expected: &str) {
+    check_interpreter(estree, expected);
+    check(estree, Options::new(), expected);
+}
+
+#[test]
+fn list_accessors() {
+    // const xs = list(1, 2, 3);
+    // set_head(tail(xs), 5);
+    // [head(xs), head(tail(xs)), head(tail(tail(xs))), is_null(tail(tail(tail(xs)))), is_pair(xs), is_pair(null), is_null(list())];
+    let estree = program(vec![
+        const_("xs", call(id("list"), vec![num(1.0), num(2.0), num(3.0)])),
+        expr_stmt(call(
+            id("set_head"),
+            vec![call(id("tail"), vec![id("xs")]), num(5.0)],
+        )),
+        expr_stmt(array(vec![
+            call(id("head"), vec![id("xs")]),
+            call(id("head"), vec![call(id("tail"), vec![id("xs")])]),
+            call(
+                id("head"),
+                vec![call(id("tail"), vec![call(id("tail"), vec![id("xs")])])],
+            ),
+            call(
+                id("is_null"),
+                vec![call(
+                    id("tail"),
+                    vec![call(id("tail"), vec![call(id("tail"), vec![id("xs")])])],
+                )],
+            ),
+            call(id("is_pair"), vec![id("xs")]),
+            call(id("is_pair"), vec![null()]),
+            call(id("is_null"), vec![call(id("list"), vec![])]),
+        ])),
+    ]);
+    check_both(&estree, "[1,5,3,true,true,false,true]");
+}
+
+#[test]
+fn pair_equality() {
+    // const p = pair(1, 2);
+    // const q = pair(1, 2);
+    // const r = p;
+    // [p === p, p === q, p === r, p !== q, p !== r, p === null, null !== p, null === null];
+    let estree = program(vec![
+        const_("p", call(id("pair"), vec![num(1.0), num(2.0)])),
+        const_("q", call(id("pair"), vec![num(1.0), num(2.0)])),
+        const_("r", id("p")),
+        expr_stmt(array(vec![
+            binary("===", id("p"), id("p")),
+            binary("===", id("p"), id("q")),
+            binary("===", id("p"), id("r")),
+            binary("!==", id("p"), id("q")),
+            binary("!==", id("p"), id("r")),
+            binary("===", id("p"), null()),
+            binary("!==", null(), id("p")),
+            binary("===", null(), null()),
+        ])),
+    ]);
+    check_both(&estree, "[true,false,true,true,false,false,true,true]");
+}
+
+#[test]
+fn pair_equality_through_any() {
+    // function eq(a, b) { return a === b; }
+    // const xs = list(1, 2);
+    // [eq(xs, xs), eq(xs, tail(xs)), eq(tail(tail(xs)), null), eq(xs, null), eq(1, 1)];
+    let estree = program(vec![
+        function(
+            "eq",
+            &["a", "b"],
+            vec![return_(binary("===", id("a"), id("b")))],
+        ),
+        const_("xs", call(id("list"), vec![num(1.0), num(2.0)])),
+        expr_stmt(array(vec![
+            call(id("eq"), vec![id("xs"), id("xs")]),
+            call(id("eq"), vec![id("xs"), call(id("tail"), vec![id("xs")])]),
+            call(
+                id("eq"),
+                vec![
+                    call(id("tail"), vec![call(id("tail"), vec![id("xs")])]),
+                    null(),
+                ],
+            ),
+            call(id("eq"), vec![id("xs"), null()]),
+            call(id("eq"), vec![num(1.0), num(1.0)]),
+        ])),
+    ]);
+    check_both(&estree, "[true,false,true,false,true]");
+}
+
+#[test]
+fn array_equality() {
+    // const a = [1];
+    // const b = [1];
+    // const c = a;
+    // c[1] = 2;
+    // [a === a, a === b, a === c, a !== b, a === null, array_length(a)];
+    let estree = program(vec![
+        const_("a", array(vec![num(1.0)])),
+        const_("b", array(vec![num(1.0)])),
+        const_("c", id("a")),
+        expr_stmt(assign(member(id("c"), num(1.0)), num(2.0))),
+        expr_stmt(array(vec![
+            binary("===", id("a"), id("a")),
+            binary("===", id("a"), id("b")),
+            binary("===", id("a"), id("c")),
+            binary("!==", id("a"), id("b")),
+            binary("===", id("a"), null()),
+            call(id("array_length"), vec![id("a")]),
+        ])),
+    ]);
+    check_both(&estree, "[true,false,true,true,false,2]");
+}
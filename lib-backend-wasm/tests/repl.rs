@@ -0,0 +1,98 @@
+/**
+ * Tests for compiling REPL input after a program whose unused items have been removed.
+ * The REPL input is run with the reference interpreter, which starts with fresh globals,
+ * so the inputs only read the globals of the program after assigning them.
+ */
+#[allow(dead_code)] // each test file only uses some of the helpers
+mod common;
+
+use common::*;
+
+#[test]
+fn keep_items_for_repl() {
+    // function double(x) { return x + x; }
+    // let counter = 0;
+    // double(21);
+    let estree = program(vec![
+        function(
+            "double",
+            &["x"],
+            vec![return_(binary("+", id("x"), id("x")))],
+        ),
+        let_("counter", num(0.0)),
+        expr_stmt(call(id("double"), vec![num(21.0)])),
+    ]);
+    let (mut repl_ctx, ir_program) = run_frontend_for_repl(&estree);
+    let ir_program = ir::opt::optimize_all(ir_program, 0);
+
+    // without the REPL, only `double` and the entry point are left
+    let (shrunk, _) = ir::opt::remove_unused(ir_program.clone(), &ir::opt::KeepAlive::new());
+    assert_eq!(shrunk.funcs.len(), 2);
+    assert_eq!(shrunk.globals.len(), 1);
+    assert_eq!(interpret(&shrunk), "42");
+
+    // with the REPL, everything that the REPL context refers to (e.g. `counter` and the builtins) is kept
+    let (mut ir_program, map) =
+        ir::opt::remove_unused(ir_program, &frontend_estree::repl_keep_alive(&repl_ctx));
+    assert_eq!(ir_program.globals.len(), 2);
+    assert!(ir_program.funcs.len() > shrunk.funcs.len());
+    frontend_estree::remap_repl_context(&mut repl_ctx, &map);
+    assert_eq!(interpret(&ir_program), "42");
+
+    // counter = 41;
+    // pair(counter + 1, "a" + "b");
+    let repl_estree = program(vec![
+        expr_stmt(assign(id("counter"), num(41.0))),
+        expr_stmt(call(
+            id("pair"),
+            vec![
+                binary("+", id("counter"), num(1.0)),
+                binary("+", string("a"), string("b")),
+            ],
+        )),
+    ]);
+    let new_funcidx_start = run_frontend_repl(&repl_estree, &mut repl_ctx, &mut ir_program);
+    let ir_program = ir::opt::optimize_all(ir_program, new_funcidx_start);
+    assert_eq!(interpret(&ir_program), r#"{"struct#0":[42,"ab"]}"#);
+}
+
+#[test]
+fn pair_type_is_pinned() {
+    // Hosts decode the tag of struct type 0 as a pair, so removing the unused items must never renumber it,
+    // even if the program itself does not use pairs.
+    // function make(x) { return () => x; }
+    // const get = make(1);
+    // get();
+    let estree = program(vec![
+        function("make", &["x"], vec![return_(arrow(&[], id("x")))]),
+        const_("get", call(id("make"), vec![num(1.0)])),
+        expr_stmt(call(id("get"), vec![])),
+    ]);
+    let (mut repl_ctx, ir_program) = run_frontend_for_repl(&estree);
+    let ir_program = ir::opt::optimize_all(ir_program, 0);
+
+    // the program does not use pairs, but struct type 0 is kept anyway
+    let (shrunk, map) = ir::opt::remove_unused(ir_program.clone(), &ir::opt::KeepAlive::new());
+    assert_eq!(map.struct_type(0), Some(0));
+    assert_eq!(interpret(&shrunk), "1");
+
+    let (mut ir_program, map) =
+        ir::opt::remove_unused(ir_program, &frontend_estree::repl_keep_alive(&repl_ctx));
+    assert_eq!(map.struct_type(0), Some(0));
+    frontend_estree::remap_repl_context(&mut repl_ctx, &map);
+
+    // the REPL input returns a pair, which both the interpreter and the wasm host decode by its tag
+    // pair(2, "a");
+    let repl_estree = program(vec![expr_stmt(call(
+        id("pair"),
+        vec![num(2.0), string("a")],
+    ))]);
+    let new_funcidx_start = run_frontend_repl(&repl_estree, &mut repl_ctx, &mut ir_program);
+    let ir_program = ir::opt::optimize_all(ir_program, new_funcidx_start);
+    assert_eq!(interpret(&ir_program), r#"{"struct#0":[2,"a"]}"#);
+    let options = backend_wasm::Options::new();
+    assert_eq!(
+        run(&compile_ir(&ir_program, options), options.get_stack_size()),
+        r#"{"head":2,"tail":"a"}"#
+    );
+}
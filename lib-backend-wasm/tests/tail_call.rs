@@ -0,0 +1,223 @@
+/**
+ * End-to-end tests for deep tail recursion, both with the wasm tail call proposal and with the trampoline.
+ * The wasm interpreter allows at most 10000 nested calls, so each of these programs only runs if its tail calls do not grow the stack.
+ * (The IR interpreter does not eliminate tail calls, so it is not used here.)
+ */
+#[allow(dead_code)] // each test file only uses some of the helpers
+mod common;
+
+use backend_wasm::Options;
+use common::*;
+
+const DEPTH: f64 = 1000000.0;
+
+fn check_tail_calls(estree: &serde_json::Value, expected: &str) {
+    check(estree, Options::new(), expected);
+    check(estree, Options::new().wasm_tail_call(true), expected);
+}
+
+fn check_ir_tail_calls(text: &str, expected: &str) {
+    let ir_program: ir::Program = ir::text::parse(text).unwrap_or_else(|e| panic!("{}", e));
+    for options in [Options::new(), Options::new().wasm_tail_call(true)].iter() {
+        assert_eq!(
+            run(&compile_ir(&ir_program, *options), options.get_stack_size()),
+            expected
+        );
+    }
+}
+
+#[test]
+fn self_tail_call() {
+    // function count(n, acc) { return n === 0 ? acc : count(n - 1, acc + 1); }
+    // count(1000000, 0);
+    let estree = program(vec![
+        function(
+            "count",
+            &["n", "acc"],
+            vec![return_(conditional(
+                binary("===", id("n"), num(0.0)),
+                id("acc"),
+                call(
+                    id("count"),
+                    vec![
+                        binary("-", id("n"), num(1.0)),
+                        binary("+", id("acc"), num(1.0)),
+                    ],
+                ),
+            ))],
+        ),
+        expr_stmt(call(id("count"), vec![num(DEPTH), num(0.0)])),
+    ]);
+    check_tail_calls(&estree, "1000000");
+}
+
+#[test]
+fn mutual_direct_tail_calls() {
+    // Written in IR, because the optimiser would inline one function into the other and make it a self tail call.
+    // even(n) and odd(n) return different types, so they both return Any.
+    let text = r#"entry 0
+
+func 0 () -> any
+  (return:void
+    (direct_appl:any 1
+      (number:number 1000000.0)))
+
+func 1 "even" (number) -> any
+  (return:void
+    (if:any
+      (prim:boolean number_eq
+        (var:number (local 0))
+        (number:number 0.0))
+      (boolean:boolean true)
+      (direct_appl:any 2
+        (prim:number number_sub
+          (var:number (local 0))
+          (number:number 1.0)))))
+
+func 2 "odd" (number) -> any
+  (return:void
+    (if:any
+      (prim:boolean number_eq
+        (var:number (local 0))
+        (number:number 0.0))
+      (number:number 0.0)
+      (direct_appl:any 1
+        (prim:number number_sub
+          (var:number (local 0))
+          (number:number 1.0)))))
+"#;
+    check_ir_tail_calls(text, "true");
+}
+
+#[test]
+fn mutual_typed_direct_tail_calls() {
+    // Like mutual_direct_tail_calls, but both functions return Number, and the entry point converts the result to Any.
+    let text = r#"entry 0
+
+func 0 () -> any
+  (return:void
+    (direct_appl:number 1
+      (number:number 1000000.0)))
+
+func 1 "even" (number) -> number
+  (return:void
+    (if:number
+      (prim:boolean number_eq
+        (var:number (local 0))
+        (number:number 0.0))
+      (number:number 1.0)
+      (direct_appl:number 2
+        (prim:number number_sub
+          (var:number (local 0))
+          (number:number 1.0)))))
+
+func 2 "odd" (number) -> number
+  (return:void
+    (if:number
+      (prim:boolean number_eq
+        (var:number (local 0))
+        (number:number 0.0))
+      (number:number 0.0)
+      (direct_appl:number 1
+        (prim:number number_sub
+          (var:number (local 0))
+          (number:number 1.0)))))
+"#;
+    check_ir_tail_calls(text, "1");
+}
+
+#[test]
+fn typed_direct_tail_calls_through_thunk() {
+    // The entry point calls "ping" indirectly, so the thunk of "ping" has to pass on the pending tail calls of "ping" and "pong".
+    let text = r#"entry 0
+
+func 0 () -> any
+  (return:void
+    (appl:any @0:1:0-1:1
+      (func:func (1)
+        (undefined:undefined))
+      (number:number 1000000.0)))
+
+func 1 "ping" (number) -> string
+  (return:void
+    (if:string
+      (prim:boolean number_eq
+        (var:number (local 0))
+        (number:number 0.0))
+      (string:string "ping")
+      (direct_appl:string 2
+        (prim:number number_sub
+          (var:number (local 0))
+          (number:number 1.0)))))
+
+func 2 "pong" (number) -> string
+  (return:void
+    (if:string
+      (prim:boolean number_eq
+        (var:number (local 0))
+        (number:number 0.0))
+      (string:string "pong")
+      (direct_appl:string 1
+        (prim:number number_sub
+          (var:number (local 0))
+          (number:number 1.0)))))
+"#;
+    check_ir_tail_calls(text, "\"ping\"");
+}
+
+#[test]
+fn mutual_indirect_tail_calls() {
+    // function ping(n, f) { return n === 0 ? "ping" : f(n - 1, ping); }
+    // function pong(n, f) { return n === 0 ? "pong" : f(n - 1, pong); }
+    // ping(1000000, pong);
+    let ping_pong = |name: &str| {
+        function(
+            name,
+            &["n", "f"],
+            vec![return_(conditional(
+                binary("===", id("n"), num(0.0)),
+                string(name),
+                call(id("f"), vec![binary("-", id("n"), num(1.0)), id(name)]),
+            ))],
+        )
+    };
+    let estree = program(vec![
+        ping_pong("ping"),
+        ping_pong("pong"),
+        expr_stmt(call(id("ping"), vec![num(DEPTH), id("pong")])),
+    ]);
+    check_tail_calls(&estree, "\"ping\"");
+}
+
+#[test]
+fn tail_calls_through_overloaded_thunk() {
+    // func 2 calls itself indirectly through a thunk with two overloads, and the later (number) overload is picked.
+    let text = r#"entry 0
+
+func 0 () -> any
+  (return:void
+    (appl:any @0:1:0-1:1
+      (func:func (1 2)
+        (undefined:undefined))
+      (number:number 1000000.0)))
+
+func 1 (any) -> any
+  (return:void
+    (string:string "wrong overload"))
+
+func 2 (number) -> any
+  (return:void
+    (if:any
+      (prim:boolean number_eq
+        (var:number (local 0))
+        (number:number 0.0))
+      (string:string "done")
+      (appl:any @0:2:0-2:1
+        (func:func (1 2)
+          (undefined:undefined))
+        (prim:number number_sub
+          (var:number (local 0))
+          (number:number 1.0)))))
+"#;
+    check_ir_tail_calls(text, "\"done\"");
+}
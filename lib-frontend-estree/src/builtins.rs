@@ -7,8 +7,6 @@ use std::collections::HashMap;
 // Operators
 const UNARY_MINUS: &str = "-u";
 const NOT: &str = "!";
-const AND: &str = "&&";
-const OR: &str = "||";
 const EQ: &str = "===";
 const NE: &str = "!==";
 const LT: &str = "<";
@@ -21,6 +19,31 @@ const MUL: &str = "*";
 const DIV: &str = "/";
 const MOD: &str = "%";
 
+// Pairs and lists
+pub const PAIR: &str = "pair";
+const HEAD: &str = "head";
+const TAIL: &str = "tail";
+const SET_HEAD: &str = "set_head";
+const SET_TAIL: &str = "set_tail";
+const IS_PAIR: &str = "is_pair";
+const IS_NULL: &str = "is_null";
+pub const LIST: &str = "list";
+
+// Arrays
+const ARRAY_LENGTH: &str = "array_length";
+const IS_ARRAY: &str = "is_array";
+
+// The pair struct is the first struct type registered, so it always has this typeidx
+// (the host relies on this to display pairs)
+const PAIR_TYPEIDX: usize = 0;
+const PAIR_HEAD_FIELDIDX: usize = 0;
+const PAIR_TAIL_FIELDIDX: usize = 1;
+
+// `list` is given overloads for up to this number of arguments, so that it can be used as a function value;
+// direct calls to `list` are expanded into nested calls to `pair` at the call site instead (see post_parse_call_expr()),
+// so they may have any number of arguments
+const LIST_MAX_OVERLOAD_ARITY: usize = 8;
+
 pub fn resolve_unary_operator(es_op: &str) -> Option<&'static str> {
     match es_op {
         "-" => Some(UNARY_MINUS),
@@ -46,14 +69,6 @@ pub fn resolve_binary_operator(es_op: &str) -> Option<&'static str> {
     }
 }
 
-pub fn resolve_logical_operator(es_op: &str) -> Option<&'static str> {
-    match es_op {
-        "&&" => Some(AND),
-        "||" => Some(OR),
-        _ => None,
-    }
-}
-
 // Skip rust fmt so we can have one operator per line
 #[rustfmt::skip]
 pub fn state_with_builtins(
@@ -65,8 +80,6 @@ pub fn state_with_builtins(
 
     register_unary_op(UNARY_MINUS, ir::PrimInst::NumberNegate, ir::VarType::Number, &mut name_ctx, &mut parse_ctx, ir_program);
     register_unary_op(NOT, ir::PrimInst::BooleanNot, ir::VarType::Boolean, &mut name_ctx, &mut parse_ctx, ir_program);
-    register_binary_op(AND, ir::PrimInst::BooleanAnd, ir::VarType::Boolean, &mut name_ctx, &mut parse_ctx, ir_program);
-    register_binary_op(OR, ir::PrimInst::BooleanOr, ir::VarType::Boolean, &mut name_ctx, &mut parse_ctx, ir_program);
     register_binary_op(SUB, ir::PrimInst::NumberSub, ir::VarType::Number, &mut name_ctx, &mut parse_ctx, ir_program);
     register_binary_op(MUL, ir::PrimInst::NumberMul, ir::VarType::Number, &mut name_ctx, &mut parse_ctx, ir_program);
     register_binary_op(DIV, ir::PrimInst::NumberDiv, ir::VarType::Number, &mut name_ctx, &mut parse_ctx, ir_program);
@@ -76,8 +89,21 @@ pub fn state_with_builtins(
     register_comparison_op(LE, ir::PrimInst::NumberLe, ir::PrimInst::StringLe, &mut name_ctx, &mut parse_ctx, ir_program);
     register_comparison_op(GT, ir::PrimInst::NumberGt, ir::PrimInst::StringGt, &mut name_ctx, &mut parse_ctx, ir_program);
     register_comparison_op(GE, ir::PrimInst::NumberGe, ir::PrimInst::StringGe, &mut name_ctx, &mut parse_ctx, ir_program);
-    register_equality_op(EQ, true, ir::PrimInst::NumberEq, ir::PrimInst::BooleanEq, ir::PrimInst::StringEq, &mut name_ctx, &mut parse_ctx, ir_program);
-    register_equality_op(NE, false, ir::PrimInst::NumberNeq, ir::PrimInst::BooleanNeq, ir::PrimInst::StringNeq, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_equality_op(EQ, true, ir::PrimInst::NumberEq, ir::PrimInst::BooleanEq, ir::PrimInst::StringEq, ir::PrimInst::ReferenceEq, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_equality_op(NE, false, ir::PrimInst::NumberNeq, ir::PrimInst::BooleanNeq, ir::PrimInst::StringNeq, ir::PrimInst::ReferenceNeq, &mut name_ctx, &mut parse_ctx, ir_program);
+
+    register_pair_struct(ir_program);
+    let pair_funcidx = register_pair_constructor(PAIR, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_pair_accessor(HEAD, PAIR_HEAD_FIELDIDX, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_pair_accessor(TAIL, PAIR_TAIL_FIELDIDX, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_pair_mutator(SET_HEAD, PAIR_HEAD_FIELDIDX, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_pair_mutator(SET_TAIL, PAIR_TAIL_FIELDIDX, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_type_predicate(IS_PAIR, ir::VarType::StructT { typeidx: PAIR_TYPEIDX }, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_type_predicate(IS_NULL, ir::VarType::Null, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_list_func(LIST, pair_funcidx, &mut name_ctx, &mut parse_ctx, ir_program);
+
+    register_array_length(ARRAY_LENGTH, &mut name_ctx, &mut parse_ctx, ir_program);
+    register_type_predicate(IS_ARRAY, ir::VarType::Array, &mut name_ctx, &mut parse_ctx, ir_program);
 
     (name_ctx, parse_ctx)
 }
@@ -103,11 +129,15 @@ fn register_unary_op(
                         next: None,
                     },
                 },
+                location: None,
             }]),
         },
+        location: None,
     };
 
     let funcidx = ir_program.add_func(ir::Func {
+        name: Some(name.to_owned()),
+        param_names: Box::new([]),
         params: Box::new([ir_vartype]),
         result: Some(ir_vartype),
         expr: ir_expr,
@@ -142,6 +172,7 @@ fn make_binary_op_impl(
                             next: None,
                         },
                     },
+                    location: None,
                 },
                 ir::Expr {
                     vartype: Some(ir_param_vartype),
@@ -151,12 +182,16 @@ fn make_binary_op_impl(
                             next: None,
                         },
                     },
+                    location: None,
                 },
             ]),
         },
+        location: None,
     };
 
     let funcidx = ir_program.add_func(ir::Func {
+        name: None,
+        param_names: Box::new([]),
         params: Box::new([ir_param_vartype, ir_param_vartype]),
         result: Some(ir_result_vartype),
         expr: ir_expr,
@@ -167,14 +202,21 @@ fn make_binary_op_impl(
 }
 
 // write the actual function (we hope it gets inlined by the ir optimizer later)
-fn make_trivial_func_undefined_impl(ret: bool, ir_program: &mut ir::Program) -> ir::FuncIdx {
+fn make_trivial_func_impl(
+    ir_param_vartypes: [ir::VarType; 2],
+    ret: bool,
+    ir_program: &mut ir::Program,
+) -> ir::FuncIdx {
     let ir_expr = ir::Expr {
         vartype: Some(ir::VarType::Boolean),
         kind: ir::ExprKind::PrimBoolean { val: ret },
+        location: None,
     };
 
     let funcidx = ir_program.add_func(ir::Func {
-        params: Box::new([ir::VarType::Undefined, ir::VarType::Undefined]),
+        name: None,
+        param_names: Box::new([]),
+        params: Box::new(ir_param_vartypes),
         result: Some(ir::VarType::Boolean),
         expr: ir_expr,
         signature_filter: Default::default(),
@@ -273,20 +315,40 @@ fn register_comparison_op(
     parse_ctx.add_direct(name.to_owned(), overload_set);
 }
 
-// overloaded all primitive types
-// struct type might need to be supported later
+// overloaded all primitive types, and pairs and arrays (which are compared by reference)
 fn register_equality_op(
     name: &str,
-    undefined_ret_val: bool,
+    undefined_ret_val: bool, // also used for null
     ir_priminst_number: ir::PrimInst,
     ir_priminst_boolean: ir::PrimInst,
     ir_priminst_string: ir::PrimInst,
+    ir_priminst_reference: ir::PrimInst,
     /*ir_priminst_func: ir::PrimInst,*/ // for now, ir has not implemented it yet
     name_ctx: &mut HashMap<String, PreVar>,
     parse_ctx: &mut ParseState,
     ir_program: &mut ir::Program,
 ) {
-    let funcidx_undefined = make_trivial_func_undefined_impl(undefined_ret_val, ir_program);
+    let funcidx_undefined = make_trivial_func_impl(
+        [ir::VarType::Undefined, ir::VarType::Undefined],
+        undefined_ret_val,
+        ir_program,
+    );
+    let funcidx_null = make_trivial_func_impl(
+        [ir::VarType::Null, ir::VarType::Null],
+        undefined_ret_val,
+        ir_program,
+    );
+    // comparing null with anything else (e.g. `xs === null` where xs is a pair) is allowed, and never equal
+    let funcidx_any_null = make_trivial_func_impl(
+        [ir::VarType::Any, ir::VarType::Null],
+        !undefined_ret_val,
+        ir_program,
+    );
+    let funcidx_null_any = make_trivial_func_impl(
+        [ir::VarType::Null, ir::VarType::Any],
+        !undefined_ret_val,
+        ir_program,
+    );
     let funcidx_number = make_binary_op_impl(
         ir_priminst_number,
         ir::VarType::Number,
@@ -305,15 +367,42 @@ fn register_equality_op(
         ir::VarType::Boolean,
         ir_program,
     );
+    let funcidx_pair = make_binary_op_impl(
+        ir_priminst_reference,
+        ir::VarType::StructT {
+            typeidx: PAIR_TYPEIDX,
+        },
+        ir::VarType::Boolean,
+        ir_program,
+    );
+    let funcidx_array = make_binary_op_impl(
+        ir_priminst_reference,
+        ir::VarType::Array,
+        ir::VarType::Boolean,
+        ir_program,
+    );
     //let funcidx_func = make_binary_op_impl(ir_priminst_func, ir::VarType::Func, ir::VarType::Boolean, ir_program);
 
     // insert the necessary things into name_ctx and parse_ctx
     name_ctx.insert(name.to_owned(), PreVar::Direct);
     let mut overload_set = OverloadSet::new();
+    // the overloads involving Any must come first, because later overloads have higher priority
+    overload_set.append((
+        Box::new([ir::VarType::Any, ir::VarType::Null]) as Box<[ir::VarType]>,
+        funcidx_any_null,
+    ));
+    overload_set.append((
+        Box::new([ir::VarType::Null, ir::VarType::Any]) as Box<[ir::VarType]>,
+        funcidx_null_any,
+    ));
     overload_set.append((
         Box::new([ir::VarType::Undefined, ir::VarType::Undefined]) as Box<[ir::VarType]>,
         funcidx_undefined,
     ));
+    overload_set.append((
+        Box::new([ir::VarType::Null, ir::VarType::Null]) as Box<[ir::VarType]>,
+        funcidx_null,
+    ));
     overload_set.append((
         Box::new([ir::VarType::Number, ir::VarType::Number]) as Box<[ir::VarType]>,
         funcidx_number,
@@ -326,6 +415,324 @@ fn register_equality_op(
         Box::new([ir::VarType::String, ir::VarType::String]) as Box<[ir::VarType]>,
         funcidx_string,
     ));
+    overload_set.append((
+        Box::new([
+            ir::VarType::StructT {
+                typeidx: PAIR_TYPEIDX,
+            },
+            ir::VarType::StructT {
+                typeidx: PAIR_TYPEIDX,
+            },
+        ]) as Box<[ir::VarType]>,
+        funcidx_pair,
+    ));
+    overload_set.append((
+        Box::new([ir::VarType::Array, ir::VarType::Array]) as Box<[ir::VarType]>,
+        funcidx_array,
+    ));
     //overload_set.append((Box::new([ir::VarType::Func, ir::VarType::Func]), funcidx_func));
     parse_ctx.add_direct(name.to_owned(), overload_set);
 }
+
+fn make_local(localidx: usize, ir_vartype: ir::VarType) -> ir::Expr {
+    ir::Expr {
+        vartype: Some(ir_vartype),
+        kind: ir::ExprKind::VarName {
+            source: ir::TargetExpr::Local {
+                localidx: localidx,
+                next: None,
+            },
+        },
+        location: None,
+    }
+}
+
+// the TargetExpr for the given field of the pair in the given local
+fn make_pair_field_target(localidx: usize, fieldidx: usize) -> ir::TargetExpr {
+    ir::TargetExpr::Local {
+        localidx: localidx,
+        next: Some(Box::new(ir::StructField {
+            typeidx: PAIR_TYPEIDX,
+            fieldidx: fieldidx,
+            next: None,
+        })),
+    }
+}
+
+fn register_pair_struct(ir_program: &mut ir::Program) {
+    assert!(
+        ir_program.struct_types.len() == PAIR_TYPEIDX,
+        "ICE: pair struct must be the first struct type"
+    );
+    ir_program
+        .struct_types
+        .push(Box::new([ir::VarType::Any, ir::VarType::Any]));
+}
+
+// pair(any, any) -> pair
+// returns the funcidx, so that `list` can use it
+fn register_pair_constructor(
+    name: &str,
+    name_ctx: &mut HashMap<String, PreVar>,
+    parse_ctx: &mut ParseState,
+    ir_program: &mut ir::Program,
+) -> ir::FuncIdx {
+    let ir_pair_vartype = ir::VarType::StructT {
+        typeidx: PAIR_TYPEIDX,
+    };
+
+    // allocate the pair into a new local (localidx 2), then fill in both fields from the params
+    let ir_expr = ir::Expr {
+        vartype: Some(ir_pair_vartype),
+        kind: ir::ExprKind::Declaration {
+            local: ir_pair_vartype,
+            name: None,
+            init: Some(Box::new(ir::Expr {
+                vartype: Some(ir_pair_vartype),
+                kind: ir::ExprKind::PrimStructT {
+                    typeidx: PAIR_TYPEIDX,
+                },
+                location: None,
+            })),
+            contained_expr: Box::new(ir::Expr {
+                vartype: Some(ir_pair_vartype),
+                kind: ir::ExprKind::Sequence {
+                    content: vec![
+                        ir::Expr {
+                            vartype: Some(ir::VarType::Undefined),
+                            kind: ir::ExprKind::Assign {
+                                target: make_pair_field_target(2, PAIR_HEAD_FIELDIDX),
+                                expr: Box::new(make_local(0, ir::VarType::Any)),
+                            },
+                            location: None,
+                        },
+                        ir::Expr {
+                            vartype: Some(ir::VarType::Undefined),
+                            kind: ir::ExprKind::Assign {
+                                target: make_pair_field_target(2, PAIR_TAIL_FIELDIDX),
+                                expr: Box::new(make_local(1, ir::VarType::Any)),
+                            },
+                            location: None,
+                        },
+                        make_local(2, ir_pair_vartype),
+                    ],
+                },
+                location: None,
+            }),
+        },
+        location: None,
+    };
+
+    let funcidx = ir_program.add_func(ir::Func {
+        name: Some(name.to_owned()),
+        param_names: Box::new([]),
+        params: Box::new([ir::VarType::Any, ir::VarType::Any]),
+        result: Some(ir_pair_vartype),
+        expr: ir_expr,
+        signature_filter: Default::default(),
+    });
+
+    // insert the necessary things into name_ctx and parse_ctx
+    name_ctx.insert(name.to_owned(), PreVar::Direct);
+    parse_ctx.add_direct(
+        name.to_owned(),
+        OverloadSet::from_single((Box::new([ir::VarType::Any, ir::VarType::Any]), funcidx)),
+    );
+
+    funcidx
+}
+
+// head(pair) -> any, tail(pair) -> any
+fn register_pair_accessor(
+    name: &str,
+    fieldidx: usize,
+    name_ctx: &mut HashMap<String, PreVar>,
+    parse_ctx: &mut ParseState,
+    ir_program: &mut ir::Program,
+) {
+    let ir_pair_vartype = ir::VarType::StructT {
+        typeidx: PAIR_TYPEIDX,
+    };
+
+    let ir_expr = ir::Expr {
+        vartype: Some(ir::VarType::Any),
+        kind: ir::ExprKind::VarName {
+            source: make_pair_field_target(0, fieldidx),
+        },
+        location: None,
+    };
+
+    let funcidx = ir_program.add_func(ir::Func {
+        name: Some(name.to_owned()),
+        param_names: Box::new([]),
+        params: Box::new([ir_pair_vartype]),
+        result: Some(ir::VarType::Any),
+        expr: ir_expr,
+        signature_filter: Default::default(),
+    });
+
+    // insert the necessary things into name_ctx and parse_ctx
+    name_ctx.insert(name.to_owned(), PreVar::Direct);
+    parse_ctx.add_direct(
+        name.to_owned(),
+        OverloadSet::from_single((Box::new([ir_pair_vartype]), funcidx)),
+    );
+}
+
+// set_head(pair, any) -> undefined, set_tail(pair, any) -> undefined
+fn register_pair_mutator(
+    name: &str,
+    fieldidx: usize,
+    name_ctx: &mut HashMap<String, PreVar>,
+    parse_ctx: &mut ParseState,
+    ir_program: &mut ir::Program,
+) {
+    let ir_pair_vartype = ir::VarType::StructT {
+        typeidx: PAIR_TYPEIDX,
+    };
+
+    let ir_expr = ir::Expr {
+        vartype: Some(ir::VarType::Undefined),
+        kind: ir::ExprKind::Assign {
+            target: make_pair_field_target(0, fieldidx),
+            expr: Box::new(make_local(1, ir::VarType::Any)),
+        },
+        location: None,
+    };
+
+    let funcidx = ir_program.add_func(ir::Func {
+        name: Some(name.to_owned()),
+        param_names: Box::new([]),
+        params: Box::new([ir_pair_vartype, ir::VarType::Any]),
+        result: Some(ir::VarType::Undefined),
+        expr: ir_expr,
+        signature_filter: Default::default(),
+    });
+
+    // insert the necessary things into name_ctx and parse_ctx
+    name_ctx.insert(name.to_owned(), PreVar::Direct);
+    parse_ctx.add_direct(
+        name.to_owned(),
+        OverloadSet::from_single((Box::new([ir_pair_vartype, ir::VarType::Any]), funcidx)),
+    );
+}
+
+// is_pair(any) -> boolean, is_null(any) -> boolean, is_array(any) -> boolean
+fn register_type_predicate(
+    name: &str,
+    ir_vartype: ir::VarType,
+    name_ctx: &mut HashMap<String, PreVar>,
+    parse_ctx: &mut ParseState,
+    ir_program: &mut ir::Program,
+) {
+    let ir_expr = ir::Expr {
+        vartype: Some(ir::VarType::Boolean),
+        kind: ir::ExprKind::TypeCast {
+            test: Box::new(make_local(0, ir::VarType::Any)),
+            expected: ir_vartype,
+            create_narrow_local: false,
+            true_expr: Box::new(ir::Expr {
+                vartype: Some(ir::VarType::Boolean),
+                kind: ir::ExprKind::PrimBoolean { val: true },
+                location: None,
+            }),
+            false_expr: Box::new(ir::Expr {
+                vartype: Some(ir::VarType::Boolean),
+                kind: ir::ExprKind::PrimBoolean { val: false },
+                location: None,
+            }),
+        },
+        location: None,
+    };
+
+    let funcidx = ir_program.add_func(ir::Func {
+        name: Some(name.to_owned()),
+        param_names: Box::new([]),
+        params: Box::new([ir::VarType::Any]),
+        result: Some(ir::VarType::Boolean),
+        expr: ir_expr,
+        signature_filter: Default::default(),
+    });
+
+    // insert the necessary things into name_ctx and parse_ctx
+    name_ctx.insert(name.to_owned(), PreVar::Direct);
+    parse_ctx.add_direct(
+        name.to_owned(),
+        OverloadSet::from_single((Box::new([ir::VarType::Any]), funcidx)),
+    );
+}
+
+// list(any, ..., any) -> pair or null, for each arity up to LIST_MAX_OVERLOAD_ARITY
+fn register_list_func(
+    name: &str,
+    pair_funcidx: ir::FuncIdx,
+    name_ctx: &mut HashMap<String, PreVar>,
+    parse_ctx: &mut ParseState,
+    ir_program: &mut ir::Program,
+) {
+    name_ctx.insert(name.to_owned(), PreVar::Direct);
+    let mut overload_set = OverloadSet::new();
+    for arity in 0..=LIST_MAX_OVERLOAD_ARITY {
+        let params: Box<[ir::VarType]> = (0..arity).map(|_| ir::VarType::Any).collect();
+        let ir_expr = (0..arity).rev().fold(
+            ir::Expr {
+                vartype: Some(ir::VarType::Null),
+                kind: ir::ExprKind::PrimNull,
+                location: None,
+            },
+            |ir_tail, i| ir::Expr {
+                vartype: Some(ir::VarType::StructT {
+                    typeidx: PAIR_TYPEIDX,
+                }),
+                kind: ir::ExprKind::DirectAppl {
+                    funcidx: pair_funcidx,
+                    args: Box::new([make_local(i, ir::VarType::Any), ir_tail]),
+                },
+                location: None,
+            },
+        );
+        let funcidx = ir_program.add_func(ir::Func {
+            name: Some(name.to_owned()),
+            param_names: Box::new([]),
+            params: params.clone(),
+            result: ir_expr.vartype,
+            expr: ir_expr,
+            signature_filter: Default::default(),
+        });
+        overload_set.append((params, funcidx));
+    }
+    parse_ctx.add_direct(name.to_owned(), overload_set);
+}
+
+// array_length(array) -> number
+fn register_array_length(
+    name: &str,
+    name_ctx: &mut HashMap<String, PreVar>,
+    parse_ctx: &mut ParseState,
+    ir_program: &mut ir::Program,
+) {
+    let ir_expr = ir::Expr {
+        vartype: Some(ir::VarType::Number),
+        kind: ir::ExprKind::PrimAppl {
+            prim_inst: ir::PrimInst::ArrayLength,
+            args: Box::new([make_local(0, ir::VarType::Array)]),
+        },
+        location: None,
+    };
+
+    let funcidx = ir_program.add_func(ir::Func {
+        name: Some(name.to_owned()),
+        param_names: Box::new([]),
+        params: Box::new([ir::VarType::Array]),
+        result: Some(ir::VarType::Number),
+        expr: ir_expr,
+        signature_filter: Default::default(),
+    });
+
+    // insert the necessary things into name_ctx and parse_ctx
+    name_ctx.insert(name.to_owned(), PreVar::Direct);
+    parse_ctx.add_direct(
+        name.to_owned(),
+        OverloadSet::from_single((Box::new([ir::VarType::Array]), funcidx)),
+    );
+}
@@ -1,7 +1,7 @@
 use serde::Deserialize;
 use std::option::Option;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct SourceLocation {
     pub source: Option<String>,
     pub start: Position,
@@ -38,6 +38,8 @@ pub enum NodeKind {
     BreakStatement(BreakStatement),
     ContinueStatement(ContinueStatement),
     IfStatement(IfStatement),
+    WhileStatement(WhileStatement),
+    ForStatement(ForStatement),
     FunctionDeclaration(FunctionDeclaration),
     VariableDeclaration(VariableDeclaration),
     VariableDeclarator(VariableDeclarator),
@@ -50,6 +52,8 @@ pub enum NodeKind {
     LogicalExpression(LogicalExpression),
     ConditionalExpression(ConditionalExpression),
     CallExpression(CallExpression),
+    ArrayExpression(ArrayExpression),
+    MemberExpression(MemberExpression),
     ImportDeclaration(ImportDeclaration),
     ImportSpecifier(ImportSpecifier),
     ImportDefaultSpecifier(ImportDefaultSpecifier),
@@ -162,6 +166,22 @@ pub struct IfStatement {
     pub alternate: Option<Box<Node>>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct WhileStatement {
+    pub test: Box<Node>,
+    pub body: Box<Node>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ForStatement {
+    pub init: Option<Box<Node>>, // set to None by pre_parse(), which hoists it into an enclosing BlockStatement
+    pub test: Option<Box<Node>>,
+    pub update: Option<Box<Node>>,
+    pub body: Box<Node>,
+    #[serde(skip)]
+    pub loop_vars: Vec<VarLocId>, // variables declared in the init part, populated by pre_parse()
+}
+
 #[derive(Deserialize, Debug)]
 pub struct FunctionDeclaration {
     pub id: Box<Node>,
@@ -256,6 +276,18 @@ pub struct CallExpression {
     pub arguments: Vec<Node>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct ArrayExpression {
+    pub elements: Vec<Option<Node>>, // `None` is a hole, e.g. `[1, , 2]`
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MemberExpression {
+    pub object: Box<Node>,
+    pub property: Box<Node>,
+    pub computed: bool, // true for `a[i]`, false for `a.b`
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ImportDeclaration {
     pub specifiers: Vec<Node>,
@@ -296,6 +328,7 @@ pub trait Function: Scope {
     fn params_body_mut(&mut self) -> (&[Node], &mut Node);
     fn captured_vars_mut(&mut self) -> &mut Vec<VarLocId>; // captured variables, except globals
     fn params_mut(&mut self) -> &mut Vec<Node>;
+    fn name(&self) -> Option<&str>; // the declared name of the function, if any
 }
 
 impl Function for FunctionDeclaration {
@@ -311,6 +344,12 @@ impl Function for FunctionDeclaration {
     fn params_mut(&mut self) -> &mut Vec<Node> {
         &mut self.params
     }
+    fn name(&self) -> Option<&str> {
+        match &self.id.kind {
+            NodeKind::Identifier(id) => Some(&id.name),
+            _ => None,
+        }
+    }
 }
 
 /*impl Function for FunctionExpression {
@@ -332,6 +371,9 @@ impl Function for ArrowFunctionExpression {
     fn params_mut(&mut self) -> &mut Vec<Node> {
         &mut self.params
     }
+    fn name(&self) -> Option<&str> {
+        None
+    }
 }
 
 pub trait Scope {
@@ -26,6 +26,12 @@ impl<K, V> VarCtx<K, V> {
     pub fn len(&self) -> usize {
         self.map.len()
     }
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.map.values()
+    }
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.map.values_mut()
+    }
 }
 
 impl<K: Hash + Eq, V: Append<V>> VarCtx<K, V> {
@@ -96,6 +96,7 @@ pub fn post_parse_program<const UNDO_PARSE_STATE: bool>(
                             globalidx: {
                                 let tmp = ir_program.globals.len();
                                 ir_program.globals.push(ir::VarType::Any);
+                                ir_program.const_globals.push(true); // functions are constant
                                 tmp
                             },
                             next: None,
@@ -116,6 +117,7 @@ pub fn post_parse_program<const UNDO_PARSE_STATE: bool>(
                             globalidx: {
                                 let tmp = ir_program.globals.len();
                                 ir_program.globals.push(ir::VarType::Any);
+                                ir_program.const_globals.push(var_decl.kind == "const");
                                 tmp
                             },
                             next: None,
@@ -155,7 +157,11 @@ pub fn post_parse_program<const UNDO_PARSE_STATE: bool>(
     // reserve ir::FuncIdx and generate the overload sets for all the directs
     let direct_entries: Box<[(String, (Box<[ir::VarType]>, ir::FuncIdx))]> = direct_funcs
         .into_iter()
-        .map(|(s, signature)| (s, (signature, ir_program.add_func(ir::Func::new()))))
+        .map(|(s, signature)| {
+            let mut ir_func = ir::Func::new();
+            ir_func.name = Some(s.clone());
+            (s, (signature, ir_program.add_func(ir_func)))
+        })
         .collect();
 
     // give the ir::FuncIdx to each direct FunctionDeclaration
@@ -290,7 +296,11 @@ fn post_parse_scope<S: Scope, PE: ScopePrefixEmitter>(
     // reserve ir::FuncIdx and generate the overload sets for all the directs
     let direct_entries: Box<[(String, (Box<[ir::VarType]>, ir::FuncIdx))]> = direct_funcs
         .into_iter()
-        .map(|(s, signature)| (s, (signature, ir_program.add_func(ir::Func::new()))))
+        .map(|(s, signature)| {
+            let mut ir_func = ir::Func::new();
+            ir_func.name = Some(s.clone());
+            (s, (signature, ir_program.add_func(ir_func)))
+        })
         .collect();
 
     // give the ir::FuncIdx to each direct FunctionDeclaration
@@ -365,6 +375,7 @@ fn post_parse_scope<S: Scope, PE: ScopePrefixEmitter>(
     let sequence_expr = ir::Expr {
         vartype: Some(ir::VarType::Undefined),
         kind: ir::ExprKind::Sequence { content: sequence },
+        location: None,
     };
 
     // remove the direct entries from the parse_ctx
@@ -384,6 +395,7 @@ fn post_parse_scope<S: Scope, PE: ScopePrefixEmitter>(
                 kind: ir::ExprKind::PrimStructT {
                     typeidx: struct_idx,
                 },
+                location: None,
             };
 
             ir::Expr {
@@ -392,9 +404,11 @@ fn post_parse_scope<S: Scope, PE: ScopePrefixEmitter>(
                     local: ir::VarType::StructT {
                         typeidx: struct_idx,
                     },
+                    name: None,
                     init: Some(Box::new(init_expr)),
                     contained_expr: Box::new(sequence_expr),
                 },
+                location: None,
             }
         }
         None => sequence_expr,
@@ -462,7 +476,8 @@ fn make_function_body<S: Scope>(
     loc: Option<esSL>,
     parse_ctx: &mut ParseState,
     ir_params_without_closure: Box<[ir::VarType]>,
-    closure_count: usize, // 0 = no closure, 1 = has closure
+    param_names: Box<[Option<String>]>, // the source names of the params, for debugging information
+    closure_count: usize,               // 0 = no closure, 1 = has closure
     depth: usize,
     filename: Option<&str>,
     ir_program: &mut ir::Program, // for adding new structs/functions if necessary
@@ -476,6 +491,7 @@ fn make_function_body<S: Scope>(
 
     struct FuncScopePrefixEmitter {
         ir_params: Box<[ir::VarType]>,
+        param_names: Box<[Option<String>]>,
         closure_count: usize,
     }
     impl FuncScopePrefixEmitter {
@@ -485,6 +501,7 @@ fn make_function_body<S: Scope>(
         >(
             j: usize,
             closure_count: usize,
+            param_names: &[Option<String>],
             ir_vartype: ir::VarType,
             more_ir_vartype_iter: J,
             parse_ctx: &mut ParseState,
@@ -506,10 +523,12 @@ fn make_function_body<S: Scope>(
                         next: None,
                     },
                 },
+                location: None,
             };
 
             post_parse_decl_helper(
                 varlocid,
+                param_names[j].clone(),
                 move |_, _, _, _, _| Ok(rhs_expr),
                 (more_ir_vartype_iter, more_stmt_attr_iter),
                 parse_ctx,
@@ -524,6 +543,7 @@ fn make_function_body<S: Scope>(
                         let (ir_expr2, (var_2, stmt_2)) = Self::post_parse_params_recurse(
                             j,
                             closure_count,
+                            param_names,
                             ir_vartype,
                             more_ir_vartype_iter,
                             parse_ctx,
@@ -575,6 +595,7 @@ fn make_function_body<S: Scope>(
                     Self::post_parse_params_recurse(
                         i,
                         self.closure_count,
+                        &self.param_names,
                         ir_param,
                         ir_param_iter,
                         parse_ctx,
@@ -601,6 +622,7 @@ fn make_function_body<S: Scope>(
         parse_ctx,
         FuncScopePrefixEmitter {
             ir_params: ir_params_without_closure,
+            param_names: param_names,
             closure_count: closure_count,
         },
         depth,
@@ -629,6 +651,8 @@ fn post_parse_direct_function(
     assert!(num_params == ir_params.len());
     //let es_params = std::mem::take(&mut es_func.params);
 
+    let param_names: Box<[Option<String>]> = make_param_names(&es_func.params);
+
     let undo_ctx = parse_ctx.enter_closure(Box::new([])); // new closure with no non-global Target entries in the parse_ctx
 
     let ir_func_body: ir::Expr = make_function_body(
@@ -636,6 +660,7 @@ fn post_parse_direct_function(
         loc,
         parse_ctx,
         ir_params.clone(),
+        param_names.clone(),
         0,
         depth,
         filename,
@@ -647,6 +672,7 @@ fn post_parse_direct_function(
     assert!(ir_funcidx >= ir_program.imports.len());
     let curr_func: &mut ir::Func = ir_program.get_func_mut(ir_funcidx);
     curr_func.params = ir_params;
+    curr_func.param_names = param_names;
     curr_func.result = Some(ir::VarType::Any);
     curr_func.expr = ir_func_body;
     Ok(())
@@ -671,6 +697,8 @@ fn post_parse_function<Func: Function>(
         .iter()
         .map(|_| ir::VarType::Any)
         .collect();
+    let param_names: Box<[Option<String>]> = make_param_names(es_func.params_mut());
+    let func_name: Option<String> = es_func.name().map(|name| name.to_owned());
 
     // the output sequence; this will go into a Declaration expression
     let mut sequence: Vec<ir::Expr> = Vec::new();
@@ -693,6 +721,7 @@ fn post_parse_function<Func: Function>(
         kind: ir::ExprKind::PrimStructT {
             typeidx: struct_idx,
         },
+        location: None,
     };
 
     // Do a few things including calculating the struct def and emitting assignment statements for the captured vars
@@ -729,8 +758,10 @@ fn post_parse_function<Func: Function>(
                                         next: None,
                                     },
                                 },
+                                location: None,
                             }),
                         },
+                        location: None,
                     });
                 }
                 Some(_) => {}
@@ -781,8 +812,10 @@ fn post_parse_function<Func: Function>(
                                                 next: None, // None - because we need to store the enclosing struct instance to do pass-by-reference
                                             },
                                         },
+                                        location: None,
                                     }),
                                 },
+                                location: None,
                             });
                             prev_var_depth = varlocid.depth;
                         } else {
@@ -837,6 +870,7 @@ fn post_parse_function<Func: Function>(
         loc,
         parse_ctx,
         ir_params_without_closure,
+        param_names.clone(),
         1,
         depth,
         filename,
@@ -848,7 +882,12 @@ fn post_parse_function<Func: Function>(
 
     // add the function to the ir_program
     let ir_funcidx = ir_program.add_func(ir::Func {
+        name: func_name,
         params: ir_params_with_closure,
+        // the closure param has no name in the source
+        param_names: std::iter::once(None)
+            .chain(param_names.into_vec())
+            .collect(),
         result: Some(ir::VarType::Any),
         expr: ir_func_body,
         signature_filter: Default::default(),
@@ -872,8 +911,10 @@ fn post_parse_function<Func: Function>(
                         next: None,
                     },
                 },
+                location: None,
             }),
         },
+        location: None,
     });
 
     // the returned expression (which returns the func variable)
@@ -884,12 +925,15 @@ fn post_parse_function<Func: Function>(
             local: ir::VarType::StructT {
                 typeidx: struct_idx,
             },
+            name: None,
             init: Some(Box::new(init_expr)),
             contained_expr: Box::new(ir::Expr {
                 vartype: Some(ir::VarType::Func),
                 kind: ir::ExprKind::Sequence { content: sequence },
+                location: None,
             }),
         },
+        location: None,
     };
 
     Ok(ret)
@@ -906,7 +950,12 @@ fn post_parse_statement<I: Iterator<Item = (Node, HashMap<String, Option<String>
     ir_program: &mut ir::Program,
 ) -> Result<(ir::Expr, I), CompileMessage<ParseProgramError>> {
     // we do not validate constraints or anything else (pre_parse should have done it)
-    match es_node.kind {
+    // declarations are not given the location of the statement, because they contain the rest of the scope
+    let location: Option<ir::SourceLocation> = match es_node.kind {
+        NodeKind::FunctionDeclaration(_) | NodeKind::VariableDeclaration(_) => None,
+        _ => as_opt_ir_sl(&es_node.loc, 0 /*FILE*/),
+    };
+    let (ir_expr, ret_more_stmt_attr_iter) = match es_node.kind {
         NodeKind::ExpressionStatement(stmt) => Ok((
             post_parse_expr_statement(
                 stmt,
@@ -955,6 +1004,38 @@ fn post_parse_statement<I: Iterator<Item = (Node, HashMap<String, Option<String>
             )?,
             more_stmt_attr_iter,
         )),
+        NodeKind::WhileStatement(stmt) => Ok((
+            post_parse_while_statement(
+                stmt,
+                es_node.loc,
+                parse_ctx,
+                depth,
+                num_locals,
+                filename,
+                ir_program,
+            )?,
+            more_stmt_attr_iter,
+        )),
+        NodeKind::ForStatement(stmt) => Ok((
+            post_parse_for_statement(
+                stmt,
+                es_node.loc,
+                parse_ctx,
+                depth,
+                num_locals,
+                filename,
+                ir_program,
+            )?,
+            more_stmt_attr_iter,
+        )),
+        NodeKind::BreakStatement(_) => Ok((
+            post_parse_break_statement(es_node.loc, parse_ctx, filename)?,
+            more_stmt_attr_iter,
+        )),
+        NodeKind::ContinueStatement(_) => Ok((
+            post_parse_continue_statement(es_node.loc, parse_ctx, filename)?,
+            more_stmt_attr_iter,
+        )),
         NodeKind::FunctionDeclaration(func_decl) => {
             if attributes.get("direct").is_some() {
                 // direct func declarations do not generate any ir::Expr in the current context
@@ -994,7 +1075,8 @@ fn post_parse_statement<I: Iterator<Item = (Node, HashMap<String, Option<String>
         ),
         NodeKind::EmptyStatement(_) => Ok((make_prim_undefined(), more_stmt_attr_iter)), // todo! IR optimisation should prune empty statments
         _ => pppanic(),
-    }
+    }?;
+    Ok((with_location(ir_expr, location), ret_more_stmt_attr_iter))
 }
 
 fn post_parse_toplevel_statement(
@@ -1012,7 +1094,8 @@ fn post_parse_toplevel_statement(
     // Also import statments to add names into `parse_ctx`, while export statements to add names to `exports`.
 
     // we do not validate constraints or anything else (pre_parse should have done it)
-    match es_node.kind {
+    let location: Option<ir::SourceLocation> = as_opt_ir_sl(&es_node.loc, 0 /*FILE*/);
+    let ir_expr: ir::Expr = match es_node.kind {
         NodeKind::ExpressionStatement(stmt) => {
             post_parse_expr_statement(stmt, es_node.loc, parse_ctx, 0, 0, filename, ir_program)
         }
@@ -1025,6 +1108,16 @@ fn post_parse_toplevel_statement(
         NodeKind::IfStatement(stmt) => {
             post_parse_if_statement(stmt, es_node.loc, parse_ctx, 0, 0, filename, ir_program)
         }
+        NodeKind::WhileStatement(stmt) => {
+            post_parse_while_statement(stmt, es_node.loc, parse_ctx, 0, 0, filename, ir_program)
+        }
+        NodeKind::ForStatement(stmt) => {
+            post_parse_for_statement(stmt, es_node.loc, parse_ctx, 0, 0, filename, ir_program)
+        }
+        NodeKind::BreakStatement(_) => post_parse_break_statement(es_node.loc, parse_ctx, filename),
+        NodeKind::ContinueStatement(_) => {
+            post_parse_continue_statement(es_node.loc, parse_ctx, filename)
+        }
         NodeKind::FunctionDeclaration(func_decl) => {
             if attributes.get("direct").is_some() {
                 // direct func declarations do not generate any ir::Expr in the current context
@@ -1057,6 +1150,7 @@ fn post_parse_toplevel_statement(
                         target: parse_ctx.get_target(&varlocid).unwrap().clone(),
                         expr: Box::new(rhs_expr),
                     },
+                    location: None,
                 })
             }
         }
@@ -1077,7 +1171,8 @@ fn post_parse_toplevel_statement(
         }
         NodeKind::EmptyStatement(_) => Ok(make_prim_undefined()), // todo! IR optimisation should prune empty statments
         _ => pppanic(),
-    }
+    }?;
+    Ok(with_location(ir_expr, location))
 }
 
 /*fn post_parse_toplevel_import_decl(
@@ -1168,6 +1263,7 @@ fn post_parse_return_statement(
                 ir_program,
             )?),
         },
+        location: None,
     })
 }
 
@@ -1212,6 +1308,7 @@ fn post_parse_if_statement(
                                 next: None,
                             },
                         },
+                        location: None,
                     }),
                     false_expr: Box::new(ir::Expr {
                         vartype: None,
@@ -1219,8 +1316,10 @@ fn post_parse_if_statement(
                             code: ir::error::ERROR_CODE_IF_STATEMENT_CONDITION_TYPE,
                             location: cond_loc,
                         },
+                        location: None,
                     }),
                 },
+                location: None,
             }),
             true_expr: Box::new({
                 let (block_stmt, loc) = as_block_statement_with_loc(*es_if.consequent);
@@ -1235,9 +1334,242 @@ fn post_parse_if_statement(
                 )?
             }),
         },
+        location: None,
+    })
+}
+
+fn post_parse_while_statement(
+    es_while: WhileStatement,
+    loc: Option<esSL>,
+    parse_ctx: &mut ParseState,
+    depth: usize,
+    num_locals: usize, // current number of IR locals
+    filename: Option<&str>,
+    ir_program: &mut ir::Program,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    post_parse_loop(
+        *es_while.test,
+        *es_while.body,
+        None,
+        None,
+        parse_ctx,
+        depth,
+        num_locals,
+        filename,
+        ir_program,
+    )
+}
+
+fn post_parse_for_statement(
+    es_for: ForStatement,
+    loc: Option<esSL>,
+    parse_ctx: &mut ParseState,
+    depth: usize,
+    num_locals: usize, // current number of IR locals
+    filename: Option<&str>,
+    ir_program: &mut ir::Program,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    // pre_parse() would have already hoisted the init part into an enclosing BlockStatement,
+    // and ensured that the test and update parts are present.
+    assert!(
+        es_for.init.is_none(),
+        "ICE: init of ForStatement should have been hoisted"
+    );
+
+    // JavaScript gives each iteration a fresh copy of the loop variables, which is only observable if they are captured by a closure.
+    // The captured (address-taken) loop variables live in the struct of the enclosing block (the one with the hoisted init part),
+    // so at the end of each iteration (before the update) we replace that struct with a copy of it.
+    // Closures made in earlier iterations keep referring to the old struct.
+    let opt_ir_copy_env: Option<ir::Expr> = es_for
+        .loop_vars
+        .iter()
+        .find_map(|varlocid| match parse_ctx.get_target(varlocid) {
+            Some(ir::TargetExpr::Local {
+                localidx,
+                next: Some(struct_field),
+            }) => Some((*localidx, struct_field.typeidx)),
+            _ => None,
+        })
+        .map(|(env_localidx, typeidx)| {
+            make_struct_copy(env_localidx, typeidx, num_locals, ir_program)
+        });
+
+    post_parse_loop(
+        *es_for.test.unwrap(),
+        *es_for.body,
+        opt_ir_copy_env,
+        Some(*es_for.update.unwrap()),
+        parse_ctx,
+        depth,
+        num_locals,
+        filename,
+        ir_program,
+    )
+}
+
+fn post_parse_loop(
+    es_test: Node,
+    es_body: Node,
+    opt_ir_copy_env: Option<ir::Expr>, // for loops only, if the loop variables need a fresh copy for each iteration
+    opt_es_update: Option<Node>,
+    parse_ctx: &mut ParseState,
+    depth: usize,
+    num_locals: usize, // current number of IR locals
+    filename: Option<&str>,
+    ir_program: &mut ir::Program,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    // Emits the following structure:
+    // Block {                                  <-- landing for `break`
+    //   Loop {
+    //     if (test) {} else { Break(1) }      (with a type check to ensure that the test is boolean type)
+    //     Block { body; undefined }            <-- landing for `continue`
+    //     copy_env;                            (for loops only, if necessary)
+    //     update;                              (for loops only)
+    //     Break(0)                             (jumps back to the beginning of the Loop)
+    //   }
+    // }
+    // The whole loop returns Undefined.
+
+    let cond_loc: ir::SourceLocation = as_ir_sl(&es_test.loc, 0 /*FILE*/);
+
+    let break_landing = parse_ctx.enter_landing();
+    parse_ctx.enter_landing();
+
+    let ir_test: ir::Expr =
+        post_parse_expr(es_test, parse_ctx, depth, num_locals, filename, ir_program)?;
+
+    let continue_landing = parse_ctx.enter_landing();
+    parse_ctx.enter_loop(break_landing, continue_landing);
+    let ir_body: ir::Expr = {
+        let (block_stmt, loc) = as_block_statement_with_loc(es_body);
+        post_parse_block_statement(
+            block_stmt, loc, parse_ctx, depth, num_locals, filename, ir_program,
+        )?
+    };
+    parse_ctx.leave_loop();
+    parse_ctx.leave_landing();
+
+    let opt_ir_update: Option<ir::Expr> = opt_es_update
+        .map(|es_update| {
+            post_parse_expr(
+                es_update, parse_ctx, depth, num_locals, filename, ir_program,
+            )
+        })
+        .transpose()?;
+
+    parse_ctx.leave_landing();
+    parse_ctx.leave_landing();
+
+    let mut loop_content: Vec<ir::Expr> = Vec::new();
+    loop_content.push(ir::Expr {
+        vartype: Some(ir::VarType::Undefined),
+        kind: ir::ExprKind::Conditional {
+            cond: Box::new(ir::Expr {
+                vartype: Some(ir::VarType::Boolean),
+                kind: ir::ExprKind::TypeCast {
+                    test: Box::new(ir_test),
+                    expected: ir::VarType::Boolean,
+                    create_narrow_local: true,
+                    true_expr: Box::new(ir::Expr {
+                        vartype: Some(ir::VarType::Boolean),
+                        kind: ir::ExprKind::VarName {
+                            source: ir::TargetExpr::Local {
+                                localidx: num_locals,
+                                next: None,
+                            },
+                        },
+                        location: None,
+                    }),
+                    false_expr: Box::new(ir::Expr {
+                        vartype: None,
+                        kind: ir::ExprKind::Trap {
+                            code: ir::error::ERROR_CODE_LOOP_CONDITION_TYPE,
+                            location: cond_loc,
+                        },
+                        location: None,
+                    }),
+                },
+                location: None,
+            }),
+            true_expr: Box::new(make_prim_undefined()),
+            false_expr: Box::new(make_break(1)),
+        },
+        location: None,
+    });
+    loop_content.push(ir::Expr {
+        vartype: Some(ir::VarType::Undefined),
+        kind: ir::ExprKind::Block {
+            expr: Box::new(ir::Expr {
+                vartype: Some(ir::VarType::Undefined),
+                kind: ir::ExprKind::Sequence {
+                    content: vec![ir_body, make_prim_undefined()],
+                },
+                location: None,
+            }),
+        },
+        location: None,
+    });
+    if let Some(ir_copy_env) = opt_ir_copy_env {
+        loop_content.push(ir_copy_env);
+    }
+    if let Some(ir_update) = opt_ir_update {
+        loop_content.push(ir_update);
+    }
+    loop_content.push(make_break(0));
+
+    Ok(ir::Expr {
+        vartype: Some(ir::VarType::Undefined),
+        kind: ir::ExprKind::Block {
+            expr: Box::new(ir::Expr {
+                vartype: None,
+                kind: ir::ExprKind::Loop {
+                    expr: Box::new(ir::Expr {
+                        vartype: None,
+                        kind: ir::ExprKind::Sequence {
+                            content: loop_content,
+                        },
+                        location: None,
+                    }),
+                },
+                location: None,
+            }),
+        },
+        location: None,
     })
 }
 
+fn post_parse_break_statement(
+    loc: Option<esSL>,
+    parse_ctx: &mut ParseState,
+    filename: Option<&str>,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    parse_ctx
+        .get_break_num_frames()
+        .map(make_break)
+        .ok_or_else(|| {
+            CompileMessage::new_error(
+                loc.into_sl(filename).to_owned(),
+                ParseProgramError::ESTreeError("Break statement must be inside a loop"),
+            )
+        })
+}
+
+fn post_parse_continue_statement(
+    loc: Option<esSL>,
+    parse_ctx: &mut ParseState,
+    filename: Option<&str>,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    parse_ctx
+        .get_continue_num_frames()
+        .map(make_break)
+        .ok_or_else(|| {
+            CompileMessage::new_error(
+                loc.into_sl(filename).to_owned(),
+                ParseProgramError::ESTreeError("Continue statement must be inside a loop"),
+            )
+        })
+}
+
 fn post_parse_direct_func_decl(
     es_func_decl: FunctionDeclaration,
     loc: Option<esSL>,
@@ -1273,10 +1605,13 @@ fn post_parse_func_decl<I: Iterator<Item = (Node, HashMap<String, Option<String>
     filename: Option<&str>,
     ir_program: &mut ir::Program,
 ) -> Result<(ir::Expr, I), CompileMessage<ParseProgramError>> {
-    let varlocid = as_varlocid(as_id_ref(&*es_func_decl.id).prevar.unwrap());
+    let es_id = as_id_ref(&es_func_decl.id);
+    let varlocid = as_varlocid(es_id.prevar.unwrap());
+    let name = es_id.name.clone();
 
     post_parse_decl_helper(
         varlocid,
+        Some(name),
         move |parse_ctx, depth, num_locals, filename, ir_program| {
             post_parse_function(
                 es_func_decl,
@@ -1356,6 +1691,7 @@ fn post_parse_var_decl<I: Iterator<Item = (Node, HashMap<String, Option<String>>
             let ir_seq_expr = ir::Expr {
                 vartype: Some(ir::VarType::Undefined),
                 kind: ir::ExprKind::Sequence { content: ret },
+                location: None,
             };
 
             ir_seq_expr
@@ -1377,11 +1713,13 @@ fn post_parse_var_decr_recurse<
     filename: Option<&str>,
     ir_program: &mut ir::Program,
 ) -> Result<(ir::Expr, (J, I)), CompileMessage<ParseProgramError>> {
-    let varlocid = as_varlocid(as_id(*es_var_decr.id).prevar.unwrap());
+    let es_id = as_id(*es_var_decr.id);
+    let varlocid = as_varlocid(es_id.prevar.unwrap());
     let init_expr = *es_var_decr.init.unwrap();
 
     post_parse_decl_helper(
         varlocid,
+        Some(es_id.name),
         move |parse_ctx, depth, num_locals, filename, ir_program| {
             post_parse_expr(
                 init_expr, parse_ctx, depth, num_locals, filename, ir_program,
@@ -1453,6 +1791,7 @@ fn post_parse_decl_helper<
     ) -> Result<ir::Expr, CompileMessage<ParseProgramError>>,
 >(
     varlocid: VarLocId,
+    name: Option<String>, // the source name of the variable, for debugging information
     es_rhs_expr_maker: G,
     forwarded: R,
     parse_ctx: &mut ParseState,
@@ -1473,6 +1812,7 @@ fn post_parse_decl_helper<
                     parse_ctx, depth, num_locals, filename, ir_program,
                 )?),
             },
+            location: None,
         };
         Ok((ret_expr, forwarded))
     } else {
@@ -1509,12 +1849,15 @@ fn post_parse_decl_helper<
                 vartype: Some(ir::VarType::Undefined),
                 kind: ir::ExprKind::Declaration {
                     local: ir::VarType::Any,
+                    name: name,
                     init: Some(Box::new(init_expr)),
                     contained_expr: Box::new(ir::Expr {
                         vartype: Some(ir::VarType::Undefined),
                         kind: ir::ExprKind::Sequence { content: sequence },
+                        location: None,
                     }),
                 },
+                location: None,
             },
             ret_fwd,
         ))
@@ -1549,6 +1892,7 @@ fn post_parse_toplevel_var_decl(
                     target: parse_ctx.get_target(&varlocid).unwrap().clone(),
                     expr: Box::new(rhs_expr),
                 },
+                location: None,
             })
         })
         .collect::<Result<Vec<ir::Expr>, CompileMessage<ParseProgramError>>>()?;
@@ -1566,6 +1910,7 @@ fn post_parse_toplevel_var_decl(
         let ir_seq_expr = ir::Expr {
             vartype: Some(ir::VarType::Undefined),
             kind: ir::ExprKind::Sequence { content: ret },
+            location: None,
         };
 
         ir_seq_expr
@@ -1580,7 +1925,8 @@ fn post_parse_expr(
     filename: Option<&str>,
     ir_program: &mut ir::Program,
 ) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
-    match es_expr.kind {
+    let location: Option<ir::SourceLocation> = as_opt_ir_sl(&es_expr.loc, 0 /*FILE*/);
+    let ir_expr: ir::Expr = match es_expr.kind {
         NodeKind::Identifier(es_id) => post_parse_varname(
             es_id,
             es_expr.loc,
@@ -1662,8 +2008,27 @@ fn post_parse_expr(
             filename,
             ir_program,
         ),
+        NodeKind::ArrayExpression(array_expr) => post_parse_array_expr(
+            array_expr,
+            es_expr.loc,
+            parse_ctx,
+            depth,
+            num_locals,
+            filename,
+            ir_program,
+        ),
+        NodeKind::MemberExpression(member_expr) => post_parse_member_expr(
+            member_expr,
+            es_expr.loc,
+            parse_ctx,
+            depth,
+            num_locals,
+            filename,
+            ir_program,
+        ),
         _ => pppanic(),
-    }
+    }?;
+    Ok(with_location(ir_expr, location))
 }
 
 fn post_parse_varname(
@@ -1688,6 +2053,7 @@ fn post_parse_varname(
                         kind: ir::ExprKind::VarName {
                             source: ir_target_expr.clone(),
                         },
+                        location: None,
                     }
                 } else {
                     make_trap_for_accessing_var_before_init(as_ir_sl(&loc, 0 /*FILE*/))
@@ -1733,6 +2099,7 @@ fn post_parse_direct_varname(
             funcidxs: funcidxs,
             closure: Box::new(make_prim_undefined()),
         },
+        location: None,
     })
 }
 
@@ -1749,15 +2116,19 @@ fn post_parse_literal(
         LiteralValue::String(string_val) => Ok(ir::Expr {
             vartype: Some(ir::VarType::String),
             kind: ir::ExprKind::PrimString { val: string_val },
+            location: None,
         }),
         LiteralValue::Boolean(bool_val) => Ok(ir::Expr {
             vartype: Some(ir::VarType::Boolean),
             kind: ir::ExprKind::PrimBoolean { val: bool_val },
+            location: None,
         }),
         LiteralValue::Number(number_val) => Ok(ir::Expr {
             vartype: Some(ir::VarType::Number),
             kind: ir::ExprKind::PrimNumber { val: number_val },
+            location: None,
         }),
+        LiteralValue::Null => Ok(make_prim_null()),
         _ => pppanic(),
     }
 }
@@ -1833,27 +2204,90 @@ fn post_parse_logical_expr(
     filename: Option<&str>,
     ir_program: &mut ir::Program,
 ) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
-    // todo! there might be a bug - logical operators do not currently short-circuit
-    // operators are Direct functions
-    let opt_func_name: Option<&str> =
-        builtins::resolve_logical_operator(es_logical_expr.operator.as_str());
-    let op_str = es_logical_expr.operator;
-    let func_name = opt_func_name.ok_or_else(|| {
-        CompileMessage::new_error(
-            loc.into_sl(filename).to_owned(),
-            ParseProgramError::SourceRestrictionLogicalOperatorError(op_str),
-        )
-    })?;
-    post_parse_direct_call_helper(
-        func_name,
-        Box::new([*es_logical_expr.left, *es_logical_expr.right]),
-        loc,
+    // see post_parse_cond_expr() for comparison
+    // `a && b` is lowered to `a ? b : false`, and `a || b` is lowered to `a ? true : b`.
+    // This gives the short-circuiting behaviour, since `b` is only evaluated when needed.
+    // Like js-slang, only the left operand is required to be a boolean.
+
+    let is_and: bool = match es_logical_expr.operator.as_str() {
+        "&&" => true,
+        "||" => false,
+        _ => {
+            return Err(CompileMessage::new_error(
+                loc.into_sl(filename).to_owned(),
+                ParseProgramError::SourceRestrictionLogicalOperatorError(es_logical_expr.operator),
+            ))
+        }
+    };
+
+    let left_loc: ir::SourceLocation = as_ir_sl(&es_logical_expr.left.loc, 0 /*FILE*/);
+
+    let cond: ir::Expr = ir::Expr {
+        vartype: Some(ir::VarType::Boolean),
+        kind: ir::ExprKind::TypeCast {
+            test: Box::new(post_parse_expr(
+                *es_logical_expr.left,
+                parse_ctx,
+                depth,
+                num_locals,
+                filename,
+                ir_program,
+            )?),
+            expected: ir::VarType::Boolean,
+            create_narrow_local: true,
+            true_expr: Box::new(ir::Expr {
+                vartype: Some(ir::VarType::Boolean),
+                kind: ir::ExprKind::VarName {
+                    source: ir::TargetExpr::Local {
+                        localidx: num_locals,
+                        next: None,
+                    },
+                },
+                location: None,
+            }),
+            false_expr: Box::new(ir::Expr {
+                vartype: None,
+                kind: ir::ExprKind::Trap {
+                    code: ir::error::ERROR_CODE_BINARY_OPERATOR_PARAM_TYPE,
+                    location: left_loc,
+                },
+                location: None,
+            }),
+        },
+        location: None,
+    };
+
+    let right: ir::Expr = post_parse_expr(
+        *es_logical_expr.right,
         parse_ctx,
         depth,
         num_locals,
         filename,
         ir_program,
-    )
+    )?;
+
+    // the value produced when the right operand is not evaluated
+    let short_circuit_val: ir::Expr = ir::Expr {
+        vartype: Some(ir::VarType::Boolean),
+        kind: ir::ExprKind::PrimBoolean { val: !is_and },
+        location: None,
+    };
+
+    let (true_expr, false_expr) = if is_and {
+        (right, short_circuit_val)
+    } else {
+        (short_circuit_val, right)
+    };
+
+    Ok(ir::Expr {
+        vartype: Some(ir::VarType::Any),
+        kind: ir::ExprKind::Conditional {
+            cond: Box::new(cond),
+            true_expr: Box::new(true_expr),
+            false_expr: Box::new(false_expr),
+        },
+        location: None,
+    })
 }
 
 fn post_parse_assign_expr(
@@ -1872,6 +2306,23 @@ fn post_parse_assign_expr(
             ParseProgramError::SourceRestrictionAssignmentOperatorError(es_assign_expr.operator),
         ));
     }
+    // an array element assignment, that also returns undefined
+    if let Node {
+        loc: lhs_loc,
+        kind: NodeKind::MemberExpression(member_expr),
+    } = *es_assign_expr.left
+    {
+        return post_parse_array_store(
+            member_expr,
+            lhs_loc,
+            *es_assign_expr.right,
+            parse_ctx,
+            depth,
+            num_locals,
+            filename,
+            ir_program,
+        );
+    }
     // an assignment expr, that returns undefined
     let varlocid = as_varlocid(as_id(*es_assign_expr.left).prevar.unwrap());
     Ok(ir::Expr {
@@ -1887,6 +2338,7 @@ fn post_parse_assign_expr(
                 ir_program,
             )?),
         },
+        location: None,
     })
 }
 
@@ -1931,6 +2383,7 @@ fn post_parse_cond_expr(
                                 next: None,
                             },
                         },
+                        location: None,
                     }),
                     false_expr: Box::new(ir::Expr {
                         vartype: None,
@@ -1938,8 +2391,10 @@ fn post_parse_cond_expr(
                             code: ir::error::ERROR_CODE_IF_STATEMENT_CONDITION_TYPE,
                             location: cond_loc,
                         },
+                        location: None,
                     }),
                 },
+                location: None,
             }),
             true_expr: Box::new(post_parse_expr(
                 *es_cond_expr.consequent,
@@ -1958,6 +2413,7 @@ fn post_parse_cond_expr(
                 ir_program,
             )?),
         },
+        location: None,
     })
 }
 
@@ -1975,6 +2431,25 @@ fn post_parse_call_expr(
     // TODO: should we detect direct calls anyway, because we have the post_parse_direct_call_helper()?
     // (since we wouldn't need to generate a lot of redundant things)
 
+    // `list` is variadic, so direct calls to it are expanded into nested calls to `pair`
+    if let NodeKind::Identifier(Identifier {
+        name,
+        prevar: Some(PreVar::Direct),
+    }) = &es_call_expr.callee.kind
+    {
+        if name.as_str() == builtins::LIST {
+            return post_parse_list_call(
+                es_call_expr.arguments,
+                loc,
+                parse_ctx,
+                depth,
+                num_locals,
+                filename,
+                ir_program,
+            );
+        }
+    }
+
     let callee_loc: ir::SourceLocation = as_ir_sl(&es_call_expr.callee.loc, 0 /*FILE*/);
 
     // We synthesise the typecheck to ensure that the func is a Func.
@@ -2001,6 +2476,7 @@ fn post_parse_call_expr(
                         next: None,
                     },
                 },
+                location: None,
             }),
             false_expr: Box::new(ir::Expr {
                 vartype: None,
@@ -2008,8 +2484,10 @@ fn post_parse_call_expr(
                     code: ir::error::ERROR_CODE_FUNCTION_APPLICATION_NOT_CALLABLE_TYPE,
                     location: callee_loc,
                 },
+                location: None,
             }),
         },
+        location: None,
     };
     post_parse_call_func_with_params_helper(
         func,
@@ -2043,9 +2521,251 @@ fn post_parse_call_func_with_params_helper(
             args: args,
             location: as_ir_sl(&loc, 0 /*FILE*/),
         },
+        location: None,
+    })
+}
+
+fn post_parse_list_call(
+    es_args: Vec<Node>,
+    loc: Option<esSL>,
+    parse_ctx: &mut ParseState,
+    depth: usize,
+    num_locals: usize, // current number of IR locals
+    filename: Option<&str>,
+    ir_program: &mut ir::Program,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    // Emits `pair(a, pair(b, pair(c, null)))` for `list(a, b, c)`.
+    // The args are still evaluated from left to right, because each pair evaluates its head before its tail.
+    let args: Vec<ir::Expr> = es_args
+        .into_iter()
+        .map(|arg| post_parse_expr(arg, parse_ctx, depth, num_locals, filename, ir_program))
+        .collect::<Result<Vec<ir::Expr>, CompileMessage<ParseProgramError>>>()?;
+    let mut ret: ir::Expr = make_prim_null();
+    for arg in args.into_iter().rev() {
+        ret = ir::Expr {
+            vartype: Some(ir::VarType::Any),
+            kind: ir::ExprKind::Appl {
+                func: Box::new(post_parse_direct_varname(
+                    builtins::PAIR,
+                    parse_ctx,
+                    depth,
+                    num_locals,
+                    filename,
+                    ir_program,
+                )?),
+                args: Box::new([arg, ret]),
+                location: as_ir_sl(&loc, 0 /*FILE*/),
+            },
+            location: None,
+        };
+    }
+    Ok(ret)
+}
+
+fn post_parse_array_expr(
+    es_array_expr: ArrayExpression,
+    loc: Option<esSL>,
+    parse_ctx: &mut ParseState,
+    depth: usize,
+    num_locals: usize, // current number of IR locals
+    filename: Option<&str>,
+    ir_program: &mut ir::Program,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    // Emits the following structure:
+    // Declaration (local: Array = PrimArray) {
+    //   ArrayStore(local, 0, a);
+    //   ArrayStore(local, 1, b);
+    //   ...
+    //   local
+    // }
+    // The elements are still evaluated from left to right.
+
+    let ir_sl: ir::SourceLocation = as_ir_sl(&loc, 0 /*FILE*/);
+
+    let mut sequence: Vec<ir::Expr> = es_array_expr
+        .elements
+        .into_iter()
+        .enumerate()
+        .map(|(i, opt_elem)| {
+            Ok(ir::Expr {
+                vartype: Some(ir::VarType::Undefined),
+                kind: ir::ExprKind::ArrayStore {
+                    array: Box::new(make_local_varname(num_locals, ir::VarType::Array)),
+                    index: Box::new(ir::Expr {
+                        vartype: Some(ir::VarType::Number),
+                        kind: ir::ExprKind::PrimNumber { val: i as f64 },
+                        location: None,
+                    }),
+                    expr: Box::new(post_parse_expr(
+                        opt_elem.unwrap(),
+                        parse_ctx,
+                        depth,
+                        num_locals + 1,
+                        filename,
+                        ir_program,
+                    )?),
+                    location: ir_sl,
+                },
+                location: None,
+            })
+        })
+        .collect::<Result<Vec<ir::Expr>, CompileMessage<ParseProgramError>>>()?;
+    sequence.push(make_local_varname(num_locals, ir::VarType::Array));
+
+    Ok(ir::Expr {
+        vartype: Some(ir::VarType::Array),
+        kind: ir::ExprKind::Declaration {
+            local: ir::VarType::Array,
+            name: None,
+            init: Some(Box::new(ir::Expr {
+                vartype: Some(ir::VarType::Array),
+                kind: ir::ExprKind::PrimArray,
+                location: None,
+            })),
+            contained_expr: Box::new(ir::Expr {
+                vartype: Some(ir::VarType::Array),
+                kind: ir::ExprKind::Sequence { content: sequence },
+                location: None,
+            }),
+        },
+        location: None,
     })
 }
 
+fn post_parse_member_expr(
+    es_member_expr: MemberExpression,
+    loc: Option<esSL>,
+    parse_ctx: &mut ParseState,
+    depth: usize,
+    num_locals: usize, // current number of IR locals
+    filename: Option<&str>,
+    ir_program: &mut ir::Program,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    // Emits the following structure:
+    // Declaration (local_a: Any = a) {
+    //   Declaration (local_i: Any = i) {
+    //     ArrayLoad(local_a, local_i)        (with type checks to ensure that local_a is an array and local_i is a number)
+    //   }
+    // }
+
+    let ir_sl: ir::SourceLocation = as_ir_sl(&loc, 0 /*FILE*/);
+
+    let ir_array: ir::Expr = post_parse_expr(
+        *es_member_expr.object,
+        parse_ctx,
+        depth,
+        num_locals,
+        filename,
+        ir_program,
+    )?;
+    let ir_index: ir::Expr = post_parse_expr(
+        *es_member_expr.property,
+        parse_ctx,
+        depth,
+        num_locals + 1,
+        filename,
+        ir_program,
+    )?;
+
+    Ok(make_any_declaration(
+        ir_array,
+        make_any_declaration(
+            ir_index,
+            make_array_access_typecheck(
+                num_locals,
+                num_locals + 1,
+                num_locals + 2,
+                ir::VarType::Any,
+                ir_sl,
+                |ir_array, ir_index| ir::Expr {
+                    vartype: Some(ir::VarType::Any),
+                    kind: ir::ExprKind::ArrayLoad {
+                        array: Box::new(ir_array),
+                        index: Box::new(ir_index),
+                        location: ir_sl,
+                    },
+                    location: None,
+                },
+            ),
+        ),
+    ))
+}
+
+fn post_parse_array_store(
+    es_member_expr: MemberExpression,
+    loc: Option<esSL>,
+    es_rhs: Node,
+    parse_ctx: &mut ParseState,
+    depth: usize,
+    num_locals: usize, // current number of IR locals
+    filename: Option<&str>,
+    ir_program: &mut ir::Program,
+) -> Result<ir::Expr, CompileMessage<ParseProgramError>> {
+    // Emits the following structure:
+    // Declaration (local_a: Any = a) {
+    //   Declaration (local_i: Any = i) {
+    //     Declaration (local_v: Any = v) {
+    //       ArrayStore(local_a, local_i, local_v)        (with type checks to ensure that local_a is an array and local_i is a number)
+    //     }
+    //   }
+    // }
+    // The type checks are done after evaluating the RHS, like in JavaScript.
+
+    let ir_sl: ir::SourceLocation = as_ir_sl(&loc, 0 /*FILE*/);
+
+    let ir_array: ir::Expr = post_parse_expr(
+        *es_member_expr.object,
+        parse_ctx,
+        depth,
+        num_locals,
+        filename,
+        ir_program,
+    )?;
+    let ir_index: ir::Expr = post_parse_expr(
+        *es_member_expr.property,
+        parse_ctx,
+        depth,
+        num_locals + 1,
+        filename,
+        ir_program,
+    )?;
+    let ir_rhs: ir::Expr = post_parse_expr(
+        es_rhs,
+        parse_ctx,
+        depth,
+        num_locals + 2,
+        filename,
+        ir_program,
+    )?;
+
+    Ok(make_any_declaration(
+        ir_array,
+        make_any_declaration(
+            ir_index,
+            make_any_declaration(
+                ir_rhs,
+                make_array_access_typecheck(
+                    num_locals,
+                    num_locals + 1,
+                    num_locals + 3,
+                    ir::VarType::Undefined,
+                    ir_sl,
+                    |ir_array, ir_index| ir::Expr {
+                        vartype: Some(ir::VarType::Undefined),
+                        kind: ir::ExprKind::ArrayStore {
+                            array: Box::new(ir_array),
+                            index: Box::new(ir_index),
+                            expr: Box::new(make_local_varname(num_locals + 2, ir::VarType::Any)),
+                            location: ir_sl,
+                        },
+                        location: None,
+                    },
+                ),
+            ),
+        ),
+    ))
+}
+
 fn post_parse_direct_call_helper(
     func_name: &str,
     params: Box<[Node]>,
@@ -2079,6 +2799,169 @@ fn make_prim_undefined() -> ir::Expr {
     ir::Expr {
         vartype: Some(ir::VarType::Undefined),
         kind: ir::ExprKind::PrimUndefined,
+        location: None,
+    }
+}
+
+fn make_prim_null() -> ir::Expr {
+    ir::Expr {
+        vartype: Some(ir::VarType::Null),
+        kind: ir::ExprKind::PrimNull,
+        location: None,
+    }
+}
+
+// Makes a Break (with Undefined value) that jumps out of the given number of frames
+fn make_break(num_frames: usize) -> ir::Expr {
+    ir::Expr {
+        vartype: None,
+        kind: ir::ExprKind::Break {
+            num_frames: num_frames,
+            expr: Box::new(make_prim_undefined()),
+        },
+        location: None,
+    }
+}
+
+// Makes a VarName that reads the given local
+fn make_local_varname(localidx: usize, ir_vartype: ir::VarType) -> ir::Expr {
+    ir::Expr {
+        vartype: Some(ir_vartype),
+        kind: ir::ExprKind::VarName {
+            source: ir::TargetExpr::Local {
+                localidx: localidx,
+                next: None,
+            },
+        },
+        location: None,
+    }
+}
+
+// Makes an Assign that replaces the struct in the local `localidx` with a new struct that has the same field values
+// (`num_locals` is the current number of IR locals; the new struct is put into a temporary local while it is being filled)
+fn make_struct_copy(
+    localidx: usize,
+    typeidx: usize,
+    num_locals: usize,
+    ir_program: &ir::Program,
+) -> ir::Expr {
+    let struct_type = ir::VarType::StructT { typeidx: typeidx };
+    let mut content: Vec<ir::Expr> = ir_program.struct_types[typeidx]
+        .iter()
+        .copied()
+        .enumerate()
+        .map(|(fieldidx, field_vartype)| {
+            let field = |localidx: usize| ir::TargetExpr::Local {
+                localidx: localidx,
+                next: Some(Box::new(ir::StructField {
+                    typeidx: typeidx,
+                    fieldidx: fieldidx,
+                    next: None,
+                })),
+            };
+            ir::Expr {
+                vartype: Some(ir::VarType::Undefined),
+                kind: ir::ExprKind::Assign {
+                    target: field(num_locals),
+                    expr: Box::new(ir::Expr {
+                        vartype: Some(field_vartype),
+                        kind: ir::ExprKind::VarName {
+                            source: field(localidx),
+                        },
+                        location: None,
+                    }),
+                },
+                location: None,
+            }
+        })
+        .collect();
+    content.push(make_local_varname(num_locals, struct_type));
+    ir::Expr {
+        vartype: Some(ir::VarType::Undefined),
+        kind: ir::ExprKind::Assign {
+            target: ir::TargetExpr::Local {
+                localidx: localidx,
+                next: None,
+            },
+            expr: Box::new(ir::Expr {
+                vartype: Some(struct_type),
+                kind: ir::ExprKind::Declaration {
+                    local: struct_type,
+                    name: None,
+                    init: Some(Box::new(ir::Expr {
+                        vartype: Some(struct_type),
+                        kind: ir::ExprKind::PrimStructT { typeidx: typeidx },
+                        location: None,
+                    })),
+                    contained_expr: Box::new(ir::Expr {
+                        vartype: Some(struct_type),
+                        kind: ir::ExprKind::Sequence { content: content },
+                        location: None,
+                    }),
+                },
+                location: None,
+            }),
+        },
+        location: None,
+    }
+}
+
+// Makes a Declaration of a new Any local initialized to `init`, which can be used in `contained_expr`
+fn make_any_declaration(init: ir::Expr, contained_expr: ir::Expr) -> ir::Expr {
+    ir::Expr {
+        vartype: contained_expr.vartype,
+        kind: ir::ExprKind::Declaration {
+            local: ir::VarType::Any,
+            name: None,
+            init: Some(Box::new(init)),
+            contained_expr: Box::new(contained_expr),
+        },
+        location: None,
+    }
+}
+
+// Makes the type checks to ensure that the Any local `array_localidx` is an array and the Any local `index_localidx` is a number,
+// trapping with ERROR_CODE_ARRAY_ACCESS_TYPE otherwise.
+// The narrowed array and index are put into new locals starting from `narrow_localidx`, and passed to `make_access`.
+fn make_array_access_typecheck<F: FnOnce(ir::Expr, ir::Expr) -> ir::Expr>(
+    array_localidx: usize,
+    index_localidx: usize,
+    narrow_localidx: usize,
+    ir_vartype: ir::VarType,
+    ir_sl: ir::SourceLocation,
+    make_access: F,
+) -> ir::Expr {
+    let make_trap = || ir::Expr {
+        vartype: None,
+        kind: ir::ExprKind::Trap {
+            code: ir::error::ERROR_CODE_ARRAY_ACCESS_TYPE,
+            location: ir_sl,
+        },
+        location: None,
+    };
+    ir::Expr {
+        vartype: Some(ir_vartype),
+        kind: ir::ExprKind::TypeCast {
+            test: Box::new(make_local_varname(array_localidx, ir::VarType::Any)),
+            expected: ir::VarType::Array,
+            create_narrow_local: true,
+            true_expr: Box::new(ir::Expr {
+                vartype: Some(ir_vartype),
+                kind: ir::ExprKind::TypeCast {
+                    test: Box::new(make_local_varname(index_localidx, ir::VarType::Any)),
+                    expected: ir::VarType::Number,
+                    create_narrow_local: true,
+                    true_expr: Box::new(make_access(
+                        make_local_varname(narrow_localidx, ir::VarType::Array),
+                        make_local_varname(narrow_localidx + 1, ir::VarType::Number),
+                    )),
+                    false_expr: Box::new(make_trap()),
+                },
+                location: None,
+            }),
+            false_expr: Box::new(make_trap()),
+        },
+        location: None,
     }
 }
 
@@ -2089,6 +2972,7 @@ fn make_trap_for_accessing_var_before_init(ir_sl: ir::SourceLocation) -> ir::Exp
             code: ir::error::ERROR_CODE_ACCESS_VAR_BEFORE_INIT,
             location: ir_sl,
         },
+        location: None,
     }
 }
 
@@ -2164,6 +3048,27 @@ fn as_id_ref(es_node: &Node) -> &Identifier {
     }
 }
 
+// Returns the source names of the given params (which are all Identifiers in Source).
+fn make_param_names(es_params: &[Node]) -> Box<[Option<String>]> {
+    es_params
+        .iter()
+        .map(|es_param| Some(as_id_ref(es_param).name.clone()))
+        .collect()
+}
+
+// Like as_ir_sl(), but returns None if the ESTree node has no location.
+fn as_opt_ir_sl(opt_es_sl: &Option<SourceLocation>, fileidx: u32) -> Option<ir::SourceLocation> {
+    opt_es_sl.as_ref().map(|_| as_ir_sl(opt_es_sl, fileidx))
+}
+
+// Gives the location of an ESTree node to the ir::Expr generated from it, unless it already has a (more precise) location.
+fn with_location(mut ir_expr: ir::Expr, location: Option<ir::SourceLocation>) -> ir::Expr {
+    if ir_expr.location.is_none() {
+        ir_expr.location = location;
+    }
+    ir_expr
+}
+
 // TODO: store both line and column, and make fileidx work.
 fn as_ir_sl(opt_es_sl: &Option<SourceLocation>, fileidx: u32) -> ir::SourceLocation {
     let (start, end) = match opt_es_sl {
@@ -339,12 +339,27 @@ fn pre_parse_statement(
         NodeKind::VariableDeclaration(var_decl) => {
             pre_parse_var_decl(var_decl, &es_node.loc, name_ctx, depth, filename)
         }
+        NodeKind::WhileStatement(stmt) => {
+            pre_parse_while_statement(stmt, &es_node.loc, name_ctx, depth, filename)
+        }
+        NodeKind::ForStatement(_) => pre_parse_for_statement(es_node, name_ctx, depth, filename),
+        NodeKind::BreakStatement(BreakStatement { label })
+        | NodeKind::ContinueStatement(ContinueStatement { label }) => {
+            if label.is_some() {
+                Err(CompileMessage::new_error(
+                    es_node.loc.into_sl(filename).to_owned(),
+                    ParseProgramError::SourceRestrictionError(
+                        "Labelled break and continue statements are not allowed",
+                    ),
+                ))
+            } else {
+                Ok(BTreeMap::new()) // break and continue do not use any variables
+            }
+        }
         NodeKind::EmptyStatement(_) => Ok(BTreeMap::new()), // EmptyStatement does not use any variables
         NodeKind::DebuggerStatement(_)
         | NodeKind::WithStatement(_)
-        | NodeKind::LabeledStatement(_)
-        | NodeKind::BreakStatement(_)
-        | NodeKind::ContinueStatement(_) => Err(CompileMessage::new_error(
+        | NodeKind::LabeledStatement(_) => Err(CompileMessage::new_error(
             es_node.loc.into_sl(filename).to_owned(),
             ParseProgramError::ESTreeError("This statement type is not allowed"),
         )),
@@ -386,7 +401,19 @@ fn pre_parse_expr_statement(
     filename: Option<&str>,
 ) -> Result<BTreeMap<VarLocId, Usage>, CompileMessage<ParseProgramError>> {
     // we have to detect the AssignmentExpression here, since in Source AssignmentExpression is not allowed to be nested.
-    let es_expr_node: &mut Node = &mut *es_expr_stmt.expression;
+    pre_parse_expr_or_assignment(&mut *es_expr_stmt.expression, name_ctx, depth, filename)
+}
+
+/**
+ * Like pre_parse_expr, but also allows a (non-nested) AssignmentExpression.
+ * Used for expression statements and the update part of for statements.
+ */
+fn pre_parse_expr_or_assignment(
+    es_expr_node: &mut Node,
+    name_ctx: &mut HashMap<String, PreVar>, // contains all names referenceable from outside the current sequence
+    depth: usize,
+    filename: Option<&str>,
+) -> Result<BTreeMap<VarLocId, Usage>, CompileMessage<ParseProgramError>> {
     if let NodeKind::AssignmentExpression(AssignmentExpression {
         operator,
         left,
@@ -396,13 +423,17 @@ fn pre_parse_expr_statement(
         match operator.as_str() {
             "=" => match &mut **left {
                 Node {
-                    loc: _,
+                    loc,
                     kind: NodeKind::Identifier(Identifier { name, prevar }),
                 } => {
                     let rhs_expr = pre_parse_expr(&mut **right, name_ctx, depth, filename)?;
-                    let resvar = *name_ctx.get(name.as_str()).unwrap();
-                    assert!(*prevar == Some(resvar)); // they should already have a prevar attached
-                                                      // note: this is probably a bug, they would not have prevar attached yet...
+                    let resvar = *name_ctx.get(name.as_str()).ok_or_else(|| {
+                        CompileMessage::new_error(
+                            loc.into_sl(filename).to_owned(),
+                            ParseProgramError::UndeclaredNameError(name.clone()),
+                        )
+                    })?;
+                    *prevar = Some(resvar); // save the variable location
                     let varlocid = match resvar {
                         PreVar::Target(varlocid) => varlocid,
                         PreVar::Direct => panic!("ICE: Should be VarLocId"),
@@ -420,10 +451,20 @@ fn pre_parse_expr_statement(
                         ))
                     }
                 }
+                Node {
+                    loc,
+                    kind: NodeKind::MemberExpression(member_expr),
+                } => {
+                    // array element assignment, i.e. a[i] = v
+                    // JS evaluates the array, then the index, then the RHS, before doing the actual assignment
+                    let lhs_expr = pre_parse_member_expr(member_expr, loc, name_ctx, depth, filename)?;
+                    let rhs_expr = pre_parse_expr(&mut **right, name_ctx, depth, filename)?;
+                    Ok(varusage::merge_series(lhs_expr, rhs_expr))
+                }
                 Node { loc, kind: _ } => Err(CompileMessage::new_error(
                     loc.into_sl(filename).to_owned(),
                     ParseProgramError::ESTreeError(
-                        "Expected ESTree Identifier at LHS of AssignmentExpression",
+                        "Expected ESTree Identifier or MemberExpression at LHS of AssignmentExpression",
                     ),
                 )),
             },
@@ -509,6 +550,144 @@ fn pre_parse_if_statement(
     }
 }
 
+fn pre_parse_while_statement(
+    es_while: &mut WhileStatement,
+    loc: &Option<esSL>,
+    name_ctx: &mut HashMap<String, PreVar>, // contains all names referenceable from outside the current sequence
+    depth: usize,
+    filename: Option<&str>,
+) -> Result<BTreeMap<VarLocId, Usage>, CompileMessage<ParseProgramError>> {
+    if let NodeKind::BlockStatement(es_body_block) = &mut es_while.body.kind {
+        // the test and the body are executed repeatedly, i.e. the result is (a + b)*
+        Ok(varusage::wrap_loop(varusage::merge_series(
+            pre_parse_expr(&mut *es_while.test, name_ctx, depth, filename)?,
+            pre_parse_block_statement(
+                es_body_block,
+                &es_while.body.loc,
+                name_ctx,
+                depth,
+                filename,
+            )?,
+        )))
+    } else {
+        Err(CompileMessage::new_error(
+            loc.into_sl(filename).to_owned(),
+            ParseProgramError::SourceRestrictionError("Body of while statement must be a block"),
+        ))
+    }
+}
+
+/**
+ * If the for statement has an init part, it is hoisted out so that the whole node becomes
+ * `{ <init>; for (; <test>; <update>) <body> }`, and the new block is pre-parsed instead.
+ * This gives the variables declared in the init part their own scope, enclosing the loop.
+ * Post-parse hence only ever sees for statements without an init part.
+ * The variables declared in the init part are saved in `loop_vars`, because post-parse gives each iteration a fresh copy of them.
+ */
+fn pre_parse_for_statement(
+    es_node: &mut Node,
+    name_ctx: &mut HashMap<String, PreVar>, // contains all names referenceable from outside the current sequence
+    depth: usize,
+    filename: Option<&str>,
+) -> Result<BTreeMap<VarLocId, Usage>, CompileMessage<ParseProgramError>> {
+    let es_for: &mut ForStatement = if let NodeKind::ForStatement(es_for) = &mut es_node.kind {
+        es_for
+    } else {
+        panic!("ICE: Should be ForStatement");
+    };
+
+    if let Some(es_init_node) = es_for.init.take() {
+        // hoist the init part into a new block
+        let es_init_stmt: Node = match es_init_node.kind {
+            NodeKind::VariableDeclaration(_) => *es_init_node,
+            _ => Node {
+                loc: es_init_node.loc.clone(),
+                kind: NodeKind::ExpressionStatement(ExpressionStatement {
+                    expression: es_init_node,
+                }),
+            },
+        };
+        let es_for_kind = std::mem::replace(
+            &mut es_node.kind,
+            NodeKind::EmptyStatement(EmptyStatement {}),
+        );
+        es_node.kind = NodeKind::BlockStatement(BlockStatement {
+            body: vec![
+                es_init_stmt,
+                Node {
+                    loc: es_node.loc.clone(),
+                    kind: es_for_kind,
+                },
+            ],
+            address_taken_vars: Vec::new(),
+            direct_funcs: Vec::new(),
+        });
+        return if let NodeKind::BlockStatement(es_block) = &mut es_node.kind {
+            let ret = pre_parse_block_statement(es_block, &es_node.loc, name_ctx, depth, filename)?;
+            let loop_vars: Vec<VarLocId> = match &es_block.body[0].kind {
+                NodeKind::VariableDeclaration(var_decl) => var_decl
+                    .declarations
+                    .iter()
+                    .filter_map(|decl| match &decl.kind {
+                        NodeKind::VariableDeclarator(VariableDeclarator { id, init: _ }) => {
+                            match &id.kind {
+                                NodeKind::Identifier(Identifier {
+                                    name: _,
+                                    prevar: Some(PreVar::Target(varlocid)),
+                                }) => Some(*varlocid),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if let NodeKind::ForStatement(es_for) = &mut es_block.body[1].kind {
+                es_for.loop_vars = loop_vars;
+            }
+            Ok(ret)
+        } else {
+            unreachable!("ICE: es_node was just replaced by a BlockStatement");
+        };
+    }
+
+    match (&mut es_for.test, &mut es_for.update) {
+        (Some(es_test), Some(es_update)) => {
+            if let NodeKind::BlockStatement(es_body_block) = &mut es_for.body.kind {
+                // the test, the body and the update are executed repeatedly, i.e. the result is (a + b + c)*
+                let test_ret = pre_parse_expr(&mut **es_test, name_ctx, depth, filename)?;
+                let body_ret = pre_parse_block_statement(
+                    es_body_block,
+                    &es_for.body.loc,
+                    name_ctx,
+                    depth,
+                    filename,
+                )?;
+                let update_ret =
+                    pre_parse_expr_or_assignment(&mut **es_update, name_ctx, depth, filename)?;
+                Ok(varusage::wrap_loop(varusage::merge_series(
+                    varusage::merge_series(test_ret, body_ret),
+                    update_ret,
+                )))
+            } else {
+                Err(CompileMessage::new_error(
+                    es_node.loc.into_sl(filename).to_owned(),
+                    ParseProgramError::SourceRestrictionError(
+                        "Body of for statement must be a block",
+                    ),
+                ))
+            }
+        }
+        _ => Err(CompileMessage::new_error(
+            es_node.loc.into_sl(filename).to_owned(),
+            ParseProgramError::SourceRestrictionError(
+                "Test and update of for statement must be present",
+            ),
+        )),
+    }
+}
+
 /**
  * This is a normal function declaration, not the direct kind.  So it is equivalent to a const declaration.
  */
@@ -783,13 +962,10 @@ fn pre_parse_expr(
             pre_parse_identifier_use(identifier, &es_expr.loc, name_ctx, depth, filename)
         }
         NodeKind::Literal(literal) => match literal.value {
-            LiteralValue::String(_) | LiteralValue::Boolean(_) | LiteralValue::Number(_) => {
-                Ok(BTreeMap::new())
-            }
-            LiteralValue::Null => Err(CompileMessage::new_error(
-                es_expr.loc.into_sl(filename).to_owned(),
-                ParseProgramError::SourceRestrictionError("Null literal not allowed"),
-            )),
+            LiteralValue::String(_)
+            | LiteralValue::Boolean(_)
+            | LiteralValue::Null
+            | LiteralValue::Number(_) => Ok(BTreeMap::new()),
             LiteralValue::RegExp => Err(CompileMessage::new_error(
                 es_expr.loc.into_sl(filename).to_owned(),
                 ParseProgramError::SourceRestrictionError("Regular expression not allowed"),
@@ -860,6 +1036,31 @@ fn pre_parse_expr(
                     })
                 })
         }
+        NodeKind::ArrayExpression(array_expr) => {
+            // array literal, i.e. [a, b, ...]
+            // JS requires 'a' to be evaluated first, followed by 'b', etc.
+            let loc = &es_expr.loc;
+            array_expr
+                .elements
+                .iter_mut()
+                .fold(Ok(BTreeMap::new()), |r_prev, opt_elem| {
+                    r_prev.and_then(|prev| match opt_elem {
+                        Some(elem) => Ok(varusage::merge_series(
+                            prev,
+                            pre_parse_expr(elem, name_ctx, depth, filename)?,
+                        )),
+                        None => Err(CompileMessage::new_error(
+                            loc.into_sl(filename).to_owned(),
+                            ParseProgramError::SourceRestrictionError(
+                                "Holes in array literals not allowed",
+                            ),
+                        )),
+                    })
+                })
+        }
+        NodeKind::MemberExpression(member_expr) => {
+            pre_parse_member_expr(member_expr, &es_expr.loc, name_ctx, depth, filename)
+        }
         _ => Err(CompileMessage::new_error(
             es_expr.loc.into_sl(filename).to_owned(),
             ParseProgramError::ESTreeError("Expression node expected"),
@@ -867,6 +1068,29 @@ fn pre_parse_expr(
     }
 }
 
+/**
+ * Array element access, i.e. a[i].
+ * Used for both reading and assigning to an array element.
+ */
+fn pre_parse_member_expr(
+    member_expr: &mut MemberExpression,
+    loc: &Option<esSL>,
+    name_ctx: &mut HashMap<String, PreVar>,
+    depth: usize,
+    filename: Option<&str>,
+) -> Result<BTreeMap<VarLocId, Usage>, CompileMessage<ParseProgramError>> {
+    if !member_expr.computed {
+        return Err(CompileMessage::new_error(
+            loc.into_sl(filename).to_owned(),
+            ParseProgramError::SourceRestrictionError("Object property access not allowed"),
+        ));
+    }
+    // JS requires 'a' to be evaluated first, followed by 'i'
+    let object = pre_parse_expr(&mut *member_expr.object, name_ctx, depth, filename)?;
+    let property = pre_parse_expr(&mut *member_expr.property, name_ctx, depth, filename)?;
+    Ok(varusage::merge_series(object, property))
+}
+
 fn pre_parse_identifier_use(
     es_id: &mut Identifier,
     loc: &Option<esSL>,
@@ -9,6 +9,7 @@ mod frontendvar;
 mod func;
 mod import_name_resolver;
 mod importer;
+mod library;
 mod parse_state;
 
 use async_trait::async_trait;
@@ -29,6 +30,8 @@ use std::marker::Send;
 use std::result::Result;
 
 use estree::*;
+use library::FuncShift;
+pub use library::Library;
 
 pub type ProgramPreExports = VarCtx<String, VarValue<VarLocId, Box<[ir::VarType]>>>;
 pub type ParseState = parse_state::ParseState;
@@ -40,15 +43,18 @@ pub type ReplContext = (HashMap<String, PreVar>, ParseState, usize);
 enum SourceItem {
     ESTree(estree::Node),
     ImportSpec(importer::ImportSpec),
+    Precompiled(String), // a module in the library being linked, by its resolved name
+    LibraryRoot(Box<[String]>), // the names of the modules to compile into a library (only used as the root of the dependency graph)
 }
 
 #[derive(Copy, Clone)]
-struct SourceFetcher<F> {
+struct SourceFetcher<'l, F> {
     raw_fetch: F,
+    library: Option<&'l Library>, // modules in the library are linked instead of fetched
 }
 //#[async_trait(?Send)]
-impl<Fut: Future<Output = Option<String>>, F: 'static + Copy + FnOnce(String) -> Fut>
-    dep_graph::Fetcher<SourceItem> for SourceFetcher<F>
+impl<'l, Fut: Future<Output = Option<String>>, F: 'static + Copy + FnOnce(String) -> Fut>
+    dep_graph::Fetcher<SourceItem> for SourceFetcher<'l, F>
 {
     fn fetch<'a>(
         self,
@@ -57,8 +63,16 @@ impl<Fut: Future<Output = Option<String>>, F: 'static + Copy + FnOnce(String) ->
     ) -> std::pin::Pin<
         Box<dyn 'a + Future<Output = Result<SourceItem, CompileMessage<FetcherError>>>>,
     > {
+        if let Some(library) = self.library {
+            if library.has_module(name) {
+                return Box::pin(std::future::ready(Ok(SourceItem::Precompiled(
+                    name.to_owned(),
+                ))));
+            }
+        }
+        let raw_fetch = self.raw_fetch;
         Box::pin((|| async move {
-            (self.raw_fetch)(name.to_owned()).await.map_or_else(
+            (raw_fetch)(name.to_owned()).await.map_or_else(
                 || {
                     Err(
                         CompileMessage::new_error(sl.to_owned(), FetchError::new(name.to_owned()))
@@ -119,10 +133,123 @@ impl<'a> dep_graph::ExtractDeps<'a> for SourceItem {
                 }),
             ),
             SourceItem::ImportSpec(import_spec) => Box::new(std::iter::empty()),
+            // the library already contains the modules that this module depends on
+            SourceItem::Precompiled(_) => Box::new(std::iter::empty()),
+            SourceItem::LibraryRoot(names) => Box::new(names.iter().map(|name| {
+                (
+                    import_name_resolver::resolve(name.as_str(), None),
+                    plSLRef::entire_file(None),
+                )
+            })),
+        }
+    }
+}
+
+/**
+ * The state of the frontend before the first module that is not in a library is compiled.
+ */
+struct ProgramStart {
+    ir_program: ir::Program,
+    start_idx: usize,
+    name_ctx: HashMap<String, PreVar>, // the builtins
+    parse_state: ParseState,           // the builtins
+    ir_toplevel_sequence: Vec<ir::Expr>,
+}
+
+impl ProgramStart {
+    /**
+     * Makes a new program containing only the given imports and the builtins.
+     */
+    fn new(imports: Vec<ir::Import>) -> ProgramStart {
+        // construct the ir_program with the given imports
+        let mut ir_program = ir::Program::new_with_imports(imports.into_boxed_slice());
+
+        // contains builtins, e.g. __string_to_number(), and __undefined.
+        // The builtins are encoded as string, e.g. "+", "-", etc, and are all Direct
+        // the mapping is in builtins module, there is a special transformation for unary minus to avoid name clash
+        // todo: also add the automatic imports
+        let mut start_idx = 0;
+        let (name_ctx, parse_state): (HashMap<String, PreVar>, ParseState) =
+            builtins::state_with_builtins(&mut start_idx, &mut ir_program);
+        ProgramStart {
+            ir_program: ir_program,
+            start_idx: start_idx,
+            name_ctx: name_ctx,
+            parse_state: parse_state,
+            ir_toplevel_sequence: Vec::new(),
         }
     }
 }
 
+/**
+ * Finds all the FFI imports in the dependency graph, sorted and deduplicated.
+ */
+fn collect_imports(dep_graph: &dep_graph::Graph<SourceItem>) -> Vec<ir::Import> {
+    let mut imports: Vec<ir::Import> = dep_graph
+        .topological_traverse()
+        .filter_map(|(source_item, _)| {
+            if let SourceItem::ImportSpec(import_spec) = source_item {
+                Some(import_spec)
+            } else {
+                None
+            }
+        })
+        .flat_map(|import_spec| import_spec.content.iter().map(|(_, import)| import))
+        .cloned()
+        .collect();
+
+    // sort and deduplicate the imports so we don't have duplicated imports
+    // note: we can import the same module+entity pair under multiple signatures, this is allowed in ir and wasm
+    imports.sort_unstable();
+    imports.dedup();
+    imports
+}
+
+/**
+ * Compiles a module that the main program depends on (other than the main program itself), and returns its exports.
+ * `linked_library` is the library being linked (if any), which provides the Precompiled modules.
+ */
+fn parse_dep<L: Logger>(
+    i: usize,
+    deps: Box<[&(ProgramPreExports, ParseState)]>,
+    source_item: SourceItem,
+    filename: Option<String>,
+    start: &mut ProgramStart,
+    import_funcidx_map: &HashMap<ir::Import, ir::FuncIdx>,
+    linked_library: Option<(&Library, FuncShift)>,
+    logger: &L,
+) -> Result<(ProgramPreExports, ParseState), ()> {
+    match source_item {
+        SourceItem::ESTree(es_program) => func::parse_dep_program(
+            &start.name_ctx,
+            &start.parse_state,
+            es_program,
+            deps,
+            &mut start.start_idx,
+            filename,
+            i,
+            &mut start.ir_program,
+            &mut start.ir_toplevel_sequence,
+        )
+        .map_err(|cm| {
+            logger.log(cm);
+        }),
+        SourceItem::ImportSpec(import_spec) => {
+            assert!(deps.is_empty(), "Import spec should be empty");
+            Ok(importer::make_export_state(
+                import_spec,
+                i,
+                import_funcidx_map,
+            ))
+        }
+        SourceItem::Precompiled(name) => {
+            let (library, shift) = linked_library.expect("Precompiled module without a library");
+            Ok(library.module_exports(name.as_str(), shift))
+        }
+        SourceItem::LibraryRoot(_) => panic!("Library root cannot be a dependency"),
+    }
+}
+
 /// Main entry point for the frontend.  Call this and everything will work.
 pub async fn run_frontend<
     L: Logger,
@@ -132,6 +259,34 @@ pub async fn run_frontend<
     estree_str: String,
     raw_fetch: F,
     logger: L,
+) -> Result<(ReplContext, ir::Program), ()> {
+    run_frontend_impl(estree_str, raw_fetch, None, logger).await
+}
+
+/// Like `run_frontend()`, but the modules in the given library are linked from it instead of being fetched and compiled.
+/// All the modules in the library become part of the program (and their toplevel code is run), even if they are not imported.
+pub async fn run_frontend_with_library<
+    L: Logger,
+    F: 'static + Copy + FnOnce(String) -> Fut,
+    Fut: Future<Output = Option<String>>,
+>(
+    estree_str: String,
+    raw_fetch: F,
+    library: &Library,
+    logger: L,
+) -> Result<(ReplContext, ir::Program), ()> {
+    run_frontend_impl(estree_str, raw_fetch, Some(library), logger).await
+}
+
+async fn run_frontend_impl<
+    L: Logger,
+    F: 'static + Copy + FnOnce(String) -> Fut,
+    Fut: Future<Output = Option<String>>,
+>(
+    estree_str: String,
+    raw_fetch: F,
+    opt_library: Option<&Library>,
+    logger: L,
 ) -> Result<(ReplContext, ir::Program), ()> {
     // parse the given string as estree
     let es_program: estree::Node = serde_json::from_str(estree_str.as_str())
@@ -140,11 +295,12 @@ pub async fn run_frontend<
         })
         .log_err(&logger)?;
 
-    // fetch and parse all the import files
+    // fetch and parse all the import files (except those in the library)
     let dep_graph = dep_graph::Graph::try_async_build_from_root(
         SourceItem::ESTree(es_program),
         SourceFetcher::<F> {
             raw_fetch: raw_fetch,
+            library: opt_library,
         },
     )
     .await
@@ -152,104 +308,131 @@ pub async fn run_frontend<
 
     // find all the FFI imports first
     // (because ir imports must come before all other functions in the ir_program)
-    let mut imports: Vec<ir::Import> = dep_graph
-        .topological_traverse()
-        .filter_map(|(source_item, _)| {
-            if let SourceItem::ImportSpec(import_spec) = source_item {
-                Some(import_spec)
-            } else {
-                None
-            }
-        })
-        .flat_map(|import_spec| import_spec.content.iter().map(|(name, import)| import))
-        .cloned()
-        .collect();
+    let imports: Vec<ir::Import> = collect_imports(&dep_graph);
 
-    // sort and deduplicate the imports so we don't have duplicated imports
-    // note: we can import the same module+entity pair under multiple signatures, this is allowed in ir and wasm
-    imports.sort_unstable();
-    imports.dedup();
+    // start with the builtins, or with everything in the library
+    let (mut start, linked_library): (ProgramStart, Option<(&Library, FuncShift)>) =
+        match opt_library {
+            Some(library) => {
+                let (start, shift) = library.link(imports);
+                (start, Some((library, shift)))
+            }
+            None => (ProgramStart::new(imports), None),
+        };
 
     // keep a map from import to funcidx, so that we can use it later
-    let import_funcidx_map: HashMap<ir::Import, ir::FuncIdx> = imports
+    let import_funcidx_map: HashMap<ir::Import, ir::FuncIdx> = start
+        .ir_program
+        .imports
         .iter()
         .enumerate()
         .map(|(i, import)| (import.clone(), i))
         .collect();
 
-    // construct the ir_program with the given imports
-    let mut ir_program = ir::Program::new_with_imports(imports.into_boxed_slice());
-    let mut ir_toplevel_sequence: Vec<ir::Expr> = Vec::new();
-
     // parse all the source files in topological order
-    //let default_state: compact_state::CompactState<compact_state::FrontendVar> =
-    //    builtins::state_with_builtins();
-    // contains builtins, e.g. __string_to_number(), and __undefined.
-    // The builtins are encoded as string, e.g. "+", "-", etc, and are all Direct
-    // the mapping is in builtins module, there is a special transformation for unary minus to avoid name clash
-    // todo: also add the automatic imports
-    let mut start_idx = 0;
-    let (name_ctx, parse_state): (HashMap<String, PreVar>, ParseState) =
-        builtins::state_with_builtins(&mut start_idx, &mut ir_program);
     // We act as if every global in the main program (i.e. the main file) is exported,
     // so when we compile additional stuff from the REPL later, it is as if we just imported the main program.
     let (main_name_ctx, main_parse_state): (HashMap<String, PreVar>, ParseState) = dep_graph
         .topological_traverse_state_into(
-            |i, deps, source_item, filename, (start_idx, ir_program, ir_toplevel_sequence)| {
-                match source_item {
-                    SourceItem::ESTree(es_program) => func::parse_dep_program(
-                        &name_ctx,
-                        &parse_state,
-                        es_program,
-                        deps,
-                        start_idx,
-                        filename,
-                        i,
-                        ir_program,
-                        ir_toplevel_sequence,
-                    )
-                    .map_err(|cm| {
-                        logger.log(cm);
-                    }),
-                    SourceItem::ImportSpec(import_spec) => {
-                        assert!(deps.is_empty(), "Import spec should be empty");
-                        Ok(importer::make_export_state(
-                            import_spec,
-                            i,
-                            &import_funcidx_map,
-                        ))
-                    }
-                }
+            |i, deps, source_item, filename, start| {
+                parse_dep(
+                    i,
+                    deps,
+                    source_item,
+                    filename,
+                    start,
+                    &import_funcidx_map,
+                    linked_library,
+                    &logger,
+                )
             },
-            |i, deps, source_item, filename, (start_idx, ir_program, ir_toplevel_sequence)| {
-                match source_item {
-                    SourceItem::ESTree(es_program) => func::parse_main_program(
-                        &name_ctx,
-                        &parse_state,
-                        es_program,
-                        deps,
-                        start_idx,
-                        filename,
-                        i,
-                        ir_program,
-                        ir_toplevel_sequence,
-                    )
-                    .map_err(|cm| {
-                        logger.log(cm);
-                    }),
-                    SourceItem::ImportSpec(import_spec) => {
-                        panic!("Main program cannot be ImportSpec")
-                    }
-                }
+            |i, deps, source_item, filename, start| match source_item {
+                SourceItem::ESTree(es_program) => func::parse_main_program(
+                    &start.name_ctx,
+                    &start.parse_state,
+                    es_program,
+                    deps,
+                    &mut start.start_idx,
+                    filename,
+                    i,
+                    &mut start.ir_program,
+                    &mut start.ir_toplevel_sequence,
+                )
+                .map_err(|cm| {
+                    logger.log(cm);
+                }),
+                _ => panic!("Main program must be ESTree"),
             },
-            &mut (&mut start_idx, &mut ir_program, &mut ir_toplevel_sequence),
+            &mut start,
         )?;
 
     // put the toplevel sequence into the program
     // and set it as the entry_point function
-    gen_toplevel_func(&mut ir_program, ir_toplevel_sequence);
+    gen_toplevel_func(&mut start.ir_program, start.ir_toplevel_sequence);
 
-    Ok(((main_name_ctx, main_parse_state, start_idx), ir_program))
+    Ok((
+        (main_name_ctx, main_parse_state, start.start_idx),
+        start.ir_program,
+    ))
+}
+
+/// Compiles the given modules (and the modules that they import) into a library,
+/// which can then be linked into many programs by `run_frontend_with_library()`.
+/// The names are resolved in the same way as the sources of the import declarations in the main program.
+pub async fn compile_library<
+    L: Logger,
+    F: 'static + Copy + FnOnce(String) -> Fut,
+    Fut: Future<Output = Option<String>>,
+>(
+    names: &[String],
+    raw_fetch: F,
+    logger: L,
+) -> Result<Library, ()> {
+    let dep_graph = dep_graph::Graph::try_async_build_from_root(
+        SourceItem::LibraryRoot(names.into()),
+        SourceFetcher::<F> {
+            raw_fetch: raw_fetch,
+            library: None,
+        },
+    )
+    .await
+    .log_err(&logger)?;
+
+    // see run_frontend_impl() for more comments
+    let imports: Vec<ir::Import> = collect_imports(&dep_graph);
+    let import_funcidx_map: HashMap<ir::Import, ir::FuncIdx> = imports
+        .iter()
+        .enumerate()
+        .map(|(i, import)| (import.clone(), i))
+        .collect();
+    let mut start = ProgramStart::new(imports);
+
+    // keep the exports of every module, so that programs can import any of them
+    let mut modules: HashMap<String, (ProgramPreExports, ParseState)> = HashMap::new();
+    dep_graph.topological_traverse_state_into(
+        |i, deps, source_item, filename, (start, modules)| {
+            let resolved_name: Option<String> = filename.clone();
+            let exports = parse_dep(
+                i,
+                deps,
+                source_item,
+                filename,
+                start,
+                &import_funcidx_map,
+                None,
+                &logger,
+            )?;
+            modules.insert(
+                resolved_name.expect("Dependency must have a name"),
+                exports.clone(),
+            );
+            Ok(exports)
+        },
+        |_, _, _, _, _| Ok(()),
+        &mut (&mut start, &mut modules),
+    )?;
+
+    Ok(Library::new(start, modules))
 }
 
 /// Main entry point for appending code for REPL.  Pass in the ReplContext obtained previously.
@@ -302,11 +485,25 @@ pub fn run_frontend_repl<L: Logger>(
     ))
 }
 
+/// Returns the items of the program that code compiled later for the REPL (with the given ReplContext) can refer to,
+/// which must be kept when removing the unused items of the program (see ir::opt::remove_unused()).
+pub fn repl_keep_alive(repl_ctx: &ReplContext) -> ir::opt::KeepAlive {
+    repl_ctx.1.keep_alive()
+}
+
+/// Renumbers the items that the ReplContext refers to,
+/// after the unused items of the program have been removed with the items from repl_keep_alive() kept.
+pub fn remap_repl_context(repl_ctx: &mut ReplContext, map: &ir::opt::ItemMap) {
+    repl_ctx.1.remap_items(map);
+}
+
 /// Puts the toplevel sequence into the program
 /// and set it as the entry_point function
 fn gen_toplevel_func(ir_program: &mut ir::Program, ir_toplevel_sequence: Vec<ir::Expr>) {
     let ir_toplevel_func = ir::Func {
+        name: None,
         params: Box::new([]),
+        param_names: Box::new([]),
         result: Some(ir::VarType::Any),
         expr: ir::Expr {
             vartype: ir_toplevel_sequence
@@ -315,6 +512,7 @@ fn gen_toplevel_func(ir_program: &mut ir::Program, ir_toplevel_sequence: Vec<ir:
             kind: ir::ExprKind::Sequence {
                 content: ir_toplevel_sequence,
             },
+            location: None,
         },
         signature_filter: Default::default(),
     };
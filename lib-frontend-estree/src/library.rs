@@ -0,0 +1,118 @@
+/**
+ * Precompiled libraries: modules (e.g. the standard library) that are compiled once by `compile_library()`,
+ * and then linked into each program that imports them by `run_frontend_with_library()`,
+ * instead of being fetched and compiled again for every program.
+ *
+ * A library is the state of the frontend just after its modules have been compiled.
+ * Its IR program (the imports, the builtins, and the functions of the modules) becomes the start of every program that links it,
+ * so the globals, struct types and VarLocIds of the library stay valid in the program.
+ * Only the funcidxs after the imports need to be shifted, if the program needs imports that the library does not have.
+ */
+use super::*;
+
+pub struct Library {
+    ir_program: ir::Program, // the imports, builtins, and functions of the modules (but no entry point)
+    ir_toplevel_sequence: Vec<ir::Expr>, // the toplevel code of the modules, in topological order
+    name_ctx: HashMap<String, PreVar>, // the builtins
+    parse_state: ParseState, // the builtins
+    start_idx: usize,
+    modules: HashMap<String, (ProgramPreExports, ParseState)>, // the exports of each module, by the resolved name that it was fetched with
+}
+
+/**
+ * Maps the funcidxs in a library to the funcidxs in a program that links it.
+ * The program's own imports are added after the imports of the library, so all the other functions move back by that many.
+ */
+#[derive(Copy, Clone)]
+pub struct FuncShift {
+    num_library_imports: usize,
+    num_new_imports: usize,
+}
+
+impl FuncShift {
+    fn apply(self, funcidx: ir::FuncIdx) -> ir::FuncIdx {
+        if funcidx < self.num_library_imports {
+            funcidx
+        } else {
+            funcidx + self.num_new_imports
+        }
+    }
+}
+
+impl Library {
+    pub(crate) fn new(
+        start: ProgramStart,
+        modules: HashMap<String, (ProgramPreExports, ParseState)>,
+    ) -> Library {
+        Library {
+            ir_program: start.ir_program,
+            ir_toplevel_sequence: start.ir_toplevel_sequence,
+            name_ctx: start.name_ctx,
+            parse_state: start.parse_state,
+            start_idx: start.start_idx,
+            modules: modules,
+        }
+    }
+    /**
+     * Returns true if the library contains the module with the given resolved name.
+     */
+    pub fn has_module(&self, name: &str) -> bool {
+        self.modules.contains_key(name)
+    }
+    /**
+     * Makes the start of a program that links this library and needs the given imports.
+     */
+    pub(crate) fn link(&self, imports: Vec<ir::Import>) -> (ProgramStart, FuncShift) {
+        let new_imports: Vec<ir::Import> = imports
+            .into_iter()
+            .filter(|import| !self.ir_program.imports.contains(import))
+            .collect();
+        let shift = FuncShift {
+            num_library_imports: self.ir_program.imports.len(),
+            num_new_imports: new_imports.len(),
+        };
+        let mut ir_program: ir::Program = self.ir_program.clone();
+        let mut ir_toplevel_sequence: Vec<ir::Expr> = self.ir_toplevel_sequence.clone();
+        let mut parse_state: ParseState = self.parse_state.clone();
+        if shift.num_new_imports != 0 {
+            ir_program.imports = self
+                .ir_program
+                .imports
+                .iter()
+                .cloned()
+                .chain(new_imports)
+                .collect();
+            let remap = |funcidx| shift.apply(funcidx);
+            ir_program.remap_funcidxs(remap);
+            for ir_expr in &mut ir_toplevel_sequence {
+                ir_expr.remap_funcidxs(&remap);
+            }
+            parse_state.remap_funcidxs(&remap);
+        }
+        (
+            ProgramStart {
+                ir_program: ir_program,
+                start_idx: self.start_idx,
+                name_ctx: self.name_ctx.clone(),
+                parse_state: parse_state,
+                ir_toplevel_sequence: ir_toplevel_sequence,
+            },
+            shift,
+        )
+    }
+    /**
+     * Returns the exports of the module with the given resolved name, for a program linked with the given shift.
+     */
+    pub(crate) fn module_exports(
+        &self,
+        name: &str,
+        shift: FuncShift,
+    ) -> (ProgramPreExports, ParseState) {
+        let (pre_exports, parse_state) = &self.modules[name];
+        let mut parse_state: ParseState = parse_state.clone();
+        if shift.num_new_imports != 0 {
+            parse_state.remap_funcidxs(&|funcidx| shift.apply(funcidx));
+        }
+        (pre_exports.clone(), parse_state)
+    }
+}
@@ -7,6 +7,8 @@ use std::collections::HashMap;
 pub struct ParseState {
     targets: HashMap<VarLocId, ir::TargetExpr>, // for the Targets
     directs: VarCtx<String, OverloadSet<(Box<[ir::VarType]>, ir::FuncIdx)>>, // for the Directs
+    landings: usize, // number of ir::ExprKind::Block and ir::ExprKind::Loop enclosing the current position (in the current function)
+    loops: Vec<(usize, usize)>, // (break landing, continue landing) of each enclosing loop (in the current function), where a landing is the value of `landings` just inside the Block or Loop
 }
 
 // Undoable multiple targets
@@ -134,7 +136,113 @@ impl ParseState {
     }
 }
 
-type ClosureUndoCtx = HashMap<VarLocId, ir::TargetExpr>;
+// Renumbering of functions (see ir::Program::remap_funcidxs())
+impl ParseState {
+    pub fn remap_funcidxs<F: Fn(ir::FuncIdx) -> ir::FuncIdx>(&mut self, f: &F) {
+        for overload_set in self.directs.values_mut() {
+            for (_, funcidx) in &mut overload_set.signatures {
+                *funcidx = f(*funcidx);
+            }
+        }
+    }
+}
+
+// The items that the targets and directs refer to, and renumbering them (see ir::opt::remove_unused())
+impl ParseState {
+    pub fn keep_alive(&self) -> ir::opt::KeepAlive {
+        let mut funcidxs: Vec<ir::FuncIdx> = Vec::new();
+        let mut globalidxs: Vec<usize> = Vec::new();
+        let mut typeidxs: Vec<usize> = Vec::new();
+        for target in self.targets.values() {
+            let mut next: &Option<Box<ir::StructField>> = match target {
+                ir::TargetExpr::Global { globalidx, next } => {
+                    globalidxs.push(*globalidx);
+                    next
+                }
+                ir::TargetExpr::Local { localidx: _, next } => next,
+            };
+            while let Some(field) = next {
+                typeidxs.push(field.typeidx);
+                next = &field.next;
+            }
+        }
+        for overload_set in self.directs.values() {
+            for (param_types, funcidx) in &overload_set.signatures {
+                funcidxs.push(*funcidx);
+                for vartype in param_types.iter() {
+                    if let ir::VarType::StructT { typeidx } = vartype {
+                        typeidxs.push(*typeidx);
+                    }
+                }
+            }
+        }
+        ir::opt::KeepAlive::new()
+            .funcs(&funcidxs)
+            .globals(&globalidxs)
+            .struct_types(&typeidxs)
+    }
+    pub fn remap_items(&mut self, map: &ir::opt::ItemMap) {
+        const MSG: &str = "ICE: REPL context refers to a removed item";
+        for target in self.targets.values_mut() {
+            target.remap_globalidx(&|globalidx| map.global(globalidx).expect(MSG));
+            target.remap_typeidxs(&|typeidx| map.struct_type(typeidx).expect(MSG));
+        }
+        self.remap_funcidxs(&|funcidx| map.func(funcidx).expect(MSG));
+        for overload_set in self.directs.values_mut() {
+            for (param_types, _) in &mut overload_set.signatures {
+                for vartype in param_types.iter_mut() {
+                    vartype.remap_typeidx(&|typeidx| map.struct_type(typeidx).expect(MSG));
+                }
+            }
+        }
+    }
+}
+
+// Landings for break and continue
+impl ParseState {
+    /**
+     * Should be called when entering an ir::ExprKind::Block or ir::ExprKind::Loop.
+     * Returns the index of the new landing, to be passed to enter_loop() if necessary.
+     */
+    pub fn enter_landing(&mut self) -> usize {
+        self.landings += 1;
+        self.landings
+    }
+    pub fn leave_landing(&mut self) {
+        self.landings -= 1;
+    }
+    pub fn enter_loop(&mut self, break_landing: usize, continue_landing: usize) {
+        self.loops.push((break_landing, continue_landing));
+    }
+    pub fn leave_loop(&mut self) {
+        self.loops.pop();
+    }
+    /**
+     * Returns the num_frames of the ir::ExprKind::Break that implements a break statement at the current position,
+     * or None if we are not inside a loop.
+     */
+    pub fn get_break_num_frames(&self) -> Option<usize> {
+        self.loops
+            .last()
+            .map(|(break_landing, _)| self.landings - break_landing)
+    }
+    /**
+     * Returns the num_frames of the ir::ExprKind::Break that implements a continue statement at the current position,
+     * or None if we are not inside a loop.
+     */
+    pub fn get_continue_num_frames(&self) -> Option<usize> {
+        self.loops
+            .last()
+            .map(|(_, continue_landing)| self.landings - continue_landing)
+    }
+}
+
+// Breaks cannot cross function boundaries, so the landings and loops are reset when entering a closure
+type ClosureUndoCtx = (
+    HashMap<VarLocId, ir::TargetExpr>,
+    usize,
+    Vec<(usize, usize)>,
+);
 impl ParseState {
     pub fn enter_closure(
         &mut self,
@@ -149,9 +257,16 @@ impl ParseState {
         for (varlocid, target_expr) in Vec::from(closed_targets) {
             new_targets.insert(varlocid, target_expr);
         }
-        std::mem::replace(&mut self.targets, new_targets)
+        (
+            std::mem::replace(&mut self.targets, new_targets),
+            std::mem::replace(&mut self.landings, 0),
+            std::mem::replace(&mut self.loops, Vec::new()),
+        )
     }
     pub fn leave_closure(&mut self, undo_ctx: ClosureUndoCtx) {
-        self.targets = undo_ctx;
+        let (targets, landings, loops) = undo_ctx;
+        self.targets = targets;
+        self.landings = landings;
+        self.loops = loops;
     }
 }
@@ -0,0 +1,556 @@
+use super::value::StructFields;
+use super::value::Value;
+use super::Error;
+use super::Host;
+use super::Options;
+use super::ARRAY_MAX_LENGTH;
+use ir::error;
+use ir::ExprKind;
+use ir::TargetExpr;
+use ir::VarType;
+use std::sync::{Arc, RwLock};
+
+/**
+ * Non-local control flow that propagates up through the evaluation of expressions.
+ */
+enum Unwind {
+    Break(usize, Value), // num_frames (relative to the current expr) and the value of the break
+    Return(Value),
+    Error(Error),
+}
+
+impl From<Error> for Unwind {
+    fn from(e: Error) -> Self {
+        Unwind::Error(e)
+    }
+}
+
+type EvalResult = Result<Value, Unwind>;
+
+fn trap(code: u32, location: &ir::SourceLocation) -> Unwind {
+    Unwind::Error(Error::Trap {
+        code: code,
+        location: *location,
+    })
+}
+
+pub struct Interpreter<'a, H: Host> {
+    program: &'a ir::Program,
+    host: &'a mut H,
+    options: Options,
+    globals: Vec<Value>,
+    call_depth: usize,
+}
+
+impl<'a, H: Host> Interpreter<'a, H> {
+    pub fn new(program: &'a ir::Program, host: &'a mut H, options: Options) -> Self {
+        Interpreter {
+            program: program,
+            host: host,
+            options: options,
+            globals: program
+                .globals
+                .iter()
+                .copied()
+                .map(Value::default_for)
+                .collect(),
+            call_depth: 0,
+        }
+    }
+
+    pub fn run(mut self) -> Result<Value, Error> {
+        self.call(self.program.entry_point, Vec::new())
+    }
+
+    /**
+     * Calls the given function (which may be an import).
+     * The args must already fit the params of the function.
+     */
+    fn call(&mut self, funcidx: ir::FuncIdx, args: Vec<Value>) -> Result<Value, Error> {
+        if funcidx < self.program.imports.len() {
+            let import: &ir::Import = &self.program.imports[funcidx];
+            let ret: Value = self.host.call_import(import, &args);
+            assert!(
+                ret.fits(import.result.into()),
+                "ICE: IR interpreter: host returned {} from an import with result {:?}",
+                ret,
+                import.result
+            );
+            return Ok(ret);
+        }
+        if self.call_depth == self.options.max_call_depth {
+            return Err(Error::CallStackExhausted);
+        }
+        let func: &ir::Func = self.program.get_func(funcidx);
+        assert!(
+            args.len() == func.params.len()
+                && args
+                    .iter()
+                    .zip(func.params.iter())
+                    .all(|(arg, param)| arg.fits(*param)),
+            "ICE: IR interpreter: args do not match the params of func {}",
+            funcidx
+        );
+        self.call_depth += 1;
+        let mut locals: Vec<Value> = args;
+        let result = self.eval(&func.expr, &mut locals);
+        self.call_depth -= 1;
+        let ret: Value = match result {
+            Ok(value) | Err(Unwind::Return(value)) => value,
+            Err(Unwind::Break(_, _)) => {
+                panic!(
+                    "ICE: IR interpreter: Break without a landing in func {}",
+                    funcidx
+                )
+            }
+            Err(Unwind::Error(e)) => return Err(e),
+        };
+        assert!(
+            func.result.is_some_and(|result| ret.fits(result)),
+            "ICE: IR interpreter: func {} returned {} but its result is {:?}",
+            funcidx,
+            ret,
+            func.result
+        );
+        Ok(ret)
+    }
+
+    /**
+     * Evaluates the expr, and checks that the value fits the vartype of the expr.
+     * `locals` contains the params and all the locals in scope.
+     */
+    fn eval(&mut self, expr: &ir::Expr, locals: &mut Vec<Value>) -> EvalResult {
+        let value: Value = self.eval_kind(expr, locals)?;
+        check_value(expr, &value);
+        Ok(value)
+    }
+
+    // Evaluates the expr with an additional local, which is removed afterwards (even if we are unwinding).
+    fn eval_with_local(
+        &mut self,
+        expr: &ir::Expr,
+        local: Value,
+        locals: &mut Vec<Value>,
+    ) -> EvalResult {
+        let len = locals.len();
+        locals.push(local);
+        let ret = self.eval(expr, locals);
+        locals.truncate(len);
+        ret
+    }
+
+    fn eval_args(
+        &mut self,
+        args: &[ir::Expr],
+        locals: &mut Vec<Value>,
+    ) -> Result<Vec<Value>, Unwind> {
+        args.iter().map(|arg| self.eval(arg, locals)).collect()
+    }
+
+    fn eval_kind(&mut self, expr: &ir::Expr, locals: &mut Vec<Value>) -> EvalResult {
+        match &expr.kind {
+            ExprKind::PrimUndefined => Ok(Value::Undefined),
+            ExprKind::PrimNull => Ok(Value::Null),
+            ExprKind::PrimNumber { val } => Ok(Value::Number(*val)),
+            ExprKind::PrimBoolean { val } => Ok(Value::Boolean(*val)),
+            ExprKind::PrimString { val } => Ok(Value::String(val.as_str().into())),
+            ExprKind::PrimArray => Ok(Value::Array(Arc::new(RwLock::new(Vec::new())))),
+            ExprKind::PrimStructT { typeidx } => Ok(Value::new_struct(
+                *typeidx,
+                &self.program.struct_types[*typeidx],
+            )),
+            ExprKind::PrimFunc { funcidxs, closure } => {
+                let closure_value: Value = self.eval(closure, locals)?;
+                Ok(Value::Func {
+                    funcidxs: funcidxs.iter().copied().collect(),
+                    closure: Arc::new(closure_value),
+                })
+            }
+            ExprKind::TypeCast {
+                test,
+                expected,
+                create_narrow_local,
+                true_expr,
+                false_expr,
+            } => self.eval_typecast(
+                test,
+                *expected,
+                *create_narrow_local,
+                true_expr,
+                false_expr,
+                locals,
+            ),
+            ExprKind::VarName { source } => Ok(self.read_target(source, locals)),
+            ExprKind::PrimAppl { prim_inst, args } => {
+                let arg_values: Vec<Value> = self.eval_args(args, locals)?;
+                Ok(eval_prim_inst(*prim_inst, &arg_values))
+            }
+            ExprKind::ArrayLoad {
+                array,
+                index,
+                location,
+            } => self.eval_array_load(array, index, location, locals),
+            ExprKind::ArrayStore {
+                array,
+                index,
+                expr: expr2,
+                location,
+            } => self.eval_array_store(array, index, expr2, location, locals),
+            ExprKind::Appl {
+                func,
+                args,
+                location,
+            } => self.eval_appl(func, args, location, locals),
+            ExprKind::DirectAppl { funcidx, args } => {
+                let arg_values: Vec<Value> = self.eval_args(args, locals)?;
+                Ok(self.call(*funcidx, arg_values)?)
+            }
+            ExprKind::Conditional {
+                cond,
+                true_expr,
+                false_expr,
+            } => match self.eval(cond, locals)? {
+                Value::Boolean(true) => self.eval(true_expr, locals),
+                Value::Boolean(false) => self.eval(false_expr, locals),
+                _ => panic!("ICE: IR interpreter: condition is not a boolean"),
+            },
+            ExprKind::Declaration {
+                local,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                let init_value: Value = match init {
+                    Some(init_expr) => self.eval(init_expr, locals)?,
+                    None => Value::default_for(*local),
+                };
+                self.eval_with_local(contained_expr, init_value, locals)
+            }
+            ExprKind::Assign {
+                target,
+                expr: expr2,
+            } => self.eval_assign(target, expr2, locals),
+            ExprKind::Return { expr: expr2 } => {
+                let value: Value = self.eval(expr2, locals)?;
+                Err(Unwind::Return(value))
+            }
+            ExprKind::Break {
+                num_frames,
+                expr: expr2,
+            } => {
+                let value: Value = self.eval(expr2, locals)?;
+                Err(Unwind::Break(*num_frames, value))
+            }
+            ExprKind::Block { expr: expr2 } => match self.eval(expr2, locals) {
+                Err(Unwind::Break(0, value)) => Ok(value),
+                Err(Unwind::Break(num_frames, value)) => Err(Unwind::Break(num_frames - 1, value)),
+                other => other,
+            },
+            ExprKind::Loop { expr: expr2 } => loop {
+                match self.eval(expr2, locals) {
+                    Err(Unwind::Break(0, _)) => {} // jump back to the beginning of the loop
+                    Err(Unwind::Break(num_frames, value)) => {
+                        return Err(Unwind::Break(num_frames - 1, value))
+                    }
+                    other => return other,
+                }
+            },
+            ExprKind::Sequence { content } => {
+                let mut ret: Value = Value::Undefined;
+                for expr2 in content {
+                    ret = self.eval(expr2, locals)?;
+                }
+                Ok(ret)
+            }
+            ExprKind::Trap { code, location } => Err(trap(*code, location)),
+        }
+    }
+
+    fn eval_typecast(
+        &mut self,
+        test: &ir::Expr,
+        expected: VarType,
+        create_narrow_local: bool,
+        true_expr: &ir::Expr,
+        false_expr: &ir::Expr,
+        locals: &mut Vec<Value>,
+    ) -> EvalResult {
+        let test_value: Value = self.eval(test, locals)?;
+        if test_value.vartype() == expected {
+            if create_narrow_local {
+                self.eval_with_local(true_expr, test_value, locals)
+            } else {
+                self.eval(true_expr, locals)
+            }
+        } else {
+            self.eval(false_expr, locals)
+        }
+    }
+
+    fn eval_array_load(
+        &mut self,
+        array: &ir::Expr,
+        index: &ir::Expr,
+        location: &ir::SourceLocation,
+        locals: &mut Vec<Value>,
+    ) -> EvalResult {
+        let array_value: Value = self.eval(array, locals)?;
+        let index_value: Value = self.eval(index, locals)?;
+        let elements = as_array(&array_value).read().unwrap();
+        let i: usize = checked_index(&index_value, elements.len(), location)?;
+        Ok(elements[i].clone())
+    }
+
+    // Like the wasm backend, the index is checked before the value is evaluated.
+    fn eval_array_store(
+        &mut self,
+        array: &ir::Expr,
+        index: &ir::Expr,
+        value: &ir::Expr,
+        location: &ir::SourceLocation,
+        locals: &mut Vec<Value>,
+    ) -> EvalResult {
+        let array_value: Value = self.eval(array, locals)?;
+        let index_value: Value = self.eval(index, locals)?;
+        let i: usize = checked_index(&index_value, ARRAY_MAX_LENGTH as usize, location)?;
+        let value: Value = self.eval(value, locals)?;
+        let mut elements = as_array(&array_value).write().unwrap();
+        if i >= elements.len() {
+            elements.resize(i + 1, Value::Undefined);
+        }
+        elements[i] = value;
+        Ok(Value::Undefined)
+    }
+
+    fn eval_appl(
+        &mut self,
+        func: &ir::Expr,
+        args: &[ir::Expr],
+        location: &ir::SourceLocation,
+        locals: &mut Vec<Value>,
+    ) -> EvalResult {
+        let func_value: Value = self.eval(func, locals)?;
+        let arg_values: Vec<Value> = self.eval_args(args, locals)?;
+        let (funcidxs, closure) = match func_value {
+            Value::Func { funcidxs, closure } => (funcidxs, closure),
+            _ => panic!("ICE: IR interpreter: Appl of a non-function"),
+        };
+        // find the overload to call (from back to front)
+        let program = self.program;
+        let overload: Option<&ir::OverloadEntry> = funcidxs.iter().rev().find(|oe| {
+            let params: &[VarType] = signature_params(program, oe.funcidx);
+            let params = if oe.has_closure_param {
+                &params[1..]
+            } else {
+                params
+            };
+            params.len() == arg_values.len()
+                && params
+                    .iter()
+                    .zip(arg_values.iter())
+                    .all(|(param, arg)| *param == VarType::Any || arg.vartype() == *param)
+        });
+        match overload {
+            None => Err(trap(error::ERROR_CODE_FUNCTION_PARAM_TYPE, location)),
+            Some(oe) => {
+                let mut call_args: Vec<Value> = Vec::new();
+                if oe.has_closure_param {
+                    call_args.push((*closure).clone());
+                }
+                call_args.extend(arg_values);
+                Ok(self.call(oe.funcidx, call_args)?)
+            }
+        }
+    }
+
+    // Like the wasm backend, the struct that contains the target (if any) is found before evaluating the value.
+    fn eval_assign(
+        &mut self,
+        target: &TargetExpr,
+        value: &ir::Expr,
+        locals: &mut Vec<Value>,
+    ) -> EvalResult {
+        match target_struct(target, &self.globals, locals) {
+            None => {
+                let value: Value = self.eval(value, locals)?;
+                match target {
+                    TargetExpr::Local { localidx, next: _ } => locals[*localidx] = value,
+                    TargetExpr::Global { globalidx, next: _ } => self.globals[*globalidx] = value,
+                }
+            }
+            Some((fields, fieldidx)) => {
+                let value: Value = self.eval(value, locals)?;
+                fields.write().unwrap()[fieldidx] = value;
+            }
+        }
+        Ok(Value::Undefined)
+    }
+
+    fn read_target(&self, target: &TargetExpr, locals: &[Value]) -> Value {
+        match target_struct(target, &self.globals, locals) {
+            None => match target {
+                TargetExpr::Local { localidx, next: _ } => locals[*localidx].clone(),
+                TargetExpr::Global { globalidx, next: _ } => self.globals[*globalidx].clone(),
+            },
+            Some((fields, fieldidx)) => fields.read().unwrap()[fieldidx].clone(),
+        }
+    }
+}
+
+/**
+ * If the target refers to a field of a struct, returns the fields of the innermost struct and the index of the field.
+ * Otherwise, the target is a local or global, and None is returned.
+ */
+fn target_struct(
+    target: &TargetExpr,
+    globals: &[Value],
+    locals: &[Value],
+) -> Option<(StructFields, usize)> {
+    let (mut value, mut next): (Value, &Option<Box<ir::StructField>>) = match target {
+        TargetExpr::Local { localidx, next } => (locals[*localidx].clone(), next),
+        TargetExpr::Global { globalidx, next } => (globals[*globalidx].clone(), next),
+    };
+    loop {
+        let struct_field: &ir::StructField = next.as_deref()?;
+        let fields = match value {
+            Value::Struct { typeidx, fields } if typeidx == struct_field.typeidx => fields,
+            _ => panic!(
+                "ICE: IR interpreter: field access on {}, which is not a struct#{}",
+                value, struct_field.typeidx
+            ),
+        };
+        if struct_field.next.is_none() {
+            return Some((fields, struct_field.fieldidx));
+        }
+        value = fields.read().unwrap()[struct_field.fieldidx].clone();
+        next = &struct_field.next;
+    }
+}
+
+// Panics if the value does not fit the vartype of the expr that produced it.
+// (This is a separate function to keep the stack frame of eval() small, since it is recursive.)
+fn check_value(expr: &ir::Expr, value: &Value) {
+    match expr.vartype {
+        None => panic!(
+            "ICE: IR interpreter: noreturn expr evaluated to {}: {}",
+            value,
+            ir::text::print_expr(expr)
+        ),
+        Some(vartype) => assert!(
+            value.fits(vartype),
+            "ICE: IR interpreter: expr evaluated to {}: {}",
+            value,
+            ir::text::print_expr(expr)
+        ),
+    }
+}
+
+fn signature_params(program: &ir::Program, funcidx: ir::FuncIdx) -> &[VarType] {
+    assert!(
+        funcidx >= program.imports.len(),
+        "ICE: IR interpreter: function values cannot refer to imports"
+    );
+    &program.get_func(funcidx).params
+}
+
+fn as_array(value: &Value) -> &Arc<RwLock<Vec<Value>>> {
+    match value {
+        Value::Array(elements) => elements,
+        _ => panic!("ICE: IR interpreter: {} is not an array", value),
+    }
+}
+
+// Returns the index if it is an integer in the range [0, limit), otherwise traps (like the wasm backend).
+fn checked_index(
+    index: &Value,
+    limit: usize,
+    location: &ir::SourceLocation,
+) -> Result<usize, Unwind> {
+    match index {
+        Value::Number(val) if *val >= 0.0 && *val < limit as f64 && val.floor() == *val => {
+            Ok(*val as usize)
+        }
+        Value::Number(_) => Err(trap(error::ERROR_CODE_ARRAY_INDEX_OUT_OF_RANGE, location)),
+        _ => panic!("ICE: IR interpreter: array index is not a number"),
+    }
+}
+
+/**
+ * Evaluates a primitive instruction.  The args must match its signature.
+ * This uses the same arithmetic as the constant evaluation in the optimiser.
+ */
+fn eval_prim_inst(prim_inst: ir::PrimInst, args: &[Value]) -> Value {
+    use ir::PrimInst;
+    fn number(value: &Value) -> f64 {
+        match value {
+            Value::Number(val) => *val,
+            _ => panic!("ICE: IR interpreter: {} is not a number", value),
+        }
+    }
+    fn boolean(value: &Value) -> bool {
+        match value {
+            Value::Boolean(val) => *val,
+            _ => panic!("ICE: IR interpreter: {} is not a boolean", value),
+        }
+    }
+    fn string(value: &Value) -> &str {
+        match value {
+            Value::String(val) => val,
+            _ => panic!("ICE: IR interpreter: {} is not a string", value),
+        }
+    }
+    let (params, _) = prim_inst.signature();
+    assert!(
+        params.len() == args.len(),
+        "ICE: IR interpreter: wrong number of args for {:?}",
+        prim_inst
+    );
+    match prim_inst {
+        PrimInst::NumberAdd => Value::Number(number(&args[0]) + number(&args[1])),
+        PrimInst::NumberSub => Value::Number(number(&args[0]) - number(&args[1])),
+        PrimInst::NumberMul => Value::Number(number(&args[0]) * number(&args[1])),
+        PrimInst::NumberDiv => Value::Number(number(&args[0]) / number(&args[1])),
+        PrimInst::NumberRem => Value::Number(number(&args[0]) % number(&args[1])),
+        PrimInst::NumberEq => Value::Boolean(number(&args[0]) == number(&args[1])),
+        PrimInst::NumberNeq => Value::Boolean(number(&args[0]) != number(&args[1])),
+        PrimInst::NumberGt => Value::Boolean(number(&args[0]) > number(&args[1])),
+        PrimInst::NumberLt => Value::Boolean(number(&args[0]) < number(&args[1])),
+        PrimInst::NumberGe => Value::Boolean(number(&args[0]) >= number(&args[1])),
+        PrimInst::NumberLe => Value::Boolean(number(&args[0]) <= number(&args[1])),
+        PrimInst::BooleanEq => Value::Boolean(boolean(&args[0]) == boolean(&args[1])),
+        PrimInst::BooleanNeq => Value::Boolean(boolean(&args[0]) != boolean(&args[1])),
+        PrimInst::BooleanAnd => Value::Boolean(boolean(&args[0]) && boolean(&args[1])),
+        PrimInst::BooleanOr => Value::Boolean(boolean(&args[0]) || boolean(&args[1])),
+        PrimInst::BooleanNot => Value::Boolean(!boolean(&args[0])),
+        PrimInst::NumberNegate => Value::Number(-number(&args[0])),
+        PrimInst::StringAdd => {
+            Value::String((string(&args[0]).to_owned() + string(&args[1])).into())
+        }
+        // strings are compared bytewise, like the wasm backend
+        PrimInst::StringEq => Value::Boolean(string(&args[0]) == string(&args[1])),
+        PrimInst::StringNeq => Value::Boolean(string(&args[0]) != string(&args[1])),
+        PrimInst::StringGt => Value::Boolean(string(&args[0]) > string(&args[1])),
+        PrimInst::StringLt => Value::Boolean(string(&args[0]) < string(&args[1])),
+        PrimInst::StringGe => Value::Boolean(string(&args[0]) >= string(&args[1])),
+        PrimInst::StringLe => Value::Boolean(string(&args[0]) <= string(&args[1])),
+        PrimInst::ArrayLength => match &args[0] {
+            Value::Array(elements) => Value::Number(elements.read().unwrap().len() as f64),
+            value => panic!("ICE: IR interpreter: {} is not an array", value),
+        },
+        PrimInst::ReferenceEq => Value::Boolean(same_reference(&args[0], &args[1])),
+        PrimInst::ReferenceNeq => Value::Boolean(!same_reference(&args[0], &args[1])),
+    }
+}
+
+// Whether both values are the same array or struct (references of different types are never the same).
+fn same_reference(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Array(a), Value::Array(b)) => Arc::ptr_eq(a, b),
+        (Value::Struct { fields: a, .. }, Value::Struct { fields: b, .. }) => Arc::ptr_eq(a, b),
+        (Value::Array(_), Value::Struct { .. }) | (Value::Struct { .. }, Value::Array(_)) => false,
+        (a, b) => panic!(
+            "ICE: IR interpreter: {} and {} are not both references",
+            a, b
+        ),
+    }
+}
@@ -0,0 +1,325 @@
+/**
+ * A reference interpreter for the IR.
+ * It evaluates an `ir::Program` directly, without generating wasm, so that it can be used for differential testing
+ * (e.g. of the optimised program against the unoptimised program, or of the wasm backend against the interpreter).
+ *
+ * The semantics follow the comments in lib-ir and the behaviour of the wasm backend:
+ * * Runtime errors are reported as traps with the error codes from `ir::error`, at the same places as the wasm backend.
+ * * An Appl dispatches to the last overload (in the overload set of the function value) whose arity and param types match the args.
+ * * Uninitialized places are zeroed (so an uninitialized Any is Unassigned).
+ * * Imported functions are called through the `Host` trait.
+ *
+ * Since the IR is statically typed, the interpreter also checks that every value fits the static type of the expression that produced it,
+ * and panics with an ICE if it does not (which means that the IR is wrong, e.g. because an optimisation pass has a bug).
+ */
+mod eval;
+mod value;
+
+pub use value::Value;
+
+use std::fmt;
+
+// Stores to array indices at least this large will trap (the same as the wasm backend).
+const ARRAY_MAX_LENGTH: u32 = 1 << 24;
+
+const DEFAULT_MAX_CALL_DEPTH: usize = 256;
+
+// The evaluator recurses on the native stack, so it runs on its own thread with this much stack space for every allowed call.
+// This is plenty for the nesting depth of expressions in real programs (a call in a debug build uses about 8 KiB).
+const STACK_SIZE_PER_CALL: usize = 64 << 10;
+const STACK_SIZE_BASE: usize = 1 << 20;
+
+/**
+ * Options for `run()`.
+ * For now this is only the limit on recursion, which the wasm backend has too (as the size of the wasm call stack),
+ * e.g. `Options::new().max_call_depth(1000)` for a program that recurses deeper than the default allows.
+ */
+#[derive(Copy, Clone)]
+pub struct Options {
+    max_call_depth: usize, // Maximum number of nested function calls (including the entry point)
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+        }
+    }
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /**
+     * Sets the maximum number of nested function calls, after which `Error::CallStackExhausted` is returned.
+     * The stack of the thread that runs the interpreter is sized from this limit.
+     */
+    pub fn max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+}
+
+/**
+ * The ways that running a program can fail.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Trap {
+        code: u32, // one of the error codes in ir::error
+        location: ir::SourceLocation,
+    },
+    CallStackExhausted,
+}
+
+/**
+ * Prints the error in the same format as the runner of the end-to-end tests of the wasm backend (e.g. `error 25`).
+ */
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Trap { code, location: _ } => write!(f, "error {}", code),
+            Error::CallStackExhausted => write!(f, "call stack exhausted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/**
+ * Implements the `ir::Import`s of the program, which `DirectAppl` calls like any other function.
+ * The args match the params of the import (i.e. they are Undefined, Number, or String),
+ * and the returned value must match its result.
+ * The host is called on the thread that runs the interpreter, which is why `run()` requires it to be Send.
+ * A closure taking the import and the args can be passed directly, as the tests do.
+ */
+pub trait Host {
+    fn call_import(&mut self, import: &ir::Import, args: &[Value]) -> Value;
+}
+
+impl<F: FnMut(&ir::Import, &[Value]) -> Value> Host for F {
+    fn call_import(&mut self, import: &ir::Import, args: &[Value]) -> Value {
+        self(import, args)
+    }
+}
+
+/**
+ * Runs the entry point of the program, and returns its result.
+ * The program runs on a new thread whose stack is large enough for `max_call_depth` nested calls,
+ * so deep recursion returns `Error::CallStackExhausted` instead of overflowing the stack of the calling thread.
+ */
+pub fn run<H: Host + Send>(
+    program: &ir::Program,
+    host: &mut H,
+    options: Options,
+) -> Result<Value, Error> {
+    let stack_size: usize = options
+        .max_call_depth
+        .saturating_mul(STACK_SIZE_PER_CALL)
+        .saturating_add(STACK_SIZE_BASE);
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("ir-interp".to_string())
+            .stack_size(stack_size)
+            .spawn_scoped(scope, || {
+                eval::Interpreter::new(program, host, options).run()
+            })
+            .expect("failed to spawn the interpreter thread")
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_text(text: &str) -> String {
+        let program = ir::text::parse(text).unwrap();
+        let mut host = |_: &ir::Import, _: &[Value]| -> Value { panic!("no imports") };
+        match run(&program, &mut host, Options::new()) {
+            Ok(value) => value.to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn loops_and_locals() {
+        // let i = 0; let sum = 0; while (i < 5) { sum = sum + i; i = i + 1; } sum;
+        assert_eq!(
+            run_text(
+                r#"
+entry 0
+func 0 () -> any
+  (declare:number number (number:number 0.0)
+    (declare:number number (number:number 0.0)
+      (seq:number
+        (block:undefined
+          (loop:undefined
+            (if:undefined
+              (prim:boolean number_lt (var:number (local 0)) (number:number 5.0))
+              (seq:void
+                (assign:undefined (local 1)
+                  (prim:number number_add (var:number (local 1)) (var:number (local 0))))
+                (assign:undefined (local 0)
+                  (prim:number number_add (var:number (local 0)) (number:number 1.0)))
+                (break:void 0 (undefined:undefined)))
+              (break:void 1 (undefined:undefined)))))
+        (var:number (local 1)))))
+"#
+            ),
+            "10"
+        );
+    }
+
+    #[test]
+    fn overloads_and_closures() {
+        // the number overload is tried first (overloads are matched from back to front), then the catch-all overload
+        assert_eq!(
+            run_text(
+                r#"
+struct 0 (string)
+entry 2
+func 0 (struct#0 any) -> any
+  (var:string (local 0 0.0))
+func 1 (number) -> number
+  (prim:number number_mul (var:number (local 0)) (number:number 2.0))
+func 2 () -> any
+  (declare:any struct#0 (struct:struct#0 0)
+    (seq:any
+      (assign:undefined (local 0 0.0) (string:string "s"))
+      (declare:any func
+        (func:func (0+closure 1) (var:struct#0 (local 0)))
+        (declare:any array (array:array)
+          (seq:any
+            (array_store:undefined @0:0:0-0:0
+              (var:array (local 2))
+              (number:number 0.0)
+              (appl:any @0:1:0-1:1 (var:func (local 1)) (number:number 21.0)))
+            (array_store:undefined @0:0:0-0:0
+              (var:array (local 2))
+              (number:number 2.0)
+              (appl:any @0:1:0-1:1 (var:func (local 1)) (boolean:boolean true)))
+            (var:array (local 2)))))))
+"#
+            ),
+            r#"[42,"undefined","s"]"#
+        );
+    }
+
+    #[test]
+    fn traps() {
+        // calling with the wrong number of args
+        assert_eq!(
+            run_text(
+                r#"
+entry 1
+func 0 (number) -> number
+  (var:number (local 0))
+func 1 () -> any
+  (appl:any @0:1:0-1:1 (func:func (0) (undefined:undefined)))
+"#
+            ),
+            "error 17"
+        );
+        // reading past the end of an array
+        assert_eq!(
+            run_text(
+                r#"
+entry 0
+func 0 () -> any
+  (array_load:any @0:1:0-1:1 (array:array) (number:number 0.0))
+"#
+            ),
+            "error 25"
+        );
+        // unbounded recursion
+        assert_eq!(
+            run_text(
+                r#"
+entry 1
+func 0 () -> any
+  (direct_appl:any 0)
+func 1 () -> any
+  (direct_appl:any 0)
+"#
+            ),
+            "call stack exhausted"
+        );
+    }
+
+    #[test]
+    fn deep_recursion() {
+        // function count(n) { return n === 0 ? 0 : count(n - 1) + 1; }
+        let program = |n: f64| {
+            ir::text::parse(&format!(
+                r#"
+entry 1
+func 0 (number) -> number
+  (return:void
+    (if:number
+      (prim:boolean number_eq (var:number (local 0)) (number:number 0.0))
+      (number:number 0.0)
+      (prim:number number_add
+        (direct_appl:number 0
+          (prim:number number_sub (var:number (local 0)) (number:number 1.0)))
+        (number:number 1.0))))
+func 1 () -> any
+  (direct_appl:number 0 (number:number {:?}))
+"#,
+                n
+            ))
+            .unwrap()
+        };
+        let mut host = |_: &ir::Import, _: &[Value]| -> Value { panic!("no imports") };
+        // the entry point is also a call, so there is room for max_call_depth - 2 recursive calls
+        assert_eq!(
+            run(&program(254.0), &mut host, Options::new())
+                .unwrap()
+                .to_string(),
+            "254"
+        );
+        assert_eq!(
+            run(&program(255.0), &mut host, Options::new()).unwrap_err(),
+            Error::CallStackExhausted
+        );
+        assert_eq!(
+            run(
+                &program(1000000.0),
+                &mut host,
+                Options::new().max_call_depth(10000)
+            )
+            .unwrap_err(),
+            Error::CallStackExhausted
+        );
+    }
+
+    #[test]
+    fn imports() {
+        let program = ir::text::parse(
+            r#"
+import 0 "misc" "concat_twice" (string) -> string
+entry 1
+func 1 () -> any
+  (prim:string string_add
+    (direct_appl:string 0 (string:string "ab"))
+    (string:string "!"))
+"#,
+        )
+        .unwrap();
+        let mut host = |import: &ir::Import, args: &[Value]| -> Value {
+            assert_eq!(import.entity_name, "concat_twice");
+            match &args[0] {
+                Value::String(s) => Value::String(format!("{}{}", s, s).into()),
+                _ => panic!("wrong arg"),
+            }
+        };
+        assert_eq!(
+            run(&program, &mut host, Options::new())
+                .unwrap()
+                .to_string(),
+            "\"abab!\""
+        );
+    }
+}
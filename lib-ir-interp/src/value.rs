@@ -0,0 +1,163 @@
+use ir::VarType;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+// The fields of a struct, which are shared by all copies of the struct value.
+pub type StructFields = Arc<RwLock<Box<[Value]>>>;
+
+/**
+ * A runtime value.
+ * Every value knows its own type (like an Any in the wasm backend), so the interpreter never needs the static types to read it.
+ * Reference types share their contents when cloned, like pointers in linear memory.
+ * (They use Arc rather than Rc, so that the result can be sent back from the thread that runs the interpreter.)
+ */
+#[derive(Debug, Clone)]
+pub enum Value {
+    Unassigned, // also used for reference-typed places that were never initialized (i.e. the null pointers of the wasm backend)
+    Undefined,
+    Number(f64),
+    Boolean(bool),
+    String(Arc<str>),
+    Func {
+        funcidxs: Arc<[ir::OverloadEntry]>, // overload set, matched from back to front
+        closure: Arc<Value>,
+    },
+    Null,
+    Array(Arc<RwLock<Vec<Value>>>),
+    Struct {
+        typeidx: usize,
+        fields: StructFields,
+    },
+}
+
+impl Value {
+    /**
+     * Returns the (most specific) vartype of this value, i.e. the tag it would have if it were stored in an Any.
+     */
+    pub fn vartype(&self) -> VarType {
+        match self {
+            Value::Unassigned => VarType::Unassigned,
+            Value::Undefined => VarType::Undefined,
+            Value::Number(_) => VarType::Number,
+            Value::Boolean(_) => VarType::Boolean,
+            Value::String(_) => VarType::String,
+            Value::Func { .. } => VarType::Func,
+            Value::Null => VarType::Null,
+            Value::Array(_) => VarType::Array,
+            Value::Struct { typeidx, fields: _ } => VarType::StructT { typeidx: *typeidx },
+        }
+    }
+
+    /**
+     * Returns true if this value may be stored in a place of the given vartype.
+     * Uninitialized reference-typed places hold Unassigned, so it is allowed for all reference types.
+     */
+    pub fn fits(&self, vartype: VarType) -> bool {
+        match (self, vartype) {
+            (_, VarType::Any) => true,
+            (
+                Value::Unassigned,
+                VarType::String | VarType::Func | VarType::Array | VarType::StructT { .. },
+            ) => true,
+            _ => self.vartype() == vartype,
+        }
+    }
+
+    /**
+     * The value of a place of the given vartype that was not explicitly initialized.
+     * This is the same as in the wasm backend, where such places are zeroed.
+     */
+    pub fn default_for(vartype: VarType) -> Value {
+        match vartype {
+            VarType::Undefined => Value::Undefined,
+            VarType::Number => Value::Number(0.0),
+            VarType::Boolean => Value::Boolean(false),
+            VarType::Null => Value::Null,
+            VarType::Any
+            | VarType::Unassigned
+            | VarType::String
+            | VarType::Func
+            | VarType::Array
+            | VarType::StructT { .. } => Value::Unassigned,
+        }
+    }
+
+    /**
+     * Creates a struct with all fields default-initialized.
+     */
+    pub fn new_struct(typeidx: usize, field_types: &[VarType]) -> Value {
+        Value::Struct {
+            typeidx: typeidx,
+            fields: Arc::new(RwLock::new(
+                field_types
+                    .iter()
+                    .copied()
+                    .map(Value::default_for)
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/**
+ * Prints the value as JSON, in the same format as the runner of the end-to-end tests of the wasm backend,
+ * so that results can be compared directly.
+ * Values that have no JSON equivalent are printed as strings (e.g. `"undefined"` and `"function"`),
+ * and structs are printed as `{"struct#<typeidx>":[<fields...>]}`.
+ */
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Unassigned => write!(f, "\"tag 0\""),
+            Value::Undefined => write!(f, "\"undefined\""),
+            Value::Number(val) => {
+                if *val == 0.0 {
+                    write!(f, "0") // JSON has no negative zero
+                } else if val.is_finite() {
+                    write!(f, "{}", val)
+                } else {
+                    write!(f, "null")
+                }
+            }
+            Value::Boolean(val) => write!(f, "{}", val),
+            Value::String(val) => write_json_string(f, val),
+            Value::Func { .. } => write!(f, "\"function\""),
+            Value::Null => write!(f, "null"),
+            Value::Array(elements) => write_list(f, &elements.read().unwrap()),
+            Value::Struct { typeidx, fields } => {
+                write!(f, "{{\"struct#{}\":", typeidx)?;
+                write_list(f, &fields.read().unwrap())?;
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, values: &[Value]) -> fmt::Result {
+    write!(f, "[")?;
+    for (i, value) in values.iter().enumerate() {
+        if i != 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}", value)?;
+    }
+    write!(f, "]")
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            '\u{8}' => write!(f, "\\b")?,
+            '\u{c}' => write!(f, "\\f")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
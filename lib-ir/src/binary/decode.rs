@@ -0,0 +1,394 @@
+use super::*;
+
+/**
+ * An error from decoding the binary IR encoding.
+ * `offset` is the position in the input where the problem was found.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/**
+ * Decodes a program written by `encode()`.
+ * Programs encoded with any other version of the encoding are rejected.
+ */
+pub fn decode(bytes: &[u8]) -> Result<Program, DecodeError> {
+    let mut decoder = Decoder {
+        bytes: bytes,
+        pos: 0,
+    };
+    decoder.program()
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn error<T>(&self, offset: usize, message: String) -> Result<T, DecodeError> {
+        Err(DecodeError {
+            offset: offset,
+            message: message,
+        })
+    }
+
+    fn slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if len > self.bytes.len() - self.pos {
+            return self.error(self.bytes.len(), "unexpected end of input".to_owned());
+        }
+        let ret = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(ret)
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.slice(1)?[0])
+    }
+
+    fn uleb(&mut self) -> Result<u64, DecodeError> {
+        let start = self.pos;
+        let mut ret: u64 = 0;
+        let mut shift: u32 = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 64 || (shift == 63 && byte > 1) {
+                return self.error(start, "integer too large".to_owned());
+            }
+            ret |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(ret);
+            }
+            shift += 7;
+        }
+    }
+
+    fn usize(&mut self) -> Result<usize, DecodeError> {
+        let start = self.pos;
+        let val = self.uleb()?;
+        if val > usize::MAX as u64 {
+            return self.error(start, "integer too large".to_owned());
+        }
+        Ok(val as usize)
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        let start = self.pos;
+        let val = self.uleb()?;
+        if val > u32::MAX as u64 {
+            return self.error(start, "integer too large".to_owned());
+        }
+        Ok(val as u32)
+    }
+
+    fn f64(&mut self) -> Result<f64, DecodeError> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.slice(8)?);
+        Ok(f64::from_le_bytes(buf))
+    }
+
+    fn bool(&mut self) -> Result<bool, DecodeError> {
+        let start = self.pos;
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => self.error(start, format!("invalid boolean {}", b)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let len = self.usize()?;
+        let start = self.pos;
+        let bytes = self.slice(len)?;
+        std::str::from_utf8(bytes)
+            .map(|s| s.to_owned())
+            .or_else(|_| self.error(start, "invalid UTF-8 in string".to_owned()))
+    }
+
+    fn list<T, F: Fn(&mut Self) -> Result<T, DecodeError>>(
+        &mut self,
+        read_elem: F,
+    ) -> Result<Vec<T>, DecodeError> {
+        let len = self.usize()?;
+        // every element takes at least one byte, so don't trust the length for the allocation
+        let mut ret = Vec::with_capacity(std::cmp::min(len, self.bytes.len() - self.pos));
+        for _ in 0..len {
+            ret.push(read_elem(self)?);
+        }
+        Ok(ret)
+    }
+
+    fn option<T, F: Fn(&mut Self) -> Result<T, DecodeError>>(
+        &mut self,
+        read_elem: F,
+    ) -> Result<Option<T>, DecodeError> {
+        let start = self.pos;
+        match self.byte()? {
+            0 => Ok(None),
+            1 => read_elem(self).map(Some),
+            b => self.error(start, format!("invalid option tag {}", b)),
+        }
+    }
+
+    // Reads the tag of an enum value that has no fields (see `write_tag()`).
+    fn tag<T: Copy>(&mut self, table: &[T], what: &str) -> Result<T, DecodeError> {
+        let start = self.pos;
+        let tag = self.byte()?;
+        match table.get(tag as usize) {
+            Some(value) => Ok(*value),
+            None => self.error(start, format!("invalid {} tag {}", what, tag)),
+        }
+    }
+
+    fn program(&mut self) -> Result<Program, DecodeError> {
+        if self.bytes.len() < MAGIC.len() || self.bytes[0..MAGIC.len()] != MAGIC {
+            return self.error(0, "not an encoded IR program".to_owned());
+        }
+        self.pos = MAGIC.len();
+        let mut version_bytes = [0u8; 4];
+        version_bytes.copy_from_slice(self.slice(4)?);
+        let version = u32::from_le_bytes(version_bytes);
+        if version != VERSION {
+            return self.error(
+                MAGIC.len(),
+                format!("unsupported version {} (expected {})", version, VERSION),
+            );
+        }
+
+        let struct_types = self.list(|d| d.list(Self::vartype).map(|v| v.into_boxed_slice()))?;
+        let imports = self.list(Self::import)?.into_boxed_slice();
+        let globals = self.list(Self::vartype)?;
+        let const_globals = self.list(Self::bool)?;
+        let entry_point = self.usize()?;
+        let funcs = self.list(Self::func)?;
+        if self.pos != self.bytes.len() {
+            return self.error(
+                self.pos,
+                "unexpected bytes after the end of the program".to_owned(),
+            );
+        }
+        Ok(Program {
+            struct_types: struct_types,
+            imports: imports,
+            funcs: funcs,
+            globals: globals,
+            const_globals: const_globals,
+            entry_point: entry_point,
+        })
+    }
+
+    fn vartype(&mut self) -> Result<VarType, DecodeError> {
+        let start = self.pos;
+        Ok(match self.byte()? {
+            VARTYPE_ANY => VarType::Any,
+            VARTYPE_UNASSIGNED => VarType::Unassigned,
+            VARTYPE_UNDEFINED => VarType::Undefined,
+            VARTYPE_NUMBER => VarType::Number,
+            VARTYPE_BOOLEAN => VarType::Boolean,
+            VARTYPE_STRING => VarType::String,
+            VARTYPE_FUNC => VarType::Func,
+            VARTYPE_NULL => VarType::Null,
+            VARTYPE_ARRAY => VarType::Array,
+            VARTYPE_STRUCT_T => VarType::StructT {
+                typeidx: self.usize()?,
+            },
+            tag => return self.error(start, format!("invalid vartype tag {}", tag)),
+        })
+    }
+
+    fn import_val_type(&mut self) -> Result<ImportValType, DecodeError> {
+        self.tag(&IMPORT_VAL_TYPES, "import type")
+    }
+
+    fn import(&mut self) -> Result<Import, DecodeError> {
+        Ok(Import {
+            module_name: self.string()?,
+            entity_name: self.string()?,
+            params: self.list(Self::import_val_type)?.into_boxed_slice(),
+            result: self.import_val_type()?,
+        })
+    }
+
+    fn func(&mut self) -> Result<Func, DecodeError> {
+        let params = self.list(Self::vartype)?.into_boxed_slice();
+        let result = self.option(Self::vartype)?;
+        let name = self.option(Self::string)?;
+        let param_names = self.list(|d| d.option(Self::string))?.into_boxed_slice();
+        let signature_filter = self.list(|d| {
+            Ok((
+                d.list(Self::vartype)?.into_boxed_slice(),
+                d.vartype()?,
+                d.usize()?,
+            ))
+        })?;
+        let expr = self.expr()?;
+        Ok(Func {
+            params: params,
+            result: result,
+            expr: expr,
+            signature_filter: signature_filter,
+            name: name,
+            param_names: param_names,
+        })
+    }
+
+    fn source_location(&mut self) -> Result<SourceLocation, DecodeError> {
+        Ok(SourceLocation {
+            file: self.u32()?,
+            start: Position {
+                line: self.u32()?,
+                column: self.u32()?,
+            },
+            end: Position {
+                line: self.u32()?,
+                column: self.u32()?,
+            },
+        })
+    }
+
+    fn struct_field(&mut self) -> Result<StructField, DecodeError> {
+        Ok(StructField {
+            typeidx: self.usize()?,
+            fieldidx: self.usize()?,
+            next: self.option(|d| d.struct_field().map(Box::new))?,
+        })
+    }
+
+    fn target_expr(&mut self) -> Result<TargetExpr, DecodeError> {
+        let start = self.pos;
+        match self.byte()? {
+            TARGET_GLOBAL => Ok(TargetExpr::Global {
+                globalidx: self.usize()?,
+                next: self.option(|d| d.struct_field().map(Box::new))?,
+            }),
+            TARGET_LOCAL => Ok(TargetExpr::Local {
+                localidx: self.usize()?,
+                next: self.option(|d| d.struct_field().map(Box::new))?,
+            }),
+            tag => self.error(start, format!("invalid target tag {}", tag)),
+        }
+    }
+
+    fn boxed_expr(&mut self) -> Result<Box<Expr>, DecodeError> {
+        self.expr().map(Box::new)
+    }
+
+    fn exprs(&mut self) -> Result<Box<[Expr]>, DecodeError> {
+        self.list(Self::expr).map(|v| v.into_boxed_slice())
+    }
+
+    fn expr(&mut self) -> Result<Expr, DecodeError> {
+        let vartype = self.option(Self::vartype)?;
+        let location = self.option(Self::source_location)?;
+        let start = self.pos;
+        let kind = match self.byte()? {
+            EXPR_PRIM_UNDEFINED => ExprKind::PrimUndefined,
+            EXPR_PRIM_NULL => ExprKind::PrimNull,
+            EXPR_PRIM_NUMBER => ExprKind::PrimNumber { val: self.f64()? },
+            EXPR_PRIM_BOOLEAN => ExprKind::PrimBoolean { val: self.bool()? },
+            EXPR_PRIM_STRING => ExprKind::PrimString {
+                val: self.string()?,
+            },
+            EXPR_PRIM_ARRAY => ExprKind::PrimArray,
+            EXPR_PRIM_STRUCT_T => ExprKind::PrimStructT {
+                typeidx: self.usize()?,
+            },
+            EXPR_PRIM_FUNC => ExprKind::PrimFunc {
+                funcidxs: self
+                    .list(|d| {
+                        Ok(OverloadEntry {
+                            funcidx: d.usize()?,
+                            has_closure_param: d.bool()?,
+                        })
+                    })?
+                    .into_boxed_slice(),
+                closure: self.boxed_expr()?,
+            },
+            EXPR_TYPE_CAST => ExprKind::TypeCast {
+                test: self.boxed_expr()?,
+                expected: self.vartype()?,
+                create_narrow_local: self.bool()?,
+                true_expr: self.boxed_expr()?,
+                false_expr: self.boxed_expr()?,
+            },
+            EXPR_VAR_NAME => ExprKind::VarName {
+                source: self.target_expr()?,
+            },
+            EXPR_PRIM_APPL => ExprKind::PrimAppl {
+                prim_inst: self.tag(&PRIM_INSTS, "primitive")?,
+                args: self.exprs()?,
+            },
+            EXPR_ARRAY_LOAD => ExprKind::ArrayLoad {
+                array: self.boxed_expr()?,
+                index: self.boxed_expr()?,
+                location: self.source_location()?,
+            },
+            EXPR_ARRAY_STORE => ExprKind::ArrayStore {
+                array: self.boxed_expr()?,
+                index: self.boxed_expr()?,
+                expr: self.boxed_expr()?,
+                location: self.source_location()?,
+            },
+            EXPR_APPL => ExprKind::Appl {
+                func: self.boxed_expr()?,
+                args: self.exprs()?,
+                location: self.source_location()?,
+            },
+            EXPR_DIRECT_APPL => ExprKind::DirectAppl {
+                funcidx: self.usize()?,
+                args: self.exprs()?,
+            },
+            EXPR_CONDITIONAL => ExprKind::Conditional {
+                cond: self.boxed_expr()?,
+                true_expr: self.boxed_expr()?,
+                false_expr: self.boxed_expr()?,
+            },
+            EXPR_DECLARATION => ExprKind::Declaration {
+                local: self.vartype()?,
+                name: self.option(Self::string)?,
+                init: self.option(Self::boxed_expr)?,
+                contained_expr: self.boxed_expr()?,
+            },
+            EXPR_ASSIGN => ExprKind::Assign {
+                target: self.target_expr()?,
+                expr: self.boxed_expr()?,
+            },
+            EXPR_RETURN => ExprKind::Return {
+                expr: self.boxed_expr()?,
+            },
+            EXPR_BREAK => ExprKind::Break {
+                num_frames: self.usize()?,
+                expr: self.boxed_expr()?,
+            },
+            EXPR_BLOCK => ExprKind::Block {
+                expr: self.boxed_expr()?,
+            },
+            EXPR_LOOP => ExprKind::Loop {
+                expr: self.boxed_expr()?,
+            },
+            EXPR_SEQUENCE => ExprKind::Sequence {
+                content: self.list(Self::expr)?,
+            },
+            EXPR_TRAP => ExprKind::Trap {
+                code: self.u32()?,
+                location: self.source_location()?,
+            },
+            tag => return self.error(start, format!("invalid expression tag {}", tag)),
+        };
+        Ok(Expr {
+            vartype: vartype,
+            kind: kind,
+            location: location,
+        })
+    }
+}
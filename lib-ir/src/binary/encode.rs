@@ -0,0 +1,299 @@
+use super::*;
+
+/**
+ * Encodes the program in the binary IR encoding (see `ir::binary`).
+ */
+pub fn encode(program: &Program) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::new();
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    write_list(&mut out, &program.struct_types, |out, fields| {
+        write_list(out, fields, write_vartype)
+    });
+    write_list(&mut out, &program.imports, write_import);
+    write_list(&mut out, &program.globals, write_vartype);
+    write_list(&mut out, &program.const_globals, |out, is_const| {
+        write_bool(out, *is_const)
+    });
+    write_usize(&mut out, program.entry_point);
+    write_list(&mut out, &program.funcs, write_func);
+    out
+}
+
+fn write_uleb(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte: u8 = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_usize(out: &mut Vec<u8>, value: usize) {
+    write_uleb(out, value as u64);
+}
+
+fn write_u32(out: &mut Vec<u8>, value: u32) {
+    write_uleb(out, value as u64);
+}
+
+fn write_bool(out: &mut Vec<u8>, value: bool) {
+    out.push(value as u8);
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    write_usize(out, value.len());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_list<T, F: Fn(&mut Vec<u8>, &T)>(out: &mut Vec<u8>, list: &[T], write_elem: F) {
+    write_usize(out, list.len());
+    for elem in list {
+        write_elem(out, elem);
+    }
+}
+
+fn write_option<T, F: Fn(&mut Vec<u8>, &T)>(out: &mut Vec<u8>, opt: &Option<T>, write_elem: F) {
+    match opt {
+        None => out.push(0),
+        Some(elem) => {
+            out.push(1);
+            write_elem(out, elem);
+        }
+    }
+}
+
+fn write_vartype(out: &mut Vec<u8>, vartype: &VarType) {
+    match vartype {
+        VarType::Any => out.push(VARTYPE_ANY),
+        VarType::Unassigned => out.push(VARTYPE_UNASSIGNED),
+        VarType::Undefined => out.push(VARTYPE_UNDEFINED),
+        VarType::Number => out.push(VARTYPE_NUMBER),
+        VarType::Boolean => out.push(VARTYPE_BOOLEAN),
+        VarType::String => out.push(VARTYPE_STRING),
+        VarType::Func => out.push(VARTYPE_FUNC),
+        VarType::Null => out.push(VARTYPE_NULL),
+        VarType::Array => out.push(VARTYPE_ARRAY),
+        VarType::StructT { typeidx } => {
+            out.push(VARTYPE_STRUCT_T);
+            write_usize(out, *typeidx);
+        }
+    }
+}
+
+// Writes the tag of an enum value that has no fields, which is its index in `table`.
+fn write_tag<T: PartialEq + std::fmt::Debug>(out: &mut Vec<u8>, table: &[T], value: &T) {
+    let tag: usize = table
+        .iter()
+        .position(|x| x == value)
+        .unwrap_or_else(|| panic!("{:?} has no tag", value));
+    out.push(tag as u8);
+}
+
+fn write_import_val_type(out: &mut Vec<u8>, ivt: &ImportValType) {
+    write_tag(out, &IMPORT_VAL_TYPES, ivt);
+}
+
+fn write_import(out: &mut Vec<u8>, import: &Import) {
+    write_string(out, &import.module_name);
+    write_string(out, &import.entity_name);
+    write_list(out, &import.params, write_import_val_type);
+    write_import_val_type(out, &import.result);
+}
+
+fn write_func(out: &mut Vec<u8>, func: &Func) {
+    write_list(out, &func.params, write_vartype);
+    write_option(out, &func.result, write_vartype);
+    write_option(out, &func.name, |out, name| write_string(out, name));
+    write_list(out, &func.param_names, |out, opt_name| {
+        write_option(out, opt_name, |out, name| write_string(out, name))
+    });
+    write_list(
+        out,
+        &func.signature_filter,
+        |out, (param_types, return_type, constrained_func)| {
+            write_list(out, param_types, write_vartype);
+            write_vartype(out, return_type);
+            write_usize(out, *constrained_func);
+        },
+    );
+    write_expr(out, &func.expr);
+}
+
+fn write_source_location(out: &mut Vec<u8>, sl: &SourceLocation) {
+    write_u32(out, sl.file);
+    write_u32(out, sl.start.line);
+    write_u32(out, sl.start.column);
+    write_u32(out, sl.end.line);
+    write_u32(out, sl.end.column);
+}
+
+fn write_struct_field(out: &mut Vec<u8>, field: &StructField) {
+    write_usize(out, field.typeidx);
+    write_usize(out, field.fieldidx);
+    write_option(out, &field.next, |out, next| write_struct_field(out, next));
+}
+
+fn write_target_expr(out: &mut Vec<u8>, target: &TargetExpr) {
+    let (tag, idx, next) = match target {
+        TargetExpr::Global { globalidx, next } => (TARGET_GLOBAL, globalidx, next),
+        TargetExpr::Local { localidx, next } => (TARGET_LOCAL, localidx, next),
+    };
+    out.push(tag);
+    write_usize(out, *idx);
+    write_option(out, next, |out, next| write_struct_field(out, next));
+}
+
+fn write_exprs(out: &mut Vec<u8>, exprs: &[Expr]) {
+    write_list(out, exprs, write_expr);
+}
+
+fn write_expr(out: &mut Vec<u8>, expr: &Expr) {
+    write_option(out, &expr.vartype, write_vartype);
+    write_option(out, &expr.location, write_source_location);
+    match &expr.kind {
+        ExprKind::PrimUndefined => out.push(EXPR_PRIM_UNDEFINED),
+        ExprKind::PrimNull => out.push(EXPR_PRIM_NULL),
+        ExprKind::PrimNumber { val } => {
+            out.push(EXPR_PRIM_NUMBER);
+            out.extend_from_slice(&val.to_le_bytes());
+        }
+        ExprKind::PrimBoolean { val } => {
+            out.push(EXPR_PRIM_BOOLEAN);
+            write_bool(out, *val);
+        }
+        ExprKind::PrimString { val } => {
+            out.push(EXPR_PRIM_STRING);
+            write_string(out, val);
+        }
+        ExprKind::PrimArray => out.push(EXPR_PRIM_ARRAY),
+        ExprKind::PrimStructT { typeidx } => {
+            out.push(EXPR_PRIM_STRUCT_T);
+            write_usize(out, *typeidx);
+        }
+        ExprKind::PrimFunc { funcidxs, closure } => {
+            out.push(EXPR_PRIM_FUNC);
+            write_list(out, funcidxs, |out, overload_entry| {
+                write_usize(out, overload_entry.funcidx);
+                write_bool(out, overload_entry.has_closure_param);
+            });
+            write_expr(out, closure);
+        }
+        ExprKind::TypeCast {
+            test,
+            expected,
+            create_narrow_local,
+            true_expr,
+            false_expr,
+        } => {
+            out.push(EXPR_TYPE_CAST);
+            write_expr(out, test);
+            write_vartype(out, expected);
+            write_bool(out, *create_narrow_local);
+            write_expr(out, true_expr);
+            write_expr(out, false_expr);
+        }
+        ExprKind::VarName { source } => {
+            out.push(EXPR_VAR_NAME);
+            write_target_expr(out, source);
+        }
+        ExprKind::PrimAppl { prim_inst, args } => {
+            out.push(EXPR_PRIM_APPL);
+            write_tag(out, &PRIM_INSTS, prim_inst);
+            write_exprs(out, args);
+        }
+        ExprKind::ArrayLoad {
+            array,
+            index,
+            location,
+        } => {
+            out.push(EXPR_ARRAY_LOAD);
+            write_expr(out, array);
+            write_expr(out, index);
+            write_source_location(out, location);
+        }
+        ExprKind::ArrayStore {
+            array,
+            index,
+            expr,
+            location,
+        } => {
+            out.push(EXPR_ARRAY_STORE);
+            write_expr(out, array);
+            write_expr(out, index);
+            write_expr(out, expr);
+            write_source_location(out, location);
+        }
+        ExprKind::Appl {
+            func,
+            args,
+            location,
+        } => {
+            out.push(EXPR_APPL);
+            write_expr(out, func);
+            write_exprs(out, args);
+            write_source_location(out, location);
+        }
+        ExprKind::DirectAppl { funcidx, args } => {
+            out.push(EXPR_DIRECT_APPL);
+            write_usize(out, *funcidx);
+            write_exprs(out, args);
+        }
+        ExprKind::Conditional {
+            cond,
+            true_expr,
+            false_expr,
+        } => {
+            out.push(EXPR_CONDITIONAL);
+            write_expr(out, cond);
+            write_expr(out, true_expr);
+            write_expr(out, false_expr);
+        }
+        ExprKind::Declaration {
+            local,
+            name,
+            init,
+            contained_expr,
+        } => {
+            out.push(EXPR_DECLARATION);
+            write_vartype(out, local);
+            write_option(out, name, |out, name| write_string(out, name));
+            write_option(out, init, |out, init| write_expr(out, init));
+            write_expr(out, contained_expr);
+        }
+        ExprKind::Assign { target, expr } => {
+            out.push(EXPR_ASSIGN);
+            write_target_expr(out, target);
+            write_expr(out, expr);
+        }
+        ExprKind::Return { expr } => {
+            out.push(EXPR_RETURN);
+            write_expr(out, expr);
+        }
+        ExprKind::Break { num_frames, expr } => {
+            out.push(EXPR_BREAK);
+            write_usize(out, *num_frames);
+            write_expr(out, expr);
+        }
+        ExprKind::Block { expr } => {
+            out.push(EXPR_BLOCK);
+            write_expr(out, expr);
+        }
+        ExprKind::Loop { expr } => {
+            out.push(EXPR_LOOP);
+            write_expr(out, expr);
+        }
+        ExprKind::Sequence { content } => {
+            out.push(EXPR_SEQUENCE);
+            write_exprs(out, content);
+        }
+        ExprKind::Trap { code, location } => {
+            out.push(EXPR_TRAP);
+            write_u32(out, *code);
+            write_source_location(out, location);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+/**
+ * A compact binary encoding of the IR, with a version number, so that IR can be stored and loaded again later
+ * (e.g. for caching optimised IR, persisting REPL state, or attaching IR to bug reports).
+ * Unlike the textual syntax (see `ir::text`), it keeps everything in the program, including source locations.
+ *
+ * The encoding starts with the magic bytes `\0sir` and the format version (`VERSION`, as a little-endian u32).
+ * Decoders reject any other version, so the version must be bumped whenever the encoding changes,
+ * including when variants are added to or removed from the enums below.
+ *
+ * The rest of the encoding follows the structure of `Program`:
+ * * Integers (counts, indices, funcidxs, line numbers, etc.) are unsigned LEB128.
+ * * Numbers are f64, little-endian; booleans are a single byte, 0 or 1.
+ * * Strings are the byte length followed by the UTF-8 bytes.
+ * * Lists are the number of elements followed by the elements; `Option`s are a 0 or 1 byte followed by the value if it is 1.
+ * * Enum values are a tag byte (the index of the variant in the tables below) followed by the fields of the variant.
+ * * `Program` fields are written in the order: struct_types, imports, globals, const_globals, entry_point, funcs.
+ * * `Func` fields are written in the order: params, result, name, param_names, signature_filter, expr.
+ * * `Expr` fields are written in the order: vartype, location, kind.
+ * * The fields of each variant of `ExprKind` are written in declaration order.
+ *
+ * Decoding only checks that the encoding is well-formed; use `ir::verify` to check that the decoded program is valid.
+ */
+mod decode;
+mod encode;
+
+pub use decode::decode;
+pub use decode::DecodeError;
+pub use encode::encode;
+
+use super::*;
+
+// The first bytes of every encoded program.
+pub const MAGIC: [u8; 4] = *b"\0sir";
+
+// The version of the encoding that is written by `encode()` and accepted by `decode()`.
+pub const VERSION: u32 = 2;
+
+// Tags of VarType (StructT is followed by the typeidx)
+const VARTYPE_ANY: u8 = 0;
+const VARTYPE_UNASSIGNED: u8 = 1;
+const VARTYPE_UNDEFINED: u8 = 2;
+const VARTYPE_NUMBER: u8 = 3;
+const VARTYPE_BOOLEAN: u8 = 4;
+const VARTYPE_STRING: u8 = 5;
+const VARTYPE_FUNC: u8 = 6;
+const VARTYPE_NULL: u8 = 7;
+const VARTYPE_ARRAY: u8 = 8;
+const VARTYPE_STRUCT_T: u8 = 9;
+
+const IMPORT_VAL_TYPES: [ImportValType; 3] = [
+    ImportValType::Undefined,
+    ImportValType::Number,
+    ImportValType::String,
+];
+
+const PRIM_INSTS: [PrimInst; NUM_PRIM_INST as usize] = [
+    PrimInst::NumberAdd,
+    PrimInst::NumberSub,
+    PrimInst::NumberMul,
+    PrimInst::NumberDiv,
+    PrimInst::NumberRem,
+    PrimInst::NumberEq,
+    PrimInst::NumberNeq,
+    PrimInst::NumberGt,
+    PrimInst::NumberLt,
+    PrimInst::NumberGe,
+    PrimInst::NumberLe,
+    PrimInst::BooleanEq,
+    PrimInst::BooleanNeq,
+    PrimInst::BooleanAnd,
+    PrimInst::BooleanOr,
+    PrimInst::BooleanNot,
+    PrimInst::NumberNegate,
+    PrimInst::StringAdd,
+    PrimInst::StringEq,
+    PrimInst::StringNeq,
+    PrimInst::StringGt,
+    PrimInst::StringLt,
+    PrimInst::StringGe,
+    PrimInst::StringLe,
+    PrimInst::ArrayLength,
+    PrimInst::ReferenceEq,
+    PrimInst::ReferenceNeq,
+];
+
+// Tags of TargetExpr
+const TARGET_GLOBAL: u8 = 0;
+const TARGET_LOCAL: u8 = 1;
+
+// Tags of ExprKind
+const EXPR_PRIM_UNDEFINED: u8 = 0;
+const EXPR_PRIM_NULL: u8 = 1;
+const EXPR_PRIM_NUMBER: u8 = 2;
+const EXPR_PRIM_BOOLEAN: u8 = 3;
+const EXPR_PRIM_STRING: u8 = 4;
+const EXPR_PRIM_ARRAY: u8 = 5;
+const EXPR_PRIM_STRUCT_T: u8 = 6;
+const EXPR_PRIM_FUNC: u8 = 7;
+const EXPR_TYPE_CAST: u8 = 8;
+const EXPR_VAR_NAME: u8 = 9;
+const EXPR_PRIM_APPL: u8 = 10;
+const EXPR_ARRAY_LOAD: u8 = 11;
+const EXPR_ARRAY_STORE: u8 = 12;
+const EXPR_APPL: u8 = 13;
+const EXPR_DIRECT_APPL: u8 = 14;
+const EXPR_CONDITIONAL: u8 = 15;
+const EXPR_DECLARATION: u8 = 16;
+const EXPR_ASSIGN: u8 = 17;
+const EXPR_RETURN: u8 = 18;
+const EXPR_BREAK: u8 = 19;
+const EXPR_BLOCK: u8 = 20;
+const EXPR_LOOP: u8 = 21;
+const EXPR_SEQUENCE: u8 = 22;
+const EXPR_TRAP: u8 = 23;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A program that uses every kind of expression.
+    const PROGRAM: &str = r#"struct 0 (any number)
+struct 1 (struct#0 string)
+import 0 "misc" "display" (string number) -> undefined
+global 0 any
+global 1 struct#1 const
+entry 2
+
+func 1 "f" (any "x" number) -> any
+  filter (number number) -> number 2
+  (seq:any
+    (undefined:undefined)
+    (null:null)
+    (number:number -1.5)
+    (number:number NaN)
+    (boolean:boolean true)
+    (string:string "a\n\"b\"")
+    (array:array)
+    (struct:struct#0 0)
+    (func:func (1 2+closure)
+      (undefined:undefined))
+    (typecast:any number narrow
+      (var:any (local 0))
+      (var:number (local 2))
+      (undefined:undefined))
+    (prim:number number_add
+      (number:number 1)
+      (number:number 2))
+    (array_load:any @0:1:2-3:4
+      (array:array)
+      (number:number 0))
+    (array_store:undefined @1:2:0-2:5
+      (array:array)
+      (number:number 0)
+      (undefined:undefined))
+    (appl:any @0:1:0-1:1
+      (var:func (global 1 1.0 0.0))
+      (number:number 1))
+    (direct_appl:undefined 0
+      (string:string "")
+      (number:number 1))
+    (if:any
+      (boolean:boolean false)
+      (undefined:any)
+      (number:any 1))
+    (declare:undefined struct#0 "y"
+      (struct:struct#0 0)
+      (declare:undefined any default
+        (assign:undefined (local 2 0.1)
+          (number:number 3))))
+    (block:undefined
+      (loop:undefined
+        (break:void 1
+          (undefined:undefined))))
+    (trap:void @0:5:0-5:1 7)
+    (return:void
+      (var:any (global 0))))
+
+func 2 (number number) -> number
+  (return:void
+    (var:number (local 1)))
+"#;
+
+    fn program() -> Program {
+        let mut program = text::parse(PROGRAM).unwrap();
+        // the textual syntax has no expression locations
+        program.funcs[1].expr.location = Some(SourceLocation {
+            file: 1,
+            start: Position { line: 2, column: 3 },
+            end: Position { line: 4, column: 5 },
+        });
+        program
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = program();
+        let bytes: Vec<u8> = encode(&program);
+        assert_eq!(bytes[0..4], MAGIC);
+        let decoded: Program = decode(&bytes).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(text::print(&decoded), text::print(&program));
+        assert_eq!(
+            decoded.funcs[1].expr.location,
+            program.funcs[1].expr.location
+        );
+        assert_eq!(encode(&decoded), bytes);
+    }
+
+    #[test]
+    fn errors() {
+        let bytes: Vec<u8> = encode(&program());
+        let message = |bytes: &[u8]| decode(bytes).unwrap_err().to_string();
+        assert_eq!(message(b"\0asm\x01\0\0\0"), "0: not an encoded IR program");
+        assert_eq!(
+            message(&[&MAGIC[..], &[1, 0, 0, 0]].concat()),
+            "4: unsupported version 1 (expected 2)"
+        );
+        assert_eq!(
+            message(&bytes[..bytes.len() - 1]),
+            format!("{}: unexpected end of input", bytes.len() - 1)
+        );
+        assert_eq!(
+            message(&[&bytes[..], &[0]].concat()),
+            format!(
+                "{}: unexpected bytes after the end of the program",
+                bytes.len()
+            )
+        );
+        // the first struct type starts with the vartype of its first field
+        let mut bad_vartype: Vec<u8> = bytes.clone();
+        assert_eq!(bad_vartype[10], VARTYPE_ANY);
+        bad_vartype[10] = 100;
+        assert_eq!(message(&bad_vartype), "10: invalid vartype tag 100");
+    }
+}
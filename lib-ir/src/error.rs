@@ -2,6 +2,9 @@ pub const ERROR_CODE_OUT_OF_MEMORY: u32 = 0x1;
 pub const ERROR_CODE_FUNCTION_PARAM_TYPE: u32 = 0x11;
 pub const ERROR_CODE_UNARY_OPERATOR_PARAM_TYPE: u32 = 0x12;
 pub const ERROR_CODE_BINARY_OPERATOR_PARAM_TYPE: u32 = 0x13;
+pub const ERROR_CODE_ARRAY_ACCESS_TYPE: u32 = 0x14;
 pub const ERROR_CODE_FUNCTION_APPLICATION_NOT_CALLABLE_TYPE: u32 = 0x16;
 pub const ERROR_CODE_IF_STATEMENT_CONDITION_TYPE: u32 = 0x17;
+pub const ERROR_CODE_LOOP_CONDITION_TYPE: u32 = 0x18;
+pub const ERROR_CODE_ARRAY_INDEX_OUT_OF_RANGE: u32 = 0x19;
 pub const ERROR_CODE_ACCESS_VAR_BEFORE_INIT: u32 = 0x1A;
@@ -25,22 +25,28 @@ use std::vec::Vec;
  * * * It should also figure out if a variable is read but never written between two function calls, then we can know if we need to pop then push it back to the gc roots, or just tee it from the gc roots into locals.
  * * todo!: Also, functions should be annotated with a flag whether they might do heap allocations.
  */
+pub mod binary;
 pub mod error;
 pub mod opt;
+pub mod remap;
 pub mod superset;
+pub mod text;
+pub mod verify;
+pub mod visit;
 // mod primfunc;
 
 // If it stores value `func_idx`, then it refers to imports[func_idx] if (func_idx < imports.len())
 // or funcs[func_idx - imports.len()] otherwise.
 pub type FuncIdx = usize;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Program {
     pub struct_types: Vec<Box<[VarType]>>, // stores the list of fields of all structs (i.e. objects) in the program (indexed with typeidx)
     pub imports: Box<[Import]>,            // list of imported functions
     pub funcs: Vec<Func>, // list of functions (some will be pre-generated for the pre-declared operators, e.g. + - * / % === and more)
     pub globals: Vec<VarType>, // list of global variables
-    pub entry_point: FuncIdx, // index of function to run when the program is started
+    pub const_globals: Vec<bool>, // whether each global is only ever assigned once (e.g. declared with `const`), either empty (if unknown) or one for each global
+    pub entry_point: FuncIdx,     // index of function to run when the program is started
 }
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
@@ -52,6 +58,8 @@ pub enum VarType {
     Boolean,
     String,                     // reference type
     Func,                       // holds a function ptr and a closure
+    Null,                       // the empty list; like Undefined, it has only one possible value
+    Array,                      // reference type; a growable array of Any
     StructT { typeidx: usize }, // reference type; typeid starts from zero and should be in range [0, object_types.len()).
 }
 impl Default for VarType {
@@ -71,11 +79,13 @@ impl VarType {
             VarType::Boolean => 3,
             VarType::String => 4,
             VarType::Func => 5,
+            VarType::Null => 6,
+            VarType::Array => 7,
             VarType::StructT { typeidx } => (NUM_PRIMITIVE_TAG_TYPES + typeidx) as i32,
         }
     }
 }
-pub const NUM_PRIMITIVE_TAG_TYPES: usize = 6; // does not include Any
+pub const NUM_PRIMITIVE_TAG_TYPES: usize = 8; // does not include Any
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug)]
 pub struct Import {
@@ -93,25 +103,28 @@ pub enum ImportValType {
     String, // compiles into i32(ptr) parameter, the host should look into our linear memory to figure out the length and the actual string content.
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Func {
     pub params: Box<[VarType]>, // list of function parameters (including closure)
     pub result: Option<VarType>, // if `None`, it means that this function never returns (e.g. it guarantees to trap or infinite loop, see the generated runtime error function)
     pub expr: Expr, // body of the function, must either return Void or return the correct result type
     pub signature_filter: Vec<(Box<[VarType]>, VarType, FuncIdx)>, // list of possibly acceptable signatures (param_types, return_type, constrained_func).
-                                                                   // If a signature is not in this list, then it will be guaranteed to error;
-                                                                   // but converse need not be true.  All entries must be a subtype of `params`.
-                                                                   // `constrained_func` (funcidx) is a version of this function that has the specified param_types and return_type of this entry.
-                                                                   // (i.e. if the caller can guarantee to have the correct types,
-                                                                   // then it can emit code to call the constrained_func instead of the current one)
-                                                                   // this list should not contain the entry where all the param types and return type are identical to the current one
-                                                                   // (because there is no use for a self-reference)
+    // If a signature is not in this list, then it will be guaranteed to error;
+    // but converse need not be true.  All entries must be a subtype of `params`.
+    // `constrained_func` (funcidx) is a version of this function that has the specified param_types and return_type of this entry.
+    // (i.e. if the caller can guarantee to have the correct types,
+    // then it can emit code to call the constrained_func instead of the current one)
+    // this list should not contain the entry where all the param types and return type are identical to the current one
+    // (because there is no use for a self-reference)
+    pub name: Option<String>, // name of the function in the source code (if any), only used for debugging (e.g. in the wasm name section)
+    pub param_names: Box<[Option<String>]>, // names of the params in the source code, either empty (if unknown) or one for each param
 }
 
 #[derive(Debug, Clone)]
 pub struct Expr {
     pub vartype: Option<VarType>, // the type set that this Expr is guaranteed to evaluate to (if unknown, just use ValType::Any).  Users of this expression will generate code that only works on this type.  It may also affect the memory layout of the expr.  Use `None` if this function is guaranteed to never return (aka it returns Void).
     pub kind: ExprKind,           // the variant kind of this expression
+    pub location: Option<SourceLocation>, // the source code that this expression was generated from (if known), only used for debugging (e.g. in source maps)
 }
 
 // Represents any lvalue (assignable value)
@@ -146,6 +159,7 @@ pub struct OverloadEntry {
 #[derive(Debug, Clone)]
 pub enum ExprKind {
     PrimUndefined, // also functions as a "no-op"
+    PrimNull,      // the empty list, i.e. `null`
     PrimNumber {
         val: f64,
     }, // e.g. `2`
@@ -155,6 +169,7 @@ pub enum ExprKind {
     PrimString {
         val: String,
     }, // e.g. `"hello world"`, may be placed in a region of memory immune to garbage collection
+    PrimArray, // an empty array, i.e. `[]` (semantically like PrimStructT, it will do a heap allocation)
     PrimStructT {
         typeidx: usize,
     }, // a struct (Any will be set to Unassigned variant; String, Func::closure, StructT will be set to something that the GC can recognise as a "null pointer" for that VarType)
@@ -188,6 +203,17 @@ pub enum ExprKind {
         prim_inst: PrimInst,
         args: Box<[Expr]>,
     }, // primitive operations (e.g. number+number) hardcoded into the compiler.  Expr must have the correct VarType.  Should not be added directly by semantic analyser, because parser is not type-aware.
+    ArrayLoad {
+        array: Box<Expr>,
+        index: Box<Expr>,
+        location: SourceLocation, // will be displayed in the error message if the index is out of range
+    }, // e.g. `a[i]`.  Static type of array must be array, and static type of index must be number.  Returns Any.
+    ArrayStore {
+        array: Box<Expr>,
+        index: Box<Expr>,
+        expr: Box<Expr>,
+        location: SourceLocation, // will be displayed in the error message if the index is out of range
+    }, // e.g. `a[i] = v`.  Static types are like ArrayLoad, and expr must not be noreturn.  Grows the array if index >= length (new elements are undefined).  Has Undefined type.
     Appl {
         func: Box<Expr>,
         args: Box<[Expr]>,
@@ -204,6 +230,7 @@ pub enum ExprKind {
     }, // a ? b : c // Static type of cond must be bool.
     Declaration {
         local: VarType,            // local variable being declared
+        name: Option<String>, // name of the local variable in the source code (if any), only used for debugging
         init: Option<Box<Expr>>, // initialization expr for this declaration (if None, then it is default initialized in a way determined by the GC); the declaration itself is not accessible here
         contained_expr: Box<Expr>, // expr in which the newly declared local is accessible
     }, // has the type of the `expr` (returns the value of the contained expr)
@@ -221,6 +248,9 @@ pub enum ExprKind {
     Block {
         expr: Box<Expr>,
     }, // Jump landing for Break; type must be at least as wide as expr.vartype and all Breaks that target this block
+    Loop {
+        expr: Box<Expr>,
+    }, // Jump landing for Break (at the beginning of the loop); all Breaks that target this loop must have Undefined type (the value is discarded); falling off the end of expr exits the loop, so this has the type of expr
     Sequence {
         content: Vec<Expr>,
     }, // returns the value of the last expression, or `undefined` if there are zero expressions
@@ -261,8 +291,11 @@ pub enum PrimInst {
     StringLt,
     StringGe,
     StringLe,
+    ArrayLength,
+    ReferenceEq, // true if both are the same reference (Array or StructT); the args are widened to Any so one prim works for every struct type
+    ReferenceNeq, // negation of ReferenceEq
 }
-pub const NUM_PRIM_INST: u8 = PrimInst::StringLe as u8 + 1;
+pub const NUM_PRIM_INST: u8 = PrimInst::ReferenceNeq as u8 + 1;
 
 // enum of pre-declared operators
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
@@ -313,6 +346,7 @@ impl Program {
             imports: imports,
             funcs: Default::default(),
             globals: Default::default(),
+            const_globals: Default::default(),
             entry_point: Default::default(),
         };
         //primfunc::add_prim_inst(program);
@@ -367,8 +401,11 @@ impl Func {
             expr: Expr {
                 vartype: Some(VarType::Undefined),
                 kind: ExprKind::PrimUndefined,
+                location: None,
             },
             signature_filter: Default::default(),
+            name: None,
+            param_names: Box::new([]),
         }
     }
     pub fn new_with_params_and_result(params: &[VarType], result: VarType) -> Func {
@@ -378,8 +415,11 @@ impl Func {
             expr: Expr {
                 vartype: Some(VarType::Undefined),
                 kind: ExprKind::PrimUndefined,
+                location: None,
             },
             signature_filter: Default::default(),
+            name: None,
+            param_names: Box::new([]),
         }
     }
     pub fn signature(&self) -> (&[VarType], Option<VarType>) {
@@ -416,6 +456,10 @@ impl PrimInst {
             | Self::StringLt
             | Self::StringGe
             | Self::StringLe => (&[VarType::String, VarType::String], Some(VarType::Boolean)),
+            Self::ArrayLength => (&[VarType::Array], Some(VarType::Number)),
+            Self::ReferenceEq | Self::ReferenceNeq => {
+                (&[VarType::Any, VarType::Any], Some(VarType::Boolean))
+            }
         }
     }
 }
@@ -435,6 +479,7 @@ impl Expr {
         if let Expr {
             vartype: Some(VarType::Undefined),
             kind: ExprKind::PrimUndefined,
+            location: _,
         } = self
         {
             true
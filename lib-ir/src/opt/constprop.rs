@@ -0,0 +1,589 @@
+/**
+ * Discretionary optimisation to propagate constants and copies through variables, so that `propagate` can fold them
+ * (e.g. `const n = 10; n * 2` into `20`, or the concatenation of constant strings into a single PrimString).
+ *
+ * Reads of these variables are replaced by their value:
+ * * locals that are declared with an initializer and never assigned (the value is a constant, or a copy of another such local)
+ * * globals that are in `const_globals` and are always assigned the same constant
+ * * struct fields (e.g. variables captured by closures) that are always assigned the same constant
+ *
+ * Globals and struct fields are checked over the whole program (including the functions before start_funcidx).
+ * Only const globals are used, because code that is compiled later in the REPL might assign to other globals.
+ *
+ * The IR does not check for reads of a variable before it is assigned (which give Unassigned),
+ * so a global or struct field is only replaced where an assignment to it is known to have happened already,
+ * i.e. the assignment comes earlier in the same function, and every path to the read goes through it.
+ * For struct fields, the struct must also be in the same local as in the assignment, and that local must never be assigned.
+ * This means that their values do not propagate into other functions, e.g. into closures.
+ * The declarations themselves are kept, even if nothing reads them any more.
+ */
+use super::superset::*;
+use super::*;
+use std::collections::HashMap;
+
+// The "constprop" pass (see `pipeline::Pass` for the return value).
+pub fn optimize(mut program: Program, start_funcidx: usize) -> (Program, bool) {
+    let places: HashMap<Place, Option<Expr>> = assigned_constants(&program);
+    let globals: Vec<Option<Expr>> = (0..program.globals.len())
+        .map(|globalidx| {
+            if program.const_globals.get(globalidx) == Some(&true) {
+                places.get(&Place::Global(globalidx)).cloned().flatten()
+            } else {
+                None
+            }
+        })
+        .collect();
+    let fields: HashMap<(usize, usize), Expr> = places
+        .into_iter()
+        .filter_map(|(place, opt_val)| match (place, opt_val) {
+            (Place::Field(typeidx, fieldidx), Some(val)) => Some(((typeidx, fieldidx), val)),
+            _ => None,
+        })
+        .collect();
+
+    let mut changed = false;
+    for func in program.funcs.iter_mut().skip(start_funcidx) {
+        let mut scopes = ScopeAnalysis {
+            stack: Vec::new(),
+            assigned: Vec::new(),
+        };
+        for _ in 0..func.params.len() {
+            scopes.push();
+        }
+        scopes.expr(&func.expr);
+
+        let mut rewriter = Rewriter {
+            globals: &globals,
+            fields: &fields,
+            assigned: &scopes.assigned,
+            next_scope: func.params.len(),
+            locals: (0..func.params.len())
+                .map(|i| (i, Value::Unknown))
+                .collect(),
+            known: HashMap::new(),
+            changed: false,
+        };
+        rewriter.expr(&mut func.expr);
+        changed |= rewriter.changed;
+    }
+    (program, changed)
+}
+
+// A variable that is not a local.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Place {
+    Global(usize),
+    Field(usize, usize), // (typeidx, fieldidx)
+}
+
+fn place_of(target: &TargetExpr) -> Option<Place> {
+    let (opt_global, mut next) = match target {
+        TargetExpr::Global { globalidx, next } => (Some(*globalidx), next),
+        TargetExpr::Local { localidx: _, next } => (None, next),
+    };
+    let mut ret: Option<Place> = opt_global.map(Place::Global);
+    while let Some(struct_field) = next {
+        ret = Some(Place::Field(struct_field.typeidx, struct_field.fieldidx));
+        next = &struct_field.next;
+    }
+    ret
+}
+
+fn is_constant(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::PrimUndefined
+            | ExprKind::PrimNull
+            | ExprKind::PrimNumber { val: _ }
+            | ExprKind::PrimBoolean { val: _ }
+            | ExprKind::PrimString { val: _ }
+    )
+}
+
+/**
+ * Returns the constant that the expr evaluates to, if it is a constant, or if it only wraps a constant
+ * in declarations (with constant or variable initializers) and blocks (e.g. the result of inlining a function).
+ */
+fn constant_value(expr: &Expr) -> Option<&Expr> {
+    // num_landings: the number of blocks in the expr that enclose the current subexpression
+    fn value(expr: &Expr, num_landings: usize) -> Option<&Expr> {
+        match &expr.kind {
+            _ if is_constant(expr) => Some(expr),
+            ExprKind::Declaration {
+                local: _,
+                name: _,
+                init,
+                contained_expr,
+            } => match init {
+                Some(init_expr)
+                    if !is_constant(init_expr)
+                        && !matches!(init_expr.kind, ExprKind::VarName { source: _ }) =>
+                {
+                    None
+                }
+                _ => value(contained_expr, num_landings),
+            },
+            ExprKind::Block { expr: expr2 } => value(expr2, num_landings + 1),
+            // every block is in a tail position, so its result is the result of the whole expr
+            ExprKind::Break {
+                num_frames,
+                expr: expr2,
+            } if *num_frames < num_landings => value(expr2, num_landings),
+            _ => None,
+        }
+    }
+    value(expr, 0)
+}
+
+// Both exprs must be constants.
+fn same_constant(a: &Expr, b: &Expr) -> bool {
+    match (&a.kind, &b.kind) {
+        (ExprKind::PrimUndefined, ExprKind::PrimUndefined) => true,
+        (ExprKind::PrimNull, ExprKind::PrimNull) => true,
+        // compare the bits, so that NaN is the same as itself but 0 is not the same as -0
+        (ExprKind::PrimNumber { val: x }, ExprKind::PrimNumber { val: y }) => {
+            x.to_bits() == y.to_bits()
+        }
+        (ExprKind::PrimBoolean { val: x }, ExprKind::PrimBoolean { val: y }) => x == y,
+        (ExprKind::PrimString { val: x }, ExprKind::PrimString { val: y }) => x == y,
+        _ => false,
+    }
+}
+
+/**
+ * Finds the value of every global and struct field that is assigned anywhere in the program:
+ * Some(constant) if all the assignments assign the same constant, or None otherwise.
+ */
+fn assigned_constants(program: &Program) -> HashMap<Place, Option<Expr>> {
+    fn visit(expr: &Expr, out: &mut HashMap<Place, Option<Expr>>) {
+        if let ExprKind::Assign {
+            target,
+            expr: expr2,
+        } = &expr.kind
+        {
+            if let Some(place) = place_of(target) {
+                let val: Option<Expr> = constant_value(expr2).cloned();
+                let entry = out.entry(place).or_insert_with(|| val.clone());
+                if let Some(old_val) = entry {
+                    if !val.is_some_and(|val| same_constant(old_val, &val)) {
+                        *entry = None;
+                    }
+                }
+            }
+        }
+        expr.for_each_child(|child| visit(child, out));
+    }
+    let mut ret: HashMap<Place, Option<Expr>> = HashMap::new();
+    for func in &program.funcs {
+        visit(&func.expr, &mut ret);
+    }
+    ret
+}
+
+/**
+ * Finds the locals that are assigned.
+ * Each local (params, declarations and narrowing typecasts) gets a scope id, in evaluation order.
+ */
+struct ScopeAnalysis {
+    stack: Vec<usize>, // the scope id of each local that is in scope (indexed by localidx)
+    assigned: Vec<bool>, // whether each scope (indexed by scope id) is assigned
+}
+
+impl ScopeAnalysis {
+    fn push(&mut self) {
+        self.stack.push(self.assigned.len());
+        self.assigned.push(false);
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Declaration {
+                local: _,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                if let Some(init_expr) = init {
+                    self.expr(init_expr);
+                }
+                self.push();
+                self.expr(contained_expr);
+                self.stack.pop();
+            }
+            ExprKind::TypeCast {
+                test,
+                expected: _,
+                create_narrow_local,
+                true_expr,
+                false_expr,
+            } => {
+                self.expr(test);
+                if *create_narrow_local {
+                    self.push();
+                    self.expr(true_expr);
+                    self.stack.pop();
+                } else {
+                    self.expr(true_expr);
+                }
+                self.expr(false_expr);
+            }
+            ExprKind::Assign {
+                target,
+                expr: expr2,
+            } => {
+                self.expr(expr2);
+                if let TargetExpr::Local {
+                    localidx,
+                    next: None,
+                } = target
+                {
+                    self.assigned[self.stack[*localidx]] = true;
+                }
+            }
+            _ => expr.for_each_child(|child| self.expr(child)),
+        }
+    }
+}
+
+// The value of a local.
+#[derive(Clone)]
+enum Value {
+    Unknown,
+    Constant(Expr),
+    Copy(usize, Option<VarType>), // the same value as the local with this localidx (which is never assigned), with the vartype of the read
+}
+
+// A global or struct field that can be read in the function being rewritten.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Slot {
+    Global(usize),
+    Field(usize, usize, usize), // (scope id of the local that holds the struct, typeidx, fieldidx)
+}
+
+/**
+ * Replaces the reads of variables with known values.
+ * Visits the locals in the same order as ScopeAnalysis, so that the scope ids match.
+ * Also visits the exprs in evaluation order, so that it knows which slots have been assigned before each read.
+ */
+struct Rewriter<'a> {
+    globals: &'a [Option<Expr>],
+    fields: &'a HashMap<(usize, usize), Expr>,
+    assigned: &'a [bool],
+    next_scope: usize,
+    locals: Vec<(usize, Value)>, // the (scope id, value) of each local that is in scope
+    known: HashMap<Slot, Expr>, // the slots that have certainly been assigned their constant by now
+    changed: bool,
+}
+
+impl<'a> Rewriter<'a> {
+    fn push(&mut self, value: Value) {
+        self.locals.push((self.next_scope, value));
+        self.next_scope += 1;
+    }
+
+    fn is_assigned(&self, localidx: usize) -> bool {
+        self.assigned[self.locals[localidx].0]
+    }
+
+    // Returns the slot of the target, if it is a global or a field of a struct in a local that is never assigned.
+    fn slot_of(&self, target: &TargetExpr) -> Option<Slot> {
+        match target {
+            TargetExpr::Global {
+                globalidx,
+                next: None,
+            } => Some(Slot::Global(*globalidx)),
+            TargetExpr::Local {
+                localidx,
+                next: Some(struct_field),
+            } if struct_field.next.is_none() && !self.is_assigned(*localidx) => Some(Slot::Field(
+                self.locals[*localidx].0,
+                struct_field.typeidx,
+                struct_field.fieldidx,
+            )),
+            _ => None,
+        }
+    }
+
+    // Returns the constant that every assignment to the slot assigns, if there is one.
+    fn constant_of(&self, slot: Slot) -> Option<&'a Expr> {
+        match slot {
+            Slot::Global(globalidx) => self.globals[globalidx].as_ref(),
+            Slot::Field(_, typeidx, fieldidx) => self.fields.get(&(typeidx, fieldidx)),
+        }
+    }
+
+    // Returns the value that a read of the target would give, if it is known.
+    fn value_of(&self, target: &TargetExpr) -> Value {
+        match target {
+            TargetExpr::Local {
+                localidx,
+                next: None,
+            } => self.locals[*localidx].1.clone(),
+            _ => self
+                .slot_of(target)
+                .and_then(|slot| self.known.get(&slot))
+                .cloned()
+                .map_or(Value::Unknown, Value::Constant),
+        }
+    }
+
+    // Visits an expr that might not be evaluated (or that might be left early by a break), so its assignments are forgotten afterwards.
+    fn expr_maybe(&mut self, expr: &mut Expr) {
+        let known = self.known.clone();
+        self.expr(expr);
+        self.known = known;
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::VarName { source } => match self.value_of(source) {
+                Value::Unknown => {}
+                Value::Constant(val) => {
+                    // the vartype of the read might be narrower if the variable was read before it was assigned
+                    if expr
+                        .vartype
+                        .is_some_and(|v| v.superset(&val.vartype.unwrap()))
+                    {
+                        let location = expr.location;
+                        *expr = val;
+                        expr.location = location;
+                        self.changed = true;
+                    }
+                }
+                Value::Copy(localidx, vartype) => {
+                    *source = TargetExpr::Local {
+                        localidx: localidx,
+                        next: None,
+                    };
+                    expr.vartype = vartype;
+                    self.changed = true;
+                }
+            },
+            ExprKind::Declaration {
+                local: _,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                let mut value = Value::Unknown;
+                if let Some(init_expr) = init {
+                    self.expr(init_expr);
+                    if !self.assigned[self.next_scope] {
+                        if let Some(val) = constant_value(init_expr) {
+                            value = Value::Constant(val.clone());
+                        } else if let ExprKind::VarName {
+                            source:
+                                TargetExpr::Local {
+                                    localidx,
+                                    next: None,
+                                },
+                        } = &init_expr.kind
+                        {
+                            if !self.is_assigned(*localidx) {
+                                value = Value::Copy(*localidx, init_expr.vartype);
+                            }
+                        }
+                    }
+                }
+                self.push(value);
+                self.expr(contained_expr);
+                self.locals.pop();
+            }
+            ExprKind::TypeCast {
+                test,
+                expected: _,
+                create_narrow_local,
+                true_expr,
+                false_expr,
+            } => {
+                self.expr(test);
+                if *create_narrow_local {
+                    self.push(Value::Unknown);
+                    self.expr_maybe(true_expr);
+                    self.locals.pop();
+                } else {
+                    self.expr_maybe(true_expr);
+                }
+                self.expr_maybe(false_expr);
+            }
+            ExprKind::Conditional {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                self.expr(cond);
+                self.expr_maybe(true_expr);
+                self.expr_maybe(false_expr);
+            }
+            ExprKind::Block { expr: expr2 } | ExprKind::Loop { expr: expr2 } => {
+                self.expr_maybe(expr2)
+            }
+            ExprKind::Assign {
+                target,
+                expr: expr2,
+            } => {
+                self.expr(expr2);
+                if let Some(slot) = self.slot_of(target) {
+                    if let Some(val) = self.constant_of(slot) {
+                        self.known.insert(slot, val.clone());
+                    }
+                }
+            }
+            _ => expr.for_each_child_mut(|child| self.expr(child)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs constprop and propagate until they make no more changes, and prints the body of the given function.
+    fn optimized_body(program: &str, funcidx: FuncIdx) -> String {
+        let program = text::parse(program).unwrap();
+        let optimized = Pipeline::new()
+            .fixed_point(&["constprop", "propagate"])
+            .unwrap()
+            .run(program, 0);
+        text::print_expr(&optimized.get_func(funcidx).expr)
+    }
+
+    #[test]
+    fn locals() {
+        // a constant, and a copy of the param
+        let body = optimized_body(
+            r#"entry 1
+
+func 0 (number) -> any
+  (declare:any number
+    (number:number 10)
+    (declare:any number
+      (var:number (local 0))
+      (seq:any
+        (prim:number number_mul
+          (var:number (local 1))
+          (number:number 2))
+        (prim:number number_add
+          (var:number (local 2))
+          (var:number (local 1))))))
+
+func 1 () -> any
+  (undefined:undefined)
+"#,
+            0,
+        );
+        // (the product is folded, and then dropped since it is unused)
+        assert!(
+            body.ends_with(
+                "(prim:number number_add\n      (var:number (local 0))\n      (number:number 10.0))))"
+            ),
+            "{}",
+            body
+        );
+        assert!(body.contains("(var:number (local 0))\n"), "{}", body);
+        assert!(!body.contains("(local 2)"), "{}", body);
+
+        // assigned locals are left alone
+        let body = optimized_body(
+            r#"entry 0
+
+func 0 () -> any
+  (declare:any number
+    (number:number 10)
+    (seq:any
+      (assign:undefined (local 0)
+        (number:number 11))
+      (prim:number number_mul
+        (var:number (local 0))
+        (number:number 2))))
+"#,
+            0,
+        );
+        assert!(body.contains("(var:number (local 0))"), "{}", body);
+    }
+
+    #[test]
+    fn globals_and_fields() {
+        // func 0 and func 1 only consume their args, so that the reads in the args are not dropped
+        const PROGRAM: &str = r#"struct 0 (any string)
+global 0 any const
+global 1 any
+entry 4
+
+func 0 (string string) -> any
+  (undefined:undefined)
+
+func 1 (any any) -> any
+  (undefined:undefined)
+
+func 2 (struct#0) -> any
+  (prim:string string_add
+    (var:string (local 0 0.1))
+    (var:string (global 0)))
+
+func 3 (boolean) -> any
+  (declare:any struct#0
+    (struct:struct#0 0)
+    (seq:any
+      (direct_appl:any 0
+        (var:string (local 1 0.1))
+        (var:string (global 0)))
+      (assign:undefined (global 0)
+        (block:string
+          (declare:string string
+            (string:string "b")
+            (break:void 0
+              (var:string (local 2))))))
+      (assign:undefined (global 1)
+        (number:number 1))
+      (if:undefined
+        (var:boolean (local 0))
+        (assign:undefined (local 1 0.0)
+          (boolean:boolean true))
+        (undefined:undefined))
+      (assign:undefined (local 1 0.1)
+        (string:string "a"))
+      (direct_appl:any 1
+        (var:any (local 1 0.0))
+        (var:any (global 1)))
+      (prim:string string_add
+        (var:string (local 1 0.1))
+        (var:string (global 0)))))
+
+func 4 () -> any
+  (direct_appl:any 3
+    (boolean:boolean true))
+"#;
+        let body = optimized_body(PROGRAM, 3);
+        // the reads after the assignments are replaced
+        assert!(body.contains("(string:string \"ab\")"), "{}", body);
+        // the reads before the assignments are left alone
+        assert!(
+            body.contains("(direct_appl:undefined 0\n      (var:string (local 1 0.1))\n      (var:string (global 0)))"),
+            "{}",
+            body
+        );
+        // a field that is only assigned in one branch, and a global that is not const, are left alone
+        assert!(
+            body.contains("(direct_appl:undefined 1\n      (var:any (local 1 0.0))\n      (var:any (global 1)))"),
+            "{}",
+            body
+        );
+        // reads in other functions are left alone, since they might run before the assignments
+        let body = optimized_body(PROGRAM, 2);
+        assert!(body.contains("(local 0 0.1)"), "{}", body);
+        assert!(body.contains("(global 0)"), "{}", body);
+
+        // a field that is assigned different values is left alone
+        let program = PROGRAM.replace(
+            "(string:string \"a\"))\n",
+            "(string:string \"a\"))\n      (assign:undefined (local 1 0.1)\n        (string:string \"c\"))\n",
+        );
+        let body = optimized_body(&program, 3);
+        assert!(
+            body.contains("(prim:string string_add\n      (var:string (local 1 0.1))\n      (string:string \"b\"))"),
+            "{}",
+            body
+        );
+    }
+}
@@ -0,0 +1,240 @@
+/**
+ * Snapshots of the IR before and after passes, for debugging the optimiser (see `Pipeline::run_with_dumps()`).
+ *
+ * Snapshots are printed in the textual IR syntax (see `ir::text`) with a `;` comment header,
+ * so a snapshot of the whole program can be parsed again (e.g. to rerun a single pass on it).
+ * Instead of the snapshot after a pass, the dump can be a diff of the functions that the pass changed.
+ */
+use super::pipeline::find_passes;
+use super::*;
+
+#[derive(Debug, Clone)]
+enum PassFilter {
+    Nothing,
+    All,
+    Only(Vec<&'static str>),
+}
+
+impl PassFilter {
+    fn from_names(names: &[&str]) -> Result<Self, String> {
+        if names.contains(&"all") {
+            return Ok(PassFilter::All);
+        }
+        Ok(PassFilter::Only(
+            find_passes(names)?
+                .into_iter()
+                .map(|pass| pass.name)
+                .collect(),
+        ))
+    }
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            PassFilter::Nothing => false,
+            PassFilter::All => true,
+            PassFilter::Only(names) => names.contains(&name),
+        }
+    }
+    fn matches_nothing(&self) -> bool {
+        match self {
+            PassFilter::Nothing => true,
+            PassFilter::All => false,
+            PassFilter::Only(names) => names.is_empty(),
+        }
+    }
+}
+
+/**
+ * Which snapshots to take.  Use the builder methods to make one; by default, nothing is dumped.
+ */
+#[derive(Debug, Clone)]
+pub struct DumpOptions {
+    before: PassFilter,
+    after: PassFilter,
+    funcidxs: Option<Vec<FuncIdx>>,
+    diff: bool,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        DumpOptions {
+            before: PassFilter::Nothing,
+            after: PassFilter::Nothing,
+            funcidxs: None,
+            diff: false,
+        }
+    }
+}
+
+impl DumpOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /**
+     * Dumps the IR before each run of the given passes (or of every pass, if the names contain "all").
+     * Unless one of them is "all", the names are checked with `find_passes()`.
+     */
+    pub fn print_before(mut self, names: &[&str]) -> Result<Self, String> {
+        self.before = PassFilter::from_names(names)?;
+        Ok(self)
+    }
+    /**
+     * Dumps the IR after each run of the given passes (or of every pass, if the names contain "all").
+     * The names are checked like in `print_before()`.
+     */
+    pub fn print_after(mut self, names: &[&str]) -> Result<Self, String> {
+        self.after = PassFilter::from_names(names)?;
+        Ok(self)
+    }
+    /**
+     * Only dumps the functions with the given funcidxs (by default, the dumps have the other items of the program
+     * and all the functions that are being optimised).
+     */
+    pub fn funcs(mut self, funcidxs: &[FuncIdx]) -> Self {
+        self.funcidxs = Some(funcidxs.to_vec());
+        self
+    }
+    /**
+     * If true, the dumps after passes are diffs against the IR before the pass,
+     * and passes that did not change the dumped functions have no dump.
+     */
+    pub fn diff(mut self, diff: bool) -> Self {
+        self.diff = diff;
+        self
+    }
+    /**
+     * Returns true if no snapshots would be taken.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.before.matches_nothing() && self.after.matches_nothing()
+    }
+
+    pub(super) fn before_pass(&self, name: &str) -> bool {
+        self.before.matches(name)
+    }
+    pub(super) fn after_pass(&self, name: &str) -> bool {
+        self.after.matches(name)
+    }
+    pub(super) fn is_diff(&self) -> bool {
+        self.diff
+    }
+
+    /**
+     * Prints the parts of the program selected by these options, as (label, text) sections.
+     */
+    pub(super) fn snapshot(
+        &self,
+        program: &Program,
+        start_funcidx: usize,
+    ) -> Vec<(String, String)> {
+        let mut ret: Vec<(String, String)> = Vec::new();
+        let num_imports = program.imports.len();
+        match &self.funcidxs {
+            None => {
+                ret.push(("items".to_owned(), text::print_items(program)));
+                for funcidx in num_imports + start_funcidx..num_imports + program.funcs.len() {
+                    ret.push(func_section(program, funcidx));
+                }
+            }
+            Some(funcidxs) => {
+                for funcidx in funcidxs {
+                    if *funcidx >= num_imports && *funcidx < num_imports + program.funcs.len() {
+                        ret.push(func_section(program, *funcidx));
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+fn func_section(program: &Program, funcidx: FuncIdx) -> (String, String) {
+    (
+        format!("func {}", funcidx),
+        text::print_func(program, funcidx),
+    )
+}
+
+/**
+ * Joins the sections of a snapshot into a single text.
+ */
+pub(super) fn join_snapshot(sections: &[(String, String)]) -> String {
+    sections
+        .iter()
+        .map(|(_, text)| text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/**
+ * Returns the diff between two snapshots, section by section (sections that are the same are left out).
+ */
+pub(super) fn diff_snapshots(old: &[(String, String)], new: &[(String, String)]) -> String {
+    let mut out = String::new();
+    let mut push_diff = |label: &str, old_text: &str, new_text: &str| {
+        let hunks = text::diff(old_text, new_text);
+        if !hunks.is_empty() {
+            out.push_str(&format!("--- {}\n+++ {}\n", label, label));
+            out.push_str(&hunks);
+        }
+    };
+    for (label, old_text) in old {
+        let new_text: &str = new
+            .iter()
+            .find(|(l, _)| l == label)
+            .map_or("", |(_, text)| text.as_str());
+        push_diff(label, old_text, new_text);
+    }
+    // sections that the pass added (e.g. new functions)
+    for (label, new_text) in new {
+        if !old.iter().any(|(l, _)| l == label) {
+            push_diff(label, "", new_text);
+        }
+    }
+    out
+}
+
+/**
+ * The kind of a dump.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpKind {
+    Before,
+    After,
+    Diff, // the changes made by the pass
+}
+
+/**
+ * A snapshot (or diff) of the IR at a point in a pipeline.
+ */
+#[derive(Debug, Clone)]
+pub struct PassDump {
+    pub name: &'static str, // the name of the pass
+    pub group: usize,       // index of the group in the pipeline
+    pub round: usize,       // one-based
+    pub kind: DumpKind,
+    pub text: String,
+}
+
+/**
+ * Prints the dump with a header comment, e.g.:
+ * ```text
+ * ;; IR after propagate (group 0, round 1)
+ * func 1 () -> any
+ *   ...
+ * ```
+ */
+impl std::fmt::Display for PassDump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.kind {
+            DumpKind::Before => "IR before",
+            DumpKind::After => "IR after",
+            DumpKind::Diff => "IR changes made by",
+        };
+        writeln!(
+            f,
+            ";; {} {} (group {}, round {})",
+            what, self.name, self.group, self.round
+        )?;
+        write!(f, "{}", self.text)
+    }
+}
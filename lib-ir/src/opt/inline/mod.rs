@@ -21,7 +21,7 @@ const INLINING_MAX_ALLOWABLE_COST: usize = 40;
  * 2. Let G be the call graph built from F (add an edge from f to g iff f contains a direct call to g (only actual direct calls, not transitive)).
  * 3. If there is at least one function in G that has zero outgoing edges:
  *   3a. Take any such function f, remove it from G.
- *     3ai. If there is only one direct call and no indirect calls to f, inline the call (at this point we can also remove f from the program - but we don't do this because REPL might still invoke the function later).
+ *     3ai. If there is only one direct call and no indirect calls to f, inline the call (f is not removed from the program here, because the REPL might still invoke it later - opt::remove_unused() removes it if nothing can).
  *     3aii. Otherwise, if f has a cost that is less than C, then inline all direct calls to f.
  *     3aiii. Otherwise, do nothing.
  *   3b. Go to step 3.
@@ -269,6 +269,7 @@ fn inline_by_destructive_move(direct_call_expr: &mut Expr, site: SiteProperties,
     *direct_call_expr = wrap_declarations(
         Vec::from(actual_args).into_iter(),
         &func.params,
+        &func.param_names,
         site,
         |site| relabel_inline_func(tmp_expr, site).0,
     );
@@ -279,6 +280,7 @@ fn inline_by_copy(direct_call_expr: &mut Expr, site: SiteProperties, func: &Func
     *direct_call_expr = wrap_declarations(
         Vec::from(actual_args).into_iter(),
         &func.params,
+        &func.param_names,
         site,
         |site| {
             let expr = func.expr.clone();
@@ -290,20 +292,22 @@ fn inline_by_copy(direct_call_expr: &mut Expr, site: SiteProperties, func: &Func
 /**
  * Wraps declarations on the Expr returned by f.
  * Actual args must be a subtype of the params.
+ * The declarations take the names of the params (if known), so that they can still be seen when debugging.
  */
 fn wrap_declarations<F: FnOnce(SiteProperties) -> Expr>(
     actual_args: impl Iterator<Item = Expr>,
     params: &[VarType],
+    param_names: &[Option<String>],
     site: SiteProperties,
     f: F,
 ) -> Expr {
     fn wrap_decl_recursive<F: FnOnce(SiteProperties) -> Expr>(
-        mut args_remaining: impl Iterator<Item = (Expr, VarType)>,
+        mut args_remaining: impl Iterator<Item = (Expr, VarType, Option<String>)>,
         args_relabeller: &mut Relabeller,
         f_site: SiteProperties,
         f: F,
     ) -> Expr {
-        if let Some((mut expr, vartype)) = args_remaining.next() {
+        if let Some((mut expr, vartype, name)) = args_remaining.next() {
             // emit one declaration
             let contained_expr = args_relabeller.with_skipped_new(|args_relabeller| {
                 wrap_decl_recursive(args_remaining, args_relabeller, f_site, f)
@@ -313,16 +317,21 @@ fn wrap_declarations<F: FnOnce(SiteProperties) -> Expr>(
                 vartype: contained_expr.vartype,
                 kind: ExprKind::Declaration {
                     local: vartype,
+                    name: name,
                     init: Some(Box::new(expr)),
                     contained_expr: Box::new(contained_expr),
                 },
+                location: None,
             }
         } else {
             f(f_site)
         }
     }
     wrap_decl_recursive(
-        actual_args.zip(params.iter().copied()),
+        actual_args
+            .zip(params.iter().copied())
+            .enumerate()
+            .map(|(i, (expr, vartype))| (expr, vartype, param_names.get(i).cloned().flatten())),
         &mut Relabeller::new_with_identities((0..site.num_locals()).into_iter()),
         site,
         f,
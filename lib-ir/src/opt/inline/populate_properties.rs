@@ -26,8 +26,10 @@ fn populate_properties(
     // Note: we explicitly list out all possibilities so we will get a compile error if a new exprkind is added.
     match &mut expr.kind {
         ExprKind::PrimUndefined => {}
+        ExprKind::PrimNull => {}
         ExprKind::PrimNumber { val: _ }
         | ExprKind::PrimBoolean { val: _ }
+        | ExprKind::PrimArray
         | ExprKind::PrimStructT { typeidx: _ }
         | ExprKind::PrimString { val: _ } => {
             inc_cost(&mut func_props[funcidx]);
@@ -67,6 +69,26 @@ fn populate_properties(
                 populate_properties(funcidx, arg, func_props, site);
             }
         }
+        ExprKind::ArrayLoad {
+            array,
+            index,
+            location: _,
+        } => {
+            populate_properties(funcidx, array, func_props, site);
+            populate_properties(funcidx, index, func_props, site);
+            inc_cost(&mut func_props[funcidx]);
+        }
+        ExprKind::ArrayStore {
+            array,
+            index,
+            expr,
+            location: _,
+        } => {
+            populate_properties(funcidx, array, func_props, site);
+            populate_properties(funcidx, index, func_props, site);
+            populate_properties(funcidx, expr, func_props, site);
+            inc_cost(&mut func_props[funcidx]);
+        }
         ExprKind::Appl {
             func,
             args,
@@ -98,6 +120,7 @@ fn populate_properties(
         }
         ExprKind::Declaration {
             local: _,
+            name: _,
             init,
             contained_expr,
         } => {
@@ -121,6 +144,9 @@ fn populate_properties(
         ExprKind::Block { expr } => {
             populate_properties(funcidx, expr, func_props, site);
         }
+        ExprKind::Loop { expr } => {
+            populate_properties(funcidx, expr, func_props, site);
+        }
         ExprKind::Sequence { content } => {
             for expr in content {
                 populate_properties(funcidx, expr, func_props, site);
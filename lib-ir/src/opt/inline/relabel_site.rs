@@ -15,6 +15,7 @@ pub(super) fn relabel_inline_func(mut expr: Expr, site: SiteProperties) -> (Expr
             kind: ExprKind::Block {
                 expr: Box::new(expr),
             },
+            location: None,
         },
         ret,
     )
@@ -24,8 +25,10 @@ fn relabel_site(expr: &mut Expr, site: SiteProperties, num_landings: usize) -> b
     // Note: we explicitly list out all possibilities so we will get a compile error if a new exprkind is added.
     match &mut expr.kind {
         ExprKind::PrimUndefined
+        | ExprKind::PrimNull
         | ExprKind::PrimNumber { val: _ }
         | ExprKind::PrimBoolean { val: _ }
+        | ExprKind::PrimArray
         | ExprKind::PrimStructT { typeidx: _ }
         | ExprKind::PrimString { val: _ } => false,
         ExprKind::PrimFunc {
@@ -47,6 +50,24 @@ fn relabel_site(expr: &mut Expr, site: SiteProperties, num_landings: usize) -> b
         ExprKind::PrimAppl { prim_inst: _, args } => args.iter_mut().fold(false, |prev, arg| {
             prev | relabel_site(arg, site, num_landings)
         }),
+        ExprKind::ArrayLoad {
+            array,
+            index,
+            location: _,
+        } => {
+            relabel_site(&mut **array, site, num_landings)
+                | relabel_site(&mut **index, site, num_landings)
+        }
+        ExprKind::ArrayStore {
+            array,
+            index,
+            expr,
+            location: _,
+        } => {
+            relabel_site(&mut **array, site, num_landings)
+                | relabel_site(&mut **index, site, num_landings)
+                | relabel_site(&mut **expr, site, num_landings)
+        }
         ExprKind::Appl {
             func,
             args,
@@ -71,6 +92,7 @@ fn relabel_site(expr: &mut Expr, site: SiteProperties, num_landings: usize) -> b
         }
         ExprKind::Declaration {
             local: _,
+            name: _,
             init,
             contained_expr,
         } => {
@@ -92,6 +114,7 @@ fn relabel_site(expr: &mut Expr, site: SiteProperties, num_landings: usize) -> b
                     num_frames: num_landings,
                     expr: Box::new(std::mem::replace(&mut **inner_expr, dummy_expr())),
                 },
+                location: expr.location,
             };
             true
         }
@@ -100,6 +123,7 @@ fn relabel_site(expr: &mut Expr, site: SiteProperties, num_landings: usize) -> b
             expr,
         } => relabel_site(&mut **expr, site, num_landings),
         ExprKind::Block { expr } => relabel_site(&mut **expr, site, num_landings + 1),
+        ExprKind::Loop { expr } => relabel_site(&mut **expr, site, num_landings + 1),
         ExprKind::Sequence { content } => content.iter_mut().fold(false, |prev, expr| {
             prev | relabel_site(expr, site, num_landings)
         }),
@@ -128,5 +152,6 @@ fn dummy_expr() -> Expr {
     Expr {
         vartype: Some(VarType::Undefined),
         kind: ExprKind::PrimUndefined,
+        location: None,
     }
 }
@@ -1,84 +1,62 @@
+mod constprop;
+mod dump;
 mod inline;
 mod landing_context;
+mod pipeline;
 mod propagate;
 mod relabeller;
+mod remove_unused;
+mod scalarize;
 mod typecast;
 mod unreachable;
 
 use super::*;
 
+pub use dump::DumpKind;
+pub use dump::DumpOptions;
+pub use dump::PassDump;
+pub use pipeline::pass_names;
+pub use pipeline::PassRun;
+pub use pipeline::Pipeline;
+pub use pipeline::Stats;
+pub use pipeline::MAX_OPT_LEVEL;
+pub use remove_unused::remove_unused;
+pub use remove_unused::ItemMap;
+pub use remove_unused::KeepAlive;
+
 /**
  * Main function to do mandatory optimizations for a program.
  * Mandatory optimizations are those that are required for the IR to function correctly.
+ * This is the pipeline of optimisation level 0.
  */
-pub fn optimize_mandatory(mut program: Program, start_funcidx: usize) -> Program {
-    let mut n: usize = 0;
-    const TOTAL: usize = 2;
-    loop {
-        {
-            let (new_program, changed) = unreachable::optimize(program, start_funcidx);
-            program = new_program;
-            if changed {
-                n = 1;
-            } else {
-                n += 1;
-            }
-            if n == TOTAL {
-                break;
-            }
-        }
-        {
-            let (new_program, changed) = typecast::optimize(program, start_funcidx);
-            program = new_program;
-            if changed {
-                n = 1;
-            } else {
-                n += 1;
-            }
-            if n == TOTAL {
-                break;
-            }
-        }
-    }
-
-    program
+pub fn optimize_mandatory(program: Program, start_funcidx: usize) -> Program {
+    Pipeline::for_level(0).run(program, start_funcidx)
 }
 
 /**
  * Main function to do discretionary optimizations for a program.
  * start_funcidx: The funcidx from which to optimise (used for REPL where part of the program has already been optimised).
+ * This is the pipeline of optimisation level 1.
  */
-pub fn optimize_all(mut program: Program, start_funcidx: usize) -> Program {
-    let mut n: usize = 0;
-    const TOTAL: usize = 2;
-    loop {
-        {
-            let (new_program, changed) = propagate::optimize(program, start_funcidx);
-            program = new_program;
-            if changed {
-                n = 0;
-            } else {
-                n += 1;
-            }
-            if n == TOTAL {
-                break;
-            }
-        }
-        {
-            let (new_program, changed) = inline::optimize(program, start_funcidx);
-            program = new_program;
-            if changed {
-                n = 0;
-            } else {
-                n += 1;
-            }
-            if n == TOTAL {
-                break;
-            }
+pub fn optimize_all(program: Program, start_funcidx: usize) -> Program {
+    Pipeline::for_level(1).run(program, start_funcidx)
+}
+
+/**
+ * In debug builds, checks the invariants of the program (see verify.rs) and panics if any of them are violated.
+ * `stage` is the pass that produced the program (or "the frontend" for the program given to the optimiser).
+ */
+fn debug_verify(program: &Program, stage: &str) {
+    if cfg!(debug_assertions) {
+        if let Err(errors) = verify::verify(program) {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            panic!(
+                "ICE: IR verification failed after {}:\n{}",
+                stage,
+                messages.join("\n")
+            );
         }
     }
-
-    program
 }
 
 /**
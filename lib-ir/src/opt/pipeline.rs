@@ -0,0 +1,524 @@
+/**
+ * The pass manager: runs optimisation passes (registered by name in `PASSES`) in a pipeline.
+ *
+ * A pipeline is a sequence of groups, and each group runs its passes in order, over and over,
+ * until a full round of the passes makes no change (i.e. until the program reaches a fixed point).
+ * Since some combinations of passes might never reach a fixed point, each group is also limited to a number of rounds.
+ *
+ * Pipelines are usually chosen by optimisation level (see `Pipeline::for_level()`),
+ * but they can also be made from explicit lists of pass names (e.g. for testing a single pass).
+ */
+use super::*;
+use std::time::Duration;
+use std::time::Instant;
+
+/**
+ * A registered pass.
+ * `run` is the `optimize()` function of the pass module, which takes the program and start_funcidx,
+ * and returns the new program and whether it differs from the old one (the pipeline relies on this to detect a fixed point).
+ */
+pub(super) struct Pass {
+    pub(super) name: &'static str,
+    run: fn(Program, usize) -> (Program, bool),
+    idempotent: bool, // if true, running the pass again straight after it made a change will not make any more changes
+}
+
+const PASSES: [Pass; 6] = [
+    Pass {
+        name: "unreachable",
+        run: unreachable::optimize,
+        idempotent: true,
+    },
+    Pass {
+        name: "typecast",
+        run: typecast::optimize,
+        idempotent: true,
+    },
+    Pass {
+        name: "propagate",
+        run: propagate::optimize,
+        idempotent: false,
+    },
+    Pass {
+        name: "constprop",
+        run: constprop::optimize,
+        idempotent: false,
+    },
+    Pass {
+        name: "inline",
+        run: inline::optimize,
+        idempotent: false,
+    },
+    Pass {
+        name: "scalarize",
+        run: scalarize::optimize,
+        idempotent: true,
+    },
+];
+
+// The highest optimisation level; higher levels are treated like this one.
+pub const MAX_OPT_LEVEL: u32 = 2;
+
+// The default limit on the number of rounds of each group.
+const DEFAULT_MAX_ROUNDS: usize = 100;
+
+/**
+ * Returns the names of all the passes that can be used in a pipeline.
+ */
+pub fn pass_names() -> impl Iterator<Item = &'static str> {
+    PASSES.iter().map(|pass| pass.name)
+}
+
+/**
+ * Returns the registered passes with the given names, in the same order.
+ * This is how every option that takes pass names checks them:
+ * if any of the names is not a registered pass, the error message names it and lists the registered passes.
+ */
+pub(super) fn find_passes(names: &[&str]) -> Result<Vec<&'static Pass>, String> {
+    names
+        .iter()
+        .map(|name| {
+            PASSES
+                .iter()
+                .find(|pass| pass.name == *name)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown pass `{}` (the passes are: {})",
+                        name,
+                        pass_names().collect::<Vec<_>>().join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+struct Group {
+    passes: Vec<&'static Pass>,
+}
+
+/**
+ * A sequence of groups of passes.  Use the builder methods to make one, and `run()` to optimise a program with it.
+ */
+#[derive(Clone)]
+pub struct Pipeline {
+    groups: Vec<Group>,
+    max_rounds: usize,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Pipeline {
+            groups: Vec::new(),
+            max_rounds: DEFAULT_MAX_ROUNDS,
+        }
+    }
+}
+
+impl Pipeline {
+    /**
+     * Makes a pipeline without any passes.
+     */
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /**
+     * Makes the pipeline for the given optimisation level:
+     * 0: only the mandatory passes (unreachable, typecast)
+     * 1: propagation, inlining and scalar replacement (propagate, constprop, inline, scalarize)
+     * 2 and above: level 1, and then the mandatory passes on the result
+     */
+    pub fn for_level(level: u32) -> Self {
+        let mandatory: &[&str] = &["unreachable", "typecast"];
+        let discretionary: &[&str] = &["propagate", "constprop", "inline", "scalarize"];
+        let groups: &[&[&str]] = match level {
+            0 => &[mandatory],
+            1 => &[discretionary],
+            _ => &[discretionary, mandatory],
+        };
+        groups.iter().fold(Pipeline::new(), |pipeline, names| {
+            pipeline
+                .fixed_point(names)
+                .expect("built-in pipeline has an unknown pass")
+        })
+    }
+    /**
+     * Adds a group with the given passes (which run in the given order) to the end of the pipeline.
+     * Fails if `find_passes()` rejects the names.
+     */
+    pub fn fixed_point(mut self, names: &[&str]) -> Result<Self, String> {
+        let passes: Vec<&'static Pass> = find_passes(names)?;
+        if !passes.is_empty() {
+            self.groups.push(Group { passes: passes });
+        }
+        Ok(self)
+    }
+    /**
+     * Sets the maximum number of rounds of each group (the default is 100).
+     * A group that reaches the limit stops there, even though the program might not be at a fixed point yet.
+     */
+    pub fn max_rounds(mut self, max_rounds: usize) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+    /**
+     * Optimises the program.
+     * start_funcidx: The funcidx from which to optimise (used for REPL where part of the program has already been optimised).
+     */
+    pub fn run(&self, program: Program, start_funcidx: usize) -> Program {
+        self.run_impl(program, start_funcidx, None, None)
+    }
+    /**
+     * Like `run()`, but also returns statistics about each pass that was run.
+     * This measures time with `std::time::Instant`, so it is not available on wasm32-unknown-unknown.
+     */
+    pub fn run_with_stats(&self, program: Program, start_funcidx: usize) -> (Program, Stats) {
+        let mut stats = Stats::default();
+        let program = self.run_impl(program, start_funcidx, Some(&mut stats), None);
+        (program, stats)
+    }
+    /**
+     * Like `run_with_stats()`, but also calls `on_dump` with the snapshots of the IR selected by `options`
+     * (the time taken to print them is not counted in the statistics).
+     */
+    pub fn run_with_dumps<F: FnMut(PassDump)>(
+        &self,
+        program: Program,
+        start_funcidx: usize,
+        options: &DumpOptions,
+        mut on_dump: F,
+    ) -> (Program, Stats) {
+        let mut stats = Stats::default();
+        let program = self.run_impl(
+            program,
+            start_funcidx,
+            Some(&mut stats),
+            Some((options, &mut on_dump)),
+        );
+        (program, stats)
+    }
+
+    fn run_impl(
+        &self,
+        mut program: Program,
+        start_funcidx: usize,
+        mut opt_stats: Option<&mut Stats>,
+        mut opt_dumps: Option<(&DumpOptions, &mut dyn FnMut(PassDump))>,
+    ) -> Program {
+        debug_verify(&program, "the frontend");
+        for (group_index, group) in self.groups.iter().enumerate() {
+            // the number of passes since the last change that do not need to be run again
+            let mut n: usize = 0;
+            let mut round: usize = 0;
+            'group: loop {
+                if round == self.max_rounds {
+                    if let Some(stats) = opt_stats.as_deref_mut() {
+                        stats.capped_groups.push(group_index);
+                    }
+                    break;
+                }
+                round += 1;
+                for pass in &group.passes {
+                    let make_dump = |kind: DumpKind, text: String| PassDump {
+                        name: pass.name,
+                        group: group_index,
+                        round: round,
+                        kind: kind,
+                        text: text,
+                    };
+                    // the snapshot before the pass, if it is needed for a diff
+                    let mut opt_old_snapshot: Option<Vec<(String, String)>> = None;
+                    if let Some((options, on_dump)) = opt_dumps.as_mut() {
+                        if options.before_pass(pass.name) || options.after_pass(pass.name) {
+                            let snapshot = options.snapshot(&program, start_funcidx);
+                            if options.before_pass(pass.name) {
+                                on_dump(make_dump(
+                                    DumpKind::Before,
+                                    dump::join_snapshot(&snapshot),
+                                ));
+                            }
+                            if options.after_pass(pass.name) && options.is_diff() {
+                                opt_old_snapshot = Some(snapshot);
+                            }
+                        }
+                    }
+                    let changed: bool;
+                    match opt_stats.as_deref_mut() {
+                        None => {
+                            let (new_program, new_changed) = (pass.run)(program, start_funcidx);
+                            program = new_program;
+                            changed = new_changed;
+                        }
+                        Some(stats) => {
+                            let num_exprs_before = num_exprs(&program, start_funcidx);
+                            let start_time = Instant::now();
+                            let (new_program, new_changed) = (pass.run)(program, start_funcidx);
+                            let time = start_time.elapsed();
+                            program = new_program;
+                            changed = new_changed;
+                            stats.runs.push(PassRun {
+                                name: pass.name,
+                                group: group_index,
+                                round: round,
+                                changed: changed,
+                                time: time,
+                                num_exprs_before: num_exprs_before,
+                                num_exprs_after: num_exprs(&program, start_funcidx),
+                            });
+                        }
+                    }
+                    debug_verify(&program, pass.name);
+                    if let Some((options, on_dump)) = opt_dumps.as_mut() {
+                        if options.after_pass(pass.name) {
+                            let snapshot = options.snapshot(&program, start_funcidx);
+                            match &opt_old_snapshot {
+                                None => on_dump(make_dump(
+                                    DumpKind::After,
+                                    dump::join_snapshot(&snapshot),
+                                )),
+                                Some(old_snapshot) => {
+                                    let diff = dump::diff_snapshots(old_snapshot, &snapshot);
+                                    if !diff.is_empty() {
+                                        on_dump(make_dump(DumpKind::Diff, diff));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    if changed {
+                        n = if pass.idempotent { 1 } else { 0 };
+                    } else {
+                        n += 1;
+                    }
+                    if n >= group.passes.len() {
+                        break 'group;
+                    }
+                }
+            }
+        }
+        program
+    }
+}
+
+// The number of expressions in the functions that are being optimised.
+fn num_exprs(program: &Program, start_funcidx: usize) -> usize {
+    program
+        .funcs
+        .iter()
+        .skip(start_funcidx)
+        .map(|func| func.expr.num_exprs())
+        .sum()
+}
+
+/**
+ * Statistics about a single run of a pass.
+ */
+#[derive(Debug, Clone)]
+pub struct PassRun {
+    pub name: &'static str,
+    pub group: usize, // index of the group in the pipeline
+    pub round: usize, // one-based
+    pub changed: bool,
+    pub time: Duration,
+    pub num_exprs_before: usize, // the number of expressions in the functions being optimised
+    pub num_exprs_after: usize,
+}
+
+/**
+ * Statistics about a run of a pipeline.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub runs: Vec<PassRun>,        // every run of every pass, in order
+    pub capped_groups: Vec<usize>, // the groups that stopped because they reached the maximum number of rounds
+}
+
+/**
+ * Prints a summary of the runs of each pass, e.g.:
+ * ```text
+ * pass          runs  changed   time (ms)   exprs
+ * propagate        3        2       1.234    -120
+ * ```
+ */
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<12} {:>5} {:>8} {:>11} {:>7}",
+            "pass", "runs", "changed", "time (ms)", "exprs"
+        )?;
+        // passes in order of their first run
+        let mut names: Vec<&'static str> = Vec::new();
+        for run in &self.runs {
+            if !names.contains(&run.name) {
+                names.push(run.name);
+            }
+        }
+        for name in names {
+            let runs = self.runs.iter().filter(|run| run.name == name);
+            let num_runs = runs.clone().count();
+            let num_changed = runs.clone().filter(|run| run.changed).count();
+            let time: Duration = runs.clone().map(|run| run.time).sum();
+            let delta: i64 = runs
+                .map(|run| run.num_exprs_after as i64 - run.num_exprs_before as i64)
+                .sum();
+            writeln!(
+                f,
+                "{:<12} {:>5} {:>8} {:>11.3} {:>+7}",
+                name,
+                num_runs,
+                num_changed,
+                time.as_secs_f64() * 1000.0,
+                delta
+            )?;
+        }
+        for group in &self.capped_groups {
+            writeln!(f, "group {} stopped at the maximum number of rounds", group)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A function that is called once (so it gets inlined), and a dead expression after a trap.
+    const PROGRAM: &str = r#"entry 2
+
+func 0 (number) -> number
+  (prim:number number_add
+    (var:number (local 0))
+    (number:number 1))
+
+func 1 (boolean) -> any
+  (if:any
+    (var:boolean (local 0))
+    (direct_appl:number 0
+      (number:number 41))
+    (seq:any
+      (trap:void @0:1:0-1:1 7)
+      (number:number 1)))
+
+func 2 () -> any
+  (direct_appl:any 1
+    (boolean:boolean true))
+"#;
+
+    #[test]
+    fn unknown_pass() {
+        assert_eq!(
+            Pipeline::new().fixed_point(&["inline", "nope"]).err(),
+            Some(
+                "unknown pass `nope` (the passes are: unreachable, typecast, propagate, constprop, inline, scalarize)"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn levels() {
+        let program = text::parse(PROGRAM).unwrap();
+        for level in 0..=MAX_OPT_LEVEL + 1 {
+            let (optimized, stats) = Pipeline::for_level(level).run_with_stats(program.clone(), 0);
+            assert!(stats.capped_groups.is_empty());
+            // every pass in the pipeline is run until it makes no more changes
+            let names: Vec<&str> = stats.runs.iter().map(|run| run.name).collect();
+            assert!(!stats.runs.last().unwrap().changed);
+            match level {
+                0 => {
+                    assert_eq!(names[0], "unreachable");
+                    assert!(stats.runs[0].changed);
+                    assert!(stats.runs[0].num_exprs_after < stats.runs[0].num_exprs_before);
+                    assert!(!names.contains(&"inline"));
+                }
+                _ => {
+                    assert_eq!(names[0], "propagate");
+                    assert!(names.contains(&"inline"));
+                    assert_eq!(names.contains(&"typecast"), level >= 2);
+                }
+            }
+            assert_eq!(
+                text::print(&optimized),
+                text::print(&Pipeline::for_level(level).run(program.clone(), 0))
+            );
+        }
+        // the summary has a line for each pass
+        let (_, stats) = Pipeline::for_level(2).run_with_stats(program, 0);
+        assert_eq!(stats.to_string().lines().count(), 7);
+    }
+
+    #[test]
+    fn dumps() {
+        let program = text::parse(PROGRAM).unwrap();
+        let pipeline = Pipeline::for_level(1);
+        let run = |options: &DumpOptions| -> Vec<PassDump> {
+            let mut dumps: Vec<PassDump> = Vec::new();
+            let (optimized, _) =
+                pipeline.run_with_dumps(program.clone(), 0, options, |dump| dumps.push(dump));
+            assert_eq!(
+                text::print(&optimized),
+                text::print(&pipeline.run(program.clone(), 0))
+            );
+            dumps
+        };
+
+        // the first snapshot is the whole program, and it can be parsed again
+        let dumps = run(&DumpOptions::new().print_before(&["all"]).unwrap());
+        assert_eq!(dumps[0].kind, DumpKind::Before);
+        assert_eq!(dumps[0].name, "propagate");
+        assert_eq!(dumps[0].text, text::print(&program));
+        assert!(dumps[0]
+            .to_string()
+            .starts_with(";; IR before propagate (group 0, round 1)\nentry 2\n"));
+        text::parse(&dumps[1].to_string()).unwrap();
+
+        // only the selected passes and functions
+        let dumps = run(&DumpOptions::new()
+            .print_after(&["inline"])
+            .unwrap()
+            .funcs(&[2]));
+        assert!(dumps
+            .iter()
+            .all(|dump| dump.name == "inline" && dump.kind == DumpKind::After));
+        assert!(dumps[0].text.starts_with("func 2 () -> any\n"));
+        assert!(!dumps[0].text.contains("direct_appl"));
+
+        // diffs are only dumped for passes that changed the selected functions
+        let dumps = run(&DumpOptions::new().print_after(&["all"]).unwrap().diff(true));
+        assert!(!dumps.is_empty());
+        assert!(dumps.iter().all(|dump| dump.kind == DumpKind::Diff));
+        let inlined = dumps.iter().find(|dump| dump.name == "inline").unwrap();
+        assert!(inlined
+            .text
+            .starts_with("--- func 1\n+++ func 1\n@@ -2,5 +2,9 @@\n"));
+        assert!(inlined
+            .text
+            .contains("\n--- func 2\n+++ func 2\n@@ -1,3 +1,13 @@\n"));
+        assert!(inlined.text.contains("\n-  (direct_appl:any 1\n"));
+
+        assert_eq!(
+            DumpOptions::new().print_before(&["nope"]).err(),
+            Some(
+                "unknown pass `nope` (the passes are: unreachable, typecast, propagate, constprop, inline, scalarize)"
+                    .to_owned()
+            )
+        );
+        assert!(DumpOptions::new().is_empty());
+    }
+
+    #[test]
+    fn max_rounds() {
+        let program = text::parse(PROGRAM).unwrap();
+        let pipeline = Pipeline::new()
+            .fixed_point(&["propagate", "inline"])
+            .unwrap()
+            .max_rounds(1);
+        let (_, stats) = pipeline.run_with_stats(program, 0);
+        assert_eq!(stats.runs.len(), 2);
+        assert_eq!(stats.capped_groups, vec![0]);
+        assert!(stats
+            .to_string()
+            .ends_with("group 0 stopped at the maximum number of rounds\n"));
+    }
+}
@@ -98,6 +98,10 @@ fn optimize_expr(
             assert!(expr.vartype == Some(VarType::Undefined));
             false
         }
+        ExprKind::PrimNull => {
+            assert!(expr.vartype == Some(VarType::Null));
+            false
+        }
         ExprKind::PrimNumber { val: _ } => {
             assert!(expr.vartype == Some(VarType::Number));
             false
@@ -106,6 +110,10 @@ fn optimize_expr(
             assert!(expr.vartype == Some(VarType::Boolean));
             false
         }
+        ExprKind::PrimArray => {
+            assert!(expr.vartype == Some(VarType::Array));
+            false
+        }
         ExprKind::PrimStructT { typeidx } => {
             assert!(expr.vartype == Some(VarType::StructT { typeidx: *typeidx }));
             false
@@ -173,9 +181,11 @@ fn optimize_expr(
                                     vartype: then.vartype,
                                     kind: ExprKind::Declaration {
                                         local: test_vartype,
+                                        name: None,
                                         init: Some(Box::new(test)),
                                         contained_expr: Box::new(then),
                                     },
+                                    location: out.location,
                                 };
                             } else {
                                 *out = Expr {
@@ -183,6 +193,7 @@ fn optimize_expr(
                                     kind: ExprKind::Sequence {
                                         content: vec![test, then],
                                     },
+                                    location: out.location,
                                 };
                             }
                         }
@@ -226,6 +237,62 @@ fn optimize_expr(
             }
             ret | try_const_eval(expr)
         }
+        ExprKind::ArrayLoad {
+            array,
+            index,
+            location: _,
+        } => {
+            assert!(expr.vartype == Some(VarType::Any));
+            let ret = optimize_expr(&mut **array, local_map, ctx, landing_ctx);
+            if array.vartype.is_none() {
+                let tmp_array = std::mem::replace(&mut **array, dummy_expr());
+                *expr = tmp_array;
+                true
+            } else {
+                let ret = ret | optimize_expr(&mut **index, local_map, ctx, landing_ctx);
+                if index.vartype.is_none() {
+                    let tmp_array = std::mem::replace(&mut **array, dummy_expr());
+                    let tmp_index = std::mem::replace(&mut **index, dummy_expr());
+                    *expr = make_sequence_from_exprs(vec![tmp_array, tmp_index]);
+                    true
+                } else {
+                    ret
+                }
+            }
+        }
+        ExprKind::ArrayStore {
+            array,
+            index,
+            expr: expr2,
+            location: _,
+        } => {
+            assert!(expr.vartype == Some(VarType::Undefined));
+            // If any operand is noreturn, then the store can't actually happen,
+            // so we are just executing the operands (up to the noreturn one) for their side-effects.
+            let ret = optimize_expr(&mut **array, local_map, ctx, landing_ctx);
+            if array.vartype.is_none() {
+                let tmp_array = std::mem::replace(&mut **array, dummy_expr());
+                *expr = tmp_array;
+                return true;
+            }
+            let ret = ret | optimize_expr(&mut **index, local_map, ctx, landing_ctx);
+            if index.vartype.is_none() {
+                let tmp_array = std::mem::replace(&mut **array, dummy_expr());
+                let tmp_index = std::mem::replace(&mut **index, dummy_expr());
+                *expr = make_sequence_from_exprs(vec![tmp_array, tmp_index]);
+                return true;
+            }
+            let ret = ret | optimize_expr(&mut **expr2, local_map, ctx, landing_ctx);
+            if expr2.vartype.is_none() {
+                let tmp_array = std::mem::replace(&mut **array, dummy_expr());
+                let tmp_index = std::mem::replace(&mut **index, dummy_expr());
+                let tmp_expr2 = std::mem::replace(&mut **expr2, dummy_expr());
+                *expr = make_sequence_from_exprs(vec![tmp_array, tmp_index, tmp_expr2]);
+                return true;
+            }
+            // the return type of ArrayStore is Undefined, so we don't need to change it
+            ret
+        }
         ExprKind::Appl {
             func,
             args,
@@ -307,6 +374,7 @@ fn optimize_expr(
         }
         ExprKind::Declaration {
             local: _,
+            name: _,
             init,
             contained_expr,
         } => {
@@ -400,6 +468,14 @@ fn optimize_expr(
                 )
             }
         }
+        ExprKind::Loop { expr: expr2 } => {
+            // Breaks that target this loop jump to its beginning, so their (Undefined) values never escape;
+            // the only way to leave the loop normally is to fall off the end of the body.
+            let (ret, _) = landing_ctx.with_landing(|landing_ctx| {
+                optimize_expr(&mut **expr2, local_map, ctx, landing_ctx)
+            });
+            ret | useful_update(&mut expr.vartype, expr2.vartype)
+        }
         ExprKind::Sequence { content } => {
             let tmp_content = std::mem::take(content);
             let mut changed = false;
@@ -741,6 +817,16 @@ fn try_const_eval(expr: &mut Expr) -> bool {
                     set_vartype(&mut expr.vartype, VarType::Boolean)
                 }
             }
+            PrimInst::ArrayLength => {
+                // arrays are mutable, so we can't evaluate this at compile time
+                assert!(args.len() == 1);
+                set_vartype(&mut expr.vartype, VarType::Number)
+            }
+            PrimInst::ReferenceEq | PrimInst::ReferenceNeq => {
+                // identity is only known at runtime
+                assert!(args.len() == 2);
+                set_vartype(&mut expr.vartype, VarType::Boolean)
+            }
         }
     } else {
         panic!("Expected PrimAppl");
@@ -774,7 +860,7 @@ fn try_devirtualize_appl(
             let overloads = std::mem::take(funcidxs);
             let mut allowable_overloads: Vec<OverloadEntry> = Vec::new();
             // iterate in the reverse direction, since we match them from back to front
-            'outer: for overload in Vec::from(overloads).into_iter() {
+            'outer: for overload in Vec::from(overloads).into_iter().rev() {
                 let sig: &[VarType] = &ctx.param_types[overload.funcidx];
                 if sig.len() != args.len() {
                     // wrong number of params, will never be matched
@@ -820,6 +906,7 @@ fn try_devirtualize_appl(
                             code: error::ERROR_CODE_FUNCTION_PARAM_TYPE,
                             location: std::mem::take(location),
                         },
+                        location: None,
                     });
 
                     *expr = make_sequence_from_exprs(content);
@@ -867,6 +954,7 @@ fn try_devirtualize_appl(
                                                 next: None,
                                             },
                                         },
+                                        location: None,
                                     })
                                     .chain(arg_localidxs.iter().copied().enumerate().map(
                                         |(i, localidx)| {
@@ -884,11 +972,13 @@ fn try_devirtualize_appl(
                                                         next: None,
                                                     },
                                                 },
+                                                location: None,
                                             }
                                         },
                                     ))
                                     .collect(),
                                 },
+                                location: None,
                             };
                             out.push(if direct_appl.vartype.is_some() {
                                 Expr {
@@ -897,6 +987,7 @@ fn try_devirtualize_appl(
                                         num_frames: 0, // jump out to the closest containing Block
                                         expr: Box::new(direct_appl),
                                     },
+                                    location: None,
                                 }
                             } else {
                                 direct_appl
@@ -963,7 +1054,8 @@ fn try_devirtualize_appl(
                                 arg_localidxs[idx] = orig_arg_localidx;
                                 let tmp_seq = make_sequence_from_exprs(new_out);
                                 out.push(Expr {
-                                    vartype: tmp_seq.vartype,
+                                    // the false branch falls through with undefined (to the next check or the trap)
+                                    vartype: union_type(tmp_seq.vartype, Some(VarType::Undefined)),
                                     kind: ExprKind::TypeCast {
                                         test: Box::new(Expr {
                                             vartype: args[idx].vartype,
@@ -973,12 +1065,14 @@ fn try_devirtualize_appl(
                                                     next: None,
                                                 },
                                             },
+                                            location: None,
                                         }),
                                         expected: vartype,
                                         create_narrow_local: true,
                                         true_expr: Box::new(tmp_seq),
                                         false_expr: Box::new(make_prim_undefined()),
                                     },
+                                    location: None,
                                 });
 
                                 if is_last && new_is_last {
@@ -989,6 +1083,7 @@ fn try_devirtualize_appl(
                                             code: error::ERROR_CODE_FUNCTION_PARAM_TYPE,
                                             location: location,
                                         },
+                                        location: None,
                                     });
                                 }
                             }
@@ -1051,9 +1146,11 @@ fn try_devirtualize_appl(
                                 vartype: inner_expr.vartype,
                                 kind: ExprKind::Declaration {
                                     local: arg.vartype.unwrap(),
+                                    name: None,
                                     init: Some(Box::new(arg)),
                                     contained_expr: Box::new(inner_expr),
                                 },
+                                location: None,
                             }
                         } else {
                             wrapped_expr
@@ -1080,9 +1177,11 @@ fn try_devirtualize_appl(
                                 vartype: wrapped_declarations_expr.vartype,
                                 kind: ExprKind::Declaration {
                                     local: closure.vartype.unwrap(),
+                                    name: None,
                                     init: Some(Box::new(tmp_closure)),
                                     contained_expr: Box::new(wrapped_declarations_expr),
                                 },
+                                location: None,
                             }
                         });
                     // wrap it in a block so that we can jump here after calling the function
@@ -1091,6 +1190,7 @@ fn try_devirtualize_appl(
                         kind: ExprKind::Block {
                             expr: Box::new(wrapped_declarations_with_closure),
                         },
+                        location: expr.location,
                     };
                 }
             }
@@ -1111,6 +1211,7 @@ fn make_sequence_from_exprs(exprs: Vec<Expr>) -> Expr {
         _ => Expr {
             vartype: exprs.last().unwrap().vartype,
             kind: ExprKind::Sequence { content: exprs },
+            location: None,
         },
     }
 }
@@ -1149,6 +1250,7 @@ fn dummy_expr() -> Expr {
     Expr {
         vartype: Some(VarType::Undefined),
         kind: ExprKind::PrimUndefined,
+        location: None,
     }
 }
 
@@ -1156,6 +1258,7 @@ fn make_prim_undefined() -> Expr {
     Expr {
         vartype: Some(VarType::Undefined),
         kind: ExprKind::PrimUndefined,
+        location: None,
     }
 }
 
@@ -1163,6 +1266,7 @@ fn make_prim_number(val: f64) -> Expr {
     Expr {
         vartype: Some(VarType::Number),
         kind: ExprKind::PrimNumber { val: val },
+        location: None,
     }
 }
 
@@ -1170,6 +1274,7 @@ fn make_prim_boolean(val: bool) -> Expr {
     Expr {
         vartype: Some(VarType::Boolean),
         kind: ExprKind::PrimBoolean { val: val },
+        location: None,
     }
 }
 
@@ -1177,16 +1282,18 @@ fn make_prim_string(val: String) -> Expr {
     Expr {
         vartype: Some(VarType::String),
         kind: ExprKind::PrimString { val: val },
+        location: None,
     }
 }
 
 /**
  * Returns true if this expr is a primitive that has no side effects,
- * i.e. it is PrimUndefined, PrimNumber, PrimBoolean, PrimString, PrimStructT, or PrimFunc whose closure is also a pure primitive.
+ * i.e. it is PrimUndefined, PrimNull, PrimNumber, PrimBoolean, PrimString, PrimStructT, or PrimFunc whose closure is also a pure primitive.
  */
-fn is_pure_primitive(expr: &Expr) -> bool {
+pub(super) fn is_pure_primitive(expr: &Expr) -> bool {
     match &expr.kind {
         ExprKind::PrimUndefined
+        | ExprKind::PrimNull
         | ExprKind::PrimNumber { val: _ }
         | ExprKind::PrimBoolean { val: _ }
         | ExprKind::PrimString { val: _ }
@@ -1198,3 +1305,37 @@ fn is_pure_primitive(expr: &Expr) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devirtualize_last_matching_overload() {
+        // overloads are matched from back to front, so a number arg must call func 1 (not the catch-all func 0)
+        let program = text::parse(
+            r#"entry 2
+
+func 0 (any) -> any
+  (var:any (local 0))
+
+func 1 (number) -> number
+  (var:number (local 0))
+
+func 2 () -> any
+  (appl:any @0:1:0-1:1
+    (func:func (0 1)
+      (undefined:undefined))
+    (number:number 1.0))
+"#,
+        )
+        .unwrap();
+        let optimized = Pipeline::new()
+            .fixed_point(&["propagate"])
+            .unwrap()
+            .run(program, 0);
+        let body = text::print_expr(&optimized.get_func(2).expr);
+        assert!(body.contains("(direct_appl:number 1"), "{}", body);
+        assert!(!body.contains("(direct_appl:any 0"), "{}", body);
+    }
+}
@@ -9,8 +9,10 @@ pub fn relabel(expr: &mut Expr, relabeller: &mut Relabeller) -> bool {
     // Note: we explicitly list out all possibilities so we will get a compile error if a new exprkind is added.
     match &mut expr.kind {
         ExprKind::PrimUndefined
+        | ExprKind::PrimNull
         | ExprKind::PrimNumber { val: _ }
         | ExprKind::PrimBoolean { val: _ }
+        | ExprKind::PrimArray
         | ExprKind::PrimStructT { typeidx: _ }
         | ExprKind::PrimString { val: _ } => false,
         ExprKind::PrimFunc {
@@ -36,6 +38,21 @@ pub fn relabel(expr: &mut Expr, relabeller: &mut Relabeller) -> bool {
         ExprKind::PrimAppl { prim_inst: _, args } => args
             .iter_mut()
             .fold(false, |prev, arg| prev | relabel(arg, relabeller)),
+        ExprKind::ArrayLoad {
+            array,
+            index,
+            location: _,
+        } => relabel(&mut **array, relabeller) | relabel(&mut **index, relabeller),
+        ExprKind::ArrayStore {
+            array,
+            index,
+            expr,
+            location: _,
+        } => {
+            relabel(&mut **array, relabeller)
+                | relabel(&mut **index, relabeller)
+                | relabel(&mut **expr, relabeller)
+        }
         ExprKind::Appl {
             func,
             args,
@@ -60,6 +77,7 @@ pub fn relabel(expr: &mut Expr, relabeller: &mut Relabeller) -> bool {
         }
         ExprKind::Declaration {
             local: _,
+            name: _,
             init,
             contained_expr,
         } => {
@@ -79,6 +97,7 @@ pub fn relabel(expr: &mut Expr, relabeller: &mut Relabeller) -> bool {
             expr,
         } => relabel(&mut **expr, relabeller),
         ExprKind::Block { expr } => relabel(&mut **expr, relabeller),
+        ExprKind::Loop { expr } => relabel(&mut **expr, relabeller),
         ExprKind::Sequence { content } => content
             .iter_mut()
             .fold(false, |prev, expr| prev | relabel(expr, relabeller)),
@@ -0,0 +1,461 @@
+/**
+ * Removal of the functions, globals and struct types that the program can never use,
+ * e.g. library functions that are never called, or functions that have been inlined into all their callers.
+ *
+ * The used items are found from the roots, which are the entry point and the items in the `KeepAlive` set:
+ * * A function is used if a used function calls it, makes a closure from it, or has it in its signature filter.
+ * * A global is used if a used function reads it.  Assignments to the other globals are removed,
+ *   but the assigned expr is kept if it might have side effects.
+ * * A struct type is used if a used function, global or struct type refers to it.
+ *   Struct type 0 is always kept, so that its typeidx (and so its tag) never changes:
+ *   it is the pair type of the frontend, and hosts decode values with its tag as pairs (e.g. the REPL in the driver).
+ *
+ * The remaining items keep their order, and the returned `ItemMap` tells the new index of each of them.
+ * Imports are never removed.
+ *
+ * This is not an optimisation pass in the pipeline, because anything else that holds indices into the program
+ * has to be renumbered with the `ItemMap` too.  For example, code compiled later in the REPL can refer to
+ * any name in the REPL context, so the items of those names have to be in the `KeepAlive` set,
+ * and the REPL context has to be renumbered afterwards.
+ */
+use super::propagate::is_pure_primitive;
+use super::*;
+use std::collections::HashMap;
+
+// The struct type that is always kept (see above).
+const PINNED_TYPEIDX: usize = 0;
+
+/**
+ * The items to keep even if the program does not use them (the entry point is always kept).
+ * Use the builder methods to make one.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct KeepAlive {
+    funcidxs: Vec<FuncIdx>,
+    globalidxs: Vec<usize>,
+    typeidxs: Vec<usize>,
+}
+
+impl KeepAlive {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /**
+     * Also keeps the functions with the given funcidxs.
+     */
+    pub fn funcs(mut self, funcidxs: &[FuncIdx]) -> Self {
+        self.funcidxs.extend_from_slice(funcidxs);
+        self
+    }
+    /**
+     * Also keeps the globals with the given globalidxs.
+     */
+    pub fn globals(mut self, globalidxs: &[usize]) -> Self {
+        self.globalidxs.extend_from_slice(globalidxs);
+        self
+    }
+    /**
+     * Also keeps the struct types with the given typeidxs.
+     */
+    pub fn struct_types(mut self, typeidxs: &[usize]) -> Self {
+        self.typeidxs.extend_from_slice(typeidxs);
+        self
+    }
+}
+
+/**
+ * Where each item of the original program went (`None` if it was removed).
+ */
+#[derive(Debug, Clone)]
+pub struct ItemMap {
+    funcs: Vec<Option<FuncIdx>>, // indexed by the old funcidx (including imports)
+    globals: Vec<Option<usize>>,
+    struct_types: Vec<Option<usize>>,
+}
+
+impl ItemMap {
+    /**
+     * Returns the new funcidx of the function with the given old funcidx, or None if it was removed.
+     */
+    pub fn func(&self, funcidx: FuncIdx) -> Option<FuncIdx> {
+        self.funcs.get(funcidx).copied().flatten()
+    }
+    /**
+     * Returns the new globalidx of the global with the given old globalidx, or None if it was removed.
+     */
+    pub fn global(&self, globalidx: usize) -> Option<usize> {
+        self.globals.get(globalidx).copied().flatten()
+    }
+    /**
+     * Returns the new typeidx of the struct type with the given old typeidx, or None if it was removed.
+     */
+    pub fn struct_type(&self, typeidx: usize) -> Option<usize> {
+        self.struct_types.get(typeidx).copied().flatten()
+    }
+    /**
+     * Returns the number of functions (not including imports), globals and struct types that were removed.
+     */
+    pub fn num_removed(&self) -> (usize, usize, usize) {
+        let count = |items: &[Option<usize>]| items.iter().filter(|item| item.is_none()).count();
+        (
+            count(&self.funcs),
+            count(&self.globals),
+            count(&self.struct_types),
+        )
+    }
+}
+
+/**
+ * Removes the unused items of the program (see the module comment), and returns the new program with the map of the items.
+ */
+pub fn remove_unused(mut program: Program, keep_alive: &KeepAlive) -> (Program, ItemMap) {
+    let used: Used = mark_used(&program, keep_alive);
+    let map = ItemMap {
+        funcs: renumber(&used.funcs),
+        globals: renumber(&used.globals),
+        struct_types: renumber(&used.struct_types),
+    };
+
+    // the dead assignments still have the old globalidxs
+    for func in &mut program.funcs {
+        remove_dead_assigns(&mut func.expr, &used.globals);
+    }
+    let num_imports = program.imports.len();
+    program.funcs = retain(program.funcs, &used.funcs[num_imports..]);
+    program.globals = retain(program.globals, &used.globals);
+    if !program.const_globals.is_empty() {
+        program.const_globals = retain(program.const_globals, &used.globals);
+    }
+    program.struct_types = retain(program.struct_types, &used.struct_types);
+    program.remap_funcidxs(|funcidx| {
+        map.func(funcidx)
+            .expect("ICE: IR->IR: a used function refers to a removed function")
+    });
+    program.remap_globalidxs(|globalidx| {
+        map.global(globalidx)
+            .expect("ICE: IR->IR: a used function refers to a removed global")
+    });
+    program.remap_typeidxs(|typeidx| {
+        map.struct_type(typeidx)
+            .expect("ICE: IR->IR: a used item refers to a removed struct type")
+    });
+
+    debug_verify(&program, "remove_unused");
+    (program, map)
+}
+
+struct Used {
+    funcs: Vec<bool>, // indexed by funcidx (including imports)
+    globals: Vec<bool>,
+    struct_types: Vec<bool>,
+}
+
+fn mark_used(program: &Program, keep_alive: &KeepAlive) -> Used {
+    let num_imports = program.imports.len();
+    let mut marker = Marker {
+        program: program,
+        used: Used {
+            funcs: (0..num_imports + program.funcs.len())
+                .map(|funcidx| funcidx < num_imports)
+                .collect(),
+            globals: vec![false; program.globals.len()],
+            struct_types: vec![false; program.struct_types.len()],
+        },
+        queue: Vec::new(),
+        pending: HashMap::new(),
+    };
+    marker.func(program.entry_point);
+    if !program.struct_types.is_empty() {
+        marker.struct_type(PINNED_TYPEIDX);
+    }
+    keep_alive
+        .funcidxs
+        .iter()
+        .for_each(|funcidx| marker.func(*funcidx));
+    keep_alive
+        .globalidxs
+        .iter()
+        .for_each(|globalidx| marker.global(*globalidx));
+    keep_alive
+        .typeidxs
+        .iter()
+        .for_each(|typeidx| marker.struct_type(*typeidx));
+    while let Some(funcidx) = marker.queue.pop() {
+        let func: &Func = program.get_func(funcidx);
+        func.params
+            .iter()
+            .for_each(|vartype| marker.vartype(*vartype));
+        if let Some(vartype) = func.result {
+            marker.vartype(vartype);
+        }
+        for (param_types, result_type, constrained_func) in &func.signature_filter {
+            param_types
+                .iter()
+                .for_each(|vartype| marker.vartype(*vartype));
+            marker.vartype(*result_type);
+            marker.func(*constrained_func);
+        }
+        marker.expr(&func.expr);
+    }
+    marker.used
+}
+
+struct Marker<'a> {
+    program: &'a Program,
+    used: Used,
+    queue: Vec<FuncIdx>, // functions that are used but not visited yet
+    pending: HashMap<usize, Vec<&'a Expr>>, // pure exprs assigned to each global that is not known to be used yet (they only need to be visited if the global is used)
+}
+
+impl<'a> Marker<'a> {
+    fn func(&mut self, funcidx: FuncIdx) {
+        if !self.used.funcs[funcidx] {
+            self.used.funcs[funcidx] = true;
+            self.queue.push(funcidx);
+        }
+    }
+    fn global(&mut self, globalidx: usize) {
+        if !self.used.globals[globalidx] {
+            self.used.globals[globalidx] = true;
+            self.vartype(self.program.globals[globalidx]);
+            for expr in self.pending.remove(&globalidx).unwrap_or_default() {
+                self.expr(expr);
+            }
+        }
+    }
+    fn struct_type(&mut self, typeidx: usize) {
+        if !self.used.struct_types[typeidx] {
+            self.used.struct_types[typeidx] = true;
+            let program: &'a Program = self.program;
+            program.struct_types[typeidx]
+                .iter()
+                .for_each(|vartype| self.vartype(*vartype));
+        }
+    }
+    fn vartype(&mut self, vartype: VarType) {
+        if let VarType::StructT { typeidx } = vartype {
+            self.struct_type(typeidx);
+        }
+    }
+    // Marks the target as read.
+    fn target(&mut self, target: &TargetExpr) {
+        let mut next: &Option<Box<StructField>> = match target {
+            TargetExpr::Global { globalidx, next } => {
+                self.global(*globalidx);
+                next
+            }
+            TargetExpr::Local { localidx: _, next } => next,
+        };
+        while let Some(field) = next {
+            self.struct_type(field.typeidx);
+            next = &field.next;
+        }
+    }
+    fn expr(&mut self, expr: &'a Expr) {
+        if let Some(vartype) = expr.vartype {
+            self.vartype(vartype);
+        }
+        match &expr.kind {
+            ExprKind::PrimStructT { typeidx } => self.struct_type(*typeidx),
+            ExprKind::PrimFunc {
+                funcidxs,
+                closure: _,
+            } => funcidxs
+                .iter()
+                .for_each(|overload_entry| self.func(overload_entry.funcidx)),
+            ExprKind::TypeCast {
+                test: _,
+                expected,
+                create_narrow_local: _,
+                true_expr: _,
+                false_expr: _,
+            } => self.vartype(*expected),
+            ExprKind::DirectAppl { funcidx, args: _ } => self.func(*funcidx),
+            ExprKind::Declaration {
+                local,
+                name: _,
+                init: _,
+                contained_expr: _,
+            } => self.vartype(*local),
+            ExprKind::VarName { source } => self.target(source),
+            ExprKind::Assign {
+                target:
+                    TargetExpr::Global {
+                        globalidx,
+                        next: None,
+                    },
+                expr: value,
+            } if !self.used.globals[*globalidx] && is_pure_primitive(value) => {
+                self.pending.entry(*globalidx).or_default().push(value);
+                return;
+            }
+            // assigning to a global does not make it used
+            ExprKind::Assign {
+                target:
+                    TargetExpr::Global {
+                        globalidx: _,
+                        next: None,
+                    },
+                expr: _,
+            } => {}
+            ExprKind::Assign { target, expr: _ } => self.target(target),
+            _ => {}
+        }
+        expr.for_each_child(|child| self.expr(child));
+    }
+}
+
+// Returns the new index of each item, where the unused items are removed and the used ones keep their order.
+fn renumber(used: &[bool]) -> Vec<Option<usize>> {
+    let mut next: usize = 0;
+    used.iter()
+        .map(|is_used| {
+            if *is_used {
+                next += 1;
+                Some(next - 1)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn retain<T>(items: Vec<T>, used: &[bool]) -> Vec<T> {
+    items
+        .into_iter()
+        .zip(used)
+        .filter_map(|(item, is_used)| if *is_used { Some(item) } else { None })
+        .collect()
+}
+
+// Replaces the assignments to unused globals by their side effects (if any).
+fn remove_dead_assigns(expr: &mut Expr, used_globals: &[bool]) {
+    if let ExprKind::Assign {
+        target: TargetExpr::Global {
+            globalidx,
+            next: None,
+        },
+        expr: value,
+    } = &mut expr.kind
+    {
+        if !used_globals[*globalidx] {
+            let value: Expr = std::mem::replace(
+                &mut **value,
+                Expr {
+                    vartype: Some(VarType::Undefined),
+                    kind: ExprKind::PrimUndefined,
+                    location: None,
+                },
+            );
+            expr.kind = if is_pure_primitive(&value) {
+                ExprKind::PrimUndefined
+            } else {
+                ExprKind::Sequence {
+                    content: vec![
+                        value,
+                        Expr {
+                            vartype: Some(VarType::Undefined),
+                            kind: ExprKind::PrimUndefined,
+                            location: None,
+                        },
+                    ],
+                }
+            };
+        }
+    }
+    expr.for_each_child_mut(|child| remove_dead_assigns(child, used_globals));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = r#"struct 0 (any)
+struct 1 (any)
+struct 2 (number struct#3)
+struct 3 (any)
+import 0 "misc" "display" (string) -> undefined
+global 0 any const
+global 1 struct#2
+global 2 any
+entry 4
+
+func 1 () -> any
+  (return:void
+    (struct:struct#1 1))
+
+func 2 () -> any
+  (return:void
+    (number:number 2))
+
+func 3 () -> any
+  (return:void
+    (direct_appl:undefined 0
+      (string:string "x")))
+
+func 4 () -> any
+  (seq:any
+    (assign:undefined (global 0)
+      (func:func (1)
+        (undefined:undefined)))
+    (assign:undefined (global 2)
+      (direct_appl:any 3))
+    (var:struct#2 (global 1)))
+"#;
+
+    #[test]
+    fn unused_items() {
+        let program = text::parse(PROGRAM).unwrap();
+        let (program, map) = remove_unused(program, &KeepAlive::new());
+        assert_eq!(
+            text::print(&program),
+            r#"struct 0 (any)
+struct 1 (number struct#2)
+struct 2 (any)
+import 0 "misc" "display" (string) -> undefined
+global 0 struct#1
+entry 2
+
+func 1 () -> any
+  (return:void
+    (direct_appl:undefined 0
+      (string:string "x")))
+
+func 2 () -> any
+  (seq:any
+    (undefined:undefined)
+    (seq:undefined
+      (direct_appl:any 1)
+      (undefined:undefined))
+    (var:struct#1 (global 0)))
+"#
+        );
+        assert_eq!(map.func(0), Some(0));
+        assert_eq!(map.func(1), None);
+        assert_eq!(map.func(4), Some(2));
+        assert_eq!(map.global(1), Some(0));
+        // struct 0 is kept even though nothing uses it
+        assert_eq!(map.struct_type(0), Some(0));
+        assert_eq!(map.struct_type(1), None);
+        assert_eq!(map.struct_type(3), Some(2));
+        assert_eq!(map.num_removed(), (2, 2, 1));
+    }
+
+    #[test]
+    fn keep_alive() {
+        let program = text::parse(PROGRAM).unwrap();
+        let keep_alive = KeepAlive::new()
+            .funcs(&[2])
+            .globals(&[0])
+            .struct_types(&[1]);
+        let (program, map) = remove_unused(program, &keep_alive);
+        // func 1 is used because it is assigned to global 0
+        assert_eq!(map.num_removed(), (0, 1, 0));
+        assert_eq!(map.global(0), Some(0));
+        assert_eq!(map.global(2), None);
+        assert_eq!(program.const_globals, vec![true, false]);
+        assert!(
+            text::print(&program).contains("(assign:undefined (global 0)\n      (func:func (1)")
+        );
+    }
+}
@@ -0,0 +1,485 @@
+/**
+ * Discretionary optimisation to replace structs that never escape the function by a separate local for each field
+ * (scalar replacement), so that they need no heap allocation.
+ *
+ * The frontend puts the variables that are captured by closures into an environment struct,
+ * i.e. a local that is declared with a PrimStructT.  The struct escapes when a closure is made from it,
+ * but after a call to the closure is devirtualised and inlined, it might not escape any more
+ * (the closure param of the inlined function is then just a copy of the struct).
+ *
+ * A struct local does not escape if it is only used in field accesses (VarName and Assign with a StructField),
+ * or as the init of another struct local (an alias) that does not escape either.
+ * Using it in any other way, e.g. storing it somewhere else, making a closure from it, or passing it to a function,
+ * makes it (and all its aliases) escape.  Assigning to the local (or an alias) as a whole also counts as escaping.
+ *
+ * The declaration of a struct that does not escape is replaced by declarations of its fields (which are default-initialized,
+ * like the fields of a new struct), and the declarations of its aliases are removed.
+ * The struct type itself is left in the program, even if nothing uses it any more (see `remove_unused()`).
+ */
+use super::*;
+
+// The "scalarize" pass (see `pipeline::Pass` for the return value).
+pub fn optimize(mut program: Program, start_funcidx: usize) -> (Program, bool) {
+    let mut changed = false;
+    for func in program.funcs.iter_mut().skip(start_funcidx) {
+        let mut analysis = EscapeAnalysis {
+            stack: vec![None; func.params.len()],
+            scopes: Vec::new(),
+        };
+        analysis.expr(&func.expr);
+        let replaced: Vec<bool> = analysis
+            .scopes
+            .iter()
+            .map(|scope| match scope {
+                Scope::Other => false,
+                Scope::Struct {
+                    typeidx: _,
+                    escapes,
+                } => !escapes,
+                Scope::Alias(struct_scope) => matches!(
+                    analysis.scopes[*struct_scope],
+                    Scope::Struct {
+                        typeidx: _,
+                        escapes: false
+                    }
+                ),
+            })
+            .collect();
+        if replaced.contains(&true) {
+            let mut rewriter = Rewriter {
+                struct_types: &program.struct_types,
+                scopes: &analysis.scopes,
+                replaced: &replaced,
+                next_scope: 0,
+                locals: (0..func.params.len()).map(Local::Kept).collect(),
+                num_locals: func.params.len(),
+            };
+            rewriter.expr(&mut func.expr);
+            changed = true;
+        }
+    }
+    (program, changed)
+}
+
+// What a local (a Declaration, or the narrowed local of a TypeCast) is, as far as escape analysis is concerned.
+#[derive(Copy, Clone)]
+enum Scope {
+    Other,
+    Struct { typeidx: usize, escapes: bool }, // declared with a new struct
+    Alias(usize), // declared with a copy of the struct with this scope id (and the same type)
+}
+
+/**
+ * Finds the struct locals that escape.
+ * Each local gets a scope id, in the order that the locals are declared (see constprop.rs).
+ */
+struct EscapeAnalysis {
+    stack: Vec<Option<usize>>, // the scope id of each local that is in scope (indexed by localidx), or None for params
+    scopes: Vec<Scope>,        // indexed by scope id
+}
+
+impl EscapeAnalysis {
+    // Returns the scope id of the struct that the local is (or is an alias of), if any.
+    fn struct_of(&self, localidx: usize) -> Option<usize> {
+        let scope_id: usize = self.stack[localidx]?;
+        match self.scopes[scope_id] {
+            Scope::Other => None,
+            Scope::Struct {
+                typeidx: _,
+                escapes: _,
+            } => Some(scope_id),
+            Scope::Alias(struct_scope) => Some(struct_scope),
+        }
+    }
+
+    fn escape(&mut self, localidx: usize) {
+        if let Some(struct_scope) = self.struct_of(localidx) {
+            if let Scope::Struct {
+                typeidx: _,
+                escapes,
+            } = &mut self.scopes[struct_scope]
+            {
+                *escapes = true;
+            }
+        }
+    }
+
+    fn push(&mut self, scope: Scope) {
+        self.stack.push(Some(self.scopes.len()));
+        self.scopes.push(scope);
+    }
+
+    // Returns the scope of a local that is declared with the given type and init.
+    fn declared_scope(&mut self, local: VarType, init: &Option<Box<Expr>>) -> Scope {
+        let init_expr: &Expr = match init {
+            Some(init_expr) => init_expr,
+            None => return Scope::Other,
+        };
+        match &init_expr.kind {
+            ExprKind::PrimStructT { typeidx }
+                if local == (VarType::StructT { typeidx: *typeidx }) =>
+            {
+                return Scope::Struct {
+                    typeidx: *typeidx,
+                    escapes: false,
+                };
+            }
+            ExprKind::VarName {
+                source:
+                    TargetExpr::Local {
+                        localidx,
+                        next: None,
+                    },
+            } => {
+                if let Some(struct_scope) = self.struct_of(*localidx) {
+                    if let Scope::Struct {
+                        typeidx,
+                        escapes: _,
+                    } = self.scopes[struct_scope]
+                    {
+                        if local == (VarType::StructT { typeidx: typeidx }) {
+                            return Scope::Alias(struct_scope);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.expr(init_expr);
+        Scope::Other
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Declaration {
+                local,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                let scope: Scope = self.declared_scope(*local, init);
+                self.push(scope);
+                self.expr(contained_expr);
+                self.stack.pop();
+            }
+            ExprKind::TypeCast {
+                test,
+                expected: _,
+                create_narrow_local,
+                true_expr,
+                false_expr,
+            } => {
+                self.expr(test);
+                if *create_narrow_local {
+                    self.push(Scope::Other);
+                    self.expr(true_expr);
+                    self.stack.pop();
+                } else {
+                    self.expr(true_expr);
+                }
+                self.expr(false_expr);
+            }
+            ExprKind::VarName {
+                source:
+                    TargetExpr::Local {
+                        localidx,
+                        next: None,
+                    },
+            } => self.escape(*localidx),
+            ExprKind::Assign {
+                target:
+                    TargetExpr::Local {
+                        localidx,
+                        next: None,
+                    },
+                expr: expr2,
+            } => {
+                self.expr(expr2);
+                self.escape(*localidx);
+            }
+            _ => expr.for_each_child(|child| self.expr(child)),
+        }
+    }
+}
+
+// Where an old local went.
+#[derive(Copy, Clone)]
+enum Local {
+    Kept(usize),   // the new localidx
+    Fields(usize), // the struct was replaced, and this is the new localidx of its first field
+}
+
+/**
+ * Replaces the structs that do not escape, and renumbers the locals.
+ * Visits the locals in the same order as EscapeAnalysis, so that the scope ids match.
+ */
+struct Rewriter<'a> {
+    struct_types: &'a [Box<[VarType]>],
+    scopes: &'a [Scope],
+    replaced: &'a [bool], // whether each scope (indexed by scope id) gets replaced
+    next_scope: usize,
+    locals: Vec<Local>, // indexed by the old localidx
+    num_locals: usize,  // the number of new locals that are in scope
+}
+
+impl<'a> Rewriter<'a> {
+    // Rewrites the expr with the given local (and the given number of new locals) in scope.
+    fn with_local(&mut self, local: Local, num_new_locals: usize, expr: &mut Expr) {
+        self.locals.push(local);
+        self.num_locals += num_new_locals;
+        self.expr(expr);
+        self.num_locals -= num_new_locals;
+        self.locals.pop();
+    }
+
+    fn target(&self, target: &mut TargetExpr) {
+        if let TargetExpr::Local { localidx, next } = target {
+            match self.locals[*localidx] {
+                Local::Kept(new_localidx) => *localidx = new_localidx,
+                Local::Fields(first_localidx) => {
+                    let field: StructField = *next
+                        .take()
+                        .expect("ICE: IR->IR: replaced struct is used as a whole");
+                    *localidx = first_localidx + field.fieldidx;
+                    *next = field.next;
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::Declaration {
+                local: _,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                // replaced structs and aliases are initialized with exprs that do not declare any locals,
+                // so the scope id of this declaration is only known after rewriting the other inits
+                let simple_init: Option<&Expr> = init.as_deref().filter(|init_expr| {
+                    matches!(
+                        init_expr.kind,
+                        ExprKind::PrimStructT { typeidx: _ } | ExprKind::VarName { source: _ }
+                    )
+                });
+                if simple_init.is_none() {
+                    if let Some(init_expr) = init {
+                        self.expr(init_expr);
+                    }
+                }
+                let scope_id: usize = self.next_scope;
+                self.next_scope += 1;
+                if !self.replaced[scope_id] {
+                    if let Some(init_expr) = init {
+                        if let ExprKind::VarName { source } = &mut init_expr.kind {
+                            self.target(source);
+                        }
+                    }
+                    self.with_local(Local::Kept(self.num_locals), 1, contained_expr);
+                    return;
+                }
+                match self.scopes[scope_id] {
+                    Scope::Struct {
+                        typeidx,
+                        escapes: _,
+                    } => {
+                        let fields: &[VarType] = &self.struct_types[typeidx];
+                        self.with_local(
+                            Local::Fields(self.num_locals),
+                            fields.len(),
+                            contained_expr,
+                        );
+                        // declare the fields in order, around the contained expr
+                        let mut new_expr: Expr =
+                            std::mem::replace(&mut **contained_expr, dummy_expr());
+                        for field_vartype in fields.iter().rev() {
+                            new_expr = Expr {
+                                vartype: expr.vartype,
+                                kind: ExprKind::Declaration {
+                                    local: *field_vartype,
+                                    name: None,
+                                    init: None,
+                                    contained_expr: Box::new(new_expr),
+                                },
+                                location: None,
+                            };
+                        }
+                        if !fields.is_empty() {
+                            new_expr.location = expr.location;
+                        }
+                        *expr = new_expr;
+                    }
+                    Scope::Alias(_) => {
+                        // the alias refers to the same fields as the local that it is initialized with
+                        let local: Local = match init.as_deref().map(|init_expr| &init_expr.kind) {
+                            Some(ExprKind::VarName {
+                                source:
+                                    TargetExpr::Local {
+                                        localidx,
+                                        next: None,
+                                    },
+                            }) => self.locals[*localidx],
+                            _ => panic!("ICE: IR->IR: alias is not initialized with a local"),
+                        };
+                        self.with_local(local, 0, contained_expr);
+                        *expr = std::mem::replace(&mut **contained_expr, dummy_expr());
+                    }
+                    Scope::Other => panic!("ICE: IR->IR: replaced local is not a struct"),
+                }
+            }
+            ExprKind::TypeCast {
+                test,
+                expected: _,
+                create_narrow_local,
+                true_expr,
+                false_expr,
+            } => {
+                self.expr(test);
+                if *create_narrow_local {
+                    self.next_scope += 1;
+                    self.with_local(Local::Kept(self.num_locals), 1, true_expr);
+                } else {
+                    self.expr(true_expr);
+                }
+                self.expr(false_expr);
+            }
+            ExprKind::VarName { source } => self.target(source),
+            ExprKind::Assign {
+                target,
+                expr: expr2,
+            } => {
+                self.expr(expr2);
+                self.target(target);
+            }
+            _ => expr.for_each_child_mut(|child| self.expr(child)),
+        }
+    }
+}
+
+fn dummy_expr() -> Expr {
+    Expr {
+        vartype: Some(VarType::Undefined),
+        kind: ExprKind::PrimUndefined,
+        location: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalarized_body(program: &str) -> (String, bool) {
+        let (program, changed) = optimize(text::parse(program).unwrap(), 0);
+        (text::print_expr(&program.get_func(0).expr), changed)
+    }
+
+    #[test]
+    fn struct_and_alias() {
+        // an environment struct, and a copy of it (like the closure param of an inlined function)
+        let (body, changed) = scalarized_body(
+            r#"struct 0 (number any)
+entry 1
+
+func 0 (number) -> any
+  (declare:number struct#0
+    (struct:struct#0 0)
+    (seq:number
+      (assign:undefined (local 1 0.0)
+        (var:number (local 0)))
+      (declare:number struct#0
+        (var:struct#0 (local 1))
+        (seq:number
+          (assign:undefined (local 2 0.1)
+            (string:string "a"))
+          (declare:number number
+            (number:number 2.0)
+            (prim:number number_add
+              (var:number (local 2 0.0))
+              (var:number (local 3))))))))
+
+func 1 () -> any
+  (direct_appl:any 0
+    (number:number 1.0))
+"#,
+        );
+        assert!(changed);
+        assert_eq!(
+            body,
+            r#"(declare:number number default
+  (declare:number any default
+    (seq:number
+      (assign:undefined (local 1)
+        (var:number (local 0)))
+      (seq:number
+        (assign:undefined (local 2)
+          (string:string "a"))
+        (declare:number number
+          (number:number 2.0)
+          (prim:number number_add
+            (var:number (local 1))
+            (var:number (local 3))))))))"#
+        );
+    }
+
+    #[test]
+    fn escaping_structs() {
+        // made into a closure, or assigned as a whole
+        const PROGRAM: &str = r#"struct 0 (number)
+entry 0
+
+func 0 () -> any
+  (declare:any struct#0
+    (struct:struct#0 0)
+    (declare:any struct#0
+      (var:struct#0 (local 0))
+      (seq:any
+        (assign:undefined (local 1 0.0)
+          (number:number 1.0))
+        (func:func (0+closure)
+          (var:struct#0 (local 1))))))
+"#;
+        let (body, changed) = scalarized_body(PROGRAM);
+        assert!(!changed);
+        assert!(
+            body.starts_with("(declare:any struct#0\n  (struct:struct#0 0)"),
+            "{}",
+            body
+        );
+
+        let (_, changed) = scalarized_body(&PROGRAM.replace(
+            "(func:func (0+closure)\n          (var:struct#0 (local 1)))",
+            "(assign:undefined (local 1)\n          (struct:struct#0 0))",
+        ));
+        assert!(!changed);
+
+        // only the inner struct is stored in another struct
+        let (body, changed) = scalarized_body(
+            r#"struct 0 (struct#1)
+struct 1 (number)
+entry 0
+
+func 0 () -> any
+  (declare:number struct#1
+    (struct:struct#1 1)
+    (declare:number struct#0
+      (struct:struct#0 0)
+      (seq:number
+        (assign:undefined (local 1 0.0)
+          (var:struct#1 (local 0)))
+        (var:number (local 1 0.0 1.0)))))
+"#,
+        );
+        assert!(changed);
+        assert_eq!(
+            body,
+            r#"(declare:number struct#1
+  (struct:struct#1 1)
+  (declare:number struct#1 default
+    (seq:number
+      (assign:undefined (local 1)
+        (var:struct#1 (local 0)))
+      (var:number (local 1 1.0)))))"#
+        );
+    }
+}
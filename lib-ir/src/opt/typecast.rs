@@ -38,9 +38,11 @@ fn write_new_expr(
             vartype: then.vartype,
             kind: ExprKind::Declaration {
                 local: test_vartype,
+                name: None,
                 init: Some(Box::new(test)),
                 contained_expr: Box::new(then),
             },
+            location: out.location,
         }
     } else {
         *out = Expr {
@@ -48,6 +50,7 @@ fn write_new_expr(
             kind: ExprKind::Sequence {
                 content: vec![test, then],
             },
+            location: out.location,
         }
     }
 }
@@ -59,8 +62,10 @@ fn optimize_expr(expr: &mut Expr, local_map: &mut Relabeller) -> bool {
     // Note: we explicitly list out all possibilities so we will get a compile error if a new exprkind is added.
     match &mut expr.kind {
         ExprKind::PrimUndefined
+        | ExprKind::PrimNull
         | ExprKind::PrimNumber { val: _ }
         | ExprKind::PrimBoolean { val: _ }
+        | ExprKind::PrimArray
         | ExprKind::PrimStructT { typeidx: _ }
         | ExprKind::PrimString { val: _ } => false,
         ExprKind::PrimFunc {
@@ -125,6 +130,21 @@ fn optimize_expr(expr: &mut Expr, local_map: &mut Relabeller) -> bool {
         ExprKind::PrimAppl { prim_inst: _, args } => args
             .iter_mut()
             .fold(false, |prev, arg| prev | optimize_expr(arg, local_map)),
+        ExprKind::ArrayLoad {
+            array,
+            index,
+            location: _,
+        } => optimize_expr(&mut **array, local_map) | optimize_expr(&mut **index, local_map),
+        ExprKind::ArrayStore {
+            array,
+            index,
+            expr,
+            location: _,
+        } => {
+            optimize_expr(&mut **array, local_map)
+                | optimize_expr(&mut **index, local_map)
+                | optimize_expr(&mut **expr, local_map)
+        }
         ExprKind::Appl {
             func,
             args,
@@ -149,6 +169,7 @@ fn optimize_expr(expr: &mut Expr, local_map: &mut Relabeller) -> bool {
         }
         ExprKind::Declaration {
             local: _,
+            name: _,
             init,
             contained_expr,
         } => {
@@ -169,6 +190,7 @@ fn optimize_expr(expr: &mut Expr, local_map: &mut Relabeller) -> bool {
             expr,
         } => optimize_expr(&mut **expr, local_map),
         ExprKind::Block { expr } => optimize_expr(&mut **expr, local_map),
+        ExprKind::Loop { expr } => optimize_expr(&mut **expr, local_map),
         ExprKind::Sequence { content } => content
             .iter_mut()
             .fold(false, |prev, expr| prev | optimize_expr(expr, local_map)),
@@ -183,5 +205,6 @@ fn dummy_expr() -> Expr {
     Expr {
         vartype: Some(VarType::Undefined),
         kind: ExprKind::PrimUndefined,
+        location: None,
     }
 }
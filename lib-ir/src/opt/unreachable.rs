@@ -27,8 +27,10 @@ fn optimize_expr(expr: &mut Expr) -> bool {
     // Note: we explicitly list out all possibilities so we will get a compile error if a new exprkind is added.
     match &mut expr.kind {
         ExprKind::PrimUndefined
+        | ExprKind::PrimNull
         | ExprKind::PrimNumber { val: _ }
         | ExprKind::PrimBoolean { val: _ }
+        | ExprKind::PrimArray
         | ExprKind::PrimStructT { typeidx: _ }
         | ExprKind::PrimString { val: _ } => false,
         ExprKind::PrimFunc {
@@ -50,6 +52,17 @@ fn optimize_expr(expr: &mut Expr) -> bool {
         ExprKind::PrimAppl { prim_inst: _, args } => args
             .iter_mut()
             .fold(false, |prev, arg| prev | optimize_expr(arg)),
+        ExprKind::ArrayLoad {
+            array,
+            index,
+            location: _,
+        } => optimize_expr(&mut **array) | optimize_expr(&mut **index),
+        ExprKind::ArrayStore {
+            array,
+            index,
+            expr,
+            location: _,
+        } => optimize_expr(&mut **array) | optimize_expr(&mut **index) | optimize_expr(&mut **expr),
         ExprKind::Appl {
             func,
             args,
@@ -74,6 +87,7 @@ fn optimize_expr(expr: &mut Expr) -> bool {
         }
         ExprKind::Declaration {
             local: _,
+            name: _,
             init,
             contained_expr,
         } => {
@@ -134,6 +148,12 @@ fn optimize_expr(expr: &mut Expr) -> bool {
             }
         }
         ExprKind::Block { expr } => optimize_expr(&mut **expr),
+        ExprKind::Loop { expr: expr2 } => {
+            let ret = optimize_expr(&mut **expr2);
+            // The only way to leave the loop normally is to fall off the end of the body
+            expr.vartype = expr2.vartype;
+            ret
+        }
         ExprKind::Sequence { content } => {
             let tmp_content = std::mem::take(content);
             let mut changed = false;
@@ -165,5 +185,6 @@ fn dummy_expr() -> Expr {
     Expr {
         vartype: Some(VarType::Undefined),
         kind: ExprKind::PrimUndefined,
+        location: None,
     }
 }
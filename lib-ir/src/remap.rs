@@ -0,0 +1,295 @@
+/**
+ * Renumbering of functions, e.g. when imports are added to a program whose functions have already been generated
+ * (the imports come before all other functions, so every funcidx after them has to be shifted).
+ * Globals and struct types can be renumbered too, e.g. when unused ones are removed (see `opt::remove_unused()`).
+ */
+use super::*;
+
+impl Program {
+    /**
+     * Replaces every funcidx in the program (in function bodies, signature filters, and the entry point) with `f(funcidx)`.
+     * This does not move the functions themselves.
+     */
+    pub fn remap_funcidxs<F: Fn(FuncIdx) -> FuncIdx>(&mut self, f: F) {
+        for func in &mut self.funcs {
+            func.remap_funcidxs(&f);
+        }
+        self.entry_point = f(self.entry_point);
+    }
+}
+
+impl Func {
+    pub fn remap_funcidxs<F: Fn(FuncIdx) -> FuncIdx>(&mut self, f: &F) {
+        for (_, _, constrained_func) in &mut self.signature_filter {
+            *constrained_func = f(*constrained_func);
+        }
+        self.expr.remap_funcidxs(f);
+    }
+}
+
+impl Expr {
+    pub fn remap_funcidxs<F: Fn(FuncIdx) -> FuncIdx>(&mut self, f: &F) {
+        match &mut self.kind {
+            ExprKind::PrimUndefined
+            | ExprKind::PrimNull
+            | ExprKind::PrimNumber { val: _ }
+            | ExprKind::PrimBoolean { val: _ }
+            | ExprKind::PrimString { val: _ }
+            | ExprKind::PrimArray
+            | ExprKind::PrimStructT { typeidx: _ }
+            | ExprKind::VarName { source: _ }
+            | ExprKind::Trap {
+                code: _,
+                location: _,
+            } => {}
+            ExprKind::PrimFunc { funcidxs, closure } => {
+                for overload_entry in funcidxs.iter_mut() {
+                    overload_entry.funcidx = f(overload_entry.funcidx);
+                }
+                closure.remap_funcidxs(f);
+            }
+            ExprKind::TypeCast {
+                test,
+                expected: _,
+                create_narrow_local: _,
+                true_expr,
+                false_expr,
+            } => {
+                test.remap_funcidxs(f);
+                true_expr.remap_funcidxs(f);
+                false_expr.remap_funcidxs(f);
+            }
+            ExprKind::PrimAppl { prim_inst: _, args } => {
+                for arg in args.iter_mut() {
+                    arg.remap_funcidxs(f);
+                }
+            }
+            ExprKind::ArrayLoad {
+                array,
+                index,
+                location: _,
+            } => {
+                array.remap_funcidxs(f);
+                index.remap_funcidxs(f);
+            }
+            ExprKind::ArrayStore {
+                array,
+                index,
+                expr,
+                location: _,
+            } => {
+                array.remap_funcidxs(f);
+                index.remap_funcidxs(f);
+                expr.remap_funcidxs(f);
+            }
+            ExprKind::Appl {
+                func,
+                args,
+                location: _,
+            } => {
+                func.remap_funcidxs(f);
+                for arg in args.iter_mut() {
+                    arg.remap_funcidxs(f);
+                }
+            }
+            ExprKind::DirectAppl { funcidx, args } => {
+                *funcidx = f(*funcidx);
+                for arg in args.iter_mut() {
+                    arg.remap_funcidxs(f);
+                }
+            }
+            ExprKind::Conditional {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                cond.remap_funcidxs(f);
+                true_expr.remap_funcidxs(f);
+                false_expr.remap_funcidxs(f);
+            }
+            ExprKind::Declaration {
+                local: _,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                if let Some(init) = init {
+                    init.remap_funcidxs(f);
+                }
+                contained_expr.remap_funcidxs(f);
+            }
+            ExprKind::Assign { target: _, expr }
+            | ExprKind::Return { expr }
+            | ExprKind::Break {
+                num_frames: _,
+                expr,
+            }
+            | ExprKind::Block { expr }
+            | ExprKind::Loop { expr } => expr.remap_funcidxs(f),
+            ExprKind::Sequence { content } => {
+                for expr in content {
+                    expr.remap_funcidxs(f);
+                }
+            }
+        }
+    }
+}
+
+impl Program {
+    /**
+     * Replaces every globalidx in the function bodies with `f(globalidx)`.
+     * This does not move the globals themselves.
+     */
+    pub fn remap_globalidxs<F: Fn(usize) -> usize>(&mut self, f: F) {
+        for func in &mut self.funcs {
+            func.expr.remap_globalidxs(&f);
+        }
+    }
+    /**
+     * Replaces every typeidx in the program (in struct fields, globals, function signatures, and function bodies) with `f(typeidx)`.
+     * This does not move the struct types themselves.
+     */
+    pub fn remap_typeidxs<F: Fn(usize) -> usize>(&mut self, f: F) {
+        for fields in &mut self.struct_types {
+            for vartype in fields.iter_mut() {
+                vartype.remap_typeidx(&f);
+            }
+        }
+        for vartype in &mut self.globals {
+            vartype.remap_typeidx(&f);
+        }
+        for func in &mut self.funcs {
+            func.remap_typeidxs(&f);
+        }
+    }
+}
+
+impl Func {
+    pub fn remap_typeidxs<F: Fn(usize) -> usize>(&mut self, f: &F) {
+        for vartype in self.params.iter_mut() {
+            vartype.remap_typeidx(f);
+        }
+        if let Some(vartype) = &mut self.result {
+            vartype.remap_typeidx(f);
+        }
+        for (param_types, result_type, _) in &mut self.signature_filter {
+            for vartype in param_types.iter_mut() {
+                vartype.remap_typeidx(f);
+            }
+            result_type.remap_typeidx(f);
+        }
+        self.expr.remap_typeidxs(f);
+    }
+}
+
+impl Expr {
+    pub fn remap_globalidxs<F: Fn(usize) -> usize>(&mut self, f: &F) {
+        match &mut self.kind {
+            ExprKind::VarName { source: target } | ExprKind::Assign { target, expr: _ } => {
+                target.remap_globalidx(f)
+            }
+            _ => {}
+        }
+        self.for_each_child_mut(|expr| expr.remap_globalidxs(f));
+    }
+    pub fn remap_typeidxs<F: Fn(usize) -> usize>(&mut self, f: &F) {
+        if let Some(vartype) = &mut self.vartype {
+            vartype.remap_typeidx(f);
+        }
+        match &mut self.kind {
+            ExprKind::PrimStructT { typeidx } => *typeidx = f(*typeidx),
+            ExprKind::TypeCast {
+                test: _,
+                expected,
+                create_narrow_local: _,
+                true_expr: _,
+                false_expr: _,
+            } => expected.remap_typeidx(f),
+            ExprKind::Declaration {
+                local,
+                name: _,
+                init: _,
+                contained_expr: _,
+            } => local.remap_typeidx(f),
+            ExprKind::VarName { source: target } | ExprKind::Assign { target, expr: _ } => {
+                target.remap_typeidxs(f)
+            }
+            _ => {}
+        }
+        self.for_each_child_mut(|expr| expr.remap_typeidxs(f));
+    }
+}
+
+impl TargetExpr {
+    pub fn remap_globalidx<F: Fn(usize) -> usize>(&mut self, f: &F) {
+        if let TargetExpr::Global { globalidx, next: _ } = self {
+            *globalidx = f(*globalidx);
+        }
+    }
+    pub fn remap_typeidxs<F: Fn(usize) -> usize>(&mut self, f: &F) {
+        let mut next: &mut Option<Box<StructField>> = match self {
+            TargetExpr::Global { globalidx: _, next } | TargetExpr::Local { localidx: _, next } => {
+                next
+            }
+        };
+        while let Some(field) = next {
+            field.typeidx = f(field.typeidx);
+            next = &mut field.next;
+        }
+    }
+}
+
+impl VarType {
+    pub fn remap_typeidx<F: Fn(usize) -> usize>(&mut self, f: &F) {
+        if let VarType::StructT { typeidx } = self {
+            *typeidx = f(*typeidx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shift_funcs() {
+        // make room for one more import before the functions
+        let mut program = text::parse(
+            r#"import 0 "misc" "display" (string) -> undefined
+entry 2
+
+func 1 (any) -> any
+  filter (number) -> number 2
+  (return:void
+    (direct_appl:any 0
+      (string:string "a")))
+
+func 2 (number) -> number
+  (return:void
+    (appl:any @0:1:0-1:1
+      (func:func (1 2+closure)
+        (undefined:undefined))))
+"#,
+        )
+        .unwrap();
+        program.remap_funcidxs(|funcidx| if funcidx < 1 { funcidx } else { funcidx + 1 });
+        assert_eq!(
+            text::print(&program),
+            r#"import 0 "misc" "display" (string) -> undefined
+entry 3
+
+func 1 (any) -> any
+  filter (number) -> number 3
+  (return:void
+    (direct_appl:any 0
+      (string:string "a")))
+
+func 2 (number) -> number
+  (return:void
+    (appl:any @0:1:0-1:1
+      (func:func (2 3+closure)
+        (undefined:undefined))))
+"#
+        );
+    }
+}
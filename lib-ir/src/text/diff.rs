@@ -0,0 +1,171 @@
+/**
+ * Line-based diffs of printed IR, in the style of `diff -u`.
+ */
+use std::cmp;
+use std::iter;
+
+// The number of unchanged lines printed around each change.
+const CONTEXT_LINES: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/**
+ * Returns the differences between the two texts as unified diff hunks (without the `---`/`+++` header lines),
+ * or an empty string if the texts have the same lines.
+ */
+pub fn diff(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits: Vec<Edit> = edit_script(&old_lines, &new_lines);
+
+    let mut out = String::new();
+    let mut i: usize = 0; // index into edits
+    let mut old_pos: usize = 0; // line number (zero-based) in old at edits[i]
+    let mut new_pos: usize = 0;
+    while i < edits.len() {
+        if edits[i] == Edit::Keep {
+            i += 1;
+            old_pos += 1;
+            new_pos += 1;
+            continue;
+        }
+        // start a hunk with some context before the first change
+        let context_before = cmp::min(CONTEXT_LINES, old_pos);
+        let hunk_start = i - context_before;
+        let hunk_old_start = old_pos - context_before;
+        let hunk_new_start = new_pos - context_before;
+        // extend the hunk until there are more than twice the context of unchanged lines
+        let mut hunk_end = i;
+        let mut keeps: usize = 0;
+        while hunk_end < edits.len() && keeps <= 2 * CONTEXT_LINES {
+            if edits[hunk_end] == Edit::Keep {
+                keeps += 1;
+            } else {
+                keeps = 0;
+            }
+            hunk_end += 1;
+        }
+        // drop the trailing unchanged lines beyond the context
+        hunk_end -= keeps.saturating_sub(CONTEXT_LINES);
+
+        let mut body = String::new();
+        let mut old_count: usize = 0;
+        let mut new_count: usize = 0;
+        let (mut o, mut n) = (hunk_old_start, hunk_new_start);
+        for edit in &edits[hunk_start..hunk_end] {
+            match edit {
+                Edit::Keep => {
+                    body.push(' ');
+                    body.push_str(old_lines[o]);
+                    o += 1;
+                    n += 1;
+                    old_count += 1;
+                    new_count += 1;
+                }
+                Edit::Delete => {
+                    body.push('-');
+                    body.push_str(old_lines[o]);
+                    o += 1;
+                    old_count += 1;
+                }
+                Edit::Insert => {
+                    body.push('+');
+                    body.push_str(new_lines[n]);
+                    n += 1;
+                    new_count += 1;
+                }
+            }
+            body.push('\n');
+        }
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(hunk_old_start, old_count),
+            hunk_range(hunk_new_start, new_count)
+        ));
+        out.push_str(&body);
+        i = hunk_end;
+        old_pos = o;
+        new_pos = n;
+    }
+    out
+}
+
+// Formats the range of a hunk like `diff -u` (one-based start line, and the length if it is not 1).
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+// Computes a shortest edit script from the longest common subsequence of lines.
+// The common prefix and suffix are trimmed first, since passes usually change only a small part of the IR.
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let prefix: usize = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix: usize = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j] is the length of the longest common subsequence of old_mid[i..] and new_mid[j..]
+    let mut lcs: Vec<Vec<usize>> = vec![vec![0; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ret: Vec<Edit> = vec![Edit::Keep; prefix];
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            ret.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if j == new_mid.len() || (i < old_mid.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ret.push(Edit::Delete);
+            i += 1;
+        } else {
+            ret.push(Edit::Insert);
+            j += 1;
+        }
+    }
+    ret.extend(iter::repeat_n(Edit::Keep, suffix));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hunks() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), "");
+        assert_eq!(
+            diff("1\n2\n3\n4\n5\n6\n", "1\n2\n3\nx\n5\n6\n"),
+            "@@ -2,5 +2,5 @@\n 2\n 3\n-4\n+x\n 5\n 6\n"
+        );
+        assert_eq!(diff("", "a\n"), "@@ -0,0 +1 @@\n+a\n");
+        // changes that are far apart are in separate hunks
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "x\n2\n3\n4\n5\n6\n7\n8\n";
+        assert_eq!(
+            diff(old, new),
+            "@@ -1,3 +1,3 @@\n-1\n+x\n 2\n 3\n@@ -7,3 +7,2 @@\n 7\n 8\n-9\n"
+        );
+    }
+}
@@ -0,0 +1,288 @@
+/**
+ * A compact textual syntax for the IR, with a printer and a parser that round-trips.
+ * It is meant for inspecting the IR (e.g. dumps from the compiler) and for writing IR test cases by hand.
+ *
+ * A program is a list of items:
+ * ```text
+ * struct 0 (any number)                             ; struct_types[0]
+ * import 0 "misc" "display" (string) -> undefined   ; imports[0]
+ * global 0 any const                                ; globals[0] (`const` if it is in const_globals)
+ * entry 1                                           ; entry_point
+ * func 1 "f" (any "x") -> undefined                 ; funcidx 1 (funcidxs of functions start after the imports)
+ *   filter (number) -> undefined 2                  ; signature_filter entry (param_types, return_type, constrained_func)
+ *   (seq:undefined ...)                             ; the body of the function
+ * ```
+ * The indices are only there for readability, but they are checked by the parser.
+ * Names of functions, params and declared locals (from the source code) are optional.
+ * Source locations of expressions (used for source maps) are not printed, and parsed expressions have none.
+ * Comments start with `;` and continue to the end of the line.
+ *
+ * Vartypes are written as `any`, `unassigned`, `undefined`, `number`, `boolean`, `string`, `func`, `null`, `array`, or `struct#<typeidx>`.
+ * `void` is used where the vartype is `None` (i.e. the expression or function never returns).
+ *
+ * Each expression is written as `(<kind>:<vartype> <fields...> <subexpressions...>)`:
+ * ```text
+ * (undefined:T)                                  PrimUndefined
+ * (null:T)                                       PrimNull
+ * (number:T 1.5)                                 PrimNumber (also NaN, inf, -inf)
+ * (boolean:T true)                               PrimBoolean
+ * (string:T "a\n")                               PrimString (with Rust-style escapes)
+ * (array:T)                                      PrimArray
+ * (struct:T <typeidx>)                           PrimStructT
+ * (func:T (<funcidx>[+closure]...) <closure>)    PrimFunc (`+closure` sets has_closure_param)
+ * (typecast:T <expected> narrow|nonarrow <test> <true_expr> <false_expr>)
+ * (var:T <target>)                               VarName
+ * (prim:T <prim_inst> <args...>)                 PrimAppl (e.g. `number_add`)
+ * (array_load:T <loc> <array> <index>)
+ * (array_store:T <loc> <array> <index> <expr>)
+ * (appl:T <loc> <func> <args...>)
+ * (direct_appl:T <funcidx> <args...>)
+ * (if:T <cond> <true_expr> <false_expr>)          Conditional
+ * (declare:T <local> ["name"] <init> <contained_expr>)  Declaration (`default` instead of <init> if there is no init)
+ * (assign:T <target> <expr>)
+ * (return:T <expr>)
+ * (break:T <num_frames> <expr>)
+ * (block:T <expr>)
+ * (loop:T <expr>)
+ * (seq:T <content...>)                           Sequence
+ * (trap:T <loc> <code>)
+ * ```
+ * Targets are written as `(local <localidx> <fields...>)` or `(global <globalidx> <fields...>)`,
+ * where each field is `<typeidx>.<fieldidx>` (i.e. a StructField).
+ * Source locations are written as `@<file>:<line>:<column>-<line>:<column>`.
+ */
+mod diff;
+mod parse;
+mod print;
+
+pub use diff::diff;
+pub use parse::parse;
+pub use parse::ParseError;
+pub use print::print;
+pub use print::print_expr;
+pub use print::print_func;
+pub use print::print_items;
+
+use super::*;
+
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", print(self))
+    }
+}
+
+const PRIM_INST_NAMES: [(PrimInst, &str); NUM_PRIM_INST as usize] = [
+    (PrimInst::NumberAdd, "number_add"),
+    (PrimInst::NumberSub, "number_sub"),
+    (PrimInst::NumberMul, "number_mul"),
+    (PrimInst::NumberDiv, "number_div"),
+    (PrimInst::NumberRem, "number_rem"),
+    (PrimInst::NumberEq, "number_eq"),
+    (PrimInst::NumberNeq, "number_neq"),
+    (PrimInst::NumberGt, "number_gt"),
+    (PrimInst::NumberLt, "number_lt"),
+    (PrimInst::NumberGe, "number_ge"),
+    (PrimInst::NumberLe, "number_le"),
+    (PrimInst::BooleanEq, "boolean_eq"),
+    (PrimInst::BooleanNeq, "boolean_neq"),
+    (PrimInst::BooleanAnd, "boolean_and"),
+    (PrimInst::BooleanOr, "boolean_or"),
+    (PrimInst::BooleanNot, "boolean_not"),
+    (PrimInst::NumberNegate, "number_negate"),
+    (PrimInst::StringAdd, "string_add"),
+    (PrimInst::StringEq, "string_eq"),
+    (PrimInst::StringNeq, "string_neq"),
+    (PrimInst::StringGt, "string_gt"),
+    (PrimInst::StringLt, "string_lt"),
+    (PrimInst::StringGe, "string_ge"),
+    (PrimInst::StringLe, "string_le"),
+    (PrimInst::ArrayLength, "array_length"),
+    (PrimInst::ReferenceEq, "reference_eq"),
+    (PrimInst::ReferenceNeq, "reference_neq"),
+];
+
+const IMPORT_VAL_TYPE_NAMES: [(ImportValType, &str); 3] = [
+    (ImportValType::Undefined, "undefined"),
+    (ImportValType::Number, "number"),
+    (ImportValType::String, "string"),
+];
+
+const STRUCT_PREFIX: &str = "struct#";
+const VOID: &str = "void";
+const NO_INIT: &str = "default";
+const NARROW: &str = "narrow";
+const NO_NARROW: &str = "nonarrow";
+const CLOSURE_SUFFIX: &str = "+closure";
+const CONST: &str = "const";
+
+pub(crate) fn vartype_name(vartype: VarType) -> String {
+    match vartype {
+        VarType::Any => "any".to_owned(),
+        VarType::Unassigned => "unassigned".to_owned(),
+        VarType::Undefined => "undefined".to_owned(),
+        VarType::Number => "number".to_owned(),
+        VarType::Boolean => "boolean".to_owned(),
+        VarType::String => "string".to_owned(),
+        VarType::Func => "func".to_owned(),
+        VarType::Null => "null".to_owned(),
+        VarType::Array => "array".to_owned(),
+        VarType::StructT { typeidx } => format!("{}{}", STRUCT_PREFIX, typeidx),
+    }
+}
+
+// Also used for the result of a function or the vartype of an expression, where `None` means noreturn.
+pub(crate) fn result_name(vartype: Option<VarType>) -> String {
+    vartype.map_or_else(|| VOID.to_owned(), vartype_name)
+}
+
+fn vartype_from_name(name: &str) -> Option<VarType> {
+    match name {
+        "any" => Some(VarType::Any),
+        "unassigned" => Some(VarType::Unassigned),
+        "undefined" => Some(VarType::Undefined),
+        "number" => Some(VarType::Number),
+        "boolean" => Some(VarType::Boolean),
+        "string" => Some(VarType::String),
+        "func" => Some(VarType::Func),
+        "null" => Some(VarType::Null),
+        "array" => Some(VarType::Array),
+        _ => name
+            .strip_prefix(STRUCT_PREFIX)
+            .and_then(|s| s.parse::<usize>().ok())
+            .map(|typeidx| VarType::StructT { typeidx: typeidx }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parses and prints the text, and checks that we get back exactly the same text.
+    fn round_trip(text: &str) {
+        let program = parse(text).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(print(&program), text);
+    }
+
+    #[test]
+    fn empty_program() {
+        round_trip("entry 0\n");
+        let program = parse("").unwrap();
+        assert_eq!(program.entry_point, 0);
+        assert!(program.funcs.is_empty());
+    }
+
+    #[test]
+    fn items() {
+        round_trip(
+            r#"struct 0 (any number struct#0)
+struct 1 ()
+import 0 "misc" "display" (string) -> undefined
+import 1 "misc" "get_time" () -> number
+global 0 any
+global 1 struct#1
+entry 2
+
+func 2 () -> undefined
+  (seq:undefined)
+
+func 3 "fact" (struct#0 any "n") -> any
+  filter (struct#0 number) -> number 4
+  (return:void
+    (var:any (local 1)))
+
+func 4 (struct#0 number) -> void
+  (trap:void @0:1:2-3:4 17)
+"#,
+        );
+    }
+
+    #[test]
+    fn exprs() {
+        round_trip(
+            r#"entry 0
+
+func 0 (func any) -> any
+  (seq:any
+    (undefined:undefined)
+    (null:null)
+    (number:number 1.5)
+    (number:number -0.0)
+    (number:number NaN)
+    (number:number -inf)
+    (number:number 1e300)
+    (boolean:boolean false)
+    (string:string "a \"quoted\" string\n\t\\ \u{7f}")
+    (array:array)
+    (struct:struct#0 0)
+    (func:func (0 1+closure)
+      (undefined:undefined))
+    (typecast:any number narrow
+      (var:any (local 1))
+      (prim:number number_add
+        (var:number (local 2))
+        (number:number 1.0))
+      (var:any (local 1)))
+    (typecast:boolean string nonarrow
+      (var:any (local 1))
+      (boolean:boolean true)
+      (boolean:boolean false))
+    (var:any (global 0 0.1 2.0))
+    (array_load:any @1:2:3-4:5
+      (array:array)
+      (number:number 0.0))
+    (array_store:undefined @0:0:0-0:0
+      (array:array)
+      (number:number 0.0)
+      (null:null))
+    (appl:any @0:7:0-7:10
+      (var:func (local 0))
+      (number:number 1.0)
+      (string:string ""))
+    (direct_appl:any 0
+      (var:func (local 0))
+      (var:any (local 1)))
+    (if:number
+      (boolean:boolean true)
+      (number:number 1.0)
+      (number:number 2.0))
+    (declare:undefined number "x" default
+      (assign:undefined (local 2)
+        (number:number 3.0)))
+    (declare:undefined struct#0
+      (struct:struct#0 0)
+      (assign:undefined (local 2 0.0)
+        (null:null)))
+    (block:undefined
+      (loop:undefined
+        (break:void 1
+          (undefined:undefined))))
+    (return:void
+      (var:any (local 1))))
+"#,
+        );
+    }
+
+    #[test]
+    fn comments_and_whitespace() {
+        let program = parse(
+            "; a comment\nentry 0 func 0 () -> number ; another comment\n (number:number\n 1)",
+        )
+        .unwrap();
+        assert_eq!(
+            print(&program),
+            "entry 0\n\nfunc 0 () -> number\n  (number:number 1.0)\n"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let err = parse("entry 0\nfunc 1 () -> number\n  (number:number 1.0)").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 6));
+        let err = parse("entry 0\nfunc 0 () -> number\n  (numbr:number 1)").unwrap_err();
+        assert_eq!((err.line, err.column), (3, 4));
+        let err = parse("entry 0\nfunc 0 () -> number\n  (number:number 1").unwrap_err();
+        assert_eq!(err.message, "unexpected end of input");
+        let err = parse("func 0 () -> numbr (number:number 1.0)").unwrap_err();
+        assert_eq!(err.message, "expected a vartype, found `numbr`");
+    }
+}
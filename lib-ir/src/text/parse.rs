@@ -0,0 +1,629 @@
+use super::*;
+
+/**
+ * An error from parsing the textual IR syntax.
+ * `line` and `column` are one-based, and refer to the start of the offending token.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/**
+ * Parses a program written in the textual IR syntax.
+ */
+pub fn parse(text: &str) -> Result<Program, ParseError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens: tokens,
+        pos: 0,
+    };
+    parser.program()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Symbol(String), // any other run of characters, e.g. `func`, `12`, `any`, `@0:1:2-3:4`
+    Str(String),    // string literal (unescaped)
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::Symbol(s) => write!(f, "`{}`", s),
+            Token::Str(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Pos {
+    line: usize,
+    column: usize,
+}
+
+fn error<T>(pos: Pos, message: String) -> Result<T, ParseError> {
+    Err(ParseError {
+        line: pos.line,
+        column: pos.column,
+        message: message,
+    })
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, Pos)>, ParseError> {
+    let mut ret: Vec<(Token, Pos)> = Vec::new();
+    let mut pos = Pos { line: 1, column: 1 };
+    let mut iter = text.chars().peekable();
+    // advances the iterator and the position
+    fn next<I: Iterator<Item = char>>(iter: &mut I, pos: &mut Pos) -> Option<char> {
+        let c = iter.next()?;
+        if c == '\n' {
+            pos.line += 1;
+            pos.column = 1;
+        } else {
+            pos.column += 1;
+        }
+        Some(c)
+    }
+    while let Some(&c) = iter.peek() {
+        let start = pos;
+        match c {
+            '(' => {
+                next(&mut iter, &mut pos);
+                ret.push((Token::LParen, start));
+            }
+            ')' => {
+                next(&mut iter, &mut pos);
+                ret.push((Token::RParen, start));
+            }
+            ';' => {
+                while iter.peek().is_some() && iter.peek() != Some(&'\n') {
+                    next(&mut iter, &mut pos);
+                }
+            }
+            '"' => {
+                next(&mut iter, &mut pos);
+                let mut s = String::new();
+                loop {
+                    let esc_pos = pos;
+                    match next(&mut iter, &mut pos) {
+                        None => return error(start, "unterminated string".to_owned()),
+                        Some('"') => break,
+                        Some('\\') => match next(&mut iter, &mut pos) {
+                            Some('n') => s.push('\n'),
+                            Some('r') => s.push('\r'),
+                            Some('t') => s.push('\t'),
+                            Some('0') => s.push('\0'),
+                            Some('\\') => s.push('\\'),
+                            Some('"') => s.push('"'),
+                            Some('\'') => s.push('\''),
+                            Some('u') => {
+                                let mut hex = String::new();
+                                if next(&mut iter, &mut pos) != Some('{') {
+                                    return error(esc_pos, "invalid unicode escape".to_owned());
+                                }
+                                loop {
+                                    match next(&mut iter, &mut pos) {
+                                        Some('}') => break,
+                                        Some(h) if h.is_ascii_hexdigit() => hex.push(h),
+                                        _ => {
+                                            return error(
+                                                esc_pos,
+                                                "invalid unicode escape".to_owned(),
+                                            )
+                                        }
+                                    }
+                                }
+                                match u32::from_str_radix(&hex, 16)
+                                    .ok()
+                                    .and_then(std::char::from_u32)
+                                {
+                                    Some(ch) => s.push(ch),
+                                    None => {
+                                        return error(esc_pos, "invalid unicode escape".to_owned())
+                                    }
+                                }
+                            }
+                            _ => return error(esc_pos, "invalid escape".to_owned()),
+                        },
+                        Some(ch) => s.push(ch),
+                    }
+                }
+                ret.push((Token::Str(s), start));
+            }
+            _ if c.is_whitespace() => {
+                next(&mut iter, &mut pos);
+            }
+            _ => {
+                let mut s = String::new();
+                while let Some(&c) = iter.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' || c == ';' {
+                        break;
+                    }
+                    s.push(c);
+                    next(&mut iter, &mut pos);
+                }
+                ret.push((Token::Symbol(s), start));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Pos)>,
+    pos: usize, // index of the next token
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+    // position of the next token (for error messages)
+    fn peek_pos(&self) -> Pos {
+        self.tokens
+            .get(self.pos)
+            .map_or_else(|| Pos { line: 0, column: 0 }, |(_, p)| *p)
+    }
+    fn next(&mut self) -> Result<(Token, Pos), ParseError> {
+        match self.tokens.get(self.pos) {
+            Some(tp) => {
+                self.pos += 1;
+                Ok(tp.clone())
+            }
+            None => {
+                let pos = self
+                    .tokens
+                    .last()
+                    .map_or(Pos { line: 1, column: 1 }, |(_, p)| *p);
+                error(pos, "unexpected end of input".to_owned())
+            }
+        }
+    }
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        let (token, pos) = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            error(pos, format!("expected {}, found {}", expected, token))
+        }
+    }
+    fn expect_symbol(&mut self, expected: &str) -> Result<(), ParseError> {
+        self.expect(Token::Symbol(expected.to_owned()))
+    }
+    fn symbol(&mut self, what: &str) -> Result<(String, Pos), ParseError> {
+        match self.next()? {
+            (Token::Symbol(s), pos) => Ok((s, pos)),
+            (token, pos) => error(pos, format!("expected {}, found {}", what, token)),
+        }
+    }
+    fn string(&mut self) -> Result<String, ParseError> {
+        match self.next()? {
+            (Token::Str(s), _) => Ok(s),
+            (token, pos) => error(pos, format!("expected a string, found {}", token)),
+        }
+    }
+    fn parsed<T: std::str::FromStr>(&mut self, what: &str) -> Result<T, ParseError> {
+        let (s, pos) = self.symbol(what)?;
+        s.parse::<T>()
+            .or_else(|_| error(pos, format!("expected {}, found `{}`", what, s)))
+    }
+    fn index(&mut self) -> Result<usize, ParseError> {
+        self.parsed("an index")
+    }
+    // checks that the index written in the text is the one we expect
+    fn expect_index(&mut self, expected: usize) -> Result<(), ParseError> {
+        let pos = self.peek_pos();
+        let idx = self.index()?;
+        if idx == expected {
+            Ok(())
+        } else {
+            error(pos, format!("expected index {}, found {}", expected, idx))
+        }
+    }
+    fn is_rparen(&self) -> bool {
+        self.peek() == Some(&Token::RParen)
+    }
+    fn vartype(&mut self) -> Result<VarType, ParseError> {
+        let (s, pos) = self.symbol("a vartype")?;
+        vartype_from_name(&s).map_or_else(
+            || error(pos, format!("expected a vartype, found `{}`", s)),
+            Ok,
+        )
+    }
+    fn result_vartype_from(s: &str, pos: Pos) -> Result<Option<VarType>, ParseError> {
+        if s == VOID {
+            Ok(None)
+        } else {
+            vartype_from_name(s).map_or_else(
+                || error(pos, format!("expected a vartype, found `{}`", s)),
+                |vt| Ok(Some(vt)),
+            )
+        }
+    }
+    fn result_vartype(&mut self) -> Result<Option<VarType>, ParseError> {
+        let (s, pos) = self.symbol("a vartype")?;
+        Self::result_vartype_from(&s, pos)
+    }
+    fn vartype_list(&mut self) -> Result<Box<[VarType]>, ParseError> {
+        self.expect(Token::LParen)?;
+        let mut ret = Vec::new();
+        while !self.is_rparen() {
+            ret.push(self.vartype()?);
+        }
+        self.expect(Token::RParen)?;
+        Ok(ret.into_boxed_slice())
+    }
+    // Like vartype_list(), but each vartype may be followed by a name.
+    fn param_list(&mut self) -> Result<Vec<(VarType, Option<String>)>, ParseError> {
+        self.expect(Token::LParen)?;
+        let mut ret = Vec::new();
+        while !self.is_rparen() {
+            let vartype = self.vartype()?;
+            ret.push((vartype, self.optional_string()));
+        }
+        self.expect(Token::RParen)?;
+        Ok(ret)
+    }
+    fn optional_string(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Str(s)) => {
+                let ret = s.clone();
+                self.pos += 1;
+                Some(ret)
+            }
+            _ => None,
+        }
+    }
+    fn import_val_type(&mut self) -> Result<ImportValType, ParseError> {
+        let (s, pos) = self.symbol("an import type")?;
+        IMPORT_VAL_TYPE_NAMES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map_or_else(
+                || error(pos, format!("expected an import type, found `{}`", s)),
+                |(ivt, _)| Ok(*ivt),
+            )
+    }
+    fn location(&mut self) -> Result<SourceLocation, ParseError> {
+        let (s, pos) = self.symbol("a source location")?;
+        // @file:line:column-line:column
+        let parsed: Option<SourceLocation> = (|| {
+            let s = s.strip_prefix('@')?;
+            let (file, rest) = s.split_at(s.find(':')?);
+            let (start, end) = rest[1..].split_at(rest[1..].find('-')?);
+            let position = |p: &str| -> Option<Position> {
+                let (line, column) = p.split_at(p.find(':')?);
+                Some(Position {
+                    line: line.parse().ok()?,
+                    column: column[1..].parse().ok()?,
+                })
+            };
+            Some(SourceLocation {
+                file: file.parse().ok()?,
+                start: position(start)?,
+                end: position(&end[1..])?,
+            })
+        })();
+        parsed.map_or_else(
+            || error(pos, format!("expected a source location, found `{}`", s)),
+            Ok,
+        )
+    }
+    fn target(&mut self) -> Result<TargetExpr, ParseError> {
+        self.expect(Token::LParen)?;
+        let (kind, kind_pos) = self.symbol("`local` or `global`")?;
+        let idx = self.index()?;
+        let mut fields: Vec<(usize, usize)> = Vec::new();
+        while !self.is_rparen() {
+            let (s, pos) = self.symbol("a struct field")?;
+            let field: Option<(usize, usize)> = s
+                .find('.')
+                .and_then(|dot| Some((s[..dot].parse().ok()?, s[(dot + 1)..].parse().ok()?)));
+            match field {
+                Some(f) => fields.push(f),
+                None => return error(pos, format!("expected a struct field, found `{}`", s)),
+            }
+        }
+        self.expect(Token::RParen)?;
+        let next: Option<Box<StructField>> =
+            fields
+                .into_iter()
+                .rev()
+                .fold(None, |next, (typeidx, fieldidx)| {
+                    Some(Box::new(StructField {
+                        typeidx: typeidx,
+                        fieldidx: fieldidx,
+                        next: next,
+                    }))
+                });
+        match kind.as_str() {
+            "local" => Ok(TargetExpr::Local {
+                localidx: idx,
+                next: next,
+            }),
+            "global" => Ok(TargetExpr::Global {
+                globalidx: idx,
+                next: next,
+            }),
+            _ => error(
+                kind_pos,
+                format!("expected `local` or `global`, found `{}`", kind),
+            ),
+        }
+    }
+    // parses expressions until the closing parenthesis (which is not consumed)
+    fn exprs_until_rparen(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut ret = Vec::new();
+        while !self.is_rparen() {
+            ret.push(self.expr()?);
+        }
+        Ok(ret)
+    }
+    fn boxed_expr(&mut self) -> Result<Box<Expr>, ParseError> {
+        self.expr().map(Box::new)
+    }
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.expect(Token::LParen)?;
+        let (head, head_pos) = self.symbol("an expression kind")?;
+        let colon = match head.find(':') {
+            Some(colon) => colon,
+            None => {
+                return error(
+                    head_pos,
+                    format!("expected `<kind>:<vartype>`, found `{}`", head),
+                )
+            }
+        };
+        let vartype = Self::result_vartype_from(&head[(colon + 1)..], head_pos)?;
+        let kind: ExprKind = match &head[..colon] {
+            "undefined" => ExprKind::PrimUndefined,
+            "null" => ExprKind::PrimNull,
+            "number" => ExprKind::PrimNumber {
+                val: self.parsed("a number")?,
+            },
+            "boolean" => ExprKind::PrimBoolean {
+                val: self.parsed("`true` or `false`")?,
+            },
+            "string" => ExprKind::PrimString {
+                val: self.string()?,
+            },
+            "array" => ExprKind::PrimArray,
+            "struct" => ExprKind::PrimStructT {
+                typeidx: self.index()?,
+            },
+            "func" => {
+                self.expect(Token::LParen)?;
+                let mut funcidxs = Vec::new();
+                while !self.is_rparen() {
+                    let (s, pos) = self.symbol("a funcidx")?;
+                    let (idx_str, has_closure_param) = match s.strip_suffix(CLOSURE_SUFFIX) {
+                        Some(idx_str) => (idx_str, true),
+                        None => (s.as_str(), false),
+                    };
+                    match idx_str.parse::<FuncIdx>() {
+                        Ok(funcidx) => funcidxs.push(OverloadEntry {
+                            funcidx: funcidx,
+                            has_closure_param: has_closure_param,
+                        }),
+                        Err(_) => return error(pos, format!("expected a funcidx, found `{}`", s)),
+                    }
+                }
+                self.expect(Token::RParen)?;
+                ExprKind::PrimFunc {
+                    funcidxs: funcidxs.into_boxed_slice(),
+                    closure: self.boxed_expr()?,
+                }
+            }
+            "typecast" => {
+                let expected = self.vartype()?;
+                let (s, pos) = self.symbol("`narrow` or `nonarrow`")?;
+                let create_narrow_local = match s.as_str() {
+                    NARROW => true,
+                    NO_NARROW => false,
+                    _ => {
+                        return error(
+                            pos,
+                            format!("expected `{}` or `{}`, found `{}`", NARROW, NO_NARROW, s),
+                        )
+                    }
+                };
+                ExprKind::TypeCast {
+                    test: self.boxed_expr()?,
+                    expected: expected,
+                    create_narrow_local: create_narrow_local,
+                    true_expr: self.boxed_expr()?,
+                    false_expr: self.boxed_expr()?,
+                }
+            }
+            "var" => ExprKind::VarName {
+                source: self.target()?,
+            },
+            "prim" => {
+                let (s, pos) = self.symbol("a primitive instruction")?;
+                let prim_inst = match PRIM_INST_NAMES.iter().find(|(_, name)| *name == s) {
+                    Some((prim_inst, _)) => *prim_inst,
+                    None => {
+                        return error(
+                            pos,
+                            format!("expected a primitive instruction, found `{}`", s),
+                        )
+                    }
+                };
+                ExprKind::PrimAppl {
+                    prim_inst: prim_inst,
+                    args: self.exprs_until_rparen()?.into_boxed_slice(),
+                }
+            }
+            "array_load" => ExprKind::ArrayLoad {
+                location: self.location()?,
+                array: self.boxed_expr()?,
+                index: self.boxed_expr()?,
+            },
+            "array_store" => ExprKind::ArrayStore {
+                location: self.location()?,
+                array: self.boxed_expr()?,
+                index: self.boxed_expr()?,
+                expr: self.boxed_expr()?,
+            },
+            "appl" => ExprKind::Appl {
+                location: self.location()?,
+                func: self.boxed_expr()?,
+                args: self.exprs_until_rparen()?.into_boxed_slice(),
+            },
+            "direct_appl" => ExprKind::DirectAppl {
+                funcidx: self.index()?,
+                args: self.exprs_until_rparen()?.into_boxed_slice(),
+            },
+            "if" => ExprKind::Conditional {
+                cond: self.boxed_expr()?,
+                true_expr: self.boxed_expr()?,
+                false_expr: self.boxed_expr()?,
+            },
+            "declare" => {
+                let local = self.vartype()?;
+                let name = self.optional_string();
+                let init = if self.peek() == Some(&Token::Symbol(NO_INIT.to_owned())) {
+                    self.pos += 1;
+                    None
+                } else {
+                    Some(self.boxed_expr()?)
+                };
+                ExprKind::Declaration {
+                    local: local,
+                    name: name,
+                    init: init,
+                    contained_expr: self.boxed_expr()?,
+                }
+            }
+            "assign" => ExprKind::Assign {
+                target: self.target()?,
+                expr: self.boxed_expr()?,
+            },
+            "return" => ExprKind::Return {
+                expr: self.boxed_expr()?,
+            },
+            "break" => ExprKind::Break {
+                num_frames: self.index()?,
+                expr: self.boxed_expr()?,
+            },
+            "block" => ExprKind::Block {
+                expr: self.boxed_expr()?,
+            },
+            "loop" => ExprKind::Loop {
+                expr: self.boxed_expr()?,
+            },
+            "seq" => ExprKind::Sequence {
+                content: self.exprs_until_rparen()?,
+            },
+            "trap" => ExprKind::Trap {
+                location: self.location()?,
+                code: self.parsed("an error code")?,
+            },
+            kind => return error(head_pos, format!("unknown expression kind `{}`", kind)),
+        };
+        self.expect(Token::RParen)?;
+        Ok(Expr {
+            vartype: vartype,
+            kind: kind,
+            location: None,
+        })
+    }
+    fn program(&mut self) -> Result<Program, ParseError> {
+        let mut program = Program::default();
+        let mut imports: Vec<Import> = Vec::new();
+        while self.peek().is_some() {
+            let (item, pos) = self.symbol("an item")?;
+            match item.as_str() {
+                "struct" => {
+                    self.expect_index(program.struct_types.len())?;
+                    let fields = self.vartype_list()?;
+                    program.struct_types.push(fields);
+                }
+                "import" => {
+                    if !program.funcs.is_empty() {
+                        return error(pos, "imports must come before functions".to_owned());
+                    }
+                    self.expect_index(imports.len())?;
+                    let module_name = self.string()?;
+                    let entity_name = self.string()?;
+                    self.expect(Token::LParen)?;
+                    let mut params = Vec::new();
+                    while !self.is_rparen() {
+                        params.push(self.import_val_type()?);
+                    }
+                    self.expect(Token::RParen)?;
+                    self.expect_symbol("->")?;
+                    imports.push(Import {
+                        module_name: module_name,
+                        entity_name: entity_name,
+                        params: params.into_boxed_slice(),
+                        result: self.import_val_type()?,
+                    });
+                }
+                "global" => {
+                    self.expect_index(program.globals.len())?;
+                    let vartype = self.vartype()?;
+                    let is_const = self.peek() == Some(&Token::Symbol(CONST.to_owned()));
+                    if is_const {
+                        self.pos += 1;
+                    }
+                    program.globals.push(vartype);
+                    program.const_globals.push(is_const);
+                }
+                "entry" => {
+                    program.entry_point = self.index()?;
+                }
+                "func" => {
+                    self.expect_index(imports.len() + program.funcs.len())?;
+                    let name = self.optional_string();
+                    let (params, mut param_names): (Vec<VarType>, Vec<Option<String>>) =
+                        self.param_list()?.into_iter().unzip();
+                    // the names are empty if none of the params have names
+                    if param_names.iter().all(|name| name.is_none()) {
+                        param_names.clear();
+                    }
+                    self.expect_symbol("->")?;
+                    let result = self.result_vartype()?;
+                    let mut signature_filter = Vec::new();
+                    while self.peek() == Some(&Token::Symbol("filter".to_owned())) {
+                        self.pos += 1;
+                        let filter_params = self.vartype_list()?;
+                        self.expect_symbol("->")?;
+                        let filter_result = self.vartype()?;
+                        signature_filter.push((filter_params, filter_result, self.index()?));
+                    }
+                    program.funcs.push(Func {
+                        name: name,
+                        params: params.into_boxed_slice(),
+                        param_names: param_names.into_boxed_slice(),
+                        result: result,
+                        expr: self.expr()?,
+                        signature_filter: signature_filter,
+                    });
+                }
+                _ => return error(pos, format!("expected an item, found `{}`", item)),
+            }
+        }
+        // like param names, const_globals is empty if none of the globals are const
+        if program.const_globals.iter().all(|is_const| !is_const) {
+            program.const_globals.clear();
+        }
+        program.imports = imports.into_boxed_slice();
+        Ok(program)
+    }
+}
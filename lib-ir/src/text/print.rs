@@ -0,0 +1,285 @@
+use super::*;
+
+/**
+ * Prints the program in the textual IR syntax.
+ */
+pub fn print(program: &Program) -> String {
+    let mut out = print_items(program);
+    for funcidx in program.imports.len()..program.imports.len() + program.funcs.len() {
+        out.push('\n');
+        out.push_str(&print_func(program, funcidx));
+    }
+    out
+}
+
+/**
+ * Prints the items of the program other than the functions (i.e. the struct types, imports, globals and entry point).
+ */
+pub fn print_items(program: &Program) -> String {
+    let mut out = String::new();
+    for (typeidx, fields) in program.struct_types.iter().enumerate() {
+        out.push_str(&format!("struct {} ", typeidx));
+        write_vartype_list(&mut out, fields);
+        out.push('\n');
+    }
+    for (i, import) in program.imports.iter().enumerate() {
+        out.push_str(&format!(
+            "import {} {:?} {:?} (",
+            i, import.module_name, import.entity_name
+        ));
+        for (j, param) in import.params.iter().enumerate() {
+            if j != 0 {
+                out.push(' ');
+            }
+            out.push_str(import_val_type_name(*param));
+        }
+        out.push_str(") -> ");
+        out.push_str(import_val_type_name(import.result));
+        out.push('\n');
+    }
+    for (globalidx, vartype) in program.globals.iter().enumerate() {
+        out.push_str(&format!("global {} {}", globalidx, vartype_name(*vartype)));
+        if program.const_globals.get(globalidx) == Some(&true) {
+            out.push(' ');
+            out.push_str(CONST);
+        }
+        out.push('\n');
+    }
+    out.push_str(&format!("entry {}\n", program.entry_point));
+    out
+}
+
+/**
+ * Prints the function with the given funcidx (which must not be an import).
+ */
+pub fn print_func(program: &Program, funcidx: FuncIdx) -> String {
+    let func: &Func = &program.funcs[funcidx - program.imports.len()];
+    let mut out = format!("func {} ", funcidx);
+    if let Some(name) = &func.name {
+        out.push_str(&format!("{:?} ", name));
+    }
+    write_param_list(&mut out, &func.params, &func.param_names);
+    out.push_str(" -> ");
+    out.push_str(&result_name(func.result));
+    out.push('\n');
+    for (params, result, funcidx) in &func.signature_filter {
+        out.push_str("  filter ");
+        write_vartype_list(&mut out, params);
+        out.push_str(&format!(" -> {} {}\n", vartype_name(*result), funcidx));
+    }
+    out.push_str("  ");
+    write_expr(&mut out, &func.expr, 2);
+    out.push('\n');
+    out
+}
+
+/**
+ * Prints a single expression in the textual IR syntax (useful for debugging).
+ */
+pub fn print_expr(expr: &Expr) -> String {
+    let mut out = String::new();
+    write_expr(&mut out, expr, 0);
+    out
+}
+
+fn import_val_type_name(ivt: ImportValType) -> &'static str {
+    IMPORT_VAL_TYPE_NAMES
+        .iter()
+        .find(|(x, _)| *x == ivt)
+        .unwrap()
+        .1
+}
+
+fn prim_inst_name(prim_inst: PrimInst) -> &'static str {
+    PRIM_INST_NAMES
+        .iter()
+        .find(|(x, _)| *x == prim_inst)
+        .unwrap()
+        .1
+}
+
+fn write_vartype_list(out: &mut String, vartypes: &[VarType]) {
+    out.push('(');
+    for (i, vartype) in vartypes.iter().enumerate() {
+        if i != 0 {
+            out.push(' ');
+        }
+        out.push_str(&vartype_name(*vartype));
+    }
+    out.push(')');
+}
+
+// Like write_vartype_list(), but each vartype is followed by the name of the param (if known).
+fn write_param_list(out: &mut String, vartypes: &[VarType], names: &[Option<String>]) {
+    out.push('(');
+    for (i, vartype) in vartypes.iter().enumerate() {
+        if i != 0 {
+            out.push(' ');
+        }
+        out.push_str(&vartype_name(*vartype));
+        if let Some(Some(name)) = names.get(i) {
+            out.push_str(&format!(" {:?}", name));
+        }
+    }
+    out.push(')');
+}
+
+fn location_str(location: &SourceLocation) -> String {
+    format!(
+        "@{}:{}:{}-{}:{}",
+        location.file,
+        location.start.line,
+        location.start.column,
+        location.end.line,
+        location.end.column
+    )
+}
+
+fn target_str(target: &TargetExpr) -> String {
+    let (mut ret, mut next) = match target {
+        TargetExpr::Global { globalidx, next } => (format!("(global {}", globalidx), next),
+        TargetExpr::Local { localidx, next } => (format!("(local {}", localidx), next),
+    };
+    while let Some(field) = next {
+        ret.push_str(&format!(" {}.{}", field.typeidx, field.fieldidx));
+        next = &field.next;
+    }
+    ret.push(')');
+    ret
+}
+
+// Writes the expression, where `indent` is the indentation of the line that the expression starts on.
+// Subexpressions are written on their own lines, with two more spaces of indentation.
+fn write_expr(out: &mut String, expr: &Expr, indent: usize) {
+    let (name, fields, children): (&str, Vec<String>, Vec<&Expr>) = match &expr.kind {
+        ExprKind::PrimUndefined => ("undefined", vec![], vec![]),
+        ExprKind::PrimNull => ("null", vec![], vec![]),
+        ExprKind::PrimNumber { val } => ("number", vec![format!("{:?}", val)], vec![]),
+        ExprKind::PrimBoolean { val } => ("boolean", vec![val.to_string()], vec![]),
+        ExprKind::PrimString { val } => ("string", vec![format!("{:?}", val)], vec![]),
+        ExprKind::PrimArray => ("array", vec![], vec![]),
+        ExprKind::PrimStructT { typeidx } => ("struct", vec![typeidx.to_string()], vec![]),
+        ExprKind::PrimFunc { funcidxs, closure } => (
+            "func",
+            vec![format!(
+                "({})",
+                funcidxs
+                    .iter()
+                    .map(|oe| if oe.has_closure_param {
+                        format!("{}{}", oe.funcidx, CLOSURE_SUFFIX)
+                    } else {
+                        oe.funcidx.to_string()
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )],
+            vec![closure],
+        ),
+        ExprKind::TypeCast {
+            test,
+            expected,
+            create_narrow_local,
+            true_expr,
+            false_expr,
+        } => (
+            "typecast",
+            vec![
+                vartype_name(*expected),
+                (if *create_narrow_local {
+                    NARROW
+                } else {
+                    NO_NARROW
+                })
+                .to_owned(),
+            ],
+            vec![test, true_expr, false_expr],
+        ),
+        ExprKind::VarName { source } => ("var", vec![target_str(source)], vec![]),
+        ExprKind::PrimAppl { prim_inst, args } => (
+            "prim",
+            vec![prim_inst_name(*prim_inst).to_owned()],
+            args.iter().collect(),
+        ),
+        ExprKind::ArrayLoad {
+            array,
+            index,
+            location,
+        } => (
+            "array_load",
+            vec![location_str(location)],
+            vec![array, index],
+        ),
+        ExprKind::ArrayStore {
+            array,
+            index,
+            expr,
+            location,
+        } => (
+            "array_store",
+            vec![location_str(location)],
+            vec![array, index, expr],
+        ),
+        ExprKind::Appl {
+            func,
+            args,
+            location,
+        } => (
+            "appl",
+            vec![location_str(location)],
+            std::iter::once(&**func).chain(args.iter()).collect(),
+        ),
+        ExprKind::DirectAppl { funcidx, args } => (
+            "direct_appl",
+            vec![funcidx.to_string()],
+            args.iter().collect(),
+        ),
+        ExprKind::Conditional {
+            cond,
+            true_expr,
+            false_expr,
+        } => ("if", vec![], vec![cond, true_expr, false_expr]),
+        ExprKind::Declaration {
+            local,
+            name,
+            init,
+            contained_expr,
+        } => {
+            let mut fields: Vec<String> = vec![vartype_name(*local)];
+            if let Some(name) = name {
+                fields.push(format!("{:?}", name));
+            }
+            match init {
+                Some(init) => ("declare", fields, vec![init, contained_expr]),
+                None => {
+                    fields.push(NO_INIT.to_owned());
+                    ("declare", fields, vec![contained_expr])
+                }
+            }
+        }
+        ExprKind::Assign { target, expr } => ("assign", vec![target_str(target)], vec![expr]),
+        ExprKind::Return { expr } => ("return", vec![], vec![expr]),
+        ExprKind::Break { num_frames, expr } => ("break", vec![num_frames.to_string()], vec![expr]),
+        ExprKind::Block { expr } => ("block", vec![], vec![expr]),
+        ExprKind::Loop { expr } => ("loop", vec![], vec![expr]),
+        ExprKind::Sequence { content } => ("seq", vec![], content.iter().collect()),
+        ExprKind::Trap { code, location } => (
+            "trap",
+            vec![location_str(location), code.to_string()],
+            vec![],
+        ),
+    };
+    out.push('(');
+    out.push_str(name);
+    out.push(':');
+    out.push_str(&result_name(expr.vartype));
+    for field in fields {
+        out.push(' ');
+        out.push_str(&field);
+    }
+    for child in children {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent + 2));
+        write_expr(out, child, indent + 2);
+    }
+    out.push(')');
+}
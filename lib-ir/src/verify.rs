@@ -0,0 +1,702 @@
+/**
+ * Checks the structural and type invariants of a program that are otherwise only documented in comments
+ * (e.g. that every Break lands on an enclosing Block or Loop, that an Assign only widens, that locals are in scope).
+ * It is run on the input and after each pass of the optimiser in debug builds,
+ * so that a pass that breaks the IR is caught immediately instead of much later as a wasm validation failure.
+ *
+ * Noreturn subexpressions (i.e. those with `None` vartype) are allowed anywhere,
+ * because the IR may legitimately contain them until the unreachable code is removed.
+ */
+use super::superset::Superset;
+use super::text::result_name;
+use super::*;
+use std::fmt;
+
+/**
+ * A violation of an invariant.
+ * `path` leads from the body of the function to the offending expression (e.g. `body/seq.content[2]/if.true_expr`),
+ * and is empty if the violation is in the function signature or in the program items themselves.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub funcidx: Option<FuncIdx>, // None if the violation is not inside a function
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.funcidx, self.path.is_empty()) {
+            (None, _) => write!(f, "program: {}", self.message),
+            (Some(funcidx), true) => write!(f, "func {}: {}", funcidx, self.message),
+            (Some(funcidx), false) => {
+                write!(f, "func {} at {}: {}", funcidx, self.path, self.message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/**
+ * Checks the whole program, and returns all the violations that were found.
+ */
+pub fn verify(program: &Program) -> Result<(), Vec<VerifyError>> {
+    let mut errors: Vec<VerifyError> = Vec::new();
+    verify_items(program, &mut errors);
+    for (i, func) in program.funcs.iter().enumerate() {
+        verify_func(program, program.imports.len() + i, func, &mut errors);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/**
+ * Returns the (param_types, result_type) of the given funcidx (which may be an import), or None if it is out of range.
+ */
+fn signature_of(program: &Program, funcidx: FuncIdx) -> Option<(Box<[VarType]>, Option<VarType>)> {
+    if funcidx < program.imports.len() {
+        let import = &program.imports[funcidx];
+        Some((
+            import.params.iter().map(|ivt| (*ivt).into()).collect(),
+            Some(import.result.into()),
+        ))
+    } else {
+        program
+            .funcs
+            .get(funcidx - program.imports.len())
+            .map(|func| (func.params.clone(), func.result))
+    }
+}
+
+/**
+ * Returns true if a value of type `inner` can be stored in a place of type `outer`.
+ * A noreturn `inner` fits anywhere, since it never produces a value.
+ */
+fn fits(outer: Option<VarType>, inner: Option<VarType>) -> bool {
+    match (outer, inner) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(o), Some(i)) => o.superset(&i),
+    }
+}
+
+fn verify_items(program: &Program, errors: &mut Vec<VerifyError>) {
+    let mut error = |message: String| {
+        errors.push(VerifyError {
+            funcidx: None,
+            path: String::new(),
+            message: message,
+        })
+    };
+    let num_structs = program.struct_types.len();
+    for (typeidx, fields) in program.struct_types.iter().enumerate() {
+        for vartype in fields.iter() {
+            if let VarType::StructT { typeidx: t } = vartype {
+                if *t >= num_structs {
+                    error(format!(
+                        "struct {} has a field of nonexistent struct type {}",
+                        typeidx, t
+                    ));
+                }
+            }
+        }
+    }
+    for (globalidx, vartype) in program.globals.iter().enumerate() {
+        if let VarType::StructT { typeidx } = vartype {
+            if *typeidx >= num_structs {
+                error(format!(
+                    "global {} has nonexistent struct type {}",
+                    globalidx, typeidx
+                ));
+            }
+        }
+    }
+    if !program.const_globals.is_empty() && program.const_globals.len() != program.globals.len() {
+        error(format!(
+            "there are {} const_globals entries for {} globals",
+            program.const_globals.len(),
+            program.globals.len()
+        ));
+    }
+    if program.entry_point < program.imports.len()
+        || program.entry_point >= program.imports.len() + program.funcs.len()
+    {
+        error(format!(
+            "entry point {} is not a function in this program",
+            program.entry_point
+        ));
+    } else if !program.get_func(program.entry_point).params.is_empty() {
+        error(format!(
+            "entry point {} must not have any params",
+            program.entry_point
+        ));
+    }
+}
+
+fn verify_func(program: &Program, funcidx: FuncIdx, func: &Func, errors: &mut Vec<VerifyError>) {
+    let mut verifier = Verifier {
+        program: program,
+        funcidx: funcidx,
+        result: func.result,
+        locals: Vec::new(),
+        landings: Vec::new(),
+        path: Vec::new(),
+        errors: errors,
+    };
+    for (i, param) in func.params.iter().enumerate() {
+        verifier.check_vartype(Some(*param), || format!("param {}", i));
+    }
+    verifier.check_vartype(func.result, || "result".to_owned());
+    if !func.param_names.is_empty() && func.param_names.len() != func.params.len() {
+        verifier.error(format!(
+            "function has {} params but {} param names",
+            func.params.len(),
+            func.param_names.len()
+        ));
+    }
+    for (params, result, constrained_func) in &func.signature_filter {
+        if !func.params.superset(params) {
+            verifier.error(format!(
+                "signature filter params {} are not a subtype of the function params {}",
+                vartype_list_name(params),
+                vartype_list_name(&func.params)
+            ));
+        }
+        if !fits(func.result, Some(*result)) {
+            verifier.error(format!(
+                "signature filter result {} is not a subtype of the function result {}",
+                text::vartype_name(*result),
+                result_name(func.result)
+            ));
+        }
+        match signature_of(program, *constrained_func) {
+            None => verifier.error(format!(
+                "signature filter refers to nonexistent function {}",
+                constrained_func
+            )),
+            Some((constrained_params, _)) => {
+                if constrained_params.len() != params.len() {
+                    verifier.error(format!(
+                        "signature filter has {} params but function {} has {}",
+                        params.len(),
+                        constrained_func,
+                        constrained_params.len()
+                    ));
+                }
+            }
+        }
+    }
+    verifier.locals.extend(func.params.iter().copied());
+    verifier.path.push(Segment::Body);
+    verifier.expr(&func.expr);
+    verifier.path.pop();
+    if !fits(func.result, func.expr.vartype) {
+        verifier.error(format!(
+            "body has type {} which does not fit the function result {}",
+            result_name(func.expr.vartype),
+            result_name(func.result)
+        ));
+    }
+}
+
+fn vartype_list_name(vartypes: &[VarType]) -> String {
+    let names: Vec<String> = vartypes.iter().map(|v| text::vartype_name(*v)).collect();
+    format!("({})", names.join(" "))
+}
+
+// One step of the path to the current expression.
+// The names of the expression kinds are the same as those in the textual IR syntax.
+#[derive(Copy, Clone)]
+enum Segment {
+    Body,
+    Field(&'static str, &'static str), // e.g. `if.true_expr`
+    Element(&'static str, &'static str, usize), // e.g. `appl.args[1]`
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Body => write!(f, "body"),
+            Segment::Field(kind, field) => write!(f, "{}.{}", kind, field),
+            Segment::Element(kind, field, i) => write!(f, "{}.{}[{}]", kind, field, i),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Landing {
+    Block(Option<VarType>), // the vartype of the Block
+    Loop,
+}
+
+struct Verifier<'a> {
+    program: &'a Program,
+    funcidx: FuncIdx,
+    result: Option<VarType>,
+    locals: Vec<VarType>,   // type of each local in scope (params first)
+    landings: Vec<Landing>, // enclosing Blocks and Loops (innermost last)
+    path: Vec<Segment>,
+    errors: &'a mut Vec<VerifyError>,
+}
+
+impl<'a> Verifier<'a> {
+    fn error(&mut self, message: String) {
+        let segments: Vec<String> = self.path.iter().map(|s| s.to_string()).collect();
+        self.errors.push(VerifyError {
+            funcidx: Some(self.funcidx),
+            path: segments.join("/"),
+            message: message,
+        });
+    }
+
+    fn check_vartype<F: FnOnce() -> String>(&mut self, vartype: Option<VarType>, what: F) {
+        if let Some(VarType::StructT { typeidx }) = vartype {
+            if typeidx >= self.program.struct_types.len() {
+                let message = format!("{} has nonexistent struct type {}", what(), typeidx);
+                self.error(message);
+            }
+        }
+    }
+
+    // Checks that a subexpression has a type that fits `expected`.
+    fn check_fits(&mut self, expected: VarType, actual: Option<VarType>, what: &str) {
+        if !fits(Some(expected), actual) {
+            self.error(format!(
+                "{} should have type {} but has type {}",
+                what,
+                text::vartype_name(expected),
+                result_name(actual)
+            ));
+        }
+    }
+
+    // Checks that the vartype of this expr is wide enough for a value of type `produced`.
+    fn check_result(&mut self, vartype: Option<VarType>, produced: Option<VarType>, what: &str) {
+        if !fits(vartype, produced) {
+            self.error(format!(
+                "has type {} which is narrower than the type {} of {}",
+                result_name(vartype),
+                result_name(produced),
+                what
+            ));
+        }
+    }
+
+    fn check_exact(&mut self, vartype: Option<VarType>, expected: Option<VarType>) {
+        if vartype != expected {
+            self.error(format!(
+                "should have type {} but has type {}",
+                result_name(expected),
+                result_name(vartype)
+            ));
+        }
+    }
+
+    fn child(&mut self, segment: Segment, expr: &Expr) {
+        self.path.push(segment);
+        self.expr(expr);
+        self.path.pop();
+    }
+
+    fn children(&mut self, kind: &'static str, field: &'static str, exprs: &[Expr]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            self.child(Segment::Element(kind, field, i), expr);
+        }
+    }
+
+    // Returns the vartype of the place referred to by the target, or None if the target is invalid.
+    fn target(&mut self, target: &TargetExpr) -> Option<VarType> {
+        let (mut vartype, mut next) = match target {
+            TargetExpr::Local { localidx, next } => match self.locals.get(*localidx) {
+                Some(vartype) => (*vartype, next),
+                None => {
+                    self.error(format!(
+                        "local {} is not in scope ({} locals are in scope)",
+                        localidx,
+                        self.locals.len()
+                    ));
+                    return None;
+                }
+            },
+            TargetExpr::Global { globalidx, next } => match self.program.globals.get(*globalidx) {
+                Some(vartype) => (*vartype, next),
+                None => {
+                    self.error(format!("global {} does not exist", globalidx));
+                    return None;
+                }
+            },
+        };
+        while let Some(struct_field) = next {
+            if vartype
+                != (VarType::StructT {
+                    typeidx: struct_field.typeidx,
+                })
+            {
+                self.error(format!(
+                    "field {}.{} is accessed on a value of type {}",
+                    struct_field.typeidx,
+                    struct_field.fieldidx,
+                    text::vartype_name(vartype)
+                ));
+                return None;
+            }
+            match self
+                .program
+                .struct_types
+                .get(struct_field.typeidx)
+                .and_then(|fields| fields.get(struct_field.fieldidx))
+            {
+                Some(field_vartype) => vartype = *field_vartype,
+                None => {
+                    self.error(format!(
+                        "field {}.{} does not exist",
+                        struct_field.typeidx, struct_field.fieldidx
+                    ));
+                    return None;
+                }
+            }
+            next = &struct_field.next;
+        }
+        Some(vartype)
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        let vartype = expr.vartype;
+        self.check_vartype(vartype, || "expression".to_owned());
+        match &expr.kind {
+            ExprKind::PrimUndefined => self.check_exact(vartype, Some(VarType::Undefined)),
+            ExprKind::PrimNull => self.check_exact(vartype, Some(VarType::Null)),
+            ExprKind::PrimNumber { val: _ } => self.check_exact(vartype, Some(VarType::Number)),
+            ExprKind::PrimBoolean { val: _ } => self.check_exact(vartype, Some(VarType::Boolean)),
+            ExprKind::PrimString { val: _ } => self.check_exact(vartype, Some(VarType::String)),
+            ExprKind::PrimArray => self.check_exact(vartype, Some(VarType::Array)),
+            ExprKind::PrimStructT { typeidx } => {
+                self.check_exact(vartype, Some(VarType::StructT { typeidx: *typeidx }))
+            }
+            ExprKind::PrimFunc { funcidxs, closure } => {
+                self.check_exact(vartype, Some(VarType::Func));
+                for entry in funcidxs.iter() {
+                    match signature_of(self.program, entry.funcidx) {
+                        None => self.error(format!(
+                            "overload refers to nonexistent function {}",
+                            entry.funcidx
+                        )),
+                        Some((params, _)) => {
+                            if entry.has_closure_param && params.is_empty() {
+                                self.error(format!(
+                                    "overload {} takes the closure but has no params",
+                                    entry.funcidx
+                                ));
+                            }
+                        }
+                    }
+                }
+                self.child(Segment::Field("func", "closure"), closure);
+            }
+            ExprKind::TypeCast {
+                test,
+                expected,
+                create_narrow_local,
+                true_expr,
+                false_expr,
+            } => {
+                self.check_vartype(Some(*expected), || "typecast".to_owned());
+                self.child(Segment::Field("typecast", "test"), test);
+                if *create_narrow_local {
+                    self.locals.push(*expected);
+                    self.child(Segment::Field("typecast", "true_expr"), true_expr);
+                    self.locals.pop();
+                } else {
+                    self.child(Segment::Field("typecast", "true_expr"), true_expr);
+                }
+                self.child(Segment::Field("typecast", "false_expr"), false_expr);
+                self.check_result(vartype, true_expr.vartype, "the true branch");
+                self.check_result(vartype, false_expr.vartype, "the false branch");
+            }
+            ExprKind::VarName { source } => {
+                // the vartype may be narrower than the target, if the compiler can prove it
+                if let Some(target_vartype) = self.target(source) {
+                    match vartype {
+                        None => self.error("variable access cannot be noreturn".to_owned()),
+                        Some(v) => {
+                            if !target_vartype.superset(&v) {
+                                self.error(format!(
+                                    "has type {} which does not fit in the variable of type {}",
+                                    text::vartype_name(v),
+                                    text::vartype_name(target_vartype)
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            ExprKind::PrimAppl { prim_inst, args } => {
+                let (params, result) = prim_inst.signature();
+                if params.len() != args.len() {
+                    self.error(format!(
+                        "primitive takes {} args but is given {}",
+                        params.len(),
+                        args.len()
+                    ));
+                }
+                self.children("prim", "args", args);
+                for (i, (param, arg)) in params.iter().zip(args.iter()).enumerate() {
+                    self.check_fits(*param, arg.vartype, &format!("arg {}", i));
+                }
+                self.check_result(vartype, result, "the primitive");
+            }
+            ExprKind::ArrayLoad {
+                array,
+                index,
+                location: _,
+            } => {
+                self.child(Segment::Field("array_load", "array"), array);
+                self.child(Segment::Field("array_load", "index"), index);
+                self.check_fits(VarType::Array, array.vartype, "array");
+                self.check_fits(VarType::Number, index.vartype, "index");
+                self.check_exact(vartype, Some(VarType::Any));
+            }
+            ExprKind::ArrayStore {
+                array,
+                index,
+                expr: expr2,
+                location: _,
+            } => {
+                self.child(Segment::Field("array_store", "array"), array);
+                self.child(Segment::Field("array_store", "index"), index);
+                self.child(Segment::Field("array_store", "expr"), expr2);
+                self.check_fits(VarType::Array, array.vartype, "array");
+                self.check_fits(VarType::Number, index.vartype, "index");
+                self.check_exact(vartype, Some(VarType::Undefined));
+            }
+            ExprKind::Appl {
+                func,
+                args,
+                location: _,
+            } => {
+                self.child(Segment::Field("appl", "func"), func);
+                self.children("appl", "args", args);
+                self.check_fits(VarType::Func, func.vartype, "callee");
+            }
+            ExprKind::DirectAppl { funcidx, args } => {
+                self.children("direct_appl", "args", args);
+                match signature_of(self.program, *funcidx) {
+                    None => self.error(format!("callee {} does not exist", funcidx)),
+                    Some((params, result)) => {
+                        if params.len() != args.len() {
+                            self.error(format!(
+                                "callee {} takes {} args but is given {}",
+                                funcidx,
+                                params.len(),
+                                args.len()
+                            ));
+                        }
+                        for (i, (param, arg)) in params.iter().zip(args.iter()).enumerate() {
+                            self.check_fits(*param, arg.vartype, &format!("arg {}", i));
+                        }
+                        self.check_result(vartype, result, "the callee result");
+                    }
+                }
+            }
+            ExprKind::Conditional {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                self.child(Segment::Field("if", "cond"), cond);
+                self.child(Segment::Field("if", "true_expr"), true_expr);
+                self.child(Segment::Field("if", "false_expr"), false_expr);
+                self.check_fits(VarType::Boolean, cond.vartype, "condition");
+                self.check_result(vartype, true_expr.vartype, "the true branch");
+                self.check_result(vartype, false_expr.vartype, "the false branch");
+            }
+            ExprKind::Declaration {
+                local,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                self.check_vartype(Some(*local), || "declared local".to_owned());
+                if let Some(init_expr) = init {
+                    self.child(Segment::Field("declare", "init"), init_expr);
+                    self.check_fits(*local, init_expr.vartype, "initializer");
+                }
+                self.locals.push(*local);
+                self.child(Segment::Field("declare", "contained_expr"), contained_expr);
+                self.locals.pop();
+                self.check_result(vartype, contained_expr.vartype, "the contained expr");
+            }
+            ExprKind::Assign {
+                target,
+                expr: expr2,
+            } => {
+                self.child(Segment::Field("assign", "expr"), expr2);
+                if let Some(target_vartype) = self.target(target) {
+                    // assignment may only widen
+                    self.check_fits(target_vartype, expr2.vartype, "assigned value");
+                }
+                self.check_exact(vartype, Some(VarType::Undefined));
+            }
+            ExprKind::Return { expr: expr2 } => {
+                self.child(Segment::Field("return", "expr"), expr2);
+                if !fits(self.result, expr2.vartype) {
+                    self.error(format!(
+                        "returns type {} which does not fit the function result {}",
+                        result_name(expr2.vartype),
+                        result_name(self.result)
+                    ));
+                }
+                self.check_exact(vartype, None);
+            }
+            ExprKind::Break {
+                num_frames,
+                expr: expr2,
+            } => {
+                self.child(Segment::Field("break", "expr"), expr2);
+                let num_landings = self.landings.len();
+                if *num_frames >= num_landings {
+                    self.error(format!(
+                        "breaks out of {} frames but only {} are enclosing",
+                        num_frames + 1,
+                        num_landings
+                    ));
+                } else {
+                    match self.landings[num_landings - 1 - num_frames] {
+                        Landing::Block(block_vartype) => {
+                            if !fits(block_vartype, expr2.vartype) {
+                                self.error(format!(
+                                    "breaks with type {} which does not fit the target block of type {}",
+                                    result_name(expr2.vartype),
+                                    result_name(block_vartype)
+                                ));
+                            }
+                        }
+                        Landing::Loop => {
+                            self.check_fits(VarType::Undefined, expr2.vartype, "break to a loop")
+                        }
+                    }
+                }
+                self.check_exact(vartype, None);
+            }
+            ExprKind::Block { expr: expr2 } => {
+                self.landings.push(Landing::Block(vartype));
+                self.child(Segment::Field("block", "expr"), expr2);
+                self.landings.pop();
+                self.check_result(vartype, expr2.vartype, "the block body");
+            }
+            ExprKind::Loop { expr: expr2 } => {
+                self.landings.push(Landing::Loop);
+                self.child(Segment::Field("loop", "expr"), expr2);
+                self.landings.pop();
+                self.check_result(vartype, expr2.vartype, "the loop body");
+            }
+            ExprKind::Sequence { content } => {
+                self.children("seq", "content", content);
+                let last_vartype = content
+                    .last()
+                    .map_or(Some(VarType::Undefined), |last| last.vartype);
+                self.check_result(vartype, last_vartype, "the last expr");
+            }
+            ExprKind::Trap {
+                code: _,
+                location: _,
+            } => self.check_exact(vartype, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(text: &str) -> Vec<String> {
+        match verify(&text::parse(text).unwrap()) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn valid_program() {
+        assert!(errors(
+            r#"
+struct 0 (number)
+entry 1
+func 0 (number) -> number
+  (var:number (local 0))
+func 1 () -> any
+  (declare:any struct#0 (struct:struct#0 0)
+    (seq:any
+      (assign:undefined (local 0 0.0) (number:number 1.0))
+      (block:any
+        (loop:undefined
+          (break:void 1 (direct_appl:number 0 (var:number (local 0 0.0))))))))
+"#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn program_items() {
+        assert_eq!(
+            errors("global 0 struct#1 entry 0 func 0 (any) -> any (undefined:undefined)"),
+            vec![
+                "program: global 0 has nonexistent struct type 1",
+                "program: entry point 0 must not have any params",
+            ]
+        );
+    }
+
+    #[test]
+    fn locals_and_landings() {
+        assert_eq!(
+            errors(
+                r#"
+entry 0
+func 0 () -> any
+  (seq:undefined
+    (declare:undefined number default (undefined:undefined))
+    (var:number (local 0))
+    (block:undefined (break:void 1 (undefined:undefined)))
+    (loop:undefined (break:void 0 (number:number 1.0))))
+"#
+            ),
+            vec![
+                "func 0 at body/seq.content[1]: local 0 is not in scope (0 locals are in scope)",
+                "func 0 at body/seq.content[2]/block.expr: breaks out of 2 frames but only 1 are enclosing",
+                "func 0 at body/seq.content[3]/loop.expr: break to a loop should have type undefined but has type number",
+            ]
+        );
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(
+            errors(
+                r#"
+entry 1
+func 0 (number) -> number
+  filter (any) -> number 0
+  (var:number (local 0))
+func 1 () -> any
+  (declare:undefined number default
+    (seq:undefined
+      (assign:undefined (local 0) (string:string "a"))
+      (direct_appl:number 0)
+      (prim:number number_add (number:number 1.0) (boolean:boolean true))
+      (undefined:undefined)))
+"#
+            ),
+            vec![
+                "func 0: signature filter params (any) are not a subtype of the function params (number)",
+                "func 1 at body/declare.contained_expr/seq.content[0]: assigned value should have type number but has type string",
+                "func 1 at body/declare.contained_expr/seq.content[1]: callee 0 takes 1 args but is given 0",
+                "func 1 at body/declare.contained_expr/seq.content[2]: arg 1 should have type number but has type boolean",
+            ]
+        );
+    }
+}
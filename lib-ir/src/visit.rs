@@ -0,0 +1,228 @@
+/**
+ * Generic traversal of expressions, for analyses that only care about some kinds of expressions
+ * and want to look into all the others without matching every kind.
+ */
+use super::*;
+
+impl Expr {
+    /**
+     * Calls `f` on each direct subexpression of this expression, in evaluation order.
+     */
+    pub fn for_each_child<'a, F: FnMut(&'a Expr)>(&'a self, mut f: F) {
+        match &self.kind {
+            ExprKind::PrimUndefined
+            | ExprKind::PrimNull
+            | ExprKind::PrimNumber { val: _ }
+            | ExprKind::PrimBoolean { val: _ }
+            | ExprKind::PrimString { val: _ }
+            | ExprKind::PrimArray
+            | ExprKind::PrimStructT { typeidx: _ }
+            | ExprKind::VarName { source: _ }
+            | ExprKind::Trap {
+                code: _,
+                location: _,
+            } => {}
+            ExprKind::PrimFunc {
+                funcidxs: _,
+                closure,
+            } => f(closure),
+            ExprKind::TypeCast {
+                test,
+                expected: _,
+                create_narrow_local: _,
+                true_expr,
+                false_expr,
+            } => {
+                f(test);
+                f(true_expr);
+                f(false_expr);
+            }
+            ExprKind::PrimAppl { prim_inst: _, args }
+            | ExprKind::DirectAppl { funcidx: _, args } => args.iter().for_each(f),
+            ExprKind::ArrayLoad {
+                array,
+                index,
+                location: _,
+            } => {
+                f(array);
+                f(index);
+            }
+            ExprKind::ArrayStore {
+                array,
+                index,
+                expr,
+                location: _,
+            } => {
+                f(array);
+                f(index);
+                f(expr);
+            }
+            ExprKind::Appl {
+                func,
+                args,
+                location: _,
+            } => {
+                f(func);
+                args.iter().for_each(f);
+            }
+            ExprKind::Conditional {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                f(cond);
+                f(true_expr);
+                f(false_expr);
+            }
+            ExprKind::Declaration {
+                local: _,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                if let Some(init) = init {
+                    f(init);
+                }
+                f(contained_expr);
+            }
+            ExprKind::Assign { target: _, expr }
+            | ExprKind::Return { expr }
+            | ExprKind::Break {
+                num_frames: _,
+                expr,
+            }
+            | ExprKind::Block { expr }
+            | ExprKind::Loop { expr } => f(expr),
+            ExprKind::Sequence { content } => content.iter().for_each(f),
+        }
+    }
+
+    /**
+     * Like `for_each_child()`, but gives mutable references to the subexpressions.
+     */
+    pub fn for_each_child_mut<F: FnMut(&mut Expr)>(&mut self, mut f: F) {
+        match &mut self.kind {
+            ExprKind::PrimUndefined
+            | ExprKind::PrimNull
+            | ExprKind::PrimNumber { val: _ }
+            | ExprKind::PrimBoolean { val: _ }
+            | ExprKind::PrimString { val: _ }
+            | ExprKind::PrimArray
+            | ExprKind::PrimStructT { typeidx: _ }
+            | ExprKind::VarName { source: _ }
+            | ExprKind::Trap {
+                code: _,
+                location: _,
+            } => {}
+            ExprKind::PrimFunc {
+                funcidxs: _,
+                closure,
+            } => f(closure),
+            ExprKind::TypeCast {
+                test,
+                expected: _,
+                create_narrow_local: _,
+                true_expr,
+                false_expr,
+            } => {
+                f(test);
+                f(true_expr);
+                f(false_expr);
+            }
+            ExprKind::PrimAppl { prim_inst: _, args }
+            | ExprKind::DirectAppl { funcidx: _, args } => args.iter_mut().for_each(f),
+            ExprKind::ArrayLoad {
+                array,
+                index,
+                location: _,
+            } => {
+                f(array);
+                f(index);
+            }
+            ExprKind::ArrayStore {
+                array,
+                index,
+                expr,
+                location: _,
+            } => {
+                f(array);
+                f(index);
+                f(expr);
+            }
+            ExprKind::Appl {
+                func,
+                args,
+                location: _,
+            } => {
+                f(func);
+                args.iter_mut().for_each(f);
+            }
+            ExprKind::Conditional {
+                cond,
+                true_expr,
+                false_expr,
+            } => {
+                f(cond);
+                f(true_expr);
+                f(false_expr);
+            }
+            ExprKind::Declaration {
+                local: _,
+                name: _,
+                init,
+                contained_expr,
+            } => {
+                if let Some(init) = init {
+                    f(init);
+                }
+                f(contained_expr);
+            }
+            ExprKind::Assign { target: _, expr }
+            | ExprKind::Return { expr }
+            | ExprKind::Break {
+                num_frames: _,
+                expr,
+            }
+            | ExprKind::Block { expr }
+            | ExprKind::Loop { expr } => f(expr),
+            ExprKind::Sequence { content } => content.iter_mut().for_each(f),
+        }
+    }
+
+    /**
+     * Returns the number of expressions in this expression (including itself).
+     */
+    pub fn num_exprs(&self) -> usize {
+        let mut ret: usize = 1;
+        self.for_each_child(|child| ret += child.num_exprs());
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn num_exprs() {
+        let expr: Expr = text::parse(
+            r#"entry 0
+
+func 0 (number) -> any
+  (declare:any any default
+    (seq:any
+      (assign:undefined (local 1)
+        (prim:number number_add
+          (var:number (local 0))
+          (number:number 1)))
+      (return:void
+        (var:any (local 1)))))
+"#,
+        )
+        .unwrap()
+        .funcs
+        .remove(0)
+        .expr;
+        assert_eq!(expr.num_exprs(), 8);
+    }
+}
@@ -36,6 +36,16 @@ impl<T: Eq + Hash + Clone> SearchableVec<T> {
             }
         }
     }
+    /**
+     * Appends the given element even if an equal element already exists, and returns its new index.
+     * Searching for the element will still find the existing element.
+     */
+    pub fn push(&mut self, value: T) -> usize {
+        let curr_len = self.vec.len();
+        self.index.entry(value.clone()).or_insert(curr_len);
+        self.vec.push(value);
+        curr_len
+    }
     pub fn insert_copy(&mut self, value: &T) -> usize {
         self.insert(value.clone())
     }
@@ -0,0 +1,534 @@
+/**
+ * Instantiation and execution of modules.
+ */
+use super::instr::Instr;
+use super::module::ConstExpr;
+use super::module::ExportDesc;
+use super::module::Module;
+use super::numeric::exec_numeric;
+use super::numeric::pop;
+use super::numeric::pop_i32;
+use super::Error;
+use super::Host;
+use super::Options;
+use super::Trap;
+use super::Val;
+use super::WASM_MAX_PAGES;
+use super::WASM_PAGE_SIZE;
+
+/**
+ * An instantiated module, with its own memory, globals and table.
+ * The state persists between invocations, like a WebAssembly instance in a browser.
+ */
+pub struct Instance<H: Host> {
+    module: Module,
+    host: H,
+    options: Options,
+    memory: Vec<u8>,
+    memory_max_pages: u32,
+    globals: Vec<Val>,
+    table: Vec<Option<u32>>, // funcidxs (None if the element is uninitialized)
+}
+
+// A function activation.
+struct Frame<'a> {
+    code: &'a [Instr],
+    pc: usize,           // position of the next instruction in `code`
+    locals_start: usize, // position of the first local in the locals stack
+    labels_start: usize, // position of the first label in the label stack
+    height: usize, // height of the operand stack when the function was entered (after popping the args)
+    arity: usize,  // number of results
+}
+
+// A structured control instruction that has been entered and not yet exited.
+#[derive(Copy, Clone)]
+struct Label {
+    cont: usize,   // where to continue when branching to this label
+    arity: usize,  // number of values carried by a branch to this label
+    height: usize, // height of the operand stack when the label was entered
+    is_loop: bool, // branching to a loop stays inside it, so the label is not popped
+}
+
+// The state of a single invocation.
+struct Machine<'a, H: Host> {
+    module: &'a Module,
+    host: &'a mut H,
+    memory: &'a mut Vec<u8>,
+    memory_max_pages: u32,
+    globals: &'a mut Vec<Val>,
+    table: &'a [Option<u32>],
+    max_call_depth: usize,
+    stack: Vec<Val>,
+    locals: Vec<Val>,
+    labels: Vec<Label>,
+    frames: Vec<Frame<'a>>,
+}
+
+impl<H: Host> Instance<H> {
+    /**
+     * Instantiates the module: initializes the memory, globals and table, and runs the start function (if any).
+     */
+    pub fn new(module: Module, host: H, options: Options) -> Result<Self, Error> {
+        let (memory_pages, memory_max_pages): (u32, u32) = match module.memory {
+            Some(limits) => (limits.min, limits.max.unwrap_or(WASM_MAX_PAGES)),
+            None => (0, 0),
+        };
+        let mut globals: Vec<Val> = Vec::new();
+        for global in &module.globals {
+            let val: Val = eval_const_expr(global.init, &globals)?;
+            if val.valtype() != global.valtype {
+                return Err(Error::Link(
+                    "global initializer has the wrong type".to_string(),
+                ));
+            }
+            globals.push(val);
+        }
+        let mut table: Vec<Option<u32>> = match module.table {
+            Some(limits) => vec![None; limits.min as usize],
+            None => Vec::new(),
+        };
+        for elem in &module.elems {
+            let offset: usize = const_expr_offset(elem.offset, &globals)?;
+            if offset + elem.funcidxs.len() > table.len() {
+                return Err(Error::Link("element segment does not fit".to_string()));
+            }
+            for (i, funcidx) in elem.funcidxs.iter().enumerate() {
+                table[offset + i] = Some(*funcidx);
+            }
+        }
+        let mut memory: Vec<u8> = vec![0; memory_pages as usize * WASM_PAGE_SIZE];
+        for data in &module.datas {
+            let offset: usize = const_expr_offset(data.offset, &globals)?;
+            if offset + data.content.len() > memory.len() {
+                return Err(Error::Link("data segment does not fit".to_string()));
+            }
+            memory[offset..offset + data.content.len()].copy_from_slice(&data.content);
+        }
+        let start: Option<u32> = module.start;
+        let mut instance = Instance {
+            module: module,
+            host: host,
+            options: options,
+            memory: memory,
+            memory_max_pages: memory_max_pages,
+            globals: globals,
+            table: table,
+        };
+        if let Some(funcidx) = start {
+            instance.call(funcidx, &[])?;
+        }
+        Ok(instance)
+    }
+
+    /**
+     * Calls the exported function with the given name, and returns its results.
+     */
+    pub fn invoke(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, Error> {
+        match self.export(name) {
+            Some(ExportDesc::Func(funcidx)) => self.call(funcidx, args),
+            _ => Err(Error::Link(format!("no exported function named {}", name))),
+        }
+    }
+
+    /**
+     * Returns the value of the exported global with the given name.
+     */
+    pub fn global(&self, name: &str) -> Option<Val> {
+        match self.export(name) {
+            Some(ExportDesc::Global(globalidx)) => Some(self.globals[globalidx as usize]),
+            _ => None,
+        }
+    }
+
+    /**
+     * The linear memory (empty if the module has no memory).
+     */
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    pub fn host(&self) -> &H {
+        &self.host
+    }
+
+    pub fn host_mut(&mut self) -> &mut H {
+        &mut self.host
+    }
+
+    fn export(&self, name: &str) -> Option<ExportDesc> {
+        self.module
+            .exports
+            .iter()
+            .find(|export| export.name == name)
+            .map(|export| export.desc)
+    }
+
+    fn call(&mut self, funcidx: u32, args: &[Val]) -> Result<Vec<Val>, Error> {
+        let functype = self.module.func_type(funcidx);
+        if args.len() != functype.params.len()
+            || args
+                .iter()
+                .zip(functype.params.iter())
+                .any(|(arg, param)| arg.valtype() != *param)
+        {
+            return Err(Error::Link(format!(
+                "wrong arguments for function {}",
+                funcidx
+            )));
+        }
+        let mut machine = Machine {
+            module: &self.module,
+            host: &mut self.host,
+            memory: &mut self.memory,
+            memory_max_pages: self.memory_max_pages,
+            globals: &mut self.globals,
+            table: &self.table,
+            max_call_depth: self.options.max_call_depth,
+            stack: args.to_vec(),
+            locals: Vec::new(),
+            labels: Vec::new(),
+            frames: Vec::new(),
+        };
+        machine.call(funcidx)?;
+        machine.run()?;
+        Ok(machine.stack)
+    }
+}
+
+fn eval_const_expr(expr: ConstExpr, globals: &[Val]) -> Result<Val, Error> {
+    match expr {
+        ConstExpr::Const(val) => Ok(val),
+        ConstExpr::GlobalGet(globalidx) => globals
+            .get(globalidx as usize)
+            .copied()
+            .ok_or_else(|| Error::Link("initializer refers to a later global".to_string())),
+    }
+}
+
+fn const_expr_offset(expr: ConstExpr, globals: &[Val]) -> Result<usize, Error> {
+    match eval_const_expr(expr, globals)? {
+        Val::I32(offset) => Ok(offset as u32 as usize),
+        _ => Err(Error::Link("segment offset is not an i32".to_string())),
+    }
+}
+
+// Returns the range of memory accessed by a load or store, or traps if it is out of bounds.
+fn mem_range(
+    addr: i32,
+    offset: u32,
+    size: usize,
+    memory: &[u8],
+) -> Result<std::ops::Range<usize>, Trap> {
+    let begin: u64 = addr as u32 as u64 + offset as u64;
+    if begin + size as u64 > memory.len() as u64 {
+        return Err(Trap::MemoryOutOfBounds);
+    }
+    Ok(begin as usize..begin as usize + size)
+}
+
+// Size in bytes of the memory accessed by a load or store instruction.
+fn access_size(opcode: u8) -> usize {
+    match opcode {
+        0x28 | 0x2A | 0x34 | 0x35 | 0x36 | 0x38 | 0x3E => 4,
+        0x29 | 0x2B | 0x37 | 0x39 => 8,
+        0x2C | 0x2D | 0x30 | 0x31 | 0x3A | 0x3C => 1,
+        0x2E | 0x2F | 0x32 | 0x33 | 0x3B | 0x3D => 2,
+        _ => panic!(
+            "ICE: wasm interpreter: {:#04x} is not a memory access",
+            opcode
+        ),
+    }
+}
+
+fn load(opcode: u8, bytes: &[u8]) -> Val {
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    let raw: u64 = u64::from_le_bytes(buf);
+    match opcode {
+        0x28 => Val::I32(raw as i32),
+        0x29 => Val::I64(raw as i64),
+        0x2A => Val::F32(f32::from_bits(raw as u32)),
+        0x2B => Val::F64(f64::from_bits(raw)),
+        0x2C => Val::I32(raw as i8 as i32),
+        0x2D => Val::I32(raw as u8 as i32),
+        0x2E => Val::I32(raw as i16 as i32),
+        0x2F => Val::I32(raw as u16 as i32),
+        0x30 => Val::I64(raw as i8 as i64),
+        0x31 => Val::I64(raw as u8 as i64),
+        0x32 => Val::I64(raw as i16 as i64),
+        0x33 => Val::I64(raw as u16 as i64),
+        0x34 => Val::I64(raw as i32 as i64),
+        0x35 => Val::I64(raw as u32 as i64),
+        _ => panic!("ICE: wasm interpreter: {:#04x} is not a load", opcode),
+    }
+}
+
+// Returns the bits of the value to store (the instruction decides how many of the low bytes are written).
+fn store_bits(opcode: u8, val: Val) -> u64 {
+    match (opcode, val) {
+        (0x36, Val::I32(x)) | (0x3A, Val::I32(x)) | (0x3B, Val::I32(x)) => x as u32 as u64,
+        (0x37, Val::I64(x)) | (0x3C, Val::I64(x)) | (0x3D, Val::I64(x)) | (0x3E, Val::I64(x)) => {
+            x as u64
+        }
+        (0x38, Val::F32(x)) => x.to_bits() as u64,
+        (0x39, Val::F64(x)) => x.to_bits(),
+        _ => panic!(
+            "wasm interpreter: store {:#04x} of {:?} (the module is invalid)",
+            opcode, val
+        ),
+    }
+}
+
+impl<'a, H: Host> Machine<'a, H> {
+    // Calls a function, taking its args from the operand stack.
+    // Imported functions are run immediately, and other functions get a new frame that will be run by `run()`.
+    fn call(&mut self, funcidx: u32) -> Result<(), Error> {
+        let module: &'a Module = self.module;
+        let functype = module.func_type(funcidx);
+        let args_start: usize = self.stack.len() - functype.params.len();
+        let num_imports: usize = module.imports.len();
+        if (funcidx as usize) < num_imports {
+            let import = &module.imports[funcidx as usize];
+            let results: Vec<Val> = self.host.call_import(
+                &import.module_name,
+                &import.entity_name,
+                &self.stack[args_start..],
+                &mut self.memory[..],
+            )?;
+            if results.len() != functype.results.len()
+                || results
+                    .iter()
+                    .zip(functype.results.iter())
+                    .any(|(result, valtype)| result.valtype() != *valtype)
+            {
+                return Err(Trap::Host(format!(
+                    "import {}.{} returned values of the wrong types",
+                    import.module_name, import.entity_name
+                ))
+                .into());
+            }
+            self.stack.truncate(args_start);
+            self.stack.extend(results);
+            return Ok(());
+        }
+        if self.frames.len() >= self.max_call_depth {
+            return Err(Trap::CallStackExhausted.into());
+        }
+        let func = &module.funcs[funcidx as usize - num_imports];
+        let locals_start: usize = self.locals.len();
+        self.locals.extend(self.stack.drain(args_start..));
+        self.locals
+            .extend(func.locals.iter().map(|valtype| Val::zero(*valtype)));
+        self.frames.push(Frame {
+            code: &func.code,
+            pc: 0,
+            locals_start: locals_start,
+            labels_start: self.labels.len(),
+            height: self.stack.len(),
+            arity: functype.results.len(),
+        });
+        Ok(())
+    }
+
+    // Looks up the function in the table for call_indirect.
+    fn table_funcidx(&mut self, typeidx: u32) -> Result<u32, Trap> {
+        let elemidx = pop_i32(&mut self.stack) as u32 as usize;
+        let funcidx: u32 = match self.table.get(elemidx) {
+            None => return Err(Trap::UndefinedElement),
+            Some(None) => return Err(Trap::UninitializedElement),
+            Some(Some(funcidx)) => *funcidx,
+        };
+        if *self.module.func_type(funcidx) != self.module.types[typeidx as usize] {
+            return Err(Trap::IndirectCallTypeMismatch);
+        }
+        Ok(funcidx)
+    }
+
+    // Returns from the current function, leaving its results on the operand stack.
+    fn ret(&mut self) {
+        let frame: Frame = self.frames.pop().unwrap();
+        let results_start: usize = self.stack.len() - frame.arity;
+        self.stack.drain(frame.height..results_start);
+        self.locals.truncate(frame.locals_start);
+        self.labels.truncate(frame.labels_start);
+    }
+
+    // Tail-calls a function, i.e. replaces the current function with the callee.
+    fn tail_call(&mut self, funcidx: u32) -> Result<(), Error> {
+        let frame: Frame = self.frames.pop().unwrap();
+        let args_start: usize = self.stack.len() - self.module.func_type(funcidx).params.len();
+        self.stack.drain(frame.height..args_start);
+        self.locals.truncate(frame.locals_start);
+        self.labels.truncate(frame.labels_start);
+        self.call(funcidx)
+    }
+
+    // Branches to the label with the given depth (which may be the function itself, in which case it returns).
+    fn branch(&mut self, depth: u32) {
+        let frame: &mut Frame = self.frames.last_mut().unwrap();
+        let depth = depth as usize;
+        if depth == self.labels.len() - frame.labels_start {
+            self.ret();
+            return;
+        }
+        let idx: usize = self.labels.len() - 1 - depth;
+        let label: Label = self.labels[idx];
+        let values_start: usize = self.stack.len() - label.arity;
+        self.stack.drain(label.height..values_start);
+        self.labels
+            .truncate(if label.is_loop { idx + 1 } else { idx });
+        frame.pc = label.cont;
+    }
+
+    fn push_label(&mut self, cont: usize, arity: u32, is_loop: bool) {
+        self.labels.push(Label {
+            cont: cont,
+            arity: arity as usize,
+            height: self.stack.len(),
+            is_loop: is_loop,
+        });
+    }
+
+    fn local(&mut self, localidx: u32) -> &mut Val {
+        let locals_start: usize = self.frames.last().unwrap().locals_start;
+        &mut self.locals[locals_start + localidx as usize]
+    }
+
+    // Runs until all the frames have returned.
+    fn run(&mut self) -> Result<(), Error> {
+        while let Some(frame) = self.frames.last_mut() {
+            let code: &'a [Instr] = frame.code;
+            let pc: usize = frame.pc;
+            frame.pc += 1;
+            match &code[pc] {
+                Instr::Unreachable => return Err(Trap::Unreachable.into()),
+                Instr::Nop => {}
+                Instr::Block { arity, end } => self.push_label(*end as usize + 1, *arity, false),
+                Instr::Loop => self.push_label(pc + 1, 0, true),
+                Instr::If {
+                    arity,
+                    false_pc,
+                    end,
+                } => {
+                    let cond: i32 = pop_i32(&mut self.stack);
+                    self.push_label(*end as usize + 1, *arity, false);
+                    if cond == 0 {
+                        self.frames.last_mut().unwrap().pc = *false_pc as usize;
+                    }
+                }
+                Instr::Else { end } => {
+                    // reached the end of the true branch
+                    self.labels.pop();
+                    self.frames.last_mut().unwrap().pc = *end as usize + 1;
+                }
+                Instr::End => {
+                    if self.labels.len() > frame.labels_start {
+                        self.labels.pop();
+                    } else {
+                        self.ret();
+                    }
+                }
+                Instr::Br(depth) => self.branch(*depth),
+                Instr::BrIf(depth) => {
+                    if pop_i32(&mut self.stack) != 0 {
+                        self.branch(*depth);
+                    }
+                }
+                Instr::BrTable(depths, default_depth) => {
+                    let i = pop_i32(&mut self.stack) as u32 as usize;
+                    self.branch(*depths.get(i).unwrap_or(default_depth));
+                }
+                Instr::Return => self.ret(),
+                Instr::Call(funcidx) => self.call(*funcidx)?,
+                Instr::CallIndirect(typeidx) => {
+                    let funcidx: u32 = self.table_funcidx(*typeidx)?;
+                    self.call(funcidx)?;
+                }
+                Instr::ReturnCall(funcidx) => self.tail_call(*funcidx)?,
+                Instr::ReturnCallIndirect(typeidx) => {
+                    let funcidx: u32 = self.table_funcidx(*typeidx)?;
+                    self.tail_call(funcidx)?;
+                }
+                Instr::Drop => {
+                    pop(&mut self.stack);
+                }
+                Instr::Select => {
+                    let cond: i32 = pop_i32(&mut self.stack);
+                    let val2: Val = pop(&mut self.stack);
+                    let val1: Val = pop(&mut self.stack);
+                    self.stack.push(if cond != 0 { val1 } else { val2 });
+                }
+                Instr::LocalGet(localidx) => {
+                    let val: Val = *self.local(*localidx);
+                    self.stack.push(val);
+                }
+                Instr::LocalSet(localidx) => {
+                    let val: Val = pop(&mut self.stack);
+                    *self.local(*localidx) = val;
+                }
+                Instr::LocalTee(localidx) => {
+                    let val: Val = *self.stack.last().unwrap();
+                    *self.local(*localidx) = val;
+                }
+                Instr::GlobalGet(globalidx) => self.stack.push(self.globals[*globalidx as usize]),
+                Instr::GlobalSet(globalidx) => {
+                    self.globals[*globalidx as usize] = pop(&mut self.stack);
+                }
+                Instr::Load { opcode, offset } => {
+                    let addr: i32 = pop_i32(&mut self.stack);
+                    let range = mem_range(addr, *offset, access_size(*opcode), self.memory)?;
+                    self.stack.push(load(*opcode, &self.memory[range]));
+                }
+                Instr::Store { opcode, offset } => {
+                    let val: Val = pop(&mut self.stack);
+                    let addr: i32 = pop_i32(&mut self.stack);
+                    let range = mem_range(addr, *offset, access_size(*opcode), self.memory)?;
+                    let size: usize = range.len();
+                    self.memory[range]
+                        .copy_from_slice(&store_bits(*opcode, val).to_le_bytes()[..size]);
+                }
+                Instr::MemorySize => self
+                    .stack
+                    .push(Val::I32((self.memory.len() / WASM_PAGE_SIZE) as i32)),
+                Instr::MemoryGrow => {
+                    let delta = pop_i32(&mut self.stack) as u32 as u64;
+                    let old_pages = (self.memory.len() / WASM_PAGE_SIZE) as u64;
+                    if old_pages + delta > self.memory_max_pages as u64 {
+                        self.stack.push(Val::I32(-1));
+                    } else {
+                        self.memory
+                            .resize((old_pages + delta) as usize * WASM_PAGE_SIZE, 0);
+                        self.stack.push(Val::I32(old_pages as i32));
+                    }
+                }
+                Instr::MemoryCopy => {
+                    let len = pop_i32(&mut self.stack) as u32 as usize;
+                    let src: i32 = pop_i32(&mut self.stack);
+                    let dest: i32 = pop_i32(&mut self.stack);
+                    let src_range = mem_range(src, 0, len, self.memory)?;
+                    let dest_range = mem_range(dest, 0, len, self.memory)?;
+                    self.memory.copy_within(src_range, dest_range.start);
+                }
+                Instr::MemoryFill => {
+                    let len = pop_i32(&mut self.stack) as u32 as usize;
+                    let val: i32 = pop_i32(&mut self.stack);
+                    let dest: i32 = pop_i32(&mut self.stack);
+                    let range = mem_range(dest, 0, len, self.memory)?;
+                    for b in &mut self.memory[range] {
+                        *b = val as u8;
+                    }
+                }
+                Instr::I32Const(val) => self.stack.push(Val::I32(*val)),
+                Instr::I64Const(val) => self.stack.push(Val::I64(*val)),
+                Instr::F32Const(val) => self.stack.push(Val::F32(*val)),
+                Instr::F64Const(val) => self.stack.push(Val::F64(*val)),
+                Instr::Numeric(opcode) => exec_numeric(*opcode, &mut self.stack)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+/**
+ * The decoded form of instructions, and the decoder for function bodies.
+ * Structured control instructions store the positions (indices into the decoded function body) of their matching `else` and `end`,
+ * so that the interpreter never needs to scan for them.
+ */
+use super::reader::Reader;
+use super::Error;
+
+#[derive(Clone, Debug)]
+pub enum Instr {
+    Unreachable,
+    Nop,
+    Block {
+        arity: u32, // number of results
+        end: u32,   // position of the matching End
+    },
+    Loop,
+    If {
+        arity: u32,    // number of results
+        false_pc: u32, // where to continue if the condition is false (after the matching Else, or the matching End if there is no else)
+        end: u32,      // position of the matching End
+    },
+    Else {
+        end: u32, // position of the matching End
+    },
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(Box<[u32]>, u32),
+    Return,
+    Call(u32),
+    CallIndirect(u32),
+    ReturnCall(u32),
+    ReturnCallIndirect(u32),
+    Drop,
+    Select,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    Load {
+        opcode: u8,
+        offset: u32,
+    },
+    Store {
+        opcode: u8,
+        offset: u32,
+    },
+    MemorySize,
+    MemoryGrow,
+    MemoryCopy,
+    MemoryFill,
+    I32Const(i32),
+    I64Const(i64),
+    F32Const(f32),
+    F64Const(f64),
+    Numeric(u8), // all the numeric instructions without immediates, identified by their opcode
+}
+
+// Reads a block type, and returns the number of results.
+fn blocktype(reader: &mut Reader) -> Result<u32, Error> {
+    match reader.byte()? {
+        0x40 => Ok(0),
+        0x7C..=0x7F => Ok(1), // i32, i64, f32, or f64
+        _ => reader.error(
+            "unsupported block type (only empty and single-value block types are supported)",
+        ),
+    }
+}
+
+// Reads the reserved memory index byte of memory instructions.
+fn memidx(reader: &mut Reader) -> Result<(), Error> {
+    if reader.byte()? != 0x00 {
+        return reader.error("only memory 0 is supported");
+    }
+    Ok(())
+}
+
+/**
+ * Decodes the expression of a function body, up to and including its final `end`.
+ */
+pub fn decode_func_body(reader: &mut Reader) -> Result<Box<[Instr]>, Error> {
+    let mut code: Vec<Instr> = Vec::new();
+    let mut open_blocks: Vec<usize> = Vec::new(); // positions of the Block, Loop, and If instructions that have not been closed yet
+    loop {
+        let offset: usize = reader.offset();
+        let pos: u32 = code.len() as u32;
+        let instr: Instr = match reader.byte()? {
+            0x00 => Instr::Unreachable,
+            0x01 => Instr::Nop,
+            0x02 => {
+                open_blocks.push(code.len());
+                Instr::Block {
+                    arity: blocktype(reader)?,
+                    end: 0, // filled in at the matching End
+                }
+            }
+            0x03 => {
+                blocktype(reader)?; // branches to a loop carry no values, so the arity is not needed
+                open_blocks.push(code.len());
+                Instr::Loop
+            }
+            0x04 => {
+                open_blocks.push(code.len());
+                Instr::If {
+                    arity: blocktype(reader)?,
+                    false_pc: 0, // filled in at the matching Else or End
+                    end: 0,      // filled in at the matching End
+                }
+            }
+            0x05 => {
+                match open_blocks.last().map(|start| &mut code[*start]) {
+                    Some(Instr::If {
+                        arity: _,
+                        false_pc,
+                        end: _,
+                    }) if *false_pc == 0 => *false_pc = pos + 1,
+                    _ => return reader.error_at(offset, "else without matching if"),
+                }
+                Instr::Else { end: 0 } // filled in at the matching End
+            }
+            0x0B => match open_blocks.pop() {
+                None => {
+                    // end of the function body
+                    code.push(Instr::End);
+                    return Ok(code.into_boxed_slice());
+                }
+                Some(start) => {
+                    let else_pos: Option<usize> = match &mut code[start] {
+                        Instr::Block { arity: _, end } => {
+                            *end = pos;
+                            None
+                        }
+                        Instr::Loop => None,
+                        Instr::If {
+                            arity: _,
+                            false_pc,
+                            end,
+                        } => {
+                            *end = pos;
+                            if *false_pc == 0 {
+                                *false_pc = pos;
+                                None
+                            } else {
+                                Some(*false_pc as usize - 1)
+                            }
+                        }
+                        _ => panic!("ICE: wasm interpreter: open block is not a block instruction"),
+                    };
+                    if let Some(else_pos) = else_pos {
+                        code[else_pos] = Instr::Else { end: pos };
+                    }
+                    Instr::End
+                }
+            },
+            0x0C => Instr::Br(reader.u32()?),
+            0x0D => Instr::BrIf(reader.u32()?),
+            0x0E => {
+                let len: usize = reader.len()?;
+                let labelidxs: Box<[u32]> =
+                    (0..len).map(|_| reader.u32()).collect::<Result<_, _>>()?;
+                Instr::BrTable(labelidxs, reader.u32()?)
+            }
+            0x0F => Instr::Return,
+            0x10 => Instr::Call(reader.u32()?),
+            0x11 => {
+                let typeidx: u32 = reader.u32()?;
+                if reader.u32()? != 0 {
+                    return reader.error_at(offset, "only table 0 is supported");
+                }
+                Instr::CallIndirect(typeidx)
+            }
+            0x12 => Instr::ReturnCall(reader.u32()?),
+            0x13 => {
+                let typeidx: u32 = reader.u32()?;
+                if reader.u32()? != 0 {
+                    return reader.error_at(offset, "only table 0 is supported");
+                }
+                Instr::ReturnCallIndirect(typeidx)
+            }
+            0x1A => Instr::Drop,
+            0x1B => Instr::Select,
+            0x20 => Instr::LocalGet(reader.u32()?),
+            0x21 => Instr::LocalSet(reader.u32()?),
+            0x22 => Instr::LocalTee(reader.u32()?),
+            0x23 => Instr::GlobalGet(reader.u32()?),
+            0x24 => Instr::GlobalSet(reader.u32()?),
+            opcode @ 0x28..=0x3E => {
+                reader.u32()?; // alignment hint (ignored)
+                let memarg_offset: u32 = reader.u32()?;
+                if opcode <= 0x35 {
+                    Instr::Load {
+                        opcode: opcode,
+                        offset: memarg_offset,
+                    }
+                } else {
+                    Instr::Store {
+                        opcode: opcode,
+                        offset: memarg_offset,
+                    }
+                }
+            }
+            0x3F => {
+                memidx(reader)?;
+                Instr::MemorySize
+            }
+            0x40 => {
+                memidx(reader)?;
+                Instr::MemoryGrow
+            }
+            0x41 => Instr::I32Const(reader.i32()?),
+            0x42 => Instr::I64Const(reader.i64()?),
+            0x43 => Instr::F32Const(reader.f32()?),
+            0x44 => Instr::F64Const(reader.f64()?),
+            opcode @ 0x45..=0xC4 => Instr::Numeric(opcode),
+            0xFC => match reader.u32()? {
+                0x0A => {
+                    memidx(reader)?;
+                    memidx(reader)?;
+                    Instr::MemoryCopy
+                }
+                0x0B => {
+                    memidx(reader)?;
+                    Instr::MemoryFill
+                }
+                _ => return reader.error_at(offset, "unsupported instruction"),
+            },
+            _ => return reader.error_at(offset, "unsupported instruction"),
+        };
+        code.push(instr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_targets() {
+        // block (if (else) end) loop end end end
+        let bytes: &[u8] = &[
+            0x02, 0x40, // 0: block
+            0x41, 0x00, // 1: i32.const 0
+            0x04, 0x7F, // 2: if (result i32)
+            0x41, 0x01, // 3: i32.const 1
+            0x05, // 4: else
+            0x41, 0x02, // 5: i32.const 2
+            0x0B, // 6: end (if)
+            0x1A, // 7: drop
+            0x03, 0x40, // 8: loop
+            0x0B, // 9: end (loop)
+            0x0B, // 10: end (block)
+            0x0B, // 11: end (function)
+        ];
+        let code = decode_func_body(&mut Reader::new(bytes)).unwrap();
+        assert_eq!(code.len(), 12);
+        match &code[0] {
+            Instr::Block { arity: 0, end: 10 } => {}
+            other => panic!("wrong block: {:?}", other),
+        }
+        match &code[2] {
+            Instr::If {
+                arity: 1,
+                false_pc: 5,
+                end: 6,
+            } => {}
+            other => panic!("wrong if: {:?}", other),
+        }
+        match &code[4] {
+            Instr::Else { end: 6 } => {}
+            other => panic!("wrong else: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        // else without if
+        assert!(decode_func_body(&mut Reader::new(&[0x05, 0x0B])).is_err());
+        // missing end
+        assert!(decode_func_body(&mut Reader::new(&[0x02, 0x40, 0x0B])).is_err());
+        // unknown opcode
+        assert!(decode_func_body(&mut Reader::new(&[0xFF, 0x0B])).is_err());
+    }
+}
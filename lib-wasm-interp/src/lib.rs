@@ -0,0 +1,418 @@
+/**
+ * A small WebAssembly interpreter, so that the modules generated by the backend can be run natively (e.g. under `cargo test`)
+ * without a browser or Node.js.
+ *
+ * It supports the instruction set that `wasmgen::ExprBuilder` can emit, i.e. WebAssembly 1.0,
+ * multi-value function results, the tail call proposal (`return_call` and `return_call_indirect`),
+ * and `memory.copy` and `memory.fill` from the bulk memory proposal.
+ * Only functions may be imported; they are provided through the `Host` trait.
+ *
+ * The module is assumed to be valid (the interpreter does not type-check function bodies),
+ * and the interpreter panics if it finds a value of the wrong type on the operand stack.
+ * Function bodies are decoded once when the module is parsed, with the targets of all structured control instructions resolved,
+ * and execution uses explicit stacks, so deep recursion in the wasm module does not recurse on the native stack.
+ */
+mod exec;
+mod instr;
+mod module;
+mod numeric;
+mod reader;
+
+pub use exec::Instance;
+pub use module::FuncType;
+pub use module::Module;
+pub use wasmgen::ValType;
+
+use std::fmt;
+
+const WASM_PAGE_SIZE: usize = 65536;
+const WASM_MAX_PAGES: u32 = 65536;
+
+const DEFAULT_MAX_CALL_DEPTH: usize = 10000;
+
+/**
+ * A WebAssembly value.
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Val {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl Val {
+    pub fn valtype(&self) -> ValType {
+        match self {
+            Val::I32(_) => ValType::I32,
+            Val::I64(_) => ValType::I64,
+            Val::F32(_) => ValType::F32,
+            Val::F64(_) => ValType::F64,
+        }
+    }
+    // The initial value of locals of the given type.
+    fn zero(valtype: ValType) -> Val {
+        match valtype {
+            ValType::I32 => Val::I32(0),
+            ValType::I64 => Val::I64(0),
+            ValType::F32 => Val::F32(0.0),
+            ValType::F64 => Val::F64(0.0),
+        }
+    }
+}
+
+/**
+ * Options for instantiating a module with `Instance::new()`.
+ * The call depth limit stands in for the stack limit of a real engine.
+ * The default is in the same range as the limits of browsers, so that code which runs out of stack there also runs out of stack here.
+ */
+#[derive(Copy, Clone)]
+pub struct Options {
+    max_call_depth: usize, // Maximum number of nested wasm function calls
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+        }
+    }
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /**
+     * Sets the maximum number of nested function calls, after which `Trap::CallStackExhausted` is raised.
+     * (Tail calls do not count, since they replace the caller.)
+     */
+    pub fn max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+}
+
+/**
+ * The runtime errors that abort execution.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trap {
+    Unreachable,
+    MemoryOutOfBounds,
+    IntegerDivideByZero,
+    IntegerOverflow,
+    InvalidConversionToInteger,
+    UndefinedElement,
+    UninitializedElement,
+    IndirectCallTypeMismatch,
+    CallStackExhausted,
+    Host(String), // raised by an imported function
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::Unreachable => write!(f, "unreachable executed"),
+            Trap::MemoryOutOfBounds => write!(f, "out of bounds memory access"),
+            Trap::IntegerDivideByZero => write!(f, "integer divide by zero"),
+            Trap::IntegerOverflow => write!(f, "integer overflow"),
+            Trap::InvalidConversionToInteger => write!(f, "invalid conversion to integer"),
+            Trap::UndefinedElement => write!(f, "undefined element"),
+            Trap::UninitializedElement => write!(f, "uninitialized element"),
+            Trap::IndirectCallTypeMismatch => write!(f, "indirect call type mismatch"),
+            Trap::CallStackExhausted => write!(f, "call stack exhausted"),
+            Trap::Host(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/**
+ * The ways that loading or running a module can fail.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Malformed { offset: usize, message: String }, // the binary could not be decoded (offset is in bytes from the start of the binary)
+    Link(String), // the module could not be instantiated, or an export could not be invoked
+    Trap(Trap),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Malformed { offset, message } => {
+                write!(f, "malformed module at offset {}: {}", offset, message)
+            }
+            Error::Link(message) => write!(f, "link error: {}", message),
+            Error::Trap(trap) => write!(f, "trap: {}", trap),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Trap> for Error {
+    fn from(trap: Trap) -> Self {
+        Error::Trap(trap)
+    }
+}
+
+/**
+ * Implements the function imports of the module, which are looked up by module name and entity name when they are called.
+ * The args match the params of the import, and the returned values must match its results.
+ * The host may read and write the linear memory (e.g. to decode a string), and returning a `Trap` (usually `Trap::Host`) aborts execution of the module.
+ * Any function or closure with the same params as `call_import` can be used as a host, e.g. the one that provides the "core" imports in the end-to-end tests.
+ */
+pub trait Host {
+    fn call_import(
+        &mut self,
+        module_name: &str,
+        entity_name: &str,
+        args: &[Val],
+        memory: &mut [u8],
+    ) -> Result<Vec<Val>, Trap>;
+}
+
+impl<F: FnMut(&str, &str, &[Val], &mut [u8]) -> Result<Vec<Val>, Trap>> Host for F {
+    fn call_import(
+        &mut self,
+        module_name: &str,
+        entity_name: &str,
+        args: &[Val],
+        memory: &mut [u8],
+    ) -> Result<Vec<Val>, Trap> {
+        self(module_name, entity_name, args, memory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmgen::{CodeBuilder, MemArg, WasmModule, WasmSerialize};
+
+    fn no_imports(_: &str, _: &str, _: &[Val], _: &mut [u8]) -> Result<Vec<Val>, Trap> {
+        panic!("no imports")
+    }
+
+    fn instantiate<H: Host>(wasm_module: WasmModule, host: H) -> Instance<H> {
+        let mut bytes = Vec::<u8>::new();
+        wasm_module.wasm_serialize(&mut bytes);
+        Instance::new(Module::parse(&bytes).unwrap(), host, Options::new()).unwrap()
+    }
+
+    #[test]
+    fn recursion() {
+        // fn fact(n: i32) -> i32 { if n <= 1 { 1 } else { n * fact(n - 1) } }
+        let mut wasm_module = WasmModule::new_builder().build();
+        let functype = wasmgen::FuncType::new(Box::new([ValType::I32]), Box::new([ValType::I32]));
+        let (_, funcidx) = wasm_module.register_func(&functype);
+        let mut code_builder = CodeBuilder::new(functype);
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let n = locals_builder.param(0);
+            expr_builder.local_get(n);
+            expr_builder.i32_const(1);
+            expr_builder.i32_le_s();
+            expr_builder.if_(&[ValType::I32]);
+            expr_builder.i32_const(1);
+            expr_builder.else_();
+            expr_builder.local_get(n);
+            expr_builder.local_get(n);
+            expr_builder.i32_const(1);
+            expr_builder.i32_sub();
+            expr_builder.call(funcidx);
+            expr_builder.i32_mul();
+            expr_builder.end();
+            expr_builder.end();
+        }
+        wasm_module.commit_func(funcidx, code_builder);
+        wasm_module.export_func(funcidx, "fact".to_string());
+        let mut instance = instantiate(wasm_module, no_imports);
+        assert_eq!(
+            instance.invoke("fact", &[Val::I32(10)]),
+            Ok(vec![Val::I32(3628800)])
+        );
+        assert_eq!(
+            instance.invoke("fact", &[Val::I32(100000)]),
+            Err(Error::Trap(Trap::CallStackExhausted))
+        );
+    }
+
+    #[test]
+    fn loops_and_memory() {
+        // stores 0..n to memory as i32s, then sums them by reading them back
+        let mut wasm_module = WasmModule::new_builder().build();
+        let memidx = wasm_module.add_unbounded_memory(1);
+        wasm_module.export_mem(memidx, "memory".to_string());
+        let functype = wasmgen::FuncType::new(Box::new([ValType::I32]), Box::new([ValType::I64]));
+        let (_, funcidx) = wasm_module.register_func(&functype);
+        let mut code_builder = CodeBuilder::new(functype);
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let n = locals_builder.param(0);
+            let i = locals_builder.add(ValType::I32);
+            let sum = locals_builder.add(ValType::I64);
+            // do { mem[i * 4] = i; i = i + 1; } while (i < n);
+            expr_builder.loop_(&[]);
+            expr_builder.local_get(i);
+            expr_builder.i32_const(4);
+            expr_builder.i32_mul();
+            expr_builder.local_get(i);
+            expr_builder.i32_store(MemArg::new4(0));
+            expr_builder.local_get(i);
+            expr_builder.i32_const(1);
+            expr_builder.i32_add();
+            expr_builder.local_tee(i);
+            expr_builder.local_get(n);
+            expr_builder.i32_lt_u();
+            expr_builder.br_if(0);
+            expr_builder.end();
+            // while (i != 0) { i = i - 1; sum = sum + mem[i * 4]; }
+            expr_builder.block(&[]);
+            expr_builder.loop_(&[]);
+            expr_builder.local_get(i);
+            expr_builder.i32_eqz();
+            expr_builder.br_if(1);
+            expr_builder.local_get(i);
+            expr_builder.i32_const(1);
+            expr_builder.i32_sub();
+            expr_builder.local_tee(i);
+            expr_builder.i32_const(4);
+            expr_builder.i32_mul();
+            expr_builder.i64_load32_u(MemArg::new4(0));
+            expr_builder.local_get(sum);
+            expr_builder.i64_add();
+            expr_builder.local_set(sum);
+            expr_builder.br(0);
+            expr_builder.end();
+            expr_builder.end();
+            expr_builder.local_get(sum);
+            expr_builder.end();
+        }
+        wasm_module.commit_func(funcidx, code_builder);
+        wasm_module.export_func(funcidx, "sum".to_string());
+        let mut instance = instantiate(wasm_module, no_imports);
+        assert_eq!(
+            instance.invoke("sum", &[Val::I32(100)]),
+            Ok(vec![Val::I64(4950)])
+        );
+        assert_eq!(&instance.memory()[396..400], &99u32.to_le_bytes());
+        // 16384 i32s fill exactly one page
+        assert_eq!(
+            instance.invoke("sum", &[Val::I32(16385)]),
+            Err(Error::Trap(Trap::MemoryOutOfBounds))
+        );
+    }
+
+    #[test]
+    fn indirect_and_tail_calls() {
+        let mut wasm_module = WasmModule::new_builder().build();
+        let tableidx = wasm_module.get_or_add_table();
+        let functype = wasmgen::FuncType::new(Box::new([ValType::I32]), Box::new([ValType::I32]));
+        let other_functype = wasmgen::FuncType::new(Box::new([]), Box::new([]));
+        let (typeidx, count_down) = wasm_module.register_func(&functype);
+        let (_, noop) = wasm_module.register_func(&other_functype);
+        let (_, call_elem) = wasm_module.register_func(&functype);
+        // fn count_down(n) { if n == 0 { 42 } else { return_call_indirect[0](n - 1) } }
+        let mut code_builder = CodeBuilder::new(functype.clone());
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let n = locals_builder.param(0);
+            expr_builder.local_get(n);
+            expr_builder.i32_eqz();
+            expr_builder.if_(&[]);
+            expr_builder.i32_const(42);
+            expr_builder.return_();
+            expr_builder.end();
+            expr_builder.local_get(n);
+            expr_builder.i32_const(1);
+            expr_builder.i32_sub();
+            expr_builder.i32_const(0);
+            expr_builder.return_call_indirect(typeidx, tableidx);
+            expr_builder.end();
+        }
+        wasm_module.commit_func(count_down, code_builder);
+        let mut code_builder = CodeBuilder::new(other_functype);
+        code_builder.expr_builder().end();
+        wasm_module.commit_func(noop, code_builder);
+        // fn call_elem(i) { call_indirect[i](10) }
+        let mut code_builder = CodeBuilder::new(functype);
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            expr_builder.i32_const(10);
+            expr_builder.local_get(locals_builder.param(0));
+            expr_builder.call_indirect(typeidx, tableidx);
+            expr_builder.end();
+        }
+        wasm_module.commit_func(call_elem, code_builder);
+        let offset = wasm_module.reserve_table_elements(tableidx, 3);
+        wasm_module.commit_table_elements(tableidx, offset, Box::new([count_down, noop]));
+        wasm_module.export_func(count_down, "count_down".to_string());
+        wasm_module.export_func(call_elem, "call_elem".to_string());
+        let mut instance = instantiate(wasm_module, no_imports);
+        // tail calls do not use up the call stack
+        assert_eq!(
+            instance.invoke("count_down", &[Val::I32(100000)]),
+            Ok(vec![Val::I32(42)])
+        );
+        assert_eq!(
+            instance.invoke("call_elem", &[Val::I32(0)]),
+            Ok(vec![Val::I32(42)])
+        );
+        assert_eq!(
+            instance.invoke("call_elem", &[Val::I32(1)]),
+            Err(Error::Trap(Trap::IndirectCallTypeMismatch))
+        );
+        assert_eq!(
+            instance.invoke("call_elem", &[Val::I32(2)]),
+            Err(Error::Trap(Trap::UninitializedElement))
+        );
+        assert_eq!(
+            instance.invoke("call_elem", &[Val::I32(3)]),
+            Err(Error::Trap(Trap::UndefinedElement))
+        );
+    }
+
+    #[test]
+    fn imports() {
+        let mut builder = WasmModule::new_builder();
+        let log = builder.import_func(
+            "env".to_string(),
+            "log".to_string(),
+            &wasmgen::FuncType::new(Box::new([ValType::I32, ValType::F64]), Box::new([])),
+        );
+        let mut wasm_module = builder.build();
+        let functype = wasmgen::FuncType::new(Box::new([]), Box::new([]));
+        let (_, funcidx) = wasm_module.register_func(&functype);
+        let mut code_builder = CodeBuilder::new(functype);
+        {
+            let expr_builder = code_builder.expr_builder();
+            expr_builder.i32_const(1);
+            expr_builder.f64_const(0.5);
+            expr_builder.call(log);
+            expr_builder.i32_const(2);
+            expr_builder.f64_const(1.5);
+            expr_builder.call(log);
+            expr_builder.unreachable();
+            expr_builder.end();
+        }
+        wasm_module.commit_func(funcidx, code_builder);
+        wasm_module.export_func(funcidx, "main".to_string());
+        let mut logged: Vec<Val> = Vec::new();
+        let host = |module_name: &str, entity_name: &str, args: &[Val], _: &mut [u8]| {
+            assert_eq!((module_name, entity_name), ("env", "log"));
+            if args[0] == Val::I32(2) {
+                return Err(Trap::Host("stop".to_string()));
+            }
+            logged.extend_from_slice(args);
+            Ok(vec![])
+        };
+        let mut instance = instantiate(wasm_module, host);
+        assert_eq!(
+            instance.invoke("main", &[]),
+            Err(Error::Trap(Trap::Host("stop".to_string())))
+        );
+        drop(instance);
+        assert_eq!(logged, vec![Val::I32(1), Val::F64(0.5)]);
+    }
+}
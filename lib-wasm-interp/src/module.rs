@@ -0,0 +1,324 @@
+/**
+ * The decoded form of a wasm module, and the parser for the binary format.
+ */
+use super::instr;
+use super::instr::Instr;
+use super::reader::Reader;
+use super::Error;
+use super::Val;
+use super::ValType;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct FuncType {
+    pub params: Box<[ValType]>,
+    pub results: Box<[ValType]>,
+}
+
+pub struct Import {
+    pub module_name: String,
+    pub entity_name: String,
+    pub typeidx: u32,
+}
+
+pub struct Func {
+    pub typeidx: u32,
+    pub locals: Box<[ValType]>, // excluding the params
+    pub code: Box<[Instr]>,
+}
+
+#[derive(Copy, Clone)]
+pub struct Limits {
+    pub min: u32,
+    pub max: Option<u32>,
+}
+
+// The constant expressions allowed in initializers.
+#[derive(Copy, Clone)]
+pub enum ConstExpr {
+    Const(Val),
+    GlobalGet(u32),
+}
+
+pub struct Global {
+    pub valtype: ValType,
+    pub init: ConstExpr,
+}
+
+#[derive(Copy, Clone)]
+pub enum ExportDesc {
+    Func(u32),
+    Table,
+    Mem,
+    Global(u32),
+}
+
+pub struct Export {
+    pub name: String,
+    pub desc: ExportDesc,
+}
+
+pub struct Elem {
+    pub offset: ConstExpr,
+    pub funcidxs: Box<[u32]>,
+}
+
+pub struct Data {
+    pub offset: ConstExpr,
+    pub content: Box<[u8]>,
+}
+
+/**
+ * A decoded wasm module.
+ * Only function imports are supported, and there can be at most one table and one memory.
+ */
+#[derive(Default)]
+pub struct Module {
+    pub(crate) types: Vec<FuncType>,
+    pub(crate) imports: Vec<Import>, // function imports (they come first in the function index space)
+    pub(crate) funcs: Vec<Func>,
+    pub(crate) table: Option<Limits>,
+    pub(crate) memory: Option<Limits>,
+    pub(crate) globals: Vec<Global>,
+    pub(crate) exports: Vec<Export>,
+    pub(crate) start: Option<u32>,
+    pub(crate) elems: Vec<Elem>,
+    pub(crate) datas: Vec<Data>,
+}
+
+impl Module {
+    /**
+     * Decodes a module from the binary format.
+     */
+    pub fn parse(bytes: &[u8]) -> Result<Module, Error> {
+        let mut reader = Reader::new(bytes);
+        if reader.bytes(4).ok() != Some(b"\0asm") {
+            return reader.error_at(0, "magic value not found");
+        }
+        if reader.bytes(4)? != [1, 0, 0, 0] {
+            return reader.error_at(4, "unsupported version");
+        }
+        let mut module = Module::default();
+        let mut func_typeidxs: Vec<u32> = Vec::new();
+        let mut last_id: u8 = 0;
+        while !reader.is_empty() {
+            let id_offset: usize = reader.offset();
+            let id: u8 = reader.byte()?;
+            let len: usize = reader.len()?;
+            let mut section = reader.sub_reader(len)?;
+            if id != 0 {
+                // Data count section (12) goes between the Elem and Code sections
+                let order = |id: u8| if id == 12 { 9 } else { id };
+                if order(id) <= order(last_id) && last_id != 0 {
+                    return reader.error_at(id_offset, "section out of order");
+                }
+                last_id = id;
+            }
+            match id {
+                0 => {} // custom sections are ignored
+                1 => {
+                    module.types = vec_of(&mut section, |r| {
+                        if r.byte()? != 0x60 {
+                            return r.error("expected function type");
+                        }
+                        Ok(FuncType {
+                            params: r.valtypes()?,
+                            results: r.valtypes()?,
+                        })
+                    })?
+                }
+                2 => module.imports = vec_of(&mut section, parse_import)?,
+                3 => func_typeidxs = vec_of(&mut section, |r| r.u32())?,
+                4 => {
+                    let tables = vec_of(&mut section, |r| {
+                        if r.byte()? != 0x70 {
+                            return r.error("expected funcref");
+                        }
+                        parse_limits(r)
+                    })?;
+                    if tables.len() > 1 {
+                        return section.error("multiple tables");
+                    }
+                    module.table = tables.into_iter().next();
+                }
+                5 => {
+                    let mems = vec_of(&mut section, parse_limits)?;
+                    if mems.len() > 1 {
+                        return section.error("multiple memories");
+                    }
+                    module.memory = mems.into_iter().next();
+                }
+                6 => {
+                    module.globals = vec_of(&mut section, |r| {
+                        let valtype: ValType = r.valtype()?;
+                        r.byte()?; // mutability (not needed, since the module is assumed to be valid)
+                        Ok(Global {
+                            valtype: valtype,
+                            init: parse_const_expr(r)?,
+                        })
+                    })?
+                }
+                7 => {
+                    module.exports = vec_of(&mut section, |r| {
+                        let name: String = r.name()?;
+                        let desc = match r.byte()? {
+                            0x00 => ExportDesc::Func(r.u32()?),
+                            0x01 => {
+                                r.u32()?;
+                                ExportDesc::Table
+                            }
+                            0x02 => {
+                                r.u32()?;
+                                ExportDesc::Mem
+                            }
+                            0x03 => ExportDesc::Global(r.u32()?),
+                            _ => return r.error("invalid export kind"),
+                        };
+                        Ok(Export {
+                            name: name,
+                            desc: desc,
+                        })
+                    })?
+                }
+                8 => module.start = Some(section.u32()?),
+                9 => {
+                    module.elems = vec_of(&mut section, |r| {
+                        if r.u32()? != 0 {
+                            return r.error("unsupported element segment kind");
+                        }
+                        Ok(Elem {
+                            offset: parse_const_expr(r)?,
+                            funcidxs: vec_of(r, |r| r.u32())?.into_boxed_slice(),
+                        })
+                    })?
+                }
+                10 => {
+                    let bodies = vec_of(&mut section, |r| {
+                        let len: usize = r.len()?;
+                        let mut body = r.sub_reader(len)?;
+                        let mut locals: Vec<ValType> = Vec::new();
+                        for _ in 0..body.len()? {
+                            let count: usize = body.len()?;
+                            let valtype: ValType = body.valtype()?;
+                            if locals.len() + count > u32::MAX as usize {
+                                return body.error("too many locals");
+                            }
+                            locals.resize(locals.len() + count, valtype);
+                        }
+                        let code: Box<[Instr]> = instr::decode_func_body(&mut body)?;
+                        if !body.is_empty() {
+                            return body.error("function body has trailing bytes");
+                        }
+                        Ok((locals.into_boxed_slice(), code))
+                    })?;
+                    if bodies.len() != func_typeidxs.len() {
+                        return section.error_at(
+                            id_offset,
+                            "function and code section have different lengths",
+                        );
+                    }
+                    module.funcs = func_typeidxs
+                        .iter()
+                        .zip(bodies)
+                        .map(|(typeidx, (locals, code))| Func {
+                            typeidx: *typeidx,
+                            locals: locals,
+                            code: code,
+                        })
+                        .collect();
+                }
+                11 => {
+                    module.datas = vec_of(&mut section, |r| {
+                        if r.u32()? != 0 {
+                            return r.error("unsupported data segment kind");
+                        }
+                        let offset: ConstExpr = parse_const_expr(r)?;
+                        let len: usize = r.len()?;
+                        Ok(Data {
+                            offset: offset,
+                            content: r.bytes(len)?.into(),
+                        })
+                    })?
+                }
+                12 => {
+                    section.u32()?; // data count (not needed)
+                }
+                _ => return reader.error_at(id_offset, "unknown section"),
+            }
+            if id != 0 && !section.is_empty() {
+                return section.error("section has trailing bytes");
+            }
+        }
+        if module.funcs.len() != func_typeidxs.len() {
+            return reader.error("function section without code section");
+        }
+        Ok(module)
+    }
+
+    // The type of the function (which may be imported) with the given index.
+    pub(crate) fn func_type(&self, funcidx: u32) -> &FuncType {
+        let funcidx = funcidx as usize;
+        let typeidx: u32 = if funcidx < self.imports.len() {
+            self.imports[funcidx].typeidx
+        } else {
+            self.funcs[funcidx - self.imports.len()].typeidx
+        };
+        &self.types[typeidx as usize]
+    }
+}
+
+fn vec_of<'a, T, F: FnMut(&mut Reader<'a>) -> Result<T, Error>>(
+    reader: &mut Reader<'a>,
+    mut parse_elem: F,
+) -> Result<Vec<T>, Error> {
+    let len: usize = reader.len()?;
+    let mut ret: Vec<T> = Vec::new();
+    for _ in 0..len {
+        ret.push(parse_elem(reader)?);
+    }
+    Ok(ret)
+}
+
+fn parse_import(reader: &mut Reader) -> Result<Import, Error> {
+    let module_name: String = reader.name()?;
+    let entity_name: String = reader.name()?;
+    if reader.byte()? != 0x00 {
+        return reader.error(&format!(
+            "import {}.{} is not a function (only function imports are supported)",
+            module_name, entity_name
+        ));
+    }
+    Ok(Import {
+        module_name: module_name,
+        entity_name: entity_name,
+        typeidx: reader.u32()?,
+    })
+}
+
+fn parse_limits(reader: &mut Reader) -> Result<Limits, Error> {
+    match reader.byte()? {
+        0x00 => Ok(Limits {
+            min: reader.u32()?,
+            max: None,
+        }),
+        0x01 => Ok(Limits {
+            min: reader.u32()?,
+            max: Some(reader.u32()?),
+        }),
+        _ => reader.error("invalid limits"),
+    }
+}
+
+fn parse_const_expr(reader: &mut Reader) -> Result<ConstExpr, Error> {
+    let ret = match reader.byte()? {
+        0x41 => ConstExpr::Const(Val::I32(reader.i32()?)),
+        0x42 => ConstExpr::Const(Val::I64(reader.i64()?)),
+        0x43 => ConstExpr::Const(Val::F32(reader.f32()?)),
+        0x44 => ConstExpr::Const(Val::F64(reader.f64()?)),
+        0x23 => ConstExpr::GlobalGet(reader.u32()?),
+        _ => return reader.error("unsupported constant expression"),
+    };
+    if reader.byte()? != 0x0B {
+        return reader.error("expected end of constant expression");
+    }
+    Ok(ret)
+}
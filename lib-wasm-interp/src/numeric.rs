@@ -0,0 +1,564 @@
+/**
+ * The numeric instructions (those without immediates, opcodes 0x45 to 0xC4).
+ * Semantics follow the WebAssembly spec, including the traps for integer division and float-to-int truncation.
+ */
+use super::Trap;
+use super::Val;
+
+pub fn pop(stack: &mut Vec<Val>) -> Val {
+    stack
+        .pop()
+        .expect("wasm interpreter: operand stack underflow (the module is invalid)")
+}
+
+pub fn pop_i32(stack: &mut Vec<Val>) -> i32 {
+    match pop(stack) {
+        Val::I32(x) => x,
+        other => type_mismatch("i32", other),
+    }
+}
+
+fn pop_i64(stack: &mut Vec<Val>) -> i64 {
+    match pop(stack) {
+        Val::I64(x) => x,
+        other => type_mismatch("i64", other),
+    }
+}
+
+fn pop_f32(stack: &mut Vec<Val>) -> f32 {
+    match pop(stack) {
+        Val::F32(x) => x,
+        other => type_mismatch("f32", other),
+    }
+}
+
+fn pop_f64(stack: &mut Vec<Val>) -> f64 {
+    match pop(stack) {
+        Val::F64(x) => x,
+        other => type_mismatch("f64", other),
+    }
+}
+
+fn type_mismatch(expected: &str, found: Val) -> ! {
+    panic!(
+        "wasm interpreter: expected {} on the operand stack but found {:?} (the module is invalid)",
+        expected, found
+    )
+}
+
+fn i32_unop(stack: &mut Vec<Val>, f: impl FnOnce(i32) -> i32) {
+    let a = pop_i32(stack);
+    stack.push(Val::I32(f(a)));
+}
+
+fn i32_binop(stack: &mut Vec<Val>, f: impl FnOnce(i32, i32) -> i32) {
+    let b = pop_i32(stack);
+    let a = pop_i32(stack);
+    stack.push(Val::I32(f(a, b)));
+}
+
+fn i32_binop_checked(
+    stack: &mut Vec<Val>,
+    f: impl FnOnce(i32, i32) -> Result<i32, Trap>,
+) -> Result<(), Trap> {
+    let b = pop_i32(stack);
+    let a = pop_i32(stack);
+    stack.push(Val::I32(f(a, b)?));
+    Ok(())
+}
+
+fn i32_cmp(stack: &mut Vec<Val>, f: impl FnOnce(i32, i32) -> bool) {
+    let b = pop_i32(stack);
+    let a = pop_i32(stack);
+    stack.push(Val::I32(f(a, b) as i32));
+}
+
+fn i64_unop(stack: &mut Vec<Val>, f: impl FnOnce(i64) -> i64) {
+    let a = pop_i64(stack);
+    stack.push(Val::I64(f(a)));
+}
+
+fn i64_binop(stack: &mut Vec<Val>, f: impl FnOnce(i64, i64) -> i64) {
+    let b = pop_i64(stack);
+    let a = pop_i64(stack);
+    stack.push(Val::I64(f(a, b)));
+}
+
+fn i64_binop_checked(
+    stack: &mut Vec<Val>,
+    f: impl FnOnce(i64, i64) -> Result<i64, Trap>,
+) -> Result<(), Trap> {
+    let b = pop_i64(stack);
+    let a = pop_i64(stack);
+    stack.push(Val::I64(f(a, b)?));
+    Ok(())
+}
+
+fn i64_cmp(stack: &mut Vec<Val>, f: impl FnOnce(i64, i64) -> bool) {
+    let b = pop_i64(stack);
+    let a = pop_i64(stack);
+    stack.push(Val::I32(f(a, b) as i32));
+}
+
+fn f32_unop(stack: &mut Vec<Val>, f: impl FnOnce(f32) -> f32) {
+    let a = pop_f32(stack);
+    stack.push(Val::F32(f(a)));
+}
+
+fn f32_binop(stack: &mut Vec<Val>, f: impl FnOnce(f32, f32) -> f32) {
+    let b = pop_f32(stack);
+    let a = pop_f32(stack);
+    stack.push(Val::F32(f(a, b)));
+}
+
+fn f32_cmp(stack: &mut Vec<Val>, f: impl FnOnce(f32, f32) -> bool) {
+    let b = pop_f32(stack);
+    let a = pop_f32(stack);
+    stack.push(Val::I32(f(a, b) as i32));
+}
+
+fn f64_unop(stack: &mut Vec<Val>, f: impl FnOnce(f64) -> f64) {
+    let a = pop_f64(stack);
+    stack.push(Val::F64(f(a)));
+}
+
+fn f64_binop(stack: &mut Vec<Val>, f: impl FnOnce(f64, f64) -> f64) {
+    let b = pop_f64(stack);
+    let a = pop_f64(stack);
+    stack.push(Val::F64(f(a, b)));
+}
+
+fn f64_cmp(stack: &mut Vec<Val>, f: impl FnOnce(f64, f64) -> bool) {
+    let b = pop_f64(stack);
+    let a = pop_f64(stack);
+    stack.push(Val::I32(f(a, b) as i32));
+}
+
+// Wasm min and max propagate NaN, and treat -0 as less than +0 (unlike f64::min and f64::max).
+fn fmin(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        if a.is_sign_negative() {
+            a
+        } else {
+            b
+        }
+    } else {
+        a.min(b)
+    }
+}
+
+fn fmax(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        if a.is_sign_positive() {
+            a
+        } else {
+            b
+        }
+    } else {
+        a.max(b)
+    }
+}
+
+// Truncates towards zero, trapping if the result is not in the open interval (lower, upper).
+fn trunc(x: f64, lower: f64, upper: f64) -> Result<f64, Trap> {
+    if x.is_nan() {
+        return Err(Trap::InvalidConversionToInteger);
+    }
+    let t = x.trunc();
+    if t > lower && t < upper {
+        Ok(t)
+    } else {
+        Err(Trap::IntegerOverflow)
+    }
+}
+
+fn trunc_i32_s(x: f64) -> Result<i32, Trap> {
+    Ok(trunc(x, -2147483649.0, 2147483648.0)? as i32)
+}
+
+fn trunc_i32_u(x: f64) -> Result<i32, Trap> {
+    Ok(trunc(x, -1.0, 4294967296.0)? as u32 as i32)
+}
+
+fn trunc_i64_s(x: f64) -> Result<i64, Trap> {
+    // the lower bound is the next f64 below -2^63
+    Ok(trunc(x, -9223372036854777856.0, 9223372036854775808.0)? as i64)
+}
+
+fn trunc_i64_u(x: f64) -> Result<i64, Trap> {
+    Ok(trunc(x, -1.0, 18446744073709551616.0)? as u64 as i64)
+}
+
+fn div_s<T: Copy + PartialEq + Default>(
+    a: T,
+    b: T,
+    min: T,
+    minus_one: T,
+    f: impl FnOnce(T, T) -> T,
+) -> Result<T, Trap> {
+    if b == T::default() {
+        Err(Trap::IntegerDivideByZero)
+    } else if a == min && b == minus_one {
+        Err(Trap::IntegerOverflow)
+    } else {
+        Ok(f(a, b))
+    }
+}
+
+fn div_u<T: Copy + PartialEq + Default>(a: T, b: T, f: impl FnOnce(T, T) -> T) -> Result<T, Trap> {
+    if b == T::default() {
+        Err(Trap::IntegerDivideByZero)
+    } else {
+        Ok(f(a, b))
+    }
+}
+
+/**
+ * Executes the numeric instruction with the given opcode.
+ */
+pub fn exec_numeric(opcode: u8, stack: &mut Vec<Val>) -> Result<(), Trap> {
+    match opcode {
+        0x45 => i32_unop(stack, |a| (a == 0) as i32),
+        0x46 => i32_cmp(stack, |a, b| a == b),
+        0x47 => i32_cmp(stack, |a, b| a != b),
+        0x48 => i32_cmp(stack, |a, b| a < b),
+        0x49 => i32_cmp(stack, |a, b| (a as u32) < (b as u32)),
+        0x4A => i32_cmp(stack, |a, b| a > b),
+        0x4B => i32_cmp(stack, |a, b| (a as u32) > (b as u32)),
+        0x4C => i32_cmp(stack, |a, b| a <= b),
+        0x4D => i32_cmp(stack, |a, b| (a as u32) <= (b as u32)),
+        0x4E => i32_cmp(stack, |a, b| a >= b),
+        0x4F => i32_cmp(stack, |a, b| (a as u32) >= (b as u32)),
+
+        0x50 => {
+            let a = pop_i64(stack);
+            stack.push(Val::I32((a == 0) as i32));
+        }
+        0x51 => i64_cmp(stack, |a, b| a == b),
+        0x52 => i64_cmp(stack, |a, b| a != b),
+        0x53 => i64_cmp(stack, |a, b| a < b),
+        0x54 => i64_cmp(stack, |a, b| (a as u64) < (b as u64)),
+        0x55 => i64_cmp(stack, |a, b| a > b),
+        0x56 => i64_cmp(stack, |a, b| (a as u64) > (b as u64)),
+        0x57 => i64_cmp(stack, |a, b| a <= b),
+        0x58 => i64_cmp(stack, |a, b| (a as u64) <= (b as u64)),
+        0x59 => i64_cmp(stack, |a, b| a >= b),
+        0x5A => i64_cmp(stack, |a, b| (a as u64) >= (b as u64)),
+
+        0x5B => f32_cmp(stack, |a, b| a == b),
+        0x5C => f32_cmp(stack, |a, b| a != b),
+        0x5D => f32_cmp(stack, |a, b| a < b),
+        0x5E => f32_cmp(stack, |a, b| a > b),
+        0x5F => f32_cmp(stack, |a, b| a <= b),
+        0x60 => f32_cmp(stack, |a, b| a >= b),
+
+        0x61 => f64_cmp(stack, |a, b| a == b),
+        0x62 => f64_cmp(stack, |a, b| a != b),
+        0x63 => f64_cmp(stack, |a, b| a < b),
+        0x64 => f64_cmp(stack, |a, b| a > b),
+        0x65 => f64_cmp(stack, |a, b| a <= b),
+        0x66 => f64_cmp(stack, |a, b| a >= b),
+
+        0x67 => i32_unop(stack, |a| a.leading_zeros() as i32),
+        0x68 => i32_unop(stack, |a| a.trailing_zeros() as i32),
+        0x69 => i32_unop(stack, |a| a.count_ones() as i32),
+        0x6A => i32_binop(stack, |a, b| a.wrapping_add(b)),
+        0x6B => i32_binop(stack, |a, b| a.wrapping_sub(b)),
+        0x6C => i32_binop(stack, |a, b| a.wrapping_mul(b)),
+        0x6D => i32_binop_checked(stack, |a, b| div_s(a, b, i32::MIN, -1, |a, b| a / b))?,
+        0x6E => i32_binop_checked(stack, |a, b| {
+            div_u(a as u32, b as u32, |a, b| a / b).map(|x| x as i32)
+        })?,
+        0x6F => i32_binop_checked(stack, |a, b| div_u(a, b, |a, b| a.wrapping_rem(b)))?,
+        0x70 => i32_binop_checked(stack, |a, b| {
+            div_u(a as u32, b as u32, |a, b| a % b).map(|x| x as i32)
+        })?,
+        0x71 => i32_binop(stack, |a, b| a & b),
+        0x72 => i32_binop(stack, |a, b| a | b),
+        0x73 => i32_binop(stack, |a, b| a ^ b),
+        0x74 => i32_binop(stack, |a, b| a.wrapping_shl(b as u32)),
+        0x75 => i32_binop(stack, |a, b| a.wrapping_shr(b as u32)),
+        0x76 => i32_binop(stack, |a, b| (a as u32).wrapping_shr(b as u32) as i32),
+        0x77 => i32_binop(stack, |a, b| a.rotate_left(b as u32 % 32)),
+        0x78 => i32_binop(stack, |a, b| a.rotate_right(b as u32 % 32)),
+
+        0x79 => i64_unop(stack, |a| a.leading_zeros() as i64),
+        0x7A => i64_unop(stack, |a| a.trailing_zeros() as i64),
+        0x7B => i64_unop(stack, |a| a.count_ones() as i64),
+        0x7C => i64_binop(stack, |a, b| a.wrapping_add(b)),
+        0x7D => i64_binop(stack, |a, b| a.wrapping_sub(b)),
+        0x7E => i64_binop(stack, |a, b| a.wrapping_mul(b)),
+        0x7F => i64_binop_checked(stack, |a, b| div_s(a, b, i64::MIN, -1, |a, b| a / b))?,
+        0x80 => i64_binop_checked(stack, |a, b| {
+            div_u(a as u64, b as u64, |a, b| a / b).map(|x| x as i64)
+        })?,
+        0x81 => i64_binop_checked(stack, |a, b| div_u(a, b, |a, b| a.wrapping_rem(b)))?,
+        0x82 => i64_binop_checked(stack, |a, b| {
+            div_u(a as u64, b as u64, |a, b| a % b).map(|x| x as i64)
+        })?,
+        0x83 => i64_binop(stack, |a, b| a & b),
+        0x84 => i64_binop(stack, |a, b| a | b),
+        0x85 => i64_binop(stack, |a, b| a ^ b),
+        0x86 => i64_binop(stack, |a, b| a.wrapping_shl(b as u32)),
+        0x87 => i64_binop(stack, |a, b| a.wrapping_shr(b as u32)),
+        0x88 => i64_binop(stack, |a, b| (a as u64).wrapping_shr(b as u32) as i64),
+        0x89 => i64_binop(stack, |a, b| a.rotate_left((b as u64 % 64) as u32)),
+        0x8A => i64_binop(stack, |a, b| a.rotate_right((b as u64 % 64) as u32)),
+
+        0x8B => f32_unop(stack, f32::abs),
+        0x8C => f32_unop(stack, |a| -a),
+        0x8D => f32_unop(stack, f32::ceil),
+        0x8E => f32_unop(stack, f32::floor),
+        0x8F => f32_unop(stack, f32::trunc),
+        0x90 => f32_unop(stack, f32::round_ties_even),
+        0x91 => f32_unop(stack, f32::sqrt),
+        0x92 => f32_binop(stack, |a, b| a + b),
+        0x93 => f32_binop(stack, |a, b| a - b),
+        0x94 => f32_binop(stack, |a, b| a * b),
+        0x95 => f32_binop(stack, |a, b| a / b),
+        0x96 => f32_binop(stack, |a, b| fmin(a as f64, b as f64) as f32),
+        0x97 => f32_binop(stack, |a, b| fmax(a as f64, b as f64) as f32),
+        0x98 => f32_binop(stack, f32::copysign),
+
+        0x99 => f64_unop(stack, f64::abs),
+        0x9A => f64_unop(stack, |a| -a),
+        0x9B => f64_unop(stack, f64::ceil),
+        0x9C => f64_unop(stack, f64::floor),
+        0x9D => f64_unop(stack, f64::trunc),
+        0x9E => f64_unop(stack, f64::round_ties_even),
+        0x9F => f64_unop(stack, f64::sqrt),
+        0xA0 => f64_binop(stack, |a, b| a + b),
+        0xA1 => f64_binop(stack, |a, b| a - b),
+        0xA2 => f64_binop(stack, |a, b| a * b),
+        0xA3 => f64_binop(stack, |a, b| a / b),
+        0xA4 => f64_binop(stack, fmin),
+        0xA5 => f64_binop(stack, fmax),
+        0xA6 => f64_binop(stack, f64::copysign),
+
+        0xA7 => {
+            let a = pop_i64(stack);
+            stack.push(Val::I32(a as i32));
+        }
+        0xA8 => {
+            let a = pop_f32(stack) as f64;
+            stack.push(Val::I32(trunc_i32_s(a)?));
+        }
+        0xA9 => {
+            let a = pop_f32(stack) as f64;
+            stack.push(Val::I32(trunc_i32_u(a)?));
+        }
+        0xAA => {
+            let a = pop_f64(stack);
+            stack.push(Val::I32(trunc_i32_s(a)?));
+        }
+        0xAB => {
+            let a = pop_f64(stack);
+            stack.push(Val::I32(trunc_i32_u(a)?));
+        }
+        0xAC => {
+            let a = pop_i32(stack);
+            stack.push(Val::I64(a as i64));
+        }
+        0xAD => {
+            let a = pop_i32(stack);
+            stack.push(Val::I64(a as u32 as i64));
+        }
+        0xAE => {
+            let a = pop_f32(stack) as f64;
+            stack.push(Val::I64(trunc_i64_s(a)?));
+        }
+        0xAF => {
+            let a = pop_f32(stack) as f64;
+            stack.push(Val::I64(trunc_i64_u(a)?));
+        }
+        0xB0 => {
+            let a = pop_f64(stack);
+            stack.push(Val::I64(trunc_i64_s(a)?));
+        }
+        0xB1 => {
+            let a = pop_f64(stack);
+            stack.push(Val::I64(trunc_i64_u(a)?));
+        }
+        0xB2 => {
+            let a = pop_i32(stack);
+            stack.push(Val::F32(a as f32));
+        }
+        0xB3 => {
+            let a = pop_i32(stack);
+            stack.push(Val::F32(a as u32 as f32));
+        }
+        0xB4 => {
+            let a = pop_i64(stack);
+            stack.push(Val::F32(a as f32));
+        }
+        0xB5 => {
+            let a = pop_i64(stack);
+            stack.push(Val::F32(a as u64 as f32));
+        }
+        0xB6 => {
+            let a = pop_f64(stack);
+            stack.push(Val::F32(a as f32));
+        }
+        0xB7 => {
+            let a = pop_i32(stack);
+            stack.push(Val::F64(a as f64));
+        }
+        0xB8 => {
+            let a = pop_i32(stack);
+            stack.push(Val::F64(a as u32 as f64));
+        }
+        0xB9 => {
+            let a = pop_i64(stack);
+            stack.push(Val::F64(a as f64));
+        }
+        0xBA => {
+            let a = pop_i64(stack);
+            stack.push(Val::F64(a as u64 as f64));
+        }
+        0xBB => {
+            let a = pop_f32(stack);
+            stack.push(Val::F64(a as f64));
+        }
+        0xBC => {
+            let a = pop_f32(stack);
+            stack.push(Val::I32(a.to_bits() as i32));
+        }
+        0xBD => {
+            let a = pop_f64(stack);
+            stack.push(Val::I64(a.to_bits() as i64));
+        }
+        0xBE => {
+            let a = pop_i32(stack);
+            stack.push(Val::F32(f32::from_bits(a as u32)));
+        }
+        0xBF => {
+            let a = pop_i64(stack);
+            stack.push(Val::F64(f64::from_bits(a as u64)));
+        }
+
+        // sign extension operators
+        0xC0 => i32_unop(stack, |a| a as i8 as i32),
+        0xC1 => i32_unop(stack, |a| a as i16 as i32),
+        0xC2 => i64_unop(stack, |a| a as i8 as i64),
+        0xC3 => i64_unop(stack, |a| a as i16 as i64),
+        0xC4 => i64_unop(stack, |a| a as i32 as i64),
+
+        _ => panic!(
+            "ICE: wasm interpreter: {:#04x} is not a numeric instruction",
+            opcode
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(opcode: u8, args: &[Val]) -> Result<Val, Trap> {
+        let mut stack: Vec<Val> = args.to_vec();
+        exec_numeric(opcode, &mut stack)?;
+        assert_eq!(stack.len(), 1);
+        Ok(stack[0])
+    }
+
+    #[test]
+    fn integer_division() {
+        assert_eq!(run(0x6D, &[Val::I32(-7), Val::I32(2)]), Ok(Val::I32(-3)));
+        assert_eq!(
+            run(0x6E, &[Val::I32(-7), Val::I32(2)]),
+            Ok(Val::I32(2147483644))
+        );
+        assert_eq!(run(0x6F, &[Val::I32(-7), Val::I32(2)]), Ok(Val::I32(-1)));
+        assert_eq!(
+            run(0x6D, &[Val::I32(1), Val::I32(0)]),
+            Err(Trap::IntegerDivideByZero)
+        );
+        assert_eq!(
+            run(0x6D, &[Val::I32(i32::MIN), Val::I32(-1)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(0x6F, &[Val::I32(i32::MIN), Val::I32(-1)]),
+            Ok(Val::I32(0))
+        );
+        assert_eq!(
+            run(0x82, &[Val::I64(1), Val::I64(0)]),
+            Err(Trap::IntegerDivideByZero)
+        );
+    }
+
+    #[test]
+    fn shifts_and_bits() {
+        assert_eq!(run(0x74, &[Val::I32(1), Val::I32(33)]), Ok(Val::I32(2)));
+        assert_eq!(run(0x75, &[Val::I32(-8), Val::I32(1)]), Ok(Val::I32(-4)));
+        assert_eq!(
+            run(0x76, &[Val::I32(-8), Val::I32(1)]),
+            Ok(Val::I32(0x7FFFFFFC))
+        );
+        assert_eq!(
+            run(0x77, &[Val::I32(i32::MIN), Val::I32(1)]),
+            Ok(Val::I32(1))
+        );
+        assert_eq!(
+            run(0x88, &[Val::I64(-1), Val::I64(32)]),
+            Ok(Val::I64(0xFFFFFFFF))
+        );
+        assert_eq!(run(0x67, &[Val::I32(1)]), Ok(Val::I32(31)));
+        assert_eq!(run(0xAD, &[Val::I32(-1)]), Ok(Val::I64(0xFFFFFFFF)));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(run(0x9E, &[Val::F64(2.5)]), Ok(Val::F64(2.0)));
+        assert_eq!(run(0x9E, &[Val::F64(3.5)]), Ok(Val::F64(4.0)));
+        match run(0xA4, &[Val::F64(0.0), Val::F64(-0.0)]) {
+            Ok(Val::F64(x)) => assert!(x == 0.0 && x.is_sign_negative()),
+            other => panic!("{:?}", other),
+        }
+        match run(0xA5, &[Val::F64(f64::NAN), Val::F64(1.0)]) {
+            Ok(Val::F64(x)) => assert!(x.is_nan()),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(
+            run(0x63, &[Val::F64(f64::NAN), Val::F64(1.0)]),
+            Ok(Val::I32(0))
+        );
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(run(0xAA, &[Val::F64(-2.9)]), Ok(Val::I32(-2)));
+        assert_eq!(
+            run(0xAA, &[Val::F64(-2147483648.9)]),
+            Ok(Val::I32(i32::MIN))
+        );
+        assert_eq!(
+            run(0xAA, &[Val::F64(2147483648.0)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(run(0xAB, &[Val::F64(-0.9)]), Ok(Val::I32(0)));
+        assert_eq!(run(0xAB, &[Val::F64(4294967295.0)]), Ok(Val::I32(-1)));
+        assert_eq!(run(0xAB, &[Val::F64(-1.0)]), Err(Trap::IntegerOverflow));
+        assert_eq!(
+            run(0xB0, &[Val::F64(f64::NAN)]),
+            Err(Trap::InvalidConversionToInteger)
+        );
+        assert_eq!(
+            run(0xB0, &[Val::F64(-9223372036854775808.0)]),
+            Ok(Val::I64(i64::MIN))
+        );
+        assert_eq!(
+            run(0xB0, &[Val::F64(9223372036854775808.0)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(0xB1, &[Val::F64(18446744073709549568.0)]),
+            Ok(Val::I64(-2048))
+        );
+    }
+}
@@ -0,0 +1,201 @@
+/**
+ * A cursor over the bytes of a wasm binary, with methods to decode the primitive encodings.
+ */
+use super::Error;
+use super::ValType;
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    base: usize, // offset of `bytes` in the whole binary (for error messages)
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader {
+            bytes: bytes,
+            pos: 0,
+            base: 0,
+        }
+    }
+
+    // Offset of the next byte, from the start of the whole binary.
+    pub fn offset(&self) -> usize {
+        self.base + self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    pub fn error<T>(&self, message: &str) -> Result<T, Error> {
+        self.error_at(self.offset(), message)
+    }
+
+    pub fn error_at<T>(&self, offset: usize, message: &str) -> Result<T, Error> {
+        Err(Error::Malformed {
+            offset: offset,
+            message: message.to_string(),
+        })
+    }
+
+    pub fn byte(&mut self) -> Result<u8, Error> {
+        match self.bytes.get(self.pos) {
+            Some(b) => {
+                self.pos += 1;
+                Ok(*b)
+            }
+            None => self.error("unexpected end"),
+        }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.bytes.len() - self.pos {
+            return self.error("unexpected end");
+        }
+        let ret: &'a [u8] = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(ret)
+    }
+
+    // Splits off the next `len` bytes into a separate reader (used for sections and function bodies).
+    pub fn sub_reader(&mut self, len: usize) -> Result<Reader<'a>, Error> {
+        let base: usize = self.offset();
+        Ok(Reader {
+            bytes: self.bytes(len)?,
+            pos: 0,
+            base: base,
+        })
+    }
+
+    // Reads an unsigned LEB128 integer of at most `bits` bits.
+    fn uleb(&mut self, bits: u32) -> Result<u64, Error> {
+        let mut result: u64 = 0;
+        let mut shift: u32 = 0;
+        loop {
+            let b = self.byte()?;
+            if shift >= bits || (shift + 7 > bits && (b & 0x7F) >> (bits - shift) != 0) {
+                return self.error("integer too large");
+            }
+            result |= ((b & 0x7F) as u64) << shift;
+            shift += 7;
+            if b & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+    }
+
+    // Reads a signed LEB128 integer of at most `bits` bits.
+    fn sleb(&mut self, bits: u32) -> Result<i64, Error> {
+        let mut result: i64 = 0;
+        let mut shift: u32 = 0;
+        loop {
+            let b = self.byte()?;
+            if shift >= bits {
+                return self.error("integer too large");
+            }
+            result |= ((b & 0x7F) as i64) << shift;
+            shift += 7;
+            if b & 0x80 == 0 {
+                if shift < 64 && b & 0x40 != 0 {
+                    // sign extend
+                    result |= -1i64 << shift;
+                }
+                let unused_bits: u32 = 64 - bits;
+                if unused_bits != 0 && (result << unused_bits) >> unused_bits != result {
+                    return self.error("integer too large");
+                }
+                return Ok(result);
+            }
+        }
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Error> {
+        Ok(self.uleb(32)? as u32)
+    }
+
+    pub fn i32(&mut self) -> Result<i32, Error> {
+        Ok(self.sleb(32)? as i32)
+    }
+
+    pub fn i64(&mut self) -> Result<i64, Error> {
+        self.sleb(64)
+    }
+
+    pub fn f32(&mut self) -> Result<f32, Error> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.bytes(4)?);
+        Ok(f32::from_bits(u32::from_le_bytes(buf)))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, Error> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.bytes(8)?);
+        Ok(f64::from_bits(u64::from_le_bytes(buf)))
+    }
+
+    // Reads a vector length.
+    pub fn len(&mut self) -> Result<usize, Error> {
+        Ok(self.u32()? as usize)
+    }
+
+    pub fn name(&mut self) -> Result<String, Error> {
+        let offset: usize = self.offset();
+        let len: usize = self.len()?;
+        match std::str::from_utf8(self.bytes(len)?) {
+            Ok(s) => Ok(s.to_string()),
+            Err(_) => self.error_at(offset, "name is not valid UTF-8"),
+        }
+    }
+
+    pub fn valtype(&mut self) -> Result<ValType, Error> {
+        let offset: usize = self.offset();
+        match self.byte()? {
+            0x7F => Ok(ValType::I32),
+            0x7E => Ok(ValType::I64),
+            0x7D => Ok(ValType::F32),
+            0x7C => Ok(ValType::F64),
+            _ => self.error_at(offset, "invalid value type"),
+        }
+    }
+
+    pub fn valtypes(&mut self) -> Result<Box<[ValType]>, Error> {
+        let len: usize = self.len()?;
+        (0..len).map(|_| self.valtype()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmgen::LebSerialize;
+
+    #[test]
+    fn leb_round_trip() {
+        for &val in &[0u32, 1, 127, 128, 624485, u32::MAX] {
+            let mut buf = Vec::<u8>::new();
+            val.leb_serialize(&mut buf);
+            assert_eq!(Reader::new(&buf).u32().unwrap(), val);
+        }
+        for &val in &[0i32, 1, -1, 63, 64, -64, -65, i32::MIN, i32::MAX] {
+            let mut buf = Vec::<u8>::new();
+            val.leb_serialize(&mut buf);
+            assert_eq!(Reader::new(&buf).i32().unwrap(), val);
+        }
+        for &val in &[0i64, -1, i64::MIN, i64::MAX, 1 << 40] {
+            let mut buf = Vec::<u8>::new();
+            val.leb_serialize(&mut buf);
+            assert_eq!(Reader::new(&buf).i64().unwrap(), val);
+        }
+    }
+
+    #[test]
+    fn leb_too_large() {
+        assert!(Reader::new(&[0x80, 0x80, 0x80, 0x80, 0x10]).u32().is_err());
+        assert!(Reader::new(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00])
+            .u32()
+            .is_err());
+        assert!(Reader::new(&[0x80, 0x80, 0x80, 0x80, 0x70]).i32().is_err());
+        assert!(Reader::new(&[0xFF, 0xFF, 0xFF, 0xFF, 0x7F]).i32().unwrap() == -1);
+    }
+}
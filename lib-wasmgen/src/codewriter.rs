@@ -7,11 +7,13 @@ use projstd::iter::SequentialCountAdapter;
 #[derive(Default)]
 pub struct ExprBuilder {
     bytecode: Vec<u8>,
+    source_positions: Vec<(u32, Option<SourcePosition>)>, // (offset into bytecode, source position of the code starting there), for source maps
 }
 
 pub struct LocalsManager {
     num_params: u32,
     locals: Vec<ValType>,
+    names: BTreeMap<u32, String>, // names of params and locals, for the name section
 }
 
 pub struct CodeBuilder {
@@ -32,6 +34,25 @@ impl ExprBuilder {
     fn write_to_slice(self, out: &mut [u8]) {
         out.copy_from_slice(self.bytecode.as_slice());
     }
+    /**
+     * Sets the source position of the instructions that are emitted after this (until the next call).
+     * `None` means that the instructions do not come from any particular source position.
+     */
+    pub fn set_source_position(&mut self, position: Option<SourcePosition>) {
+        let offset = self.bytecode.len() as u32;
+        // no instructions were emitted since the previous call, so the previous position is useless
+        if self
+            .source_positions
+            .last()
+            .map(|(last_offset, _)| *last_offset)
+            == Some(offset)
+        {
+            self.source_positions.pop();
+        }
+        if self.source_positions.last().and_then(|(_, last)| *last) != position {
+            self.source_positions.push((offset, position));
+        }
+    }
 }
 
 impl LocalsManager {
@@ -46,6 +67,19 @@ impl LocalsManager {
         assert!(idx < self.num_params as u32);
         LocalIdx { idx: idx }
     }
+    /**
+     * Names a param or local, so that debuggers can show it.
+     * Locals may be reused for different variables (e.g. by Scratch), so all the names given to a local are kept, separated by '/'.
+     */
+    pub fn add_name(&mut self, localidx: LocalIdx, name: &str) {
+        let entry: &mut String = self.names.entry(localidx.idx).or_default();
+        if entry.is_empty() {
+            entry.push_str(name);
+        } else if !entry.split('/').any(|existing| existing == name) {
+            entry.push('/');
+            entry.push_str(name);
+        }
+    }
 }
 
 impl CodeBuilder {
@@ -56,17 +90,30 @@ impl CodeBuilder {
             locals_builder: LocalsManager {
                 num_params: num_params,
                 locals: Default::default(),
+                names: Default::default(),
             },
             expr: Default::default(),
         }
     }
     pub fn build(self) -> (FuncType, Box<[u8]>) {
+        let (functype, bytes, _) = self.build_with_source_positions();
+        (functype, bytes)
+    }
+    // Like build(), but also returns the source positions, with offsets from the beginning of the returned bytes.
+    pub(crate) fn build_with_source_positions(mut self) -> (FuncType, Box<[u8]>, SourcePositions) {
         let mut receiver = Vec::<u8>::new();
         serialize_locals(self.locals_builder.locals, &mut receiver);
         let locals_len = receiver.len();
+        let source_positions: SourcePositions = std::mem::take(&mut self.expr.source_positions)
+            .into_iter()
+            .map(|(offset, position)| (locals_len as u32 + offset, position))
+            .collect();
         receiver.resize_with(locals_len + self.expr.len(), Default::default);
         self.expr.write_to_slice(&mut receiver[locals_len..]);
-        (self.functype, receiver.into_boxed_slice())
+        (self.functype, receiver.into_boxed_slice(), source_positions)
+    }
+    pub(crate) fn take_local_names(&mut self) -> BTreeMap<u32, String> {
+        std::mem::take(&mut self.locals_builder.names)
     }
     pub fn split(&mut self) -> (&mut LocalsManager, &mut ExprBuilder) {
         (&mut self.locals_builder, &mut self.expr)
@@ -483,10 +530,10 @@ impl OpCode {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct MemArg {
-    offset: u32,
-    align: u32, // expressed as the logarithm of the actual alignment
+    pub(crate) offset: u32,
+    pub(crate) align: u32, // expressed as the logarithm of the actual alignment
 }
 
 impl MemArg {
@@ -0,0 +1,425 @@
+/**
+ * Decoding of WebAssembly binaries back into a `WasmModule`, e.g. to inspect the generated code or to link precompiled modules.
+ * Only modules that this crate could have produced are supported:
+ * function bodies and constant expressions may only use the instructions supported by `InstrReader`.
+ * The name section and the `sourceMappingURL` section are decoded, but other custom sections are dropped.
+ */
+use super::instr::*;
+use super::*;
+
+impl WasmModule {
+    /**
+     * Decodes a WebAssembly binary.  The offsets in errors are relative to the start of `bytes`.
+     * The decoded module is not validated (use `validate()` for that).
+     */
+    pub fn decode(bytes: &[u8]) -> Result<WasmModule, DecodeError> {
+        let mut reader = InstrReader::new(bytes, 0);
+        if reader.slice(4)? != b"\0asm" {
+            return reader.error(0, "invalid magic number");
+        }
+        if reader.slice(4)? != [0x01, 0x00, 0x00, 0x00] {
+            return reader.error(4, "unsupported version");
+        }
+        let mut module = WasmModule::default();
+        let mut last_id: u8 = 0; // the id of the previous non-custom section
+        while reader.current_offset() < bytes.len() {
+            let offset: usize = reader.current_offset();
+            let id: u8 = reader.byte()?;
+            let size: usize = reader.u32()? as usize;
+            let content_start: usize = reader.current_offset();
+            reader.slice(size)?;
+            let content_end: usize = reader.current_offset();
+            // the section reader can't read past the end of the section
+            let mut section = InstrReader::new(&bytes[..content_end], content_start);
+            if id != 0 {
+                if id <= last_id {
+                    return reader.error(offset, "section out of order");
+                }
+                last_id = id;
+            }
+            match id {
+                0 => decode_custom_section(&mut section, &mut module)?,
+                1 => {
+                    for functype in decode_vec(&mut section, decode_functype)? {
+                        module.type_section.content.push(functype);
+                    }
+                }
+                2 => {
+                    module.import_section.content = decode_vec(&mut section, decode_import)?;
+                }
+                3 => {
+                    module.func_section.content =
+                        decode_vec(&mut section, |r| Ok(TypeIdx { idx: r.u32()? }))?;
+                }
+                4 => {
+                    module.table_section.content = decode_vec(&mut section, |r| {
+                        Ok(Table {
+                            table_type: decode_tabletype(r)?,
+                        })
+                    })?;
+                }
+                5 => {
+                    module.mem_section.content = decode_vec(&mut section, |r| {
+                        Ok(Mem {
+                            mem_type: MemType {
+                                limits: decode_limits(r)?,
+                            },
+                        })
+                    })?;
+                }
+                6 => {
+                    module.global_section.content = decode_vec(&mut section, |r| {
+                        Ok(Global {
+                            global_type: decode_globaltype(r)?,
+                            init_expr: decode_const_expr(r, bytes)?,
+                        })
+                    })?;
+                }
+                7 => {
+                    module.export_section.content = decode_vec(&mut section, decode_export)?;
+                }
+                8 => {
+                    module.start_section.start = Some(FuncIdx {
+                        idx: section.u32()?,
+                    });
+                }
+                9 => {
+                    module.elem_section.content = decode_vec(&mut section, |r| {
+                        Ok(Elem {
+                            table_idx: TableIdx { idx: r.u32()? },
+                            offset: decode_const_expr(r, bytes)?,
+                            content: decode_vec(r, |r| Ok(FuncIdx { idx: r.u32()? }))?
+                                .into_boxed_slice(),
+                        })
+                    })?;
+                }
+                10 => {
+                    module.code_section.content = decode_vec(&mut section, decode_code)?;
+                }
+                11 => {
+                    module.data_section.content = decode_vec(&mut section, |r| {
+                        let mem_idx = MemIdx { idx: r.u32()? };
+                        let offset: Expr = decode_const_expr(r, bytes)?;
+                        let len: usize = r.u32()? as usize;
+                        Ok(Data {
+                            mem_idx: mem_idx,
+                            offset: offset,
+                            content: r.slice(len)?.into(),
+                        })
+                    })?;
+                }
+                _ => return reader.error(offset, "unsupported section"),
+            }
+            if section.current_offset() != content_end {
+                return section.error(section.current_offset(), "section size mismatch");
+            }
+        }
+        if module.func_section.content.len() != module.code_section.content.len() {
+            return reader.error(
+                bytes.len(),
+                "function and code sections have different lengths",
+            );
+        }
+
+        // the indices of the entities defined in this module start after the imports
+        for import in &module.import_section.content {
+            match import.desc {
+                ImportDesc::Func(_) => module.func_section.idx_offset += 1,
+                ImportDesc::Table(_) => module.table_section.idx_offset += 1,
+                ImportDesc::Mem(_) => module.mem_section.idx_offset += 1,
+                ImportDesc::Global(_) => module.global_section.idx_offset += 1,
+            }
+        }
+        Ok(module)
+    }
+}
+
+fn decode_vec<'a, T, F: FnMut(&mut InstrReader<'a>) -> Result<T, DecodeError>>(
+    reader: &mut InstrReader<'a>,
+    mut decode_elem: F,
+) -> Result<Vec<T>, DecodeError> {
+    let len: u32 = reader.u32()?;
+    (0..len).map(|_| decode_elem(reader)).collect()
+}
+
+fn decode_name(reader: &mut InstrReader) -> Result<String, DecodeError> {
+    let offset: usize = reader.current_offset();
+    let len: usize = reader.u32()? as usize;
+    match std::str::from_utf8(reader.slice(len)?) {
+        Ok(name) => Ok(name.to_string()),
+        Err(_) => reader.error(offset, "invalid UTF-8 in name"),
+    }
+}
+
+fn decode_functype(reader: &mut InstrReader) -> Result<FuncType, DecodeError> {
+    let offset: usize = reader.current_offset();
+    if reader.byte()? != 0x60 {
+        return reader.error(offset, "invalid function type");
+    }
+    let param_types: Vec<ValType> = decode_vec(reader, |r| r.valtype(r.current_offset()))?;
+    let result_types: Vec<ValType> = decode_vec(reader, |r| r.valtype(r.current_offset()))?;
+    Ok(FuncType::new(
+        param_types.into_boxed_slice(),
+        result_types.into_boxed_slice(),
+    ))
+}
+
+fn decode_limits(reader: &mut InstrReader) -> Result<Limits, DecodeError> {
+    let offset: usize = reader.current_offset();
+    match reader.byte()? {
+        0x00 => Ok(Limits::Unbounded { min: reader.u32()? }),
+        0x01 => Ok(Limits::Bounded {
+            min: reader.u32()?,
+            max: reader.u32()?,
+        }),
+        _ => reader.error(offset, "invalid limits"),
+    }
+}
+
+fn decode_tabletype(reader: &mut InstrReader) -> Result<TableType, DecodeError> {
+    let offset: usize = reader.current_offset();
+    if reader.byte()? != 0x70 {
+        return reader.error(offset, "invalid element type");
+    }
+    Ok(TableType {
+        elem_type: ElemType::FuncRef,
+        limits: decode_limits(reader)?,
+    })
+}
+
+fn decode_globaltype(reader: &mut InstrReader) -> Result<GlobalType, DecodeError> {
+    let val_type: ValType = reader.valtype(reader.current_offset())?;
+    let offset: usize = reader.current_offset();
+    let mutability: Mut = match reader.byte()? {
+        0x00 => Mut::Const,
+        0x01 => Mut::Var,
+        _ => return reader.error(offset, "invalid mutability"),
+    };
+    Ok(GlobalType {
+        val_type: val_type,
+        mutability: mutability,
+    })
+}
+
+fn decode_import(reader: &mut InstrReader) -> Result<Import, DecodeError> {
+    let module_name: String = decode_name(reader)?;
+    let entity_name: String = decode_name(reader)?;
+    let offset: usize = reader.current_offset();
+    let desc: ImportDesc = match reader.byte()? {
+        0x00 => ImportDesc::Func(TypeIdx { idx: reader.u32()? }),
+        0x01 => ImportDesc::Table(decode_tabletype(reader)?),
+        0x02 => ImportDesc::Mem(MemType {
+            limits: decode_limits(reader)?,
+        }),
+        0x03 => ImportDesc::Global(decode_globaltype(reader)?),
+        _ => return reader.error(offset, "invalid import kind"),
+    };
+    Ok(Import {
+        module_name: module_name,
+        entity_name: entity_name,
+        desc: desc,
+    })
+}
+
+fn decode_export(reader: &mut InstrReader) -> Result<Export, DecodeError> {
+    let entity_name: String = decode_name(reader)?;
+    let offset: usize = reader.current_offset();
+    let kind: u8 = reader.byte()?;
+    let idx: u32 = reader.u32()?;
+    let desc: ExportDesc = match kind {
+        0x00 => ExportDesc::Func(FuncIdx { idx: idx }),
+        0x01 => ExportDesc::Table(TableIdx { idx: idx }),
+        0x02 => ExportDesc::Mem(MemIdx { idx: idx }),
+        0x03 => ExportDesc::Global(GlobalIdx { idx: idx }),
+        _ => return reader.error(offset, "invalid export kind"),
+    };
+    Ok(Export {
+        entity_name: entity_name,
+        desc: desc,
+    })
+}
+
+// Decodes a constant expression (up to and including its `end`), where `bytes` is the whole module.
+fn decode_const_expr(reader: &mut InstrReader, bytes: &[u8]) -> Result<Expr, DecodeError> {
+    let start: usize = reader.current_offset();
+    loop {
+        match reader.next() {
+            Some(Ok((_, Instr::End))) => break,
+            Some(Ok(_)) => {}
+            Some(Err(e)) => return Err(e),
+            None => return reader.error(reader.current_offset(), "unexpected end of bytecode"),
+        }
+    }
+    Ok(Expr {
+        bytecode: bytes[start..reader.current_offset()].into(),
+    })
+}
+
+fn decode_code(reader: &mut InstrReader) -> Result<Code, DecodeError> {
+    let size: usize = reader.u32()? as usize;
+    let body_offset: usize = reader.current_offset();
+    let body: &[u8] = reader.slice(size)?;
+    // check that the body can be decoded, so that other tools can assume that
+    let relocate = |e: DecodeError| DecodeError {
+        offset: body_offset + e.offset,
+        message: e.message,
+    };
+    let (_, start) = decode_locals(body).map_err(relocate)?;
+    for res in InstrReader::new(body, start) {
+        res.map_err(relocate)?;
+    }
+    Ok(Code {
+        func: Some(body.into()),
+        source_positions: Box::new([]),
+    })
+}
+
+fn decode_custom_section(
+    reader: &mut InstrReader,
+    module: &mut WasmModule,
+) -> Result<(), DecodeError> {
+    match decode_name(reader)?.as_str() {
+        "name" => decode_name_section(reader, &mut module.name_section),
+        "sourceMappingURL" => {
+            module.source_map_url = Some(decode_name(reader)?);
+            Ok(())
+        }
+        _ => {
+            reader.skip_to_end();
+            Ok(())
+        }
+    }
+}
+
+fn decode_name_section(
+    reader: &mut InstrReader,
+    name_section: &mut NameSection,
+) -> Result<(), DecodeError> {
+    fn decode_name_map(reader: &mut InstrReader) -> Result<BTreeMap<u32, String>, DecodeError> {
+        Ok(decode_vec(reader, |r| Ok((r.u32()?, decode_name(r)?)))?
+            .into_iter()
+            .collect())
+    }
+    // the section reader ends at the end of the section, so this loops over all the subsections
+    while !reader.at_end() {
+        let id: u8 = reader.byte()?;
+        let size: usize = reader.u32()? as usize;
+        let subsection_end: usize = reader.current_offset() + size;
+        match id {
+            1 => name_section.func_names = decode_name_map(reader)?,
+            2 => {
+                name_section.local_names =
+                    decode_vec(reader, |r| Ok((r.u32()?, decode_name_map(r)?)))?
+                        .into_iter()
+                        .collect()
+            }
+            _ => {
+                reader.slice(size)?;
+            }
+        }
+        if reader.current_offset() != subsection_end {
+            return reader.error(reader.current_offset(), "subsection size mismatch");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialize(wasm_module: &WasmModule) -> Vec<u8> {
+        let mut bytes = Vec::<u8>::new();
+        wasm_module.wasm_serialize(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut builder = WasmModule::new_builder();
+        let import_type = FuncType::new(Box::new([ValType::I32]), Box::new([]));
+        let imported = builder.import_func("core".to_string(), "log".to_string(), &import_type);
+        builder.import_unbounded_memory("core".to_string(), "mem".to_string(), 1);
+        let mut wasm_module = builder.build();
+        let globalidx = wasm_module.add_i32_global(Mut::Var, -5);
+        let functype = FuncType::new(Box::new([ValType::F64]), Box::new([ValType::I32]));
+        let (_, funcidx) = wasm_module.register_func(&functype);
+        let mut code_builder = CodeBuilder::new(functype);
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let localidx = locals_builder.add(ValType::I64);
+            locals_builder.add_name(localidx, "n");
+            expr_builder.global_get(globalidx);
+            expr_builder.call(imported);
+            expr_builder.i64_const(7);
+            expr_builder.local_set(localidx);
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.i32_trunc_f64_s();
+            expr_builder.end();
+        }
+        wasm_module.commit_func(funcidx, code_builder);
+        wasm_module.set_func_name(funcidx, "f".to_string());
+        wasm_module.export_func(funcidx, "main".to_string());
+        let tableidx = wasm_module.get_or_add_table();
+        let offset = wasm_module.reserve_table_elements(tableidx, 2);
+        wasm_module.commit_table_elements(tableidx, offset, Box::new([funcidx, imported]));
+        wasm_module.add_data(MemIdx { idx: 0 }, 16, b"abc");
+        wasm_module.set_source_map_url("prog.wasm.map".to_string());
+
+        let bytes: Vec<u8> = serialize(&wasm_module);
+        let decoded = WasmModule::decode(&bytes).unwrap();
+        assert!(decoded.validate().is_ok());
+        assert_eq!(decoded.func_section.idx_offset, 1);
+        assert_eq!(decoded.mem_section.idx_offset, 1);
+        assert_eq!(decoded.name_section.func_names.get(&1).unwrap(), "f");
+        assert_eq!(decoded.source_map_url.as_deref(), Some("prog.wasm.map"));
+        assert_eq!(serialize(&decoded), bytes);
+    }
+
+    #[test]
+    fn duplicate_types() {
+        // types that are not deduplicated keep their indices, and unknown custom sections are dropped
+        let bytes: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+            0x00, 0x03, 0x01, b'x', 0xFF, // custom section "x"
+            0x01, 0x07, 0x02, 0x60, 0x00, 0x00, 0x60, 0x00,
+            0x00, // type section: [() -> (), () -> ()]
+            0x03, 0x02, 0x01, 0x01, // function section: [type 1]
+            0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B, // code section: [(no locals) end]
+        ];
+        let decoded = WasmModule::decode(&bytes).unwrap();
+        assert_eq!(decoded.type_section.content.vec().len(), 2);
+        assert_eq!(serialize(&decoded), [&bytes[..8], &bytes[13..]].concat());
+    }
+
+    #[test]
+    fn errors() {
+        let header: &[u8] = &[0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+        let decode = |rest: &[u8]| WasmModule::decode(&[header, rest].concat()).err().unwrap();
+        assert_eq!(
+            WasmModule::decode(b"\0wasm\x01\0\0\0")
+                .err()
+                .unwrap()
+                .message,
+            "invalid magic number"
+        );
+        let err = decode(&[0x01, 0x05, 0x01, 0x60, 0x00]);
+        assert_eq!(err.offset, 10);
+        assert_eq!(err.message, "unexpected end of bytecode");
+        let err = decode(&[0x03, 0x01, 0x00, 0x01, 0x01, 0x00]);
+        assert_eq!(err.offset, 11);
+        assert_eq!(err.message, "section out of order");
+        let err = decode(&[0x03, 0x02, 0x01, 0x00]);
+        assert_eq!(
+            err.message,
+            "function and code sections have different lengths"
+        );
+        let err = decode(&[0x03, 0x03, 0x01, 0x00, 0x00]);
+        assert_eq!(err.offset, 12);
+        assert_eq!(err.message, "section size mismatch");
+        // errors in function bodies have offsets relative to the module
+        let err = decode(&[0x03, 0x02, 0x01, 0x00, 0x0A, 0x04, 0x01, 0x02, 0x00, 0xFF]);
+        assert_eq!(err.offset, 17);
+        assert_eq!(err.message, "unsupported instruction");
+    }
+}
@@ -0,0 +1,554 @@
+/**
+ * Decoding of the bytecode written by `ExprBuilder` and `CodeBuilder`, for tools that need to inspect the generated code
+ * (e.g. the validator).
+ * Only the instructions that `ExprBuilder` can emit are supported.
+ */
+use super::*;
+use std::fmt;
+
+/**
+ * A decoded instruction.
+ * Block types are `None` for empty blocks, since blocks can have at most one result.
+ * Loads and stores are identified by their opcode (see `memory_op_info()`),
+ * and so are the numeric instructions without immediates (see `numeric_op_info()`).
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instr {
+    Unreachable,
+    Nop,
+    Block(Option<ValType>),
+    Loop(Option<ValType>),
+    If(Option<ValType>),
+    Else,
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(Box<[u32]>, u32),
+    Return,
+    Call(FuncIdx),
+    CallIndirect(TypeIdx, TableIdx),
+    ReturnCall(FuncIdx),
+    ReturnCallIndirect(TypeIdx, TableIdx),
+    Drop,
+    Select,
+    LocalGet(LocalIdx),
+    LocalSet(LocalIdx),
+    LocalTee(LocalIdx),
+    GlobalGet(GlobalIdx),
+    GlobalSet(GlobalIdx),
+    Load(u8, MemArg),
+    Store(u8, MemArg),
+    MemorySize(MemIdx),
+    MemoryGrow(MemIdx),
+    MemoryCopy(MemIdx, MemIdx),
+    MemoryFill(MemIdx),
+    I32Const(i32),
+    I64Const(i64),
+    F32Const(f32),
+    F64Const(f64),
+    Numeric(u8),
+}
+
+/**
+ * An error encountered while decoding, with the byte offset of the instruction (relative to the start of the decoded bytes).
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offset {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/**
+ * Iterates over the instructions in some bytecode, yielding each instruction with its offset.
+ * Iteration stops after the first error.
+ */
+pub struct InstrReader<'a> {
+    bytecode: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+impl<'a> InstrReader<'a> {
+    // Starts reading at the given offset (all offsets are still relative to the start of `bytecode`).
+    pub fn new(bytecode: &'a [u8], start: usize) -> InstrReader<'a> {
+        InstrReader {
+            bytecode: bytecode,
+            pos: start,
+            failed: false,
+        }
+    }
+    // The offset of the next byte to be read.
+    pub(crate) fn current_offset(&self) -> usize {
+        self.pos
+    }
+    pub(crate) fn at_end(&self) -> bool {
+        self.pos == self.bytecode.len()
+    }
+    pub(crate) fn skip_to_end(&mut self) {
+        self.pos = self.bytecode.len();
+    }
+    pub(crate) fn error<T>(&self, offset: usize, message: &str) -> Result<T, DecodeError> {
+        Err(DecodeError {
+            offset: offset,
+            message: message.to_string(),
+        })
+    }
+    pub(crate) fn byte(&mut self) -> Result<u8, DecodeError> {
+        match self.bytecode.get(self.pos) {
+            Some(b) => {
+                self.pos += 1;
+                Ok(*b)
+            }
+            None => self.error(self.pos, "unexpected end of bytecode"),
+        }
+    }
+    // Reads a LEB128 integer of at most `bits` bits, sign-extending it if `signed` is true.
+    fn leb(&mut self, bits: u32, signed: bool) -> Result<u64, DecodeError> {
+        let start: usize = self.pos;
+        let mut result: u64 = 0;
+        let mut shift: u32 = 0;
+        loop {
+            let b = self.byte()?;
+            if shift >= bits {
+                return self.error(start, "integer too large");
+            }
+            result |= ((b & 0x7F) as u64) << shift;
+            shift += 7;
+            if b & 0x80 == 0 {
+                if signed && shift < 64 && b & 0x40 != 0 {
+                    result |= !0u64 << shift;
+                }
+                return Ok(result);
+            }
+        }
+    }
+    pub(crate) fn u32(&mut self) -> Result<u32, DecodeError> {
+        let start: usize = self.pos;
+        let val: u64 = self.leb(32, false)?;
+        if val > u32::MAX as u64 {
+            return self.error(start, "integer too large");
+        }
+        Ok(val as u32)
+    }
+    // Reads the next `len` bytes.
+    pub(crate) fn slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytecode.len() - self.pos < len {
+            return self.error(self.pos, "unexpected end of bytecode");
+        }
+        let ret: &'a [u8] = &self.bytecode[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(ret)
+    }
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        if self.bytecode.len() - self.pos < N {
+            return self.error(self.pos, "unexpected end of bytecode");
+        }
+        let mut ret = [0u8; N];
+        ret.copy_from_slice(&self.bytecode[self.pos..self.pos + N]);
+        self.pos += N;
+        Ok(ret)
+    }
+    pub(crate) fn valtype(&mut self, offset: usize) -> Result<ValType, DecodeError> {
+        match self.byte()? {
+            0x7F => Ok(ValType::I32),
+            0x7E => Ok(ValType::I64),
+            0x7D => Ok(ValType::F32),
+            0x7C => Ok(ValType::F64),
+            _ => self.error(offset, "invalid value type"),
+        }
+    }
+    fn blocktype(&mut self, offset: usize) -> Result<Option<ValType>, DecodeError> {
+        if self.bytecode.get(self.pos) == Some(&0x40) {
+            self.pos += 1;
+            Ok(None)
+        } else {
+            self.valtype(offset).map(Some)
+        }
+    }
+    fn memarg(&mut self) -> Result<MemArg, DecodeError> {
+        let align: u32 = self.u32()?;
+        let offset: u32 = self.u32()?;
+        Ok(MemArg {
+            offset: offset,
+            align: align,
+        })
+    }
+    fn memidx(&mut self) -> Result<MemIdx, DecodeError> {
+        Ok(MemIdx { idx: self.u32()? })
+    }
+    fn instr(&mut self, offset: usize) -> Result<Instr, DecodeError> {
+        Ok(match self.byte()? {
+            0x00 => Instr::Unreachable,
+            0x01 => Instr::Nop,
+            0x02 => Instr::Block(self.blocktype(offset)?),
+            0x03 => Instr::Loop(self.blocktype(offset)?),
+            0x04 => Instr::If(self.blocktype(offset)?),
+            0x05 => Instr::Else,
+            0x0B => Instr::End,
+            0x0C => Instr::Br(self.u32()?),
+            0x0D => Instr::BrIf(self.u32()?),
+            0x0E => {
+                let len: u32 = self.u32()?;
+                let labelidxs: Box<[u32]> =
+                    (0..len).map(|_| self.u32()).collect::<Result<_, _>>()?;
+                Instr::BrTable(labelidxs, self.u32()?)
+            }
+            0x0F => Instr::Return,
+            0x10 => Instr::Call(FuncIdx { idx: self.u32()? }),
+            0x11 => {
+                Instr::CallIndirect(TypeIdx { idx: self.u32()? }, TableIdx { idx: self.u32()? })
+            }
+            0x12 => Instr::ReturnCall(FuncIdx { idx: self.u32()? }),
+            0x13 => Instr::ReturnCallIndirect(
+                TypeIdx { idx: self.u32()? },
+                TableIdx { idx: self.u32()? },
+            ),
+            0x1A => Instr::Drop,
+            0x1B => Instr::Select,
+            0x20 => Instr::LocalGet(LocalIdx { idx: self.u32()? }),
+            0x21 => Instr::LocalSet(LocalIdx { idx: self.u32()? }),
+            0x22 => Instr::LocalTee(LocalIdx { idx: self.u32()? }),
+            0x23 => Instr::GlobalGet(GlobalIdx { idx: self.u32()? }),
+            0x24 => Instr::GlobalSet(GlobalIdx { idx: self.u32()? }),
+            opcode @ 0x28..=0x35 => Instr::Load(opcode, self.memarg()?),
+            opcode @ 0x36..=0x3E => Instr::Store(opcode, self.memarg()?),
+            0x3F => Instr::MemorySize(self.memidx()?),
+            0x40 => Instr::MemoryGrow(self.memidx()?),
+            0x41 => Instr::I32Const(self.leb(32, true)? as i32),
+            0x42 => Instr::I64Const(self.leb(64, true)? as i64),
+            0x43 => Instr::F32Const(f32::from_le_bytes(self.bytes::<4>()?)),
+            0x44 => Instr::F64Const(f64::from_le_bytes(self.bytes::<8>()?)),
+            opcode @ 0x45..=0xBF => Instr::Numeric(opcode),
+            0xFC => match self.u32()? {
+                0x0A => Instr::MemoryCopy(self.memidx()?, self.memidx()?),
+                0x0B => Instr::MemoryFill(self.memidx()?),
+                _ => return self.error(offset, "unsupported instruction"),
+            },
+            _ => return self.error(offset, "unsupported instruction"),
+        })
+    }
+}
+
+impl<'a> Iterator for InstrReader<'a> {
+    type Item = Result<(usize, Instr), DecodeError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos == self.bytecode.len() {
+            return None;
+        }
+        let offset: usize = self.pos;
+        let ret = self.instr(offset).map(|instr| (offset, instr));
+        self.failed = ret.is_err();
+        Some(ret)
+    }
+}
+
+/**
+ * Decodes the local declarations at the start of a function body (as produced by `CodeBuilder::build()`).
+ * Returns the types of the locals (excluding the params), and the offset where the expression starts.
+ */
+pub fn decode_locals(body: &[u8]) -> Result<(Vec<ValType>, usize), DecodeError> {
+    let mut reader = InstrReader::new(body, 0);
+    let num_groups: u32 = reader.u32()?;
+    let mut locals: Vec<ValType> = Vec::new();
+    for _ in 0..num_groups {
+        let offset: usize = reader.pos;
+        let count: u32 = reader.u32()?;
+        let valtype: ValType = reader.valtype(offset)?;
+        if locals.len() + count as usize > u32::MAX as usize {
+            return reader.error(offset, "too many locals");
+        }
+        locals.resize(locals.len() + count as usize, valtype);
+    }
+    Ok((locals, reader.pos))
+}
+
+/**
+ * Returns the name of a value type in the text format.
+ */
+pub fn valtype_name(valtype: ValType) -> &'static str {
+    match valtype {
+        ValType::I32 => "i32",
+        ValType::I64 => "i64",
+        ValType::F32 => "f32",
+        ValType::F64 => "f64",
+    }
+}
+
+/**
+ * Returns the name, the type of the value loaded or stored, and the natural alignment (as a logarithm) of a load or store instruction.
+ */
+pub fn memory_op_info(opcode: u8) -> (&'static str, ValType, u32) {
+    match opcode {
+        0x28 => ("i32.load", ValType::I32, 2),
+        0x29 => ("i64.load", ValType::I64, 3),
+        0x2A => ("f32.load", ValType::F32, 2),
+        0x2B => ("f64.load", ValType::F64, 3),
+        0x2C => ("i32.load8_s", ValType::I32, 0),
+        0x2D => ("i32.load8_u", ValType::I32, 0),
+        0x2E => ("i32.load16_s", ValType::I32, 1),
+        0x2F => ("i32.load16_u", ValType::I32, 1),
+        0x30 => ("i64.load8_s", ValType::I64, 0),
+        0x31 => ("i64.load8_u", ValType::I64, 0),
+        0x32 => ("i64.load16_s", ValType::I64, 1),
+        0x33 => ("i64.load16_u", ValType::I64, 1),
+        0x34 => ("i64.load32_s", ValType::I64, 2),
+        0x35 => ("i64.load32_u", ValType::I64, 2),
+        0x36 => ("i32.store", ValType::I32, 2),
+        0x37 => ("i64.store", ValType::I64, 3),
+        0x38 => ("f32.store", ValType::F32, 2),
+        0x39 => ("f64.store", ValType::F64, 3),
+        0x3A => ("i32.store8", ValType::I32, 0),
+        0x3B => ("i32.store16", ValType::I32, 1),
+        0x3C => ("i64.store8", ValType::I64, 0),
+        0x3D => ("i64.store16", ValType::I64, 1),
+        0x3E => ("i64.store32", ValType::I64, 2),
+        _ => panic!("not a memory instruction"),
+    }
+}
+
+// Names of the numeric instructions, starting from opcode 0x45.
+const NUMERIC_OP_NAMES: [&str; 0xC0 - 0x45] = [
+    "i32.eqz",
+    "i32.eq",
+    "i32.ne",
+    "i32.lt_s",
+    "i32.lt_u",
+    "i32.gt_s",
+    "i32.gt_u",
+    "i32.le_s",
+    "i32.le_u",
+    "i32.ge_s",
+    "i32.ge_u",
+    "i64.eqz",
+    "i64.eq",
+    "i64.ne",
+    "i64.lt_s",
+    "i64.lt_u",
+    "i64.gt_s",
+    "i64.gt_u",
+    "i64.le_s",
+    "i64.le_u",
+    "i64.ge_s",
+    "i64.ge_u",
+    "f32.eq",
+    "f32.ne",
+    "f32.lt",
+    "f32.gt",
+    "f32.le",
+    "f32.ge",
+    "f64.eq",
+    "f64.ne",
+    "f64.lt",
+    "f64.gt",
+    "f64.le",
+    "f64.ge",
+    "i32.clz",
+    "i32.ctz",
+    "i32.popcnt",
+    "i32.add",
+    "i32.sub",
+    "i32.mul",
+    "i32.div_s",
+    "i32.div_u",
+    "i32.rem_s",
+    "i32.rem_u",
+    "i32.and",
+    "i32.or",
+    "i32.xor",
+    "i32.shl",
+    "i32.shr_s",
+    "i32.shr_u",
+    "i32.rotl",
+    "i32.rotr",
+    "i64.clz",
+    "i64.ctz",
+    "i64.popcnt",
+    "i64.add",
+    "i64.sub",
+    "i64.mul",
+    "i64.div_s",
+    "i64.div_u",
+    "i64.rem_s",
+    "i64.rem_u",
+    "i64.and",
+    "i64.or",
+    "i64.xor",
+    "i64.shl",
+    "i64.shr_s",
+    "i64.shr_u",
+    "i64.rotl",
+    "i64.rotr",
+    "f32.abs",
+    "f32.neg",
+    "f32.ceil",
+    "f32.floor",
+    "f32.trunc",
+    "f32.nearest",
+    "f32.sqrt",
+    "f32.add",
+    "f32.sub",
+    "f32.mul",
+    "f32.div",
+    "f32.min",
+    "f32.max",
+    "f32.copysign",
+    "f64.abs",
+    "f64.neg",
+    "f64.ceil",
+    "f64.floor",
+    "f64.trunc",
+    "f64.nearest",
+    "f64.sqrt",
+    "f64.add",
+    "f64.sub",
+    "f64.mul",
+    "f64.div",
+    "f64.min",
+    "f64.max",
+    "f64.copysign",
+    "i32.wrap_i64",
+    "i32.trunc_f32_s",
+    "i32.trunc_f32_u",
+    "i32.trunc_f64_s",
+    "i32.trunc_f64_u",
+    "i64.extend_i32_s",
+    "i64.extend_i32_u",
+    "i64.trunc_f32_s",
+    "i64.trunc_f32_u",
+    "i64.trunc_f64_s",
+    "i64.trunc_f64_u",
+    "f32.convert_i32_s",
+    "f32.convert_i32_u",
+    "f32.convert_i64_s",
+    "f32.convert_i64_u",
+    "f32.demote_f64",
+    "f64.convert_i32_s",
+    "f64.convert_i32_u",
+    "f64.convert_i64_s",
+    "f64.convert_i64_u",
+    "f64.promote_f32",
+    "i32.reinterpret_f32",
+    "i64.reinterpret_f64",
+    "f32.reinterpret_i32",
+    "f64.reinterpret_i64",
+];
+
+/**
+ * Returns the name, the param types, and the result type of a numeric instruction (opcodes 0x45 to 0xBF).
+ */
+pub fn numeric_op_info(opcode: u8) -> (&'static str, &'static [ValType], ValType) {
+    const I32: ValType = ValType::I32;
+    const I64: ValType = ValType::I64;
+    const F32: ValType = ValType::F32;
+    const F64: ValType = ValType::F64;
+    let (params, result): (&'static [ValType], ValType) = match opcode {
+        0x45 => (&[I32], I32),
+        0x46..=0x4F => (&[I32, I32], I32),
+        0x50 => (&[I64], I32),
+        0x51..=0x5A => (&[I64, I64], I32),
+        0x5B..=0x60 => (&[F32, F32], I32),
+        0x61..=0x66 => (&[F64, F64], I32),
+        0x67..=0x69 => (&[I32], I32),
+        0x6A..=0x78 => (&[I32, I32], I32),
+        0x79..=0x7B => (&[I64], I64),
+        0x7C..=0x8A => (&[I64, I64], I64),
+        0x8B..=0x91 => (&[F32], F32),
+        0x92..=0x98 => (&[F32, F32], F32),
+        0x99..=0x9F => (&[F64], F64),
+        0xA0..=0xA6 => (&[F64, F64], F64),
+        0xA7 => (&[I64], I32),
+        0xA8 | 0xA9 => (&[F32], I32),
+        0xAA | 0xAB => (&[F64], I32),
+        0xAC | 0xAD => (&[I32], I64),
+        0xAE | 0xAF => (&[F32], I64),
+        0xB0 | 0xB1 => (&[F64], I64),
+        0xB2 | 0xB3 => (&[I32], F32),
+        0xB4 | 0xB5 => (&[I64], F32),
+        0xB6 => (&[F64], F32),
+        0xB7 | 0xB8 => (&[I32], F64),
+        0xB9 | 0xBA => (&[I64], F64),
+        0xBB => (&[F32], F64),
+        0xBC => (&[F32], I32),
+        0xBD => (&[F64], I64),
+        0xBE => (&[I32], F32),
+        0xBF => (&[I64], F64),
+        _ => panic!("not a numeric instruction"),
+    };
+    (NUMERIC_OP_NAMES[(opcode - 0x45) as usize], params, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut code_builder = CodeBuilder::new(FuncType::new(
+            Box::new([ValType::I32]),
+            Box::new([ValType::I64]),
+        ));
+        {
+            let (locals_builder, expr_builder) = code_builder.split();
+            let localidx_a = locals_builder.add(ValType::I64);
+            let localidx_b = locals_builder.add(ValType::I64);
+            locals_builder.add(ValType::F64);
+            expr_builder.block(&[ValType::I64]);
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.br_table(&[0, 1], 0);
+            expr_builder.end();
+            expr_builder.local_tee(localidx_a);
+            expr_builder.i64_const(-1234567890123);
+            expr_builder.i64_store(MemArg::new8(16));
+            expr_builder.f64_const(0.5);
+            expr_builder.i64_trunc_f64_s();
+            expr_builder.local_set(localidx_b);
+            expr_builder.memory_copy(MemIdx { idx: 0 }, MemIdx { idx: 0 });
+            expr_builder.end();
+        }
+        let (_functype, body) = code_builder.build();
+        let (locals, start) = decode_locals(&body).unwrap();
+        assert!(locals == vec![ValType::I64, ValType::I64, ValType::F64]);
+        let instrs: Vec<Instr> = InstrReader::new(&body, start)
+            .map(|res| res.unwrap().1)
+            .collect();
+        assert_eq!(
+            instrs,
+            vec![
+                Instr::Block(Some(ValType::I64)),
+                Instr::LocalGet(LocalIdx { idx: 0 }),
+                Instr::BrTable(Box::new([0, 1]), 0),
+                Instr::End,
+                Instr::LocalTee(LocalIdx { idx: 1 }),
+                Instr::I64Const(-1234567890123),
+                Instr::Store(0x37, MemArg::new8(16)),
+                Instr::F64Const(0.5),
+                Instr::Numeric(0xB0),
+                Instr::LocalSet(LocalIdx { idx: 2 }),
+                Instr::MemoryCopy(MemIdx { idx: 0 }, MemIdx { idx: 0 }),
+                Instr::End,
+            ]
+        );
+        assert_eq!(numeric_op_info(0xB0).0, "i64.trunc_f64_s");
+    }
+
+    #[test]
+    fn errors() {
+        let err = InstrReader::new(&[0x01, 0x41], 0)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(err.offset, 2);
+        assert_eq!(err.message, "unexpected end of bytecode");
+        let err = InstrReader::new(&[0x01, 0xFF], 0)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(err.message, "unsupported instruction");
+    }
+}
@@ -1,4 +1,5 @@
 use projstd::searchablevec::SearchableVec;
+use std::collections::BTreeMap;
 use std::option::Option;
 /**
  * The structs here are equivalent to those in the WebAssembly spec here:
@@ -7,12 +8,20 @@ use std::option::Option;
 use std::vec::Vec;
 
 pub mod codewriter;
+pub mod decode;
+pub mod instr;
 pub mod scratch;
 pub mod serialize;
+pub mod sourcemap;
+pub mod validate;
+pub mod wat;
 pub mod write;
 pub use codewriter::*;
 pub use scratch::*;
 pub use serialize::*;
+pub use sourcemap::*;
+pub use validate::*;
+pub use wat::*;
 pub use write::*;
 
 #[derive(Default)]
@@ -28,6 +37,8 @@ pub struct WasmModule {
     elem_section: ElemSection,
     code_section: CodeSection,
     data_section: DataSection,
+    name_section: NameSection, // custom section for debugging (https://webassembly.github.io/spec/core/appendix/custom.html#name-section)
+    source_map_url: Option<String>, // custom section that tells debuggers where to find the source map
 }
 
 pub trait Insert<T> {
@@ -44,7 +55,7 @@ pub struct FuncType {
     result_types: Box<[ValType]>,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash)]
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub enum ValType {
     I32,
     I64,
@@ -189,6 +200,7 @@ pub struct Code {
     func: Option<Box<[u8]>>,
     // `func` is pre-serialized by the CodeWriter.
     // If `func` is None, then this function has been registered but not yet committed.
+    source_positions: SourcePositions, // (offset into `func`, source position of the code starting there), sorted by offset
 }
 
 #[derive(Default)]
@@ -202,32 +214,38 @@ pub struct Data {
     content: Box<[u8]>,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash)]
+#[derive(Default)]
+pub struct NameSection {
+    func_names: BTreeMap<u32, String>, // map from funcidx to name
+    local_names: BTreeMap<u32, BTreeMap<u32, String>>, // map from funcidx to (map from localidx to name)
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub struct TypeIdx {
     pub idx: u32,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash)]
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub struct FuncIdx {
     pub idx: u32,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash)]
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub struct TableIdx {
     pub idx: u32,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash)]
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub struct MemIdx {
     pub idx: u32,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash)]
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub struct GlobalIdx {
     pub idx: u32,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash)]
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub struct LocalIdx {
     pub idx: u32,
 }
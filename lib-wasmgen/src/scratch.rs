@@ -89,6 +89,10 @@ impl<'a> Scratch<'a> {
             ValType::F64 => self.pop_f64(),
         }
     }
+    // Names a scratch local for debugging (see LocalsManager::add_name()).
+    pub fn add_name(&mut self, localidx: LocalIdx, name: &str) {
+        self.locals_builder.add_name(localidx, name);
+    }
     fn push_impl(
         locals_builder: &mut LocalsManager,
         valtype: ValType,
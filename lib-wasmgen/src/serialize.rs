@@ -42,6 +42,22 @@ pub trait LebSerialize5Byte {
 
 impl WasmSerialize for WasmModule {
     fn wasm_serialize<Rec>(&self, receiver: &mut Rec)
+    where
+        for<'a> Rec: std::iter::Extend<&'a u8>,
+    {
+        self.serialize_before_code(receiver);
+        self.code_section.wasm_serialize(receiver);
+        self.data_section.wasm_serialize(receiver);
+        self.name_section.wasm_serialize(receiver);
+        if let Some(url) = &self.source_map_url {
+            serialize_custom_section("sourceMappingURL", url, receiver);
+        }
+    }
+}
+
+impl WasmModule {
+    // Serializes everything before the code section.
+    fn serialize_before_code<Rec>(&self, receiver: &mut Rec)
     where
         for<'a> Rec: std::iter::Extend<&'a u8>,
     {
@@ -56,9 +72,42 @@ impl WasmSerialize for WasmModule {
         self.export_section.wasm_serialize(receiver);
         self.start_section.wasm_serialize(receiver);
         self.elem_section.wasm_serialize(receiver);
-        self.code_section.wasm_serialize(receiver);
-        self.data_section.wasm_serialize(receiver);
     }
+    /**
+     * Returns the offset of each function body (after its size) in the serialized module, in the order of the code section.
+     * This must be kept in sync with the serialization of the module and the code section.
+     */
+    pub(crate) fn code_offsets(&self) -> Vec<u32> {
+        let mut prefix = Vec::<u8>::new();
+        self.serialize_before_code(&mut prefix);
+        let mut content = Vec::<u8>::new();
+        (self.code_section.content.len() as u32).leb_serialize(&mut content);
+        let mut offsets: Vec<u32> = Vec::new();
+        for code in &self.code_section.content {
+            let bytes: &[u8] = code.func.as_deref().unwrap_or(&[]);
+            (bytes.len() as u32).leb_serialize(&mut content);
+            offsets.push(content.len() as u32);
+            content.extend(bytes);
+        }
+        let mut header = vec![10u8]; // 10u8: the magic value for Code Section
+        (content.len() as u32).leb_serialize(&mut header);
+        let base = (prefix.len() + header.len()) as u32;
+        offsets.into_iter().map(|offset| base + offset).collect()
+    }
+}
+
+// Serializes a custom section whose content is a single string.
+fn serialize_custom_section<Rec>(name: &str, content: &str, receiver: &mut Rec)
+where
+    for<'a> Rec: std::iter::Extend<&'a u8>,
+{
+    let mut buf = Vec::<u8>::new();
+    name.wasm_serialize(&mut buf);
+    content.wasm_serialize(&mut buf);
+    // 0u8: the magic value for Custom Section
+    receiver.extend(&[0u8]);
+    (buf.len() as u32).leb_serialize(receiver);
+    receiver.extend(&buf);
 }
 
 impl WasmSerialize for TypeSection {
@@ -535,6 +584,54 @@ impl<T> WasmContainerSerialize<T> for Vec<T> {
     }
 }
 
+impl WasmSerialize for NameSection {
+    fn wasm_serialize<Rec>(&self, receiver: &mut Rec)
+    where
+        for<'a> Rec: std::iter::Extend<&'a u8>,
+    {
+        if self.func_names.is_empty() && self.local_names.is_empty() {
+            return;
+        }
+        let mut buf = Vec::<u8>::new();
+        "name".wasm_serialize(&mut buf);
+        if !self.func_names.is_empty() {
+            buf.push(1u8); // 1u8: the id of the function names subsection
+            serialize_section_content(&self.func_names, &mut buf);
+        }
+        if !self.local_names.is_empty() {
+            buf.push(2u8); // 2u8: the id of the local names subsection
+            serialize_section_content(&self.local_names, &mut buf);
+        }
+        // 0u8: the magic value for Custom Section
+        receiver.extend(&[0u8]);
+        (buf.len() as u32).leb_serialize(receiver);
+        receiver.extend(&buf);
+    }
+}
+
+// Serializes a name map or an indirect name map (the keys are already sorted, as required by the name section).
+impl<T: WasmSerialize> WasmSerialize for BTreeMap<u32, T> {
+    fn wasm_serialize<Rec>(&self, receiver: &mut Rec)
+    where
+        for<'a> Rec: std::iter::Extend<&'a u8>,
+    {
+        (self.len() as u32).leb_serialize(receiver);
+        for (idx, value) in self {
+            idx.leb_serialize(receiver);
+            value.wasm_serialize(receiver);
+        }
+    }
+}
+
+impl WasmSerialize for String {
+    fn wasm_serialize<Rec>(&self, receiver: &mut Rec)
+    where
+        for<'a> Rec: std::iter::Extend<&'a u8>,
+    {
+        self.as_str().wasm_serialize(receiver);
+    }
+}
+
 impl WasmSerialize for str {
     fn wasm_serialize<Rec>(&self, receiver: &mut Rec)
     where
@@ -692,6 +789,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn wasm_serialize_name_section() {
+        assert_eq!(wasm_serializer_wrapper(&NameSection::default()), []);
+        let mut local_names = BTreeMap::new();
+        local_names.insert(1, "x/y".to_string());
+        local_names.insert(0, "n".to_string());
+        let mut name_section = NameSection::default();
+        name_section.func_names.insert(3, "f".to_string());
+        name_section.local_names.insert(3, local_names);
+        assert_eq!(
+            wasm_serializer_wrapper(&name_section),
+            [
+                0, 24, // custom section
+                4, b'n', b'a', b'm', b'e', // section name
+                1, 4, 1, 3, 1, b'f', // function names: [3 => "f"]
+                2, 11, 1, 3, 2, 0, 1, b'n', 1, 3, b'x', b'/',
+                b'y' // local names: [3 => [0 => "n", 1 => "x/y"]]
+            ]
+        );
+    }
+
     #[test]
     fn leb_serialize_unsigned() {
         assert_eq!(leb_serializer_wrapper(0u32), [0]);
@@ -0,0 +1,170 @@
+/**
+ * Source maps (https://sourcemaps.info/spec.html) from the generated code back to the source code.
+ * Code generators give each instruction a source position via `ExprBuilder::set_source_position()`,
+ * and `WasmModule::source_map()` writes them out as a source map in the convention used by browsers for WebAssembly:
+ * there is only one line, and the generated column is the byte offset of the instruction in the serialized module.
+ */
+use super::*;
+
+/**
+ * A position in the source code.  Unlike positions in ESTree, lines and columns are both zero-based.
+ * `source` is an index into the list of source files given to `WasmModule::source_map()`.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub source: u32,
+    pub line: u32,
+    pub column: u32,
+}
+
+// (offset into the function body, source position of the code starting there), sorted by offset
+pub(crate) type SourcePositions = Box<[(u32, Option<SourcePosition>)]>;
+
+impl WasmModule {
+    /**
+     * Returns the source map of the code in this module as JSON, where `sources` are the names of the source files.
+     * Code that is not given any source position is left unmapped.
+     * All registered functions must have been committed.
+     */
+    pub fn source_map(&self, sources: &[&str]) -> String {
+        let mut mappings = String::new();
+        let mut prev_offset: i64 = 0;
+        let mut prev_position = SourcePosition {
+            source: 0,
+            line: 0,
+            column: 0,
+        };
+        let mut add_segment = |offset: u32, opt_position: Option<SourcePosition>| {
+            if !mappings.is_empty() {
+                mappings.push(',');
+            }
+            // all the fields are relative to the previous segment
+            encode_vlq(offset as i64 - prev_offset, &mut mappings);
+            prev_offset = offset as i64;
+            if let Some(position) = opt_position {
+                encode_vlq(
+                    position.source as i64 - prev_position.source as i64,
+                    &mut mappings,
+                );
+                encode_vlq(
+                    position.line as i64 - prev_position.line as i64,
+                    &mut mappings,
+                );
+                encode_vlq(
+                    position.column as i64 - prev_position.column as i64,
+                    &mut mappings,
+                );
+                prev_position = position;
+            }
+        };
+        for (code, code_offset) in self.code_section.content.iter().zip(self.code_offsets()) {
+            for (offset, opt_position) in code.source_positions.iter() {
+                add_segment(code_offset + offset, *opt_position);
+            }
+            // don't let the last position of this function continue into the next function
+            if let Some((_, Some(_))) = code.source_positions.last() {
+                let len = code.func.as_ref().map_or(0, |bytes| bytes.len());
+                add_segment(code_offset + len as u32, None);
+            }
+        }
+
+        let sources_json: Vec<String> = sources.iter().map(|s| json_string(s)).collect();
+        format!(
+            "{{\"version\":3,\"sources\":[{}],\"names\":[],\"mappings\":{}}}",
+            sources_json.join(","),
+            json_string(&mappings)
+        )
+    }
+}
+
+// Appends the base64 VLQ encoding of `value` (as used by source maps).
+fn encode_vlq(value: i64, out: &mut String) {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    // the sign is stored in the least significant bit
+    let mut vlq: u64 = if value < 0 {
+        ((-value as u64) << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (vlq & 0b11111) as usize;
+        vlq >>= 5;
+        if vlq != 0 {
+            digit |= 0b100000; // continuation bit
+        }
+        out.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+// Returns `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vlq() {
+        let mut out = String::new();
+        for value in &[0, 1, -1, 15, 16, -16, 1000] {
+            encode_vlq(*value, &mut out);
+            out.push(' ');
+        }
+        assert_eq!(out, "A C D e gB hB w+B ");
+    }
+
+    #[test]
+    fn source_map() {
+        let mut wasm_module = WasmModule::default();
+        let functype = FuncType::new(Box::new([]), Box::new([ValType::I32]));
+        let (_, funcidx) = wasm_module.register_func(&functype);
+        let mut code_builder = CodeBuilder::new(functype);
+        {
+            let expr_builder = code_builder.expr_builder();
+            expr_builder.set_source_position(Some(SourcePosition {
+                source: 0,
+                line: 2,
+                column: 4,
+            }));
+            expr_builder.i32_const(1);
+            // positions without any code are dropped
+            expr_builder.set_source_position(None);
+            expr_builder.set_source_position(Some(SourcePosition {
+                source: 0,
+                line: 3,
+                column: 0,
+            }));
+            expr_builder.i32_const(2);
+            expr_builder.i32_add();
+            expr_builder.set_source_position(None);
+            expr_builder.end();
+        }
+        wasm_module.commit_func(funcidx, code_builder);
+
+        // the module is: header (8 bytes), type section (7 bytes), function section (4 bytes),
+        // then the code section header (2 bytes), the number of bodies (1 byte), the body size (1 byte) and the (empty) locals (1 byte)
+        let mut bytes = Vec::<u8>::new();
+        wasm_module.wasm_serialize(&mut bytes);
+        assert_eq!(bytes[24], 0x41); // i32.const
+        assert_eq!(
+            wasm_module.source_map(&["prog.js"]),
+            r#"{"version":3,"sources":["prog.js"],"names":[],"mappings":"wBAEI,EACJ,G"}"#
+        );
+    }
+}
@@ -0,0 +1,827 @@
+/**
+ * Validation of a `WasmModule`, following the validation algorithm in the appendix of the WebAssembly spec.
+ * This type-checks the operand stack of every function body, and checks block types, branch targets,
+ * local/global/function/type indices, memory arguments, and the other sections against the module.
+ *
+ * It is meant for catching bugs in code generators, so it reports the offending function and the byte offset of the instruction
+ * (relative to the start of the function body, i.e. the local declarations are at offset 0)
+ * instead of leaving it to the wasm engine to reject the whole module at instantiation time.
+ */
+use super::instr::*;
+use super::*;
+use std::fmt;
+
+const WASM_PAGE_SIZE: u64 = 65536;
+
+/**
+ * An error found by the validator.
+ * `funcidx` is the function that contains the error (None if the error is in another section),
+ * and `offset` is the byte offset of the offending instruction in the function body.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub funcidx: Option<FuncIdx>,
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.funcidx {
+            None => write!(f, "module: {}", self.message),
+            Some(funcidx) => write!(
+                f,
+                "func {} at offset {}: {}",
+                funcidx.idx, self.offset, self.message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl WasmModule {
+    /**
+     * Validates the whole module, and returns all the errors that were found.
+     * At most one error is reported for each function body, since the operand stack is meaningless after the first error.
+     */
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let ctx = Context::new(self);
+        let mut errors: Vec<ValidationError> = Vec::new();
+        ctx.validate_sections(&mut |message| {
+            errors.push(ValidationError {
+                funcidx: None,
+                offset: 0,
+                message: message,
+            })
+        });
+        for (i, code) in self.code_section.content.iter().enumerate() {
+            let funcidx = FuncIdx {
+                idx: self.func_section.idx_offset + i as u32,
+            };
+            let result = match &code.func {
+                None => Err((0, "function was registered but never committed".to_string())),
+                Some(body) => ctx.validate_func(self.func_section.content[i], body),
+            };
+            if let Err((offset, message)) = result {
+                errors.push(ValidationError {
+                    funcidx: Some(funcidx),
+                    offset: offset,
+                    message: message,
+                });
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn limits_min(limits: Limits) -> u32 {
+    match limits {
+        Limits::Unbounded { min } => min,
+        Limits::Bounded { min, max: _ } => min,
+    }
+}
+
+// The index spaces of the module (imports come before the definitions in each index space).
+struct Context<'a> {
+    module: &'a WasmModule,
+    types: &'a [FuncType],
+    funcs: Vec<TypeIdx>,
+    tables: Vec<TableType>,
+    mems: Vec<MemType>,
+    globals: Vec<GlobalType>,
+}
+
+impl<'a> Context<'a> {
+    fn new(module: &'a WasmModule) -> Context<'a> {
+        let mut ctx = Context {
+            module: module,
+            types: module.type_section.content.vec(),
+            funcs: Vec::new(),
+            tables: Vec::new(),
+            mems: Vec::new(),
+            globals: Vec::new(),
+        };
+        for import in &module.import_section.content {
+            match import.desc {
+                ImportDesc::Func(typeidx) => ctx.funcs.push(typeidx),
+                ImportDesc::Table(table_type) => ctx.tables.push(table_type),
+                ImportDesc::Mem(mem_type) => ctx.mems.push(mem_type),
+                ImportDesc::Global(global_type) => ctx.globals.push(global_type),
+            }
+        }
+        ctx.funcs
+            .extend(module.func_section.content.iter().copied());
+        ctx.tables
+            .extend(module.table_section.content.iter().map(|t| t.table_type));
+        ctx.mems
+            .extend(module.mem_section.content.iter().map(|m| m.mem_type));
+        ctx.globals
+            .extend(module.global_section.content.iter().map(|g| g.global_type));
+        ctx
+    }
+
+    fn func_type(&self, funcidx: FuncIdx) -> Result<&'a FuncType, String> {
+        let typeidx: TypeIdx = *self
+            .funcs
+            .get(funcidx.idx as usize)
+            .ok_or_else(|| format!("function {} does not exist", funcidx.idx))?;
+        self.type_at(typeidx)
+    }
+
+    fn type_at(&self, typeidx: TypeIdx) -> Result<&'a FuncType, String> {
+        self.types
+            .get(typeidx.idx as usize)
+            .ok_or_else(|| format!("type {} does not exist", typeidx.idx))
+    }
+
+    fn check_table(&self, tableidx: TableIdx) -> Result<(), String> {
+        if (tableidx.idx as usize) < self.tables.len() {
+            Ok(())
+        } else {
+            Err(format!("table {} does not exist", tableidx.idx))
+        }
+    }
+
+    fn check_mem(&self, memidx: MemIdx) -> Result<(), String> {
+        if (memidx.idx as usize) < self.mems.len() {
+            Ok(())
+        } else {
+            Err(format!("memory {} does not exist", memidx.idx))
+        }
+    }
+
+    // Checks that the expression is a constant expression of the given type, and returns its value if it is an i32.const.
+    fn check_const_expr(&self, expr: &Expr, valtype: ValType) -> Result<Option<i32>, String> {
+        let instrs: Vec<Instr> = InstrReader::new(&expr.bytecode, 0)
+            .map(|res| res.map(|(_, instr)| instr))
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        let (actual, value): (ValType, Option<i32>) = match instrs.as_slice() {
+            [Instr::I32Const(val), Instr::End] => (ValType::I32, Some(*val)),
+            [Instr::I64Const(_), Instr::End] => (ValType::I64, None),
+            [Instr::F32Const(_), Instr::End] => (ValType::F32, None),
+            [Instr::F64Const(_), Instr::End] => (ValType::F64, None),
+            [Instr::GlobalGet(globalidx), Instr::End] => {
+                // only imported globals may be used in constant expressions
+                if globalidx.idx >= self.module.global_section.idx_offset {
+                    return Err(format!(
+                        "constant expression refers to global {}, which is not imported",
+                        globalidx.idx
+                    ));
+                }
+                let global_type: GlobalType = self.globals[globalidx.idx as usize];
+                if global_type.mutability != Mut::Const {
+                    return Err(format!(
+                        "constant expression refers to mutable global {}",
+                        globalidx.idx
+                    ));
+                }
+                (global_type.val_type, None)
+            }
+            _ => return Err("not a constant expression".to_string()),
+        };
+        if actual != valtype {
+            return Err(format!(
+                "constant expression has type {} but {} is expected",
+                valtype_name(actual),
+                valtype_name(valtype)
+            ));
+        }
+        Ok(value)
+    }
+
+    fn validate_sections(&self, report: &mut dyn FnMut(String)) {
+        if self.tables.len() > 1 {
+            report("there can be at most one table".to_string());
+        }
+        if self.mems.len() > 1 {
+            report("there can be at most one memory".to_string());
+        }
+        for (i, typeidx) in self.funcs.iter().enumerate() {
+            if let Err(message) = self.type_at(*typeidx) {
+                report(format!("function {}: {}", i, message));
+            }
+        }
+        for (i, global) in self.module.global_section.content.iter().enumerate() {
+            let globalidx: u32 = self.module.global_section.idx_offset + i as u32;
+            if let Err(message) =
+                self.check_const_expr(&global.init_expr, global.global_type.val_type)
+            {
+                report(format!("global {}: {}", globalidx, message));
+            }
+        }
+        let mut export_names: std::collections::HashSet<&str> = std::collections::HashSet::new();
+        for export in &self.module.export_section.content {
+            if !export_names.insert(&export.entity_name) {
+                report(format!("duplicate export name \"{}\"", export.entity_name));
+            }
+            let result: Result<(), String> = match export.desc {
+                ExportDesc::Func(funcidx) => self.func_type(funcidx).map(|_| ()),
+                ExportDesc::Table(tableidx) => self.check_table(tableidx),
+                ExportDesc::Mem(memidx) => self.check_mem(memidx),
+                ExportDesc::Global(globalidx) => {
+                    if (globalidx.idx as usize) < self.globals.len() {
+                        Ok(())
+                    } else {
+                        Err(format!("global {} does not exist", globalidx.idx))
+                    }
+                }
+            };
+            if let Err(message) = result {
+                report(format!("export \"{}\": {}", export.entity_name, message));
+            }
+        }
+        if let Some(funcidx) = self.module.start_section.start {
+            match self.func_type(funcidx) {
+                Ok(functype) => {
+                    if !functype.param_types.is_empty() || !functype.result_types.is_empty() {
+                        report("start function must have type [] -> []".to_string());
+                    }
+                }
+                Err(message) => report(format!("start function: {}", message)),
+            }
+        }
+        for elem in &self.module.elem_section.content {
+            let result: Result<(), String> = self.check_table(elem.table_idx).and_then(|_| {
+                for funcidx in elem.content.iter() {
+                    self.func_type(*funcidx)?;
+                }
+                let offset: Option<i32> = self.check_const_expr(&elem.offset, ValType::I32)?;
+                let table_size: u32 = limits_min(self.tables[elem.table_idx.idx as usize].limits);
+                match offset {
+                    Some(offset)
+                        if offset as u32 as u64 + elem.content.len() as u64 > table_size as u64 =>
+                    {
+                        Err(format!(
+                            "elements at offset {} do not fit in the table of size {}",
+                            offset as u32, table_size
+                        ))
+                    }
+                    _ => Ok(()),
+                }
+            });
+            if let Err(message) = result {
+                report(format!("element segment: {}", message));
+            }
+        }
+        for data in &self.module.data_section.content {
+            let result: Result<(), String> = self.check_mem(data.mem_idx).and_then(|_| {
+                let offset: Option<i32> = self.check_const_expr(&data.offset, ValType::I32)?;
+                let mem_size: u64 =
+                    limits_min(self.mems[data.mem_idx.idx as usize].limits) as u64 * WASM_PAGE_SIZE;
+                match offset {
+                    Some(offset) if offset as u32 as u64 + data.content.len() as u64 > mem_size => {
+                        Err(format!(
+                            "data at offset {} does not fit in the initial memory of {} bytes",
+                            offset as u32, mem_size
+                        ))
+                    }
+                    _ => Ok(()),
+                }
+            });
+            if let Err(message) = result {
+                report(format!("data segment: {}", message));
+            }
+        }
+    }
+
+    // Validates a function body, returning the offset of the offending instruction and the error message on failure.
+    fn validate_func(&self, typeidx: TypeIdx, body: &[u8]) -> Result<(), (usize, String)> {
+        let functype: &FuncType = self.type_at(typeidx).map_err(|message| (0, message))?;
+        let (locals, start) = decode_locals(body).map_err(|e| (e.offset, e.message))?;
+        let mut validator = FuncValidator {
+            ctx: self,
+            functype: functype,
+            locals: functype.param_types.iter().copied().chain(locals).collect(),
+            operands: Vec::new(),
+            ctrls: vec![CtrlFrame {
+                kind: CtrlKind::Func,
+                results: functype.result_types.to_vec(),
+                height: 0,
+                unreachable: false,
+            }],
+        };
+        let mut reader = InstrReader::new(body, start);
+        loop {
+            match reader.next() {
+                None => return Err((body.len(), "missing end of function".to_string())),
+                Some(Err(e)) => return Err((e.offset, e.message)),
+                Some(Ok((offset, instr))) => {
+                    validator
+                        .instr(instr)
+                        .map_err(|message| (offset, message))?;
+                    if validator.ctrls.is_empty() {
+                        // the function has ended
+                        return match reader.next() {
+                            None => Ok(()),
+                            Some(Ok((offset, _))) | Some(Err(DecodeError { offset, .. })) => Err((
+                                offset,
+                                "instructions after the end of the function".to_string(),
+                            )),
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum CtrlKind {
+    Func,
+    Block,
+    Loop,
+    If,
+    Else,
+}
+
+struct CtrlFrame {
+    kind: CtrlKind,
+    results: Vec<ValType>,
+    height: usize,     // height of the operand stack when the frame was entered
+    unreachable: bool, // true if the rest of the frame is unreachable (so the operand stack is polymorphic)
+}
+
+impl CtrlFrame {
+    // The types of the values carried by a branch to this frame.
+    fn label_types(&self) -> &[ValType] {
+        if self.kind == CtrlKind::Loop {
+            &[]
+        } else {
+            &self.results
+        }
+    }
+}
+
+// The state of the validation of a single function body.
+// Operand types are None when they are unknown (i.e. popped from the polymorphic stack of unreachable code).
+struct FuncValidator<'a, 'b> {
+    ctx: &'b Context<'a>,
+    functype: &'a FuncType,
+    locals: Vec<ValType>,
+    operands: Vec<Option<ValType>>,
+    ctrls: Vec<CtrlFrame>,
+}
+
+impl<'a, 'b> FuncValidator<'a, 'b> {
+    fn push(&mut self, valtype: ValType) {
+        self.operands.push(Some(valtype));
+    }
+
+    fn push_all(&mut self, valtypes: &[ValType]) {
+        self.operands.extend(valtypes.iter().map(|t| Some(*t)));
+    }
+
+    fn pop(&mut self) -> Result<Option<ValType>, String> {
+        let frame: &CtrlFrame = self.ctrls.last().unwrap();
+        if self.operands.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return Err("operand stack is empty".to_string());
+        }
+        Ok(self.operands.pop().unwrap())
+    }
+
+    fn pop_expect(&mut self, expected: ValType) -> Result<(), String> {
+        match self.pop() {
+            Ok(Some(actual)) if actual != expected => Err(format!(
+                "expected {} on the operand stack but found {}",
+                valtype_name(expected),
+                valtype_name(actual)
+            )),
+            Ok(_) => Ok(()),
+            Err(_) => Err(format!(
+                "expected {} on the operand stack but it is empty",
+                valtype_name(expected)
+            )),
+        }
+    }
+
+    fn pop_all(&mut self, expected: &[ValType]) -> Result<(), String> {
+        for valtype in expected.iter().rev() {
+            self.pop_expect(*valtype)?;
+        }
+        Ok(())
+    }
+
+    fn push_ctrl(&mut self, kind: CtrlKind, blocktype: Option<ValType>) {
+        self.ctrls.push(CtrlFrame {
+            kind: kind,
+            results: blocktype.into_iter().collect(),
+            height: self.operands.len(),
+            unreachable: false,
+        });
+    }
+
+    // Checks that the operand stack holds exactly the results of the innermost frame, and pops the frame.
+    fn pop_ctrl(&mut self) -> Result<CtrlFrame, String> {
+        let results: Vec<ValType> = self.ctrls.last().unwrap().results.clone();
+        self.pop_all(&results)?;
+        let frame: CtrlFrame = self.ctrls.pop().unwrap();
+        if self.operands.len() != frame.height {
+            return Err(format!(
+                "{} extra value(s) left on the operand stack at the end of the block",
+                self.operands.len() - frame.height
+            ));
+        }
+        Ok(frame)
+    }
+
+    fn set_unreachable(&mut self) {
+        let frame: &mut CtrlFrame = self.ctrls.last_mut().unwrap();
+        self.operands.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn label(&self, labelidx: u32) -> Result<Vec<ValType>, String> {
+        if labelidx as usize >= self.ctrls.len() {
+            return Err(format!(
+                "branch to label {}, which does not exist",
+                labelidx
+            ));
+        }
+        Ok(self.ctrls[self.ctrls.len() - 1 - labelidx as usize]
+            .label_types()
+            .to_vec())
+    }
+
+    fn local(&self, localidx: LocalIdx) -> Result<ValType, String> {
+        self.locals
+            .get(localidx.idx as usize)
+            .copied()
+            .ok_or_else(|| format!("local {} does not exist", localidx.idx))
+    }
+
+    fn global(&self, globalidx: GlobalIdx) -> Result<GlobalType, String> {
+        self.ctx
+            .globals
+            .get(globalidx.idx as usize)
+            .copied()
+            .ok_or_else(|| format!("global {} does not exist", globalidx.idx))
+    }
+
+    fn memarg(&self, opcode: u8, memarg: MemArg) -> Result<ValType, String> {
+        self.ctx.check_mem(MemIdx { idx: 0 })?;
+        let (name, valtype, natural_align) = memory_op_info(opcode);
+        if memarg.align > natural_align {
+            return Err(format!(
+                "alignment 2^{} of {} is larger than its natural alignment 2^{}",
+                memarg.align, name, natural_align
+            ));
+        }
+        Ok(valtype)
+    }
+
+    fn call(&mut self, functype: &FuncType) -> Result<(), String> {
+        self.pop_all(&functype.param_types)?;
+        self.push_all(&functype.result_types);
+        Ok(())
+    }
+
+    fn return_call(&mut self, functype: &FuncType) -> Result<(), String> {
+        if functype.result_types != self.functype.result_types {
+            return Err("tail call to a function with different results".to_string());
+        }
+        self.pop_all(&functype.param_types)?;
+        self.set_unreachable();
+        Ok(())
+    }
+
+    fn call_indirect_type(
+        &mut self,
+        typeidx: TypeIdx,
+        tableidx: TableIdx,
+    ) -> Result<&'a FuncType, String> {
+        self.ctx.check_table(tableidx)?;
+        let functype: &'a FuncType = self.ctx.type_at(typeidx)?;
+        self.pop_expect(ValType::I32)?;
+        Ok(functype)
+    }
+
+    fn instr(&mut self, instr: Instr) -> Result<(), String> {
+        match instr {
+            Instr::Unreachable => self.set_unreachable(),
+            Instr::Nop => {}
+            Instr::Block(blocktype) => self.push_ctrl(CtrlKind::Block, blocktype),
+            Instr::Loop(blocktype) => self.push_ctrl(CtrlKind::Loop, blocktype),
+            Instr::If(blocktype) => {
+                self.pop_expect(ValType::I32)?;
+                self.push_ctrl(CtrlKind::If, blocktype);
+            }
+            Instr::Else => {
+                if self.ctrls.last().unwrap().kind != CtrlKind::If {
+                    return Err("else without matching if".to_string());
+                }
+                let frame: CtrlFrame = self.pop_ctrl()?;
+                self.ctrls.push(CtrlFrame {
+                    kind: CtrlKind::Else,
+                    results: frame.results,
+                    height: frame.height,
+                    unreachable: false,
+                });
+            }
+            Instr::End => {
+                let frame: CtrlFrame = self.pop_ctrl()?;
+                if frame.kind == CtrlKind::If && !frame.results.is_empty() {
+                    return Err("if without else cannot have results".to_string());
+                }
+                self.push_all(&frame.results);
+            }
+            Instr::Br(labelidx) => {
+                let label_types: Vec<ValType> = self.label(labelidx)?;
+                self.pop_all(&label_types)?;
+                self.set_unreachable();
+            }
+            Instr::BrIf(labelidx) => {
+                self.pop_expect(ValType::I32)?;
+                let label_types: Vec<ValType> = self.label(labelidx)?;
+                self.pop_all(&label_types)?;
+                self.push_all(&label_types);
+            }
+            Instr::BrTable(labelidxs, default_labelidx) => {
+                self.pop_expect(ValType::I32)?;
+                let label_types: Vec<ValType> = self.label(default_labelidx)?;
+                for labelidx in labelidxs.iter() {
+                    if self.label(*labelidx)? != label_types {
+                        return Err(format!(
+                            "br_table targets label {} whose types differ from the default label",
+                            labelidx
+                        ));
+                    }
+                }
+                self.pop_all(&label_types)?;
+                self.set_unreachable();
+            }
+            Instr::Return => {
+                let functype: &FuncType = self.functype;
+                self.pop_all(&functype.result_types)?;
+                self.set_unreachable();
+            }
+            Instr::Call(funcidx) => {
+                let functype: &FuncType = self.ctx.func_type(funcidx)?;
+                self.call(functype)?;
+            }
+            Instr::CallIndirect(typeidx, tableidx) => {
+                let functype: &FuncType = self.call_indirect_type(typeidx, tableidx)?;
+                self.call(functype)?;
+            }
+            Instr::ReturnCall(funcidx) => {
+                let functype: &FuncType = self.ctx.func_type(funcidx)?;
+                self.return_call(functype)?;
+            }
+            Instr::ReturnCallIndirect(typeidx, tableidx) => {
+                let functype: &FuncType = self.call_indirect_type(typeidx, tableidx)?;
+                self.return_call(functype)?;
+            }
+            Instr::Drop => {
+                self.pop()?;
+            }
+            Instr::Select => {
+                self.pop_expect(ValType::I32)?;
+                let second: Option<ValType> = self.pop()?;
+                let first: Option<ValType> = self.pop()?;
+                match (first, second) {
+                    (Some(first), Some(second)) if first != second => {
+                        return Err(format!(
+                            "select operands have different types {} and {}",
+                            valtype_name(first),
+                            valtype_name(second)
+                        ))
+                    }
+                    _ => self.operands.push(first.or(second)),
+                }
+            }
+            Instr::LocalGet(localidx) => {
+                let valtype: ValType = self.local(localidx)?;
+                self.push(valtype);
+            }
+            Instr::LocalSet(localidx) => {
+                let valtype: ValType = self.local(localidx)?;
+                self.pop_expect(valtype)?;
+            }
+            Instr::LocalTee(localidx) => {
+                let valtype: ValType = self.local(localidx)?;
+                self.pop_expect(valtype)?;
+                self.push(valtype);
+            }
+            Instr::GlobalGet(globalidx) => {
+                let global_type: GlobalType = self.global(globalidx)?;
+                self.push(global_type.val_type);
+            }
+            Instr::GlobalSet(globalidx) => {
+                let global_type: GlobalType = self.global(globalidx)?;
+                if global_type.mutability != Mut::Var {
+                    return Err(format!("global {} is immutable", globalidx.idx));
+                }
+                self.pop_expect(global_type.val_type)?;
+            }
+            Instr::Load(opcode, memarg) => {
+                let valtype: ValType = self.memarg(opcode, memarg)?;
+                self.pop_expect(ValType::I32)?;
+                self.push(valtype);
+            }
+            Instr::Store(opcode, memarg) => {
+                let valtype: ValType = self.memarg(opcode, memarg)?;
+                self.pop_expect(valtype)?;
+                self.pop_expect(ValType::I32)?;
+            }
+            Instr::MemorySize(memidx) => {
+                self.ctx.check_mem(memidx)?;
+                self.push(ValType::I32);
+            }
+            Instr::MemoryGrow(memidx) => {
+                self.ctx.check_mem(memidx)?;
+                self.pop_expect(ValType::I32)?;
+                self.push(ValType::I32);
+            }
+            Instr::MemoryCopy(dest_memidx, src_memidx) => {
+                self.ctx.check_mem(dest_memidx)?;
+                self.ctx.check_mem(src_memidx)?;
+                self.pop_all(&[ValType::I32, ValType::I32, ValType::I32])?;
+            }
+            Instr::MemoryFill(memidx) => {
+                self.ctx.check_mem(memidx)?;
+                self.pop_all(&[ValType::I32, ValType::I32, ValType::I32])?;
+            }
+            Instr::I32Const(_) => self.push(ValType::I32),
+            Instr::I64Const(_) => self.push(ValType::I64),
+            Instr::F32Const(_) => self.push(ValType::F32),
+            Instr::F64Const(_) => self.push(ValType::F64),
+            Instr::Numeric(opcode) => {
+                let (name, params, result) = numeric_op_info(opcode);
+                self.pop_all(params)
+                    .map_err(|message| format!("{}: {}", name, message))?;
+                self.push(result);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a module with a memory, a table, an immutable global, and a function of the given type whose body is emitted by `f`.
+    fn module_with_func<F: FnOnce(&mut ExprBuilder)>(functype: FuncType, f: F) -> WasmModule {
+        let mut wasm_module = WasmModule::new_builder().build();
+        wasm_module.add_unbounded_memory(1);
+        wasm_module.get_or_add_table();
+        wasm_module.add_i32_global(Mut::Const, 0);
+        let (_, funcidx) = wasm_module.register_func(&functype);
+        let mut code_builder = CodeBuilder::new(functype);
+        code_builder.locals_builder().add(ValType::F64);
+        f(code_builder.expr_builder());
+        wasm_module.commit_func(funcidx, code_builder);
+        wasm_module
+    }
+
+    fn i32_to_i32() -> FuncType {
+        FuncType::new(Box::new([ValType::I32]), Box::new([ValType::I32]))
+    }
+
+    fn single_error(wasm_module: &WasmModule) -> ValidationError {
+        let mut errors: Vec<ValidationError> = wasm_module.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        errors.pop().unwrap()
+    }
+
+    #[test]
+    fn valid() {
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.block(&[ValType::I32]);
+            expr_builder.loop_(&[]);
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.br_if(0);
+            expr_builder.end();
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.i32_load(MemArg::new4(8));
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.br_if(0);
+            expr_builder.f64_const(1.5);
+            expr_builder.local_set(LocalIdx { idx: 1 });
+            expr_builder.unreachable();
+            expr_builder.i32_add(); // operands come from the polymorphic stack
+            expr_builder.end();
+            expr_builder.end();
+        });
+        assert!(wasm_module.validate().is_ok());
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.local_get(LocalIdx { idx: 0 }); // offset 3
+            expr_builder.local_get(LocalIdx { idx: 1 }); // offset 5
+            expr_builder.i32_add(); // offset 7
+            expr_builder.end();
+        });
+        let error: ValidationError = single_error(&wasm_module);
+        assert_eq!(error.funcidx, Some(FuncIdx { idx: 0 }));
+        assert_eq!(error.offset, 7);
+        assert_eq!(
+            error.to_string(),
+            "func 0 at offset 7: i32.add: expected i32 on the operand stack but found f64"
+        );
+    }
+
+    #[test]
+    fn block_results() {
+        // leaves an extra value at the end of the function
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.end();
+        });
+        assert!(single_error(&wasm_module)
+            .message
+            .contains("extra value(s) left"));
+        // if with a result but without else
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.if_(&[ValType::I32]);
+            expr_builder.i32_const(1);
+            expr_builder.end();
+            expr_builder.end();
+        });
+        assert_eq!(
+            single_error(&wasm_module).message,
+            "if without else cannot have results"
+        );
+        // branch to a label that does not exist
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.br(1);
+            expr_builder.end();
+        });
+        assert_eq!(
+            single_error(&wasm_module).message,
+            "branch to label 1, which does not exist"
+        );
+    }
+
+    #[test]
+    fn indices_and_memargs() {
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.local_get(LocalIdx { idx: 2 });
+            expr_builder.end();
+        });
+        assert_eq!(single_error(&wasm_module).message, "local 2 does not exist");
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.call(FuncIdx { idx: 5 });
+            expr_builder.end();
+        });
+        assert_eq!(
+            single_error(&wasm_module).message,
+            "function 5 does not exist"
+        );
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.global_set(GlobalIdx { idx: 0 });
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.end();
+        });
+        assert_eq!(single_error(&wasm_module).message, "global 0 is immutable");
+        let wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.i32_load16_u(MemArg::new4(0));
+            expr_builder.end();
+        });
+        assert_eq!(
+            single_error(&wasm_module).message,
+            "alignment 2^2 of i32.load16_u is larger than its natural alignment 2^1"
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let mut wasm_module = module_with_func(i32_to_i32(), |expr_builder| {
+            expr_builder.local_get(LocalIdx { idx: 0 });
+            expr_builder.end();
+        });
+        wasm_module.register_func(&i32_to_i32());
+        wasm_module.export_func(FuncIdx { idx: 0 }, "f".to_string());
+        wasm_module.export_func(FuncIdx { idx: 0 }, "f".to_string());
+        wasm_module.add_data(MemIdx { idx: 0 }, 65535, &[1, 2]);
+        let errors: Vec<String> = wasm_module
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "module: duplicate export name \"f\"",
+                "module: data segment: data at offset 65535 does not fit in the initial memory of 65536 bytes",
+                "func 1 at offset 0: function was registered but never committed",
+            ]
+        );
+    }
+}
@@ -0,0 +1,823 @@
+/**
+ * Printing of a `WasmModule` in the WebAssembly text format, for debugging code generators.
+ * Function bodies are decoded from the bytecode written by `CodeBuilder`, and can be printed either flat
+ * (one instruction per line, in the same order as the binary format) or folded (as S-expressions nested according to their operands).
+ *
+ * Functions are given identifiers from the name section where possible, and all indices are also printed as comments (e.g. `(func $fact (;3;) ...)`) to make them easy to look up.
+ */
+use super::instr::*;
+use super::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WatStyle {
+    Flat,
+    Folded,
+}
+
+impl WasmModule {
+    /**
+     * Returns the module in the WebAssembly text format.
+     * Bodies that were registered but never committed, or that cannot be decoded, are printed with a comment instead of failing.
+     */
+    pub fn to_wat(&self, style: WatStyle) -> String {
+        let types: &[FuncType] = self.type_section.content.vec();
+        let mut funcs: Vec<TypeIdx> = Vec::new();
+        let mut lines: Vec<String> = vec!["(module".to_string()];
+
+        for (i, functype) in types.iter().enumerate() {
+            lines.push(format!(
+                "  (type (;{};) (func{}))",
+                i,
+                signature_text(functype)
+            ));
+        }
+
+        let (mut num_tables, mut num_mems, mut num_globals): (u32, u32, u32) = (0, 0, 0);
+        for import in &self.import_section.content {
+            let desc: String = match import.desc {
+                ImportDesc::Func(typeidx) => {
+                    funcs.push(typeidx);
+                    format!(
+                        "(func{} (;{};) (type {}))",
+                        self.func_id_text(funcs.len() - 1),
+                        funcs.len() - 1,
+                        typeidx.idx
+                    )
+                }
+                ImportDesc::Table(table_type) => {
+                    num_tables += 1;
+                    format!(
+                        "(table (;{};) {})",
+                        num_tables - 1,
+                        table_type_text(table_type)
+                    )
+                }
+                ImportDesc::Mem(mem_type) => {
+                    num_mems += 1;
+                    format!(
+                        "(memory (;{};) {})",
+                        num_mems - 1,
+                        limits_text(mem_type.limits)
+                    )
+                }
+                ImportDesc::Global(global_type) => {
+                    num_globals += 1;
+                    format!(
+                        "(global (;{};) {})",
+                        num_globals - 1,
+                        global_type_text(global_type)
+                    )
+                }
+            };
+            lines.push(format!(
+                "  (import {} {} {})",
+                string_text(import.module_name.as_bytes()),
+                string_text(import.entity_name.as_bytes()),
+                desc
+            ));
+        }
+        funcs.extend(self.func_section.content.iter().copied());
+
+        for (i, code) in self.code_section.content.iter().enumerate() {
+            let typeidx: TypeIdx = self.func_section.content[i];
+            let functype: Option<&FuncType> = types.get(typeidx.idx as usize);
+            let funcidx: usize = self.func_section.idx_offset as usize + i;
+            lines.push(format!(
+                "  (func{} (;{};) (type {}){}",
+                self.func_id_text(funcidx),
+                funcidx,
+                typeidx.idx,
+                functype.map_or_else(String::new, signature_text)
+            ));
+            match (&code.func, functype) {
+                (None, _) => lines.push("    (; not committed ;)".to_string()),
+                (Some(_), None) => lines.push("    (; invalid type ;)".to_string()),
+                (Some(body), Some(functype)) => FuncPrinter {
+                    types: types,
+                    funcs: &funcs,
+                    functype: functype,
+                    lines: &mut lines,
+                }
+                .print(body, style),
+            }
+            lines.last_mut().unwrap().push(')');
+        }
+
+        for (i, table) in self.table_section.content.iter().enumerate() {
+            lines.push(format!(
+                "  (table (;{};) {})",
+                self.table_section.idx_offset as usize + i,
+                table_type_text(table.table_type)
+            ));
+        }
+        for (i, mem) in self.mem_section.content.iter().enumerate() {
+            lines.push(format!(
+                "  (memory (;{};) {})",
+                self.mem_section.idx_offset as usize + i,
+                limits_text(mem.mem_type.limits)
+            ));
+        }
+        for (i, global) in self.global_section.content.iter().enumerate() {
+            lines.push(format!(
+                "  (global (;{};) {} {})",
+                self.global_section.idx_offset as usize + i,
+                global_type_text(global.global_type),
+                const_expr_text(&global.init_expr)
+            ));
+        }
+        for export in &self.export_section.content {
+            let desc: String = match export.desc {
+                ExportDesc::Func(funcidx) => format!("(func {})", funcidx.idx),
+                ExportDesc::Table(tableidx) => format!("(table {})", tableidx.idx),
+                ExportDesc::Mem(memidx) => format!("(memory {})", memidx.idx),
+                ExportDesc::Global(globalidx) => format!("(global {})", globalidx.idx),
+            };
+            lines.push(format!(
+                "  (export {} {})",
+                string_text(export.entity_name.as_bytes()),
+                desc
+            ));
+        }
+        if let Some(funcidx) = self.start_section.start {
+            lines.push(format!("  (start {})", funcidx.idx));
+        }
+        for (i, elem) in self.elem_section.content.iter().enumerate() {
+            let table: String = if elem.table_idx.idx == 0 {
+                String::new()
+            } else {
+                format!(" (table {})", elem.table_idx.idx)
+            };
+            let content: String = elem
+                .content
+                .iter()
+                .map(|funcidx| format!(" {}", funcidx.idx))
+                .collect();
+            lines.push(format!(
+                "  (elem (;{};){} {} func{})",
+                i,
+                table,
+                const_expr_text(&elem.offset),
+                content
+            ));
+        }
+        for (i, data) in self.data_section.content.iter().enumerate() {
+            let mem: String = if data.mem_idx.idx == 0 {
+                String::new()
+            } else {
+                format!(" (memory {})", data.mem_idx.idx)
+            };
+            lines.push(format!(
+                "  (data (;{};){} {} {})",
+                i,
+                mem,
+                const_expr_text(&data.offset),
+                string_text(&data.content)
+            ));
+        }
+
+        lines.last_mut().unwrap().push(')');
+        let mut ret: String = lines.join("\n");
+        ret.push('\n');
+        ret
+    }
+}
+
+impl WasmModule {
+    // Returns the name of a function as an identifier (with a leading space), if it has a name that is a valid identifier.
+    // Names shared by several functions (e.g. shadowed Source functions) are not printed, since identifiers must be unique.
+    fn func_id_text(&self, funcidx: usize) -> String {
+        let func_names = &self.name_section.func_names;
+        match func_names.get(&(funcidx as u32)) {
+            Some(name)
+                if !name.is_empty()
+                    && name.chars().all(is_idchar)
+                    && func_names.values().filter(|other| *other == name).count() == 1 =>
+            {
+                format!(" ${}", name)
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+fn is_idchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c)
+}
+
+// Returns the params and results of a function type, e.g. " (param i32 i32) (result i32)".
+fn signature_text(functype: &FuncType) -> String {
+    let mut ret = String::new();
+    for (keyword, valtypes) in &[
+        ("param", &functype.param_types),
+        ("result", &functype.result_types),
+    ] {
+        if !valtypes.is_empty() {
+            ret.push_str(" (");
+            ret.push_str(keyword);
+            for valtype in valtypes.iter() {
+                ret.push(' ');
+                ret.push_str(valtype_name(*valtype));
+            }
+            ret.push(')');
+        }
+    }
+    ret
+}
+
+fn limits_text(limits: Limits) -> String {
+    match limits {
+        Limits::Unbounded { min } => format!("{}", min),
+        Limits::Bounded { min, max } => format!("{} {}", min, max),
+    }
+}
+
+fn table_type_text(table_type: TableType) -> String {
+    match table_type.elem_type {
+        ElemType::FuncRef => format!("{} funcref", limits_text(table_type.limits)),
+    }
+}
+
+fn global_type_text(global_type: GlobalType) -> String {
+    match global_type.mutability {
+        Mut::Const => valtype_name(global_type.val_type).to_string(),
+        Mut::Var => format!("(mut {})", valtype_name(global_type.val_type)),
+    }
+}
+
+// Returns a string literal, escaping everything that is not printable ASCII.
+fn string_text(bytes: &[u8]) -> String {
+    let mut ret = String::from("\"");
+    for b in bytes {
+        match b {
+            b'"' | b'\\' => {
+                ret.push('\\');
+                ret.push(*b as char);
+            }
+            0x20..=0x7E => ret.push(*b as char),
+            _ => ret.push_str(&format!("\\{:02x}", b)),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+// Returns a constant expression in folded form (without the final `end`).
+fn const_expr_text(expr: &Expr) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for res in InstrReader::new(&expr.bytecode, 0) {
+        match res {
+            Ok((_, Instr::End)) => {}
+            Ok((_, instr)) => parts.push(format!("({})", instr_text(&instr))),
+            Err(e) => parts.push(format!("(; cannot decode: {} ;)", e)),
+        }
+    }
+    parts.join(" ")
+}
+
+fn f32_text(val: f32) -> String {
+    if val.is_nan() {
+        let sign: &str = if val.is_sign_negative() { "-" } else { "" };
+        let payload: u32 = val.to_bits() & 0x7F_FFFF;
+        if payload == 0x40_0000 {
+            format!("{}nan", sign)
+        } else {
+            format!("{}nan:0x{:x}", sign, payload)
+        }
+    } else if val.is_infinite() {
+        if val < 0.0 { "-inf" } else { "inf" }.to_string()
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+fn f64_text(val: f64) -> String {
+    if val.is_nan() {
+        let sign: &str = if val.is_sign_negative() { "-" } else { "" };
+        let payload: u64 = val.to_bits() & 0xF_FFFF_FFFF_FFFF;
+        if payload == 0x8_0000_0000_0000 {
+            format!("{}nan", sign)
+        } else {
+            format!("{}nan:0x{:x}", sign, payload)
+        }
+    } else if val.is_infinite() {
+        if val < 0.0 { "-inf" } else { "inf" }.to_string()
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+fn blocktype_text(keyword: &str, blocktype: Option<ValType>) -> String {
+    match blocktype {
+        None => keyword.to_string(),
+        Some(valtype) => format!("{} (result {})", keyword, valtype_name(valtype)),
+    }
+}
+
+// Returns the offset and alignment of a load or store, omitting the defaults.
+fn memarg_text(opcode: u8, memarg: MemArg) -> String {
+    let (name, _, natural_align) = memory_op_info(opcode);
+    let mut ret: String = name.to_string();
+    if memarg.offset != 0 {
+        ret.push_str(&format!(" offset={}", memarg.offset));
+    }
+    if memarg.align != natural_align {
+        ret.push_str(&format!(" align={}", 1u64 << memarg.align));
+    }
+    ret
+}
+
+fn memidx_text(name: &str, memidxs: &[MemIdx]) -> String {
+    if memidxs.iter().all(|memidx| memidx.idx == 0) {
+        name.to_string()
+    } else {
+        let mut ret: String = name.to_string();
+        for memidx in memidxs {
+            ret.push_str(&format!(" {}", memidx.idx));
+        }
+        ret
+    }
+}
+
+fn call_indirect_text(name: &str, typeidx: TypeIdx, tableidx: TableIdx) -> String {
+    if tableidx.idx == 0 {
+        format!("{} (type {})", name, typeidx.idx)
+    } else {
+        format!("{} {} (type {})", name, tableidx.idx, typeidx.idx)
+    }
+}
+
+// Returns an instruction with its immediates.
+fn instr_text(instr: &Instr) -> String {
+    match instr {
+        Instr::Unreachable => "unreachable".to_string(),
+        Instr::Nop => "nop".to_string(),
+        Instr::Block(blocktype) => blocktype_text("block", *blocktype),
+        Instr::Loop(blocktype) => blocktype_text("loop", *blocktype),
+        Instr::If(blocktype) => blocktype_text("if", *blocktype),
+        Instr::Else => "else".to_string(),
+        Instr::End => "end".to_string(),
+        Instr::Br(labelidx) => format!("br {}", labelidx),
+        Instr::BrIf(labelidx) => format!("br_if {}", labelidx),
+        Instr::BrTable(labelidxs, default_labelidx) => {
+            let mut ret = String::from("br_table");
+            for labelidx in labelidxs.iter().chain(std::iter::once(default_labelidx)) {
+                ret.push_str(&format!(" {}", labelidx));
+            }
+            ret
+        }
+        Instr::Return => "return".to_string(),
+        Instr::Call(funcidx) => format!("call {}", funcidx.idx),
+        Instr::CallIndirect(typeidx, tableidx) => {
+            call_indirect_text("call_indirect", *typeidx, *tableidx)
+        }
+        Instr::ReturnCall(funcidx) => format!("return_call {}", funcidx.idx),
+        Instr::ReturnCallIndirect(typeidx, tableidx) => {
+            call_indirect_text("return_call_indirect", *typeidx, *tableidx)
+        }
+        Instr::Drop => "drop".to_string(),
+        Instr::Select => "select".to_string(),
+        Instr::LocalGet(localidx) => format!("local.get {}", localidx.idx),
+        Instr::LocalSet(localidx) => format!("local.set {}", localidx.idx),
+        Instr::LocalTee(localidx) => format!("local.tee {}", localidx.idx),
+        Instr::GlobalGet(globalidx) => format!("global.get {}", globalidx.idx),
+        Instr::GlobalSet(globalidx) => format!("global.set {}", globalidx.idx),
+        Instr::Load(opcode, memarg) | Instr::Store(opcode, memarg) => memarg_text(*opcode, *memarg),
+        Instr::MemorySize(memidx) => memidx_text("memory.size", &[*memidx]),
+        Instr::MemoryGrow(memidx) => memidx_text("memory.grow", &[*memidx]),
+        Instr::MemoryCopy(dest_memidx, src_memidx) => {
+            memidx_text("memory.copy", &[*dest_memidx, *src_memidx])
+        }
+        Instr::MemoryFill(memidx) => memidx_text("memory.fill", &[*memidx]),
+        Instr::I32Const(val) => format!("i32.const {}", val),
+        Instr::I64Const(val) => format!("i64.const {}", val),
+        Instr::F32Const(val) => format!("f32.const {}", f32_text(*val)),
+        Instr::F64Const(val) => format!("f64.const {}", f64_text(*val)),
+        Instr::Numeric(opcode) => numeric_op_info(*opcode).0.to_string(),
+    }
+}
+
+/**
+ * A node of a folded function body.
+ * Operands are the nodes that push the values consumed by the instruction, and are printed before it when flattened.
+ */
+enum Node {
+    Plain {
+        text: String,
+        operands: Vec<Node>,
+    },
+    Block {
+        text: String,
+        body: Vec<Node>,
+    },
+    If {
+        text: String,
+        operands: Vec<Node>,
+        then_body: Vec<Node>,
+        else_body: Option<Vec<Node>>,
+    },
+}
+
+// A block that is being folded.
+struct FoldFrame {
+    instr: Instr, // the instruction that started the block (`Nop` for the function body)
+    arity: usize, // the number of values returned from the block
+    condition: Vec<Node>,
+    then_body: Option<Vec<Node>>,
+    nodes: Vec<(Node, usize)>, // the nodes so far, with the number of values each of them pushes
+}
+
+struct FuncPrinter<'a, 'b> {
+    types: &'a [FuncType],
+    funcs: &'a [TypeIdx],
+    functype: &'a FuncType,
+    lines: &'b mut Vec<String>,
+}
+
+impl<'a, 'b> FuncPrinter<'a, 'b> {
+    fn print(mut self, body: &[u8], style: WatStyle) {
+        let (locals, start) = match decode_locals(body) {
+            Ok(ret) => ret,
+            Err(e) => {
+                self.lines
+                    .push(format!("    (; cannot decode locals: {} ;)", e));
+                return;
+            }
+        };
+        if !locals.is_empty() {
+            let names: Vec<&str> = locals
+                .iter()
+                .map(|valtype| valtype_name(*valtype))
+                .collect();
+            self.lines.push(format!("    (local {})", names.join(" ")));
+        }
+        let reader = InstrReader::new(body, start);
+        match style {
+            WatStyle::Flat => self.print_flat(reader),
+            WatStyle::Folded => self.print_folded(reader),
+        }
+    }
+
+    fn push_line(&mut self, depth: usize, text: &str) {
+        self.lines
+            .push(format!("{}{}", "  ".repeat(depth + 2), text));
+    }
+
+    fn print_flat(&mut self, reader: InstrReader) {
+        let mut depth: usize = 0;
+        for res in reader {
+            match res {
+                Ok((_, instr)) => {
+                    if let Instr::End | Instr::Else = instr {
+                        if depth == 0 {
+                            // the end of the function is implicit in the text format
+                            continue;
+                        }
+                        depth -= 1;
+                    }
+                    self.push_line(depth, &instr_text(&instr));
+                    if let Instr::Block(_) | Instr::Loop(_) | Instr::If(_) | Instr::Else = instr {
+                        depth += 1;
+                    }
+                }
+                Err(e) => self.push_line(depth, &format!("(; cannot decode: {} ;)", e)),
+            }
+        }
+    }
+
+    // Returns the number of values consumed and pushed by an instruction that is not a structured control instruction.
+    fn arity(&self, instr: &Instr, frames: &[FoldFrame]) -> (usize, usize) {
+        let label_arity = |labelidx: u32| -> usize {
+            frames
+                .len()
+                .checked_sub(labelidx as usize + 1)
+                .map_or(0, |i| match frames[i].instr {
+                    Instr::Loop(_) => 0,
+                    _ => frames[i].arity,
+                })
+        };
+        let func_arity = |funcidx: FuncIdx| -> (usize, usize) {
+            self.funcs
+                .get(funcidx.idx as usize)
+                .map_or((0, 0), |typeidx| self.type_arity(*typeidx))
+        };
+        match instr {
+            Instr::Br(labelidx) => (label_arity(*labelidx), 0),
+            Instr::BrIf(labelidx) => (label_arity(*labelidx) + 1, label_arity(*labelidx)),
+            Instr::BrTable(_, default_labelidx) => (label_arity(*default_labelidx) + 1, 0),
+            Instr::Return => (self.functype.result_types.len(), 0),
+            Instr::Call(funcidx) => func_arity(*funcidx),
+            Instr::CallIndirect(typeidx, _) => {
+                let (params, results) = self.type_arity(*typeidx);
+                (params + 1, results)
+            }
+            Instr::ReturnCall(funcidx) => (func_arity(*funcidx).0, 0),
+            Instr::ReturnCallIndirect(typeidx, _) => (self.type_arity(*typeidx).0 + 1, 0),
+            Instr::Drop | Instr::LocalSet(_) | Instr::GlobalSet(_) => (1, 0),
+            Instr::Select => (3, 1),
+            Instr::LocalTee(_) | Instr::Load(_, _) | Instr::MemoryGrow(_) => (1, 1),
+            Instr::Store(_, _) => (2, 0),
+            Instr::MemoryCopy(_, _) | Instr::MemoryFill(_) => (3, 0),
+            Instr::LocalGet(_)
+            | Instr::GlobalGet(_)
+            | Instr::MemorySize(_)
+            | Instr::I32Const(_)
+            | Instr::I64Const(_)
+            | Instr::F32Const(_)
+            | Instr::F64Const(_) => (0, 1),
+            Instr::Numeric(opcode) => (numeric_op_info(*opcode).1.len(), 1),
+            _ => (0, 0),
+        }
+    }
+
+    fn type_arity(&self, typeidx: TypeIdx) -> (usize, usize) {
+        self.types
+            .get(typeidx.idx as usize)
+            .map_or((0, 0), |functype| {
+                (functype.param_types.len(), functype.result_types.len())
+            })
+    }
+
+    // Takes the nodes that push the operands of an instruction, if all of them are the most recent nodes in the block.
+    fn take_operands(nodes: &mut Vec<(Node, usize)>, count: usize) -> Vec<Node> {
+        if count > nodes.len() || nodes[nodes.len() - count..].iter().any(|(_, n)| *n != 1) {
+            return Vec::new();
+        }
+        nodes
+            .drain(nodes.len() - count..)
+            .map(|(node, _)| node)
+            .collect()
+    }
+
+    fn print_folded(&mut self, reader: InstrReader) {
+        let mut frames: Vec<FoldFrame> = vec![FoldFrame {
+            instr: Instr::Nop,
+            arity: self.functype.result_types.len(),
+            condition: Vec::new(),
+            then_body: None,
+            nodes: Vec::new(),
+        }];
+        let mut error: Option<DecodeError> = None;
+        for res in reader {
+            let instr: Instr = match res {
+                Ok((_, instr)) => instr,
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            };
+            match instr {
+                Instr::Block(blocktype) | Instr::Loop(blocktype) | Instr::If(blocktype) => {
+                    let condition: Vec<Node> = match instr {
+                        Instr::If(_) => {
+                            Self::take_operands(&mut frames.last_mut().unwrap().nodes, 1)
+                        }
+                        _ => Vec::new(),
+                    };
+                    frames.push(FoldFrame {
+                        instr: instr,
+                        arity: blocktype.iter().count(),
+                        condition: condition,
+                        then_body: None,
+                        nodes: Vec::new(),
+                    });
+                }
+                Instr::Else => {
+                    let frame: &mut FoldFrame = frames.last_mut().unwrap();
+                    let then_body: Vec<Node> =
+                        frame.nodes.drain(..).map(|(node, _)| node).collect();
+                    frame.then_body = Some(then_body);
+                }
+                Instr::End => {
+                    if frames.len() == 1 {
+                        // the end of the function is implicit in the text format
+                        continue;
+                    }
+                    let frame: FoldFrame = frames.pop().unwrap();
+                    let text: String = instr_text(&frame.instr);
+                    let body: Vec<Node> = frame.nodes.into_iter().map(|(node, _)| node).collect();
+                    let node: Node = match frame.instr {
+                        Instr::If(_) => match frame.then_body {
+                            None => Node::If {
+                                text: text,
+                                operands: frame.condition,
+                                then_body: body,
+                                else_body: None,
+                            },
+                            Some(then_body) => Node::If {
+                                text: text,
+                                operands: frame.condition,
+                                then_body: then_body,
+                                else_body: Some(body),
+                            },
+                        },
+                        _ => Node::Block {
+                            text: text,
+                            body: body,
+                        },
+                    };
+                    frames.last_mut().unwrap().nodes.push((node, frame.arity));
+                }
+                _ => {
+                    let (params, results) = self.arity(&instr, &frames);
+                    let nodes: &mut Vec<(Node, usize)> = &mut frames.last_mut().unwrap().nodes;
+                    let operands: Vec<Node> = Self::take_operands(nodes, params);
+                    nodes.push((
+                        Node::Plain {
+                            text: instr_text(&instr),
+                            operands: operands,
+                        },
+                        results,
+                    ));
+                }
+            }
+        }
+        // print whatever could be decoded, even if the blocks were not closed properly
+        while frames.len() > 1 {
+            let frame: FoldFrame = frames.pop().unwrap();
+            let body: Vec<Node> = frame.nodes.into_iter().map(|(node, _)| node).collect();
+            frames.last_mut().unwrap().nodes.push((
+                Node::Block {
+                    text: instr_text(&frame.instr),
+                    body: body,
+                },
+                0,
+            ));
+        }
+        for (node, _) in frames.pop().unwrap().nodes {
+            self.print_node(&node, 0);
+        }
+        if let Some(e) = error {
+            self.push_line(0, &format!("(; cannot decode: {} ;)", e));
+        }
+    }
+
+    // Returns the node on a single line, if it does not contain any blocks.
+    fn inline_text(node: &Node) -> Option<String> {
+        match node {
+            Node::Plain { text, operands } => {
+                let mut ret: String = format!("({}", text);
+                for operand in operands {
+                    ret.push(' ');
+                    ret.push_str(&Self::inline_text(operand)?);
+                }
+                ret.push(')');
+                Some(ret)
+            }
+            _ => None,
+        }
+    }
+
+    fn print_node(&mut self, node: &Node, depth: usize) {
+        if let Some(text) = Self::inline_text(node) {
+            self.push_line(depth, &text);
+            return;
+        }
+        match node {
+            Node::Plain { text, operands } => {
+                self.push_line(depth, &format!("({}", text));
+                self.print_nodes(operands, depth + 1);
+            }
+            Node::Block { text, body } => {
+                self.push_line(depth, &format!("({}", text));
+                self.print_nodes(body, depth + 1);
+            }
+            Node::If {
+                text,
+                operands,
+                then_body,
+                else_body,
+            } => {
+                self.push_line(depth, &format!("({}", text));
+                self.print_nodes(operands, depth + 1);
+                self.push_line(depth + 1, "(then");
+                self.print_nodes(then_body, depth + 2);
+                self.lines.last_mut().unwrap().push(')');
+                if let Some(else_body) = else_body {
+                    self.push_line(depth + 1, "(else");
+                    self.print_nodes(else_body, depth + 2);
+                    self.lines.last_mut().unwrap().push(')');
+                }
+            }
+        }
+        self.lines.last_mut().unwrap().push(')');
+    }
+
+    fn print_nodes(&mut self, nodes: &[Node], depth: usize) {
+        for node in nodes {
+            self.print_node(node, depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_module() -> WasmModule {
+        let mut builder = WasmModule::new_builder();
+        builder.import_func(
+            "core".to_string(),
+            "error".to_string(),
+            &FuncType::new(Box::new([ValType::I32]), Box::new([])),
+        );
+        let mut wasm_module = builder.build();
+        let memidx: MemIdx = wasm_module.add_unbounded_memory(1);
+        let globalidx: GlobalIdx = wasm_module.add_i32_global(Mut::Var, 1024);
+        wasm_module.add_data(memidx, 8, b"hi\n\"");
+
+        // computes (x > 0 ? x : -x) * 2.5, stores it, and returns the old value of the global
+        let functype = FuncType::new(Box::new([ValType::F64]), Box::new([ValType::I32]));
+        let (_, funcidx) = wasm_module.register_func(&functype);
+        let mut code_builder = CodeBuilder::new(functype);
+        let (locals_builder, expr_builder) = code_builder.split();
+        let tmp: LocalIdx = locals_builder.add(ValType::F64);
+        expr_builder.global_get(globalidx);
+        expr_builder.local_get(LocalIdx { idx: 0 });
+        expr_builder.f64_const(0.0);
+        expr_builder.f64_gt();
+        expr_builder.if_(&[ValType::F64]);
+        expr_builder.local_get(LocalIdx { idx: 0 });
+        expr_builder.else_();
+        expr_builder.local_get(LocalIdx { idx: 0 });
+        expr_builder.f64_neg();
+        expr_builder.end();
+        expr_builder.f64_const(2.5);
+        expr_builder.f64_mul();
+        expr_builder.local_set(tmp);
+        expr_builder.i32_const(16);
+        expr_builder.local_get(tmp);
+        expr_builder.f64_store(MemArg::new8(4));
+        expr_builder.end();
+        wasm_module.commit_func(funcidx, code_builder);
+        wasm_module.export_func(funcidx, "main".to_string());
+        wasm_module
+    }
+
+    #[test]
+    fn flat() {
+        assert_eq!(
+            test_module().to_wat(WatStyle::Flat),
+            r#"(module
+  (type (;0;) (func (param i32)))
+  (type (;1;) (func (param f64) (result i32)))
+  (import "core" "error" (func (;0;) (type 0)))
+  (func (;1;) (type 1) (param f64) (result i32)
+    (local f64)
+    global.get 0
+    local.get 0
+    f64.const 0.0
+    f64.gt
+    if (result f64)
+      local.get 0
+    else
+      local.get 0
+      f64.neg
+    end
+    f64.const 2.5
+    f64.mul
+    local.set 1
+    i32.const 16
+    local.get 1
+    f64.store offset=4)
+  (memory (;0;) 1)
+  (global (;0;) (mut i32) (i32.const 1024))
+  (export "main" (func 1))
+  (data (;0;) (i32.const 8) "hi\0a\""))
+"#
+        );
+    }
+
+    #[test]
+    fn folded() {
+        let wat: String = test_module().to_wat(WatStyle::Folded);
+        assert!(wat.contains(
+            r#"
+  (func (;1;) (type 1) (param f64) (result i32)
+    (local f64)
+    (global.get 0)
+    (local.set 1
+      (f64.mul
+        (if (result f64)
+          (f64.gt (local.get 0) (f64.const 0.0))
+          (then
+            (local.get 0))
+          (else
+            (f64.neg (local.get 0))))
+        (f64.const 2.5)))
+    (f64.store offset=4 (i32.const 16) (local.get 1)))
+"#
+        ));
+    }
+
+    #[test]
+    fn uncommitted_and_floats() {
+        let mut wasm_module = WasmModule::new_builder().build();
+        wasm_module.register_func(&FuncType::new(Box::new([]), Box::new([])));
+        let wat: String = wasm_module.to_wat(WatStyle::Flat);
+        assert!(wat.contains("(func (;0;) (type 0)\n    (; not committed ;))"));
+        assert_eq!(f64_text(f64::NAN), "nan");
+        assert_eq!(f64_text(-f64::INFINITY), "-inf");
+        assert_eq!(f32_text(f32::from_bits(0x7FC0_0001)), "nan:0x400001");
+        assert_eq!(f64_text(1e-7), "1e-7");
+    }
+}
@@ -34,14 +34,33 @@ impl WasmModule {
     pub fn register_func(&mut self, functype: &FuncType) -> (TypeIdx, FuncIdx) {
         let typeidx = self.type_section.insert_copy(functype);
         let funcidx = self.func_section.push(typeidx);
-        self.code_section.push(Code { func: None });
+        self.code_section.push(Code {
+            func: None,
+            source_positions: Box::new([]),
+        });
         (typeidx, funcidx)
     }
     // Commit a function that has been previously registered
-    pub fn commit_func(&mut self, funcidx: FuncIdx, code_builder: CodeBuilder) {
-        let (_functype, bytes) = code_builder.build();
-        self.code_section.content[self.func_section.plain_index_without_offset(funcidx) as usize]
-            .func = Some(bytes);
+    pub fn commit_func(&mut self, funcidx: FuncIdx, mut code_builder: CodeBuilder) {
+        let local_names = code_builder.take_local_names();
+        if !local_names.is_empty() {
+            self.name_section
+                .local_names
+                .insert(funcidx.idx, local_names);
+        }
+        let (_functype, bytes, source_positions) = code_builder.build_with_source_positions();
+        let code: &mut Code = &mut self.code_section.content
+            [self.func_section.plain_index_without_offset(funcidx) as usize];
+        code.func = Some(bytes);
+        code.source_positions = source_positions;
+    }
+    // Record the URL of the source map (see `WasmModule::source_map()`) in the module, so that debuggers can find it
+    pub fn set_source_map_url(&mut self, url: String) {
+        self.source_map_url = Some(url);
+    }
+    // Name a function, so that it is shown in stack traces and debuggers (the names of its locals are given to its CodeBuilder instead)
+    pub fn set_func_name(&mut self, funcidx: FuncIdx, name: String) {
+        self.name_section.func_names.insert(funcidx.idx, name);
     }
     // Export a function so that the environment (i.e. JavaScript) can call it
     pub fn export_func(&mut self, funcidx: FuncIdx, exported_name: String) {
@@ -78,14 +78,97 @@ struct ReplContext {
 // For storing existing compilation information for use with REPL.
 static mut CONTEXTUAL_STORE: Option<HashMap<i32, ReplContext>> = None;
 
+// The library set by precompile_library(), which is linked into every program compiled after that.
+static mut LIBRARY: Option<frontend_estree::Library> = None;
+
+/**
+ * Compilation options that can be set by the host.
+ * By default, no WebAssembly proposals are used, and all optimisations are done.
+ * The heap and stack size must be the same for `compile()` and all subsequent calls to `compile_repl()` with the same context,
+ * because the REPL reuses the linear memory of the original program.
+ */
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub struct CompileOptions {
+    backend: backend_wasm::Options,
+    opt_level: u32, // see ir::opt::Pipeline::for_level()
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            backend: backend_wasm::Options::default(),
+            opt_level: 1,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl CompileOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn set_wasm_multi_value(&mut self, enabled: bool) {
+        self.backend = self.backend.wasm_multi_value(enabled);
+    }
+    pub fn set_wasm_bulk_memory(&mut self, enabled: bool) {
+        self.backend = self.backend.wasm_bulk_memory(enabled);
+    }
+    pub fn set_wasm_tail_call(&mut self, enabled: bool) {
+        self.backend = self.backend.wasm_tail_call(enabled);
+    }
+    /**
+     * Sets the heap manager (garbage collector) by name.
+     * "cheney" (the default) and "marksweep" collect garbage, and "leaky" never frees memory but allocates faster.
+     * "marksweep" does not move objects, and needs less memory than "cheney".
+     */
+    pub fn set_heap(&mut self, name: &str) -> Result<(), JsValue> {
+        let heap = match name {
+            "cheney" => backend_wasm::Heap::Cheney,
+            "leaky" => backend_wasm::Heap::Leaky,
+            "marksweep" => backend_wasm::Heap::MarkSweep,
+            _ => return Err(JsValue::from_str("Unknown heap manager")),
+        };
+        self.backend = self.backend.heap(heap);
+        Ok(())
+    }
+    /**
+     * Sets the size of the stack, in units of WebAssembly pages (64 KiB).
+     * The result of the program is written to the last 12 bytes of the stack.
+     * The size must be between one page and 16384 pages (1 GiB).
+     */
+    pub fn set_stack_size(&mut self, num_pages: u32) -> Result<(), JsValue> {
+        backend_wasm::check_stack_size(num_pages).map_err(|e| JsValue::from_str(&e))?;
+        self.backend = self.backend.stack_size(num_pages);
+        Ok(())
+    }
+    #[wasm_bindgen(getter)]
+    pub fn stack_size(&self) -> u32 {
+        self.backend.get_stack_size()
+    }
+    pub fn set_opt_level(&mut self, opt_level: u32) {
+        self.opt_level = opt_level;
+    }
+}
+
+fn optimize(ir_program: ir::Program, start_funcidx: usize, opt_level: u32) -> ir::Program {
+    ir::opt::Pipeline::for_level(opt_level).run(ir_program, start_funcidx)
+}
+
 /**
  * The entry function for compilation.
  * `context` is an opaque value so that the host code can associate our calls to compiler_log() with the correct call to compile().
  * `source_code`: ESTree JSON representation of validated program
  * `import_spec`: list of imports following the import file format
+ * `options`: compilation options (if not given, the defaults are used)
  */
 #[wasm_bindgen]
-pub async fn compile(context: i32, source_code: String) -> js_sys::Uint8Array {
+pub async fn compile(
+    context: i32,
+    source_code: String,
+    options: Option<CompileOptions>,
+) -> js_sys::Uint8Array {
     // nice console errors in debug mode
     #[cfg(all(debug_assertions, target_arch = "wasm32"))]
     console_error_panic_hook::set_once();
@@ -96,21 +179,38 @@ pub async fn compile(context: i32, source_code: String) -> js_sys::Uint8Array {
         }
     }
 
+    let options: CompileOptions = options.unwrap_or_default();
+
     (|| async {
         use wasmgen::WasmSerialize;
 
-        let (frontend_repl_ctx, ir_program) = frontend_estree::run_frontend(
-            source_code,
-            move |name| fetch_dep_proxy(context, name),
-            MainLogger::new(context),
-        )
-        .await?;
-        let ir_program_opt = ir::opt::optimize_all(ir_program, 0);
-        let wasm_module = backend_wasm::run_backend(
-            &ir_program_opt,
-            usize::MAX,
-            backend_wasm::Options::default(),
+        let (mut frontend_repl_ctx, ir_program) = match unsafe { &LIBRARY } {
+            Some(library) => {
+                frontend_estree::run_frontend_with_library(
+                    source_code,
+                    move |name| fetch_dep_proxy(context, name),
+                    library,
+                    MainLogger::new(context),
+                )
+                .await?
+            }
+            None => {
+                frontend_estree::run_frontend(
+                    source_code,
+                    move |name| fetch_dep_proxy(context, name),
+                    MainLogger::new(context),
+                )
+                .await?
+            }
+        };
+        let ir_program_opt = optimize(ir_program, 0, options.opt_level);
+        // keep everything that the REPL can still refer to
+        let (ir_program_opt, item_map) = ir::opt::remove_unused(
+            ir_program_opt,
+            &frontend_estree::repl_keep_alive(&frontend_repl_ctx),
         );
+        frontend_estree::remap_repl_context(&mut frontend_repl_ctx, &item_map);
+        let wasm_module = backend_wasm::run_backend(&ir_program_opt, usize::MAX, options.backend);
         let num_funcs = ir_program_opt.funcs.len();
         unsafe { (&mut CONTEXTUAL_STORE).as_mut().unwrap() }.insert(
             context,
@@ -128,20 +228,52 @@ pub async fn compile(context: i32, source_code: String) -> js_sys::Uint8Array {
     .unwrap_or_else(|_: ()| js_sys::Uint8Array::new_with_length(0))
 }
 
+/**
+ * Compiles the given library modules (e.g. the standard library) once,
+ * so that subsequent calls to compile() link them instead of fetching and compiling them again.
+ * `context` is used like in compile(), for fetching the modules and logging errors.
+ * `names`: the names of the modules, as they would be written in import declarations
+ * Returns false (and keeps the previous library, if any) if the modules could not be compiled.
+ */
+#[wasm_bindgen]
+pub async fn precompile_library(context: i32, names: Box<[JsValue]>) -> bool {
+    let names: Vec<String> = names.iter().filter_map(|name| name.as_string()).collect();
+    match frontend_estree::compile_library(
+        &names,
+        move |name| fetch_dep_proxy(context, name),
+        MainLogger::new(context),
+    )
+    .await
+    {
+        Ok(library) => {
+            unsafe { LIBRARY = Some(library) };
+            true
+        }
+        Err(()) => false,
+    }
+}
+
 /**
  * The entry function for compilation (for REPL).
  * `context` must be the an existing value from the previous invocation.
  * `source_code`: ESTree JSON representation of validated program
  * `import_spec`: list of imports following the import file format
+ * `options`: compilation options (if not given, the defaults are used)
  */
 #[wasm_bindgen]
-pub async fn compile_repl(context: i32, source_code: String) -> js_sys::Uint8Array {
+pub async fn compile_repl(
+    context: i32,
+    source_code: String,
+    options: Option<CompileOptions>,
+) -> js_sys::Uint8Array {
     // nice console errors in debug mode
     #[cfg(all(debug_assertions, target_arch = "wasm32"))]
     console_error_panic_hook::set_once();
 
     assert!(!unsafe { &CONTEXTUAL_STORE }.is_none());
 
+    let options: CompileOptions = options.unwrap_or_default();
+
     (|| async {
         use wasmgen::WasmSerialize;
 
@@ -159,12 +291,13 @@ pub async fn compile_repl(context: i32, source_code: String) -> js_sys::Uint8Arr
             ir_program,
             MainLogger::new(context),
         )?;
-        let ir_program_opt = ir::opt::optimize_all(std::mem::take(ir_program), new_funcidx_start);
-        let wasm_module = backend_wasm::run_backend(
-            &ir_program_opt,
-            *repl_funcidx_start,
-            backend_wasm::Options::default(),
+        let ir_program_opt = optimize(
+            std::mem::take(ir_program),
+            new_funcidx_start,
+            options.opt_level,
         );
+        let wasm_module =
+            backend_wasm::run_backend(&ir_program_opt, *repl_funcidx_start, options.backend);
         *frontend_repl_ctx = new_frontend_repl_ctx;
         *ir_program = ir_program_opt;
         let mut receiver = std::vec::Vec::<u8>::new();
@@ -201,10 +334,12 @@ mod tests {
         }
         module.commit_func(func_idx, code_builder);
         module.export_func(func_idx, "test".to_string());
-        let mut file = File::create("test.wasm")?;
+        // write to the temporary directory, so that running the tests does not leave files in the crate
+        let path = std::env::temp_dir().join(format!("sourceror-gen-{}.wasm", std::process::id()));
+        let mut file = File::create(&path)?;
         let mut receiver = std::vec::Vec::<u8>::new();
         module.wasm_serialize(&mut receiver);
         file.write_all(receiver.as_slice())?;
-        Ok(())
+        std::fs::remove_file(&path)
     }
 }
@@ -1,141 +1,418 @@
+/**
+ * Native command-line driver for the compiler.
+ * It compiles an ESTree JSON file (the same input that the host gives to the compiler) to a WebAssembly binary,
+ * so that programs can be compiled from scripts (e.g. in CI) without a browser.
+ *
+ * Imported modules are loaded from the local search paths instead of being fetched from the network.
+ * Since there is no Source parser here, imported Source modules must also be given as ESTree JSON.
+ */
 use projstd::log;
+use std::path::Path;
+use std::path::PathBuf;
+use wasmgen::WasmSerialize;
 
-/*const SOURCE_CODE: &'static str = r#"
-{"type":"Program","start":0,"end":72,"body":[{"type":"FunctionDeclaration","start":0,"end":72,"id":{"type":"Identifier","start":9,"end":10,"name":"f"},"expression":false,"generator":false,"async":false,"params":[{"type":"Identifier","start":11,"end":12,"name":"a"},{"type":"Identifier","start":14,"end":15,"name":"b"}],"body":{"type":"BlockStatement","start":17,"end":72,"body":[{"type":"VariableDeclaration","start":18,"end":30,"declarations":[{"type":"VariableDeclarator","start":24,"end":29,"id":{"type":"Identifier","start":24,"end":25,"name":"x"},"init":{"type":"Literal","start":28,"end":29,"value":4,"raw":"4"}}],"kind":"const"},{"type":"VariableDeclaration","start":30,"end":46,"declarations":[{"type":"VariableDeclarator","start":36,"end":45,"id":{"type":"Identifier","start":36,"end":37,"name":"y"},"init":{"type":"BinaryExpression","start":40,"end":45,"left":{"type":"Identifier","start":40,"end":41,"name":"x"},"operator":"*","right":{"type":"Identifier","start":44,"end":45,"name":"x"}}}],"kind":"const"},{"type":"VariableDeclaration","start":46,"end":62,"declarations":[{"type":"VariableDeclarator","start":52,"end":61,"id":{"type":"Identifier","start":52,"end":53,"name":"z"},"init":{"type":"BinaryExpression","start":56,"end":61,"left":{"type":"Identifier","start":56,"end":57,"name":"y"},"operator":"+","right":{"type":"Literal","start":60,"end":61,"value":5,"raw":"5"}}}],"kind":"const"},{"type":"ReturnStatement","start":62,"end":71,"argument":{"type":"Identifier","start":69,"end":70,"name":"z"}}]}}],"sourceType":"script"}
-"#;*/
-const SOURCE_CODE: &'static str = r#"
-{"type":"Program","start":0,"end":0,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":0}},"body":[],"sourceType":"module"}
-"#;
-const REPL_SOURCE_CODE: &'static str = r#"
-{"type":"Program","start":0,"end":33,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":33}},"body":[{"type":"ImportDeclaration","start":0,"end":33,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":33}},"specifiers":[{"type":"ImportSpecifier","start":8,"end":15,"loc":{"start":{"line":1,"column":8},"end":{"line":1,"column":15}},"imported":{"type":"Identifier","start":8,"end":15,"loc":{"start":{"line":1,"column":8},"end":{"line":1,"column":15}},"name":"display"},"local":{"type":"Identifier","start":8,"end":15,"loc":{"start":{"line":1,"column":8},"end":{"line":1,"column":15}},"name":"display"}}],"source":{"type":"Literal","start":22,"end":32,"loc":{"start":{"line":1,"column":22},"end":{"line":1,"column":32}},"value":"std/misc","raw":"\"std/misc\""}}],"sourceType":"module"}"#;
-/*const SOURCE_CODE: &'static str = r#"
-{"type":"Identifier","start":0,"end":72,"name":"str"}
-"#;*/
-/*const SOURCE_CODE: &'static str = r#"
-{"type":"Program","start":0,"end":2504,"loc":{"start":{"line":1,"column":0},"end":{"line":117,"column":0}},"body":[{"type":"VariableDeclaration","start":0,"end":23,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":23}},"declarations":[{"type":"VariableDeclarator","start":6,"end":22,"loc":{"start":{"line":1,"column":6},"end":{"line":1,"column":22}},"id":{"type":"Identifier","start":6,"end":14,"loc":{"start":{"line":1,"column":6},"end":{"line":1,"column":14}},"name":"Infinity"},"init":{"type":"BinaryExpression","start":17,"end":22,"loc":{"start":{"line":1,"column":17},"end":{"line":1,"column":22}},"left":{"type":"Literal","start":17,"end":18,"loc":{"start":{"line":1,"column":17},"end":{"line":1,"column":18}},"value":1,"raw":"1"},"operator":"/","right":{"type":"Literal","start":21,"end":22,"loc":{"start":{"line":1,"column":21},"end":{"line":1,"column":22}},"value":0,"raw":"0"}}}],"kind":"const"},{"type":"VariableDeclaration","start":24,"end":49,"loc":{"start":{"line":2,"column":0},"end":{"line":2,"column":25}},"declarations":[{"type":"VariableDeclarator","start":30,"end":48,"loc":{"start":{"line":2,"column":6},"end":{"line":2,"column":24}},"id":{"type":"Identifier","start":30,"end":33,"loc":{"start":{"line":2,"column":6},"end":{"line":2,"column":9}},"name":"NaN"},"init":{"type":"BinaryExpression","start":36,"end":48,"loc":{"start":{"line":2,"column":12},"end":{"line":2,"column":24}},"left":{"type":"Literal","start":36,"end":37,"loc":{"start":{"line":2,"column":12},"end":{"line":2,"column":13}},"value":0,"raw":"0"},"operator":"*","right":{"type":"Identifier","start":40,"end":48,"loc":{"start":{"line":2,"column":16},"end":{"line":2,"column":24}},"name":"Infinity"}}}],"kind":"const"},{"type":"VariableDeclaration","start":50,"end":81,"loc":{"start":{"line":3,"column":0},"end":{"line":3,"column":31}},"declarations":[{"type":"VariableDeclarator","start":56,"end":80,"loc":{"start":{"line":3,"column":6},"end":{"line":3,"column":30}},"id":{"type":"Identifier","start":56,"end":65,"loc":{"start":{"line":3,"column":6},"end":{"line":3,"column":15}},"name":"undefined"},"init":{"type":"CallExpression","start":68,"end":80,"loc":{"start":{"line":3,"column":18},"end":{"line":3,"column":30}},"callee":{"type":"ArrowFunctionExpression","start":69,"end":77,"loc":{"start":{"line":3,"column":19},"end":{"line":3,"column":27}},"id":null,"expression":false,"generator":false,"params":[],"body":{"type":"BlockStatement","start":75,"end":77,"loc":{"start":{"line":3,"column":25},"end":{"line":3,"column":27}},"body":[]}},"arguments":[]}}],"kind":"const"},{"type":"ExpressionStatement","start":373,"end":397,"loc":{"start":{"line":19,"column":0},"end":{"line":19,"column":24}},"expression":{"type":"AssignmentExpression","start":373,"end":396,"loc":{"start":{"line":19,"column":0},"end":{"line":19,"column":23}},"operator":"=","left":{"type":"Identifier","start":373,"end":385,"loc":{"start":{"line":19,"column":0},"end":{"line":19,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":388,"end":396,"loc":{"start":{"line":19,"column":15},"end":{"line":19,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":398,"end":444,"loc":{"start":{"line":20,"column":0},"end":{"line":22,"column":1}},"id":{"type":"Identifier","start":407,"end":419,"loc":{"start":{"line":20,"column":9},"end":{"line":20,"column":21}},"name":"is_undefined"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":420,"end":421,"loc":{"start":{"line":20,"column":22},"end":{"line":20,"column":23}},"name":"v"}],"body":{"type":"BlockStatement","start":423,"end":444,"loc":{"start":{"line":20,"column":25},"end":{"line":22,"column":1}},"body":[{"type":"ReturnStatement","start":429,"end":442,"loc":{"start":{"line":21,"column":4},"end":{"line":21,"column":17}},"argument":{"type":"Literal","start":436,"end":441,"loc":{"start":{"line":21,"column":11},"end":{"line":21,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":445,"end":492,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":47}},"expression":{"type":"AssignmentExpression","start":445,"end":491,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":46}},"operator":"=","left":{"type":"Identifier","start":445,"end":457,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":460,"end":491,"loc":{"start":{"line":23,"column":15},"end":{"line":23,"column":46}},"value":"direct;constraint=v:undefined","raw":"\"direct;constraint=v:undefined\""}}},{"type":"FunctionDeclaration","start":493,"end":538,"loc":{"start":{"line":24,"column":0},"end":{"line":26,"column":1}},"id":{"type":"Identifier","start":502,"end":514,"loc":{"start":{"line":24,"column":9},"end":{"line":24,"column":21}},"name":"is_undefined"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":515,"end":516,"loc":{"start":{"line":24,"column":22},"end":{"line":24,"column":23}},"name":"v"}],"body":{"type":"BlockStatement","start":518,"end":538,"loc":{"start":{"line":24,"column":25},"end":{"line":26,"column":1}},"body":[{"type":"ReturnStatement","start":524,"end":536,"loc":{"start":{"line":25,"column":4},"end":{"line":25,"column":16}},"argument":{"type":"Literal","start":531,"end":535,"loc":{"start":{"line":25,"column":11},"end":{"line":25,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":539,"end":563,"loc":{"start":{"line":27,"column":0},"end":{"line":27,"column":24}},"expression":{"type":"AssignmentExpression","start":539,"end":562,"loc":{"start":{"line":27,"column":0},"end":{"line":27,"column":23}},"operator":"=","left":{"type":"Identifier","start":539,"end":551,"loc":{"start":{"line":27,"column":0},"end":{"line":27,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":554,"end":562,"loc":{"start":{"line":27,"column":15},"end":{"line":27,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":564,"end":608,"loc":{"start":{"line":28,"column":0},"end":{"line":30,"column":1}},"id":{"type":"Identifier","start":573,"end":583,"loc":{"start":{"line":28,"column":9},"end":{"line":28,"column":19}},"name":"is_boolean"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":584,"end":585,"loc":{"start":{"line":28,"column":20},"end":{"line":28,"column":21}},"name":"v"}],"body":{"type":"BlockStatement","start":587,"end":608,"loc":{"start":{"line":28,"column":23},"end":{"line":30,"column":1}},"body":[{"type":"ReturnStatement","start":593,"end":606,"loc":{"start":{"line":29,"column":4},"end":{"line":29,"column":17}},"argument":{"type":"Literal","start":600,"end":605,"loc":{"start":{"line":29,"column":11},"end":{"line":29,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":609,"end":654,"loc":{"start":{"line":31,"column":0},"end":{"line":31,"column":45}},"expression":{"type":"AssignmentExpression","start":609,"end":653,"loc":{"start":{"line":31,"column":0},"end":{"line":31,"column":44}},"operator":"=","left":{"type":"Identifier","start":609,"end":621,"loc":{"start":{"line":31,"column":0},"end":{"line":31,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":624,"end":653,"loc":{"start":{"line":31,"column":15},"end":{"line":31,"column":44}},"value":"direct;constraint=v:boolean","raw":"\"direct;constraint=v:boolean\""}}},{"type":"FunctionDeclaration","start":655,"end":698,"loc":{"start":{"line":32,"column":0},"end":{"line":34,"column":1}},"id":{"type":"Identifier","start":664,"end":674,"loc":{"start":{"line":32,"column":9},"end":{"line":32,"column":19}},"name":"is_boolean"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":675,"end":676,"loc":{"start":{"line":32,"column":20},"end":{"line":32,"column":21}},"name":"v"}],"body":{"type":"BlockStatement","start":678,"end":698,"loc":{"start":{"line":32,"column":23},"end":{"line":34,"column":1}},"body":[{"type":"ReturnStatement","start":684,"end":696,"loc":{"start":{"line":33,"column":4},"end":{"line":33,"column":16}},"argument":{"type":"Literal","start":691,"end":695,"loc":{"start":{"line":33,"column":11},"end":{"line":33,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":699,"end":723,"loc":{"start":{"line":35,"column":0},"end":{"line":35,"column":24}},"expression":{"type":"AssignmentExpression","start":699,"end":722,"loc":{"start":{"line":35,"column":0},"end":{"line":35,"column":23}},"operator":"=","left":{"type":"Identifier","start":699,"end":711,"loc":{"start":{"line":35,"column":0},"end":{"line":35,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":714,"end":722,"loc":{"start":{"line":35,"column":15},"end":{"line":35,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":724,"end":767,"loc":{"start":{"line":36,"column":0},"end":{"line":38,"column":1}},"id":{"type":"Identifier","start":733,"end":742,"loc":{"start":{"line":36,"column":9},"end":{"line":36,"column":18}},"name":"is_number"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":743,"end":744,"loc":{"start":{"line":36,"column":19},"end":{"line":36,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":746,"end":767,"loc":{"start":{"line":36,"column":22},"end":{"line":38,"column":1}},"body":[{"type":"ReturnStatement","start":752,"end":765,"loc":{"start":{"line":37,"column":4},"end":{"line":37,"column":17}},"argument":{"type":"Literal","start":759,"end":764,"loc":{"start":{"line":37,"column":11},"end":{"line":37,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":768,"end":812,"loc":{"start":{"line":39,"column":0},"end":{"line":39,"column":44}},"expression":{"type":"AssignmentExpression","start":768,"end":811,"loc":{"start":{"line":39,"column":0},"end":{"line":39,"column":43}},"operator":"=","left":{"type":"Identifier","start":768,"end":780,"loc":{"start":{"line":39,"column":0},"end":{"line":39,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":783,"end":811,"loc":{"start":{"line":39,"column":15},"end":{"line":39,"column":43}},"value":"direct;constraint=v:number","raw":"\"direct;constraint=v:number\""}}},{"type":"FunctionDeclaration","start":813,"end":855,"loc":{"start":{"line":40,"column":0},"end":{"line":42,"column":1}},"id":{"type":"Identifier","start":822,"end":831,"loc":{"start":{"line":40,"column":9},"end":{"line":40,"column":18}},"name":"is_number"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":832,"end":833,"loc":{"start":{"line":40,"column":19},"end":{"line":40,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":835,"end":855,"loc":{"start":{"line":40,"column":22},"end":{"line":42,"column":1}},"body":[{"type":"ReturnStatement","start":841,"end":853,"loc":{"start":{"line":41,"column":4},"end":{"line":41,"column":16}},"argument":{"type":"Literal","start":848,"end":852,"loc":{"start":{"line":41,"column":11},"end":{"line":41,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":856,"end":880,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":24}},"expression":{"type":"AssignmentExpression","start":856,"end":879,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":23}},"operator":"=","left":{"type":"Identifier","start":856,"end":868,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":871,"end":879,"loc":{"start":{"line":43,"column":15},"end":{"line":43,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":881,"end":924,"loc":{"start":{"line":44,"column":0},"end":{"line":46,"column":1}},"id":{"type":"Identifier","start":890,"end":899,"loc":{"start":{"line":44,"column":9},"end":{"line":44,"column":18}},"name":"is_string"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":900,"end":901,"loc":{"start":{"line":44,"column":19},"end":{"line":44,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":903,"end":924,"loc":{"start":{"line":44,"column":22},"end":{"line":46,"column":1}},"body":[{"type":"ReturnStatement","start":909,"end":922,"loc":{"start":{"line":45,"column":4},"end":{"line":45,"column":17}},"argument":{"type":"Literal","start":916,"end":921,"loc":{"start":{"line":45,"column":11},"end":{"line":45,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":925,"end":969,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":44}},"expression":{"type":"AssignmentExpression","start":925,"end":968,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":43}},"operator":"=","left":{"type":"Identifier","start":925,"end":937,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":940,"end":968,"loc":{"start":{"line":47,"column":15},"end":{"line":47,"column":43}},"value":"direct;constraint=v:string","raw":"\"direct;constraint=v:string\""}}},{"type":"FunctionDeclaration","start":970,"end":1012,"loc":{"start":{"line":48,"column":0},"end":{"line":50,"column":1}},"id":{"type":"Identifier","start":979,"end":988,"loc":{"start":{"line":48,"column":9},"end":{"line":48,"column":18}},"name":"is_string"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":989,"end":990,"loc":{"start":{"line":48,"column":19},"end":{"line":48,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":992,"end":1012,"loc":{"start":{"line":48,"column":22},"end":{"line":50,"column":1}},"body":[{"type":"ReturnStatement","start":998,"end":1010,"loc":{"start":{"line":49,"column":4},"end":{"line":49,"column":16}},"argument":{"type":"Literal","start":1005,"end":1009,"loc":{"start":{"line":49,"column":11},"end":{"line":49,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":1013,"end":1037,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":24}},"expression":{"type":"AssignmentExpression","start":1013,"end":1036,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":23}},"operator":"=","left":{"type":"Identifier","start":1013,"end":1025,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1028,"end":1036,"loc":{"start":{"line":51,"column":15},"end":{"line":51,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":1038,"end":1083,"loc":{"start":{"line":52,"column":0},"end":{"line":54,"column":1}},"id":{"type":"Identifier","start":1047,"end":1058,"loc":{"start":{"line":52,"column":9},"end":{"line":52,"column":20}},"name":"is_function"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1059,"end":1060,"loc":{"start":{"line":52,"column":21},"end":{"line":52,"column":22}},"name":"v"}],"body":{"type":"BlockStatement","start":1062,"end":1083,"loc":{"start":{"line":52,"column":24},"end":{"line":54,"column":1}},"body":[{"type":"ReturnStatement","start":1068,"end":1081,"loc":{"start":{"line":53,"column":4},"end":{"line":53,"column":17}},"argument":{"type":"Literal","start":1075,"end":1080,"loc":{"start":{"line":53,"column":11},"end":{"line":53,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":1084,"end":1130,"loc":{"start":{"line":55,"column":0},"end":{"line":55,"column":46}},"expression":{"type":"AssignmentExpression","start":1084,"end":1129,"loc":{"start":{"line":55,"column":0},"end":{"line":55,"column":45}},"operator":"=","left":{"type":"Identifier","start":1084,"end":1096,"loc":{"start":{"line":55,"column":0},"end":{"line":55,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1099,"end":1129,"loc":{"start":{"line":55,"column":15},"end":{"line":55,"column":45}},"value":"direct;constraint=v:function","raw":"\"direct;constraint=v:function\""}}},{"type":"FunctionDeclaration","start":1131,"end":1175,"loc":{"start":{"line":56,"column":0},"end":{"line":58,"column":1}},"id":{"type":"Identifier","start":1140,"end":1151,"loc":{"start":{"line":56,"column":9},"end":{"line":56,"column":20}},"name":"is_function"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1152,"end":1153,"loc":{"start":{"line":56,"column":21},"end":{"line":56,"column":22}},"name":"v"}],"body":{"type":"BlockStatement","start":1155,"end":1175,"loc":{"start":{"line":56,"column":24},"end":{"line":58,"column":1}},"body":[{"type":"ReturnStatement","start":1161,"end":1173,"loc":{"start":{"line":57,"column":4},"end":{"line":57,"column":16}},"argument":{"type":"Literal","start":1168,"end":1172,"loc":{"start":{"line":57,"column":11},"end":{"line":57,"column":15}},"value":true,"raw":"true"}}]}},{"type":"FunctionDeclaration","start":1344,"end":1852,"loc":{"start":{"line":70,"column":0},"end":{"line":89,"column":1}},"id":{"type":"Identifier","start":1353,"end":1371,"loc":{"start":{"line":70,"column":9},"end":{"line":70,"column":27}},"name":"__stringify_number"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1372,"end":1373,"loc":{"start":{"line":70,"column":28},"end":{"line":70,"column":29}},"name":"v"}],"body":{"type":"BlockStatement","start":1375,"end":1852,"loc":{"start":{"line":70,"column":31},"end":{"line":89,"column":1}},"body":[{"type":"IfStatement","start":1381,"end":1850,"loc":{"start":{"line":71,"column":4},"end":{"line":88,"column":5}},"test":{"type":"BinaryExpression","start":1385,"end":1392,"loc":{"start":{"line":71,"column":8},"end":{"line":71,"column":15}},"left":{"type":"Identifier","start":1385,"end":1386,"loc":{"start":{"line":71,"column":8},"end":{"line":71,"column":9}},"name":"v"},"operator":"===","right":{"type":"Literal","start":1391,"end":1392,"loc":{"start":{"line":71,"column":14},"end":{"line":71,"column":15}},"value":0,"raw":"0"}},"consequent":{"type":"BlockStatement","start":1394,"end":1420,"loc":{"start":{"line":71,"column":17},"end":{"line":73,"column":5}},"body":[{"type":"ReturnStatement","start":1404,"end":1414,"loc":{"start":{"line":72,"column":8},"end":{"line":72,"column":18}},"argument":{"type":"Literal","start":1411,"end":1413,"loc":{"start":{"line":72,"column":15},"end":{"line":72,"column":17}},"value":"","raw":"\"\""}}]},"alternate":{"type":"BlockStatement","start":1426,"end":1850,"loc":{"start":{"line":73,"column":11},"end":{"line":88,"column":5}},"body":[{"type":"VariableDeclaration","start":1436,"end":1453,"loc":{"start":{"line":74,"column":8},"end":{"line":74,"column":25}},"declarations":[{"type":"VariableDeclarator","start":1442,"end":1452,"loc":{"start":{"line":74,"column":14},"end":{"line":74,"column":24}},"id":{"type":"Identifier","start":1442,"end":1443,"loc":{"start":{"line":74,"column":14},"end":{"line":74,"column":15}},"name":"d"},"init":{"type":"BinaryExpression","start":1446,"end":1452,"loc":{"start":{"line":74,"column":18},"end":{"line":74,"column":24}},"left":{"type":"Identifier","start":1446,"end":1447,"loc":{"start":{"line":74,"column":18},"end":{"line":74,"column":19}},"name":"v"},"operator":"%","right":{"type":"Literal","start":1450,"end":1452,"loc":{"start":{"line":74,"column":22},"end":{"line":74,"column":24}},"value":10,"raw":"10"}}}],"kind":"const"},{"type":"ReturnStatement","start":1462,"end":1844,"loc":{"start":{"line":75,"column":8},"end":{"line":87,"column":14}},"argument":{"type":"BinaryExpression","start":1469,"end":1843,"loc":{"start":{"line":75,"column":15},"end":{"line":87,"column":13}},"left":{"type":"CallExpression","start":1469,"end":1495,"loc":{"start":{"line":75,"column":15},"end":{"line":75,"column":41}},"callee":{"type":"Identifier","start":1469,"end":1487,"loc":{"start":{"line":75,"column":15},"end":{"line":75,"column":33}},"name":"__stringify_number"},"arguments":[{"type":"BinaryExpression","start":1488,"end":1494,"loc":{"start":{"line":75,"column":34},"end":{"line":75,"column":40}},"left":{"type":"Identifier","start":1488,"end":1489,"loc":{"start":{"line":75,"column":34},"end":{"line":75,"column":35}},"name":"v"},"operator":"/","right":{"type":"Literal","start":1492,"end":1494,"loc":{"start":{"line":75,"column":38},"end":{"line":75,"column":40}},"value":10,"raw":"10"}}]},"operator":"+","right":{"type":"ConditionalExpression","start":1528,"end":1829,"loc":{"start":{"line":77,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1528,"end":1535,"loc":{"start":{"line":77,"column":16},"end":{"line":77,"column":23}},"left":{"type":"Identifier","start":1528,"end":1529,"loc":{"start":{"line":77,"column":16},"end":{"line":77,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1534,"end":1535,"loc":{"start":{"line":77,"column":22},"end":{"line":77,"column":23}},"value":0,"raw":"0"}},"consequent":{"type":"Literal","start":1538,"end":1541,"loc":{"start":{"line":77,"column":26},"end":{"line":77,"column":29}},"value":"0","raw":"\"0\""},"alternate":{"type":"ConditionalExpression","start":1560,"end":1829,"loc":{"start":{"line":78,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1560,"end":1567,"loc":{"start":{"line":78,"column":16},"end":{"line":78,"column":23}},"left":{"type":"Identifier","start":1560,"end":1561,"loc":{"start":{"line":78,"column":16},"end":{"line":78,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1566,"end":1567,"loc":{"start":{"line":78,"column":22},"end":{"line":78,"column":23}},"value":1,"raw":"1"}},"consequent":{"type":"Literal","start":1570,"end":1573,"loc":{"start":{"line":78,"column":26},"end":{"line":78,"column":29}},"value":"1","raw":"\"1\""},"alternate":{"type":"ConditionalExpression","start":1592,"end":1829,"loc":{"start":{"line":79,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1592,"end":1599,"loc":{"start":{"line":79,"column":16},"end":{"line":79,"column":23}},"left":{"type":"Identifier","start":1592,"end":1593,"loc":{"start":{"line":79,"column":16},"end":{"line":79,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1598,"end":1599,"loc":{"start":{"line":79,"column":22},"end":{"line":79,"column":23}},"value":2,"raw":"2"}},"consequent":{"type":"Literal","start":1602,"end":1605,"loc":{"start":{"line":79,"column":26},"end":{"line":79,"column":29}},"value":"2","raw":"\"2\""},"alternate":{"type":"ConditionalExpression","start":1624,"end":1829,"loc":{"start":{"line":80,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1624,"end":1631,"loc":{"start":{"line":80,"column":16},"end":{"line":80,"column":23}},"left":{"type":"Identifier","start":1624,"end":1625,"loc":{"start":{"line":80,"column":16},"end":{"line":80,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1630,"end":1631,"loc":{"start":{"line":80,"column":22},"end":{"line":80,"column":23}},"value":3,"raw":"3"}},"consequent":{"type":"Literal","start":1634,"end":1637,"loc":{"start":{"line":80,"column":26},"end":{"line":80,"column":29}},"value":"3","raw":"\"3\""},"alternate":{"type":"ConditionalExpression","start":1656,"end":1829,"loc":{"start":{"line":81,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1656,"end":1663,"loc":{"start":{"line":81,"column":16},"end":{"line":81,"column":23}},"left":{"type":"Identifier","start":1656,"end":1657,"loc":{"start":{"line":81,"column":16},"end":{"line":81,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1662,"end":1663,"loc":{"start":{"line":81,"column":22},"end":{"line":81,"column":23}},"value":4,"raw":"4"}},"consequent":{"type":"Literal","start":1666,"end":1669,"loc":{"start":{"line":81,"column":26},"end":{"line":81,"column":29}},"value":"4","raw":"\"4\""},"alternate":{"type":"ConditionalExpression","start":1688,"end":1829,"loc":{"start":{"line":82,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1688,"end":1695,"loc":{"start":{"line":82,"column":16},"end":{"line":82,"column":23}},"left":{"type":"Identifier","start":1688,"end":1689,"loc":{"start":{"line":82,"column":16},"end":{"line":82,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1694,"end":1695,"loc":{"start":{"line":82,"column":22},"end":{"line":82,"column":23}},"value":5,"raw":"5"}},"consequent":{"type":"Literal","start":1698,"end":1701,"loc":{"start":{"line":82,"column":26},"end":{"line":82,"column":29}},"value":"5","raw":"\"5\""},"alternate":{"type":"ConditionalExpression","start":1720,"end":1829,"loc":{"start":{"line":83,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1720,"end":1727,"loc":{"start":{"line":83,"column":16},"end":{"line":83,"column":23}},"left":{"type":"Identifier","start":1720,"end":1721,"loc":{"start":{"line":83,"column":16},"end":{"line":83,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1726,"end":1727,"loc":{"start":{"line":83,"column":22},"end":{"line":83,"column":23}},"value":6,"raw":"6"}},"consequent":{"type":"Literal","start":1730,"end":1733,"loc":{"start":{"line":83,"column":26},"end":{"line":83,"column":29}},"value":"6","raw":"\"6\""},"alternate":{"type":"ConditionalExpression","start":1752,"end":1829,"loc":{"start":{"line":84,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1752,"end":1759,"loc":{"start":{"line":84,"column":16},"end":{"line":84,"column":23}},"left":{"type":"Identifier","start":1752,"end":1753,"loc":{"start":{"line":84,"column":16},"end":{"line":84,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1758,"end":1759,"loc":{"start":{"line":84,"column":22},"end":{"line":84,"column":23}},"value":7,"raw":"7"}},"consequent":{"type":"Literal","start":1762,"end":1765,"loc":{"start":{"line":84,"column":26},"end":{"line":84,"column":29}},"value":"7","raw":"\"7\""},"alternate":{"type":"ConditionalExpression","start":1784,"end":1829,"loc":{"start":{"line":85,"column":16},"end":{"line":86,"column":29}},"test":{"type":"BinaryExpression","start":1784,"end":1791,"loc":{"start":{"line":85,"column":16},"end":{"line":85,"column":23}},"left":{"type":"Identifier","start":1784,"end":1785,"loc":{"start":{"line":85,"column":16},"end":{"line":85,"column":17}},"name":"d"},"operator":"===","right":{"type":"Literal","start":1790,"end":1791,"loc":{"start":{"line":85,"column":22},"end":{"line":85,"column":23}},"value":8,"raw":"8"}},"consequent":{"type":"Literal","start":1794,"end":1797,"loc":{"start":{"line":85,"column":26},"end":{"line":85,"column":29}},"value":"8","raw":"\"8\""},"alternate":{"type":"Literal","start":1826,"end":1829,"loc":{"start":{"line":86,"column":26},"end":{"line":86,"column":29}},"value":"9","raw":"\"9\""}}}}}}}}}}}}]}}]}},{"type":"ExpressionStatement","start":1854,"end":1878,"loc":{"start":{"line":91,"column":0},"end":{"line":91,"column":24}},"expression":{"type":"AssignmentExpression","start":1854,"end":1877,"loc":{"start":{"line":91,"column":0},"end":{"line":91,"column":23}},"operator":"=","left":{"type":"Identifier","start":1854,"end":1866,"loc":{"start":{"line":91,"column":0},"end":{"line":91,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1869,"end":1877,"loc":{"start":{"line":91,"column":15},"end":{"line":91,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":1879,"end":1941,"loc":{"start":{"line":92,"column":0},"end":{"line":94,"column":1}},"id":{"type":"Identifier","start":1888,"end":1897,"loc":{"start":{"line":92,"column":9},"end":{"line":92,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1898,"end":1899,"loc":{"start":{"line":92,"column":19},"end":{"line":92,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":1901,"end":1941,"loc":{"start":{"line":92,"column":22},"end":{"line":94,"column":1}},"body":[{"type":"ReturnStatement","start":1907,"end":1939,"loc":{"start":{"line":93,"column":4},"end":{"line":93,"column":36}},"argument":{"type":"Literal","start":1914,"end":1938,"loc":{"start":{"line":93,"column":11},"end":{"line":93,"column":35}},"value":"(unstringifiable type)","raw":"\"(unstringifiable type)\""}}]}},{"type":"ExpressionStatement","start":1942,"end":1989,"loc":{"start":{"line":95,"column":0},"end":{"line":95,"column":47}},"expression":{"type":"AssignmentExpression","start":1942,"end":1988,"loc":{"start":{"line":95,"column":0},"end":{"line":95,"column":46}},"operator":"=","left":{"type":"Identifier","start":1942,"end":1954,"loc":{"start":{"line":95,"column":0},"end":{"line":95,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1957,"end":1988,"loc":{"start":{"line":95,"column":15},"end":{"line":95,"column":46}},"value":"direct;constraint=v:undefined","raw":"\"direct;constraint=v:undefined\""}}},{"type":"FunctionDeclaration","start":1990,"end":2039,"loc":{"start":{"line":96,"column":0},"end":{"line":98,"column":1}},"id":{"type":"Identifier","start":1999,"end":2008,"loc":{"start":{"line":96,"column":9},"end":{"line":96,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2009,"end":2010,"loc":{"start":{"line":96,"column":19},"end":{"line":96,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2012,"end":2039,"loc":{"start":{"line":96,"column":22},"end":{"line":98,"column":1}},"body":[{"type":"ReturnStatement","start":2018,"end":2037,"loc":{"start":{"line":97,"column":4},"end":{"line":97,"column":23}},"argument":{"type":"Literal","start":2025,"end":2036,"loc":{"start":{"line":97,"column":11},"end":{"line":97,"column":22}},"value":"undefined","raw":"\"undefined\""}}]}},{"type":"ExpressionStatement","start":2040,"end":2085,"loc":{"start":{"line":99,"column":0},"end":{"line":99,"column":45}},"expression":{"type":"AssignmentExpression","start":2040,"end":2084,"loc":{"start":{"line":99,"column":0},"end":{"line":99,"column":44}},"operator":"=","left":{"type":"Identifier","start":2040,"end":2052,"loc":{"start":{"line":99,"column":0},"end":{"line":99,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2055,"end":2084,"loc":{"start":{"line":99,"column":15},"end":{"line":99,"column":44}},"value":"direct;constraint=v:boolean","raw":"\"direct;constraint=v:boolean\""}}},{"type":"FunctionDeclaration","start":2086,"end":2144,"loc":{"start":{"line":100,"column":0},"end":{"line":102,"column":1}},"id":{"type":"Identifier","start":2095,"end":2104,"loc":{"start":{"line":100,"column":9},"end":{"line":100,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2105,"end":2106,"loc":{"start":{"line":100,"column":19},"end":{"line":100,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2108,"end":2144,"loc":{"start":{"line":100,"column":22},"end":{"line":102,"column":1}},"body":[{"type":"ReturnStatement","start":2114,"end":2142,"loc":{"start":{"line":101,"column":4},"end":{"line":101,"column":32}},"argument":{"type":"ConditionalExpression","start":2121,"end":2141,"loc":{"start":{"line":101,"column":11},"end":{"line":101,"column":31}},"test":{"type":"Identifier","start":2121,"end":2122,"loc":{"start":{"line":101,"column":11},"end":{"line":101,"column":12}},"name":"v"},"consequent":{"type":"Literal","start":2125,"end":2131,"loc":{"start":{"line":101,"column":15},"end":{"line":101,"column":21}},"value":"true","raw":"\"true\""},"alternate":{"type":"Literal","start":2134,"end":2141,"loc":{"start":{"line":101,"column":24},"end":{"line":101,"column":31}},"value":"false","raw":"\"false\""}}}]}},{"type":"ExpressionStatement","start":2145,"end":2189,"loc":{"start":{"line":103,"column":0},"end":{"line":103,"column":44}},"expression":{"type":"AssignmentExpression","start":2145,"end":2188,"loc":{"start":{"line":103,"column":0},"end":{"line":103,"column":43}},"operator":"=","left":{"type":"Identifier","start":2145,"end":2157,"loc":{"start":{"line":103,"column":0},"end":{"line":103,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2160,"end":2188,"loc":{"start":{"line":103,"column":15},"end":{"line":103,"column":43}},"value":"direct;constraint=v:number","raw":"\"direct;constraint=v:number\""}}},{"type":"FunctionDeclaration","start":2190,"end":2320,"loc":{"start":{"line":104,"column":0},"end":{"line":108,"column":1}},"id":{"type":"Identifier","start":2199,"end":2208,"loc":{"start":{"line":104,"column":9},"end":{"line":104,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2209,"end":2210,"loc":{"start":{"line":104,"column":19},"end":{"line":104,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2212,"end":2320,"loc":{"start":{"line":104,"column":22},"end":{"line":108,"column":1}},"body":[{"type":"ReturnStatement","start":2218,"end":2318,"loc":{"start":{"line":105,"column":4},"end":{"line":107,"column":32}},"argument":{"type":"ConditionalExpression","start":2225,"end":2317,"loc":{"start":{"line":105,"column":11},"end":{"line":107,"column":31}},"test":{"type":"BinaryExpression","start":2225,"end":2232,"loc":{"start":{"line":105,"column":11},"end":{"line":105,"column":18}},"left":{"type":"Identifier","start":2225,"end":2226,"loc":{"start":{"line":105,"column":11},"end":{"line":105,"column":12}},"name":"v"},"operator":"===","right":{"type":"Literal","start":2231,"end":2232,"loc":{"start":{"line":105,"column":17},"end":{"line":105,"column":18}},"value":0,"raw":"0"}},"consequent":{"type":"Literal","start":2235,"end":2238,"loc":{"start":{"line":105,"column":21},"end":{"line":105,"column":24}},"value":"0","raw":"\"0\""},"alternate":{"type":"ConditionalExpression","start":2249,"end":2317,"loc":{"start":{"line":106,"column":10},"end":{"line":107,"column":31}},"test":{"type":"BinaryExpression","start":2249,"end":2254,"loc":{"start":{"line":106,"column":10},"end":{"line":106,"column":15}},"left":{"type":"Identifier","start":2249,"end":2250,"loc":{"start":{"line":106,"column":10},"end":{"line":106,"column":11}},"name":"v"},"operator":"<","right":{"type":"Literal","start":2253,"end":2254,"loc":{"start":{"line":106,"column":14},"end":{"line":106,"column":15}},"value":0,"raw":"0"}},"consequent":{"type":"BinaryExpression","start":2257,"end":2285,"loc":{"start":{"line":106,"column":18},"end":{"line":106,"column":46}},"left":{"type":"Literal","start":2257,"end":2260,"loc":{"start":{"line":106,"column":18},"end":{"line":106,"column":21}},"value":"-","raw":"\"-\""},"operator":"+","right":{"type":"CallExpression","start":2263,"end":2285,"loc":{"start":{"line":106,"column":24},"end":{"line":106,"column":46}},"callee":{"type":"Identifier","start":2263,"end":2281,"loc":{"start":{"line":106,"column":24},"end":{"line":106,"column":42}},"name":"__stringify_number"},"arguments":[{"type":"UnaryExpression","start":2282,"end":2284,"loc":{"start":{"line":106,"column":43},"end":{"line":106,"column":45}},"operator":"-","prefix":true,"argument":{"type":"Identifier","start":2283,"end":2284,"loc":{"start":{"line":106,"column":44},"end":{"line":106,"column":45}},"name":"v"}}]}},"alternate":{"type":"CallExpression","start":2296,"end":2317,"loc":{"start":{"line":107,"column":10},"end":{"line":107,"column":31}},"callee":{"type":"Identifier","start":2296,"end":2314,"loc":{"start":{"line":107,"column":10},"end":{"line":107,"column":28}},"name":"__stringify_number"},"arguments":[{"type":"Identifier","start":2315,"end":2316,"loc":{"start":{"line":107,"column":29},"end":{"line":107,"column":30}},"name":"v"}]}}}}]}},{"type":"ExpressionStatement","start":2321,"end":2365,"loc":{"start":{"line":109,"column":0},"end":{"line":109,"column":44}},"expression":{"type":"AssignmentExpression","start":2321,"end":2364,"loc":{"start":{"line":109,"column":0},"end":{"line":109,"column":43}},"operator":"=","left":{"type":"Identifier","start":2321,"end":2333,"loc":{"start":{"line":109,"column":0},"end":{"line":109,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2336,"end":2364,"loc":{"start":{"line":109,"column":15},"end":{"line":109,"column":43}},"value":"direct;constraint=v:string","raw":"\"direct;constraint=v:string\""}}},{"type":"FunctionDeclaration","start":2366,"end":2405,"loc":{"start":{"line":110,"column":0},"end":{"line":112,"column":1}},"id":{"type":"Identifier","start":2375,"end":2384,"loc":{"start":{"line":110,"column":9},"end":{"line":110,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2385,"end":2386,"loc":{"start":{"line":110,"column":19},"end":{"line":110,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2388,"end":2405,"loc":{"start":{"line":110,"column":22},"end":{"line":112,"column":1}},"body":[{"type":"ReturnStatement","start":2394,"end":2403,"loc":{"start":{"line":111,"column":4},"end":{"line":111,"column":13}},"argument":{"type":"Identifier","start":2401,"end":2402,"loc":{"start":{"line":111,"column":11},"end":{"line":111,"column":12}},"name":"v"}}]}},{"type":"ExpressionStatement","start":2406,"end":2452,"loc":{"start":{"line":113,"column":0},"end":{"line":113,"column":46}},"expression":{"type":"AssignmentExpression","start":2406,"end":2451,"loc":{"start":{"line":113,"column":0},"end":{"line":113,"column":45}},"operator":"=","left":{"type":"Identifier","start":2406,"end":2418,"loc":{"start":{"line":113,"column":0},"end":{"line":113,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2421,"end":2451,"loc":{"start":{"line":113,"column":15},"end":{"line":113,"column":45}},"value":"direct;constraint=v:function","raw":"\"direct;constraint=v:function\""}}},{"type":"FunctionDeclaration","start":2453,"end":2503,"loc":{"start":{"line":114,"column":0},"end":{"line":116,"column":1}},"id":{"type":"Identifier","start":2462,"end":2471,"loc":{"start":{"line":114,"column":9},"end":{"line":114,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2472,"end":2473,"loc":{"start":{"line":114,"column":19},"end":{"line":114,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2475,"end":2503,"loc":{"start":{"line":114,"column":22},"end":{"line":116,"column":1}},"body":[{"type":"ReturnStatement","start":2481,"end":2501,"loc":{"start":{"line":115,"column":4},"end":{"line":115,"column":24}},"argument":{"type":"Literal","start":2488,"end":2500,"loc":{"start":{"line":115,"column":11},"end":{"line":115,"column":23}},"value":"(function)","raw":"\"(function)\""}}]}}],"sourceType":"module"}
-"#;*/
-
-const IMPORT_MATH_FFI: &'static str = r#"@SourceImports
-math_sin math sin number number
-"#;
-const IMPORT_MATH: &'static str = r#"
-{"type":"Program","start":0,"end":1617,"loc":{"start":{"line":1,"column":0},"end":{"line":59,"column":0}},"body":[{"type":"ImportDeclaration","start":0,"end":32,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":32}},"specifiers":[{"type":"ImportSpecifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"imported":{"type":"Identifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"name":"Infinity"},"local":{"type":"Identifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"name":"Infinity"}}],"source":{"type":"Literal","start":25,"end":31,"loc":{"start":{"line":1,"column":25},"end":{"line":1,"column":31}},"value":"misc","raw":"\"misc\""}},{"type":"ImportDeclaration","start":33,"end":69,"loc":{"start":{"line":2,"column":0},"end":{"line":2,"column":36}},"specifiers":[{"type":"ImportSpecifier","start":42,"end":50,"loc":{"start":{"line":2,"column":9},"end":{"line":2,"column":17}},"imported":{"type":"Identifier","start":42,"end":50,"loc":{"start":{"line":2,"column":9},"end":{"line":2,"column":17}},"name":"math_sin"},"local":{"type":"Identifier","start":42,"end":50,"loc":{"start":{"line":2,"column":9},"end":{"line":2,"column":17}},"name":"math_sin"}}],"source":{"type":"Literal","start":58,"end":68,"loc":{"start":{"line":2,"column":25},"end":{"line":2,"column":35}},"value":"math.ffi","raw":"\"math.ffi\""}},{"type":"VariableDeclaration","start":71,"end":104,"loc":{"start":{"line":4,"column":0},"end":{"line":4,"column":33}},"declarations":[{"type":"VariableDeclarator","start":77,"end":103,"loc":{"start":{"line":4,"column":6},"end":{"line":4,"column":32}},"id":{"type":"Identifier","start":77,"end":83,"loc":{"start":{"line":4,"column":6},"end":{"line":4,"column":12}},"name":"math_E"},"init":{"type":"Literal","start":86,"end":103,"loc":{"start":{"line":4,"column":15},"end":{"line":4,"column":32}},"value":2.718281828459045,"raw":"2.718281828459045"}}],"kind":"const"},{"type":"VariableDeclaration","start":105,"end":141,"loc":{"start":{"line":5,"column":0},"end":{"line":5,"column":36}},"declarations":[{"type":"VariableDeclarator","start":111,"end":140,"loc":{"start":{"line":5,"column":6},"end":{"line":5,"column":35}},"id":{"type":"Identifier","start":111,"end":119,"loc":{"start":{"line":5,"column":6},"end":{"line":5,"column":14}},"name":"math_LN2"},"init":{"type":"Literal","start":122,"end":140,"loc":{"start":{"line":5,"column":17},"end":{"line":5,"column":35}},"value":0.6931471805599453,"raw":"0.6931471805599453"}}],"kind":"const"},{"type":"VariableDeclaration","start":142,"end":178,"loc":{"start":{"line":6,"column":0},"end":{"line":6,"column":36}},"declarations":[{"type":"VariableDeclarator","start":148,"end":177,"loc":{"start":{"line":6,"column":6},"end":{"line":6,"column":35}},"id":{"type":"Identifier","start":148,"end":157,"loc":{"start":{"line":6,"column":6},"end":{"line":6,"column":15}},"name":"math_LN10"},"init":{"type":"Literal","start":160,"end":177,"loc":{"start":{"line":6,"column":18},"end":{"line":6,"column":35}},"value":2.302585092994046,"raw":"2.302585092994046"}}],"kind":"const"},{"type":"VariableDeclaration","start":179,"end":217,"loc":{"start":{"line":7,"column":0},"end":{"line":7,"column":38}},"declarations":[{"type":"VariableDeclarator","start":185,"end":216,"loc":{"start":{"line":7,"column":6},"end":{"line":7,"column":37}},"id":{"type":"Identifier","start":185,"end":195,"loc":{"start":{"line":7,"column":6},"end":{"line":7,"column":16}},"name":"math_LOG2E"},"init":{"type":"Literal","start":198,"end":216,"loc":{"start":{"line":7,"column":19},"end":{"line":7,"column":37}},"value":1.4426950408889634,"raw":"1.4426950408889634"}}],"kind":"const"},{"type":"VariableDeclaration","start":218,"end":257,"loc":{"start":{"line":8,"column":0},"end":{"line":8,"column":39}},"declarations":[{"type":"VariableDeclarator","start":224,"end":256,"loc":{"start":{"line":8,"column":6},"end":{"line":8,"column":38}},"id":{"type":"Identifier","start":224,"end":235,"loc":{"start":{"line":8,"column":6},"end":{"line":8,"column":17}},"name":"math_LOG10E"},"init":{"type":"Literal","start":238,"end":256,"loc":{"start":{"line":8,"column":20},"end":{"line":8,"column":38}},"value":0.4342944819032518,"raw":"0.4342944819032518"}}],"kind":"const"},{"type":"VariableDeclaration","start":258,"end":293,"loc":{"start":{"line":9,"column":0},"end":{"line":9,"column":35}},"declarations":[{"type":"VariableDeclarator","start":264,"end":292,"loc":{"start":{"line":9,"column":6},"end":{"line":9,"column":34}},"id":{"type":"Identifier","start":264,"end":271,"loc":{"start":{"line":9,"column":6},"end":{"line":9,"column":13}},"name":"math_PI"},"init":{"type":"Literal","start":274,"end":292,"loc":{"start":{"line":9,"column":16},"end":{"line":9,"column":34}},"value":3.141592653589793,"raw":"3.1415926535897932"}}],"kind":"const"},{"type":"VariableDeclaration","start":294,"end":334,"loc":{"start":{"line":10,"column":0},"end":{"line":10,"column":40}},"declarations":[{"type":"VariableDeclarator","start":300,"end":333,"loc":{"start":{"line":10,"column":6},"end":{"line":10,"column":39}},"id":{"type":"Identifier","start":300,"end":312,"loc":{"start":{"line":10,"column":6},"end":{"line":10,"column":18}},"name":"math_SQRT1_2"},"init":{"type":"Literal","start":315,"end":333,"loc":{"start":{"line":10,"column":21},"end":{"line":10,"column":39}},"value":0.7071067811865476,"raw":"0.7071067811865476"}}],"kind":"const"},{"type":"VariableDeclaration","start":335,"end":373,"loc":{"start":{"line":11,"column":0},"end":{"line":11,"column":38}},"declarations":[{"type":"VariableDeclarator","start":341,"end":372,"loc":{"start":{"line":11,"column":6},"end":{"line":11,"column":37}},"id":{"type":"Identifier","start":341,"end":351,"loc":{"start":{"line":11,"column":6},"end":{"line":11,"column":16}},"name":"math_SQRT2"},"init":{"type":"Literal","start":354,"end":372,"loc":{"start":{"line":11,"column":19},"end":{"line":11,"column":37}},"value":1.4142135623730951,"raw":"1.4142135623730951"}}],"kind":"const"},{"type":"ExpressionStatement","start":632,"end":676,"loc":{"start":{"line":18,"column":0},"end":{"line":18,"column":44}},"expression":{"type":"AssignmentExpression","start":632,"end":675,"loc":{"start":{"line":18,"column":0},"end":{"line":18,"column":43}},"operator":"=","left":{"type":"Identifier","start":632,"end":644,"loc":{"start":{"line":18,"column":0},"end":{"line":18,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":647,"end":675,"loc":{"start":{"line":18,"column":15},"end":{"line":18,"column":43}},"value":"direct;constraint=x:number","raw":"\"direct;constraint=x:number\""}}},{"type":"FunctionDeclaration","start":677,"end":729,"loc":{"start":{"line":19,"column":0},"end":{"line":21,"column":1}},"id":{"type":"Identifier","start":686,"end":694,"loc":{"start":{"line":19,"column":9},"end":{"line":19,"column":17}},"name":"math_abs"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":695,"end":696,"loc":{"start":{"line":19,"column":18},"end":{"line":19,"column":19}},"name":"x"}],"body":{"type":"BlockStatement","start":698,"end":729,"loc":{"start":{"line":19,"column":21},"end":{"line":21,"column":1}},"body":[{"type":"ReturnStatement","start":704,"end":727,"loc":{"start":{"line":20,"column":4},"end":{"line":20,"column":27}},"argument":{"type":"ConditionalExpression","start":711,"end":726,"loc":{"start":{"line":20,"column":11},"end":{"line":20,"column":26}},"test":{"type":"BinaryExpression","start":711,"end":717,"loc":{"start":{"line":20,"column":11},"end":{"line":20,"column":17}},"left":{"type":"Identifier","start":711,"end":712,"loc":{"start":{"line":20,"column":11},"end":{"line":20,"column":12}},"name":"x"},"operator":">=","right":{"type":"Literal","start":716,"end":717,"loc":{"start":{"line":20,"column":16},"end":{"line":20,"column":17}},"value":0,"raw":"0"}},"consequent":{"type":"Identifier","start":720,"end":721,"loc":{"start":{"line":20,"column":20},"end":{"line":20,"column":21}},"name":"x"},"alternate":{"type":"UnaryExpression","start":724,"end":726,"loc":{"start":{"line":20,"column":24},"end":{"line":20,"column":26}},"operator":"-","prefix":true,"argument":{"type":"Identifier","start":725,"end":726,"loc":{"start":{"line":20,"column":25},"end":{"line":20,"column":26}},"name":"x"}}}}]}},{"type":"ExpressionStatement","start":731,"end":775,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":44}},"expression":{"type":"AssignmentExpression","start":731,"end":774,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":43}},"operator":"=","left":{"type":"Identifier","start":731,"end":743,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":746,"end":774,"loc":{"start":{"line":23,"column":15},"end":{"line":23,"column":43}},"value":"direct;constraint=x:number","raw":"\"direct;constraint=x:number\""}}},{"type":"FunctionDeclaration","start":776,"end":840,"loc":{"start":{"line":24,"column":0},"end":{"line":26,"column":1}},"id":{"type":"Identifier","start":785,"end":794,"loc":{"start":{"line":24,"column":9},"end":{"line":24,"column":18}},"name":"math_sign"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":795,"end":796,"loc":{"start":{"line":24,"column":19},"end":{"line":24,"column":20}},"name":"x"}],"body":{"type":"BlockStatement","start":798,"end":840,"loc":{"start":{"line":24,"column":22},"end":{"line":26,"column":1}},"body":[{"type":"ReturnStatement","start":804,"end":838,"loc":{"start":{"line":25,"column":4},"end":{"line":25,"column":38}},"argument":{"type":"ConditionalExpression","start":811,"end":837,"loc":{"start":{"line":25,"column":11},"end":{"line":25,"column":37}},"test":{"type":"BinaryExpression","start":811,"end":816,"loc":{"start":{"line":25,"column":11},"end":{"line":25,"column":16}},"left":{"type":"Identifier","start":811,"end":812,"loc":{"start":{"line":25,"column":11},"end":{"line":25,"column":12}},"name":"x"},"operator":">","right":{"type":"Literal","start":815,"end":816,"loc":{"start":{"line":25,"column":15},"end":{"line":25,"column":16}},"value":0,"raw":"0"}},"consequent":{"type":"Literal","start":819,"end":820,"loc":{"start":{"line":25,"column":19},"end":{"line":25,"column":20}},"value":1,"raw":"1"},"alternate":{"type":"ConditionalExpression","start":823,"end":837,"loc":{"start":{"line":25,"column":23},"end":{"line":25,"column":37}},"test":{"type":"BinaryExpression","start":823,"end":828,"loc":{"start":{"line":25,"column":23},"end":{"line":25,"column":28}},"left":{"type":"Identifier","start":823,"end":824,"loc":{"start":{"line":25,"column":23},"end":{"line":25,"column":24}},"name":"x"},"operator":"<","right":{"type":"Literal","start":827,"end":828,"loc":{"start":{"line":25,"column":27},"end":{"line":25,"column":28}},"value":0,"raw":"0"}},"consequent":{"type":"UnaryExpression","start":831,"end":833,"loc":{"start":{"line":25,"column":31},"end":{"line":25,"column":33}},"operator":"-","prefix":true,"argument":{"type":"Literal","start":832,"end":833,"loc":{"start":{"line":25,"column":32},"end":{"line":25,"column":33}},"value":1,"raw":"1"}},"alternate":{"type":"Literal","start":836,"end":837,"loc":{"start":{"line":25,"column":36},"end":{"line":25,"column":37}},"value":0,"raw":"0"}}}}]}},{"type":"ExpressionStatement","start":941,"end":965,"loc":{"start":{"line":30,"column":0},"end":{"line":30,"column":24}},"expression":{"type":"AssignmentExpression","start":941,"end":964,"loc":{"start":{"line":30,"column":0},"end":{"line":30,"column":23}},"operator":"=","left":{"type":"Identifier","start":941,"end":953,"loc":{"start":{"line":30,"column":0},"end":{"line":30,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":956,"end":964,"loc":{"start":{"line":30,"column":15},"end":{"line":30,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":966,"end":1011,"loc":{"start":{"line":31,"column":0},"end":{"line":33,"column":1}},"id":{"type":"Identifier","start":975,"end":983,"loc":{"start":{"line":31,"column":9},"end":{"line":31,"column":17}},"name":"math_max"},"expression":false,"generator":false,"params":[],"body":{"type":"BlockStatement","start":986,"end":1011,"loc":{"start":{"line":31,"column":20},"end":{"line":33,"column":1}},"body":[{"type":"ReturnStatement","start":992,"end":1009,"loc":{"start":{"line":32,"column":4},"end":{"line":32,"column":21}},"argument":{"type":"UnaryExpression","start":999,"end":1008,"loc":{"start":{"line":32,"column":11},"end":{"line":32,"column":20}},"operator":"-","prefix":true,"argument":{"type":"Identifier","start":1000,"end":1008,"loc":{"start":{"line":32,"column":12},"end":{"line":32,"column":20}},"name":"Infinity"}}}]}},{"type":"ExpressionStatement","start":1012,"end":1056,"loc":{"start":{"line":34,"column":0},"end":{"line":34,"column":44}},"expression":{"type":"AssignmentExpression","start":1012,"end":1055,"loc":{"start":{"line":34,"column":0},"end":{"line":34,"column":43}},"operator":"=","left":{"type":"Identifier","start":1012,"end":1024,"loc":{"start":{"line":34,"column":0},"end":{"line":34,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1027,"end":1055,"loc":{"start":{"line":34,"column":15},"end":{"line":34,"column":43}},"value":"direct;constraint=x:number","raw":"\"direct;constraint=x:number\""}}},{"type":"FunctionDeclaration","start":1057,"end":1095,"loc":{"start":{"line":35,"column":0},"end":{"line":37,"column":1}},"id":{"type":"Identifier","start":1066,"end":1074,"loc":{"start":{"line":35,"column":9},"end":{"line":35,"column":17}},"name":"math_max"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1075,"end":1076,"loc":{"start":{"line":35,"column":18},"end":{"line":35,"column":19}},"name":"x"}],"body":{"type":"BlockStatement","start":1078,"end":1095,"loc":{"start":{"line":35,"column":21},"end":{"line":37,"column":1}},"body":[{"type":"ReturnStatement","start":1084,"end":1093,"loc":{"start":{"line":36,"column":4},"end":{"line":36,"column":13}},"argument":{"type":"Identifier","start":1091,"end":1092,"loc":{"start":{"line":36,"column":11},"end":{"line":36,"column":12}},"name":"x"}}]}},{"type":"ExpressionStatement","start":1096,"end":1149,"loc":{"start":{"line":38,"column":0},"end":{"line":38,"column":53}},"expression":{"type":"AssignmentExpression","start":1096,"end":1148,"loc":{"start":{"line":38,"column":0},"end":{"line":38,"column":52}},"operator":"=","left":{"type":"Identifier","start":1096,"end":1108,"loc":{"start":{"line":38,"column":0},"end":{"line":38,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1111,"end":1148,"loc":{"start":{"line":38,"column":15},"end":{"line":38,"column":52}},"value":"direct;constraint=x:number,y:number","raw":"\"direct;constraint=x:number,y:number\""}}},{"type":"FunctionDeclaration","start":1150,"end":1203,"loc":{"start":{"line":39,"column":0},"end":{"line":41,"column":1}},"id":{"type":"Identifier","start":1159,"end":1167,"loc":{"start":{"line":39,"column":9},"end":{"line":39,"column":17}},"name":"math_max"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1168,"end":1169,"loc":{"start":{"line":39,"column":18},"end":{"line":39,"column":19}},"name":"x"},{"type":"Identifier","start":1171,"end":1172,"loc":{"start":{"line":39,"column":21},"end":{"line":39,"column":22}},"name":"y"}],"body":{"type":"BlockStatement","start":1174,"end":1203,"loc":{"start":{"line":39,"column":24},"end":{"line":41,"column":1}},"body":[{"type":"ReturnStatement","start":1180,"end":1201,"loc":{"start":{"line":40,"column":4},"end":{"line":40,"column":25}},"argument":{"type":"ConditionalExpression","start":1187,"end":1200,"loc":{"start":{"line":40,"column":11},"end":{"line":40,"column":24}},"test":{"type":"BinaryExpression","start":1187,"end":1192,"loc":{"start":{"line":40,"column":11},"end":{"line":40,"column":16}},"left":{"type":"Identifier","start":1187,"end":1188,"loc":{"start":{"line":40,"column":11},"end":{"line":40,"column":12}},"name":"x"},"operator":"<","right":{"type":"Identifier","start":1191,"end":1192,"loc":{"start":{"line":40,"column":15},"end":{"line":40,"column":16}},"name":"y"}},"consequent":{"type":"Identifier","start":1195,"end":1196,"loc":{"start":{"line":40,"column":19},"end":{"line":40,"column":20}},"name":"y"},"alternate":{"type":"Identifier","start":1199,"end":1200,"loc":{"start":{"line":40,"column":23},"end":{"line":40,"column":24}},"name":"x"}}}]}},{"type":"ExpressionStatement","start":1205,"end":1229,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":24}},"expression":{"type":"AssignmentExpression","start":1205,"end":1228,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":23}},"operator":"=","left":{"type":"Identifier","start":1205,"end":1217,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1220,"end":1228,"loc":{"start":{"line":43,"column":15},"end":{"line":43,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":1230,"end":1274,"loc":{"start":{"line":44,"column":0},"end":{"line":46,"column":1}},"id":{"type":"Identifier","start":1239,"end":1247,"loc":{"start":{"line":44,"column":9},"end":{"line":44,"column":17}},"name":"math_min"},"expression":false,"generator":false,"params":[],"body":{"type":"BlockStatement","start":1250,"end":1274,"loc":{"start":{"line":44,"column":20},"end":{"line":46,"column":1}},"body":[{"type":"ReturnStatement","start":1256,"end":1272,"loc":{"start":{"line":45,"column":4},"end":{"line":45,"column":20}},"argument":{"type":"Identifier","start":1263,"end":1271,"loc":{"start":{"line":45,"column":11},"end":{"line":45,"column":19}},"name":"Infinity"}}]}},{"type":"ExpressionStatement","start":1275,"end":1319,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":44}},"expression":{"type":"AssignmentExpression","start":1275,"end":1318,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":43}},"operator":"=","left":{"type":"Identifier","start":1275,"end":1287,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1290,"end":1318,"loc":{"start":{"line":47,"column":15},"end":{"line":47,"column":43}},"value":"direct;constraint=x:number","raw":"\"direct;constraint=x:number\""}}},{"type":"FunctionDeclaration","start":1320,"end":1358,"loc":{"start":{"line":48,"column":0},"end":{"line":50,"column":1}},"id":{"type":"Identifier","start":1329,"end":1337,"loc":{"start":{"line":48,"column":9},"end":{"line":48,"column":17}},"name":"math_min"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1338,"end":1339,"loc":{"start":{"line":48,"column":18},"end":{"line":48,"column":19}},"name":"x"}],"body":{"type":"BlockStatement","start":1341,"end":1358,"loc":{"start":{"line":48,"column":21},"end":{"line":50,"column":1}},"body":[{"type":"ReturnStatement","start":1347,"end":1356,"loc":{"start":{"line":49,"column":4},"end":{"line":49,"column":13}},"argument":{"type":"Identifier","start":1354,"end":1355,"loc":{"start":{"line":49,"column":11},"end":{"line":49,"column":12}},"name":"x"}}]}},{"type":"ExpressionStatement","start":1359,"end":1412,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":53}},"expression":{"type":"AssignmentExpression","start":1359,"end":1411,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":52}},"operator":"=","left":{"type":"Identifier","start":1359,"end":1371,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1374,"end":1411,"loc":{"start":{"line":51,"column":15},"end":{"line":51,"column":52}},"value":"direct;constraint=x:number,y:number","raw":"\"direct;constraint=x:number,y:number\""}}},{"type":"FunctionDeclaration","start":1413,"end":1466,"loc":{"start":{"line":52,"column":0},"end":{"line":54,"column":1}},"id":{"type":"Identifier","start":1422,"end":1430,"loc":{"start":{"line":52,"column":9},"end":{"line":52,"column":17}},"name":"math_min"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1431,"end":1432,"loc":{"start":{"line":52,"column":18},"end":{"line":52,"column":19}},"name":"x"},{"type":"Identifier","start":1434,"end":1435,"loc":{"start":{"line":52,"column":21},"end":{"line":52,"column":22}},"name":"y"}],"body":{"type":"BlockStatement","start":1437,"end":1466,"loc":{"start":{"line":52,"column":24},"end":{"line":54,"column":1}},"body":[{"type":"ReturnStatement","start":1443,"end":1464,"loc":{"start":{"line":53,"column":4},"end":{"line":53,"column":25}},"argument":{"type":"ConditionalExpression","start":1450,"end":1463,"loc":{"start":{"line":53,"column":11},"end":{"line":53,"column":24}},"test":{"type":"BinaryExpression","start":1450,"end":1455,"loc":{"start":{"line":53,"column":11},"end":{"line":53,"column":16}},"left":{"type":"Identifier","start":1450,"end":1451,"loc":{"start":{"line":53,"column":11},"end":{"line":53,"column":12}},"name":"x"},"operator":"<","right":{"type":"Identifier","start":1454,"end":1455,"loc":{"start":{"line":53,"column":15},"end":{"line":53,"column":16}},"name":"y"}},"consequent":{"type":"Identifier","start":1458,"end":1459,"loc":{"start":{"line":53,"column":19},"end":{"line":53,"column":20}},"name":"x"},"alternate":{"type":"Identifier","start":1462,"end":1463,"loc":{"start":{"line":53,"column":23},"end":{"line":53,"column":24}},"name":"y"}}}]}},{"type":"ExportNamedDeclaration","start":1468,"end":1616,"loc":{"start":{"line":56,"column":0},"end":{"line":58,"column":46}},"declaration":null,"specifiers":[{"type":"ExportSpecifier","start":1481,"end":1487,"loc":{"start":{"line":57,"column":4},"end":{"line":57,"column":10}},"local":{"type":"Identifier","start":1481,"end":1487,"loc":{"start":{"line":57,"column":4},"end":{"line":57,"column":10}},"name":"math_E"},"exported":{"type":"Identifier","start":1481,"end":1487,"loc":{"start":{"line":57,"column":4},"end":{"line":57,"column":10}},"name":"math_E"}},{"type":"ExportSpecifier","start":1489,"end":1497,"loc":{"start":{"line":57,"column":12},"end":{"line":57,"column":20}},"local":{"type":"Identifier","start":1489,"end":1497,"loc":{"start":{"line":57,"column":12},"end":{"line":57,"column":20}},"name":"math_LN2"},"exported":{"type":"Identifier","start":1489,"end":1497,"loc":{"start":{"line":57,"column":12},"end":{"line":57,"column":20}},"name":"math_LN2"}},{"type":"ExportSpecifier","start":1499,"end":1508,"loc":{"start":{"line":57,"column":22},"end":{"line":57,"column":31}},"local":{"type":"Identifier","start":1499,"end":1508,"loc":{"start":{"line":57,"column":22},"end":{"line":57,"column":31}},"name":"math_LN10"},"exported":{"type":"Identifier","start":1499,"end":1508,"loc":{"start":{"line":57,"column":22},"end":{"line":57,"column":31}},"name":"math_LN10"}},{"type":"ExportSpecifier","start":1510,"end":1520,"loc":{"start":{"line":57,"column":33},"end":{"line":57,"column":43}},"local":{"type":"Identifier","start":1510,"end":1520,"loc":{"start":{"line":57,"column":33},"end":{"line":57,"column":43}},"name":"math_LOG2E"},"exported":{"type":"Identifier","start":1510,"end":1520,"loc":{"start":{"line":57,"column":33},"end":{"line":57,"column":43}},"name":"math_LOG2E"}},{"type":"ExportSpecifier","start":1522,"end":1533,"loc":{"start":{"line":57,"column":45},"end":{"line":57,"column":56}},"local":{"type":"Identifier","start":1522,"end":1533,"loc":{"start":{"line":57,"column":45},"end":{"line":57,"column":56}},"name":"math_LOG10E"},"exported":{"type":"Identifier","start":1522,"end":1533,"loc":{"start":{"line":57,"column":45},"end":{"line":57,"column":56}},"name":"math_LOG10E"}},{"type":"ExportSpecifier","start":1535,"end":1542,"loc":{"start":{"line":57,"column":58},"end":{"line":57,"column":65}},"local":{"type":"Identifier","start":1535,"end":1542,"loc":{"start":{"line":57,"column":58},"end":{"line":57,"column":65}},"name":"math_PI"},"exported":{"type":"Identifier","start":1535,"end":1542,"loc":{"start":{"line":57,"column":58},"end":{"line":57,"column":65}},"name":"math_PI"}},{"type":"ExportSpecifier","start":1544,"end":1556,"loc":{"start":{"line":57,"column":67},"end":{"line":57,"column":79}},"local":{"type":"Identifier","start":1544,"end":1556,"loc":{"start":{"line":57,"column":67},"end":{"line":57,"column":79}},"name":"math_SQRT1_2"},"exported":{"type":"Identifier","start":1544,"end":1556,"loc":{"start":{"line":57,"column":67},"end":{"line":57,"column":79}},"name":"math_SQRT1_2"}},{"type":"ExportSpecifier","start":1558,"end":1568,"loc":{"start":{"line":57,"column":81},"end":{"line":57,"column":91}},"local":{"type":"Identifier","start":1558,"end":1568,"loc":{"start":{"line":57,"column":81},"end":{"line":57,"column":91}},"name":"math_SQRT2"},"exported":{"type":"Identifier","start":1558,"end":1568,"loc":{"start":{"line":57,"column":81},"end":{"line":57,"column":91}},"name":"math_SQRT2"}},{"type":"ExportSpecifier","start":1574,"end":1582,"loc":{"start":{"line":58,"column":4},"end":{"line":58,"column":12}},"local":{"type":"Identifier","start":1574,"end":1582,"loc":{"start":{"line":58,"column":4},"end":{"line":58,"column":12}},"name":"math_abs"},"exported":{"type":"Identifier","start":1574,"end":1582,"loc":{"start":{"line":58,"column":4},"end":{"line":58,"column":12}},"name":"math_abs"}},{"type":"ExportSpecifier","start":1584,"end":1593,"loc":{"start":{"line":58,"column":14},"end":{"line":58,"column":23}},"local":{"type":"Identifier","start":1584,"end":1593,"loc":{"start":{"line":58,"column":14},"end":{"line":58,"column":23}},"name":"math_sign"},"exported":{"type":"Identifier","start":1584,"end":1593,"loc":{"start":{"line":58,"column":14},"end":{"line":58,"column":23}},"name":"math_sign"}},{"type":"ExportSpecifier","start":1595,"end":1603,"loc":{"start":{"line":58,"column":25},"end":{"line":58,"column":33}},"local":{"type":"Identifier","start":1595,"end":1603,"loc":{"start":{"line":58,"column":25},"end":{"line":58,"column":33}},"name":"math_max"},"exported":{"type":"Identifier","start":1595,"end":1603,"loc":{"start":{"line":58,"column":25},"end":{"line":58,"column":33}},"name":"math_max"}},{"type":"ExportSpecifier","start":1605,"end":1613,"loc":{"start":{"line":58,"column":35},"end":{"line":58,"column":43}},"local":{"type":"Identifier","start":1605,"end":1613,"loc":{"start":{"line":58,"column":35},"end":{"line":58,"column":43}},"name":"math_min"},"exported":{"type":"Identifier","start":1605,"end":1613,"loc":{"start":{"line":58,"column":35},"end":{"line":58,"column":43}},"name":"math_min"}}],"source":null}],"sourceType":"module"}
-"#;
-/*const IMPORT_MATH: &'static str = r#"
-{"type":"Program","start":0,"end":1617,"loc":{"start":{"line":1,"column":0},"end":{"line":59,"column":0}},"body":[{"type":"ImportDeclaration","start":0,"end":32,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":32}},"specifiers":[{"type":"ImportSpecifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"imported":{"type":"Identifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"name":"Infinity"},"local":{"type":"Identifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"name":"Infinity"}}],"source":{"type":"Literal","start":25,"end":31,"loc":{"start":{"line":1,"column":25},"end":{"line":1,"column":31}},"value":"misc","raw":"\"misc\""}},{"type":"VariableDeclaration","start":71,"end":104,"loc":{"start":{"line":4,"column":0},"end":{"line":4,"column":33}},"declarations":[{"type":"VariableDeclarator","start":77,"end":103,"loc":{"start":{"line":4,"column":6},"end":{"line":4,"column":32}},"id":{"type":"Identifier","start":77,"end":83,"loc":{"start":{"line":4,"column":6},"end":{"line":4,"column":12}},"name":"math_E"},"init":{"type":"Literal","start":86,"end":103,"loc":{"start":{"line":4,"column":15},"end":{"line":4,"column":32}},"value":2.718281828459045,"raw":"2.718281828459045"}}],"kind":"const"},{"type":"VariableDeclaration","start":105,"end":141,"loc":{"start":{"line":5,"column":0},"end":{"line":5,"column":36}},"declarations":[{"type":"VariableDeclarator","start":111,"end":140,"loc":{"start":{"line":5,"column":6},"end":{"line":5,"column":35}},"id":{"type":"Identifier","start":111,"end":119,"loc":{"start":{"line":5,"column":6},"end":{"line":5,"column":14}},"name":"math_LN2"},"init":{"type":"Literal","start":122,"end":140,"loc":{"start":{"line":5,"column":17},"end":{"line":5,"column":35}},"value":0.6931471805599453,"raw":"0.6931471805599453"}}],"kind":"const"},{"type":"VariableDeclaration","start":142,"end":178,"loc":{"start":{"line":6,"column":0},"end":{"line":6,"column":36}},"declarations":[{"type":"VariableDeclarator","start":148,"end":177,"loc":{"start":{"line":6,"column":6},"end":{"line":6,"column":35}},"id":{"type":"Identifier","start":148,"end":157,"loc":{"start":{"line":6,"column":6},"end":{"line":6,"column":15}},"name":"math_LN10"},"init":{"type":"Literal","start":160,"end":177,"loc":{"start":{"line":6,"column":18},"end":{"line":6,"column":35}},"value":2.302585092994046,"raw":"2.302585092994046"}}],"kind":"const"},{"type":"VariableDeclaration","start":179,"end":217,"loc":{"start":{"line":7,"column":0},"end":{"line":7,"column":38}},"declarations":[{"type":"VariableDeclarator","start":185,"end":216,"loc":{"start":{"line":7,"column":6},"end":{"line":7,"column":37}},"id":{"type":"Identifier","start":185,"end":195,"loc":{"start":{"line":7,"column":6},"end":{"line":7,"column":16}},"name":"math_LOG2E"},"init":{"type":"Literal","start":198,"end":216,"loc":{"start":{"line":7,"column":19},"end":{"line":7,"column":37}},"value":1.4426950408889634,"raw":"1.4426950408889634"}}],"kind":"const"},{"type":"VariableDeclaration","start":218,"end":257,"loc":{"start":{"line":8,"column":0},"end":{"line":8,"column":39}},"declarations":[{"type":"VariableDeclarator","start":224,"end":256,"loc":{"start":{"line":8,"column":6},"end":{"line":8,"column":38}},"id":{"type":"Identifier","start":224,"end":235,"loc":{"start":{"line":8,"column":6},"end":{"line":8,"column":17}},"name":"math_LOG10E"},"init":{"type":"Literal","start":238,"end":256,"loc":{"start":{"line":8,"column":20},"end":{"line":8,"column":38}},"value":0.4342944819032518,"raw":"0.4342944819032518"}}],"kind":"const"},{"type":"VariableDeclaration","start":258,"end":293,"loc":{"start":{"line":9,"column":0},"end":{"line":9,"column":35}},"declarations":[{"type":"VariableDeclarator","start":264,"end":292,"loc":{"start":{"line":9,"column":6},"end":{"line":9,"column":34}},"id":{"type":"Identifier","start":264,"end":271,"loc":{"start":{"line":9,"column":6},"end":{"line":9,"column":13}},"name":"math_PI"},"init":{"type":"Literal","start":274,"end":292,"loc":{"start":{"line":9,"column":16},"end":{"line":9,"column":34}},"value":3.141592653589793,"raw":"3.1415926535897932"}}],"kind":"const"},{"type":"VariableDeclaration","start":294,"end":334,"loc":{"start":{"line":10,"column":0},"end":{"line":10,"column":40}},"declarations":[{"type":"VariableDeclarator","start":300,"end":333,"loc":{"start":{"line":10,"column":6},"end":{"line":10,"column":39}},"id":{"type":"Identifier","start":300,"end":312,"loc":{"start":{"line":10,"column":6},"end":{"line":10,"column":18}},"name":"math_SQRT1_2"},"init":{"type":"Literal","start":315,"end":333,"loc":{"start":{"line":10,"column":21},"end":{"line":10,"column":39}},"value":0.7071067811865476,"raw":"0.7071067811865476"}}],"kind":"const"},{"type":"VariableDeclaration","start":335,"end":373,"loc":{"start":{"line":11,"column":0},"end":{"line":11,"column":38}},"declarations":[{"type":"VariableDeclarator","start":341,"end":372,"loc":{"start":{"line":11,"column":6},"end":{"line":11,"column":37}},"id":{"type":"Identifier","start":341,"end":351,"loc":{"start":{"line":11,"column":6},"end":{"line":11,"column":16}},"name":"math_SQRT2"},"init":{"type":"Literal","start":354,"end":372,"loc":{"start":{"line":11,"column":19},"end":{"line":11,"column":37}},"value":1.4142135623730951,"raw":"1.4142135623730951"}}],"kind":"const"},{"type":"ExpressionStatement","start":632,"end":676,"loc":{"start":{"line":18,"column":0},"end":{"line":18,"column":44}},"expression":{"type":"AssignmentExpression","start":632,"end":675,"loc":{"start":{"line":18,"column":0},"end":{"line":18,"column":43}},"operator":"=","left":{"type":"Identifier","start":632,"end":644,"loc":{"start":{"line":18,"column":0},"end":{"line":18,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":647,"end":675,"loc":{"start":{"line":18,"column":15},"end":{"line":18,"column":43}},"value":"direct;constraint=x:number","raw":"\"direct;constraint=x:number\""}}},{"type":"FunctionDeclaration","start":677,"end":729,"loc":{"start":{"line":19,"column":0},"end":{"line":21,"column":1}},"id":{"type":"Identifier","start":686,"end":694,"loc":{"start":{"line":19,"column":9},"end":{"line":19,"column":17}},"name":"math_abs"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":695,"end":696,"loc":{"start":{"line":19,"column":18},"end":{"line":19,"column":19}},"name":"x"}],"body":{"type":"BlockStatement","start":698,"end":729,"loc":{"start":{"line":19,"column":21},"end":{"line":21,"column":1}},"body":[{"type":"ReturnStatement","start":704,"end":727,"loc":{"start":{"line":20,"column":4},"end":{"line":20,"column":27}},"argument":{"type":"ConditionalExpression","start":711,"end":726,"loc":{"start":{"line":20,"column":11},"end":{"line":20,"column":26}},"test":{"type":"BinaryExpression","start":711,"end":717,"loc":{"start":{"line":20,"column":11},"end":{"line":20,"column":17}},"left":{"type":"Identifier","start":711,"end":712,"loc":{"start":{"line":20,"column":11},"end":{"line":20,"column":12}},"name":"x"},"operator":">=","right":{"type":"Literal","start":716,"end":717,"loc":{"start":{"line":20,"column":16},"end":{"line":20,"column":17}},"value":0,"raw":"0"}},"consequent":{"type":"Identifier","start":720,"end":721,"loc":{"start":{"line":20,"column":20},"end":{"line":20,"column":21}},"name":"x"},"alternate":{"type":"UnaryExpression","start":724,"end":726,"loc":{"start":{"line":20,"column":24},"end":{"line":20,"column":26}},"operator":"-","prefix":true,"argument":{"type":"Identifier","start":725,"end":726,"loc":{"start":{"line":20,"column":25},"end":{"line":20,"column":26}},"name":"x"}}}}]}},{"type":"ExpressionStatement","start":731,"end":775,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":44}},"expression":{"type":"AssignmentExpression","start":731,"end":774,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":43}},"operator":"=","left":{"type":"Identifier","start":731,"end":743,"loc":{"start":{"line":23,"column":0},"end":{"line":23,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":746,"end":774,"loc":{"start":{"line":23,"column":15},"end":{"line":23,"column":43}},"value":"direct;constraint=x:number","raw":"\"direct;constraint=x:number\""}}},{"type":"FunctionDeclaration","start":776,"end":840,"loc":{"start":{"line":24,"column":0},"end":{"line":26,"column":1}},"id":{"type":"Identifier","start":785,"end":794,"loc":{"start":{"line":24,"column":9},"end":{"line":24,"column":18}},"name":"math_sign"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":795,"end":796,"loc":{"start":{"line":24,"column":19},"end":{"line":24,"column":20}},"name":"x"}],"body":{"type":"BlockStatement","start":798,"end":840,"loc":{"start":{"line":24,"column":22},"end":{"line":26,"column":1}},"body":[{"type":"ReturnStatement","start":804,"end":838,"loc":{"start":{"line":25,"column":4},"end":{"line":25,"column":38}},"argument":{"type":"ConditionalExpression","start":811,"end":837,"loc":{"start":{"line":25,"column":11},"end":{"line":25,"column":37}},"test":{"type":"BinaryExpression","start":811,"end":816,"loc":{"start":{"line":25,"column":11},"end":{"line":25,"column":16}},"left":{"type":"Identifier","start":811,"end":812,"loc":{"start":{"line":25,"column":11},"end":{"line":25,"column":12}},"name":"x"},"operator":">","right":{"type":"Literal","start":815,"end":816,"loc":{"start":{"line":25,"column":15},"end":{"line":25,"column":16}},"value":0,"raw":"0"}},"consequent":{"type":"Literal","start":819,"end":820,"loc":{"start":{"line":25,"column":19},"end":{"line":25,"column":20}},"value":1,"raw":"1"},"alternate":{"type":"ConditionalExpression","start":823,"end":837,"loc":{"start":{"line":25,"column":23},"end":{"line":25,"column":37}},"test":{"type":"BinaryExpression","start":823,"end":828,"loc":{"start":{"line":25,"column":23},"end":{"line":25,"column":28}},"left":{"type":"Identifier","start":823,"end":824,"loc":{"start":{"line":25,"column":23},"end":{"line":25,"column":24}},"name":"x"},"operator":"<","right":{"type":"Literal","start":827,"end":828,"loc":{"start":{"line":25,"column":27},"end":{"line":25,"column":28}},"value":0,"raw":"0"}},"consequent":{"type":"UnaryExpression","start":831,"end":833,"loc":{"start":{"line":25,"column":31},"end":{"line":25,"column":33}},"operator":"-","prefix":true,"argument":{"type":"Literal","start":832,"end":833,"loc":{"start":{"line":25,"column":32},"end":{"line":25,"column":33}},"value":1,"raw":"1"}},"alternate":{"type":"Literal","start":836,"end":837,"loc":{"start":{"line":25,"column":36},"end":{"line":25,"column":37}},"value":0,"raw":"0"}}}}]}},{"type":"ExpressionStatement","start":941,"end":965,"loc":{"start":{"line":30,"column":0},"end":{"line":30,"column":24}},"expression":{"type":"AssignmentExpression","start":941,"end":964,"loc":{"start":{"line":30,"column":0},"end":{"line":30,"column":23}},"operator":"=","left":{"type":"Identifier","start":941,"end":953,"loc":{"start":{"line":30,"column":0},"end":{"line":30,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":956,"end":964,"loc":{"start":{"line":30,"column":15},"end":{"line":30,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":966,"end":1011,"loc":{"start":{"line":31,"column":0},"end":{"line":33,"column":1}},"id":{"type":"Identifier","start":975,"end":983,"loc":{"start":{"line":31,"column":9},"end":{"line":31,"column":17}},"name":"math_max"},"expression":false,"generator":false,"params":[],"body":{"type":"BlockStatement","start":986,"end":1011,"loc":{"start":{"line":31,"column":20},"end":{"line":33,"column":1}},"body":[{"type":"ReturnStatement","start":992,"end":1009,"loc":{"start":{"line":32,"column":4},"end":{"line":32,"column":21}},"argument":{"type":"UnaryExpression","start":999,"end":1008,"loc":{"start":{"line":32,"column":11},"end":{"line":32,"column":20}},"operator":"-","prefix":true,"argument":{"type":"Identifier","start":1000,"end":1008,"loc":{"start":{"line":32,"column":12},"end":{"line":32,"column":20}},"name":"Infinity"}}}]}},{"type":"ExpressionStatement","start":1012,"end":1056,"loc":{"start":{"line":34,"column":0},"end":{"line":34,"column":44}},"expression":{"type":"AssignmentExpression","start":1012,"end":1055,"loc":{"start":{"line":34,"column":0},"end":{"line":34,"column":43}},"operator":"=","left":{"type":"Identifier","start":1012,"end":1024,"loc":{"start":{"line":34,"column":0},"end":{"line":34,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1027,"end":1055,"loc":{"start":{"line":34,"column":15},"end":{"line":34,"column":43}},"value":"direct;constraint=x:number","raw":"\"direct;constraint=x:number\""}}},{"type":"FunctionDeclaration","start":1057,"end":1095,"loc":{"start":{"line":35,"column":0},"end":{"line":37,"column":1}},"id":{"type":"Identifier","start":1066,"end":1074,"loc":{"start":{"line":35,"column":9},"end":{"line":35,"column":17}},"name":"math_max"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1075,"end":1076,"loc":{"start":{"line":35,"column":18},"end":{"line":35,"column":19}},"name":"x"}],"body":{"type":"BlockStatement","start":1078,"end":1095,"loc":{"start":{"line":35,"column":21},"end":{"line":37,"column":1}},"body":[{"type":"ReturnStatement","start":1084,"end":1093,"loc":{"start":{"line":36,"column":4},"end":{"line":36,"column":13}},"argument":{"type":"Identifier","start":1091,"end":1092,"loc":{"start":{"line":36,"column":11},"end":{"line":36,"column":12}},"name":"x"}}]}},{"type":"ExpressionStatement","start":1096,"end":1149,"loc":{"start":{"line":38,"column":0},"end":{"line":38,"column":53}},"expression":{"type":"AssignmentExpression","start":1096,"end":1148,"loc":{"start":{"line":38,"column":0},"end":{"line":38,"column":52}},"operator":"=","left":{"type":"Identifier","start":1096,"end":1108,"loc":{"start":{"line":38,"column":0},"end":{"line":38,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1111,"end":1148,"loc":{"start":{"line":38,"column":15},"end":{"line":38,"column":52}},"value":"direct;constraint=x:number,y:number","raw":"\"direct;constraint=x:number,y:number\""}}},{"type":"FunctionDeclaration","start":1150,"end":1203,"loc":{"start":{"line":39,"column":0},"end":{"line":41,"column":1}},"id":{"type":"Identifier","start":1159,"end":1167,"loc":{"start":{"line":39,"column":9},"end":{"line":39,"column":17}},"name":"math_max"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1168,"end":1169,"loc":{"start":{"line":39,"column":18},"end":{"line":39,"column":19}},"name":"x"},{"type":"Identifier","start":1171,"end":1172,"loc":{"start":{"line":39,"column":21},"end":{"line":39,"column":22}},"name":"y"}],"body":{"type":"BlockStatement","start":1174,"end":1203,"loc":{"start":{"line":39,"column":24},"end":{"line":41,"column":1}},"body":[{"type":"ReturnStatement","start":1180,"end":1201,"loc":{"start":{"line":40,"column":4},"end":{"line":40,"column":25}},"argument":{"type":"ConditionalExpression","start":1187,"end":1200,"loc":{"start":{"line":40,"column":11},"end":{"line":40,"column":24}},"test":{"type":"BinaryExpression","start":1187,"end":1192,"loc":{"start":{"line":40,"column":11},"end":{"line":40,"column":16}},"left":{"type":"Identifier","start":1187,"end":1188,"loc":{"start":{"line":40,"column":11},"end":{"line":40,"column":12}},"name":"x"},"operator":"<","right":{"type":"Identifier","start":1191,"end":1192,"loc":{"start":{"line":40,"column":15},"end":{"line":40,"column":16}},"name":"y"}},"consequent":{"type":"Identifier","start":1195,"end":1196,"loc":{"start":{"line":40,"column":19},"end":{"line":40,"column":20}},"name":"y"},"alternate":{"type":"Identifier","start":1199,"end":1200,"loc":{"start":{"line":40,"column":23},"end":{"line":40,"column":24}},"name":"x"}}}]}},{"type":"ExpressionStatement","start":1205,"end":1229,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":24}},"expression":{"type":"AssignmentExpression","start":1205,"end":1228,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":23}},"operator":"=","left":{"type":"Identifier","start":1205,"end":1217,"loc":{"start":{"line":43,"column":0},"end":{"line":43,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1220,"end":1228,"loc":{"start":{"line":43,"column":15},"end":{"line":43,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":1230,"end":1274,"loc":{"start":{"line":44,"column":0},"end":{"line":46,"column":1}},"id":{"type":"Identifier","start":1239,"end":1247,"loc":{"start":{"line":44,"column":9},"end":{"line":44,"column":17}},"name":"math_min"},"expression":false,"generator":false,"params":[],"body":{"type":"BlockStatement","start":1250,"end":1274,"loc":{"start":{"line":44,"column":20},"end":{"line":46,"column":1}},"body":[{"type":"ReturnStatement","start":1256,"end":1272,"loc":{"start":{"line":45,"column":4},"end":{"line":45,"column":20}},"argument":{"type":"Identifier","start":1263,"end":1271,"loc":{"start":{"line":45,"column":11},"end":{"line":45,"column":19}},"name":"Infinity"}}]}},{"type":"ExpressionStatement","start":1275,"end":1319,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":44}},"expression":{"type":"AssignmentExpression","start":1275,"end":1318,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":43}},"operator":"=","left":{"type":"Identifier","start":1275,"end":1287,"loc":{"start":{"line":47,"column":0},"end":{"line":47,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1290,"end":1318,"loc":{"start":{"line":47,"column":15},"end":{"line":47,"column":43}},"value":"direct;constraint=x:number","raw":"\"direct;constraint=x:number\""}}},{"type":"FunctionDeclaration","start":1320,"end":1358,"loc":{"start":{"line":48,"column":0},"end":{"line":50,"column":1}},"id":{"type":"Identifier","start":1329,"end":1337,"loc":{"start":{"line":48,"column":9},"end":{"line":48,"column":17}},"name":"math_min"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1338,"end":1339,"loc":{"start":{"line":48,"column":18},"end":{"line":48,"column":19}},"name":"x"}],"body":{"type":"BlockStatement","start":1341,"end":1358,"loc":{"start":{"line":48,"column":21},"end":{"line":50,"column":1}},"body":[{"type":"ReturnStatement","start":1347,"end":1356,"loc":{"start":{"line":49,"column":4},"end":{"line":49,"column":13}},"argument":{"type":"Identifier","start":1354,"end":1355,"loc":{"start":{"line":49,"column":11},"end":{"line":49,"column":12}},"name":"x"}}]}},{"type":"ExpressionStatement","start":1359,"end":1412,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":53}},"expression":{"type":"AssignmentExpression","start":1359,"end":1411,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":52}},"operator":"=","left":{"type":"Identifier","start":1359,"end":1371,"loc":{"start":{"line":51,"column":0},"end":{"line":51,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1374,"end":1411,"loc":{"start":{"line":51,"column":15},"end":{"line":51,"column":52}},"value":"direct;constraint=x:number,y:number","raw":"\"direct;constraint=x:number,y:number\""}}},{"type":"FunctionDeclaration","start":1413,"end":1466,"loc":{"start":{"line":52,"column":0},"end":{"line":54,"column":1}},"id":{"type":"Identifier","start":1422,"end":1430,"loc":{"start":{"line":52,"column":9},"end":{"line":52,"column":17}},"name":"math_min"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1431,"end":1432,"loc":{"start":{"line":52,"column":18},"end":{"line":52,"column":19}},"name":"x"},{"type":"Identifier","start":1434,"end":1435,"loc":{"start":{"line":52,"column":21},"end":{"line":52,"column":22}},"name":"y"}],"body":{"type":"BlockStatement","start":1437,"end":1466,"loc":{"start":{"line":52,"column":24},"end":{"line":54,"column":1}},"body":[{"type":"ReturnStatement","start":1443,"end":1464,"loc":{"start":{"line":53,"column":4},"end":{"line":53,"column":25}},"argument":{"type":"ConditionalExpression","start":1450,"end":1463,"loc":{"start":{"line":53,"column":11},"end":{"line":53,"column":24}},"test":{"type":"BinaryExpression","start":1450,"end":1455,"loc":{"start":{"line":53,"column":11},"end":{"line":53,"column":16}},"left":{"type":"Identifier","start":1450,"end":1451,"loc":{"start":{"line":53,"column":11},"end":{"line":53,"column":12}},"name":"x"},"operator":"<","right":{"type":"Identifier","start":1454,"end":1455,"loc":{"start":{"line":53,"column":15},"end":{"line":53,"column":16}},"name":"y"}},"consequent":{"type":"Identifier","start":1458,"end":1459,"loc":{"start":{"line":53,"column":19},"end":{"line":53,"column":20}},"name":"x"},"alternate":{"type":"Identifier","start":1462,"end":1463,"loc":{"start":{"line":53,"column":23},"end":{"line":53,"column":24}},"name":"y"}}}]}},{"type":"ExportNamedDeclaration","start":1468,"end":1616,"loc":{"start":{"line":56,"column":0},"end":{"line":58,"column":46}},"declaration":null,"specifiers":[{"type":"ExportSpecifier","start":1481,"end":1487,"loc":{"start":{"line":57,"column":4},"end":{"line":57,"column":10}},"local":{"type":"Identifier","start":1481,"end":1487,"loc":{"start":{"line":57,"column":4},"end":{"line":57,"column":10}},"name":"math_E"},"exported":{"type":"Identifier","start":1481,"end":1487,"loc":{"start":{"line":57,"column":4},"end":{"line":57,"column":10}},"name":"math_E"}},{"type":"ExportSpecifier","start":1489,"end":1497,"loc":{"start":{"line":57,"column":12},"end":{"line":57,"column":20}},"local":{"type":"Identifier","start":1489,"end":1497,"loc":{"start":{"line":57,"column":12},"end":{"line":57,"column":20}},"name":"math_LN2"},"exported":{"type":"Identifier","start":1489,"end":1497,"loc":{"start":{"line":57,"column":12},"end":{"line":57,"column":20}},"name":"math_LN2"}},{"type":"ExportSpecifier","start":1499,"end":1508,"loc":{"start":{"line":57,"column":22},"end":{"line":57,"column":31}},"local":{"type":"Identifier","start":1499,"end":1508,"loc":{"start":{"line":57,"column":22},"end":{"line":57,"column":31}},"name":"math_LN10"},"exported":{"type":"Identifier","start":1499,"end":1508,"loc":{"start":{"line":57,"column":22},"end":{"line":57,"column":31}},"name":"math_LN10"}},{"type":"ExportSpecifier","start":1510,"end":1520,"loc":{"start":{"line":57,"column":33},"end":{"line":57,"column":43}},"local":{"type":"Identifier","start":1510,"end":1520,"loc":{"start":{"line":57,"column":33},"end":{"line":57,"column":43}},"name":"math_LOG2E"},"exported":{"type":"Identifier","start":1510,"end":1520,"loc":{"start":{"line":57,"column":33},"end":{"line":57,"column":43}},"name":"math_LOG2E"}},{"type":"ExportSpecifier","start":1522,"end":1533,"loc":{"start":{"line":57,"column":45},"end":{"line":57,"column":56}},"local":{"type":"Identifier","start":1522,"end":1533,"loc":{"start":{"line":57,"column":45},"end":{"line":57,"column":56}},"name":"math_LOG10E"},"exported":{"type":"Identifier","start":1522,"end":1533,"loc":{"start":{"line":57,"column":45},"end":{"line":57,"column":56}},"name":"math_LOG10E"}},{"type":"ExportSpecifier","start":1535,"end":1542,"loc":{"start":{"line":57,"column":58},"end":{"line":57,"column":65}},"local":{"type":"Identifier","start":1535,"end":1542,"loc":{"start":{"line":57,"column":58},"end":{"line":57,"column":65}},"name":"math_PI"},"exported":{"type":"Identifier","start":1535,"end":1542,"loc":{"start":{"line":57,"column":58},"end":{"line":57,"column":65}},"name":"math_PI"}},{"type":"ExportSpecifier","start":1544,"end":1556,"loc":{"start":{"line":57,"column":67},"end":{"line":57,"column":79}},"local":{"type":"Identifier","start":1544,"end":1556,"loc":{"start":{"line":57,"column":67},"end":{"line":57,"column":79}},"name":"math_SQRT1_2"},"exported":{"type":"Identifier","start":1544,"end":1556,"loc":{"start":{"line":57,"column":67},"end":{"line":57,"column":79}},"name":"math_SQRT1_2"}},{"type":"ExportSpecifier","start":1558,"end":1568,"loc":{"start":{"line":57,"column":81},"end":{"line":57,"column":91}},"local":{"type":"Identifier","start":1558,"end":1568,"loc":{"start":{"line":57,"column":81},"end":{"line":57,"column":91}},"name":"math_SQRT2"},"exported":{"type":"Identifier","start":1558,"end":1568,"loc":{"start":{"line":57,"column":81},"end":{"line":57,"column":91}},"name":"math_SQRT2"}},{"type":"ExportSpecifier","start":1574,"end":1582,"loc":{"start":{"line":58,"column":4},"end":{"line":58,"column":12}},"local":{"type":"Identifier","start":1574,"end":1582,"loc":{"start":{"line":58,"column":4},"end":{"line":58,"column":12}},"name":"math_abs"},"exported":{"type":"Identifier","start":1574,"end":1582,"loc":{"start":{"line":58,"column":4},"end":{"line":58,"column":12}},"name":"math_abs"}},{"type":"ExportSpecifier","start":1584,"end":1593,"loc":{"start":{"line":58,"column":14},"end":{"line":58,"column":23}},"local":{"type":"Identifier","start":1584,"end":1593,"loc":{"start":{"line":58,"column":14},"end":{"line":58,"column":23}},"name":"math_sign"},"exported":{"type":"Identifier","start":1584,"end":1593,"loc":{"start":{"line":58,"column":14},"end":{"line":58,"column":23}},"name":"math_sign"}},{"type":"ExportSpecifier","start":1595,"end":1603,"loc":{"start":{"line":58,"column":25},"end":{"line":58,"column":33}},"local":{"type":"Identifier","start":1595,"end":1603,"loc":{"start":{"line":58,"column":25},"end":{"line":58,"column":33}},"name":"math_max"},"exported":{"type":"Identifier","start":1595,"end":1603,"loc":{"start":{"line":58,"column":25},"end":{"line":58,"column":33}},"name":"math_max"}},{"type":"ExportSpecifier","start":1605,"end":1613,"loc":{"start":{"line":58,"column":35},"end":{"line":58,"column":43}},"local":{"type":"Identifier","start":1605,"end":1613,"loc":{"start":{"line":58,"column":35},"end":{"line":58,"column":43}},"name":"math_min"},"exported":{"type":"Identifier","start":1605,"end":1613,"loc":{"start":{"line":58,"column":35},"end":{"line":58,"column":43}},"name":"math_min"}}],"source":null}],"sourceType":"module"}
-"#;*/
-const IMPORT_MISC_FFI: &'static str = r#"@SourceImports
-get_time misc get_time number
-display misc display undefined string
-prompt misc prompt string string
-abort core abort undefined
-parse_int misc parse_int number string number
-parse_float misc parse_float number string
-stringify_float misc stringify_float string number
-"#;
-const IMPORT_MISC: &'static str = r#"
-{"type":"Program","start":0,"end":2869,"loc":{"start":{"line":1,"column":0},"end":{"line":128,"column":0}},"body":[{"type":"ImportDeclaration","start":0,"end":117,"loc":{"start":{"line":1,"column":0},"end":{"line":1,"column":117}},"specifiers":[{"type":"ImportSpecifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"imported":{"type":"Identifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"name":"get_time"},"local":{"type":"Identifier","start":9,"end":17,"loc":{"start":{"line":1,"column":9},"end":{"line":1,"column":17}},"name":"get_time"}},{"type":"ImportSpecifier","start":19,"end":41,"loc":{"start":{"line":1,"column":19},"end":{"line":1,"column":41}},"imported":{"type":"Identifier","start":19,"end":26,"loc":{"start":{"line":1,"column":19},"end":{"line":1,"column":26}},"name":"display"},"local":{"type":"Identifier","start":30,"end":41,"loc":{"start":{"line":1,"column":30},"end":{"line":1,"column":41}},"name":"ffi_display"}},{"type":"ImportSpecifier","start":43,"end":63,"loc":{"start":{"line":1,"column":43},"end":{"line":1,"column":63}},"imported":{"type":"Identifier","start":43,"end":49,"loc":{"start":{"line":1,"column":43},"end":{"line":1,"column":49}},"name":"prompt"},"local":{"type":"Identifier","start":53,"end":63,"loc":{"start":{"line":1,"column":53},"end":{"line":1,"column":63}},"name":"ffi_prompt"}},{"type":"ImportSpecifier","start":65,"end":70,"loc":{"start":{"line":1,"column":65},"end":{"line":1,"column":70}},"imported":{"type":"Identifier","start":65,"end":70,"loc":{"start":{"line":1,"column":65},"end":{"line":1,"column":70}},"name":"abort"},"local":{"type":"Identifier","start":65,"end":70,"loc":{"start":{"line":1,"column":65},"end":{"line":1,"column":70}},"name":"abort"}},{"type":"ImportSpecifier","start":72,"end":81,"loc":{"start":{"line":1,"column":72},"end":{"line":1,"column":81}},"imported":{"type":"Identifier","start":72,"end":81,"loc":{"start":{"line":1,"column":72},"end":{"line":1,"column":81}},"name":"parse_int"},"local":{"type":"Identifier","start":72,"end":81,"loc":{"start":{"line":1,"column":72},"end":{"line":1,"column":81}},"name":"parse_int"}},{"type":"ImportSpecifier","start":83,"end":98,"loc":{"start":{"line":1,"column":83},"end":{"line":1,"column":98}},"imported":{"type":"Identifier","start":83,"end":98,"loc":{"start":{"line":1,"column":83},"end":{"line":1,"column":98}},"name":"stringify_float"},"local":{"type":"Identifier","start":83,"end":98,"loc":{"start":{"line":1,"column":83},"end":{"line":1,"column":98}},"name":"stringify_float"}}],"source":{"type":"Literal","start":106,"end":116,"loc":{"start":{"line":1,"column":106},"end":{"line":1,"column":116}},"value":"misc.ffi","raw":"\"misc.ffi\""}},{"type":"VariableDeclaration","start":119,"end":142,"loc":{"start":{"line":3,"column":0},"end":{"line":3,"column":23}},"declarations":[{"type":"VariableDeclarator","start":125,"end":141,"loc":{"start":{"line":3,"column":6},"end":{"line":3,"column":22}},"id":{"type":"Identifier","start":125,"end":133,"loc":{"start":{"line":3,"column":6},"end":{"line":3,"column":14}},"name":"Infinity"},"init":{"type":"BinaryExpression","start":136,"end":141,"loc":{"start":{"line":3,"column":17},"end":{"line":3,"column":22}},"left":{"type":"Literal","start":136,"end":137,"loc":{"start":{"line":3,"column":17},"end":{"line":3,"column":18}},"value":1,"raw":"1"},"operator":"/","right":{"type":"Literal","start":140,"end":141,"loc":{"start":{"line":3,"column":21},"end":{"line":3,"column":22}},"value":0,"raw":"0"}}}],"kind":"const"},{"type":"VariableDeclaration","start":143,"end":168,"loc":{"start":{"line":4,"column":0},"end":{"line":4,"column":25}},"declarations":[{"type":"VariableDeclarator","start":149,"end":167,"loc":{"start":{"line":4,"column":6},"end":{"line":4,"column":24}},"id":{"type":"Identifier","start":149,"end":152,"loc":{"start":{"line":4,"column":6},"end":{"line":4,"column":9}},"name":"NaN"},"init":{"type":"BinaryExpression","start":155,"end":167,"loc":{"start":{"line":4,"column":12},"end":{"line":4,"column":24}},"left":{"type":"Literal","start":155,"end":156,"loc":{"start":{"line":4,"column":12},"end":{"line":4,"column":13}},"value":0,"raw":"0"},"operator":"*","right":{"type":"Identifier","start":159,"end":167,"loc":{"start":{"line":4,"column":16},"end":{"line":4,"column":24}},"name":"Infinity"}}}],"kind":"const"},{"type":"VariableDeclaration","start":169,"end":200,"loc":{"start":{"line":5,"column":0},"end":{"line":5,"column":31}},"declarations":[{"type":"VariableDeclarator","start":175,"end":199,"loc":{"start":{"line":5,"column":6},"end":{"line":5,"column":30}},"id":{"type":"Identifier","start":175,"end":184,"loc":{"start":{"line":5,"column":6},"end":{"line":5,"column":15}},"name":"undefined"},"init":{"type":"CallExpression","start":187,"end":199,"loc":{"start":{"line":5,"column":18},"end":{"line":5,"column":30}},"callee":{"type":"ArrowFunctionExpression","start":188,"end":196,"loc":{"start":{"line":5,"column":19},"end":{"line":5,"column":27}},"id":null,"expression":false,"generator":false,"params":[],"body":{"type":"BlockStatement","start":194,"end":196,"loc":{"start":{"line":5,"column":25},"end":{"line":5,"column":27}},"body":[]}},"arguments":[]}}],"kind":"const"},{"type":"ExpressionStatement","start":202,"end":226,"loc":{"start":{"line":7,"column":0},"end":{"line":7,"column":24}},"expression":{"type":"AssignmentExpression","start":202,"end":225,"loc":{"start":{"line":7,"column":0},"end":{"line":7,"column":23}},"operator":"=","left":{"type":"Identifier","start":202,"end":214,"loc":{"start":{"line":7,"column":0},"end":{"line":7,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":217,"end":225,"loc":{"start":{"line":7,"column":15},"end":{"line":7,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":227,"end":295,"loc":{"start":{"line":8,"column":0},"end":{"line":11,"column":1}},"id":{"type":"Identifier","start":236,"end":243,"loc":{"start":{"line":8,"column":9},"end":{"line":8,"column":16}},"name":"display"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":244,"end":245,"loc":{"start":{"line":8,"column":17},"end":{"line":8,"column":18}},"name":"v"}],"body":{"type":"BlockStatement","start":247,"end":295,"loc":{"start":{"line":8,"column":20},"end":{"line":11,"column":1}},"body":[{"type":"ExpressionStatement","start":253,"end":279,"loc":{"start":{"line":9,"column":4},"end":{"line":9,"column":30}},"expression":{"type":"CallExpression","start":253,"end":278,"loc":{"start":{"line":9,"column":4},"end":{"line":9,"column":29}},"callee":{"type":"Identifier","start":253,"end":264,"loc":{"start":{"line":9,"column":4},"end":{"line":9,"column":15}},"name":"ffi_display"},"arguments":[{"type":"CallExpression","start":265,"end":277,"loc":{"start":{"line":9,"column":16},"end":{"line":9,"column":28}},"callee":{"type":"Identifier","start":265,"end":274,"loc":{"start":{"line":9,"column":16},"end":{"line":9,"column":25}},"name":"stringify"},"arguments":[{"type":"Identifier","start":275,"end":276,"loc":{"start":{"line":9,"column":26},"end":{"line":9,"column":27}},"name":"v"}]}]}},{"type":"ReturnStatement","start":284,"end":293,"loc":{"start":{"line":10,"column":4},"end":{"line":10,"column":13}},"argument":{"type":"Identifier","start":291,"end":292,"loc":{"start":{"line":10,"column":11},"end":{"line":10,"column":12}},"name":"v"}}]}},{"type":"ExpressionStatement","start":296,"end":340,"loc":{"start":{"line":12,"column":0},"end":{"line":12,"column":44}},"expression":{"type":"AssignmentExpression","start":296,"end":339,"loc":{"start":{"line":12,"column":0},"end":{"line":12,"column":43}},"operator":"=","left":{"type":"Identifier","start":296,"end":308,"loc":{"start":{"line":12,"column":0},"end":{"line":12,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":311,"end":339,"loc":{"start":{"line":12,"column":15},"end":{"line":12,"column":43}},"value":"direct;constraint=s:string","raw":"\"direct;constraint=s:string\""}}},{"type":"FunctionDeclaration","start":341,"end":422,"loc":{"start":{"line":13,"column":0},"end":{"line":16,"column":1}},"id":{"type":"Identifier","start":350,"end":357,"loc":{"start":{"line":13,"column":9},"end":{"line":13,"column":16}},"name":"display"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":358,"end":359,"loc":{"start":{"line":13,"column":17},"end":{"line":13,"column":18}},"name":"v"},{"type":"Identifier","start":361,"end":362,"loc":{"start":{"line":13,"column":20},"end":{"line":13,"column":21}},"name":"s"}],"body":{"type":"BlockStatement","start":364,"end":422,"loc":{"start":{"line":13,"column":23},"end":{"line":16,"column":1}},"body":[{"type":"ExpressionStatement","start":370,"end":406,"loc":{"start":{"line":14,"column":4},"end":{"line":14,"column":40}},"expression":{"type":"CallExpression","start":370,"end":405,"loc":{"start":{"line":14,"column":4},"end":{"line":14,"column":39}},"callee":{"type":"Identifier","start":370,"end":381,"loc":{"start":{"line":14,"column":4},"end":{"line":14,"column":15}},"name":"ffi_display"},"arguments":[{"type":"BinaryExpression","start":382,"end":404,"loc":{"start":{"line":14,"column":16},"end":{"line":14,"column":38}},"left":{"type":"BinaryExpression","start":382,"end":389,"loc":{"start":{"line":14,"column":16},"end":{"line":14,"column":23}},"left":{"type":"Identifier","start":382,"end":383,"loc":{"start":{"line":14,"column":16},"end":{"line":14,"column":17}},"name":"s"},"operator":"+","right":{"type":"Literal","start":386,"end":389,"loc":{"start":{"line":14,"column":20},"end":{"line":14,"column":23}},"value":" ","raw":"\" \""}},"operator":"+","right":{"type":"CallExpression","start":392,"end":404,"loc":{"start":{"line":14,"column":26},"end":{"line":14,"column":38}},"callee":{"type":"Identifier","start":392,"end":401,"loc":{"start":{"line":14,"column":26},"end":{"line":14,"column":35}},"name":"stringify"},"arguments":[{"type":"Identifier","start":402,"end":403,"loc":{"start":{"line":14,"column":36},"end":{"line":14,"column":37}},"name":"v"}]}}]}},{"type":"ReturnStatement","start":411,"end":420,"loc":{"start":{"line":15,"column":4},"end":{"line":15,"column":13}},"argument":{"type":"Identifier","start":418,"end":419,"loc":{"start":{"line":15,"column":11},"end":{"line":15,"column":12}},"name":"v"}}]}},{"type":"ExpressionStatement","start":423,"end":447,"loc":{"start":{"line":17,"column":0},"end":{"line":17,"column":24}},"expression":{"type":"AssignmentExpression","start":423,"end":446,"loc":{"start":{"line":17,"column":0},"end":{"line":17,"column":23}},"operator":"=","left":{"type":"Identifier","start":423,"end":435,"loc":{"start":{"line":17,"column":0},"end":{"line":17,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":438,"end":446,"loc":{"start":{"line":17,"column":15},"end":{"line":17,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":448,"end":513,"loc":{"start":{"line":18,"column":0},"end":{"line":21,"column":1}},"id":{"type":"Identifier","start":457,"end":462,"loc":{"start":{"line":18,"column":9},"end":{"line":18,"column":14}},"name":"error"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":463,"end":464,"loc":{"start":{"line":18,"column":15},"end":{"line":18,"column":16}},"name":"v"}],"body":{"type":"BlockStatement","start":466,"end":513,"loc":{"start":{"line":18,"column":18},"end":{"line":21,"column":1}},"body":[{"type":"ExpressionStatement","start":472,"end":498,"loc":{"start":{"line":19,"column":4},"end":{"line":19,"column":30}},"expression":{"type":"CallExpression","start":472,"end":497,"loc":{"start":{"line":19,"column":4},"end":{"line":19,"column":29}},"callee":{"type":"Identifier","start":472,"end":483,"loc":{"start":{"line":19,"column":4},"end":{"line":19,"column":15}},"name":"ffi_display"},"arguments":[{"type":"CallExpression","start":484,"end":496,"loc":{"start":{"line":19,"column":16},"end":{"line":19,"column":28}},"callee":{"type":"Identifier","start":484,"end":493,"loc":{"start":{"line":19,"column":16},"end":{"line":19,"column":25}},"name":"stringify"},"arguments":[{"type":"Identifier","start":494,"end":495,"loc":{"start":{"line":19,"column":26},"end":{"line":19,"column":27}},"name":"v"}]}]}},{"type":"ExpressionStatement","start":503,"end":511,"loc":{"start":{"line":20,"column":4},"end":{"line":20,"column":12}},"expression":{"type":"CallExpression","start":503,"end":510,"loc":{"start":{"line":20,"column":4},"end":{"line":20,"column":11}},"callee":{"type":"Identifier","start":503,"end":508,"loc":{"start":{"line":20,"column":4},"end":{"line":20,"column":9}},"name":"abort"},"arguments":[]}}]}},{"type":"ExpressionStatement","start":514,"end":558,"loc":{"start":{"line":22,"column":0},"end":{"line":22,"column":44}},"expression":{"type":"AssignmentExpression","start":514,"end":557,"loc":{"start":{"line":22,"column":0},"end":{"line":22,"column":43}},"operator":"=","left":{"type":"Identifier","start":514,"end":526,"loc":{"start":{"line":22,"column":0},"end":{"line":22,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":529,"end":557,"loc":{"start":{"line":22,"column":15},"end":{"line":22,"column":43}},"value":"direct;constraint=s:string","raw":"\"direct;constraint=s:string\""}}},{"type":"FunctionDeclaration","start":559,"end":637,"loc":{"start":{"line":23,"column":0},"end":{"line":26,"column":1}},"id":{"type":"Identifier","start":568,"end":573,"loc":{"start":{"line":23,"column":9},"end":{"line":23,"column":14}},"name":"error"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":574,"end":575,"loc":{"start":{"line":23,"column":15},"end":{"line":23,"column":16}},"name":"v"},{"type":"Identifier","start":577,"end":578,"loc":{"start":{"line":23,"column":18},"end":{"line":23,"column":19}},"name":"s"}],"body":{"type":"BlockStatement","start":580,"end":637,"loc":{"start":{"line":23,"column":21},"end":{"line":26,"column":1}},"body":[{"type":"ExpressionStatement","start":586,"end":622,"loc":{"start":{"line":24,"column":4},"end":{"line":24,"column":40}},"expression":{"type":"CallExpression","start":586,"end":621,"loc":{"start":{"line":24,"column":4},"end":{"line":24,"column":39}},"callee":{"type":"Identifier","start":586,"end":597,"loc":{"start":{"line":24,"column":4},"end":{"line":24,"column":15}},"name":"ffi_display"},"arguments":[{"type":"BinaryExpression","start":598,"end":620,"loc":{"start":{"line":24,"column":16},"end":{"line":24,"column":38}},"left":{"type":"BinaryExpression","start":598,"end":605,"loc":{"start":{"line":24,"column":16},"end":{"line":24,"column":23}},"left":{"type":"Identifier","start":598,"end":599,"loc":{"start":{"line":24,"column":16},"end":{"line":24,"column":17}},"name":"s"},"operator":"+","right":{"type":"Literal","start":602,"end":605,"loc":{"start":{"line":24,"column":20},"end":{"line":24,"column":23}},"value":" ","raw":"\" \""}},"operator":"+","right":{"type":"CallExpression","start":608,"end":620,"loc":{"start":{"line":24,"column":26},"end":{"line":24,"column":38}},"callee":{"type":"Identifier","start":608,"end":617,"loc":{"start":{"line":24,"column":26},"end":{"line":24,"column":35}},"name":"stringify"},"arguments":[{"type":"Identifier","start":618,"end":619,"loc":{"start":{"line":24,"column":36},"end":{"line":24,"column":37}},"name":"v"}]}}]}},{"type":"ExpressionStatement","start":627,"end":635,"loc":{"start":{"line":25,"column":4},"end":{"line":25,"column":12}},"expression":{"type":"CallExpression","start":627,"end":634,"loc":{"start":{"line":25,"column":4},"end":{"line":25,"column":11}},"callee":{"type":"Identifier","start":627,"end":632,"loc":{"start":{"line":25,"column":4},"end":{"line":25,"column":9}},"name":"abort"},"arguments":[]}}]}},{"type":"ExpressionStatement","start":639,"end":663,"loc":{"start":{"line":28,"column":0},"end":{"line":28,"column":24}},"expression":{"type":"AssignmentExpression","start":639,"end":662,"loc":{"start":{"line":28,"column":0},"end":{"line":28,"column":23}},"operator":"=","left":{"type":"Identifier","start":639,"end":651,"loc":{"start":{"line":28,"column":0},"end":{"line":28,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":654,"end":662,"loc":{"start":{"line":28,"column":15},"end":{"line":28,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":664,"end":710,"loc":{"start":{"line":29,"column":0},"end":{"line":31,"column":1}},"id":{"type":"Identifier","start":673,"end":685,"loc":{"start":{"line":29,"column":9},"end":{"line":29,"column":21}},"name":"is_undefined"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":686,"end":687,"loc":{"start":{"line":29,"column":22},"end":{"line":29,"column":23}},"name":"v"}],"body":{"type":"BlockStatement","start":689,"end":710,"loc":{"start":{"line":29,"column":25},"end":{"line":31,"column":1}},"body":[{"type":"ReturnStatement","start":695,"end":708,"loc":{"start":{"line":30,"column":4},"end":{"line":30,"column":17}},"argument":{"type":"Literal","start":702,"end":707,"loc":{"start":{"line":30,"column":11},"end":{"line":30,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":711,"end":758,"loc":{"start":{"line":32,"column":0},"end":{"line":32,"column":47}},"expression":{"type":"AssignmentExpression","start":711,"end":757,"loc":{"start":{"line":32,"column":0},"end":{"line":32,"column":46}},"operator":"=","left":{"type":"Identifier","start":711,"end":723,"loc":{"start":{"line":32,"column":0},"end":{"line":32,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":726,"end":757,"loc":{"start":{"line":32,"column":15},"end":{"line":32,"column":46}},"value":"direct;constraint=v:undefined","raw":"\"direct;constraint=v:undefined\""}}},{"type":"FunctionDeclaration","start":759,"end":804,"loc":{"start":{"line":33,"column":0},"end":{"line":35,"column":1}},"id":{"type":"Identifier","start":768,"end":780,"loc":{"start":{"line":33,"column":9},"end":{"line":33,"column":21}},"name":"is_undefined"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":781,"end":782,"loc":{"start":{"line":33,"column":22},"end":{"line":33,"column":23}},"name":"v"}],"body":{"type":"BlockStatement","start":784,"end":804,"loc":{"start":{"line":33,"column":25},"end":{"line":35,"column":1}},"body":[{"type":"ReturnStatement","start":790,"end":802,"loc":{"start":{"line":34,"column":4},"end":{"line":34,"column":16}},"argument":{"type":"Literal","start":797,"end":801,"loc":{"start":{"line":34,"column":11},"end":{"line":34,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":805,"end":829,"loc":{"start":{"line":36,"column":0},"end":{"line":36,"column":24}},"expression":{"type":"AssignmentExpression","start":805,"end":828,"loc":{"start":{"line":36,"column":0},"end":{"line":36,"column":23}},"operator":"=","left":{"type":"Identifier","start":805,"end":817,"loc":{"start":{"line":36,"column":0},"end":{"line":36,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":820,"end":828,"loc":{"start":{"line":36,"column":15},"end":{"line":36,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":830,"end":874,"loc":{"start":{"line":37,"column":0},"end":{"line":39,"column":1}},"id":{"type":"Identifier","start":839,"end":849,"loc":{"start":{"line":37,"column":9},"end":{"line":37,"column":19}},"name":"is_boolean"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":850,"end":851,"loc":{"start":{"line":37,"column":20},"end":{"line":37,"column":21}},"name":"v"}],"body":{"type":"BlockStatement","start":853,"end":874,"loc":{"start":{"line":37,"column":23},"end":{"line":39,"column":1}},"body":[{"type":"ReturnStatement","start":859,"end":872,"loc":{"start":{"line":38,"column":4},"end":{"line":38,"column":17}},"argument":{"type":"Literal","start":866,"end":871,"loc":{"start":{"line":38,"column":11},"end":{"line":38,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":875,"end":920,"loc":{"start":{"line":40,"column":0},"end":{"line":40,"column":45}},"expression":{"type":"AssignmentExpression","start":875,"end":919,"loc":{"start":{"line":40,"column":0},"end":{"line":40,"column":44}},"operator":"=","left":{"type":"Identifier","start":875,"end":887,"loc":{"start":{"line":40,"column":0},"end":{"line":40,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":890,"end":919,"loc":{"start":{"line":40,"column":15},"end":{"line":40,"column":44}},"value":"direct;constraint=v:boolean","raw":"\"direct;constraint=v:boolean\""}}},{"type":"FunctionDeclaration","start":921,"end":964,"loc":{"start":{"line":41,"column":0},"end":{"line":43,"column":1}},"id":{"type":"Identifier","start":930,"end":940,"loc":{"start":{"line":41,"column":9},"end":{"line":41,"column":19}},"name":"is_boolean"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":941,"end":942,"loc":{"start":{"line":41,"column":20},"end":{"line":41,"column":21}},"name":"v"}],"body":{"type":"BlockStatement","start":944,"end":964,"loc":{"start":{"line":41,"column":23},"end":{"line":43,"column":1}},"body":[{"type":"ReturnStatement","start":950,"end":962,"loc":{"start":{"line":42,"column":4},"end":{"line":42,"column":16}},"argument":{"type":"Literal","start":957,"end":961,"loc":{"start":{"line":42,"column":11},"end":{"line":42,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":965,"end":989,"loc":{"start":{"line":44,"column":0},"end":{"line":44,"column":24}},"expression":{"type":"AssignmentExpression","start":965,"end":988,"loc":{"start":{"line":44,"column":0},"end":{"line":44,"column":23}},"operator":"=","left":{"type":"Identifier","start":965,"end":977,"loc":{"start":{"line":44,"column":0},"end":{"line":44,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":980,"end":988,"loc":{"start":{"line":44,"column":15},"end":{"line":44,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":990,"end":1033,"loc":{"start":{"line":45,"column":0},"end":{"line":47,"column":1}},"id":{"type":"Identifier","start":999,"end":1008,"loc":{"start":{"line":45,"column":9},"end":{"line":45,"column":18}},"name":"is_number"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1009,"end":1010,"loc":{"start":{"line":45,"column":19},"end":{"line":45,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":1012,"end":1033,"loc":{"start":{"line":45,"column":22},"end":{"line":47,"column":1}},"body":[{"type":"ReturnStatement","start":1018,"end":1031,"loc":{"start":{"line":46,"column":4},"end":{"line":46,"column":17}},"argument":{"type":"Literal","start":1025,"end":1030,"loc":{"start":{"line":46,"column":11},"end":{"line":46,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":1034,"end":1078,"loc":{"start":{"line":48,"column":0},"end":{"line":48,"column":44}},"expression":{"type":"AssignmentExpression","start":1034,"end":1077,"loc":{"start":{"line":48,"column":0},"end":{"line":48,"column":43}},"operator":"=","left":{"type":"Identifier","start":1034,"end":1046,"loc":{"start":{"line":48,"column":0},"end":{"line":48,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1049,"end":1077,"loc":{"start":{"line":48,"column":15},"end":{"line":48,"column":43}},"value":"direct;constraint=v:number","raw":"\"direct;constraint=v:number\""}}},{"type":"FunctionDeclaration","start":1079,"end":1121,"loc":{"start":{"line":49,"column":0},"end":{"line":51,"column":1}},"id":{"type":"Identifier","start":1088,"end":1097,"loc":{"start":{"line":49,"column":9},"end":{"line":49,"column":18}},"name":"is_number"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1098,"end":1099,"loc":{"start":{"line":49,"column":19},"end":{"line":49,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":1101,"end":1121,"loc":{"start":{"line":49,"column":22},"end":{"line":51,"column":1}},"body":[{"type":"ReturnStatement","start":1107,"end":1119,"loc":{"start":{"line":50,"column":4},"end":{"line":50,"column":16}},"argument":{"type":"Literal","start":1114,"end":1118,"loc":{"start":{"line":50,"column":11},"end":{"line":50,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":1122,"end":1146,"loc":{"start":{"line":52,"column":0},"end":{"line":52,"column":24}},"expression":{"type":"AssignmentExpression","start":1122,"end":1145,"loc":{"start":{"line":52,"column":0},"end":{"line":52,"column":23}},"operator":"=","left":{"type":"Identifier","start":1122,"end":1134,"loc":{"start":{"line":52,"column":0},"end":{"line":52,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1137,"end":1145,"loc":{"start":{"line":52,"column":15},"end":{"line":52,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":1147,"end":1190,"loc":{"start":{"line":53,"column":0},"end":{"line":55,"column":1}},"id":{"type":"Identifier","start":1156,"end":1165,"loc":{"start":{"line":53,"column":9},"end":{"line":53,"column":18}},"name":"is_string"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1166,"end":1167,"loc":{"start":{"line":53,"column":19},"end":{"line":53,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":1169,"end":1190,"loc":{"start":{"line":53,"column":22},"end":{"line":55,"column":1}},"body":[{"type":"ReturnStatement","start":1175,"end":1188,"loc":{"start":{"line":54,"column":4},"end":{"line":54,"column":17}},"argument":{"type":"Literal","start":1182,"end":1187,"loc":{"start":{"line":54,"column":11},"end":{"line":54,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":1191,"end":1235,"loc":{"start":{"line":56,"column":0},"end":{"line":56,"column":44}},"expression":{"type":"AssignmentExpression","start":1191,"end":1234,"loc":{"start":{"line":56,"column":0},"end":{"line":56,"column":43}},"operator":"=","left":{"type":"Identifier","start":1191,"end":1203,"loc":{"start":{"line":56,"column":0},"end":{"line":56,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1206,"end":1234,"loc":{"start":{"line":56,"column":15},"end":{"line":56,"column":43}},"value":"direct;constraint=v:string","raw":"\"direct;constraint=v:string\""}}},{"type":"FunctionDeclaration","start":1236,"end":1278,"loc":{"start":{"line":57,"column":0},"end":{"line":59,"column":1}},"id":{"type":"Identifier","start":1245,"end":1254,"loc":{"start":{"line":57,"column":9},"end":{"line":57,"column":18}},"name":"is_string"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1255,"end":1256,"loc":{"start":{"line":57,"column":19},"end":{"line":57,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":1258,"end":1278,"loc":{"start":{"line":57,"column":22},"end":{"line":59,"column":1}},"body":[{"type":"ReturnStatement","start":1264,"end":1276,"loc":{"start":{"line":58,"column":4},"end":{"line":58,"column":16}},"argument":{"type":"Literal","start":1271,"end":1275,"loc":{"start":{"line":58,"column":11},"end":{"line":58,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":1279,"end":1303,"loc":{"start":{"line":60,"column":0},"end":{"line":60,"column":24}},"expression":{"type":"AssignmentExpression","start":1279,"end":1302,"loc":{"start":{"line":60,"column":0},"end":{"line":60,"column":23}},"operator":"=","left":{"type":"Identifier","start":1279,"end":1291,"loc":{"start":{"line":60,"column":0},"end":{"line":60,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1294,"end":1302,"loc":{"start":{"line":60,"column":15},"end":{"line":60,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":1304,"end":1349,"loc":{"start":{"line":61,"column":0},"end":{"line":63,"column":1}},"id":{"type":"Identifier","start":1313,"end":1324,"loc":{"start":{"line":61,"column":9},"end":{"line":61,"column":20}},"name":"is_function"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1325,"end":1326,"loc":{"start":{"line":61,"column":21},"end":{"line":61,"column":22}},"name":"v"}],"body":{"type":"BlockStatement","start":1328,"end":1349,"loc":{"start":{"line":61,"column":24},"end":{"line":63,"column":1}},"body":[{"type":"ReturnStatement","start":1334,"end":1347,"loc":{"start":{"line":62,"column":4},"end":{"line":62,"column":17}},"argument":{"type":"Literal","start":1341,"end":1346,"loc":{"start":{"line":62,"column":11},"end":{"line":62,"column":16}},"value":false,"raw":"false"}}]}},{"type":"ExpressionStatement","start":1350,"end":1396,"loc":{"start":{"line":64,"column":0},"end":{"line":64,"column":46}},"expression":{"type":"AssignmentExpression","start":1350,"end":1395,"loc":{"start":{"line":64,"column":0},"end":{"line":64,"column":45}},"operator":"=","left":{"type":"Identifier","start":1350,"end":1362,"loc":{"start":{"line":64,"column":0},"end":{"line":64,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1365,"end":1395,"loc":{"start":{"line":64,"column":15},"end":{"line":64,"column":45}},"value":"direct;constraint=v:function","raw":"\"direct;constraint=v:function\""}}},{"type":"FunctionDeclaration","start":1397,"end":1441,"loc":{"start":{"line":65,"column":0},"end":{"line":67,"column":1}},"id":{"type":"Identifier","start":1406,"end":1417,"loc":{"start":{"line":65,"column":9},"end":{"line":65,"column":20}},"name":"is_function"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1418,"end":1419,"loc":{"start":{"line":65,"column":21},"end":{"line":65,"column":22}},"name":"v"}],"body":{"type":"BlockStatement","start":1421,"end":1441,"loc":{"start":{"line":65,"column":24},"end":{"line":67,"column":1}},"body":[{"type":"ReturnStatement","start":1427,"end":1439,"loc":{"start":{"line":66,"column":4},"end":{"line":66,"column":16}},"argument":{"type":"Literal","start":1434,"end":1438,"loc":{"start":{"line":66,"column":11},"end":{"line":66,"column":15}},"value":true,"raw":"true"}}]}},{"type":"ExpressionStatement","start":1443,"end":1487,"loc":{"start":{"line":69,"column":0},"end":{"line":69,"column":44}},"expression":{"type":"AssignmentExpression","start":1443,"end":1486,"loc":{"start":{"line":69,"column":0},"end":{"line":69,"column":43}},"operator":"=","left":{"type":"Identifier","start":1443,"end":1455,"loc":{"start":{"line":69,"column":0},"end":{"line":69,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":1458,"end":1486,"loc":{"start":{"line":69,"column":15},"end":{"line":69,"column":43}},"value":"direct;constraint=s:string","raw":"\"direct;constraint=s:string\""}}},{"type":"FunctionDeclaration","start":1488,"end":1582,"loc":{"start":{"line":70,"column":0},"end":{"line":73,"column":1}},"id":{"type":"Identifier","start":1497,"end":1503,"loc":{"start":{"line":70,"column":9},"end":{"line":70,"column":15}},"name":"prompt"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":1504,"end":1505,"loc":{"start":{"line":70,"column":16},"end":{"line":70,"column":17}},"name":"s"}],"body":{"type":"BlockStatement","start":1507,"end":1582,"loc":{"start":{"line":70,"column":19},"end":{"line":73,"column":1}},"body":[{"type":"VariableDeclaration","start":1513,"end":1537,"loc":{"start":{"line":71,"column":4},"end":{"line":71,"column":28}},"declarations":[{"type":"VariableDeclarator","start":1517,"end":1536,"loc":{"start":{"line":71,"column":8},"end":{"line":71,"column":27}},"id":{"type":"Identifier","start":1517,"end":1520,"loc":{"start":{"line":71,"column":8},"end":{"line":71,"column":11}},"name":"ret"},"init":{"type":"CallExpression","start":1523,"end":1536,"loc":{"start":{"line":71,"column":14},"end":{"line":71,"column":27}},"callee":{"type":"Identifier","start":1523,"end":1533,"loc":{"start":{"line":71,"column":14},"end":{"line":71,"column":24}},"name":"ffi_prompt"},"arguments":[{"type":"Identifier","start":1534,"end":1535,"loc":{"start":{"line":71,"column":25},"end":{"line":71,"column":26}},"name":"s"}]}}],"kind":"let"},{"type":"ReturnStatement","start":1542,"end":1580,"loc":{"start":{"line":72,"column":4},"end":{"line":72,"column":42}},"argument":{"type":"ConditionalExpression","start":1549,"end":1579,"loc":{"start":{"line":72,"column":11},"end":{"line":72,"column":41}},"test":{"type":"BinaryExpression","start":1549,"end":1561,"loc":{"start":{"line":72,"column":11},"end":{"line":72,"column":23}},"left":{"type":"Identifier","start":1549,"end":1552,"loc":{"start":{"line":72,"column":11},"end":{"line":72,"column":14}},"name":"ret"},"operator":"===","right":{"type":"Literal","start":1557,"end":1561,"loc":{"start":{"line":72,"column":19},"end":{"line":72,"column":23}},"value":"\u0000","raw":"\"\\0\""}},"consequent":{"type":"Identifier","start":1564,"end":1573,"loc":{"start":{"line":72,"column":26},"end":{"line":72,"column":35}},"name":"undefined"},"alternate":{"type":"Identifier","start":1576,"end":1579,"loc":{"start":{"line":72,"column":38},"end":{"line":72,"column":41}},"name":"ret"}}}]}},{"type":"ExpressionStatement","start":2100,"end":2124,"loc":{"start":{"line":96,"column":0},"end":{"line":96,"column":24}},"expression":{"type":"AssignmentExpression","start":2100,"end":2123,"loc":{"start":{"line":96,"column":0},"end":{"line":96,"column":23}},"operator":"=","left":{"type":"Identifier","start":2100,"end":2112,"loc":{"start":{"line":96,"column":0},"end":{"line":96,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2115,"end":2123,"loc":{"start":{"line":96,"column":15},"end":{"line":96,"column":23}},"value":"direct","raw":"\"direct\""}}},{"type":"FunctionDeclaration","start":2125,"end":2187,"loc":{"start":{"line":97,"column":0},"end":{"line":99,"column":1}},"id":{"type":"Identifier","start":2134,"end":2143,"loc":{"start":{"line":97,"column":9},"end":{"line":97,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2144,"end":2145,"loc":{"start":{"line":97,"column":19},"end":{"line":97,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2147,"end":2187,"loc":{"start":{"line":97,"column":22},"end":{"line":99,"column":1}},"body":[{"type":"ReturnStatement","start":2153,"end":2185,"loc":{"start":{"line":98,"column":4},"end":{"line":98,"column":36}},"argument":{"type":"Literal","start":2160,"end":2184,"loc":{"start":{"line":98,"column":11},"end":{"line":98,"column":35}},"value":"(unstringifiable type)","raw":"\"(unstringifiable type)\""}}]}},{"type":"ExpressionStatement","start":2188,"end":2235,"loc":{"start":{"line":100,"column":0},"end":{"line":100,"column":47}},"expression":{"type":"AssignmentExpression","start":2188,"end":2234,"loc":{"start":{"line":100,"column":0},"end":{"line":100,"column":46}},"operator":"=","left":{"type":"Identifier","start":2188,"end":2200,"loc":{"start":{"line":100,"column":0},"end":{"line":100,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2203,"end":2234,"loc":{"start":{"line":100,"column":15},"end":{"line":100,"column":46}},"value":"direct;constraint=v:undefined","raw":"\"direct;constraint=v:undefined\""}}},{"type":"FunctionDeclaration","start":2236,"end":2285,"loc":{"start":{"line":101,"column":0},"end":{"line":103,"column":1}},"id":{"type":"Identifier","start":2245,"end":2254,"loc":{"start":{"line":101,"column":9},"end":{"line":101,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2255,"end":2256,"loc":{"start":{"line":101,"column":19},"end":{"line":101,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2258,"end":2285,"loc":{"start":{"line":101,"column":22},"end":{"line":103,"column":1}},"body":[{"type":"ReturnStatement","start":2264,"end":2283,"loc":{"start":{"line":102,"column":4},"end":{"line":102,"column":23}},"argument":{"type":"Literal","start":2271,"end":2282,"loc":{"start":{"line":102,"column":11},"end":{"line":102,"column":22}},"value":"undefined","raw":"\"undefined\""}}]}},{"type":"ExpressionStatement","start":2286,"end":2331,"loc":{"start":{"line":104,"column":0},"end":{"line":104,"column":45}},"expression":{"type":"AssignmentExpression","start":2286,"end":2330,"loc":{"start":{"line":104,"column":0},"end":{"line":104,"column":44}},"operator":"=","left":{"type":"Identifier","start":2286,"end":2298,"loc":{"start":{"line":104,"column":0},"end":{"line":104,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2301,"end":2330,"loc":{"start":{"line":104,"column":15},"end":{"line":104,"column":44}},"value":"direct;constraint=v:boolean","raw":"\"direct;constraint=v:boolean\""}}},{"type":"FunctionDeclaration","start":2332,"end":2390,"loc":{"start":{"line":105,"column":0},"end":{"line":107,"column":1}},"id":{"type":"Identifier","start":2341,"end":2350,"loc":{"start":{"line":105,"column":9},"end":{"line":105,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2351,"end":2352,"loc":{"start":{"line":105,"column":19},"end":{"line":105,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2354,"end":2390,"loc":{"start":{"line":105,"column":22},"end":{"line":107,"column":1}},"body":[{"type":"ReturnStatement","start":2360,"end":2388,"loc":{"start":{"line":106,"column":4},"end":{"line":106,"column":32}},"argument":{"type":"ConditionalExpression","start":2367,"end":2387,"loc":{"start":{"line":106,"column":11},"end":{"line":106,"column":31}},"test":{"type":"Identifier","start":2367,"end":2368,"loc":{"start":{"line":106,"column":11},"end":{"line":106,"column":12}},"name":"v"},"consequent":{"type":"Literal","start":2371,"end":2377,"loc":{"start":{"line":106,"column":15},"end":{"line":106,"column":21}},"value":"true","raw":"\"true\""},"alternate":{"type":"Literal","start":2380,"end":2387,"loc":{"start":{"line":106,"column":24},"end":{"line":106,"column":31}},"value":"false","raw":"\"false\""}}}]}},{"type":"ExpressionStatement","start":2391,"end":2435,"loc":{"start":{"line":108,"column":0},"end":{"line":108,"column":44}},"expression":{"type":"AssignmentExpression","start":2391,"end":2434,"loc":{"start":{"line":108,"column":0},"end":{"line":108,"column":43}},"operator":"=","left":{"type":"Identifier","start":2391,"end":2403,"loc":{"start":{"line":108,"column":0},"end":{"line":108,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2406,"end":2434,"loc":{"start":{"line":108,"column":15},"end":{"line":108,"column":43}},"value":"direct;constraint=v:number","raw":"\"direct;constraint=v:number\""}}},{"type":"FunctionDeclaration","start":2436,"end":2492,"loc":{"start":{"line":109,"column":0},"end":{"line":111,"column":1}},"id":{"type":"Identifier","start":2445,"end":2454,"loc":{"start":{"line":109,"column":9},"end":{"line":109,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2455,"end":2456,"loc":{"start":{"line":109,"column":19},"end":{"line":109,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2458,"end":2492,"loc":{"start":{"line":109,"column":22},"end":{"line":111,"column":1}},"body":[{"type":"ReturnStatement","start":2464,"end":2490,"loc":{"start":{"line":110,"column":4},"end":{"line":110,"column":30}},"argument":{"type":"CallExpression","start":2471,"end":2489,"loc":{"start":{"line":110,"column":11},"end":{"line":110,"column":29}},"callee":{"type":"Identifier","start":2471,"end":2486,"loc":{"start":{"line":110,"column":11},"end":{"line":110,"column":26}},"name":"stringify_float"},"arguments":[{"type":"Identifier","start":2487,"end":2488,"loc":{"start":{"line":110,"column":27},"end":{"line":110,"column":28}},"name":"v"}]}}]}},{"type":"ExpressionStatement","start":2493,"end":2537,"loc":{"start":{"line":112,"column":0},"end":{"line":112,"column":44}},"expression":{"type":"AssignmentExpression","start":2493,"end":2536,"loc":{"start":{"line":112,"column":0},"end":{"line":112,"column":43}},"operator":"=","left":{"type":"Identifier","start":2493,"end":2505,"loc":{"start":{"line":112,"column":0},"end":{"line":112,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2508,"end":2536,"loc":{"start":{"line":112,"column":15},"end":{"line":112,"column":43}},"value":"direct;constraint=v:string","raw":"\"direct;constraint=v:string\""}}},{"type":"FunctionDeclaration","start":2538,"end":2591,"loc":{"start":{"line":113,"column":0},"end":{"line":115,"column":1}},"id":{"type":"Identifier","start":2547,"end":2556,"loc":{"start":{"line":113,"column":9},"end":{"line":113,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2557,"end":2558,"loc":{"start":{"line":113,"column":19},"end":{"line":113,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2560,"end":2591,"loc":{"start":{"line":113,"column":22},"end":{"line":115,"column":1}},"body":[{"type":"ReturnStatement","start":2566,"end":2589,"loc":{"start":{"line":114,"column":4},"end":{"line":114,"column":27}},"argument":{"type":"BinaryExpression","start":2573,"end":2588,"loc":{"start":{"line":114,"column":11},"end":{"line":114,"column":26}},"left":{"type":"BinaryExpression","start":2573,"end":2581,"loc":{"start":{"line":114,"column":11},"end":{"line":114,"column":19}},"left":{"type":"Literal","start":2573,"end":2577,"loc":{"start":{"line":114,"column":11},"end":{"line":114,"column":15}},"value":"\"","raw":"\"\\\"\""},"operator":"+","right":{"type":"Identifier","start":2580,"end":2581,"loc":{"start":{"line":114,"column":18},"end":{"line":114,"column":19}},"name":"v"}},"operator":"+","right":{"type":"Literal","start":2584,"end":2588,"loc":{"start":{"line":114,"column":22},"end":{"line":114,"column":26}},"value":"\"","raw":"\"\\\"\""}}}]}},{"type":"ExpressionStatement","start":2592,"end":2638,"loc":{"start":{"line":116,"column":0},"end":{"line":116,"column":46}},"expression":{"type":"AssignmentExpression","start":2592,"end":2637,"loc":{"start":{"line":116,"column":0},"end":{"line":116,"column":45}},"operator":"=","left":{"type":"Identifier","start":2592,"end":2604,"loc":{"start":{"line":116,"column":0},"end":{"line":116,"column":12}},"name":"__attributes"},"right":{"type":"Literal","start":2607,"end":2637,"loc":{"start":{"line":116,"column":15},"end":{"line":116,"column":45}},"value":"direct;constraint=v:function","raw":"\"direct;constraint=v:function\""}}},{"type":"FunctionDeclaration","start":2639,"end":2689,"loc":{"start":{"line":117,"column":0},"end":{"line":119,"column":1}},"id":{"type":"Identifier","start":2648,"end":2657,"loc":{"start":{"line":117,"column":9},"end":{"line":117,"column":18}},"name":"stringify"},"expression":false,"generator":false,"params":[{"type":"Identifier","start":2658,"end":2659,"loc":{"start":{"line":117,"column":19},"end":{"line":117,"column":20}},"name":"v"}],"body":{"type":"BlockStatement","start":2661,"end":2689,"loc":{"start":{"line":117,"column":22},"end":{"line":119,"column":1}},"body":[{"type":"ReturnStatement","start":2667,"end":2687,"loc":{"start":{"line":118,"column":4},"end":{"line":118,"column":24}},"argument":{"type":"Literal","start":2674,"end":2686,"loc":{"start":{"line":118,"column":11},"end":{"line":118,"column":23}},"value":"(function)","raw":"\"(function)\""}}]}},{"type":"ExportNamedDeclaration","start":2691,"end":2868,"loc":{"start":{"line":121,"column":0},"end":{"line":127,"column":16}},"declaration":null,"specifiers":[{"type":"ExportSpecifier","start":2704,"end":2712,"loc":{"start":{"line":122,"column":4},"end":{"line":122,"column":12}},"local":{"type":"Identifier","start":2704,"end":2712,"loc":{"start":{"line":122,"column":4},"end":{"line":122,"column":12}},"name":"Infinity"},"exported":{"type":"Identifier","start":2704,"end":2712,"loc":{"start":{"line":122,"column":4},"end":{"line":122,"column":12}},"name":"Infinity"}},{"type":"ExportSpecifier","start":2714,"end":2717,"loc":{"start":{"line":122,"column":14},"end":{"line":122,"column":17}},"local":{"type":"Identifier","start":2714,"end":2717,"loc":{"start":{"line":122,"column":14},"end":{"line":122,"column":17}},"name":"NaN"},"exported":{"type":"Identifier","start":2714,"end":2717,"loc":{"start":{"line":122,"column":14},"end":{"line":122,"column":17}},"name":"NaN"}},{"type":"ExportSpecifier","start":2719,"end":2728,"loc":{"start":{"line":122,"column":19},"end":{"line":122,"column":28}},"local":{"type":"Identifier","start":2719,"end":2728,"loc":{"start":{"line":122,"column":19},"end":{"line":122,"column":28}},"name":"undefined"},"exported":{"type":"Identifier","start":2719,"end":2728,"loc":{"start":{"line":122,"column":19},"end":{"line":122,"column":28}},"name":"undefined"}},{"type":"ExportSpecifier","start":2734,"end":2746,"loc":{"start":{"line":123,"column":4},"end":{"line":123,"column":16}},"local":{"type":"Identifier","start":2734,"end":2746,"loc":{"start":{"line":123,"column":4},"end":{"line":123,"column":16}},"name":"is_undefined"},"exported":{"type":"Identifier","start":2734,"end":2746,"loc":{"start":{"line":123,"column":4},"end":{"line":123,"column":16}},"name":"is_undefined"}},{"type":"ExportSpecifier","start":2748,"end":2758,"loc":{"start":{"line":123,"column":18},"end":{"line":123,"column":28}},"local":{"type":"Identifier","start":2748,"end":2758,"loc":{"start":{"line":123,"column":18},"end":{"line":123,"column":28}},"name":"is_boolean"},"exported":{"type":"Identifier","start":2748,"end":2758,"loc":{"start":{"line":123,"column":18},"end":{"line":123,"column":28}},"name":"is_boolean"}},{"type":"ExportSpecifier","start":2760,"end":2769,"loc":{"start":{"line":123,"column":30},"end":{"line":123,"column":39}},"local":{"type":"Identifier","start":2760,"end":2769,"loc":{"start":{"line":123,"column":30},"end":{"line":123,"column":39}},"name":"is_number"},"exported":{"type":"Identifier","start":2760,"end":2769,"loc":{"start":{"line":123,"column":30},"end":{"line":123,"column":39}},"name":"is_number"}},{"type":"ExportSpecifier","start":2771,"end":2780,"loc":{"start":{"line":123,"column":41},"end":{"line":123,"column":50}},"local":{"type":"Identifier","start":2771,"end":2780,"loc":{"start":{"line":123,"column":41},"end":{"line":123,"column":50}},"name":"is_string"},"exported":{"type":"Identifier","start":2771,"end":2780,"loc":{"start":{"line":123,"column":41},"end":{"line":123,"column":50}},"name":"is_string"}},{"type":"ExportSpecifier","start":2782,"end":2793,"loc":{"start":{"line":123,"column":52},"end":{"line":123,"column":63}},"local":{"type":"Identifier","start":2782,"end":2793,"loc":{"start":{"line":123,"column":52},"end":{"line":123,"column":63}},"name":"is_function"},"exported":{"type":"Identifier","start":2782,"end":2793,"loc":{"start":{"line":123,"column":52},"end":{"line":123,"column":63}},"name":"is_function"}},{"type":"ExportSpecifier","start":2799,"end":2808,"loc":{"start":{"line":124,"column":4},"end":{"line":124,"column":13}},"local":{"type":"Identifier","start":2799,"end":2808,"loc":{"start":{"line":124,"column":4},"end":{"line":124,"column":13}},"name":"stringify"},"exported":{"type":"Identifier","start":2799,"end":2808,"loc":{"start":{"line":124,"column":4},"end":{"line":124,"column":13}},"name":"stringify"}},{"type":"ExportSpecifier","start":2814,"end":2822,"loc":{"start":{"line":125,"column":4},"end":{"line":125,"column":12}},"local":{"type":"Identifier","start":2814,"end":2822,"loc":{"start":{"line":125,"column":4},"end":{"line":125,"column":12}},"name":"get_time"},"exported":{"type":"Identifier","start":2814,"end":2822,"loc":{"start":{"line":125,"column":4},"end":{"line":125,"column":12}},"name":"get_time"}},{"type":"ExportSpecifier","start":2828,"end":2835,"loc":{"start":{"line":126,"column":4},"end":{"line":126,"column":11}},"local":{"type":"Identifier","start":2828,"end":2835,"loc":{"start":{"line":126,"column":4},"end":{"line":126,"column":11}},"name":"display"},"exported":{"type":"Identifier","start":2828,"end":2835,"loc":{"start":{"line":126,"column":4},"end":{"line":126,"column":11}},"name":"display"}},{"type":"ExportSpecifier","start":2837,"end":2842,"loc":{"start":{"line":126,"column":13},"end":{"line":126,"column":18}},"local":{"type":"Identifier","start":2837,"end":2842,"loc":{"start":{"line":126,"column":13},"end":{"line":126,"column":18}},"name":"error"},"exported":{"type":"Identifier","start":2837,"end":2842,"loc":{"start":{"line":126,"column":13},"end":{"line":126,"column":18}},"name":"error"}},{"type":"ExportSpecifier","start":2844,"end":2850,"loc":{"start":{"line":126,"column":20},"end":{"line":126,"column":26}},"local":{"type":"Identifier","start":2844,"end":2850,"loc":{"start":{"line":126,"column":20},"end":{"line":126,"column":26}},"name":"prompt"},"exported":{"type":"Identifier","start":2844,"end":2850,"loc":{"start":{"line":126,"column":20},"end":{"line":126,"column":26}},"name":"prompt"}},{"type":"ExportSpecifier","start":2856,"end":2865,"loc":{"start":{"line":127,"column":4},"end":{"line":127,"column":13}},"local":{"type":"Identifier","start":2856,"end":2865,"loc":{"start":{"line":127,"column":4},"end":{"line":127,"column":13}},"name":"parse_int"},"exported":{"type":"Identifier","start":2856,"end":2865,"loc":{"start":{"line":127,"column":4},"end":{"line":127,"column":13}},"name":"parse_int"}}],"source":null}],"sourceType":"module"}
-"#;
+const USAGE: &str = "\
+Usage: source-compiler [OPTIONS] INPUT
 
+INPUT is an ESTree JSON file, or a directory of modules (the entry point is main.json in that directory).
+INPUT may also be a file in the textual IR syntax with the extension .ir, or a file in the binary IR encoding
+with the extension .irb (e.g. from --emit-ir-binary), which skip the frontend.
+
+Options:
+  -o, --output FILE          write the WebAssembly binary to FILE (default: INPUT with the extension .wasm)
+  -L, --search-path DIR      also look for imported modules in DIR (may be given more than once)
+      --emit-ir-unopt FILE   write the IR before optimisation to FILE (in the textual IR syntax)
+      --emit-ir FILE         write the IR after optimisation to FILE (in the textual IR syntax)
+      --emit-ir-binary FILE  write the IR after optimisation to FILE (in the binary IR encoding, which keeps source locations)
+      --emit-wat FILE        write the generated module to FILE in the WebAssembly text format
+      --wat-folded           write the function bodies in --emit-wat as folded S-expressions
+      --source-map FILE      write a source map of the generated code to FILE (the binary refers to it by this path)
+  -O, --opt-level LEVEL      0: only mandatory optimisations; 1: propagation, inlining and scalar replacement (default);
+                             2: level 1 followed by the mandatory optimisations (-O0, -O1 and -O2 also work)
+      --passes LIST          instead of the passes of the optimisation level, run the comma-separated passes
+                             repeatedly until they make no more changes (if given more than once, each list is run in turn)
+      --max-rounds N         run each list of passes at most N times (default: 100)
+      --opt-stats            print statistics about each optimisation pass to stderr
+      --print-before LIST    print the IR to stderr before each run of the comma-separated passes (or `all`)
+      --print-after LIST     print the IR to stderr after each run of the comma-separated passes (or `all`)
+      --print-funcs LIST     only print the functions with the comma-separated funcidxs in --print-before/--print-after
+      --print-diff           make --print-after print the changes made by each pass instead of the whole IR
+      --keep-unused          keep the functions, globals and struct types that the program never uses after optimisation
+      --heap NAME            heap manager: cheney (default), marksweep or leaky
+      --stack-size PAGES     size of the stack, in WebAssembly pages (64 KiB), from 1 to 16384
+      --wasm-multi-value     use the WebAssembly multi-value proposal
+      --wasm-bulk-memory     use the WebAssembly bulk memory proposal
+      --wasm-tail-call       use the WebAssembly tail call proposal
+  -h, --help                 print this message
+
+Imports are resolved by the frontend into names, which are then looked up relative to each search path in order.
+The standard library prefix (https://btzy.github.io/libsourceror/) is removed from names before the lookup,
+and a module may also be stored with an additional .json extension.
+The directory containing the entry point is always searched first.
+";
+
+// Names starting with this prefix are standard library modules, which we look up locally without the prefix.
+const STDLIB_PREFIX: &str = "https://btzy.github.io/libsourceror/";
+
+// Input files with this extension are in the textual IR syntax (see ir::text) instead of ESTree JSON.
+const IR_EXTENSION: &str = "ir";
+
+// Input files with this extension are in the binary IR encoding (see ir::binary).
+const IR_BINARY_EXTENSION: &str = "irb";
+
+// The entry point of a directory of modules.
+const DIRECTORY_ENTRY_POINT: &str = "main.json";
+
+struct Args {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    search_paths: Vec<PathBuf>,
+    emit_ir_unopt: Option<PathBuf>,
+    emit_ir: Option<PathBuf>,
+    emit_ir_binary: Option<PathBuf>,
+    emit_wat: Option<PathBuf>,
+    wat_style: wasmgen::WatStyle,
+    source_map: Option<PathBuf>,
+    pipeline: ir::opt::Pipeline,
+    opt_stats: bool,
+    dumps: ir::opt::DumpOptions,
+    keep_unused: bool,
+    backend: backend_wasm::Options,
+}
+
+// Returns None if the help message was requested.
+fn parse_args<I: Iterator<Item = String>>(mut iter: I) -> Result<Option<Args>, String> {
+    let mut input: Option<PathBuf> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut search_paths: Vec<PathBuf> = Vec::new();
+    let mut emit_ir_unopt: Option<PathBuf> = None;
+    let mut emit_ir: Option<PathBuf> = None;
+    let mut emit_ir_binary: Option<PathBuf> = None;
+    let mut emit_wat: Option<PathBuf> = None;
+    let mut wat_style = wasmgen::WatStyle::Flat;
+    let mut source_map: Option<PathBuf> = None;
+    let mut opt_level: u32 = 1;
+    let mut passes: Vec<String> = Vec::new();
+    let mut max_rounds: Option<usize> = None;
+    let mut opt_stats = false;
+    let mut print_before: Option<String> = None;
+    let mut print_after: Option<String> = None;
+    let mut print_funcs: Option<String> = None;
+    let mut print_diff = false;
+    let mut keep_unused = false;
+    let mut backend = backend_wasm::Options::default();
+
+    fn value<I: Iterator<Item = String>>(iter: &mut I, flag: &str) -> Result<String, String> {
+        iter.next()
+            .ok_or_else(|| format!("missing value for {}", flag))
+    }
+    fn number<I: Iterator<Item = String>>(iter: &mut I, flag: &str) -> Result<u32, String> {
+        let val = value(iter, flag)?;
+        val.parse::<u32>()
+            .map_err(|_| format!("invalid value for {}: {}", flag, val))
+    }
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => output = Some(value(&mut iter, &arg)?.into()),
+            "-L" | "--search-path" => search_paths.push(value(&mut iter, &arg)?.into()),
+            "--emit-ir-unopt" => emit_ir_unopt = Some(value(&mut iter, &arg)?.into()),
+            "--emit-ir" => emit_ir = Some(value(&mut iter, &arg)?.into()),
+            "--emit-ir-binary" => emit_ir_binary = Some(value(&mut iter, &arg)?.into()),
+            "--emit-wat" => emit_wat = Some(value(&mut iter, &arg)?.into()),
+            "--wat-folded" => wat_style = wasmgen::WatStyle::Folded,
+            "--source-map" => source_map = Some(value(&mut iter, &arg)?.into()),
+            "-O" | "--opt-level" => opt_level = number(&mut iter, &arg)?,
+            "--passes" => passes.push(value(&mut iter, &arg)?),
+            "--max-rounds" => max_rounds = Some(number(&mut iter, &arg)? as usize),
+            "--opt-stats" => opt_stats = true,
+            "--print-before" => print_before = Some(value(&mut iter, &arg)?),
+            "--print-after" => print_after = Some(value(&mut iter, &arg)?),
+            "--print-funcs" => print_funcs = Some(value(&mut iter, &arg)?),
+            "--print-diff" => print_diff = true,
+            "--keep-unused" => keep_unused = true,
+            _ if arg.starts_with("-O") && arg.len() > 2 => {
+                opt_level = arg[2..]
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid optimisation level: {}", arg))?;
+            }
+            "--heap" => {
+                let name = value(&mut iter, &arg)?;
+                backend = backend.heap(match name.as_str() {
+                    "cheney" => backend_wasm::Heap::Cheney,
+                    "marksweep" => backend_wasm::Heap::MarkSweep,
+                    "leaky" => backend_wasm::Heap::Leaky,
+                    _ => return Err(format!("unknown heap manager: {}", name)),
+                });
+            }
+            "--stack-size" => {
+                let num_pages = number(&mut iter, &arg)?;
+                backend_wasm::check_stack_size(num_pages)?;
+                backend = backend.stack_size(num_pages);
+            }
+            "--wasm-multi-value" => backend = backend.wasm_multi_value(true),
+            "--wasm-bulk-memory" => backend = backend.wasm_bulk_memory(true),
+            "--wasm-tail-call" => backend = backend.wasm_tail_call(true),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("unknown option: {}", arg));
+            }
+            _ => {
+                if input.is_some() {
+                    return Err("only one input can be given".to_owned());
+                }
+                input = Some(arg.into());
+            }
+        }
+    }
+
+    fn split_list(list: &str) -> Vec<&str> {
+        list.split(',').filter(|name| !name.is_empty()).collect()
+    }
+
+    // explicit passes replace the pipeline of the optimisation level
+    let mut pipeline = if passes.is_empty() {
+        ir::opt::Pipeline::for_level(opt_level)
+    } else {
+        passes
+            .iter()
+            .try_fold(ir::opt::Pipeline::new(), |pipeline, list| {
+                pipeline.fixed_point(&split_list(list))
+            })?
+    };
+    if let Some(max_rounds) = max_rounds {
+        pipeline = pipeline.max_rounds(max_rounds);
+    }
+
+    let mut dumps = ir::opt::DumpOptions::new().diff(print_diff);
+    if let Some(list) = &print_before {
+        dumps = dumps.print_before(&split_list(list))?;
+    }
+    if let Some(list) = &print_after {
+        dumps = dumps.print_after(&split_list(list))?;
+    }
+    if let Some(list) = &print_funcs {
+        let funcidxs: Vec<ir::FuncIdx> = split_list(list)
+            .into_iter()
+            .map(|funcidx| {
+                funcidx
+                    .parse::<ir::FuncIdx>()
+                    .map_err(|_| format!("invalid value for --print-funcs: {}", list))
+            })
+            .collect::<Result<_, _>>()?;
+        dumps = dumps.funcs(&funcidxs);
+    }
+
+    Ok(Some(Args {
+        input: input.ok_or_else(|| "no input given".to_owned())?,
+        output: output,
+        search_paths: search_paths,
+        emit_ir_unopt: emit_ir_unopt,
+        emit_ir: emit_ir,
+        emit_ir_binary: emit_ir_binary,
+        emit_wat: emit_wat,
+        wat_style: wat_style,
+        source_map: source_map,
+        pipeline: pipeline,
+        opt_stats: opt_stats,
+        dumps: dumps,
+        keep_unused: keep_unused,
+        backend: backend,
+    }))
+}
+
+/**
+ * Prints diagnostics to stderr, in the form `file:line:column: severity: message`.
+ * Diagnostics for the main program (which has no source name) use the name of the input file.
+ */
 #[derive(Copy, Clone)]
-pub struct MainLogger {}
-impl log::Logger for MainLogger {
+struct StderrLogger {
+    main_filename: &'static str,
+}
+impl log::Logger for StderrLogger {
     fn log<L: log::Loggable>(&self, content: L) {
-        print!(
-            "logger: {}: {}: {}",
+        let loc = content.location();
+        eprintln!(
+            "{}:{}:{}: {}: {}",
+            loc.source.unwrap_or(self.main_filename),
+            loc.start.line,
+            loc.start.column,
             content.severity(),
-            content.location(),
             content.message()
         );
     }
 }
 
-async fn fetch_dep_proxy(name: String) -> Option<String> {
-    match name.as_str() {
-        "https://btzy.github.io/libsourceror/std/misc.source" => Some(IMPORT_MISC.to_owned()),
-        "https://btzy.github.io/libsourceror/std/misc.ffi" => Some(IMPORT_MISC_FFI.to_owned()),
-        "https://btzy.github.io/libsourceror/std/math.source" => Some(IMPORT_MATH.to_owned()),
-        "https://btzy.github.io/libsourceror/std/math.ffi" => Some(IMPORT_MATH_FFI.to_owned()),
-        _ => None,
+// Maps a resolved import name to a path relative to the search paths.
+// Returns None for URLs that are not in the standard library, since we can't fetch them.
+fn local_name(name: &str) -> Option<&str> {
+    if let Some(stdlib_name) = name.strip_prefix(STDLIB_PREFIX) {
+        Some(stdlib_name)
+    } else if name.contains("//") {
+        None
+    } else {
+        Some(name)
     }
 }
 
-fn main() {
-    {
-        use std::io;
-        use std::io::prelude::*;
-        let _ = io::stdin().read(&mut [0u8]).unwrap();
-    }
-
-    let _: () = futures::executor::block_on((|| async {
-        use wasmgen::WasmSerialize;
-
-        let (frontend_repl_ctx, ir_program) =
-            frontend_estree::run_frontend(SOURCE_CODE.to_owned(), fetch_dep_proxy, MainLogger {})
-                .await?;
-        {
-            use std::io::prelude::*;
-            let mut file = std::fs::File::create("out-noop.ir").unwrap();
-            file.write_all(format!("{:#?}", &ir_program).as_bytes())
-                .unwrap();
-        }
-        let mut ir_program_opt = ir::opt::optimize_all(ir_program, 0);
-        println!("{:#?}", &ir_program_opt);
-        {
-            use std::io::prelude::*;
-            let mut file = std::fs::File::create("out.ir").unwrap();
-            file.write_all(format!("{:#?}", &ir_program_opt).as_bytes())
-                .unwrap();
+async fn fetch_dep_local(search_paths: &'static [PathBuf], name: String) -> Option<String> {
+    let local: &str = local_name(name.as_str())?;
+    search_paths
+        .iter()
+        .flat_map(|dir| {
+            let path: PathBuf = dir.join(local);
+            let mut json_path = path.clone().into_os_string();
+            json_path.push(".json");
+            vec![path, PathBuf::from(json_path)]
+        })
+        .filter(|path| path.is_file())
+        .find_map(|path| std::fs::read_to_string(path).ok())
+}
+
+fn optimize(
+    ir_program: ir::Program,
+    pipeline: &ir::opt::Pipeline,
+    opt_stats: bool,
+    dumps: &ir::opt::DumpOptions,
+) -> ir::Program {
+    if !dumps.is_empty() {
+        let (ir_program_opt, stats) =
+            pipeline.run_with_dumps(ir_program, 0, dumps, |dump| eprintln!("{}", dump));
+        if opt_stats {
+            eprint!("{}", stats);
         }
-        let wasm_module = backend_wasm::run_backend(
-            &ir_program_opt,
-            usize::MAX,
-            backend_wasm::Options::default(),
-        );
-        let mut receiver = std::vec::Vec::<u8>::new();
-        wasm_module.wasm_serialize(&mut receiver);
-        {
-            use std::io::prelude::*;
-            let mut file = std::fs::File::create("out.wasm").unwrap();
-            file.write_all(&receiver).unwrap();
+        ir_program_opt
+    } else if opt_stats {
+        let (ir_program_opt, stats) = pipeline.run_with_stats(ir_program, 0);
+        eprint!("{}", stats);
+        ir_program_opt
+    } else {
+        pipeline.run(ir_program, 0)
+    }
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<(), ()> {
+    std::fs::write(path, content).map_err(|e| {
+        eprintln!("error: cannot write {}: {}", path.display(), e);
+    })
+}
+
+fn run(args: Args) -> Result<(), ()> {
+    // find the entry point, and put its directory at the front of the search paths
+    let (entry_point, entry_dir): (PathBuf, PathBuf) = if args.input.is_dir() {
+        (args.input.join(DIRECTORY_ENTRY_POINT), args.input.clone())
+    } else {
+        (
+            args.input.clone(),
+            args.input
+                .parent()
+                .map_or_else(PathBuf::new, |dir| dir.to_path_buf()),
+        )
+    };
+    let mut search_paths: Vec<PathBuf> = vec![entry_dir];
+    search_paths.extend(args.search_paths);
+
+    let source_bytes: Vec<u8> = std::fs::read(&entry_point).map_err(|e| {
+        eprintln!("error: cannot read {}: {}", entry_point.display(), e);
+    })?;
+
+    // the fetcher and logger need to be Copy and 'static, and they live until the end of the program anyway
+    let search_paths: &'static [PathBuf] = Box::leak(search_paths.into_boxed_slice());
+    let logger = StderrLogger {
+        main_filename: Box::leak(entry_point.display().to_string().into_boxed_str()),
+    };
+
+    let is_extension = |extension: &str| entry_point.extension() == Some(extension.as_ref());
+    let ir_program: ir::Program = if is_extension(IR_BINARY_EXTENSION) {
+        // binary IR skips the frontend too
+        ir::binary::decode(&source_bytes).map_err(|e| {
+            eprintln!(
+                "{}: {}: byte {}: {}",
+                logger.main_filename,
+                log::Severity::Error,
+                e.offset,
+                e.message
+            );
+        })?
+    } else {
+        let source_code: String = String::from_utf8(source_bytes).map_err(|_| {
+            eprintln!(
+                "{}: {}: not valid UTF-8",
+                logger.main_filename,
+                log::Severity::Error
+            );
+        })?;
+        if is_extension(IR_EXTENSION) {
+            // textual IR skips the frontend
+            ir::text::parse(&source_code).map_err(|e| {
+                eprintln!(
+                    "{}:{}:{}: {}: {}",
+                    logger.main_filename,
+                    e.line,
+                    e.column,
+                    log::Severity::Error,
+                    e.message
+                );
+            })?
+        } else {
+            futures::executor::block_on(frontend_estree::run_frontend(
+                source_code,
+                move |name| fetch_dep_local(search_paths, name),
+                logger,
+            ))?
+            .1
         }
+    };
+    if let Some(path) = &args.emit_ir_unopt {
+        write_file(path, ir::text::print(&ir_program).as_bytes())?;
+    }
 
-        let repl_funcidx_start = ir_program_opt.funcs.len();
+    let mut ir_program_opt = optimize(ir_program, &args.pipeline, args.opt_stats, &args.dumps);
+    if !args.keep_unused {
+        // there is no REPL that could use anything else
+        ir_program_opt = ir::opt::remove_unused(ir_program_opt, &ir::opt::KeepAlive::new()).0;
+    }
+    if let Some(path) = &args.emit_ir {
+        write_file(path, ir::text::print(&ir_program_opt).as_bytes())?;
+    }
+    if let Some(path) = &args.emit_ir_binary {
+        write_file(path, &ir::binary::encode(&ir_program_opt))?;
+    }
 
-        let (_new_frontend_repl_ctx, new_funcidx_start) = frontend_estree::run_frontend_repl(
-            REPL_SOURCE_CODE.to_owned(),
-            frontend_repl_ctx,
-            &mut ir_program_opt,
-            MainLogger {},
+    let mut wasm_module = backend_wasm::run_backend(&ir_program_opt, usize::MAX, args.backend);
+    if let Some(path) = &args.source_map {
+        wasm_module.set_source_map_url(path.display().to_string());
+        write_file(
+            path,
+            wasm_module.source_map(&[logger.main_filename]).as_bytes(),
         )?;
-        /*{
-            use std::io::prelude::*;
-            let mut file = std::fs::File::create("out-noop.ir").unwrap();
-            file.write_all(format!("{:#?}", &ir_program).as_bytes())
-                .unwrap();
-        }*/
-        let ir_program_opt_2 = ir::opt::optimize_all(ir_program_opt, new_funcidx_start);
-        println!("{:#?}", &ir_program_opt_2);
-        /*{
-            use std::io::prelude::*;
-            let mut file = std::fs::File::create("out.ir").unwrap();
-            file.write_all(format!("{:#?}", &ir_program_opt).as_bytes())
-                .unwrap();
-        }*/
-        let wasm_module = backend_wasm::run_backend(
-            &ir_program_opt_2,
-            repl_funcidx_start,
-            backend_wasm::Options::default(),
-        );
-        let mut receiver = std::vec::Vec::<u8>::new();
-        wasm_module.wasm_serialize(&mut receiver);
-        /*{
-            use std::io::prelude::*;
-            let mut file = std::fs::File::create("out.wasm").unwrap();
-            file.write_all(&receiver).unwrap();
-        }*/
-
-        Ok(())
-    })())
-    .unwrap_or_else(|_: ()| panic!("Frontend errored out"));
+    }
+    if let Some(path) = &args.emit_wat {
+        write_file(path, wasm_module.to_wat(args.wat_style).as_bytes())?;
+    }
+    let mut receiver = std::vec::Vec::<u8>::new();
+    wasm_module.wasm_serialize(&mut receiver);
+    write_file(
+        &args
+            .output
+            .unwrap_or_else(|| entry_point.with_extension("wasm")),
+        &receiver,
+    )
+}
+
+fn main() {
+    let args: Args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if run(args).is_err() {
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,397 @@
+/**
+ * Tests for the native command-line driver.
+ */
+use serde_json::json;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+
+#[allow(dead_code)] // the command-line tests only use some of the builders
+#[path = "../../lib-backend-wasm/tests/common/estree.rs"]
+mod estree;
+
+use estree::*;
+
+// function double(x) { return x + x; }
+// export { double };
+fn util_module() -> Value {
+    program(vec![
+        function(
+            "double",
+            &["x"],
+            vec![return_(binary("+", id("x"), id("x")))],
+        ),
+        export_(&["double"]),
+    ])
+}
+
+// Makes an empty directory for a test.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sourceror-cli-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run_compiler(args: &[&std::ffi::OsStr]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_source-compiler"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn compiles_with_imports_from_search_path() {
+    // import { double } from "util";
+    // double("a");
+    let dir = test_dir("imports");
+    let lib_dir = dir.join("lib");
+    std::fs::create_dir_all(&lib_dir).unwrap();
+    std::fs::write(lib_dir.join("util.source.json"), util_module().to_string()).unwrap();
+    std::fs::write(
+        dir.join("main.json"),
+        program(vec![
+            import_(&["double"], "util"),
+            expr_stmt(call(id("double"), vec![string("a")])),
+        ])
+        .to_string(),
+    )
+    .unwrap();
+
+    let output = run_compiler(&[
+        dir.as_os_str(),
+        "-L".as_ref(),
+        lib_dir.as_os_str(),
+        "--emit-ir".as_ref(),
+        dir.join("out.ir").as_os_str(),
+        "--emit-wat".as_ref(),
+        dir.join("out.wat").as_os_str(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(std::fs::read(dir.join("main.wasm"))
+        .unwrap()
+        .starts_with(b"\0asm"));
+    assert!(std::fs::read_to_string(dir.join("out.ir"))
+        .unwrap()
+        .contains("\nfunc "));
+    // the imported function keeps its Source name
+    assert!(std::fs::read_to_string(dir.join("out.wat"))
+        .unwrap()
+        .contains("(func $double "));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn emits_source_map() {
+    let dir = test_dir("source-map");
+    let lib_dir = dir.join("lib");
+    std::fs::create_dir_all(&lib_dir).unwrap();
+    std::fs::write(lib_dir.join("util.source.json"), util_module().to_string()).unwrap();
+    std::fs::write(
+        dir.join("main.json"),
+        program(vec![
+            import_(&["double"], "util"),
+            expr_stmt(call(id("double"), vec![string("a")])),
+        ])
+        .to_string(),
+    )
+    .unwrap();
+
+    let output = run_compiler(&[
+        dir.as_os_str(),
+        "-L".as_ref(),
+        lib_dir.as_os_str(),
+        "--source-map".as_ref(),
+        dir.join("main.wasm.map").as_os_str(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let source_map: Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("main.wasm.map")).unwrap()).unwrap();
+    assert_eq!(source_map["version"], json!(3));
+    assert!(source_map["sources"][0]
+        .as_str()
+        .unwrap()
+        .ends_with("main.json"));
+    // all the nodes are at the start of the first line (the first field of each segment is the code offset)
+    assert!(source_map["mappings"].as_str().unwrap().contains("AAA,"));
+    // the binary refers to the source map
+    let wasm: Vec<u8> = std::fs::read(dir.join("main.wasm")).unwrap();
+    assert!(wasm
+        .windows(b"sourceMappingURL".len())
+        .any(|w| w == b"sourceMappingURL"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reports_missing_import() {
+    // import { double } from "util";  (but there is no such module)
+    let dir = test_dir("missing");
+    let input = dir.join("prog.json");
+    std::fs::write(
+        &input,
+        program(vec![
+            import_(&["double"], "util"),
+            expr_stmt(call(id("double"), vec![string("a")])),
+        ])
+        .to_string(),
+    )
+    .unwrap();
+
+    let output = run_compiler(&[
+        input.as_os_str(),
+        "-o".as_ref(),
+        dir.join("out.wasm").as_os_str(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("prog.json:1:0: Error: "));
+    assert!(!dir.join("out.wasm").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let output = run_compiler(&["--heap".as_ref(), "nonexistent".as_ref(), "a.json".as_ref()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown heap manager"));
+
+    // larger stacks would overflow the stack pointer
+    let output = run_compiler(&["--stack-size".as_ref(), "65536".as_ref(), "a.json".as_ref()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("stack size must be at most 16384 pages")
+    );
+    let output = run_compiler(&["--stack-size".as_ref(), "0".as_ref(), "a.json".as_ref()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("stack size must be at least one page")
+    );
+}
+
+#[test]
+fn compiles_textual_ir() {
+    let dir = test_dir("ir");
+    let input = dir.join("prog.ir");
+    std::fs::write(
+        &input,
+        "entry 0\n\nfunc 0 () -> any\n  (return:void\n    (number:number 42.0))\n",
+    )
+    .unwrap();
+
+    let output = run_compiler(&[
+        input.as_os_str(),
+        "--emit-wat".as_ref(),
+        dir.join("prog.wat").as_os_str(),
+        "--wat-folded".as_ref(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(std::fs::read(dir.join("prog.wasm"))
+        .unwrap()
+        .starts_with(b"\0asm"));
+    let wat: String = std::fs::read_to_string(dir.join("prog.wat")).unwrap();
+    assert!(wat.starts_with("(module\n"));
+    assert!(wat.contains("(f64.const 42.0)"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compiles_binary_ir() {
+    // compile a program once, then compile its binary IR again, which must give the same module
+    let dir = test_dir("ir-binary");
+    let lib_dir = dir.join("lib");
+    std::fs::create_dir_all(&lib_dir).unwrap();
+    std::fs::write(lib_dir.join("util.source.json"), util_module().to_string()).unwrap();
+    std::fs::write(
+        dir.join("main.json"),
+        program(vec![
+            import_(&["double"], "util"),
+            expr_stmt(call(id("double"), vec![string("a")])),
+        ])
+        .to_string(),
+    )
+    .unwrap();
+
+    let output = run_compiler(&[
+        dir.as_os_str(),
+        "-L".as_ref(),
+        lib_dir.as_os_str(),
+        "--emit-ir-binary".as_ref(),
+        dir.join("main.irb").as_os_str(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = run_compiler(&[
+        dir.join("main.irb").as_os_str(),
+        "-o".as_ref(),
+        dir.join("from-ir.wasm").as_os_str(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        std::fs::read(dir.join("from-ir.wasm")).unwrap(),
+        std::fs::read(dir.join("main.wasm")).unwrap()
+    );
+
+    // a truncated file is rejected
+    let bytes: Vec<u8> = std::fs::read(dir.join("main.irb")).unwrap();
+    std::fs::write(dir.join("bad.irb"), &bytes[..bytes.len() - 1]).unwrap();
+    let output = run_compiler(&[dir.join("bad.irb").as_os_str()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unexpected end of input"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn prints_opt_stats() {
+    let dir = test_dir("opt-stats");
+    let input = dir.join("prog.ir");
+    std::fs::write(
+        &input,
+        "entry 0\n\nfunc 0 () -> any\n  (return:void\n    (number:number 42.0))\n",
+    )
+    .unwrap();
+
+    let output = run_compiler(&[input.as_os_str(), "-O2".as_ref(), "--opt-stats".as_ref()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("pass "));
+    for name in &["propagate", "inline", "unreachable", "typecast"] {
+        assert!(stderr.contains(&format!("\n{} ", name)), "{}", stderr);
+    }
+
+    // explicit passes replace the pipeline of the optimisation level
+    let output = run_compiler(&[
+        input.as_os_str(),
+        "--passes".as_ref(),
+        "unreachable".as_ref(),
+        "--opt-stats".as_ref(),
+    ]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("\nunreachable "));
+    assert!(!stderr.contains("\npropagate "));
+
+    let output = run_compiler(&[
+        input.as_os_str(),
+        "--passes".as_ref(),
+        "inline,nope".as_ref(),
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown pass `nope`"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn prints_ir_around_passes() {
+    let dir = test_dir("print-passes");
+    let input = dir.join("prog.ir");
+    std::fs::write(
+        &input,
+        "entry 1\n\nfunc 0 (number) -> number\n  (var:number (local 0))\n\nfunc 1 () -> any\n  (direct_appl:number 0\n    (number:number 42.0))\n",
+    )
+    .unwrap();
+
+    let output = run_compiler(&[
+        input.as_os_str(),
+        "--print-before".as_ref(),
+        "inline".as_ref(),
+        "--print-funcs".as_ref(),
+        "1".as_ref(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with(";; IR before inline (group 0, round 1)\nfunc 1 () -> any\n"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("func 0 "));
+
+    let output = run_compiler(&[
+        input.as_os_str(),
+        "--print-after".as_ref(),
+        "all".as_ref(),
+        "--print-diff".as_ref(),
+    ]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr
+            .contains(";; IR changes made by inline (group 0, round 1)\n--- func 1\n+++ func 1\n"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("\n-  (direct_appl:number 0\n"));
+
+    let output = run_compiler(&[input.as_os_str(), "--print-after".as_ref(), "nope".as_ref()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown pass `nope`"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn removes_unused_items() {
+    let dir = test_dir("remove-unused");
+    let input = dir.join("prog.ir");
+    std::fs::write(
+        &input,
+        "global 0 number\nentry 1\n\nfunc 0 (number) -> number\n  (var:number (local 0))\n\nfunc 1 () -> any\n  (direct_appl:number 0\n    (number:number 42.0))\n",
+    )
+    .unwrap();
+    let emit_ir = dir.join("prog.opt.ir");
+
+    // func 0 is inlined, so it is not used any more
+    let output = run_compiler(&[input.as_os_str(), "--emit-ir".as_ref(), emit_ir.as_os_str()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let ir_text: String = std::fs::read_to_string(&emit_ir).unwrap();
+    assert!(
+        ir_text.starts_with("entry 0\n\nfunc 0 () -> any\n"),
+        "{}",
+        ir_text
+    );
+
+    let output = run_compiler(&[
+        input.as_os_str(),
+        "--emit-ir".as_ref(),
+        emit_ir.as_os_str(),
+        "--keep-unused".as_ref(),
+    ]);
+    assert!(output.status.success());
+    let ir_text: String = std::fs::read_to_string(&emit_ir).unwrap();
+    assert!(
+        ir_text.starts_with("global 0 number\nentry 1\n"),
+        "{}",
+        ir_text
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
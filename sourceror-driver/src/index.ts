@@ -8,6 +8,10 @@ export { makePlatformImports } from "./platform";
 import { Transcoder } from "./transcoder";
 export { Transcoder };
 import { cachedGetFile } from "./cache";
+export type CompileOptions = Sourceror.CompileOptions;
+
+// Default stack size (in WebAssembly pages), must match the compiler default
+const DEFAULT_STACK_SIZE = 16;
 
 export class CompileError extends Error {
   constructor(message: string) {
@@ -75,6 +79,7 @@ export interface ReplContext {
   context: number;
   linear_memory?: WebAssembly.Memory;
   globals: Record<string, any>;
+  options: CompileOptions; // the REPL is compiled with the same options as the original program
 }
 
 export interface SourcerorContext extends Context {
@@ -84,7 +89,8 @@ export interface SourcerorContext extends Context {
 export async function compile(
   code: string,
   context: SourcerorContext,
-  isRepl: boolean
+  isRepl: boolean,
+  options: CompileOptions = {}
 ): Promise<WebAssembly.Module> {
   context.errors = [];
   if (isRepl && !context.repl_context) {
@@ -176,11 +182,13 @@ export async function compile(
       )),
       linear_memory: undefined,
       globals: {},
+      options: options,
     };
   }
   return (isRepl ? Sourceror.compileRepl : Sourceror.compile)(
     wasm_context,
-    es_str
+    es_str,
+    context.repl_context!.options
   ).then((wasm_binary: Uint8Array) => {
     if (wasm_binary.byteLength > 0) {
       return WebAssembly.compile(wasm_binary).catch((err: string) => {
@@ -210,10 +218,55 @@ export async function compile(
   });
 }
 
-function read_js_result(linear_memory: WebAssembly.Memory): any {
+// The result is written to the last 12 bytes of the stack
+function read_js_result(
+  linear_memory: WebAssembly.Memory,
+  stack_size: number
+): any {
   const mem = new DataView(linear_memory.buffer);
-  const tag = mem.getUint32((1 << 20) - 12, true);
-  const data_offset = (1 << 20) - 8;
+  return read_any(linear_memory, mem, stack_size * 65536 - 12);
+}
+
+// Reads an Any (a 4-byte tag followed by 8 bytes of data) at the given offset.
+// Pairs and arrays are decoded with an explicit work list instead of recursion,
+// so that long lists do not overflow the JS stack.
+// Each pair or array in the heap is decoded to a single JS array, so shared and
+// cyclic structures (e.g. made with set_tail) stay shared and cyclic.
+function read_any(
+  linear_memory: WebAssembly.Memory,
+  mem: DataView,
+  tag_offset: number
+): any {
+  // heap pointer -> the JS array for that pair or array
+  const decoded: Map<number, any[]> = new Map();
+  // (JS array, index, tag offset) of the Anys that are still to be read
+  const pending: [any[], number, number][] = [];
+  const root: any[] = [undefined];
+  pending.push([root, 0, tag_offset]);
+  while (pending.length > 0) {
+    const [container, index, offset] = pending.pop()!;
+    container[index] = read_any_shallow(
+      linear_memory,
+      mem,
+      offset,
+      decoded,
+      pending
+    );
+  }
+  return root[0];
+}
+
+// Reads the Any at the given offset, but for a pair or array only allocates the
+// JS array, and adds its elements to `pending` instead of reading them.
+function read_any_shallow(
+  linear_memory: WebAssembly.Memory,
+  mem: DataView,
+  tag_offset: number,
+  decoded: Map<number, any[]>,
+  pending: [any[], number, number][]
+): any {
+  const tag = mem.getUint32(tag_offset, true);
+  const data_offset = tag_offset + 4;
   switch (tag) {
     case 0:
       return "(unassigned variable was returned)";
@@ -234,6 +287,37 @@ function read_js_result(linear_memory: WebAssembly.Memory): any {
     }
     case 5:
       return "(function was returned)";
+    case 6:
+      return null;
+    case 7: {
+      // array: header is length, capacity, then a pointer to the elements (each is an Any)
+      const ptr = mem.getUint32(data_offset, true);
+      const existing = decoded.get(ptr);
+      if (existing !== undefined) {
+        return existing;
+      }
+      const len = mem.getUint32(ptr, true);
+      const elements_ptr = mem.getUint32(ptr + 8, true);
+      const res: any[] = new Array(len);
+      decoded.set(ptr, res);
+      for (let i = len - 1; i >= 0; --i) {
+        pending.push([res, i, elements_ptr + 12 * i]);
+      }
+      return res;
+    }
+    case 8: {
+      // pair: two Any fields (head and tail)
+      const ptr = mem.getUint32(data_offset, true);
+      const existing = decoded.get(ptr);
+      if (existing !== undefined) {
+        return existing;
+      }
+      const res: any[] = [undefined, undefined];
+      decoded.set(ptr, res);
+      pending.push([res, 1, ptr + 12]);
+      pending.push([res, 0, ptr]);
+      return res;
+    }
     default:
       return "(struct or invalid type (" + tag + ") was returned)";
   }
@@ -256,10 +340,22 @@ function stringifySourcerorRuntimeErrorCode(code: number): [string, string] {
       return ["Unary operator called with incorrect parameter type", ""];
     case 0x13:
       return ["Binary operator called with incorrect parameter type", ""];
+    case 0x14:
+      return [
+        "Array access on a non-array, or with a non-number index",
+        "",
+      ];
     case 0x16:
       return ["Function call operator applied on a non-function", ""];
     case 0x17:
       return ["If statement has a non-boolean condition", ""];
+    case 0x18:
+      return ["Loop has a non-boolean condition", ""];
+    case 0x19:
+      return [
+        "Array index out of range",
+        "Array indices must be non-negative integers.  Reading an element requires the index to be less than the array length.",
+      ];
     case 0x1a:
       return ["Variable used before initialization", ""];
     default:
@@ -355,7 +451,12 @@ export async function run(
       }
       try {
         (instance.exports.main as Function)();
-        return read_js_result(external_context.linear_memory!);
+        return read_js_result(
+          external_context.linear_memory!,
+          external_context.options.stackSize !== undefined
+            ? external_context.options.stackSize
+            : DEFAULT_STACK_SIZE
+        );
       } catch (e) {
         if (e === propagationToken) {
           throw new RuntimeError("runtime error");
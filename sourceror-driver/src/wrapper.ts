@@ -28,12 +28,57 @@ export function destroyContext(context: Context) {
   delete contexts[context];
 }
 
-export function compile(context: Context, code: string) {
-  return LoadWasm().then((module) => module.compile(context, code));
+// Compilation options (anything that is not specified uses the compiler default).
+// The heap and stack size must be the same when compiling the REPL code of a context.
+export interface CompileOptions {
+  wasmMultiValue?: boolean;
+  wasmBulkMemory?: boolean;
+  wasmTailCall?: boolean;
+  heap?: string; // name of the heap manager: "cheney" (default), "marksweep" or "leaky"
+  stackSize?: number; // in WebAssembly pages (64 KiB)
+  optLevel?: number; // 0: only mandatory optimisations; 1: propagation and inlining (default); 2: level 1 followed by the mandatory optimisations
 }
 
-export function compileRepl(context: Context, code: string) {
-  return LoadWasm().then((module) => module.compile_repl(context, code));
+function makeCompileOptions(module: any, options: CompileOptions) {
+  const ret = new module.CompileOptions();
+  if (options.wasmMultiValue !== undefined)
+    ret.set_wasm_multi_value(options.wasmMultiValue);
+  if (options.wasmBulkMemory !== undefined)
+    ret.set_wasm_bulk_memory(options.wasmBulkMemory);
+  if (options.wasmTailCall !== undefined)
+    ret.set_wasm_tail_call(options.wasmTailCall);
+  if (options.heap !== undefined) ret.set_heap(options.heap);
+  if (options.stackSize !== undefined) ret.set_stack_size(options.stackSize);
+  if (options.optLevel !== undefined) ret.set_opt_level(options.optLevel);
+  return ret;
+}
+
+export function compile(
+  context: Context,
+  code: string,
+  options: CompileOptions = {}
+) {
+  return LoadWasm().then((module) =>
+    module.compile(context, code, makeCompileOptions(module, options))
+  );
+}
+
+export function compileRepl(
+  context: Context,
+  code: string,
+  options: CompileOptions = {}
+) {
+  return LoadWasm().then((module) =>
+    module.compile_repl(context, code, makeCompileOptions(module, options))
+  );
+}
+
+// Compiles the given modules once (fetching them through the context),
+// so that programs compiled later link them instead of compiling them again.
+export function precompileLibrary(context: Context, names: string[]) {
+  return LoadWasm().then((module) =>
+    module.precompile_library(context, names)
+  );
 }
 
 function compilerLog(
@@ -1,25 +1,11 @@
+/**
+ * Test harness for the wasmtest suites of backend-wasm.
+ * Each test is built into its own wasm module, which exports a function "main" that returns the number of assertions that were checked.
+ * `NormalContext` hands the module binaries to a callback (e.g. to run them in the browser),
+ * while `NativeContext` runs them immediately with the in-tree wasm interpreter.
+ */
 use wasmgen::*;
 
-use wasm_bindgen::prelude::*;
-
-#[wasm_bindgen]
-extern "C" {
-    // Use `js_namespace` here to bind `console.log(..)` instead of just
-    // `log(..)`
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
-
-    // The `console.log` is quite polymorphic, so we can bind it with multiple
-    // signatures. Note that we need to use `js_name` to ensure we always call
-    // `log` in JS.
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log_u32(a: u32);
-
-    // Multiple arguments too!
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log_many(a: &str, b: &str);
-}
-
 pub trait TestContext {
     fn add_test<F: FnOnce(&mut CodeBuilder, &mut WasmModule, wasmgen::FuncIdx, &NormalTester)>(
         &mut self,
@@ -85,67 +71,154 @@ impl<A: Fn(Box<[u8]>) -> ()> TestContext for NormalContext<A> {
         name_: &str,
         f: F,
     ) {
-        let mut wasm_builder = WasmModule::new_builder();
-        // (assert_LHS, assert_RHS)
-        let assert_failed_i32_func = wasm_builder.import_func(
-            "platform".to_string(),
-            "assert_fail".to_string(),
-            &FuncType::new(
-                Box::new([ValType::I32, ValType::I32, ValType::I32]),
-                Box::new([]),
-            ),
-        );
-        // (number of previously passed test cases)
-        let test_failed_func = wasm_builder.import_func(
-            "platform".to_string(),
-            "test_fail".to_string(),
-            &FuncType::new(Box::new([]), Box::new([])),
-        );
-        // generate the error function
-        let error_func: wasmgen::FuncIdx = wasm_builder.import_func(
-            "core".to_string(),
-            "error".to_string(),
-            &wasmgen::FuncType::new(
-                Box::new([
-                    wasmgen::ValType::I32,
-                    wasmgen::ValType::I32,
-                    wasmgen::ValType::I32,
-                    wasmgen::ValType::I32,
-                    wasmgen::ValType::I32,
-                    wasmgen::ValType::I32,
-                    wasmgen::ValType::I32,
-                ]),
-                Box::new([]),
-            ),
-        );
-        let mut wasm_module = wasm_builder.build();
-        let globalidx_assert_count = wasm_module.add_i32_global(Mut::Var, 0);
-
-        // Create a function [] -> [i32], where the returned i32 is a boolean indicating number of assertions.
-        let functype = FuncType::new(Box::new([]), Box::new([ValType::I32]));
-        let (_type_idx, func_idx) = wasm_module.register_func(&functype);
-
-        let mut code_builder = CodeBuilder::new(functype);
-        let tester = NormalTester {
-            assert_failed_i32_func: assert_failed_i32_func,
-            globalidx_assert_count: globalidx_assert_count,
-        };
-
-        // net wasm stack: [] -> []
-        f(&mut code_builder, &mut wasm_module, error_func, &tester);
-
-        // net wasm stack: [] -> [ret(i32)]
-        {
-            let expr_builder: &mut ExprBuilder = code_builder.expr_builder();
-            expr_builder.global_get(globalidx_assert_count);
-            expr_builder.end();
+        (self.add_to_js)(build_test_module(f));
+    }
+}
+
+/**
+ * A test context that runs each test with the wasm interpreter as soon as it is added,
+ * panicking (with the name of the test) if it fails.
+ */
+#[derive(Default)]
+pub struct NativeContext {
+    test_count: usize,
+    assert_count: usize,
+}
+
+impl NativeContext {
+    pub fn new() -> NativeContext {
+        Default::default()
+    }
+    // Number of tests that have passed.
+    pub fn test_count(&self) -> usize {
+        self.test_count
+    }
+    // Total number of assertions checked by the tests that have passed.
+    pub fn assert_count(&self) -> usize {
+        self.assert_count
+    }
+}
+
+// Provides the imports of the test modules; they all abort the test.
+fn native_host(
+    module_name: &str,
+    entity_name: &str,
+    args: &[wasm_interp::Val],
+    _memory: &mut [u8],
+) -> Result<Vec<wasm_interp::Val>, wasm_interp::Trap> {
+    let arg = |i: usize| match args[i] {
+        wasm_interp::Val::I32(val) => val,
+        _ => panic!("test harness: import called with wrong argument types"),
+    };
+    let message: String = match (module_name, entity_name) {
+        ("platform", "assert_fail") => {
+            format!("assertion {} failed: {} == {}", arg(2) + 1, arg(0), arg(1))
         }
+        ("platform", "test_fail") => "test failed".to_string(),
+        ("core", "error") => format!("error code {} raised", arg(0)),
+        _ => panic!(
+            "test harness: unknown import {}.{}",
+            module_name, entity_name
+        ),
+    };
+    Err(wasm_interp::Trap::Host(message))
+}
 
-        wasm_module.commit_func(func_idx, code_builder);
-        wasm_module.export_func(func_idx, "main".to_string());
+impl TestContext for NativeContext {
+    fn add_test<F: FnOnce(&mut CodeBuilder, &mut WasmModule, wasmgen::FuncIdx, &NormalTester)>(
+        &mut self,
+        name_: &str,
+        f: F,
+    ) {
+        let binary: Box<[u8]> = build_test_module(f);
+        let result = wasm_interp::Module::parse(&binary)
+            .and_then(|module| {
+                wasm_interp::Instance::new(module, native_host, wasm_interp::Options::new())
+            })
+            .and_then(|mut instance| instance.invoke("main", &[]));
+        match result {
+            Ok(results) => match results.as_slice() {
+                [wasm_interp::Val::I32(count)] => {
+                    self.test_count += 1;
+                    self.assert_count += *count as usize;
+                }
+                _ => panic!("test '{}': main returned the wrong types", name_),
+            },
+            Err(err) => panic!("test '{}' failed: {}", name_, err),
+        }
+    }
+}
 
-        let mut receiver = std::vec::Vec::<u8>::new();
-        wasm_module.wasm_serialize(&mut receiver);
-        (self.add_to_js)(receiver.into_boxed_slice());
+/**
+ * Builds the wasm binary for a single test.
+ * The binary imports "platform.assert_fail", "platform.test_fail", and "core.error",
+ * and exports a function "main" that runs the test and returns the number of assertions that were checked.
+ */
+fn build_test_module<
+    F: FnOnce(&mut CodeBuilder, &mut WasmModule, wasmgen::FuncIdx, &NormalTester),
+>(
+    f: F,
+) -> Box<[u8]> {
+    let mut wasm_builder = WasmModule::new_builder();
+    // (assert_LHS, assert_RHS)
+    let assert_failed_i32_func = wasm_builder.import_func(
+        "platform".to_string(),
+        "assert_fail".to_string(),
+        &FuncType::new(
+            Box::new([ValType::I32, ValType::I32, ValType::I32]),
+            Box::new([]),
+        ),
+    );
+    // (number of previously passed test cases)
+    let test_failed_func = wasm_builder.import_func(
+        "platform".to_string(),
+        "test_fail".to_string(),
+        &FuncType::new(Box::new([]), Box::new([])),
+    );
+    // generate the error function
+    let error_func: wasmgen::FuncIdx = wasm_builder.import_func(
+        "core".to_string(),
+        "error".to_string(),
+        &wasmgen::FuncType::new(
+            Box::new([
+                wasmgen::ValType::I32,
+                wasmgen::ValType::I32,
+                wasmgen::ValType::I32,
+                wasmgen::ValType::I32,
+                wasmgen::ValType::I32,
+                wasmgen::ValType::I32,
+                wasmgen::ValType::I32,
+            ]),
+            Box::new([]),
+        ),
+    );
+    let mut wasm_module = wasm_builder.build();
+    let globalidx_assert_count = wasm_module.add_i32_global(Mut::Var, 0);
+
+    // Create a function [] -> [i32], where the returned i32 is a boolean indicating number of assertions.
+    let functype = FuncType::new(Box::new([]), Box::new([ValType::I32]));
+    let (_type_idx, func_idx) = wasm_module.register_func(&functype);
+
+    let mut code_builder = CodeBuilder::new(functype);
+    let tester = NormalTester {
+        assert_failed_i32_func: assert_failed_i32_func,
+        globalidx_assert_count: globalidx_assert_count,
+    };
+
+    // net wasm stack: [] -> []
+    f(&mut code_builder, &mut wasm_module, error_func, &tester);
+
+    // net wasm stack: [] -> [ret(i32)]
+    {
+        let expr_builder: &mut ExprBuilder = code_builder.expr_builder();
+        expr_builder.global_get(globalidx_assert_count);
+        expr_builder.end();
     }
+
+    wasm_module.commit_func(func_idx, code_builder);
+    wasm_module.export_func(func_idx, "main".to_string());
+
+    let mut receiver = std::vec::Vec::<u8>::new();
+    wasm_module.wasm_serialize(&mut receiver);
+    receiver.into_boxed_slice()
 }
@@ -1,13 +1,17 @@
+/**
+ * Runs the wasmtest suites of backend-wasm natively, using the wasm interpreter.
+ * A failing test panics with the name of the test and the failed assertion.
+ */
 use backend_wasm;
-use wasmgen::*;
 
 use wasm_test_harness::*;
 
 pub fn main() {
-    std::io::stdin().read_line(&mut String::new()).unwrap(); // wait so I can attach debugger to process
-    let mut ctx = NormalContext::new();
+    let mut ctx = NativeContext::new();
     backend_wasm::wasmtest(&mut ctx);
-    let module = ctx.build();
-    let mut receiver = std::vec::Vec::<u8>::new();
-    module.wasm_serialize(&mut receiver);
+    println!(
+        "{} tests passed ({} assertions)",
+        ctx.test_count(),
+        ctx.assert_count()
+    );
 }